@@ -0,0 +1,141 @@
+//! Timings for the hot paths: header and key section parsing, cold and warm
+//! prefix search, record decoding and FST builds.
+//!
+//! Runs against the jitendex fixture at `resources/jitendex/jitendex.mdx`
+//! (or `MDICT_BENCH_FIXTURE`), falling back to a generated dictionary when
+//! it is missing.
+//!
+//! Regressions are caught with criterion baselines:
+//!
+//! ```text
+//! cargo bench --bench mdict_bench -- --save-baseline main
+//! cargo bench --bench mdict_bench -- --baseline main
+//! ```
+
+use std::fs::File;
+use std::hint::black_box;
+use std::path::{Path, PathBuf};
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use mdict_tools::format::{HeaderInfo, KeySection};
+use mdict_tools::mdx_conversion::{fst_indexing, reindexing};
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::Mdict;
+
+const FIXTURE_PATH: &str = "resources/jitendex/jitendex.mdx";
+const PREFIXES: [&str; 4] = ["た", "かく", "にほん", "a"];
+
+/// The fixture, or a generated dictionary of similar shape written to `dir`.
+fn fixture(dir: &Path) -> PathBuf {
+    let path = std::env::var_os("MDICT_BENCH_FIXTURE")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(FIXTURE_PATH));
+    if path.exists() {
+        return path;
+    }
+    println!(
+        "{} not found, benchmarking a generated dictionary",
+        path.display()
+    );
+    let path = dir.join("generated.mdx");
+    let mut writer = MdxWriter::new();
+    let kana = "あいうえおかきくけこさしすせそたちつてとなにぬねのはひふへほまみむめもやゆよらりるれろわをん"
+        .chars()
+        .collect::<Vec<_>>();
+    let mut keys = Vec::new();
+    for &a in &kana {
+        for &b in &kana {
+            for &c in kana.iter().step_by(4) {
+                keys.push(format!("{}{}{}", a, b, c));
+            }
+        }
+    }
+    keys.sort();
+    for (i, key) in keys.iter().enumerate() {
+        if i % 5 == 4 {
+            writer
+                .add(key.as_str(), &format!("@@@LINK={}", keys[i - 1]))
+                .unwrap();
+        } else {
+            writer
+                .add(key.as_str(), &format!("<div><b>{}</b> entry {}</div>", key, i))
+                .unwrap();
+        }
+    }
+    writer.write_to_path(&path).expect("write generated dictionary");
+    path
+}
+
+fn search(mdict: &mut Mdict<File>, prefix: &str) -> usize {
+    match mdict.search_keys_prefix(prefix) {
+        Ok(mut results) => results.collect_to_vec().map_or(0, |keys| keys.len()),
+        Err(_) => 0,
+    }
+}
+
+fn benches(c: &mut Criterion) {
+    let scratch = tempfile::tempdir().expect("create scratch dir");
+    let path = fixture(scratch.path());
+
+    c.bench_function("header_parse", |b| {
+        b.iter(|| HeaderInfo::read_from(&mut File::open(&path).unwrap()).unwrap())
+    });
+
+    let header = HeaderInfo::read_from(&mut File::open(&path).unwrap()).unwrap();
+    c.bench_function("key_section_parse", |b| {
+        b.iter(|| KeySection::read_from(&mut File::open(&path).unwrap(), &header).unwrap())
+    });
+
+    c.bench_function("prefix_search_cold", |b| {
+        b.iter_batched(
+            || Mdict::new(File::open(&path).unwrap()).unwrap(),
+            |mut mdict| {
+                PREFIXES
+                    .iter()
+                    .map(|prefix| search(&mut mdict, prefix))
+                    .sum::<usize>()
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    let mut mdict = Mdict::new(File::open(&path).unwrap()).unwrap();
+    c.bench_function("prefix_search_warm", |b| {
+        b.iter(|| {
+            PREFIXES
+                .iter()
+                .map(|prefix| search(&mut mdict, black_box(prefix)))
+                .sum::<usize>()
+        })
+    });
+
+    let blocks = (mdict.record_section().unwrap().num_record_blocks as usize).max(1);
+    let mut next_block = 0;
+    c.bench_function("record_block_decode", |b| {
+        b.iter(|| {
+            next_block = (next_block + 7) % blocks;
+            mdict.decode_record_block(next_block).unwrap().len()
+        })
+    });
+
+    let readings = reindexing::build_readings_list(&mut mdict).unwrap();
+    let mut group = c.benchmark_group("fst");
+    group.sample_size(10);
+    group.bench_function("fst_build", |b| {
+        b.iter(|| {
+            let out = scratch.path();
+            fst_indexing::create_fst_index(
+                &mut mdict,
+                &readings,
+                out.join("index.fst"),
+                out.join("readings.dat"),
+                out.join("records.dat"),
+            )
+            .unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(mdict_benches, benches);
+criterion_main!(mdict_benches);
@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mdict_tools::format::{decode_format_block, decode_format_block_sized};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decode_format_block(data);
+    // Exercise the index-sized path too, with a size taken from the input.
+    if let Some(&size) = data.last() {
+        let _ = decode_format_block_sized(data, size as usize * 64);
+    }
+});
@@ -0,0 +1,13 @@
+#![no_main]
+
+use std::io::Cursor;
+
+use libfuzzer_sys::fuzz_target;
+use mdict_tools::format::HeaderInfo;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(header) = HeaderInfo::read_from(&mut Cursor::new(data)) {
+        let _ = header.verify_checksum();
+        let _ = header.metadata();
+    }
+});
@@ -0,0 +1,20 @@
+#![no_main]
+
+use std::io::Cursor;
+
+use libfuzzer_sys::fuzz_target;
+use mdict_tools::format::{parse_key_block, HeaderInfo, KeySection};
+
+// Input is a whole MDX file: the header has to parse before the key section
+// is reached, so seed the corpus with real or `MdxWriter` output.
+fuzz_target!(|data: &[u8]| {
+    let mut reader = Cursor::new(data);
+    let Ok(header) = HeaderInfo::read_from(&mut reader) else {
+        return;
+    };
+    let Ok(keys) = KeySection::read_from(&mut reader, &header) else {
+        return;
+    };
+    let _ = keys.verify_checksum();
+    let _ = parse_key_block(data, header.get_encoding(), header.get_version());
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mdict_tools::packed_storage::PackedStorageHeader;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = PackedStorageHeader::parse_from_bytes(data);
+});
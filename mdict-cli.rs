@@ -0,0 +1,289 @@
+//! Command-line front end: inspect, query, export, verify, optimize and
+//! transcode MDX/MDD files, and map the links between entries.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use mdict_tools::error::MDictError;
+use mdict_tools::links::link_graph;
+use mdict_tools::mdict_file::create_mdict_bundle;
+use mdict_tools::mdict_optimized::{
+    create_mdict_optimized_from_bundle_with_progress, BuildProgressCallback,
+};
+use mdict_tools::mdx_conversion::export::{export, ExportFormat};
+use mdict_tools::mdx_conversion::transcode::transcode_to_zstd;
+use mdict_tools::types::{BrokenLinkPolicy, BuildProgressStage, RecordKind};
+use mdict_tools::Mdict;
+
+/// Maximum `@@@LINK=` hops `lookup` follows.
+const LOOKUP_LINK_DEPTH: u32 = 8;
+
+#[derive(Parser)]
+#[command(
+    name = "mdict-cli",
+    version,
+    about = "Inspect and query MDX/MDD dictionaries"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the header metadata.
+    Info { path: PathBuf },
+    /// Print every key, one per line, in key order.
+    Keys { path: PathBuf },
+    /// Print the records for WORD (one per sense when it is listed more
+    /// than once), following redirects. MDD resources are written to stdout
+    /// as raw bytes.
+    Lookup { path: PathBuf, word: String },
+    /// Export every entry of an MDX.
+    Export {
+        path: PathBuf,
+        #[arg(long, value_enum, default_value_t = Format::Jsonl)]
+        format: Format,
+        /// Output file, or directory for `--format html`.
+        #[arg(long, short)]
+        output: PathBuf,
+    },
+    /// Write the graph of `@@@LINK=` redirects and `entry://` links between
+    /// entries, and report how many links are broken.
+    Links {
+        path: PathBuf,
+        #[arg(long, value_enum, default_value_t = GraphFormat::Json)]
+        format: GraphFormat,
+        /// Output file; the graph goes to stdout without it.
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Check every checksum and block; exits with status 1 on any issue.
+    Verify { path: PathBuf },
+    /// Build the FST index, readings and record sidecars for an MDX.
+    Optimize {
+        path: PathBuf,
+        /// Directory receiving `index.fst`, `readings.dat` and `records.dat`.
+        #[arg(long, short)]
+        output: PathBuf,
+        /// List redirects whose target is not a key.
+        #[arg(long)]
+        strict_links: bool,
+        /// Retarget redirects whose target is not a key to the closest key
+        /// within this many edits, and list them.
+        #[arg(long, value_name = "MAX_DISTANCE")]
+        repair_links: Option<u32>,
+    },
+    /// Rewrite every block with zstd and verify all entries read back
+    /// unchanged. Only this crate reads the result.
+    Transcode {
+        path: PathBuf,
+        #[arg(long, short)]
+        output: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Jsonl,
+    Tsv,
+    Html,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum GraphFormat {
+    Json,
+    Graphml,
+}
+
+impl From<Format> for ExportFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Jsonl => ExportFormat::JsonLines,
+            Format::Tsv => ExportFormat::Tsv,
+            Format::Html => ExportFormat::HtmlDir,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("mdict-cli: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<ExitCode, MDictError> {
+    let mut stdout = std::io::stdout().lock();
+    match command {
+        Command::Info { path } => {
+            let mdict = open(&path)?;
+            let metadata = mdict.metadata();
+            let optional = |value: Option<String>| value.unwrap_or_default();
+            let fields = [
+                ("version", format!("{:?}", metadata.version)),
+                ("engine_version", optional(metadata.engine_version)),
+                ("title", optional(metadata.title)),
+                ("description", optional(metadata.description)),
+                ("encoding", format!("{:?}", metadata.encoding)),
+                ("encrypted", metadata.encrypted.to_string()),
+                ("creation_date", optional(metadata.creation_date)),
+                ("compact", metadata.compact.to_string()),
+                (
+                    "key_case_sensitive",
+                    metadata.key_case_sensitive.to_string(),
+                ),
+                ("strip_key", metadata.strip_key.to_string()),
+                ("format", optional(metadata.format)),
+                (
+                    "entries",
+                    mdict.key_block_index.key_section.num_entries.to_string(),
+                ),
+            ];
+            for (name, value) in fields {
+                writeln!(stdout, "{}: {}", name, value)?;
+            }
+        }
+        Command::Keys { path } => {
+            let mut mdict = open(&path)?;
+            let mut index = 0;
+            while let Some(key_block) = mdict.get(index)? {
+                writeln!(stdout, "{}", key_block.key_text)?;
+                index += 1;
+            }
+        }
+        Command::Lookup { path, word } => {
+            let mut mdict = open(&path)?;
+            let key_blocks = mdict.get_all(&word)?;
+            if key_blocks.is_empty() {
+                return Err(MDictError::KeyNotFound(format!("'{}' not found", word)));
+            }
+            for key_block in &key_blocks {
+                let record = mdict.record_resolved(key_block, LOOKUP_LINK_DEPTH)?;
+                match mdict.record_kind() {
+                    RecordKind::Text => {
+                        let text = mdict.key_block_index.header.get_encoding().decode(&record);
+                        writeln!(stdout, "{}", text)?;
+                    }
+                    RecordKind::Binary => stdout.write_all(&record)?,
+                }
+            }
+        }
+        Command::Export {
+            path,
+            format,
+            output,
+        } => {
+            let mut mdict = open(&path)?;
+            let written = export(&mut mdict, format.into(), &output)?;
+            eprintln!("exported {} entries to {}", written, output.display());
+        }
+        Command::Links {
+            path,
+            format,
+            output,
+        } => {
+            let mut mdict = open(&path)?;
+            let graph = link_graph(&mut mdict)?;
+            let mut writer: Box<dyn Write + '_> = match &output {
+                Some(output) => Box::new(BufWriter::new(File::create(output)?)),
+                None => Box::new(&mut stdout),
+            };
+            match format {
+                GraphFormat::Json => graph.write_json(&mut writer)?,
+                GraphFormat::Graphml => graph.write_graphml(&mut writer)?,
+            }
+            writer.flush()?;
+            eprintln!(
+                "{} links between {} entries, {} broken",
+                graph.edges.len(),
+                graph.nodes.len(),
+                graph.broken_links().count()
+            );
+        }
+        Command::Verify { path } => {
+            let mut mdict = open(&path)?;
+            let report = mdict.verify();
+            for issue in &report.issues {
+                writeln!(stdout, "{}", issue)?;
+            }
+            writeln!(
+                stdout,
+                "checked {} key blocks and {} record blocks: {} issue(s)",
+                report.key_blocks_checked,
+                report.record_blocks_checked,
+                report.issues.len()
+            )?;
+            if !report.is_ok() {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Command::Optimize {
+            path,
+            output,
+            strict_links,
+            repair_links,
+        } => {
+            std::fs::create_dir_all(&output)?;
+            let bundle = create_mdict_bundle(path_string(&path), String::new())?;
+            bundle.set_broken_link_policy(match repair_links {
+                Some(max_distance) => BrokenLinkPolicy::Repair { max_distance },
+                None if strict_links => BrokenLinkPolicy::Strict,
+                None => BrokenLinkPolicy::Lenient,
+            });
+            let optimized = create_mdict_optimized_from_bundle_with_progress(
+                &bundle,
+                path_string(&output.join("index.fst")),
+                path_string(&output.join("readings.dat")),
+                path_string(&output.join("records.dat")),
+                Some(Box::new(StderrProgress)),
+            )?;
+            if strict_links || repair_links.is_some() {
+                let links = optimized.broken_links();
+                for link in &links {
+                    match &link.repaired_to {
+                        Some(key) => {
+                            eprintln!("{} -> {}: repaired to {}", link.key_text, link.target, key)
+                        }
+                        None => eprintln!("{} -> {}: not found", link.key_text, link.target),
+                    }
+                }
+                eprintln!("{} broken link(s)", links.len());
+            }
+            eprintln!("wrote sidecars to {}", output.display());
+        }
+        Command::Transcode { path, output } => {
+            let mut mdict = open(&path)?;
+            let stats = transcode_to_zstd(&mut mdict, &output)?;
+            eprintln!(
+                "transcoded {} entries to {} ({} bytes)",
+                stats.entries,
+                output.display(),
+                stats.output_bytes
+            );
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn open(path: &Path) -> Result<Mdict<File>, MDictError> {
+    Mdict::<File>::open(path)
+}
+
+fn path_string(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+struct StderrProgress;
+
+impl BuildProgressCallback for StderrProgress {
+    fn on_progress(&self, stage: BuildProgressStage, completed: u64, total: u64) {
+        eprintln!("{:?}: {}/{}", stage, completed, total);
+    }
+}
@@ -0,0 +1,71 @@
+/*
+ * C interface to mdict_tools (crate `mdict_capi`).
+ *
+ * Functions that can fail return an MdictStatus; mdict_last_error() copies
+ * the calling thread's last error message. Strings passed in are
+ * NUL-terminated UTF-8. Results are copied into caller-owned buffers: the
+ * required size is always stored in *out_len, and MDICT_BUFFER_TOO_SMALL is
+ * returned (with nothing copied) when buf_len is smaller. Returned text is
+ * UTF-8 and not NUL-terminated. Handles may be shared between threads.
+ */
+#ifndef MDICT_H
+#define MDICT_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum MdictStatus {
+    MDICT_OK = 0,
+    MDICT_INVALID_ARGUMENT = 1,
+    MDICT_NOT_FOUND = 2,
+    MDICT_BUFFER_TOO_SMALL = 3,
+    MDICT_IO = 4,
+    MDICT_INVALID_FORMAT = 5,
+    MDICT_UNSUPPORTED = 6,
+    MDICT_CANCELLED = 7,
+    MDICT_PANIC = 8,
+    /* A block failed to decode or verify; see mdict_last_error. */
+    MDICT_CORRUPTED = 9,
+    /* The dictionary file changed on disk since it was opened. */
+    MDICT_FILE_CHANGED = 10,
+} MdictStatus;
+
+typedef struct MdictHandle MdictHandle;
+
+/* Open an MDX, plus an MDD unless mdd_path is NULL or "". */
+MdictStatus mdict_open(const char *mdx_path, const char *mdd_path, MdictHandle **out_handle);
+
+/* Release a handle; NULL is ignored. */
+void mdict_close(MdictHandle *handle);
+
+/* Number of MDX entries; 0 for NULL. */
+uint64_t mdict_entry_count(const MdictHandle *handle);
+
+/* Entry indexes [*out_start, *out_end) whose keys start with prefix. */
+MdictStatus mdict_prefix_range(const MdictHandle *handle, const char *prefix,
+                               uint64_t *out_start, uint64_t *out_end);
+
+/* Key of entry `index`. */
+MdictStatus mdict_key_at(const MdictHandle *handle, uint64_t index,
+                         uint8_t *buf, size_t buf_len, size_t *out_len);
+
+/* Record of `key` as UTF-8 HTML, following @@@LINK= redirects. */
+MdictStatus mdict_lookup(const MdictHandle *handle, const char *key,
+                         uint8_t *buf, size_t buf_len, size_t *out_len);
+
+/* Raw bytes of the MDD resource `key`, e.g. "\\img\\a.png". */
+MdictStatus mdict_resource(const MdictHandle *handle, const char *key,
+                           uint8_t *buf, size_t buf_len, size_t *out_len);
+
+/* Copy the last error message; returns its full length. */
+size_t mdict_last_error(uint8_t *buf, size_t buf_len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* MDICT_H */
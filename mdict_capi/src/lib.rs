@@ -0,0 +1,325 @@
+//! Stable C ABI over `mdict_tools`, for hosts that cannot use the UniFFI
+//! bindings (Flutter via `dart:ffi`, game engines, plain C).
+//!
+//! The matching declarations are in `include/mdict.h`. Every function that
+//! can fail returns an [`MdictStatus`]; the message of the last failure on
+//! the calling thread is available from [`mdict_last_error`]. Variable-size
+//! results are copied into caller-owned buffers: the required size is always
+//! written to `out_len`, and `MDICT_BUFFER_TOO_SMALL` is returned (with
+//! nothing copied) when `buf_len` is smaller, so callers can retry.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr};
+use std::fs::File;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use mdict_tools::error::MDictError;
+use mdict_tools::mdict_shared::MdictShared;
+use mdict_tools::seekable_mmap::SeekableMmap;
+use mdict_tools::Mdict;
+
+/// Redirect hops followed by [`mdict_lookup`].
+const LOOKUP_LINK_DEPTH: u32 = 8;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MdictStatus {
+    Ok = 0,
+    InvalidArgument = 1,
+    NotFound = 2,
+    BufferTooSmall = 3,
+    Io = 4,
+    InvalidFormat = 5,
+    Unsupported = 6,
+    Cancelled = 7,
+    Panic = 8,
+    Corrupted = 9,
+    FileChanged = 10,
+}
+
+/// An open MDX with its optional MDD. Safe to use from several threads.
+pub struct MdictHandle {
+    mdx: MdictShared<SeekableMmap>,
+    mdd: Option<MdictShared<SeekableMmap>>,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+fn set_last_error(message: String) {
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+}
+
+fn status_for(error: &MDictError) -> MdictStatus {
+    match error {
+        MDictError::Io(_) => MdictStatus::Io,
+        MDictError::InvalidFormat(_) => MdictStatus::InvalidFormat,
+        MDictError::InvalidArgument(_) => MdictStatus::InvalidArgument,
+        MDictError::KeyNotFound(_) => MdictStatus::NotFound,
+        MDictError::UnsupportedFeature(_) => MdictStatus::Unsupported,
+        MDictError::Cancelled(_) => MdictStatus::Cancelled,
+        MDictError::Corrupted { .. } => MdictStatus::Corrupted,
+        MDictError::FileChanged(_) => MdictStatus::FileChanged,
+    }
+}
+
+/// Run `f`, turning errors and panics into a status and the thread's last
+/// error message.
+fn guard(f: impl FnOnce() -> Result<(), MDictError>) -> MdictStatus {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => MdictStatus::Ok,
+        Ok(Err(error)) => {
+            let status = status_for(&error);
+            set_last_error(error.to_string());
+            status
+        }
+        Err(_) => {
+            set_last_error("panic inside mdict_capi".to_string());
+            MdictStatus::Panic
+        }
+    }
+}
+
+/// # Safety
+/// `s` is null or a valid NUL-terminated string.
+unsafe fn str_arg<'a>(s: *const c_char, name: &str) -> Result<&'a str, MDictError> {
+    if s.is_null() {
+        return Err(MDictError::InvalidArgument(format!("{} is null", name)));
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| MDictError::InvalidArgument(format!("{} is not UTF-8", name)))
+}
+
+/// # Safety
+/// `handle` is null or was returned by [`mdict_open`] and not yet closed.
+unsafe fn handle_arg<'a>(handle: *const MdictHandle) -> Result<&'a MdictHandle, MDictError> {
+    handle
+        .as_ref()
+        .ok_or_else(|| MDictError::InvalidArgument("handle is null".to_string()))
+}
+
+/// Copy `data` to `buf`, or return `MDICT_BUFFER_TOO_SMALL` without
+/// copying. The required size is written to `out_len` either way.
+///
+/// # Safety
+/// `buf` is valid for `buf_len` bytes (or null with `buf_len == 0`) and
+/// `out_len` is null or valid.
+unsafe fn copy_out(data: &[u8], buf: *mut u8, buf_len: usize, out_len: *mut usize) -> MdictStatus {
+    if !out_len.is_null() {
+        *out_len = data.len();
+    }
+    if data.len() > buf_len {
+        set_last_error(format!(
+            "buffer of {} bytes is too small for {}",
+            buf_len,
+            data.len()
+        ));
+        return MdictStatus::BufferTooSmall;
+    }
+    if !data.is_empty() {
+        ptr::copy_nonoverlapping(data.as_ptr(), buf, data.len());
+    }
+    MdictStatus::Ok
+}
+
+fn open_mmap(path: &str) -> Result<MdictShared<SeekableMmap>, MDictError> {
+    let file = File::open(path)?;
+    Ok(MdictShared::new(Mdict::new(SeekableMmap::open(&file)?)?))
+}
+
+/// Open `mdx_path`, and `mdd_path` unless it is null or empty. Writes the
+/// new handle to `out_handle`; release it with [`mdict_close`].
+///
+/// # Safety
+/// The paths are null or NUL-terminated strings; `out_handle` is valid.
+#[no_mangle]
+pub unsafe extern "C" fn mdict_open(
+    mdx_path: *const c_char,
+    mdd_path: *const c_char,
+    out_handle: *mut *mut MdictHandle,
+) -> MdictStatus {
+    guard(|| {
+        if out_handle.is_null() {
+            return Err(MDictError::InvalidArgument(
+                "out_handle is null".to_string(),
+            ));
+        }
+        let mdx = open_mmap(str_arg(mdx_path, "mdx_path")?)?;
+        let mdd_path = if mdd_path.is_null() {
+            ""
+        } else {
+            str_arg(mdd_path, "mdd_path")?
+        };
+        let mdd = (!mdd_path.is_empty())
+            .then(|| open_mmap(mdd_path))
+            .transpose()?;
+        *out_handle = Box::into_raw(Box::new(MdictHandle { mdx, mdd }));
+        Ok(())
+    })
+}
+
+/// Release a handle from [`mdict_open`]. Null is ignored.
+///
+/// # Safety
+/// `handle` is null or an open handle, which must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn mdict_close(handle: *mut MdictHandle) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Number of MDX entries; 0 for a null handle.
+///
+/// # Safety
+/// `handle` is null or an open handle.
+#[no_mangle]
+pub unsafe extern "C" fn mdict_entry_count(handle: *const MdictHandle) -> u64 {
+    handle.as_ref().map_or(0, |handle| handle.mdx.num_entries())
+}
+
+/// The half-open range `[*out_start, *out_end)` of entry indexes whose keys
+/// start with `prefix`. An empty range means no key has the prefix.
+///
+/// # Safety
+/// `handle` is an open handle, `prefix` a NUL-terminated string, and the
+/// out pointers are valid.
+#[no_mangle]
+pub unsafe extern "C" fn mdict_prefix_range(
+    handle: *const MdictHandle,
+    prefix: *const c_char,
+    out_start: *mut u64,
+    out_end: *mut u64,
+) -> MdictStatus {
+    guard(|| {
+        let handle = handle_arg(handle)?;
+        if out_start.is_null() || out_end.is_null() {
+            return Err(MDictError::InvalidArgument(
+                "out pointer is null".to_string(),
+            ));
+        }
+        let (start, end) = handle
+            .mdx
+            .prefix_range_bounds(str_arg(prefix, "prefix")?)?
+            .unwrap_or((0, 0));
+        *out_start = start as u64;
+        *out_end = end as u64;
+        Ok(())
+    })
+}
+
+/// Copy the UTF-8 key of entry `index` (not NUL-terminated) into `buf`.
+///
+/// # Safety
+/// `handle` is an open handle and `buf`/`out_len` follow the buffer rules
+/// in the module docs.
+#[no_mangle]
+pub unsafe extern "C" fn mdict_key_at(
+    handle: *const MdictHandle,
+    index: u64,
+    buf: *mut u8,
+    buf_len: usize,
+    out_len: *mut usize,
+) -> MdictStatus {
+    let mut key = String::new();
+    let status = guard(|| {
+        let key_block = handle_arg(handle)?
+            .mdx
+            .get(index as usize)?
+            .ok_or_else(|| MDictError::KeyNotFound(format!("no entry at index {}", index)))?;
+        key = key_block.key_text;
+        Ok(())
+    });
+    if status != MdictStatus::Ok {
+        return status;
+    }
+    copy_out(key.as_bytes(), buf, buf_len, out_len)
+}
+
+/// Copy the record of `key` into `buf` as UTF-8 (not NUL-terminated),
+/// following `@@@LINK=` redirects.
+///
+/// # Safety
+/// `handle` is an open handle, `key` a NUL-terminated string, and
+/// `buf`/`out_len` follow the buffer rules in the module docs.
+#[no_mangle]
+pub unsafe extern "C" fn mdict_lookup(
+    handle: *const MdictHandle,
+    key: *const c_char,
+    buf: *mut u8,
+    buf_len: usize,
+    out_len: *mut usize,
+) -> MdictStatus {
+    let mut text = String::new();
+    let status = guard(|| {
+        let handle = handle_arg(handle)?;
+        let key = str_arg(key, "key")?;
+        let key_block = find(&handle.mdx, key)?;
+        let record = handle.mdx.record_resolved(&key_block, LOOKUP_LINK_DEPTH)?;
+        text = handle.mdx.metadata().encoding.decode(&record);
+        Ok(())
+    });
+    if status != MdictStatus::Ok {
+        return status;
+    }
+    copy_out(text.as_bytes(), buf, buf_len, out_len)
+}
+
+/// Copy the MDD resource stored under `key` (e.g. `\img\a.png`) into `buf`.
+///
+/// # Safety
+/// `handle` is an open handle, `key` a NUL-terminated string, and
+/// `buf`/`out_len` follow the buffer rules in the module docs.
+#[no_mangle]
+pub unsafe extern "C" fn mdict_resource(
+    handle: *const MdictHandle,
+    key: *const c_char,
+    buf: *mut u8,
+    buf_len: usize,
+    out_len: *mut usize,
+) -> MdictStatus {
+    let mut data = Vec::new();
+    let status = guard(|| {
+        let handle = handle_arg(handle)?;
+        let key = str_arg(key, "key")?;
+        let mdd = handle
+            .mdd
+            .as_ref()
+            .ok_or_else(|| MDictError::KeyNotFound("no MDD was opened".to_string()))?;
+        data = mdd.record_at_key_block(&find(mdd, key)?)?;
+        Ok(())
+    });
+    if status != MdictStatus::Ok {
+        return status;
+    }
+    copy_out(&data, buf, buf_len, out_len)
+}
+
+/// Copy the calling thread's last error message into `buf` (UTF-8, not
+/// NUL-terminated). Returns the message length even when `buf` is short.
+///
+/// # Safety
+/// `buf` is valid for `buf_len` bytes, or null with `buf_len == 0`.
+#[no_mangle]
+pub unsafe extern "C" fn mdict_last_error(buf: *mut u8, buf_len: usize) -> usize {
+    LAST_ERROR.with(|last| {
+        let last = last.borrow();
+        let len = last.len().min(buf_len);
+        if len > 0 {
+            ptr::copy_nonoverlapping(last.as_ptr(), buf, len);
+        }
+        last.len()
+    })
+}
+
+fn find(
+    mdict: &MdictShared<SeekableMmap>,
+    key: &str,
+) -> Result<mdict_tools::types::KeyBlock, MDictError> {
+    let not_found = || MDictError::KeyNotFound(format!("'{}' not found", key));
+    let index = mdict.index_for(key)?.ok_or_else(not_found)?;
+    mdict.get(index)?.ok_or_else(not_found)
+}
@@ -0,0 +1,100 @@
+use std::ffi::CString;
+use std::ptr;
+
+use mdict_capi::*;
+use mdict_tools::mdx_writer::MdxWriter;
+
+fn c(s: &str) -> CString {
+    CString::new(s).unwrap()
+}
+
+/// Call a buffer-filling function the way C callers do: ask for the size,
+/// then fill an exactly sized buffer.
+fn fetch(f: impl Fn(*mut u8, usize, *mut usize) -> MdictStatus) -> Result<Vec<u8>, MdictStatus> {
+    let mut len = 0;
+    match f(ptr::null_mut(), 0, &mut len) {
+        MdictStatus::Ok => return Ok(Vec::new()),
+        MdictStatus::BufferTooSmall => {}
+        status => return Err(status),
+    }
+    let mut buf = vec![0u8; len];
+    assert_eq!(f(buf.as_mut_ptr(), buf.len(), &mut len), MdictStatus::Ok);
+    buf.truncate(len);
+    Ok(buf)
+}
+
+fn last_error() -> String {
+    let len = unsafe { mdict_last_error(ptr::null_mut(), 0) };
+    let mut buf = vec![0u8; len];
+    unsafe { mdict_last_error(buf.as_mut_ptr(), buf.len()) };
+    String::from_utf8(buf).unwrap()
+}
+
+#[test]
+fn open_search_lookup_and_close() {
+    let dir = tempfile::tempdir().unwrap();
+    let mdx_path = dir.path().join("dict.mdx");
+    let mdd_path = dir.path().join("dict.mdd");
+    let mut mdx = MdxWriter::new();
+    mdx.add("cat", "<p>feline</p>").unwrap();
+    mdx.add("category", "<p>class</p>").unwrap();
+    mdx.add("kitty", "@@@LINK=cat").unwrap();
+    mdx.write_to_path(&mdx_path).unwrap();
+    let mut mdd = MdxWriter::mdd();
+    mdd.add_raw("\\a.png", vec![0x89, b'P', 0x0A, 0x00])
+        .unwrap();
+    mdd.write_to_path(&mdd_path).unwrap();
+
+    let mdx_path = c(mdx_path.to_str().unwrap());
+    let mdd_path = c(mdd_path.to_str().unwrap());
+    let mut handle = ptr::null_mut();
+    let status = unsafe { mdict_open(mdx_path.as_ptr(), mdd_path.as_ptr(), &mut handle) };
+    assert_eq!(status, MdictStatus::Ok);
+    assert_eq!(unsafe { mdict_entry_count(handle) }, 3);
+
+    let (mut start, mut end) = (0, 0);
+    let prefix = c("cat");
+    let status = unsafe { mdict_prefix_range(handle, prefix.as_ptr(), &mut start, &mut end) };
+    assert_eq!(status, MdictStatus::Ok);
+    assert_eq!((start, end), (0, 2));
+    let key = fetch(|buf, len, out| unsafe { mdict_key_at(handle, 1, buf, len, out) });
+    assert_eq!(key.unwrap(), b"category");
+
+    let kitty = c("kitty");
+    let record =
+        fetch(|buf, len, out| unsafe { mdict_lookup(handle, kitty.as_ptr(), buf, len, out) });
+    assert_eq!(record.unwrap(), b"<p>feline</p>");
+
+    let png = c("\\a.png");
+    let resource =
+        fetch(|buf, len, out| unsafe { mdict_resource(handle, png.as_ptr(), buf, len, out) });
+    assert_eq!(resource.unwrap(), [0x89, b'P', 0x0A, 0x00]);
+
+    let bird = c("bird");
+    let missing =
+        fetch(|buf, len, out| unsafe { mdict_lookup(handle, bird.as_ptr(), buf, len, out) });
+    assert_eq!(missing, Err(MdictStatus::NotFound));
+    assert!(last_error().contains("'bird' not found"));
+
+    unsafe { mdict_close(handle) };
+}
+
+#[test]
+fn bad_arguments_are_reported_not_crashed_on() {
+    let mut handle = ptr::null_mut();
+    let missing = c("/nonexistent/dict.mdx");
+    let status = unsafe { mdict_open(missing.as_ptr(), ptr::null(), &mut handle) };
+    assert_eq!(status, MdictStatus::Io);
+    assert!(handle.is_null());
+
+    let status = unsafe { mdict_open(ptr::null(), ptr::null(), &mut handle) };
+    assert_eq!(status, MdictStatus::InvalidArgument);
+    assert_eq!(last_error(), "Invalid Argument: mdx_path is null");
+
+    let key = c("cat");
+    let mut len = 0;
+    let status = unsafe { mdict_lookup(ptr::null(), key.as_ptr(), ptr::null_mut(), 0, &mut len) };
+    assert_eq!(status, MdictStatus::InvalidArgument);
+    assert_eq!(unsafe { mdict_entry_count(ptr::null()) }, 0);
+    unsafe { mdict_close(ptr::null_mut()) };
+}
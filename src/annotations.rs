@@ -0,0 +1,357 @@
+//! Notes and highlights a user keeps on dictionary entries.
+//!
+//! An [`AnnotationStore`] is a sidecar file holding the [`Annotation`]s made
+//! on one dictionary, keyed by the [`EntryId`] of their entry so they stay
+//! attached when the optimized index is rebuilt. The file is a packed
+//! storage container with one zstd-compressed entry per annotation; the
+//! header's user data marks it as an annotation store and carries the next
+//! annotation id. The store is kept in memory, and every change rewrites the
+//! file aside and renames it into place, which suits the hundreds or
+//! thousands of annotations one person makes.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::block_cache::CacheCapacity;
+use crate::error::{MDictError, Result};
+use crate::packed_storage::{CompressionEncoding, PackedStorageReader, PackedStorageWriter};
+use crate::types::{Annotation, EntryId, Highlight};
+
+/// Start of the user data of an annotation store, followed by the format
+/// version and the next annotation id.
+const STORE_MAGIC: [u8; 8] = *b"MDNOTES\0";
+const STORE_VERSION: u8 = 1;
+const HAS_HIGHLIGHT: u8 = 0x01;
+
+#[derive(Debug, Clone)]
+struct Annotations {
+    next_id: u64,
+    by_id: BTreeMap<u64, Annotation>,
+}
+
+/// The annotations of one dictionary. Created by [`open_annotation_store`].
+#[derive(uniffi::Object)]
+pub struct AnnotationStore {
+    path: PathBuf,
+    annotations: Mutex<Annotations>,
+}
+
+/// Open the annotation store at `path`, e.g. `dictionary.mdx.notes` in app
+/// storage. A missing file is an empty store, written on the first change.
+#[uniffi::export]
+pub fn open_annotation_store(path: String) -> Result<AnnotationStore> {
+    AnnotationStore::open(path)
+}
+
+impl AnnotationStore {
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let annotations = match File::open(&path) {
+            Ok(file) => read_store(file)?,
+            Err(e) if e.kind() == ErrorKind::NotFound => Annotations {
+                next_id: 1,
+                by_id: BTreeMap::new(),
+            },
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            path,
+            annotations: Mutex::new(annotations),
+        })
+    }
+
+    /// Apply `f` to a copy of the annotations and keep the copy once it is
+    /// saved, so a failed save changes nothing.
+    fn change<T>(&self, f: impl FnOnce(&mut Annotations) -> Result<T>) -> Result<T> {
+        let mut annotations = self.annotations.lock().unwrap();
+        let mut changed = annotations.clone();
+        let out = f(&mut changed)?;
+        self.save(&changed)?;
+        *annotations = changed;
+        Ok(out)
+    }
+
+    fn save(&self, annotations: &Annotations) -> Result<()> {
+        let config = crate::config::config();
+        let mut user_data = STORE_MAGIC.to_vec();
+        user_data.push(STORE_VERSION);
+        user_data.extend_from_slice(&annotations.next_id.to_le_bytes());
+        let mut storage = PackedStorageWriter::new(
+            CompressionEncoding::Zstd,
+            config.record_compression_level,
+            config.packed_block_size(),
+        )?
+        .with_user_data(user_data);
+        for annotation in annotations.by_id.values() {
+            storage.push_entry(&encode_annotation(annotation))?;
+        }
+
+        let mut partial = self.path.as_os_str().to_owned();
+        partial.push(".partial");
+        let mut writer = BufWriter::new(File::create(&partial)?);
+        storage.finish_to_writer(&mut writer)?;
+        writer.flush()?;
+        drop(writer);
+        fs::rename(partial, &self.path)?;
+        Ok(())
+    }
+
+    fn matching(&self, pred: impl Fn(&Annotation) -> bool) -> Vec<Annotation> {
+        let annotations = self.annotations.lock().unwrap();
+        annotations
+            .by_id
+            .values()
+            .filter(|annotation| pred(annotation))
+            .cloned()
+            .collect()
+    }
+}
+
+#[uniffi::export]
+impl AnnotationStore {
+    pub fn path(&self) -> String {
+        self.path.to_string_lossy().into_owned()
+    }
+
+    /// Add a note, a highlight or both to entry `entry_id` and return the
+    /// annotation with its id.
+    pub fn add(
+        &self,
+        entry_id: EntryId,
+        note: String,
+        highlight: Option<Highlight>,
+    ) -> Result<Annotation> {
+        check_highlight(highlight.as_ref())?;
+        self.change(|annotations| {
+            let now = now_ms();
+            let annotation = Annotation {
+                id: annotations.next_id,
+                entry_id,
+                note,
+                highlight,
+                created_ms: now,
+                updated_ms: now,
+            };
+            annotations.next_id += 1;
+            annotations.by_id.insert(annotation.id, annotation.clone());
+            Ok(annotation)
+        })
+    }
+
+    pub fn get(&self, id: u64) -> Option<Annotation> {
+        self.annotations.lock().unwrap().by_id.get(&id).cloned()
+    }
+
+    /// The annotations of entry `entry_id`, oldest first.
+    pub fn for_entry(&self, entry_id: EntryId) -> Vec<Annotation> {
+        self.matching(|annotation| annotation.entry_id == entry_id)
+    }
+
+    /// Every annotation, oldest first.
+    pub fn all(&self) -> Vec<Annotation> {
+        self.matching(|_| true)
+    }
+
+    /// The entries with at least one annotation, each once, for marking them
+    /// in a word list.
+    pub fn annotated_entries(&self) -> Vec<EntryId> {
+        let mut entries = self
+            .all()
+            .into_iter()
+            .map(|annotation| annotation.entry_id)
+            .collect::<Vec<_>>();
+        entries.sort_unstable();
+        entries.dedup();
+        entries
+    }
+
+    pub fn count(&self) -> u64 {
+        self.annotations.lock().unwrap().by_id.len() as u64
+    }
+
+    /// Replace the note and highlight of annotation `id`.
+    pub fn update(
+        &self,
+        id: u64,
+        note: String,
+        highlight: Option<Highlight>,
+    ) -> Result<Annotation> {
+        check_highlight(highlight.as_ref())?;
+        self.change(|annotations| {
+            let annotation = annotations
+                .by_id
+                .get_mut(&id)
+                .ok_or_else(|| MDictError::KeyNotFound(format!("no annotation {}", id)))?;
+            annotation.note = note;
+            annotation.highlight = highlight;
+            annotation.updated_ms = now_ms().max(annotation.created_ms);
+            Ok(annotation.clone())
+        })
+    }
+
+    pub fn remove(&self, id: u64) -> Result<()> {
+        self.change(|annotations| {
+            annotations
+                .by_id
+                .remove(&id)
+                .map(|_| ())
+                .ok_or_else(|| MDictError::KeyNotFound(format!("no annotation {}", id)))
+        })
+    }
+
+    /// Remove every annotation of entry `entry_id` and return how many
+    /// there were.
+    pub fn remove_for_entry(&self, entry_id: EntryId) -> Result<u32> {
+        if self.for_entry(entry_id).is_empty() {
+            return Ok(0);
+        }
+        self.change(|annotations| {
+            let before = annotations.by_id.len();
+            annotations
+                .by_id
+                .retain(|_, annotation| annotation.entry_id != entry_id);
+            Ok((before - annotations.by_id.len()) as u32)
+        })
+    }
+}
+
+fn check_highlight(highlight: Option<&Highlight>) -> Result<()> {
+    if let Some(highlight) = highlight.filter(|h| h.start > h.end) {
+        return Err(MDictError::InvalidArgument(format!(
+            "highlight starts at {} after it ends at {}",
+            highlight.start, highlight.end
+        )));
+    }
+    Ok(())
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as u64)
+}
+
+fn read_store(file: File) -> Result<Annotations> {
+    let mut storage = PackedStorageReader::new(file, CacheCapacity::Entries(0))?;
+    let user_data = storage.index().header.user_data.as_deref();
+    let Some(store_header) = user_data.and_then(|data| data.strip_prefix(&STORE_MAGIC[..])) else {
+        return Err(MDictError::InvalidFormat(
+            "packed storage file is not an annotation store".to_string(),
+        ));
+    };
+    let mut input = Input(store_header);
+    let version = input.take(1)?[0];
+    if version > STORE_VERSION {
+        return Err(MDictError::UnsupportedFeature(format!(
+            "annotation store version {} is newer than {}",
+            version, STORE_VERSION
+        )));
+    }
+    let next_id = input.u64()?;
+
+    let total = storage.total_uncompressed_size() as usize;
+    let bytes = storage.read_at(0, total)?;
+    let mut input = Input(&bytes);
+    let mut by_id = BTreeMap::new();
+    for _ in 0..storage.num_entries() {
+        let annotation = decode_annotation(&mut input)?;
+        if annotation.id >= next_id {
+            return Err(MDictError::InvalidFormat(format!(
+                "annotation {} is not below the next id {}",
+                annotation.id, next_id
+            )));
+        }
+        by_id.insert(annotation.id, annotation);
+    }
+    if !input.0.is_empty() {
+        return Err(MDictError::InvalidFormat(
+            "annotation store has data after its last annotation".to_string(),
+        ));
+    }
+    Ok(Annotations { next_id, by_id })
+}
+
+fn encode_annotation(annotation: &Annotation) -> Vec<u8> {
+    let note = annotation.note.as_bytes();
+    let mut out = Vec::with_capacity(49 + note.len());
+    out.extend_from_slice(&annotation.id.to_le_bytes());
+    out.extend_from_slice(&annotation.entry_id.0.to_le_bytes());
+    out.extend_from_slice(&annotation.created_ms.to_le_bytes());
+    out.extend_from_slice(&annotation.updated_ms.to_le_bytes());
+    match &annotation.highlight {
+        Some(highlight) => {
+            out.push(HAS_HIGHLIGHT);
+            out.extend_from_slice(&highlight.start.to_le_bytes());
+            out.extend_from_slice(&highlight.end.to_le_bytes());
+            out.extend_from_slice(&highlight.color.to_le_bytes());
+        }
+        None => out.push(0),
+    }
+    out.extend_from_slice(&(note.len() as u32).to_le_bytes());
+    out.extend_from_slice(note);
+    out
+}
+
+fn decode_annotation(input: &mut Input) -> Result<Annotation> {
+    let id = input.u64()?;
+    let entry_id = EntryId(input.u64()?);
+    let created_ms = input.u64()?;
+    let updated_ms = input.u64()?;
+    let highlight = match input.take(1)?[0] {
+        0 => None,
+        HAS_HIGHLIGHT => Some(Highlight {
+            start: input.u32()?,
+            end: input.u32()?,
+            color: input.u32()?,
+        }),
+        other => {
+            return Err(MDictError::InvalidFormat(format!(
+                "annotation {}: unknown flags {:#04x}",
+                id, other
+            )))
+        }
+    };
+    let len = input.u32()? as usize;
+    let note = String::from_utf8(input.take(len)?.to_vec()).map_err(|e| {
+        MDictError::InvalidFormat(format!("annotation {}: invalid utf8 note: {}", id, e))
+    })?;
+    Ok(Annotation {
+        id,
+        entry_id,
+        note,
+        highlight,
+        created_ms,
+        updated_ms,
+    })
+}
+
+struct Input<'a>(&'a [u8]);
+
+impl<'a> Input<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(MDictError::InvalidFormat("truncated annotation".to_string()));
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.take(N)?);
+        Ok(out)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+}
@@ -0,0 +1,233 @@
+//! Non-blocking lookups on top of tokio (`async` feature).
+//!
+//! Dictionaries are read through an [`AsyncByteSource`]. Parsing and block
+//! decoding reuse the synchronous `format::*` code: each call runs on tokio's
+//! blocking pool against a reader that fetches byte ranges from the source,
+//! so neither I/O waits nor decompression stall the async executor.
+
+use std::future::Future;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::runtime::Handle;
+use tokio::sync::Mutex;
+
+use crate::error::{MDictError, Result};
+use crate::format::HeaderInfo;
+use crate::types::{DictionaryMetadata, KeyBlock};
+use crate::{Mdict, MdictShared};
+
+/// Minimum bytes fetched per source read, so the many small header and
+/// index reads do not each become a round trip.
+const READ_AHEAD: usize = 64 * 1024;
+
+/// Random-access bytes that can be fetched asynchronously: a local file, an
+/// in-memory buffer, or remote storage such as an object store.
+pub trait AsyncByteSource: Send + Sync + 'static {
+    /// Total size in bytes.
+    fn size(&self) -> u64;
+
+    /// Read `len` bytes starting at `offset`. May return fewer bytes only at
+    /// the end of the source.
+    fn read_at(&self, offset: u64, len: usize) -> impl Future<Output = io::Result<Vec<u8>>> + Send;
+}
+
+impl AsyncByteSource for Vec<u8> {
+    fn size(&self) -> u64 {
+        self.len() as u64
+    }
+
+    async fn read_at(&self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        let start = (offset as usize).min(self.len());
+        let end = start.saturating_add(len).min(self.len());
+        Ok(self[start..end].to_vec())
+    }
+}
+
+/// A [`tokio::fs::File`] as an [`AsyncByteSource`].
+pub struct TokioFileSource {
+    file: Mutex<tokio::fs::File>,
+    size: u64,
+}
+
+impl TokioFileSource {
+    pub async fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = tokio::fs::File::open(path).await?;
+        let size = file.metadata().await?.len();
+        Ok(Self {
+            file: Mutex::new(file),
+            size,
+        })
+    }
+}
+
+impl AsyncByteSource for TokioFileSource {
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    async fn read_at(&self, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        let len = len.min(self.size.saturating_sub(offset) as usize);
+        let mut buf = vec![0u8; len];
+        let mut file = self.file.lock().await;
+        file.seek(SeekFrom::Start(offset)).await?;
+        file.read_exact(&mut buf).await?;
+        Ok(buf)
+    }
+}
+
+/// Synchronous `Read + Seek` view of an [`AsyncByteSource`]. Only usable off
+/// the async executor (on the blocking pool), where it can wait on `runtime`.
+pub struct SourceReader<S: AsyncByteSource> {
+    source: Arc<S>,
+    runtime: Handle,
+    pos: u64,
+    buf_start: u64,
+    buf: Vec<u8>,
+}
+
+impl<S: AsyncByteSource> Clone for SourceReader<S> {
+    fn clone(&self) -> Self {
+        Self {
+            source: Arc::clone(&self.source),
+            runtime: self.runtime.clone(),
+            pos: self.pos,
+            buf_start: 0,
+            buf: Vec::new(),
+        }
+    }
+}
+
+impl<S: AsyncByteSource> Read for SourceReader<S> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let size = self.source.size();
+        if self.pos >= size || out.is_empty() {
+            return Ok(0);
+        }
+
+        let buf_end = self.buf_start + self.buf.len() as u64;
+        if self.pos < self.buf_start || self.pos >= buf_end {
+            let want = out.len().max(READ_AHEAD).min((size - self.pos) as usize);
+            self.buf = self.runtime.block_on(self.source.read_at(self.pos, want))?;
+            self.buf_start = self.pos;
+            if self.buf.is_empty() {
+                return Ok(0);
+            }
+        }
+
+        let offset = (self.pos - self.buf_start) as usize;
+        let n = out.len().min(self.buf.len() - offset);
+        out[..n].copy_from_slice(&self.buf[offset..offset + n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<S: AsyncByteSource> Seek for SourceReader<S> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => offset as i128,
+            SeekFrom::End(offset) => self.source.size() as i128 + offset as i128,
+            SeekFrom::Current(offset) => self.pos as i128 + offset as i128,
+        };
+        if target < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek before start of source",
+            ));
+        }
+        self.pos = target as u64;
+        Ok(self.pos)
+    }
+}
+
+/// Async counterpart of [`MdictShared`]. Cheap to clone; clones share the
+/// parsed dictionary and the handle pool.
+pub struct AsyncMdict<S: AsyncByteSource> {
+    inner: Arc<MdictShared<SourceReader<S>>>,
+}
+
+impl<S: AsyncByteSource> Clone for AsyncMdict<S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<S: AsyncByteSource> AsyncMdict<S> {
+    /// Parse the dictionary behind `source`. Must be called from within a
+    /// tokio runtime.
+    pub async fn open(source: S) -> Result<Self> {
+        let reader = SourceReader {
+            source: Arc::new(source),
+            runtime: Handle::current(),
+            pos: 0,
+            buf_start: 0,
+            buf: Vec::new(),
+        };
+        let mdict = blocking(move || Mdict::new(reader)).await?;
+        Ok(Self {
+            inner: Arc::new(MdictShared::new(mdict)),
+        })
+    }
+
+    pub fn header(&self) -> &HeaderInfo {
+        self.inner.header()
+    }
+
+    pub fn metadata(&self) -> DictionaryMetadata {
+        self.inner.metadata()
+    }
+
+    pub fn num_entries(&self) -> u64 {
+        self.inner.num_entries()
+    }
+
+    /// The key block for an exact `key`, if present.
+    pub async fn lookup(&self, key: &str) -> Result<Option<KeyBlock>> {
+        let key = key.to_string();
+        self.run(move |mdict| match mdict.index_for(&key)? {
+            Some(index) => mdict.get(index),
+            None => Ok(None),
+        })
+        .await
+    }
+
+    pub async fn get(&self, index: usize) -> Result<Option<KeyBlock>> {
+        self.run(move |mdict| mdict.get(index)).await
+    }
+
+    /// Every key starting with `prefix`, in key order.
+    pub async fn search_prefix(&self, prefix: &str) -> Result<Vec<KeyBlock>> {
+        let prefix = prefix.to_string();
+        self.run(move |mdict| mdict.search_keys_prefix(&prefix))
+            .await
+    }
+
+    pub async fn record_at(&self, key_block: KeyBlock) -> Result<Vec<u8>> {
+        self.run(move |mdict| mdict.record_at_key_block(&key_block))
+            .await
+    }
+
+    pub async fn record_resolved(&self, key_block: KeyBlock, max_depth: u32) -> Result<Vec<u8>> {
+        self.run(move |mdict| mdict.record_resolved(&key_block, max_depth))
+            .await
+    }
+
+    async fn run<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&MdictShared<SourceReader<S>>) -> Result<T> + Send + 'static,
+    ) -> Result<T> {
+        let inner = Arc::clone(&self.inner);
+        blocking(move || f(&inner)).await
+    }
+}
+
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| MDictError::Io(format!("blocking task failed: {}", e)))?
+}
@@ -0,0 +1,197 @@
+//! Dictionary maintenance checks: dangling `@@@LINK=` redirects, record data
+//! no key points at, and MDD resources no record references.
+
+use std::collections::{BTreeSet, HashSet};
+use std::io::{Read, Seek};
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::error::Result;
+use crate::links::extract_link;
+use crate::Mdict;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DanglingLink {
+    pub key_text: String,
+    pub target: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingResource {
+    pub key_text: String,
+    pub reference: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AuditReport {
+    /// `@@@LINK=` records whose target is not a key in the dictionary.
+    pub dangling_links: Vec<DanglingLink>,
+    /// Uncompressed `(start, end)` record-data ranges no key id points into.
+    pub unreferenced_record_ranges: Vec<(u64, u64)>,
+    /// MDD keys never referenced by any MDX record.
+    pub unreferenced_resources: Vec<String>,
+    /// Resource references in MDX records with no matching MDD key.
+    pub missing_resources: Vec<MissingResource>,
+}
+
+impl AuditReport {
+    pub fn is_clean(&self) -> bool {
+        self.dangling_links.is_empty()
+            && self.unreferenced_record_ranges.is_empty()
+            && self.unreferenced_resources.is_empty()
+            && self.missing_resources.is_empty()
+    }
+}
+
+fn resource_patterns() -> &'static [Regex; 2] {
+    static PATTERNS: OnceLock<[Regex; 2]> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            Regex::new(r#"(?i)\b(?:src|href)\s*=\s*["']([^"']+)["']"#).unwrap(),
+            Regex::new(r#"(?i)url\(\s*["']?([^"')]+)["']?\s*\)"#).unwrap(),
+        ]
+    })
+}
+
+/// Extract MDD resource references (`src`, `href`, CSS `url()`, `sound://`)
+/// from an HTML record, normalized to MDD key form (`\dir\file.png`).
+/// Cross-entry (`entry://`), external and inline references are skipped.
+pub fn extract_resource_references(html: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    for pattern in resource_patterns() {
+        for capture in pattern.captures_iter(html) {
+            if let Some(key) = normalize_resource_reference(&capture[1]) {
+                out.push(key);
+            }
+        }
+    }
+    out
+}
+
+/// Convert a record-side reference into an MDD key, or `None` if it does not
+/// point into the MDD.
+pub fn normalize_resource_reference(reference: &str) -> Option<String> {
+    let reference = reference.trim();
+    let lower = reference.to_ascii_lowercase();
+    let skipped = [
+        "entry://",
+        "http://",
+        "https://",
+        "data:",
+        "javascript:",
+        "mailto:",
+        "#",
+    ];
+    if reference.is_empty() || skipped.iter().any(|prefix| lower.starts_with(prefix)) {
+        return None;
+    }
+
+    let path = ["sound://", "file://"]
+        .iter()
+        .find_map(|scheme| {
+            lower
+                .starts_with(scheme)
+                .then(|| &reference[scheme.len()..])
+        })
+        .unwrap_or(reference);
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let path = path.trim_start_matches("./").replace('/', "\\");
+    if path.is_empty() {
+        return None;
+    }
+
+    if path.starts_with('\\') {
+        Some(path)
+    } else {
+        Some(format!("\\{}", path))
+    }
+}
+
+/// Walk every entry of `mdx` (and every key of `mdd`, if given) and report
+/// broken or unused data.
+pub fn audit<R: Read + Seek, S: Read + Seek>(
+    mdx: &mut Mdict<R>,
+    mdd: Option<&mut Mdict<S>>,
+) -> Result<AuditReport> {
+    let encoding = mdx.key_block_index.header.get_encoding();
+
+    let mut report = AuditReport::default();
+    let mut key_ids = BTreeSet::new();
+    let mut links = Vec::new();
+    let mut references = Vec::new();
+
+    for entry in mdx.iter_entries() {
+        let (key_block, record) = entry?;
+        key_ids.insert(key_block.key_id);
+
+        let text = encoding.decode(&record);
+        if let Some(target) = extract_link(&text) {
+            links.push((key_block.key_text, target.to_string()));
+            continue;
+        }
+
+        for reference in extract_resource_references(&text) {
+            references.push((key_block.key_text.clone(), reference));
+        }
+    }
+
+    for (key_text, target) in links {
+        if mdx
+            .key_block_index
+            .index_for(&mut mdx.reader, &target)?
+            .is_none()
+        {
+            report
+                .dangling_links
+                .push(DanglingLink { key_text, target });
+        }
+    }
+
+    let total_record_bytes = mdx
+        .record_section()?
+        .record_index_prefix_sum
+        .last()
+        .map(|ri| ri.uncompressed_size)
+        .unwrap_or(0);
+    match key_ids.first() {
+        Some(&first) if first > 0 => report.unreferenced_record_ranges.push((0, first)),
+        None if total_record_bytes > 0 => report
+            .unreferenced_record_ranges
+            .push((0, total_record_bytes)),
+        _ => {}
+    }
+
+    if let Some(mdd) = mdd {
+        let total_resources = mdd.key_block_index.key_section.num_entries as usize;
+        let mut resource_keys = HashSet::with_capacity(total_resources);
+        let mut ordered_keys = Vec::with_capacity(total_resources);
+        for i in 0..total_resources {
+            let Some(key_block) = mdd.get(i)? else {
+                break;
+            };
+            resource_keys.insert(key_block.key_text.to_lowercase());
+            ordered_keys.push(key_block.key_text);
+        }
+
+        let mut referenced = HashSet::new();
+        for (key_text, reference) in references {
+            let lowered = reference.to_lowercase();
+            if resource_keys.contains(&lowered) {
+                referenced.insert(lowered);
+            } else {
+                report.missing_resources.push(MissingResource {
+                    key_text,
+                    reference,
+                });
+            }
+        }
+
+        report.unreferenced_resources = ordered_keys
+            .into_iter()
+            .filter(|key| !referenced.contains(&key.to_lowercase()))
+            .collect();
+    }
+
+    Ok(report)
+}
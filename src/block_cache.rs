@@ -0,0 +1,145 @@
+//! Least-recently-used cache for decoded key and record blocks.
+
+use std::collections::{BTreeMap, HashMap};
+
+/// How much a [`BlockCache`] may hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheCapacity {
+    /// At most this many blocks. `Entries(0)` disables the cache.
+    Entries(usize),
+    /// Blocks until their combined decoded size reaches this many bytes. The
+    /// most recently inserted block is always kept, even if it alone exceeds
+    /// the budget.
+    Bytes(usize),
+}
+
+impl From<usize> for CacheCapacity {
+    fn from(entries: usize) -> Self {
+        CacheCapacity::Entries(entries)
+    }
+}
+
+struct CachedBlock<V> {
+    value: V,
+    weight: usize,
+    last_used: u64,
+}
+
+/// Blocks keyed by their index in the section, evicted least recently used
+/// first once the [`CacheCapacity`] is exceeded.
+pub struct BlockCache<V> {
+    capacity: CacheCapacity,
+    blocks: HashMap<usize, CachedBlock<V>>,
+    /// `last_used` tick -> block index, oldest first.
+    recency: BTreeMap<u64, usize>,
+    tick: u64,
+    total_weight: usize,
+}
+
+impl<V> BlockCache<V> {
+    pub fn new(capacity: impl Into<CacheCapacity>) -> Self {
+        Self {
+            capacity: capacity.into(),
+            blocks: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+            total_weight: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> CacheCapacity {
+        self.capacity
+    }
+
+    /// Change the capacity, evicting blocks that no longer fit.
+    pub fn set_capacity(&mut self, capacity: impl Into<CacheCapacity>) {
+        self.capacity = capacity.into();
+        self.evict_to_fit(None);
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Combined weight (decoded bytes) of the cached blocks.
+    pub fn total_weight(&self) -> usize {
+        self.total_weight
+    }
+
+    pub fn contains(&self, block: usize) -> bool {
+        self.blocks.contains_key(&block)
+    }
+
+    /// Look up `block`, marking it most recently used.
+    pub fn get(&mut self, block: usize) -> Option<&V> {
+        let tick = self.next_tick();
+        let cached = self.blocks.get_mut(&block)?;
+        self.recency.remove(&cached.last_used);
+        self.recency.insert(tick, block);
+        cached.last_used = tick;
+        Some(&cached.value)
+    }
+
+    /// Cache `value` as `block` with the given weight in bytes, evicting
+    /// older blocks as needed. A no-op when the capacity is `Entries(0)`.
+    pub fn insert(&mut self, block: usize, value: V, weight: usize) {
+        if self.capacity == CacheCapacity::Entries(0) {
+            return;
+        }
+        self.remove(block);
+
+        let tick = self.next_tick();
+        self.recency.insert(tick, block);
+        self.total_weight += weight;
+        self.blocks.insert(
+            block,
+            CachedBlock {
+                value,
+                weight,
+                last_used: tick,
+            },
+        );
+        self.evict_to_fit(Some(block));
+    }
+
+    pub fn remove(&mut self, block: usize) -> Option<V> {
+        let cached = self.blocks.remove(&block)?;
+        self.recency.remove(&cached.last_used);
+        self.total_weight -= cached.weight;
+        Some(cached.value)
+    }
+
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+        self.recency.clear();
+        self.total_weight = 0;
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn over_capacity(&self) -> bool {
+        match self.capacity {
+            CacheCapacity::Entries(max) => self.blocks.len() > max,
+            CacheCapacity::Bytes(max) => self.total_weight > max,
+        }
+    }
+
+    fn evict_to_fit(&mut self, keep: Option<usize>) {
+        while self.over_capacity() {
+            let Some((_, &oldest)) = self.recency.iter().next() else {
+                break;
+            };
+            if Some(oldest) == keep {
+                break;
+            }
+            self.remove(oldest);
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! Key ordering as MDict sorts it.
+//!
+//! MDict sorts keys by a folded form: lowercased unless the header sets
+//! `KeyCaseSensitive="Yes"`, and without spaces and ASCII punctuation when it
+//! sets `StripKey="Yes"`. Key lookups compare folded keys, so a prefix
+//! matches every key whose folded form starts with the folded prefix.
+//!
+//! Dictionaries in a legacy code page (GBK, Big5, GB18030) are sorted by the
+//! encoded bytes of the folded keys, which is not the order of their decoded
+//! text.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+#[cfg(feature = "icu_collator")]
+use std::sync::Arc;
+
+#[cfg(feature = "icu_collator")]
+use icu::collator::options::{CollatorOptions, Strength};
+#[cfg(feature = "icu_collator")]
+use icu::collator::{Collator, CollatorBorrowed};
+#[cfg(feature = "icu_collator")]
+use icu::locale::Locale;
+
+#[cfg(feature = "icu_collator")]
+use crate::error::MDictError;
+use crate::error::Result;
+use crate::format::HeaderInfo;
+use crate::types::Encoding;
+
+/// Characters MDict drops from keys when `StripKey="Yes"`.
+const STRIPPED_CHARS: &str = " _=,.;:!?@%&#~`()[]<>{}/\\$+-*^'\"\t|";
+
+/// How keys are compared. The default is MDict's own default: case-insensitive,
+/// punctuation kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct KeyCollation {
+    pub case_sensitive: bool,
+    pub strip_key: bool,
+}
+
+impl KeyCollation {
+    /// Plain `str` ordering.
+    pub const BINARY: Self = Self {
+        case_sensitive: true,
+        strip_key: false,
+    };
+
+    /// The collation named by the `KeyCaseSensitive` and `StripKey` header
+    /// attributes.
+    pub fn from_header(header: &HeaderInfo) -> Self {
+        Self {
+            case_sensitive: header.flag("KeyCaseSensitive"),
+            strip_key: header.flag("StripKey"),
+        }
+    }
+
+    /// `key` as it is sorted.
+    pub fn fold<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        let strip = self.strip_key && key.contains(|c| STRIPPED_CHARS.contains(c));
+        let lower = !self.case_sensitive && key.chars().any(|c| c.to_lowercase().ne([c]));
+        match (strip, lower) {
+            (false, false) => Cow::Borrowed(key),
+            (true, false) => Cow::Owned(strip_key(key)),
+            (false, true) => Cow::Owned(key.to_lowercase()),
+            (true, true) => Cow::Owned(strip_key(key).to_lowercase()),
+        }
+    }
+
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        self.fold(a).cmp(&self.fold(b))
+    }
+}
+
+fn strip_key(key: &str) -> String {
+    key.chars()
+        .filter(|&c| !STRIPPED_CHARS.contains(c))
+        .collect()
+}
+
+/// How key lookups order keys. Set with
+/// [`crate::random_access_key_blocks::KeyBlockIndex::set_search_options`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    pub collation: KeyCollation,
+    /// BCP 47 locale (e.g. `"de"`, `"tr"`) whose collation rules the keys
+    /// are sorted by. Replaces `collation` for lookups, which then compare
+    /// base letters only: case and accents are ignored, so `"strass"` matches
+    /// `"Straße"`.
+    #[cfg(feature = "icu_collator")]
+    pub locale: Option<String>,
+}
+
+impl From<KeyCollation> for SearchOptions {
+    fn from(collation: KeyCollation) -> Self {
+        Self {
+            collation,
+            #[cfg(feature = "icu_collator")]
+            locale: None,
+        }
+    }
+}
+
+/// A locale's collation rules, from ICU's compiled data.
+#[cfg(feature = "icu_collator")]
+pub struct LocaleCollator {
+    locale: String,
+    collator: CollatorBorrowed<'static>,
+}
+
+#[cfg(feature = "icu_collator")]
+impl LocaleCollator {
+    /// Compares at primary strength, which is consistent with keys sorted
+    /// at any strength of the same locale.
+    pub fn new(locale: &str) -> Result<Self> {
+        let parsed = Locale::try_from_str(locale).map_err(|e| {
+            MDictError::InvalidArgument(format!("invalid locale '{}': {}", locale, e))
+        })?;
+        let mut options = CollatorOptions::default();
+        options.strength = Some(Strength::Primary);
+        let collator = Collator::try_new((&parsed).into(), options).map_err(|e| {
+            MDictError::InvalidArgument(format!("no collation data for '{}': {}", locale, e))
+        })?;
+        Ok(Self {
+            locale: locale.to_string(),
+            collator,
+        })
+    }
+
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        self.collator.compare(a, b)
+    }
+
+    /// Whether some leading part of `key` collates equal to `prefix`, so
+    /// `"Straße"` starts with `"strass"` in German.
+    pub fn starts_with(&self, key: &str, prefix: &str) -> bool {
+        if prefix.is_empty() {
+            return true;
+        }
+        let ends = key.char_indices().map(|(i, _)| i).skip(1);
+        for end in ends.chain([key.len()]) {
+            match self.compare(&key[..end], prefix) {
+                Ordering::Equal => return true,
+                Ordering::Greater => return false,
+                Ordering::Less => {}
+            }
+        }
+        false
+    }
+}
+
+#[cfg(feature = "icu_collator")]
+impl std::fmt::Debug for LocaleCollator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocaleCollator")
+            .field("locale", &self.locale)
+            .finish()
+    }
+}
+
+/// The ordering key lookups run with: a [`KeyCollation`], or a locale
+/// collator when [`SearchOptions::locale`] is set.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct KeyOrder {
+    collation: KeyCollation,
+    /// Legacy code page whose encoded bytes the folded keys are compared by.
+    code_page: Option<Encoding>,
+    #[cfg(feature = "icu_collator")]
+    locale: Option<Arc<LocaleCollator>>,
+}
+
+impl KeyOrder {
+    pub(crate) fn new(options: &SearchOptions) -> Result<Self> {
+        Ok(Self {
+            collation: options.collation,
+            code_page: None,
+            #[cfg(feature = "icu_collator")]
+            locale: options
+                .locale
+                .as_deref()
+                .map(LocaleCollator::new)
+                .transpose()?
+                .map(Arc::new),
+        })
+    }
+
+    /// Compare keys as stored in `encoding`: by their encoded bytes for GBK,
+    /// Big5 and GB18030, by their text otherwise.
+    pub(crate) fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.code_page = matches!(encoding, Encoding::Gbk | Encoding::Big5 | Encoding::Gb18030)
+            .then_some(encoding);
+        self
+    }
+
+    pub(crate) fn collation(&self) -> KeyCollation {
+        self.collation
+    }
+
+    pub(crate) fn options(&self) -> SearchOptions {
+        SearchOptions {
+            collation: self.collation,
+            #[cfg(feature = "icu_collator")]
+            locale: self.locale.as_ref().map(|c| c.locale().to_string()),
+        }
+    }
+
+    pub(crate) fn compare(&self, key: &str, target: &str) -> Ordering {
+        #[cfg(feature = "icu_collator")]
+        if let Some(collator) = &self.locale {
+            return collator.compare(key, target);
+        }
+        match self.code_page {
+            Some(encoding) => encoding
+                .encode(&self.collation.fold(key))
+                .cmp(&encoding.encode(&self.collation.fold(target))),
+            None => self.collation.compare(key, target),
+        }
+    }
+
+    /// Whole characters encode to whole byte sequences, so a key starts with
+    /// `prefix` in a code page exactly when its text does.
+    pub(crate) fn starts_with(&self, key: &str, prefix: &str) -> bool {
+        #[cfg(feature = "icu_collator")]
+        if let Some(collator) = &self.locale {
+            return collator.starts_with(key, prefix);
+        }
+        self.collation
+            .fold(key)
+            .starts_with(self.collation.fold(prefix).as_ref())
+    }
+}
+
+impl From<KeyCollation> for KeyOrder {
+    fn from(collation: KeyCollation) -> Self {
+        Self {
+            collation,
+            ..Self::default()
+        }
+    }
+}
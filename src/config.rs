@@ -0,0 +1,204 @@
+//! Crate-wide configuration, installed once by the host application.
+//!
+//! Subsystems read their defaults from [`config()`] instead of hard-coding
+//! them. If [`init_config`] is never called, [`Config::default`] is used.
+
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::block_cache::CacheCapacity;
+use crate::error::MDictError;
+
+const DEFAULT_PACKED_BLOCK_SIZE: u64 = 64 * 1024;
+const DEFAULT_RECORD_COMPRESSION_LEVEL: u8 = 10;
+const DEFAULT_LINK_CACHE_SIZE: u64 = 4096;
+const DEFAULT_KEY_BLOCK_CACHE_SIZE: u64 = 8;
+const DEFAULT_ZSTD_DICTIONARY_SIZE: u64 = 112 * 1024;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct Config {
+    /// Worker threads for parallel build/export passes. 0 keeps rayon's default.
+    /// Ignored without the `threads` feature.
+    pub thread_pool_size: u32,
+    /// Record blocks cached by `Mdict::new`. 0 disables the cache.
+    pub record_block_cache_size: u64,
+    /// Byte budget for the `Mdict::new` record block cache; overrides
+    /// `record_block_cache_size` when non-zero.
+    pub record_block_cache_bytes: u64,
+    /// Key blocks cached by `Mdict::new`. At least one is always kept.
+    pub key_block_cache_size: u64,
+    /// `Mdict::new` preloads every key block of dictionaries whose decoded
+    /// key blocks take at most this many bytes. 0 never preloads.
+    pub key_preload_max_bytes: u64,
+    /// Keep each bundle's and group member's parsed key section in a
+    /// `.mdxidx`/`.mddidx` sidecar next to its file, so reopening skips
+    /// parsing it.
+    pub key_section_cache: bool,
+    /// Open every dictionary lazily, as with `OpenOptions::lazy`: record
+    /// indexes are parsed on first read and nothing is preloaded.
+    pub lazy_open: bool,
+    /// Resolved `@@@LINK=` redirects remembered per dictionary. 0 disables.
+    pub link_cache_size: u64,
+    /// Record blocks cached while building optimized indexes.
+    pub build_record_block_cache_size: u64,
+    /// Approximate bytes an optimized-index build may hold in intermediate
+    /// data, split between decoded record blocks and key runs spilled to
+    /// `temp_dir`. Overrides `build_record_block_cache_size` when non-zero.
+    /// 0 keeps every intermediate structure in memory.
+    pub build_memory_budget: u64,
+    /// Target uncompressed block size for packed record storage.
+    pub packed_block_size: u64,
+    /// Compression level (0..=10) for packed record storage.
+    pub record_compression_level: u8,
+    /// Upper bound on the zstd dictionary trained over sampled records for
+    /// packed record storage. 0 disables dictionary training.
+    pub zstd_dictionary_size: u64,
+    /// Directory for intermediate spill files. `None` uses the OS temp dir.
+    pub temp_dir: Option<String>,
+    pub log_level: LogLevel,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            thread_pool_size: 0,
+            record_block_cache_size: 0,
+            record_block_cache_bytes: 0,
+            key_block_cache_size: DEFAULT_KEY_BLOCK_CACHE_SIZE,
+            key_preload_max_bytes: 0,
+            key_section_cache: false,
+            lazy_open: false,
+            link_cache_size: DEFAULT_LINK_CACHE_SIZE,
+            build_record_block_cache_size: u64::MAX,
+            build_memory_budget: 0,
+            packed_block_size: DEFAULT_PACKED_BLOCK_SIZE,
+            record_compression_level: DEFAULT_RECORD_COMPRESSION_LEVEL,
+            zstd_dictionary_size: DEFAULT_ZSTD_DICTIONARY_SIZE,
+            temp_dir: None,
+            log_level: LogLevel::Warn,
+        }
+    }
+}
+
+impl Config {
+    pub fn temp_dir(&self) -> PathBuf {
+        self.temp_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir)
+    }
+
+    pub fn record_block_cache_size(&self) -> usize {
+        usize::try_from(self.record_block_cache_size).unwrap_or(usize::MAX)
+    }
+
+    pub fn record_block_cache_capacity(&self) -> CacheCapacity {
+        if self.record_block_cache_bytes > 0 {
+            CacheCapacity::Bytes(
+                usize::try_from(self.record_block_cache_bytes).unwrap_or(usize::MAX),
+            )
+        } else {
+            CacheCapacity::Entries(self.record_block_cache_size())
+        }
+    }
+
+    pub fn key_block_cache_capacity(&self) -> CacheCapacity {
+        CacheCapacity::Entries(usize::try_from(self.key_block_cache_size).unwrap_or(usize::MAX))
+    }
+
+    pub fn link_cache_size(&self) -> usize {
+        usize::try_from(self.link_cache_size).unwrap_or(usize::MAX)
+    }
+
+    pub fn build_record_block_cache_size(&self) -> usize {
+        usize::try_from(self.build_record_block_cache_size).unwrap_or(usize::MAX)
+    }
+
+    pub fn build_memory_budget(&self) -> Option<usize> {
+        (self.build_memory_budget > 0)
+            .then(|| usize::try_from(self.build_memory_budget).unwrap_or(usize::MAX))
+    }
+
+    /// Record block cache for optimized-index builds: half the memory budget
+    /// when one is set.
+    pub fn build_record_block_cache_capacity(&self) -> CacheCapacity {
+        match self.build_memory_budget() {
+            Some(budget) => CacheCapacity::Bytes(budget / 2),
+            None => CacheCapacity::Entries(self.build_record_block_cache_size()),
+        }
+    }
+
+    pub fn packed_block_size(&self) -> usize {
+        usize::try_from(self.packed_block_size).unwrap_or(usize::MAX)
+    }
+
+    pub fn zstd_dictionary_size(&self) -> usize {
+        usize::try_from(self.zstd_dictionary_size).unwrap_or(usize::MAX)
+    }
+}
+
+impl From<LogLevel> for log::LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Off => log::LevelFilter::Off,
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Debug,
+            LogLevel::Trace => log::LevelFilter::Trace,
+        }
+    }
+}
+
+/// Return the active configuration, falling back to defaults if the host
+/// never called [`init_config`].
+pub fn config() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// Install the crate-wide configuration. Call once at startup, before using
+/// any other API; subsequent calls fail with `InvalidArgument`.
+#[uniffi::export]
+pub fn init_config(config: Config) -> Result<(), MDictError> {
+    if config.packed_block_size == 0 {
+        return Err(MDictError::InvalidArgument(
+            "packed_block_size must be greater than 0".to_string(),
+        ));
+    }
+
+    // Fail before touching the thread pool, which cannot be rebuilt, and
+    // install the configuration only once everything it asks for is in place.
+    if CONFIG.get().is_some() {
+        return Err(already_initialized());
+    }
+
+    #[cfg(feature = "threads")]
+    if config.thread_pool_size > 0 {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(config.thread_pool_size as usize)
+            .build_global()
+            .map_err(|e| MDictError::InvalidArgument(format!("thread pool: {}", e)))?;
+    }
+
+    let log_level = config.log_level;
+    CONFIG.set(config).map_err(|_| already_initialized())?;
+    log::set_max_level(log_level.into());
+
+    Ok(())
+}
+
+fn already_initialized() -> MDictError {
+    MDictError::InvalidArgument("configuration is already initialized".to_string())
+}
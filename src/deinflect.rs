@@ -0,0 +1,215 @@
+//! Deinflection: turning conjugated forms back into dictionary forms, so that
+//! `食べました` finds `食べる`.
+//!
+//! Rule tables use Yomichan's `deinflect.json` format: an object mapping
+//! each reason (e.g. `"polite past"`) to variants
+//! `{"kanaIn", "kanaOut", "rulesIn", "rulesOut"}`. A variant replaces the
+//! suffix `kanaIn` with `kanaOut`; it applies to the looked-up term itself,
+//! or to a candidate whose `rulesOut` types overlap the variant's `rulesIn`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Seek};
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use crate::error::{MDictError, Result};
+use crate::types::KeyBlock;
+use crate::Mdict;
+
+/// Upper bound on the candidates one term expands to, so tables whose rules
+/// feed each other cannot run away.
+const MAX_CANDIDATES: usize = 512;
+
+const JAPANESE_RULES: &str = include_str!("deinflect/japanese.json");
+const KOREAN_RULES: &str = include_str!("deinflect/korean.json");
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawVariant {
+    kana_in: String,
+    kana_out: String,
+    rules_in: Vec<String>,
+    rules_out: Vec<String>,
+}
+
+struct Variant {
+    reason: Arc<str>,
+    kana_in: String,
+    kana_out: String,
+    rules_in: u64,
+    rules_out: u64,
+}
+
+/// A candidate dictionary form of a term, with the inflections undone to
+/// reach it, outermost first.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct Deinflection {
+    pub term: String,
+    pub reasons: Vec<String>,
+}
+
+/// A dictionary entry found by [`Mdict::lookup_deinflected`].
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct DeinflectedMatch {
+    pub key_block: KeyBlock,
+    pub deinflection: Deinflection,
+}
+
+/// A loaded rule table.
+#[derive(uniffi::Object)]
+pub struct Deinflector {
+    variants: Vec<Variant>,
+}
+
+impl Deinflector {
+    /// Parse a Yomichan `deinflect.json` table.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let table: BTreeMap<String, Vec<RawVariant>> = serde_json::from_str(json)
+            .map_err(|e| MDictError::InvalidFormat(format!("invalid deinflection rules: {}", e)))?;
+
+        let mut rule_bits = HashMap::new();
+        let mut mask = |names: &[String]| -> Result<u64> {
+            let mut mask = 0;
+            for name in names {
+                let next_bit = rule_bits.len();
+                let bit = *rule_bits.entry(name.clone()).or_insert(next_bit);
+                if bit >= u64::BITS as usize {
+                    return Err(MDictError::UnsupportedFeature(format!(
+                        "deinflection rules use more than {} word types",
+                        u64::BITS
+                    )));
+                }
+                mask |= 1 << bit;
+            }
+            Ok(mask)
+        };
+
+        let mut variants = Vec::new();
+        for (reason, raw_variants) in table {
+            let reason = Arc::<str>::from(reason);
+            for raw in raw_variants {
+                variants.push(Variant {
+                    reason: Arc::clone(&reason),
+                    rules_in: mask(&raw.rules_in)?,
+                    rules_out: mask(&raw.rules_out)?,
+                    kana_in: raw.kana_in,
+                    kana_out: raw.kana_out,
+                });
+            }
+        }
+        Ok(Self { variants })
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// Built-in Japanese verb and i-adjective rules.
+    pub fn japanese() -> Self {
+        Self::from_json(JAPANESE_RULES).expect("built-in Japanese rules parse")
+    }
+
+    /// Built-in rules for common Korean endings on regular stems.
+    pub fn korean() -> Self {
+        Self::from_json(KOREAN_RULES).expect("built-in Korean rules parse")
+    }
+}
+
+#[uniffi::export]
+impl Deinflector {
+    /// `term` itself followed by every form the rules deinflect it to,
+    /// breadth first, so fewer steps come first. Duplicates are kept once.
+    pub fn deinflect(&self, term: &str) -> Vec<Deinflection> {
+        let mut candidates = vec![(term.to_string(), 0u64, Vec::<Arc<str>>::new())];
+        let mut next = 0;
+        while next < candidates.len() && candidates.len() < MAX_CANDIDATES {
+            let (term, rules, reasons) = candidates[next].clone();
+            next += 1;
+            for variant in &self.variants {
+                if rules != 0 && rules & variant.rules_in == 0 {
+                    continue;
+                }
+                let Some(stem) = term.strip_suffix(variant.kana_in.as_str()) else {
+                    continue;
+                };
+                if stem.is_empty() && variant.kana_out.is_empty() {
+                    continue;
+                }
+                let mut reasons = reasons.clone();
+                reasons.push(Arc::clone(&variant.reason));
+                candidates.push((
+                    format!("{}{}", stem, variant.kana_out),
+                    variant.rules_out,
+                    reasons,
+                ));
+            }
+        }
+
+        let mut out: Vec<Deinflection> = Vec::new();
+        for (term, _, reasons) in candidates.into_iter().take(MAX_CANDIDATES) {
+            if out.iter().any(|seen| seen.term == term) {
+                continue;
+            }
+            out.push(Deinflection {
+                term,
+                reasons: reasons.iter().map(|reason| reason.to_string()).collect(),
+            });
+        }
+        out
+    }
+}
+
+#[uniffi::export]
+pub fn create_japanese_deinflector() -> Deinflector {
+    Deinflector::japanese()
+}
+
+#[uniffi::export]
+pub fn create_korean_deinflector() -> Deinflector {
+    Deinflector::korean()
+}
+
+/// A deinflector from a Yomichan `deinflect.json` table.
+#[uniffi::export]
+pub fn create_deinflector_from_json(json: String) -> Result<Deinflector> {
+    Deinflector::from_json(&json)
+}
+
+impl<R: Read + Seek> Mdict<R> {
+    /// Entries for `term` and for each of its deinflected forms, in the order
+    /// [`Deinflector::deinflect`] produces them, so an exact match comes
+    /// first. Each entry appears once.
+    pub fn lookup_deinflected(
+        &mut self,
+        term: &str,
+        deinflector: &Deinflector,
+    ) -> Result<Vec<DeinflectedMatch>> {
+        let mut out: Vec<DeinflectedMatch> = Vec::new();
+        for deinflection in deinflector.deinflect(term) {
+            let Some(index) = self
+                .key_block_index
+                .index_for(&mut self.reader, &deinflection.term)?
+            else {
+                continue;
+            };
+            let Some(key_block) = self.key_block_index.get(&mut self.reader, index)? else {
+                continue;
+            };
+            if out
+                .iter()
+                .any(|hit| hit.key_block.key_id == key_block.key_id)
+            {
+                continue;
+            }
+            out.push(DeinflectedMatch {
+                key_block,
+                deinflection,
+            });
+        }
+        Ok(out)
+    }
+}
@@ -0,0 +1,35 @@
+//! A common interface over dictionary formats.
+//!
+//! [`Dictionary`] covers what search and display layers need from any
+//! dictionary: a description, prefix search, exact lookup, records and
+//! resources. MDX files implement it through [`crate::MdictShared`] (a bare
+//! [`crate::Mdict`] needs `&mut self` to read) and [`crate::MdictBundle`],
+//! optimized indexes through [`crate::MdictOptimized`], and StarDict
+//! dictionaries through `StarDict` with the `stardict` feature. Other
+//! backends can implement it to join a [`crate::MdictGroupHandle`].
+
+use crate::error::Result;
+use crate::types::{DictionaryInfo, KeyBlock};
+
+pub trait Dictionary: Send + Sync {
+    fn info(&self) -> DictionaryInfo;
+
+    /// Up to `limit` keys starting with `prefix`, in the dictionary's key
+    /// order.
+    fn prefix_search(&self, prefix: &str, limit: usize) -> Result<Vec<KeyBlock>>;
+
+    /// Every entry for `key`, one per sense. Empty when `key` is missing.
+    fn lookup(&self, key: &str) -> Result<Vec<KeyBlock>>;
+
+    /// The record of an entry returned by this dictionary, as stored.
+    fn record(&self, key_block: &KeyBlock) -> Result<Vec<u8>>;
+
+    /// [`Self::record`] as text, usually HTML. Redirects are not followed.
+    fn record_text(&self, key_block: &KeyBlock) -> Result<String>;
+
+    /// A resource such as an image or sound referenced by records, by its
+    /// path. Formats without resources have none.
+    fn resource(&self, _path: &str) -> Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+}
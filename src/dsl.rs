@@ -0,0 +1,446 @@
+//! Reading ABBYY Lingvo DSL dictionaries, enabled with the `dsl` feature.
+//!
+//! A DSL file is text, usually UTF-16LE: `#NAME`-style header lines, then
+//! cards made of one or more headword lines at the start of a line followed
+//! by indented body lines in Lingvo's `[b]...[/b]` markup. `.dsl.dz` files
+//! are gzip or dictzip compressed. The whole file is parsed into memory when
+//! opened; records are converted to HTML on request.
+//!
+//! In headwords, `{...}` marks text that is shown but not indexed and
+//! `(...)` an optional part, so `colo(u)r` is found as `colour` and `color`.
+//! Entries are addressed with [`KeyBlock`]s whose `key_id` is the card's
+//! position in the file; every headword variant of a card shares it.
+//!
+//! To search a DSL dictionary as quickly as an MDX, build an optimized index
+//! of it with
+//! [`crate::mdx_conversion::fst_indexing::create_fst_index_from_dictionary`].
+
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use crate::dictionary::Dictionary;
+use crate::error::{MDictError, Result};
+#[cfg(feature = "fs")]
+use crate::gzip::{GzipMember, GZIP_MAGIC};
+use crate::render::push_escaped;
+use crate::types::{DictionaryFormat, DictionaryInfo, KeyBlock};
+
+/// Optional parts expanded per headword; later ones are kept as written.
+const MAX_OPTIONAL_PARTS: usize = 6;
+const IMAGE_EXTENSIONS: [&str; 7] = ["bmp", "gif", "jpeg", "jpg", "png", "svg", "webp"];
+
+struct DslCard {
+    /// The first headword variant, which `~` in the body stands for.
+    headword: String,
+    body: String,
+}
+
+/// An opened DSL dictionary.
+pub struct DslDictionary {
+    name: Option<String>,
+    index_language: Option<String>,
+    contents_language: Option<String>,
+    cards: Vec<DslCard>,
+    /// `(key, card position)` in key order.
+    keys: Vec<(String, u32)>,
+}
+
+impl DslDictionary {
+    /// Open the `.dsl` or `.dsl.dz` file at `path`.
+    #[cfg(feature = "fs")]
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        if bytes.starts_with(&GZIP_MAGIC) {
+            Self::parse(&GzipMember::parse(&bytes)?.inflate()?)
+        } else {
+            Self::parse(&bytes)
+        }
+    }
+
+    /// Parse uncompressed DSL text. A byte order mark decides the encoding;
+    /// without one, text that looks like UTF-16LE is read as such and
+    /// anything else as UTF-8.
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let fallback = if bytes.len() >= 2 && bytes[0] != 0 && bytes[1] == 0 {
+            encoding_rs::UTF_16LE
+        } else {
+            encoding_rs::UTF_8
+        };
+        let (text, _, _) = fallback.decode(bytes);
+        let text = strip_comments(&text);
+
+        let mut dictionary = Self {
+            name: None,
+            index_language: None,
+            contents_language: None,
+            cards: Vec::new(),
+            keys: Vec::new(),
+        };
+        let mut headwords: Vec<&str> = Vec::new();
+        let mut body: Vec<&str> = Vec::new();
+        for line in text.lines() {
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty() {
+                continue;
+            }
+            if line.starts_with('#') && headwords.is_empty() && dictionary.cards.is_empty() {
+                dictionary.parse_header(line);
+                continue;
+            }
+            if line.starts_with([' ', '\t']) {
+                if headwords.is_empty() {
+                    return Err(MDictError::InvalidFormat(format!(
+                        "DSL body line before any headword: '{}'",
+                        line.trim()
+                    )));
+                }
+                body.push(line.trim_start());
+                continue;
+            }
+            if !body.is_empty() {
+                dictionary.push_card(&headwords, &body);
+                headwords.clear();
+                body.clear();
+            }
+            headwords.push(line);
+        }
+        if !headwords.is_empty() {
+            dictionary.push_card(&headwords, &body);
+        }
+
+        dictionary.keys.sort();
+        dictionary.keys.dedup();
+        Ok(dictionary)
+    }
+
+    fn parse_header(&mut self, line: &str) {
+        let Some((name, value)) = line[1..].split_once(char::is_whitespace) else {
+            return;
+        };
+        let value = Some(value.trim().trim_matches('"').to_string());
+        match name {
+            "NAME" => self.name = value,
+            "INDEX_LANGUAGE" => self.index_language = value,
+            "CONTENTS_LANGUAGE" => self.contents_language = value,
+            _ => {}
+        }
+    }
+
+    fn push_card(&mut self, headwords: &[&str], body: &[&str]) {
+        let position = self.cards.len() as u32;
+        let mut first = None;
+        for headword in headwords {
+            for key in headword_keys(headword) {
+                first.get_or_insert_with(|| key.clone());
+                self.keys.push((key, position));
+            }
+        }
+        self.cards.push(DslCard {
+            headword: first.unwrap_or_default(),
+            body: body.join("\n"),
+        });
+    }
+
+    /// `#NAME` from the header.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// `#INDEX_LANGUAGE`, the language of the headwords, e.g. `"English"`.
+    pub fn index_language(&self) -> Option<&str> {
+        self.index_language.as_deref()
+    }
+
+    /// `#CONTENTS_LANGUAGE`, the language of the definitions.
+    pub fn contents_language(&self) -> Option<&str> {
+        self.contents_language.as_deref()
+    }
+
+    /// Number of cards.
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+
+    fn card(&self, key_block: &KeyBlock) -> Result<&DslCard> {
+        usize::try_from(key_block.key_id)
+            .ok()
+            .and_then(|position| self.cards.get(position))
+            .ok_or_else(|| {
+                MDictError::KeyNotFound(format!(
+                    "'{}' is not in this dictionary",
+                    key_block.key_text
+                ))
+            })
+    }
+
+    fn key_block(&self, (key, position): &(String, u32)) -> KeyBlock {
+        KeyBlock {
+            key_id: *position as u64,
+            key_text: key.clone(),
+        }
+    }
+
+    /// The body of a card in DSL markup.
+    pub fn record_dsl(&self, key_block: &KeyBlock) -> Result<&str> {
+        Ok(&self.card(key_block)?.body)
+    }
+
+    /// The body of a card as HTML; see [`dsl_to_html`].
+    pub fn record_html(&self, key_block: &KeyBlock) -> Result<String> {
+        let card = self.card(key_block)?;
+        Ok(dsl_to_html(&card.body, &card.headword))
+    }
+}
+
+impl Dictionary for DslDictionary {
+    fn info(&self) -> DictionaryInfo {
+        DictionaryInfo {
+            format: DictionaryFormat::Dsl,
+            title: self.name.clone(),
+            description: None,
+            entry_count: self.keys.len() as u64,
+        }
+    }
+
+    fn prefix_search(&self, prefix: &str, limit: usize) -> Result<Vec<KeyBlock>> {
+        let start = self.keys.partition_point(|(key, _)| key.as_str() < prefix);
+        Ok(self.keys[start..]
+            .iter()
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .take(limit)
+            .map(|entry| self.key_block(entry))
+            .collect())
+    }
+
+    fn lookup(&self, key: &str) -> Result<Vec<KeyBlock>> {
+        let start = self.keys.partition_point(|(other, _)| other.as_str() < key);
+        Ok(self.keys[start..]
+            .iter()
+            .take_while(|(other, _)| other == key)
+            .map(|entry| self.key_block(entry))
+            .collect())
+    }
+
+    fn record(&self, key_block: &KeyBlock) -> Result<Vec<u8>> {
+        Ok(self.record_dsl(key_block)?.as_bytes().to_vec())
+    }
+
+    fn record_text(&self, key_block: &KeyBlock) -> Result<String> {
+        self.record_html(key_block)
+    }
+}
+
+/// `text` without `{{...}}` comments, which may span lines.
+fn strip_comments(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        rest = match rest[start..].find("}}") {
+            Some(end) => &rest[start + end + 2..],
+            None => "",
+        };
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The index keys of a headword line: `{...}` parts removed, `(...)` parts
+/// expanded into forms with and without them, escapes resolved and spaces
+/// collapsed. The form with every optional part comes first.
+fn headword_keys(headword: &str) -> Vec<String> {
+    // (text, optional) segments.
+    let mut segments: Vec<(String, bool)> = vec![(String::new(), false)];
+    let mut hidden = 0usize;
+    let mut chars = headword.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    if hidden == 0 {
+                        segments.last_mut().unwrap().0.push(escaped);
+                    }
+                }
+            }
+            '{' => hidden += 1,
+            '}' => hidden = hidden.saturating_sub(1),
+            _ if hidden > 0 => {}
+            '(' => segments.push((String::new(), true)),
+            ')' => segments.push((String::new(), false)),
+            c => segments.last_mut().unwrap().0.push(c),
+        }
+    }
+
+    let optional = segments
+        .iter()
+        .enumerate()
+        .filter(|(_, (text, optional))| *optional && !text.is_empty())
+        .map(|(index, _)| index)
+        .take(MAX_OPTIONAL_PARTS)
+        .collect::<Vec<_>>();
+    let mut keys = Vec::with_capacity(1 << optional.len());
+    for mask in 0..1u32 << optional.len() {
+        let key = segments
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| match optional.iter().position(|o| o == index) {
+                Some(bit) => mask & (1 << bit) == 0,
+                None => true,
+            })
+            .map(|(_, (text, _))| text.as_str())
+            .collect::<String>();
+        let key = key.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !key.is_empty() && !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    keys
+}
+
+/// Convert a DSL card body to HTML. Each line becomes a `<div>`, indented
+/// by its `[mN]` tag. Formatting tags map to their HTML counterparts,
+/// `[ref]`/`<<...>>` to `entry://` links, `[s]` to images or `sound://`
+/// links and `[url]` to links; other tags are dropped and their text kept.
+/// `~` stands for `headword`.
+pub fn dsl_to_html(body: &str, headword: &str) -> String {
+    let mut out = String::with_capacity(body.len() * 2);
+    for line in body.lines() {
+        let mut line = line.trim_start();
+        let mut margin = None;
+        if let Some(rest) = line.strip_prefix("[m") {
+            if let Some((level, rest)) = rest.split_once(']') {
+                if let Ok(level) = level.parse::<u8>() {
+                    margin = Some(level);
+                    line = rest;
+                }
+            }
+        }
+        match margin {
+            Some(level) => out.push_str(&format!("<div style=\"margin-left:{}em\">", level)),
+            None => out.push_str("<div>"),
+        }
+        push_line_html(&mut out, line, headword);
+        out.push_str("</div>\n");
+    }
+    out
+}
+
+fn push_line_html(out: &mut String, line: &str, headword: &str) {
+    let mut text = String::new();
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '\\' => {
+                let mut chars = rest[1..].chars();
+                if let Some(escaped) = chars.next() {
+                    text.push(escaped);
+                }
+                rest = chars.as_str();
+            }
+            '~' => {
+                text.push_str(headword);
+                rest = &rest[1..];
+            }
+            '<' if rest.starts_with("<<") => {
+                let Some(end) = rest.find(">>") else {
+                    text.push_str(rest);
+                    break;
+                };
+                flush_text(out, &mut text);
+                push_link(out, "entry://", &rest[2..end]);
+                rest = &rest[end + 2..];
+            }
+            '[' => {
+                let Some(end) = rest.find(']') else {
+                    text.push_str(rest);
+                    break;
+                };
+                flush_text(out, &mut text);
+                rest = push_tag(out, &rest[1..end], &rest[end + 1..]);
+            }
+            c => {
+                text.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    flush_text(out, &mut text);
+}
+
+fn flush_text(out: &mut String, text: &mut String) {
+    push_escaped(out, text);
+    text.clear();
+}
+
+/// Write the HTML for tag `tag` and return the input after it. Tags whose
+/// contents become an attribute consume everything up to their end tag.
+fn push_tag<'a>(out: &mut String, tag: &str, rest: &'a str) -> &'a str {
+    let (name, argument) = tag.split_once(' ').unwrap_or((tag, ""));
+    let until_end = |end_tag: &str| match rest.find(end_tag) {
+        Some(end) => (&rest[..end], &rest[end + end_tag.len()..]),
+        None => (rest, ""),
+    };
+    match name {
+        "b" | "i" | "u" | "sup" | "sub" | "/b" | "/i" | "/u" | "/sup" | "/sub" => {
+            out.push('<');
+            out.push_str(name);
+            out.push('>');
+        }
+        "c" => {
+            let color = if argument.is_empty() { "green" } else { argument };
+            out.push_str("<span style=\"color:");
+            push_escaped(out, color);
+            out.push_str("\">");
+        }
+        "p" => out.push_str("<abbr>"),
+        "/p" => out.push_str("</abbr>"),
+        "ex" | "com" | "t" => {
+            out.push_str("<span class=\"");
+            out.push_str(name);
+            out.push_str("\">");
+        }
+        "/c" | "/ex" | "/com" | "/t" => out.push_str("</span>"),
+        "ref" => {
+            let (target, rest) = until_end("[/ref]");
+            push_link(out, "entry://", target);
+            return rest;
+        }
+        "url" => {
+            let (url, rest) = until_end("[/url]");
+            push_link(out, "", url);
+            return rest;
+        }
+        "s" => {
+            let (file, rest) = until_end("[/s]");
+            let extension = file
+                .rsplit_once('.')
+                .map(|(_, extension)| extension.to_ascii_lowercase());
+            let is_image =
+                extension.is_some_and(|extension| IMAGE_EXTENSIONS.contains(&extension.as_str()));
+            if is_image {
+                out.push_str("<img src=\"");
+                push_escaped(out, file);
+                out.push_str("\">");
+            } else {
+                push_link(out, "sound://", file);
+            }
+            return rest;
+        }
+        _ => {}
+    }
+    rest
+}
+
+fn push_link(out: &mut String, scheme: &str, target: &str) {
+    let target = target.replace('\\', "");
+    out.push_str("<a href=\"");
+    out.push_str(scheme);
+    push_escaped(out, &target);
+    out.push_str("\">");
+    push_escaped(out, &target);
+    out.push_str("</a>");
+}
@@ -0,0 +1,240 @@
+use std::collections::VecDeque;
+use std::io::{Read, Seek};
+use std::sync::mpsc::{self, Receiver};
+
+use crate::error::{MDictError, Result};
+use crate::mdict::{decode_record_block, record_batch_size};
+use crate::metrics::{BlockKind, Span};
+use crate::types::KeyBlock;
+use crate::Mdict;
+
+/// Sequential `(key, record)` iterator returned by [`Mdict::iter_entries`].
+///
+/// Holds the current record block itself instead of going through the
+/// `Mdict` block cache, so every block is decompressed exactly once regardless
+/// of the cache setting. Iteration stops after the first error.
+///
+/// With [`Self::prefetch`], the next record block is decoded on the rayon
+/// pool while the entries of the current one are consumed. With
+/// [`Self::parallel`], batches of blocks are decoded at once across the pool.
+pub struct EntryIter<'a, R: Read + Seek> {
+    mdict: &'a mut Mdict<R>,
+    index: usize,
+    total: usize,
+    next_key: Option<KeyBlock>,
+    block_idx: Option<usize>,
+    block_start: u64,
+    block: Vec<u8>,
+    done: bool,
+    prefetch: bool,
+    prefetched: Option<Prefetched>,
+    parallel: bool,
+    /// Blocks decoded in the current parallel batch, in order.
+    decoded_ahead: VecDeque<(usize, Result<Vec<u8>>)>,
+}
+
+/// A record block being decoded in the background.
+struct Prefetched {
+    rec_block: usize,
+    decoded: Receiver<Result<Vec<u8>>>,
+}
+
+/// Whether this is one of the rayon pool's own threads.
+#[cfg(feature = "threads")]
+fn on_pool_thread() -> bool {
+    rayon::current_thread_index().is_some()
+}
+
+#[cfg(not(feature = "threads"))]
+fn on_pool_thread() -> bool {
+    false
+}
+
+impl<'a, R: Read + Seek> EntryIter<'a, R> {
+    pub(crate) fn new(mdict: &'a mut Mdict<R>) -> Self {
+        let total = mdict.key_block_index.key_section.num_entries as usize;
+        Self {
+            mdict,
+            index: 0,
+            total,
+            next_key: None,
+            block_idx: None,
+            block_start: 0,
+            block: Vec::new(),
+            done: false,
+            prefetch: false,
+            prefetched: None,
+            parallel: false,
+            decoded_ahead: VecDeque::new(),
+        }
+    }
+
+    /// Decode each record block ahead, in the background, while the block
+    /// before it is consumed. Worth it when every entry is read, as in an
+    /// export; the blocks' raw bytes are still read on this thread. Without
+    /// the `threads` feature the next block is decoded up front instead.
+    pub fn prefetch(mut self, enabled: bool) -> Self {
+        self.prefetch = enabled;
+        self
+    }
+
+    /// Decode record blocks in parallel batches of a few per rayon thread,
+    /// handing them out in order. Faster than [`Self::prefetch`] on several
+    /// cores, at the cost of holding a batch of decoded blocks; takes
+    /// precedence over it.
+    pub fn parallel(mut self, enabled: bool) -> Self {
+        self.parallel = enabled;
+        self
+    }
+
+    fn key_at(&mut self, index: usize) -> Result<Option<KeyBlock>> {
+        if index >= self.total {
+            return Ok(None);
+        }
+        let mdict = &mut *self.mdict;
+        mdict.key_block_index.get(&mut mdict.reader, index)
+    }
+
+    fn ensure_block_for(&mut self, key_id: u64) -> Result<()> {
+        let block_end = self.block_start + self.block.len() as u64;
+        if self.block_idx.is_some() && key_id >= self.block_start && key_id < block_end {
+            return Ok(());
+        }
+
+        let records = self.mdict.record_section()?;
+        let rec_block = records.bin_search_record_index(key_id)? as usize;
+        let num_blocks = records.record_index_prefix_sum.len() - 1;
+        if self.parallel {
+            self.block = self.take_decoded_ahead(rec_block, num_blocks)?;
+            self.block_start = records.record_index_prefix_sum[rec_block].uncompressed_size;
+            self.block_idx = Some(rec_block);
+            return Ok(());
+        }
+
+        self.block = match self.prefetched.take() {
+            Some(prefetched) if prefetched.rec_block == rec_block => {
+                prefetched.decoded.recv().map_err(|_| {
+                    MDictError::InvalidFormat(format!(
+                        "record block {}: background decode stopped",
+                        rec_block
+                    ))
+                })??
+            }
+            _ => self.mdict.read_record_block(rec_block)?,
+        };
+        self.block_start = records.record_index_prefix_sum[rec_block].uncompressed_size;
+        self.block_idx = Some(rec_block);
+
+        // Waiting on the pool from one of its own threads could leave the
+        // decode queued behind this very thread.
+        let next_block = rec_block + 1;
+        if self.prefetch && !on_pool_thread() && next_block < num_blocks {
+            self.start_prefetch(next_block);
+        }
+        Ok(())
+    }
+
+    /// Block `rec_block` from the current batch, decoding the batch starting
+    /// at it first if it is not there.
+    fn take_decoded_ahead(&mut self, rec_block: usize, num_blocks: usize) -> Result<Vec<u8>> {
+        while self
+            .decoded_ahead
+            .front()
+            .is_some_and(|&(queued, _)| queued < rec_block)
+        {
+            self.decoded_ahead.pop_front();
+        }
+        if self.decoded_ahead.front().map(|&(queued, _)| queued) != Some(rec_block) {
+            let end = (rec_block + record_batch_size()).min(num_blocks);
+            let blocks = (rec_block..end).collect::<Vec<_>>();
+            let decoded = self.mdict.read_record_blocks(&blocks);
+            self.decoded_ahead = blocks.into_iter().zip(decoded).collect();
+        }
+        match self.decoded_ahead.pop_front() {
+            Some((_, block)) => block,
+            None => Err(MDictError::InvalidFormat(format!(
+                "record block {} is out of range",
+                rec_block
+            ))),
+        }
+    }
+
+    /// Failing to read the block is left for when it is needed, so errors
+    /// surface at the entries they belong to.
+    fn start_prefetch(&mut self, rec_block: usize) {
+        let Ok((comp_buf, decomp_size)) = self.mdict.read_compressed_record_block(rec_block)
+        else {
+            return;
+        };
+        let Ok(offset) = self.mdict.record_block_offset(rec_block) else {
+            return;
+        };
+        let (sender, decoded) = mpsc::sync_channel(1);
+        let decode = move || {
+            let span = Span::start();
+            let result = decode_record_block(rec_block, offset, &comp_buf, decomp_size);
+            if let Ok(block) = &result {
+                span.block_decoded(BlockKind::Record, rec_block, block.len());
+            }
+            // The iterator may have been dropped; nobody is waiting then.
+            let _ = sender.send(result);
+        };
+        #[cfg(feature = "threads")]
+        rayon::spawn(decode);
+        #[cfg(not(feature = "threads"))]
+        decode();
+        self.prefetched = Some(Prefetched { rec_block, decoded });
+    }
+
+    fn next_entry(&mut self) -> Result<Option<(KeyBlock, Vec<u8>)>> {
+        let current = match self.next_key.take() {
+            Some(key) => key,
+            None => match self.key_at(self.index)? {
+                Some(key) => key,
+                None => return Ok(None),
+            },
+        };
+        self.next_key = self.key_at(self.index + 1)?;
+        self.index += 1;
+
+        self.ensure_block_for(current.key_id)?;
+        let offset = (current.key_id - self.block_start) as usize;
+        let end = match &self.next_key {
+            Some(next) => {
+                (next.key_id.saturating_sub(self.block_start) as usize).min(self.block.len())
+            }
+            None => self.block.len(),
+        };
+        let record = self
+            .mdict
+            .strip_record_terminator(&self.block[offset..end.max(offset)]);
+
+        Ok(Some((current, record)))
+    }
+}
+
+impl<R: Read + Seek> Iterator for EntryIter<'_, R> {
+    type Item = Result<(KeyBlock, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_entry() {
+            Ok(Some(entry)) => Some(Ok(entry)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.total.saturating_sub(self.index);
+        (0, Some(remaining))
+    }
+}
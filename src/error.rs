@@ -13,6 +13,14 @@ pub enum MDictError {
     KeyNotFound(String),
     #[error("Unsupported Feature: {0}")]
     UnsupportedFeature(String),
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
+    /// Data at a known file offset failed to decode or verify.
+    #[error("Corrupted Data at offset {offset}: {message}")]
+    Corrupted { offset: u64, message: String },
+    /// The file behind an open dictionary changed on disk; reload it.
+    #[error("File Changed: {0}")]
+    FileChanged(String),
 }
 
 impl From<io::Error> for MDictError {
@@ -53,4 +61,36 @@ impl From<bytemuck::PodCastError> for MDictError {
 
 
 
+impl MDictError {
+    /// Prefix the message with the section it came from, e.g. `record block 3`.
+    pub fn in_section(self, section: impl std::fmt::Display) -> Self {
+        match self {
+            MDictError::Io(m) => MDictError::Io(format!("{}: {}", section, m)),
+            MDictError::InvalidFormat(m) => MDictError::InvalidFormat(format!("{}: {}", section, m)),
+            MDictError::InvalidArgument(m) => {
+                MDictError::InvalidArgument(format!("{}: {}", section, m))
+            }
+            MDictError::KeyNotFound(m) => MDictError::KeyNotFound(format!("{}: {}", section, m)),
+            MDictError::UnsupportedFeature(m) => {
+                MDictError::UnsupportedFeature(format!("{}: {}", section, m))
+            }
+            MDictError::Cancelled(m) => MDictError::Cancelled(format!("{}: {}", section, m)),
+            MDictError::Corrupted { offset, message } => MDictError::Corrupted {
+                offset,
+                message: format!("{}: {}", section, message),
+            },
+            MDictError::FileChanged(m) => MDictError::FileChanged(format!("{}: {}", section, m)),
+        }
+    }
+
+    /// Report a format error as corruption of the data at file `offset`.
+    /// Other errors are returned unchanged.
+    pub fn at_offset(self, offset: u64) -> Self {
+        match self {
+            MDictError::InvalidFormat(message) => MDictError::Corrupted { offset, message },
+            other => other,
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, MDictError>;
@@ -0,0 +1,63 @@
+//! Noticing that a dictionary file was replaced after it was opened.
+//!
+//! A mapped dictionary keeps reading whatever is at its pages, so a file
+//! rewritten in place (an update downloaded over the old one) is read as
+//! garbage. A [`FileStamp`] taken at open is compared against the file to
+//! tell that it changed.
+
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+
+/// The size, modification time and stored header checksum of a dictionary
+/// file. Any of them differing means the file is not the one stamped; the
+/// checksum catches replacements that keep the size and time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    pub size: u64,
+    /// Milliseconds since the Unix epoch.
+    pub modified_ms: u64,
+    /// The adler32 stored after the header.
+    pub header_checksum: u32,
+}
+
+impl FileStamp {
+    pub fn of(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let (size, modified_ms) = size_and_time(path)?;
+
+        let mut file = File::open(path)?;
+        let mut word = [0u8; 4];
+        file.read_exact(&mut word)?;
+        let header_size = u32::from_be_bytes(word);
+        file.seek(SeekFrom::Current(i64::from(header_size)))?;
+        file.read_exact(&mut word)?;
+
+        Ok(Self {
+            size,
+            modified_ms,
+            header_checksum: u32::from_le_bytes(word),
+        })
+    }
+
+    /// Whether the file at `path` still has this size and time. Cheaper
+    /// than a full [`Self::of`], for checking before every read; the header
+    /// checksum is not compared.
+    pub fn metadata_matches(&self, path: impl AsRef<Path>) -> Result<bool> {
+        Ok(size_and_time(path.as_ref())? == (self.size, self.modified_ms))
+    }
+}
+
+fn size_and_time(path: &Path) -> Result<(u64, u64)> {
+    let metadata = fs::metadata(path)?;
+    let modified_ms = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as u64);
+    Ok((metadata.len(), modified_ms))
+}
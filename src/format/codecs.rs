@@ -0,0 +1,138 @@
+//! The block codecs backed by C libraries, LZO (`lzo` feature) and zstd
+//! (`zstd` feature), and the adler32 checksum every block carries. Without a
+//! codec's feature its blocks are still recognised, but encoding and decoding
+//! them fail with `UnsupportedFeature`, so the crate builds for targets
+//! without a C toolchain such as `wasm32-unknown-unknown`.
+
+use crate::error::{MDictError, Result};
+
+const ADLER_MODULUS: u32 = 65521;
+/// Bytes summed between reductions: the most for which the second sum cannot
+/// overflow a `u32`.
+const ADLER_CHUNK: usize = 5552;
+
+/// adler32 (RFC 1950) of `data`, as stored after MDict headers and blocks.
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(ADLER_CHUNK) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= ADLER_MODULUS;
+        b %= ADLER_MODULUS;
+    }
+    (b << 16) | a
+}
+
+/// An lzo1x compressor and its work memory.
+#[cfg(feature = "lzo")]
+pub(crate) struct Lzo(minilzo_rs::LZO);
+
+/// Cannot be created without the `lzo` feature; [`Lzo::init`] fails instead.
+#[cfg(not(feature = "lzo"))]
+pub(crate) enum Lzo {}
+
+#[cfg(feature = "lzo")]
+impl Lzo {
+    pub(crate) fn init() -> Result<Self> {
+        minilzo_rs::LZO::init()
+            .map(Self)
+            .map_err(|e| MDictError::InvalidFormat(format!("LZO init: {}", e)))
+    }
+
+    /// A bare lzo1x stream of `data`.
+    pub(crate) fn compress(&mut self, data: &[u8]) -> Result<Vec<u8>> {
+        self.0
+            .compress(data)
+            .map_err(|e| MDictError::InvalidFormat(format!("LZO compress: {}", e)))
+    }
+
+    /// Decode a bare lzo1x stream into at most `max_size` bytes.
+    pub(crate) fn decompress(&self, payload: &[u8], max_size: usize) -> Result<Vec<u8>> {
+        self.0
+            .decompress_safe(payload, max_size)
+            .map_err(|e| MDictError::InvalidFormat(format!("LZO decompress: {}", e)))
+    }
+}
+
+#[cfg(not(feature = "lzo"))]
+impl Lzo {
+    pub(crate) fn init() -> Result<Self> {
+        Err(unsupported("LZO", "lzo"))
+    }
+
+    pub(crate) fn compress(&mut self, _data: &[u8]) -> Result<Vec<u8>> {
+        match *self {}
+    }
+
+    pub(crate) fn decompress(&self, _payload: &[u8], _max_size: usize) -> Result<Vec<u8>> {
+        match *self {}
+    }
+}
+
+/// A zstd frame of `data`, compressed against `dictionary` when one is given.
+#[cfg(feature = "zstd")]
+pub(crate) fn zstd_compress(data: &[u8], level: i32, dictionary: Option<&[u8]>) -> Result<Vec<u8>> {
+    match dictionary {
+        Some(dictionary) => zstd::bulk::Compressor::with_dictionary(level, dictionary)
+            .and_then(|mut compressor| compressor.compress(data)),
+        None => zstd::bulk::compress(data, level),
+    }
+    .map_err(|e| MDictError::InvalidFormat(format!("zstd compress: {}", e)))
+}
+
+/// Decode a zstd frame into at most `max_size` bytes.
+#[cfg(feature = "zstd")]
+pub(crate) fn zstd_decompress(
+    frame: &[u8],
+    max_size: usize,
+    dictionary: Option<&[u8]>,
+) -> Result<Vec<u8>> {
+    match dictionary {
+        Some(dictionary) => zstd::bulk::Decompressor::with_dictionary(dictionary)
+            .and_then(|mut decompressor| decompressor.decompress(frame, max_size)),
+        None => zstd::bulk::decompress(frame, max_size),
+    }
+    .map_err(|e| MDictError::InvalidFormat(format!("zstd decode: {}", e)))
+}
+
+#[cfg(feature = "zstd")]
+pub(crate) fn zstd_train_dictionary<S: AsRef<[u8]>>(
+    samples: &[S],
+    max_size: usize,
+) -> Result<Vec<u8>> {
+    zstd::dict::from_samples(samples, max_size)
+        .map_err(|e| MDictError::InvalidFormat(format!("zstd dictionary training: {}", e)))
+}
+
+#[cfg(not(feature = "zstd"))]
+pub(crate) fn zstd_compress(
+    _data: &[u8],
+    _level: i32,
+    _dictionary: Option<&[u8]>,
+) -> Result<Vec<u8>> {
+    Err(unsupported("zstd", "zstd"))
+}
+
+#[cfg(not(feature = "zstd"))]
+pub(crate) fn zstd_decompress(
+    _frame: &[u8],
+    _max_size: usize,
+    _dictionary: Option<&[u8]>,
+) -> Result<Vec<u8>> {
+    Err(unsupported("zstd", "zstd"))
+}
+
+#[cfg(not(feature = "zstd"))]
+pub(crate) fn zstd_train_dictionary<S: AsRef<[u8]>>(
+    _samples: &[S],
+    _max_size: usize,
+) -> Result<Vec<u8>> {
+    Err(unsupported("zstd", "zstd"))
+}
+
+#[cfg(not(all(feature = "lzo", feature = "zstd")))]
+fn unsupported(codec: &str, feature: &str) -> MDictError {
+    MDictError::UnsupportedFeature(format!("{} blocks need the `{}` feature", codec, feature))
+}
@@ -1,10 +1,12 @@
 use crate::error::{MDictError, Result};
+use crate::format::codecs::{adler32, zstd_decompress, Lzo};
+use crate::format::encryption::{block_key, fast_decrypt};
 use binrw::{BinRead, BinReaderExt};
+use std::collections::HashMap;
 use std::io;
+use std::sync::{Arc, OnceLock, RwLock};
 
-use minilzo_rs::{adler32, LZO};
-use zstd::bulk::decompress as zstd_decompress;
-use zune_inflate::DeflateDecoder;
+use zune_inflate::{DeflateDecoder, DeflateOptions};
 
 /// Header-only representation for a compressed-format block.
 #[derive(Debug, BinRead)]
@@ -15,39 +17,132 @@ pub struct CompressedBlockHeader {
     pub checksum: u32,
 }
 
+/// Decompresses blocks of a compression type this crate does not decode
+/// itself, e.g. one used by a fork of the format. See
+/// [`register_block_codec`].
+pub trait BlockCodec: Send + Sync {
+    /// Decompress an already decrypted block payload. `decompressed_size`
+    /// comes from the block index when the caller has it. The block's
+    /// checksum is checked on the result afterwards.
+    fn decode(&self, payload: &[u8], decompressed_size: Option<usize>) -> Result<Vec<u8>>;
+}
+
+/// Compression types decoded without a registered codec: none, LZO,
+/// zlib and zstd. LZO and zstd need the `lzo` and `zstd` features; without
+/// them their blocks fail with `UnsupportedFeature`.
+pub const BUILTIN_BLOCK_ENCODINGS: [u32; 4] = [0, 1, 2, 4];
+
+/// The compression type is the low nibble of a block's encoding word.
+const MAX_BLOCK_ENCODING: u32 = 0x0f;
+
+fn codecs() -> &'static RwLock<HashMap<u32, Arc<dyn BlockCodec>>> {
+    static CODECS: OnceLock<RwLock<HashMap<u32, Arc<dyn BlockCodec>>>> = OnceLock::new();
+    CODECS.get_or_init(Default::default)
+}
+
+/// Decode blocks of compression type `encoding` with `codec` from now on,
+/// in every open dictionary, replacing any codec registered for it before.
+/// The built-in types cannot be replaced.
+pub fn register_block_codec(encoding: u32, codec: Arc<dyn BlockCodec>) -> Result<()> {
+    if encoding > MAX_BLOCK_ENCODING {
+        return Err(MDictError::InvalidArgument(format!(
+            "block compression type {} does not fit the encoding nibble",
+            encoding
+        )));
+    }
+    if BUILTIN_BLOCK_ENCODINGS.contains(&encoding) {
+        return Err(MDictError::InvalidArgument(format!(
+            "block compression type {} is built in",
+            encoding
+        )));
+    }
+    codecs().write().unwrap().insert(encoding, codec);
+    Ok(())
+}
+
+/// Forget the codec registered for `encoding`. Returns whether there was one.
+pub fn unregister_block_codec(encoding: u32) -> bool {
+    codecs().write().unwrap().remove(&encoding).is_some()
+}
+
+/// Compression types with a registered codec, in ascending order.
+pub fn registered_block_encodings() -> Vec<u32> {
+    let mut encodings = codecs().read().unwrap().keys().copied().collect::<Vec<_>>();
+    encodings.sort_unstable();
+    encodings
+}
+
 pub fn decode_format_block(buf: &[u8]) -> Result<Vec<u8>> {
+    decode_block(buf, None)
+}
+
+/// Like [`decode_format_block`], for callers that know the decompressed size
+/// from the block index. Standard LZO blocks are a bare lzo1x stream and can
+/// only be decoded this way.
+pub fn decode_format_block_sized(buf: &[u8], decompressed_size: usize) -> Result<Vec<u8>> {
+    decode_block(buf, Some(decompressed_size))
+}
+
+fn decode_block(buf: &[u8], decompressed_size: Option<usize>) -> Result<Vec<u8>> {
     if buf.len() < 8 {
         return Err(MDictError::InvalidFormat("buffer too small".to_string()));
     }
 
     let mut cur = std::io::Cursor::new(buf);
     let fh: CompressedBlockHeader = CompressedBlockHeader::read(&mut cur)?;
-    let encoding = fh.encoding;
+    // Low nibble: compression. Next nibble: encryption method. Next byte:
+    // number of leading payload bytes that are encrypted.
+    let encoding = fh.encoding & 0x0f;
+    let encryption_method = (fh.encoding >> 4) & 0x0f;
+    let encryption_size = ((fh.encoding >> 8) & 0xff) as usize;
     let expected_checksum = fh.checksum;
-    let payload = &buf[8..];
+
+    let decrypted;
+    let payload = match encryption_method {
+        0 => &buf[8..],
+        1 => {
+            let mut data = buf[8..].to_vec();
+            let end = encryption_size.min(data.len());
+            fast_decrypt(&mut data[..end], &block_key(buf));
+            decrypted = data;
+            &decrypted[..]
+        }
+        other => {
+            return Err(MDictError::UnsupportedFeature(format!(
+                "block encryption method {}",
+                other
+            )));
+        }
+    };
 
     let res = match encoding {
         0 => payload.to_vec(),
         1 => {
-            let lzo =
-                LZO::init().map_err(|e| MDictError::InvalidFormat(format!("LZO init: {}", e)))?;
-            if payload.len() >= 4 {
+            let lzo = Lzo::init()?;
+            let sized = decompressed_size
+                .filter(|&size| size <= max_lzo_output(payload.len()))
+                .and_then(|size| lzo.decompress(payload, size).ok());
+            if let Some(decoded) = sized {
+                decoded
+            } else if payload.len() >= 4 {
                 let expected_len =
                     u32::from_le_bytes([payload[0], payload[1], payload[2], payload[3]]) as usize;
-                match lzo.decompress_safe(&payload[4..], expected_len) {
+                let expected_len = expected_len.min(max_lzo_output(payload.len()));
+                match lzo.decompress(&payload[4..], expected_len) {
                     Ok(decoded) => decoded,
-                    Err(_) => lzo
-                        .decompress(payload, payload.len())
-                        .map_err(|e| MDictError::InvalidFormat(format!("LZO decompress: {}", e)))?,
+                    Err(_) => lzo.decompress(payload, payload.len())?,
                 }
             } else {
-                lzo.decompress(payload, payload.len())
-                    .map_err(|e| MDictError::InvalidFormat(format!("LZO decompress: {}", e)))?
+                lzo.decompress(payload, payload.len())?
             }
         }
-        2 => DeflateDecoder::new(payload)
-            .decode_zlib()
-            .map_err(|e| MDictError::InvalidFormat(format!("deflate decode: {}", e)))?,
+        2 => {
+            let options = DeflateOptions::default()
+                .set_limit(decompressed_size.unwrap_or(MAX_UNSIZED_OUTPUT));
+            DeflateDecoder::new_with_options(payload, options)
+                .decode_zlib()
+                .map_err(|e| MDictError::InvalidFormat(format!("deflate decode: {}", e)))?
+        }
         4 => {
             if payload.len() < 4 {
                 return Err(MDictError::InvalidFormat(
@@ -56,21 +151,49 @@ pub fn decode_format_block(buf: &[u8]) -> Result<Vec<u8>> {
             }
             let expected_len =
                 u32::from_le_bytes([payload[0], payload[1], payload[2], payload[3]]) as usize;
-            zstd_decompress(&payload[4..], expected_len)
-                .map_err(|e| MDictError::InvalidFormat(format!("zstd decode: {}", e)))?
+            // A damaged size prefix must not drive the output allocation.
+            let expected_len = decompressed_size
+                .unwrap_or(MAX_UNSIZED_OUTPUT)
+                .min(expected_len);
+            zstd_decompress(&payload[4..], expected_len, None)?
         }
         other => {
-            return Err(MDictError::InvalidFormat(format!(
-                "unknown encoding: {}",
-                other
-            )));
+            let codec = codecs().read().unwrap().get(&other).cloned();
+            match codec {
+                Some(codec) => codec.decode(payload, decompressed_size)?,
+                None => {
+                    return Err(MDictError::UnsupportedFeature(format!(
+                        "unknown block compression type {} (encoding word {:#010x}); \
+                         register a BlockCodec for it",
+                        other, fh.encoding
+                    )));
+                }
+            }
         }
     };
 
     let checksum = adler32(&res);
     if checksum != expected_checksum {
-        return Err(MDictError::InvalidFormat("invalid checksum".to_string()));
+        return Err(checksum_mismatch(expected_checksum, checksum));
     }
 
     Ok(res)
 }
+
+/// Output cap for blocks decoded without a size from the block index, so a
+/// damaged size prefix or a decompression bomb cannot exhaust memory.
+const MAX_UNSIZED_OUTPUT: usize = 64 * 1024 * 1024;
+
+/// lzo1x encodes long matches in 255-byte steps, so no valid stream expands
+/// much beyond that. Caps the output buffer when sizes come from a damaged
+/// index.
+fn max_lzo_output(compressed_len: usize) -> usize {
+    compressed_len.saturating_mul(256).saturating_add(64)
+}
+
+pub(crate) fn checksum_mismatch(stored: u32, computed: u32) -> MDictError {
+    MDictError::InvalidFormat(format!(
+        "checksum mismatch: stored {:08x}, computed {:08x}",
+        stored, computed
+    ))
+}
@@ -0,0 +1,83 @@
+//! The MDict "fast" cipher and the header `Encrypted` flags.
+//!
+//! `Encrypted` is a bit set: bit 0 encrypts the key-section preamble with a
+//! per-user registration key (salsa20), bit 1 encrypts the compressed key-info
+//! block with a key derived from the block's own checksum. Only the latter can
+//! be undone without external input.
+
+use ripemd::{Digest, Ripemd128};
+
+/// Key-section preamble encrypted with a registration key.
+pub const ENCRYPTED_PREAMBLE: u8 = 0x01;
+/// Compressed key-info block encrypted with the fast cipher.
+pub const ENCRYPTED_KEY_INFO: u8 = 0x02;
+
+/// Parse the header `Encrypted` attribute (`"No"`, `"Yes"` or a bit set).
+pub fn parse_encrypted_flags(value: &str) -> u8 {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("no") || value.is_empty() {
+        0
+    } else if value.eq_ignore_ascii_case("yes") {
+        ENCRYPTED_PREAMBLE
+    } else {
+        value.parse().unwrap_or(ENCRYPTED_PREAMBLE)
+    }
+}
+
+fn ripemd128(parts: &[&[u8]]) -> [u8; 16] {
+    let mut hasher = Ripemd128::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+/// Key for an encrypted key-info block: ripemd128 of the stored checksum
+/// (bytes 4..8 of the block) followed by the constant `0x3695` (LE).
+fn key_info_key(block: &[u8]) -> [u8; 16] {
+    ripemd128(&[&block[4..8], &0x3695u32.to_le_bytes()])
+}
+
+pub fn fast_decrypt(data: &mut [u8], key: &[u8]) {
+    let mut previous = 0x36u8;
+    for (i, byte) in data.iter_mut().enumerate() {
+        let cipher = *byte;
+        *byte = cipher.rotate_left(4) ^ previous ^ (i as u8) ^ key[i % key.len()];
+        previous = cipher;
+    }
+}
+
+/// Inverse of [`fast_decrypt`]; used to produce encrypted fixtures.
+pub fn fast_encrypt(data: &mut [u8], key: &[u8]) {
+    let mut previous = 0x36u8;
+    for (i, byte) in data.iter_mut().enumerate() {
+        let cipher = (*byte ^ previous ^ (i as u8) ^ key[i % key.len()]).rotate_left(4);
+        *byte = cipher;
+        previous = cipher;
+    }
+}
+
+/// Decrypt a compressed key-info block in place. The 8-byte block header
+/// (compression type + checksum) is stored in the clear.
+pub fn decrypt_key_info_block(block: &mut [u8]) {
+    if block.len() <= 8 {
+        return;
+    }
+    let key = key_info_key(block);
+    fast_decrypt(&mut block[8..], &key);
+}
+
+/// Encrypt a compressed key-info block in place; inverse of [`decrypt_key_info_block`].
+pub fn encrypt_key_info_block(block: &mut [u8]) {
+    if block.len() <= 8 {
+        return;
+    }
+    let key = key_info_key(block);
+    fast_encrypt(&mut block[8..], &key);
+}
+
+/// Key for a per-block encrypted compressed block (V3 layout): ripemd128 of
+/// the stored checksum bytes.
+pub(crate) fn block_key(block: &[u8]) -> [u8; 16] {
+    ripemd128(&[&block[4..8]])
+}
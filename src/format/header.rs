@@ -1,6 +1,10 @@
-use crate::error::Result;
+use crate::error::{MDictError, Result};
+use crate::format::codecs::adler32;
+use crate::format::compressed_block::checksum_mismatch;
+use crate::stylesheet::StyleSheet;
+use crate::types::DictionaryMetadata;
 use std::collections::HashMap;
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, SeekFrom};
 
 use binrw::BinRead;
 use xmlparser::{Token, Tokenizer};
@@ -47,21 +51,29 @@ pub struct HeaderInfo {
     pub dict_info_size: u32,
     pub dict_info: HashMap<String, String>,
     pub adler32_checksum: u32,
+    computed_checksum: u32,
+    version: crate::types::MdictVersion,
+    stylesheet: StyleSheet,
 }
 
 #[derive(Debug, BinRead)]
-#[br(big)]
+#[br(big, import(available: u64))]
 struct HeaderRaw {
+    #[br(assert(dict_info_size as u64 <= available, "header size {} exceeds the file", dict_info_size))]
     dict_info_size: u32,
     #[br(count = dict_info_size as usize)]
     dict_info: Vec<u8>,
+    #[br(little)]
     adler32_checksum: u32,
 }
 
 impl HeaderInfo {
     /// Read header from a `Read + Seek` source using `binrw` for the fixed layout.
     pub fn read_from<R: Read + Seek>(reader: &mut R) -> Result<Self> {
-        let raw: HeaderRaw = HeaderRaw::read(reader)?;
+        let start = reader.stream_position()?;
+        let available = reader.seek(SeekFrom::End(0))?.saturating_sub(start);
+        reader.seek(SeekFrom::Start(start))?;
+        let raw: HeaderRaw = HeaderRaw::read_args(reader, (available,))?;
 
         let buf16: Vec<u16> = raw
             .dict_info
@@ -71,47 +83,126 @@ impl HeaderInfo {
 
         let xml = String::from_utf16_lossy(&buf16);
         let dict_info = parse_attributes(&xml);
+        let version = parse_version(&dict_info)?;
+        let stylesheet = dict_info
+            .get("StyleSheet")
+            .map(|value| StyleSheet::parse(value))
+            .unwrap_or_default();
 
         Ok(HeaderInfo {
             dict_info_size: raw.dict_info_size,
             dict_info,
             adler32_checksum: raw.adler32_checksum,
+            computed_checksum: adler32(&raw.dict_info),
+            version,
+            stylesheet,
         })
     }
 
+    /// adler32 of the header bytes actually read.
+    pub fn computed_checksum(&self) -> u32 {
+        self.computed_checksum
+    }
+
+    /// Check the stored adler32 against the header bytes actually read.
+    pub fn verify_checksum(&self) -> Result<()> {
+        if self.adler32_checksum != self.computed_checksum {
+            return Err(
+                checksum_mismatch(self.adler32_checksum, self.computed_checksum)
+                    .in_section("header"),
+            );
+        }
+        Ok(())
+    }
+
     pub fn get(&self, key: &str) -> Option<&String> {
         self.dict_info.get(key)
     }
 
-    /// Return the declared encoding for dict info: `UTF-8` -> `Utf8`, otherwise default to `Utf16LE`.
+    /// Return the declared encoding for dict info (`UTF-8`, `GBK`/`GB2312`,
+    /// `GB18030`, `BIG5`); anything else defaults to `Utf16LE`.
     pub fn get_encoding(&self) -> crate::types::Encoding {
-        if let Some(enc) = self.dict_info.get("Encoding") {
-            if enc.eq_ignore_ascii_case("UTF-8") {
-                crate::types::Encoding::Utf8
-            } else {
-                crate::types::Encoding::Utf16LE
-            }
-        } else {
-            crate::types::Encoding::Utf16LE
-        }
+        self.dict_info
+            .get("Encoding")
+            .map(|enc| crate::types::Encoding::from_label(enc))
+            .unwrap_or(crate::types::Encoding::Utf16LE)
+    }
+
+    /// The parsed `StyleSheet` attribute (empty if there is none).
+    pub fn stylesheet(&self) -> &StyleSheet {
+        &self.stylesheet
     }
 
     /// Return the engine version as an enum similar to the legacy parser.
     pub fn get_version(&self) -> crate::types::MdictVersion {
-        if let Some(version) = self.dict_info.get("GeneratedByEngineVersion") {
-            match version.as_str() {
-                "1.0" => crate::types::MdictVersion::V1,
-                "2.0" => crate::types::MdictVersion::V2,
-                "3.0" => crate::types::MdictVersion::V3,
-                _ => panic!("Unsupported version: {}", version),
-            }
-        } else {
-            crate::types::MdictVersion::MDD
+        self.version
+    }
+
+    /// Whether records use compact HTML (`Compact="Yes"`, or the misspelled
+    /// `Compat` some builders write).
+    pub fn is_compact(&self) -> bool {
+        self.flag("Compact") || self.flag("Compat")
+    }
+
+    pub(crate) fn flag(&self, key: &str) -> bool {
+        self.get(key)
+            .is_some_and(|value| value.trim().eq_ignore_ascii_case("yes"))
+    }
+
+    /// All header attributes as typed fields.
+    pub fn metadata(&self) -> DictionaryMetadata {
+        let text = |key: &str| {
+            self.get(key)
+                .filter(|value| !value.trim().is_empty())
+                .cloned()
+        };
+
+        DictionaryMetadata {
+            version: self.get_version(),
+            engine_version: text("GeneratedByEngineVersion"),
+            required_engine_version: text("RequiredEngineVersion"),
+            title: text("Title"),
+            description: text("Description"),
+            encoding: self.get_encoding(),
+            encrypted: self.encrypted_flags(),
+            creation_date: text("CreationDate"),
+            stylesheet: text("StyleSheet"),
+            compact: self.is_compact(),
+            key_case_sensitive: self.flag("KeyCaseSensitive"),
+            strip_key: self.flag("StripKey"),
+            format: text("Format"),
         }
     }
 
+    /// Return the `Encrypted` attribute as a bit set (see [`crate::format::encryption`]).
+    pub fn encrypted_flags(&self) -> u8 {
+        self.dict_info
+            .get("Encrypted")
+            .map(|value| crate::format::encryption::parse_encrypted_flags(value))
+            .unwrap_or(0)
+    }
+
     /// Return header size in bytes (4 + dict_info_size + 4)
     pub fn size(&self) -> u64 {
         4 + self.dict_info_size as u64 + 4
     }
 }
+
+/// Engine versions are written as e.g. "1.2" or "2.0"; only the major part
+/// matters. Headers without one are MDD resource files.
+fn parse_version(dict_info: &HashMap<String, String>) -> Result<crate::types::MdictVersion> {
+    let Some(version) = dict_info.get("GeneratedByEngineVersion") else {
+        return Ok(crate::types::MdictVersion::MDD);
+    };
+    match version.split('.').next().unwrap_or_default().trim() {
+        "1" => Ok(crate::types::MdictVersion::V1),
+        "2" => Ok(crate::types::MdictVersion::V2),
+        "3" => Err(MDictError::UnsupportedFeature(
+            "MDict 3.0 dictionaries".to_string(),
+        )),
+        _ => Err(MDictError::InvalidFormat(format!(
+            "header: unknown engine version '{}'",
+            version
+        ))),
+    }
+}
@@ -1,6 +1,8 @@
 use crate::error::Result;
-use crate::types::{Encoding, KeyBlock};
+use crate::types::{Encoding, KeyBlock, MdictVersion};
 use std::convert::TryInto;
+#[cfg(feature = "fs")]
+use std::ops::Range;
 
 fn read_nul_terminated(buf: &[u8], offset: &mut usize, encoding: Encoding) -> Result<String> {
     let rem = &buf[*offset..];
@@ -28,32 +30,76 @@ fn read_nul_terminated(buf: &[u8], offset: &mut usize, encoding: Encoding) -> Re
 
         _ => {
             let pos = rem.iter().position(|&b| b == 0).unwrap_or(rem.len());
-            let s = String::from_utf8_lossy(&rem[..pos]).into_owned();
+            let s = encoding.decode(&rem[..pos]);
             *offset += pos + (pos < rem.len()) as usize;
             Ok(s)
         }
     }
 }
 
-fn read_key_id_be(buf: &[u8], offset: &mut usize) -> Result<u64> {
+fn read_key_id_be(buf: &[u8], offset: &mut usize, width: usize) -> Result<u64> {
     let bytes = buf
-        .get(*offset..*offset + 8)
+        .get(*offset..*offset + width)
         .ok_or_else(|| crate::error::MDictError::from("unexpected EOF while reading key_id"))?;
 
-    *offset += 8;
-    Ok(u64::from_be_bytes(bytes.try_into().unwrap()))
+    *offset += width;
+    if width == 4 {
+        Ok(u32::from_be_bytes(bytes.try_into().unwrap()) as u64)
+    } else {
+        Ok(u64::from_be_bytes(bytes.try_into().unwrap()))
+    }
 }
 
-pub fn parse_key_block(buf: &[u8], encoding: Encoding) -> Result<Vec<KeyBlock>> {
+/// Parse a decoded key block. Key ids are `u32` in V1 files and `u64` otherwise.
+pub fn parse_key_block(
+    buf: &[u8],
+    encoding: Encoding,
+    version: MdictVersion,
+) -> Result<Vec<KeyBlock>> {
+    let key_id_width = version.index_pair_size_bytes();
     let mut offset = 0;
     let mut out = Vec::with_capacity(buf.len() / 16);
 
     while offset < buf.len() {
         out.push(KeyBlock {
-            key_id: read_key_id_be(buf, &mut offset)?,
+            key_id: read_key_id_be(buf, &mut offset, key_id_width)?,
             key_text: read_nul_terminated(buf, &mut offset, encoding)?,
         });
     }
 
     Ok(out)
 }
+
+/// Byte offset of each entry's key id in a decoded key block, with the range
+/// of its key text (without the terminator), so ids can be rewritten and
+/// keys copied without decoding them.
+#[cfg(feature = "fs")]
+pub(crate) fn key_entry_positions(
+    buf: &[u8],
+    encoding: Encoding,
+    version: MdictVersion,
+) -> Result<Vec<(usize, Range<usize>)>> {
+    let key_id_width = version.index_pair_size_bytes();
+    let nul: &[u8] = match encoding {
+        Encoding::Utf16LE => &[0, 0],
+        _ => &[0],
+    };
+    let mut offset = 0;
+    let mut out = Vec::with_capacity(buf.len() / 16);
+
+    while offset < buf.len() {
+        let key_id_at = offset;
+        read_key_id_be(buf, &mut offset, key_id_width)?;
+        let text_start = offset;
+        read_nul_terminated(buf, &mut offset, encoding)?;
+        // The last key of a block may lack its terminator.
+        let text_end = if buf[text_start..offset].ends_with(nul) {
+            offset - nul.len()
+        } else {
+            offset
+        };
+        out.push((key_id_at, text_start..text_end));
+    }
+
+    Ok(out)
+}
@@ -1,6 +1,10 @@
-use crate::error::Result;
-use crate::format::decode_format_block as decode_block;
+use crate::error::{MDictError, Result};
+use crate::format::codecs::adler32;
+use crate::format::compressed_block::checksum_mismatch;
+use crate::format::decode_format_block_sized as decode_block;
+use crate::format::encryption::{self, ENCRYPTED_KEY_INFO, ENCRYPTED_PREAMBLE};
 use crate::format::HeaderInfo;
+use crate::types::Encoding;
 use binrw::BinRead;
 use std::io::{Read, Seek};
 
@@ -18,34 +22,49 @@ pub struct KeySection {
     pub section_offset: u64,
     pub key_info_offset: u64,
     pub next_section_offset: u64,
+    /// Bytes of key info as stored, i.e. compressed in V2 files.
+    pub key_info_size: u64,
+    /// Bytes of the key block area after the key info, as the preamble
+    /// declares it.
+    pub key_blocks_size: u64,
     pub key_info_blocks: Vec<KeyBlockInfo>,
+    /// Compressed sizes of the key blocks before each one, i.e. byte offsets
+    /// into the key block area; one more element than there are blocks.
     pub key_info_prefix_sum: Vec<u64>,
+    /// Entries in the key blocks before each one, i.e. the global index of
+    /// each block's first entry; one more element than there are blocks.
     pub num_entries_prefix_sum: Vec<u64>,
     pub num_blocks: u64,
     pub num_entries: u64,
     pub addler32_checksum: u32,
+    /// adler32 of the V2 preamble as read; `None` for V1, which stores none.
+    pub computed_checksum: Option<u32>,
 }
 
 #[derive(Debug, BinRead)]
-#[br(big)]
+#[br(big, import(available: u64))]
 struct KeySectionV1Raw {
     num_blocks: u32,
     num_entries: u32,
     #[br(calc = 0)]
     num_bytes_after_decomp_v2: u32,
+    #[br(assert(key_info_block_size as u64 <= available, "key info size {} exceeds the file", key_info_block_size))]
     key_info_block_size: u32,
     key_blocks_size: u32,
+    // V1 key sections carry no checksum over the preamble.
+    #[br(calc = 0)]
     addler32_checksum: u32,
     #[br(count = key_info_block_size as usize)]
     key_info: Vec<u8>,
 }
 
 #[derive(Debug, BinRead)]
-#[br(big)]
+#[br(big, import(available: u64))]
 struct KeySectionV2Raw {
     num_blocks: u64,
     num_entries: u64,
     num_bytes_after_decomp_v2: u64,
+    #[br(assert(key_info_block_size <= available, "key info size {} exceeds the file", key_info_block_size))]
     key_info_block_size: u64,
     key_blocks_size: u64,
     addler32_checksum: u32,
@@ -58,23 +77,23 @@ struct KeySectionV2Raw {
 #[br(import(char_width: usize))]
 #[derive(Debug)]
 struct KeyBlockInfoV1Raw {
+    #[br(map = |n: u32| u64::from(n))]
     num_entries: u64,
 
+    // V1 first/last keys are not NUL-terminated.
     #[br(temp)]
     size_of_first: u8,
     #[br(count = size_of_first as usize * char_width)]
     first: Vec<u8>,
-    #[br(temp, count = char_width)]
-    first_null: Vec<u8>,
 
     #[br(temp)]
     size_of_last: u8,
     #[br(count = size_of_last as usize * char_width)]
     last: Vec<u8>,
-    #[br(temp, count = char_width)]
-    last_null: Vec<u8>,
 
+    #[br(map = |n: u32| u64::from(n))]
     compressed_size: u64,
+    #[br(map = |n: u32| u64::from(n))]
     decompressed_size: u64,
 }
 
@@ -105,8 +124,16 @@ struct KeyBlockInfoV2Raw {
 
 impl KeySection {
     pub fn read_from<R: Read + Seek>(reader: &mut R, header: &HeaderInfo) -> Result<Self> {
+        let stream_len = reader.seek(std::io::SeekFrom::End(0))?;
         reader.seek(std::io::SeekFrom::Start(header.size()))?;
 
+        let encrypted = header.encrypted_flags();
+        if encrypted & ENCRYPTED_PREAMBLE != 0 {
+            return Err(MDictError::UnsupportedFeature(
+                "key section is encrypted with a registration key".to_string(),
+            ));
+        }
+
         let ver = header.get_version();
         let (
             num_blocks,
@@ -116,7 +143,8 @@ impl KeySection {
             key_blocks_size,
             addler32_checksum,
             mut key_info_buf,
-        ) = versioned_read!(ver, reader,
+        ) = versioned_read_args!(ver, reader,
+            import: (stream_len.saturating_sub(header.size()),),
             v1: KeySectionV1Raw,
             v2: KeySectionV2Raw,
             as raw => {
@@ -134,20 +162,53 @@ impl KeySection {
 
         let key_info_offset = reader.seek(std::io::SeekFrom::Current(0))? - key_info_block_size;
 
+        let computed_checksum = num_bytes_after_decomp_v2.map(|size_after| {
+            let preamble: Vec<u8> = [
+                num_blocks,
+                num_entries,
+                size_after,
+                key_info_block_size,
+                key_blocks_size,
+            ]
+            .iter()
+            .flat_map(|field| field.to_be_bytes())
+            .collect();
+            adler32(&preamble)
+        });
+
         if let Some(size_after) = num_bytes_after_decomp_v2 {
-            let decompressed = decode_block(&key_info_buf)?;
-            assert_eq!(decompressed.len() as u64, size_after);
+            if encrypted & ENCRYPTED_KEY_INFO != 0 {
+                encryption::decrypt_key_info_block(&mut key_info_buf);
+            }
+            let decompressed = decode_block(&key_info_buf, size_after as usize)
+                .map_err(|e| e.in_section("key info block"))?;
+            if decompressed.len() as u64 != size_after {
+                return Err(MDictError::InvalidFormat(format!(
+                    "key info block: decompressed to {} bytes, preamble says {}",
+                    decompressed.len(),
+                    size_after
+                )));
+            }
             key_info_buf = decompressed;
         }
 
-        let size_of_first_or_last = header.get_encoding().char_width();
-        let key_info_blocks = parse_key_info_binrw(ver, &key_info_buf, size_of_first_or_last)?;
+        let key_info_blocks = parse_key_info_binrw(ver, &key_info_buf, header.get_encoding())?;
+        if key_info_blocks.len() as u64 != num_blocks {
+            return Err(MDictError::InvalidFormat(format!(
+                "key info block: lists {} key blocks, preamble says {}",
+                key_info_blocks.len(),
+                num_blocks
+            )));
+        }
 
         let mut prefix_sum = Vec::with_capacity(key_info_blocks.len() + 1);
         prefix_sum.push(0u64);
         let mut sum = 0u64;
         for kb in &key_info_blocks {
-            sum += kb.compressed_size;
+            sum = sum
+                .checked_add(kb.compressed_size)
+                .filter(|&sum| sum <= key_blocks_size)
+                .ok_or("key info block: key block sizes exceed the key block area")?;
             prefix_sum.push(sum);
         }
 
@@ -155,33 +216,60 @@ impl KeySection {
         num_entries_prefix_sum.push(0u64);
         let mut entries_sum = 0u64;
         for kb in &key_info_blocks {
-            entries_sum += kb.num_entries;
+            entries_sum = entries_sum
+                .checked_add(kb.num_entries)
+                .ok_or("key info block: entry counts overflow")?;
             num_entries_prefix_sum.push(entries_sum);
         }
+        if entries_sum != num_entries {
+            return Err(MDictError::InvalidFormat(format!(
+                "key info block: key blocks hold {} entries, preamble says {}",
+                entries_sum, num_entries
+            )));
+        }
 
-        let next_section_offset = key_info_offset + key_info_block_size + key_blocks_size;
+        let next_section_offset = key_info_offset
+            .checked_add(key_info_block_size)
+            .and_then(|end| end.checked_add(key_blocks_size))
+            .filter(|&end| end <= stream_len)
+            .ok_or("key section extends past the end of the file")?;
 
         Ok(KeySection {
             section_offset: header.size(),
             key_info_offset,
             next_section_offset,
+            key_info_size: key_info_block_size,
+            key_blocks_size,
             key_info_blocks,
             key_info_prefix_sum: prefix_sum,
             num_entries_prefix_sum,
             num_blocks,
             num_entries,
             addler32_checksum,
+            computed_checksum,
         })
     }
+
+    /// Check the stored preamble adler32 (V2 only) against the bytes read.
+    pub fn verify_checksum(&self) -> Result<()> {
+        match self.computed_checksum {
+            Some(computed) if computed != self.addler32_checksum => {
+                Err(checksum_mismatch(self.addler32_checksum, computed)
+                    .in_section("key section preamble"))
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 fn parse_key_info_binrw(
     ver: crate::types::MdictVersion,
     buf: &[u8],
-    size_of_first_or_last: usize,
+    encoding: Encoding,
 ) -> Result<Vec<KeyBlockInfo>> {
     use std::io::Cursor;
 
+    let size_of_first_or_last = encoding.char_width();
     let mut cur = Cursor::new(buf);
     let mut out = Vec::new();
 
@@ -191,8 +279,8 @@ fn parse_key_info_binrw(
             v1: KeyBlockInfoV1Raw,
             v2: KeyBlockInfoV2Raw,
             as raw => {
-                let first = decode_key_text(raw.first, size_of_first_or_last)?;
-                let last = decode_key_text(raw.last, size_of_first_or_last)?;
+                let first = decode_key_text(raw.first, encoding)?;
+                let last = decode_key_text(raw.last, encoding)?;
 
                 out.push(KeyBlockInfo {
                     num_entries: raw.num_entries,
@@ -208,19 +296,39 @@ fn parse_key_info_binrw(
     Ok(out)
 }
 
-fn decode_key_text(buf: Vec<u8>, char_width: usize) -> Result<String> {
-    if char_width == 1 {
-        String::from_utf8(buf).map_err(|_| format!("invalid utf8").into())
-    } else {
-        if buf.len() % 2 != 0 {
-            return Err(format!("invalid utf16 length").into());
+/// Byte range of each key block's entry in decoded key info; the entry ends
+/// with the block's compressed and decompressed size.
+#[cfg(feature = "fs")]
+pub(crate) fn key_info_entry_ranges(
+    ver: crate::types::MdictVersion,
+    buf: &[u8],
+    encoding: Encoding,
+) -> Result<Vec<std::ops::Range<usize>>> {
+    use std::io::Cursor;
+
+    let mut cur = Cursor::new(buf);
+    let mut out = Vec::new();
+
+    while (cur.position() as usize) < buf.len() {
+        let start = cur.position() as usize;
+        versioned_read_args!(
+            ver, &mut cur, import: (encoding.char_width(),),
+            v1: KeyBlockInfoV1Raw,
+            v2: KeyBlockInfoV2Raw,
+            as _raw => {}
+        );
+        out.push(start..cur.position() as usize);
+    }
+
+    Ok(out)
+}
+
+fn decode_key_text(buf: Vec<u8>, encoding: Encoding) -> Result<String> {
+    match encoding {
+        Encoding::Utf8 => String::from_utf8(buf).map_err(|_| "invalid utf8".into()),
+        Encoding::Utf16LE | Encoding::Unknown if buf.len() % 2 != 0 => {
+            Err("invalid utf16 length".into())
         }
-        let u16_iter = buf
-            .chunks_exact(2)
-            .map(|c| u16::from_le_bytes([c[0], c[1]]));
-        let s = std::char::decode_utf16(u16_iter)
-            .map(|r| r.unwrap_or('\u{FFFD}'))
-            .collect::<String>();
-        Ok(s)
+        _ => Ok(encoding.decode(&buf)),
     }
 }
@@ -0,0 +1,253 @@
+//! An opt-in sidecar holding a parsed [`KeySection`], so reopening a large
+//! dictionary skips decompressing and parsing its key info.
+//!
+//! The sidecar records a RIPEMD-128 fingerprint of everything the section is
+//! parsed from: the file length, the header, the key section preamble and
+//! the raw key info. It is only used while those bytes are unchanged; the
+//! key info is read to check, but not decompressed or parsed. Numbers are
+//! little-endian, strings a `u32` length and UTF-8.
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use ripemd::{Digest, Ripemd128};
+
+use crate::error::{MDictError, Result};
+use crate::format::key_index::{KeyBlockInfo, KeySection};
+use crate::format::HeaderInfo;
+
+const MAGIC: &[u8; 8] = b"MDXIDX\0\0";
+const FORMAT_VERSION: u32 = 2;
+
+/// Where the key section cache of the dictionary at `path` is kept: next to
+/// it, with `idx` appended to the extension (`dict.mdx` -> `dict.mdxidx`).
+pub fn key_cache_path(path: impl AsRef<Path>) -> PathBuf {
+    let path = path.as_ref();
+    let mut name = path.as_os_str().to_owned();
+    if path.extension().is_none() {
+        name.push(".");
+    }
+    name.push("idx");
+    PathBuf::from(name)
+}
+
+impl KeySection {
+    /// [`Self::read_from`], through the cache at `cache_path`: a cache
+    /// written for the same bytes is loaded instead of parsing the key info,
+    /// and a missing or stale one is rewritten. Failing to write the cache
+    /// is logged and otherwise ignored, so read-only locations still open.
+    pub fn read_cached<R: Read + Seek>(
+        reader: &mut R,
+        header: &HeaderInfo,
+        cache_path: impl AsRef<Path>,
+    ) -> Result<Self> {
+        let cache_path = cache_path.as_ref();
+        let Some(fingerprint) = fingerprint(reader, header)? else {
+            return Self::read_from(reader, header);
+        };
+
+        if let Ok(bytes) = fs::read(cache_path) {
+            match decode(&bytes, &fingerprint) {
+                Ok(Some(section)) => return Ok(section),
+                Ok(None) => log::debug!("Key section cache {:?} is stale", cache_path),
+                Err(e) => log::warn!("Ignoring key section cache {:?}: {}", cache_path, e),
+            }
+        }
+
+        let section = Self::read_from(reader, header)?;
+        if let Err(e) = write(&section, &fingerprint, cache_path) {
+            log::warn!("Could not write key section cache {:?}: {}", cache_path, e);
+        }
+        Ok(section)
+    }
+}
+
+/// The fingerprint of the bytes a key section is parsed from, or `None` when
+/// the preamble cannot be read, leaving [`KeySection::read_from`] to report
+/// why.
+fn fingerprint<R: Read + Seek>(reader: &mut R, header: &HeaderInfo) -> Result<Option<[u8; 16]>> {
+    let stream_len = reader.seek(SeekFrom::End(0))?;
+    let preamble_len: u64 = if header.get_version().major() >= 2 {
+        44
+    } else {
+        16
+    };
+    let key_info_size_at = if header.get_version().major() >= 2 {
+        24..32
+    } else {
+        8..12
+    };
+
+    let Some(preamble_end) = header.size().checked_add(preamble_len) else {
+        return Ok(None);
+    };
+    if preamble_end > stream_len {
+        return Ok(None);
+    }
+    let mut bytes = vec![0u8; preamble_end as usize];
+    reader.seek(SeekFrom::Start(0))?;
+    reader.read_exact(&mut bytes)?;
+
+    let size_bytes = &bytes[header.size() as usize..][key_info_size_at];
+    let key_info_size = size_bytes
+        .iter()
+        .fold(0u64, |size, &byte| (size << 8) | u64::from(byte));
+    match preamble_end.checked_add(key_info_size) {
+        Some(end) if end <= stream_len => {}
+        _ => return Ok(None),
+    }
+    bytes.resize((preamble_end + key_info_size) as usize, 0);
+    reader.read_exact(&mut bytes[preamble_end as usize..])?;
+
+    let mut hasher = Ripemd128::new();
+    hasher.update(stream_len.to_le_bytes());
+    hasher.update(bytes);
+    Ok(Some(hasher.finalize().into()))
+}
+
+fn write(section: &KeySection, fingerprint: &[u8; 16], path: &Path) -> Result<()> {
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    out.extend_from_slice(fingerprint);
+    for value in [
+        section.section_offset,
+        section.key_info_offset,
+        section.next_section_offset,
+        section.key_info_size,
+        section.key_blocks_size,
+        section.num_blocks,
+        section.num_entries,
+    ] {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    out.extend_from_slice(&section.addler32_checksum.to_le_bytes());
+    match section.computed_checksum {
+        Some(checksum) => {
+            out.push(1);
+            out.extend_from_slice(&checksum.to_le_bytes());
+        }
+        None => out.push(0),
+    }
+    out.extend_from_slice(&(section.key_info_blocks.len() as u64).to_le_bytes());
+    for block in &section.key_info_blocks {
+        out.extend_from_slice(&block.num_entries.to_le_bytes());
+        for key in [&block.first, &block.last] {
+            out.extend_from_slice(&(key.len() as u32).to_le_bytes());
+            out.extend_from_slice(key.as_bytes());
+        }
+        out.extend_from_slice(&block.compressed_size.to_le_bytes());
+        out.extend_from_slice(&block.decompressed_size.to_le_bytes());
+    }
+
+    // Written aside and renamed, so a reader never sees half a cache.
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let mut writer = BufWriter::new(File::create(&partial)?);
+    writer.write_all(&out)?;
+    writer.flush()?;
+    drop(writer);
+    fs::rename(partial, path)?;
+    Ok(())
+}
+
+/// The cached section, or `None` if the cache was written for other bytes.
+fn decode(bytes: &[u8], fingerprint: &[u8; 16]) -> Result<Option<KeySection>> {
+    let mut input = Input(bytes);
+    if input.take(MAGIC.len())? != MAGIC {
+        return Err(MDictError::InvalidFormat(
+            "not a key section cache".to_string(),
+        ));
+    }
+    if input.u32()? != FORMAT_VERSION || input.take(16)? != fingerprint {
+        return Ok(None);
+    }
+
+    let section_offset = input.u64()?;
+    let key_info_offset = input.u64()?;
+    let next_section_offset = input.u64()?;
+    let key_info_size = input.u64()?;
+    let key_blocks_size = input.u64()?;
+    let num_blocks = input.u64()?;
+    let num_entries = input.u64()?;
+    let addler32_checksum = input.u32()?;
+    let computed_checksum = match input.take(1)?[0] {
+        0 => None,
+        _ => Some(input.u32()?),
+    };
+
+    let block_count = input.u64()? as usize;
+    let mut key_info_blocks = Vec::with_capacity(block_count.min(bytes.len()));
+    let mut key_info_prefix_sum = vec![0u64];
+    let mut num_entries_prefix_sum = vec![0u64];
+    let (mut size_sum, mut entry_sum) = (0u64, 0u64);
+    for _ in 0..block_count {
+        let block = KeyBlockInfo {
+            num_entries: input.u64()?,
+            first: input.text()?,
+            last: input.text()?,
+            compressed_size: input.u64()?,
+            decompressed_size: input.u64()?,
+        };
+        size_sum = size_sum
+            .checked_add(block.compressed_size)
+            .ok_or("key section cache: block sizes overflow")?;
+        entry_sum = entry_sum
+            .checked_add(block.num_entries)
+            .ok_or("key section cache: entry counts overflow")?;
+        key_info_prefix_sum.push(size_sum);
+        num_entries_prefix_sum.push(entry_sum);
+        key_info_blocks.push(block);
+    }
+    if !input.0.is_empty() {
+        return Err(MDictError::InvalidFormat(
+            "trailing bytes in key section cache".to_string(),
+        ));
+    }
+
+    Ok(Some(KeySection {
+        section_offset,
+        key_info_offset,
+        next_section_offset,
+        key_info_size,
+        key_blocks_size,
+        key_info_blocks,
+        key_info_prefix_sum,
+        num_entries_prefix_sum,
+        num_blocks,
+        num_entries,
+        addler32_checksum,
+        computed_checksum,
+    }))
+}
+
+struct Input<'a>(&'a [u8]);
+
+impl<'a> Input<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(MDictError::InvalidFormat(
+                "truncated key section cache".to_string(),
+            ));
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn text(&mut self) -> Result<String> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|e| {
+            MDictError::InvalidFormat(format!("invalid utf8 in key section cache: {}", e))
+        })
+    }
+}
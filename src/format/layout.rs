@@ -0,0 +1,68 @@
+//! Where each section of an MDict file sits, for tools that split, patch or
+//! append to files rather than only read them.
+
+use std::ops::Range;
+
+use crate::format::{HeaderInfo, KeySection, RecordSection};
+
+/// Byte ranges of the sections of an MDict file, in file order. Each one
+/// starts where the one before it ends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionLayout {
+    /// Header length, UTF-16 XML attributes and their adler32.
+    pub header: Range<u64>,
+    /// Key block and entry counts, the sizes of key info and key blocks and,
+    /// in V2 files, the decompressed key info size and an adler32.
+    pub key_preamble: Range<u64>,
+    /// Entry count, first and last key and sizes of each key block;
+    /// compressed (and possibly encrypted) in V2 files.
+    pub key_info: Range<u64>,
+    pub key_blocks: Range<u64>,
+    /// Record block and entry counts and the sizes of index and data.
+    pub record_preamble: Range<u64>,
+    /// Compressed and decompressed size of each record block.
+    pub record_index: Range<u64>,
+    /// The record blocks, as far as the record index lists them.
+    pub record_data: Range<u64>,
+}
+
+impl SectionLayout {
+    pub fn new(header: &HeaderInfo, key_section: &KeySection, records: &RecordSection) -> Self {
+        let key_info_end = key_section.key_info_offset + key_section.key_info_size;
+        let record_index_start = records.record_data_offset - records.byte_size_record_index;
+        let record_data_size = records
+            .record_index_prefix_sum
+            .last()
+            .map_or(0, |ri| ri.compressed_size);
+
+        SectionLayout {
+            header: 0..header.size(),
+            key_preamble: header.size()..key_section.key_info_offset,
+            key_info: key_section.key_info_offset..key_info_end,
+            key_blocks: key_info_end..key_section.next_section_offset,
+            record_preamble: key_section.next_section_offset..record_index_start,
+            record_index: record_index_start..records.record_data_offset,
+            record_data: records.record_data_offset
+                ..records.record_data_offset + record_data_size,
+        }
+    }
+
+    /// Byte range of key block `block`, or `None` past the last block.
+    pub fn key_block(&self, key_section: &KeySection, block: usize) -> Option<Range<u64>> {
+        let start = *key_section.key_info_prefix_sum.get(block)?;
+        let end = *key_section.key_info_prefix_sum.get(block + 1)?;
+        Some(self.key_blocks.start + start..self.key_blocks.start + end)
+    }
+
+    /// Byte range of record block `block`, or `None` past the last block.
+    pub fn record_block(&self, records: &RecordSection, block: usize) -> Option<Range<u64>> {
+        let start = records.record_index_prefix_sum.get(block)?.compressed_size;
+        let end = records.record_index_prefix_sum.get(block + 1)?.compressed_size;
+        Some(self.record_data.start + start..self.record_data.start + end)
+    }
+
+    /// End of the last section; anything after it is trailing data.
+    pub fn end(&self) -> u64 {
+        self.record_data.end
+    }
+}
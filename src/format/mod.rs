@@ -1,13 +1,23 @@
 #[macro_use]
 pub mod versioned_binrw;
+pub(crate) mod codecs;
 pub mod compressed_block;
+pub mod encryption;
 pub mod header;
 pub mod key_block;
 pub mod key_index;
+#[cfg(feature = "fs")]
+pub mod key_index_cache;
+pub mod layout;
 pub mod records;
 
-pub use compressed_block::decode_format_block;
+pub use codecs::adler32;
+pub use compressed_block::{
+    decode_format_block, decode_format_block_sized, register_block_codec,
+    registered_block_encodings, unregister_block_codec, BlockCodec, BUILTIN_BLOCK_ENCODINGS,
+};
 pub use header::HeaderInfo;
 pub use key_block::parse_key_block;
 pub use key_index::KeySection;
+pub use layout::SectionLayout;
 pub use records::RecordSection;
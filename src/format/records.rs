@@ -1,4 +1,4 @@
-use crate::error::Result;
+use crate::error::{MDictError, Result};
 use crate::format::{HeaderInfo, KeySection};
 use binrw::BinRead;
 use std::io::{self, Cursor, Read, Seek, SeekFrom};
@@ -57,9 +57,13 @@ impl RecordSection {
         key_index: &KeySection,
         reader: &mut R,
     ) -> Result<RecordSection> {
+        let stream_len = reader.seek(SeekFrom::End(0))?;
         let mut offset = key_index.next_section_offset;
 
-        let mut header_buf = vec![0u8; 8 * 4];
+        let mut header_buf = vec![0u8; 4 * header_index.get_version().index_pair_size_bytes()];
+        if header_buf.len() as u64 > stream_len.saturating_sub(offset) {
+            return Err("record section header extends past the end of the file".into());
+        }
         reader.seek(SeekFrom::Start(offset))?;
         reader.read_exact(&mut header_buf)?;
         offset += header_buf.len() as u64;
@@ -74,6 +78,9 @@ impl RecordSection {
             as raw => { (raw.num_record_blocks as usize, raw.num_entries as usize, raw.byte_size_record_index as usize, raw.byte_size_record_data as usize) }
         );
 
+        if byte_size_record_index as u64 > stream_len.saturating_sub(offset) {
+            return Err("record index extends past the end of the file".into());
+        }
         let mut index_buf = vec![0u8; byte_size_record_index];
         reader.seek(SeekFrom::Start(offset))?;
         reader.read_exact(&mut index_buf)?;
@@ -89,14 +96,21 @@ impl RecordSection {
                     let compressed = pair_raw.compressed_size as u64;
                     let uncompressed = pair_raw.uncompressed_size as u64;
                     let last = record_index.last().cloned().unwrap_or(RecordIndex { compressed_size: 0, uncompressed_size: 0 });
-                    record_index.push(RecordIndex {
-                        compressed_size: last.compressed_size + compressed,
-                        uncompressed_size: last.uncompressed_size + uncompressed,
-                    });
+                    let compressed_size = last.compressed_size.checked_add(compressed);
+                    let uncompressed_size = last.uncompressed_size.checked_add(uncompressed);
+                    let (Some(compressed_size), Some(uncompressed_size)) = (compressed_size, uncompressed_size) else {
+                        return Err("record index: block sizes overflow".into());
+                    };
+                    record_index.push(RecordIndex { compressed_size, uncompressed_size });
                 }
             );
         }
 
+        let data_len = record_index.last().map_or(0, |ri| ri.compressed_size);
+        if data_len > stream_len.saturating_sub(offset) {
+            return Err("record data extends past the end of the file".into());
+        }
+
         let mut prefix = Vec::with_capacity(record_index.len() + 1);
         prefix.push(RecordIndex {
             compressed_size: 0,
@@ -115,11 +129,17 @@ impl RecordSection {
     }
 
     /// Binary-search for the record index containing `offset` (uncompressed offset)
-    pub fn bin_search_record_index(&self, offset: u64) -> u64 {
+    pub fn bin_search_record_index(&self, offset: u64) -> Result<u64> {
         let idx = self
             .record_index_prefix_sum
             .partition_point(|ri| ri.uncompressed_size <= offset);
 
-        (idx - 1) as u64
+        if idx >= self.record_index_prefix_sum.len() {
+            return Err(MDictError::InvalidFormat(format!(
+                "record offset {} is past the end of the record data",
+                offset
+            )));
+        }
+        Ok((idx - 1) as u64)
     }
 }
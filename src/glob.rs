@@ -0,0 +1,63 @@
+//! `*` / `?` key patterns shared by the legacy and FST-backed searches.
+
+use regex::Regex;
+
+use crate::error::{MDictError, Result};
+
+/// A compiled glob: `*` matches any run of characters, `?` exactly one.
+/// Everything else is literal.
+#[derive(Debug, Clone)]
+pub struct GlobPattern {
+    literal_prefix: String,
+    regex: Regex,
+}
+
+impl GlobPattern {
+    pub fn new(pattern: &str) -> Result<Self> {
+        if pattern.is_empty() {
+            return Err(MDictError::InvalidArgument(
+                "glob pattern must not be empty".to_string(),
+            ));
+        }
+
+        let literal_prefix = pattern
+            .split(['*', '?'])
+            .next()
+            .unwrap_or_default()
+            .to_string();
+
+        let mut source = String::with_capacity(pattern.len() + 8);
+        source.push_str("^(?s:");
+        let mut literal = String::new();
+        for c in pattern.chars() {
+            match c {
+                '*' | '?' => {
+                    source.push_str(&regex::escape(&literal));
+                    literal.clear();
+                    source.push_str(if c == '*' { ".*" } else { "." });
+                }
+                _ => literal.push(c),
+            }
+        }
+        source.push_str(&regex::escape(&literal));
+        source.push_str(")$");
+
+        let regex = Regex::new(&source).map_err(|e| {
+            MDictError::InvalidArgument(format!("glob pattern '{}': {}", pattern, e))
+        })?;
+
+        Ok(Self {
+            literal_prefix,
+            regex,
+        })
+    }
+
+    /// The part of the pattern before the first wildcard; every match starts with it.
+    pub fn literal_prefix(&self) -> &str {
+        &self.literal_prefix
+    }
+
+    pub fn matches(&self, key: &str) -> bool {
+        self.regex.is_match(key)
+    }
+}
@@ -0,0 +1,91 @@
+//! Just enough gzip to read compressed dictionary files: the header, the
+//! random-access chunk table dictzip adds, and whole-member inflation.
+
+use crate::error::{MDictError, Result};
+
+pub(crate) const GZIP_MAGIC: [u8; 3] = [0x1f, 0x8b, 0x08];
+
+/// The parts of a gzip member a reader needs.
+pub(crate) struct GzipMember<'a> {
+    bytes: &'a [u8],
+    #[cfg_attr(not(feature = "stardict"), allow(dead_code))]
+    extra: &'a [u8],
+    pub deflate_start: usize,
+}
+
+impl<'a> GzipMember<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<Self> {
+        const FHCRC: u8 = 0x02;
+        const FEXTRA: u8 = 0x04;
+        const FNAME: u8 = 0x08;
+        const FCOMMENT: u8 = 0x10;
+
+        let truncated = || MDictError::InvalidFormat("truncated gzip header".to_string());
+        if !bytes.starts_with(&GZIP_MAGIC) || bytes.len() < 10 {
+            return Err(truncated());
+        }
+        let flags = bytes[3];
+        let mut pos = 10;
+        let mut extra: &[u8] = &[];
+        if flags & FEXTRA != 0 {
+            let len = bytes.get(pos..pos + 2).ok_or_else(truncated)?;
+            let len = u16::from_le_bytes([len[0], len[1]]) as usize;
+            extra = bytes.get(pos + 2..pos + 2 + len).ok_or_else(truncated)?;
+            pos += 2 + len;
+        }
+        for flag in [FNAME, FCOMMENT] {
+            if flags & flag != 0 {
+                let end = bytes[pos.min(bytes.len())..]
+                    .iter()
+                    .position(|&byte| byte == 0)
+                    .ok_or_else(truncated)?;
+                pos += end + 1;
+            }
+        }
+        if flags & FHCRC != 0 {
+            pos += 2;
+        }
+        if pos > bytes.len() {
+            return Err(truncated());
+        }
+        Ok(Self {
+            bytes,
+            extra,
+            deflate_start: pos,
+        })
+    }
+
+    /// Chunk length and compressed chunk sizes from the `RA` extra field
+    /// dictzip adds, if present.
+    #[cfg_attr(not(feature = "stardict"), allow(dead_code))]
+    pub fn dictzip_chunks(&self) -> Result<Option<(usize, Vec<usize>)>> {
+        let invalid = || MDictError::InvalidFormat("invalid dictzip chunk table".to_string());
+        let mut rest = self.extra;
+        while rest.len() >= 4 {
+            let len = u16::from_le_bytes([rest[2], rest[3]]) as usize;
+            let data = rest.get(4..4 + len).ok_or_else(invalid)?;
+            if &rest[..2] == b"RA" {
+                let field = |index: usize| {
+                    data.get(index * 2..index * 2 + 2)
+                        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+                        .ok_or_else(invalid)
+                };
+                let (chunk_len, chunk_count) = (field(1)?, field(2)?);
+                if chunk_len == 0 {
+                    return Err(invalid());
+                }
+                let sizes = (0..chunk_count)
+                    .map(|chunk| field(3 + chunk))
+                    .collect::<Result<Vec<_>>>()?;
+                return Ok(Some((chunk_len, sizes)));
+            }
+            rest = &rest[4 + len..];
+        }
+        Ok(None)
+    }
+
+    pub fn inflate(&self) -> Result<Vec<u8>> {
+        miniz_oxide::inflate::decompress_to_vec(&self.bytes[self.deflate_start..])
+            .map_err(|err| MDictError::InvalidFormat(format!("invalid gzip data: {}", err)))
+    }
+}
@@ -0,0 +1,368 @@
+//! Full structural check of an opened dictionary, for triaging damaged files.
+//!
+//! [`Mdict::verify`] decodes every key and record block and cross-checks the
+//! indexes against each other. Problems are collected rather than returned as
+//! errors, so one bad block does not hide the rest. Damage that prevents
+//! opening at all (an unreadable header or key info block) is reported by the
+//! open call itself.
+
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::error::{MDictError, Result};
+use crate::format::compressed_block::checksum_mismatch;
+use crate::format::records::RecordIndex;
+use crate::format::{decode_format_block_sized, parse_key_block};
+use crate::Mdict;
+
+/// Where an [`IntegrityIssue`] was found. Displays the same way as the
+/// section prefix on decode errors (`key block 3`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegritySection {
+    Header,
+    KeySectionPreamble,
+    KeyInfo,
+    KeyBlock(usize),
+    RecordIndex,
+    RecordBlock(usize),
+}
+
+impl fmt::Display for IntegritySection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntegritySection::Header => f.write_str("header"),
+            IntegritySection::KeySectionPreamble => f.write_str("key section preamble"),
+            IntegritySection::KeyInfo => f.write_str("key info block"),
+            IntegritySection::KeyBlock(n) => write!(f, "key block {}", n),
+            IntegritySection::RecordIndex => f.write_str("record index"),
+            IntegritySection::RecordBlock(n) => write!(f, "record block {}", n),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegrityIssue {
+    pub section: IntegritySection,
+    /// File offset of the start of the section or block.
+    pub offset: u64,
+    pub message: String,
+}
+
+impl fmt::Display for IntegrityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (offset {}): {}",
+            self.section, self.offset, self.message
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct IntegrityReport {
+    pub issues: Vec<IntegrityIssue>,
+    pub key_blocks_checked: usize,
+    pub record_blocks_checked: usize,
+}
+
+impl IntegrityReport {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    fn push(&mut self, section: IntegritySection, offset: u64, message: impl Into<String>) {
+        self.issues.push(IntegrityIssue {
+            section,
+            offset,
+            message: message.into(),
+        });
+    }
+
+    fn push_error(&mut self, section: IntegritySection, offset: u64, error: MDictError) {
+        let message = match error {
+            MDictError::Io(m)
+            | MDictError::InvalidFormat(m)
+            | MDictError::InvalidArgument(m)
+            | MDictError::KeyNotFound(m)
+            | MDictError::UnsupportedFeature(m)
+            | MDictError::Cancelled(m)
+            | MDictError::FileChanged(m)
+            | MDictError::Corrupted { message: m, .. } => m,
+        };
+        self.push(section, offset, message);
+    }
+}
+
+impl<R: Read + Seek> Mdict<R> {
+    /// Decode every block and check checksums, block sizes, entry counts and
+    /// key order. Block caches are bypassed and left untouched.
+    pub fn verify(&mut self) -> IntegrityReport {
+        let mut report = IntegrityReport::default();
+        let file_size = self.reader.seek(SeekFrom::End(0)).ok();
+
+        self.verify_preamble(&mut report);
+        let key_ids = self.verify_key_blocks(&mut report);
+        self.verify_records(&mut report, file_size, &key_ids);
+        report
+    }
+
+    fn verify_preamble(&self, report: &mut IntegrityReport) {
+        let header = &self.key_block_index.header;
+        let key_section = &self.key_block_index.key_section;
+
+        if header.adler32_checksum != header.computed_checksum() {
+            report.push_error(
+                IntegritySection::Header,
+                0,
+                checksum_mismatch(header.adler32_checksum, header.computed_checksum()),
+            );
+        }
+        match key_section.computed_checksum {
+            Some(computed) if computed != key_section.addler32_checksum => report.push_error(
+                IntegritySection::KeySectionPreamble,
+                key_section.section_offset,
+                checksum_mismatch(key_section.addler32_checksum, computed),
+            ),
+            _ => {}
+        }
+
+        let offset = key_section.key_info_offset;
+        let blocks = &key_section.key_info_blocks;
+        if blocks.len() as u64 != key_section.num_blocks {
+            report.push(
+                IntegritySection::KeyInfo,
+                offset,
+                format!(
+                    "preamble declares {} key blocks, key info lists {}",
+                    key_section.num_blocks,
+                    blocks.len()
+                ),
+            );
+        }
+        let listed_entries = key_section.num_entries_prefix_sum.last().copied();
+        if listed_entries != Some(key_section.num_entries) {
+            report.push(
+                IntegritySection::KeyInfo,
+                offset,
+                format!(
+                    "preamble declares {} entries, key info lists {}",
+                    key_section.num_entries,
+                    listed_entries.unwrap_or(0)
+                ),
+            );
+        }
+        for (i, pair) in blocks.windows(2).enumerate() {
+            if self
+                .key_block_index
+                .compare_keys(&pair[1].first, &pair[0].last)
+                .is_lt()
+            {
+                report.push(
+                    IntegritySection::KeyInfo,
+                    offset,
+                    format!(
+                        "key block {} starts at '{}' before block {} ends at '{}'",
+                        i + 1,
+                        pair[1].first,
+                        i,
+                        pair[0].last
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Returns the key ids (record offsets) of every entry that decoded.
+    fn verify_key_blocks(&mut self, report: &mut IntegrityReport) -> Vec<u64> {
+        let index = &self.key_block_index;
+        let mut key_ids = Vec::new();
+        let mut previous_key: Option<String> = None;
+
+        for (i, info) in index.key_section.key_info_blocks.iter().enumerate() {
+            let section = IntegritySection::KeyBlock(i);
+            let offset = index.key_blocks_start + index.key_section.key_info_prefix_sum[i];
+            report.key_blocks_checked += 1;
+
+            let decoded = read_block(&mut self.reader, offset, info.compressed_size)
+                .and_then(|raw| decode_format_block_sized(&raw, info.decompressed_size as usize));
+            let decoded = match decoded {
+                Ok(decoded) => decoded,
+                Err(e) => {
+                    report.push_error(section, offset, e);
+                    previous_key = None;
+                    continue;
+                }
+            };
+            if decoded.len() as u64 != info.decompressed_size {
+                report.push(
+                    section,
+                    offset,
+                    format!(
+                        "decompressed to {} bytes, key info says {}",
+                        decoded.len(),
+                        info.decompressed_size
+                    ),
+                );
+            }
+
+            let entries = match parse_key_block(
+                &decoded,
+                index.header.get_encoding(),
+                index.header.get_version(),
+            ) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    report.push_error(section, offset, e);
+                    previous_key = None;
+                    continue;
+                }
+            };
+            if entries.len() as u64 != info.num_entries {
+                report.push(
+                    section,
+                    offset,
+                    format!(
+                        "holds {} entries, key info says {}",
+                        entries.len(),
+                        info.num_entries
+                    ),
+                );
+            }
+            if let (Some(first), Some(last)) = (entries.first(), entries.last()) {
+                if first.key_text != info.first || last.key_text != info.last {
+                    report.push(
+                        section,
+                        offset,
+                        format!(
+                            "spans '{}'..'{}', key info says '{}'..'{}'",
+                            first.key_text, last.key_text, info.first, info.last
+                        ),
+                    );
+                }
+            }
+
+            for entry in &entries {
+                if let Some(previous) = &previous_key {
+                    if index.compare_keys(&entry.key_text, previous).is_lt() {
+                        report.push(
+                            section,
+                            offset,
+                            format!("key '{}' sorts before '{}'", entry.key_text, previous),
+                        );
+                    }
+                }
+                if key_ids.last().is_some_and(|&id| entry.key_id < id) {
+                    report.push(
+                        section,
+                        offset,
+                        format!(
+                            "key '{}' points at record offset {}, before the previous key's",
+                            entry.key_text, entry.key_id
+                        ),
+                    );
+                }
+                previous_key = Some(entry.key_text.clone());
+                key_ids.push(entry.key_id);
+            }
+        }
+
+        key_ids
+    }
+
+    fn verify_records(
+        &mut self,
+        report: &mut IntegrityReport,
+        file_size: Option<u64>,
+        key_ids: &[u64],
+    ) {
+        let index_offset = self.key_block_index.key_section.next_section_offset;
+        let records = match self.record_section() {
+            Ok(records) => records,
+            Err(e) => {
+                report.push_error(IntegritySection::RecordIndex, index_offset, e);
+                return;
+            }
+        };
+        let blocks = &records.record_index_prefix_sum;
+        let totals = blocks.last().cloned().unwrap_or(RecordIndex {
+            compressed_size: 0,
+            uncompressed_size: 0,
+        });
+
+        if records.num_entries != self.key_block_index.key_section.num_entries {
+            report.push(
+                IntegritySection::RecordIndex,
+                index_offset,
+                format!(
+                    "record section declares {} entries, key section {}",
+                    records.num_entries, self.key_block_index.key_section.num_entries
+                ),
+            );
+        }
+        if totals.compressed_size != records.byte_size_record_data {
+            report.push(
+                IntegritySection::RecordIndex,
+                index_offset,
+                format!(
+                    "block sizes add up to {} bytes, record data is declared as {}",
+                    totals.compressed_size, records.byte_size_record_data
+                ),
+            );
+        }
+        if let Some(file_size) = file_size {
+            let data_end = records.record_data_offset + records.byte_size_record_data;
+            if data_end > file_size {
+                report.push(
+                    IntegritySection::RecordIndex,
+                    index_offset,
+                    format!(
+                        "record data ends at {}, past the end of the file ({} bytes)",
+                        data_end, file_size
+                    ),
+                );
+            }
+        }
+        if let Some(&last_id) = key_ids.last() {
+            if last_id >= totals.uncompressed_size {
+                report.push(
+                    IntegritySection::RecordIndex,
+                    index_offset,
+                    format!(
+                        "key points at record offset {}, past the {} bytes of record data",
+                        last_id, totals.uncompressed_size
+                    ),
+                );
+            }
+        }
+
+        for i in 0..blocks.len().saturating_sub(1) {
+            let offset = records.record_data_offset + blocks[i].compressed_size;
+            let expected = blocks[i + 1].uncompressed_size - blocks[i].uncompressed_size;
+            report.record_blocks_checked += 1;
+
+            let decoded = self
+                .read_compressed_record_block(i)
+                .and_then(|(raw, size)| decode_format_block_sized(&raw, size));
+            match decoded {
+                Ok(decoded) if decoded.len() as u64 != expected => report.push(
+                    IntegritySection::RecordBlock(i),
+                    offset,
+                    format!(
+                        "decompressed to {} bytes, record index says {}",
+                        decoded.len(),
+                        expected
+                    ),
+                ),
+                Ok(_) => {}
+                Err(e) => report.push_error(IntegritySection::RecordBlock(i), offset, e),
+            }
+        }
+    }
+}
+
+fn read_block(reader: &mut (impl Read + Seek), offset: u64, size: u64) -> Result<Vec<u8>> {
+    let mut buf = vec![0u8; size as usize];
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
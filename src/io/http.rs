@@ -0,0 +1,147 @@
+//! [`ByteSource`] over HTTP range requests (`http` feature), so a dictionary
+//! can be read from remote storage without downloading it first.
+
+use std::io as std_io;
+use std::sync::Mutex;
+
+use crate::block_cache::BlockCache;
+use crate::io::ByteSource;
+
+/// Small reads are served from cached `CHUNK_SIZE`-aligned ranges; larger ones
+/// (record blocks, mostly) are fetched exactly.
+const CHUNK_SIZE: u64 = 64 * 1024;
+const CACHED_CHUNKS: usize = 64;
+
+/// A remote file read with `Range: bytes=...` requests. The server must
+/// answer range requests with `206 Partial Content`.
+pub struct HttpRangeSource {
+    agent: ureq::Agent,
+    url: String,
+    size: u64,
+    chunks: Mutex<BlockCache<Vec<u8>>>,
+}
+
+impl HttpRangeSource {
+    /// Probe `url` for its size and range support.
+    pub fn open(url: impl Into<String>) -> std_io::Result<Self> {
+        let agent = ureq::Agent::new_with_defaults();
+        let url = url.into();
+        let response = agent
+            .get(&url)
+            .header("Range", "bytes=0-0")
+            .call()
+            .map_err(std_io::Error::other)?;
+        if response.status().as_u16() != 206 {
+            return Err(std_io::Error::new(
+                std_io::ErrorKind::Unsupported,
+                format!("{} does not support range requests", url),
+            ));
+        }
+        let size = response
+            .headers()
+            .get("content-range")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit_once('/'))
+            .and_then(|(_, total)| total.trim().parse::<u64>().ok())
+            .ok_or_else(|| {
+                std_io::Error::new(
+                    std_io::ErrorKind::InvalidData,
+                    format!("{} sent no usable Content-Range", url),
+                )
+            })?;
+
+        Ok(Self {
+            agent,
+            url,
+            size,
+            chunks: Mutex::new(BlockCache::new(CACHED_CHUNKS)),
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn fetch(&self, offset: u64, len: usize) -> std_io::Result<Vec<u8>> {
+        let last = offset + len as u64 - 1;
+        let mut response = self
+            .agent
+            .get(&self.url)
+            .header("Range", format!("bytes={}-{}", offset, last))
+            .call()
+            .map_err(std_io::Error::other)?;
+        if response.status().as_u16() != 206 {
+            return Err(std_io::Error::other(format!(
+                "range request to {} returned {}",
+                self.url,
+                response.status()
+            )));
+        }
+        let body = response
+            .body_mut()
+            .with_config()
+            // ureq rejects bodies that reach the limit, not just exceed it.
+            .limit(len as u64 + 1)
+            .read_to_vec()
+            .map_err(std_io::Error::other)?;
+        if body.len() != len {
+            return Err(std_io::Error::new(
+                std_io::ErrorKind::UnexpectedEof,
+                format!("short range response from {}", self.url),
+            ));
+        }
+        Ok(body)
+    }
+
+    fn copy_from_chunk(&self, chunk: u64, offset: u64, out: &mut [u8]) -> std_io::Result<()> {
+        let start = chunk * CHUNK_SIZE;
+        let from = (offset - start) as usize;
+        if let Some(bytes) = self.chunks.lock().unwrap().get(chunk as usize) {
+            out.copy_from_slice(&bytes[from..from + out.len()]);
+            return Ok(());
+        }
+
+        let len = CHUNK_SIZE.min(self.size - start) as usize;
+        let bytes = self.fetch(start, len)?;
+        out.copy_from_slice(&bytes[from..from + out.len()]);
+        self.chunks
+            .lock()
+            .unwrap()
+            .insert(chunk as usize, bytes, len);
+        Ok(())
+    }
+}
+
+impl ByteSource for HttpRangeSource {
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn read_exact_at(&self, offset: u64, buf: &mut [u8]) -> std_io::Result<()> {
+        let end = offset
+            .checked_add(buf.len() as u64)
+            .filter(|&end| end <= self.size)
+            .ok_or_else(|| {
+                std_io::Error::new(std_io::ErrorKind::UnexpectedEof, "read past end of source")
+            })?;
+        if buf.is_empty() {
+            return Ok(());
+        }
+        if buf.len() as u64 >= CHUNK_SIZE {
+            buf.copy_from_slice(&self.fetch(offset, buf.len())?);
+            return Ok(());
+        }
+
+        let mut pos = offset;
+        let mut filled = 0;
+        while pos < end {
+            let chunk = pos / CHUNK_SIZE;
+            let chunk_end = ((chunk + 1) * CHUNK_SIZE).min(end);
+            let n = (chunk_end - pos) as usize;
+            self.copy_from_chunk(chunk, pos, &mut buf[filled..filled + n])?;
+            pos += n as u64;
+            filled += n;
+        }
+        Ok(())
+    }
+}
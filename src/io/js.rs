@@ -0,0 +1,75 @@
+//! [`ByteSource`] backed by a JavaScript function (`wasm` feature), so a
+//! dictionary can be read in the browser without copying it into wasm memory.
+
+use std::io as std_io;
+
+use js_sys::{Function, Uint8Array};
+use wasm_bindgen::{JsCast, JsValue};
+
+use crate::io::ByteSource;
+
+/// A source of `size` bytes read through `read(offset, length)`, which must
+/// synchronously return a `Uint8Array` of exactly `length` bytes, e.g. by
+/// slicing a `File` with `FileReaderSync` in a worker or by a synchronous
+/// `XMLHttpRequest` with a `Range` header.
+pub struct JsRangeSource {
+    read: Function,
+    size: u64,
+}
+
+impl JsRangeSource {
+    pub fn new(size: u64, read: Function) -> Self {
+        Self { read, size }
+    }
+}
+
+fn js_io_error(value: JsValue) -> std_io::Error {
+    std_io::Error::other(format!("range read failed: {:?}", value))
+}
+
+impl ByteSource for JsRangeSource {
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn read_exact_at(&self, offset: u64, buf: &mut [u8]) -> std_io::Result<()> {
+        offset
+            .checked_add(buf.len() as u64)
+            .filter(|&end| end <= self.size)
+            .ok_or_else(|| {
+                std_io::Error::new(std_io::ErrorKind::UnexpectedEof, "read past end of source")
+            })?;
+        if buf.is_empty() {
+            return Ok(());
+        }
+
+        let bytes = self
+            .read
+            .call2(
+                &JsValue::NULL,
+                &JsValue::from_f64(offset as f64),
+                &JsValue::from_f64(buf.len() as f64),
+            )
+            .map_err(js_io_error)?
+            .dyn_into::<Uint8Array>()
+            .map_err(|_| {
+                std_io::Error::new(
+                    std_io::ErrorKind::InvalidData,
+                    "range reader did not return a Uint8Array",
+                )
+            })?;
+        if bytes.length() as usize != buf.len() {
+            return Err(std_io::Error::new(
+                std_io::ErrorKind::UnexpectedEof,
+                format!(
+                    "range reader returned {} of {} bytes at {}",
+                    bytes.length(),
+                    buf.len(),
+                    offset
+                ),
+            ));
+        }
+        bytes.copy_to(buf);
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+//! Random-access byte sources that an [`Mdict`](crate::Mdict) can be read from.
+//!
+//! A [`ByteSource`] only answers positional reads, so one source can back any
+//! number of readers. [`ByteSourceReader`] adds the cursor that the
+//! `Read + Seek` based parsers expect.
+
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(feature = "wasm")]
+pub mod js;
+
+#[cfg(feature = "fs")]
+use std::fs::File;
+use std::io::{self as std_io, Read, Seek, SeekFrom};
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::sync::Arc;
+
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
+
+pub trait ByteSource {
+    /// Total size in bytes.
+    fn size(&self) -> u64;
+
+    /// Fill `buf` with the bytes starting at `offset`. Fails with
+    /// `UnexpectedEof` if the source ends first.
+    fn read_exact_at(&self, offset: u64, buf: &mut [u8]) -> std_io::Result<()>;
+}
+
+fn unexpected_eof() -> std_io::Error {
+    std_io::Error::new(std_io::ErrorKind::UnexpectedEof, "read past end of source")
+}
+
+impl ByteSource for [u8] {
+    fn size(&self) -> u64 {
+        self.len() as u64
+    }
+
+    fn read_exact_at(&self, offset: u64, buf: &mut [u8]) -> std_io::Result<()> {
+        let start = usize::try_from(offset).map_err(|_| unexpected_eof())?;
+        let end = start.checked_add(buf.len()).ok_or_else(unexpected_eof)?;
+        let bytes = self.get(start..end).ok_or_else(unexpected_eof)?;
+        buf.copy_from_slice(bytes);
+        Ok(())
+    }
+}
+
+impl ByteSource for Vec<u8> {
+    fn size(&self) -> u64 {
+        self.as_slice().size()
+    }
+
+    fn read_exact_at(&self, offset: u64, buf: &mut [u8]) -> std_io::Result<()> {
+        self.as_slice().read_exact_at(offset, buf)
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl ByteSource for Mmap {
+    fn size(&self) -> u64 {
+        self[..].size()
+    }
+
+    fn read_exact_at(&self, offset: u64, buf: &mut [u8]) -> std_io::Result<()> {
+        self[..].read_exact_at(offset, buf)
+    }
+}
+
+/// A file read with positional reads. Its size is taken once, when it is
+/// wrapped, so a file that cannot be inspected fails there instead of
+/// reading as empty.
+#[cfg(all(feature = "fs", any(unix, windows)))]
+pub struct FileSource {
+    file: File,
+    size: u64,
+}
+
+#[cfg(all(feature = "fs", any(unix, windows)))]
+impl FileSource {
+    pub fn new(file: File) -> std_io::Result<Self> {
+        let size = file.metadata()?.len();
+        Ok(Self { file, size })
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> std_io::Result<Self> {
+        Self::new(File::open(path)?)
+    }
+}
+
+#[cfg(all(feature = "fs", any(unix, windows)))]
+impl ByteSource for FileSource {
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    #[cfg(unix)]
+    fn read_exact_at(&self, offset: u64, buf: &mut [u8]) -> std_io::Result<()> {
+        std::os::unix::fs::FileExt::read_exact_at(&self.file, buf, offset)
+    }
+
+    #[cfg(windows)]
+    fn read_exact_at(&self, mut offset: u64, mut buf: &mut [u8]) -> std_io::Result<()> {
+        while !buf.is_empty() {
+            match std::os::windows::fs::FileExt::seek_read(&self.file, buf, offset) {
+                Ok(0) => return Err(unexpected_eof()),
+                Ok(n) => {
+                    buf = &mut buf[n..];
+                    offset += n as u64;
+                }
+                Err(e) if e.kind() == std_io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: ByteSource + ?Sized> ByteSource for &T {
+    fn size(&self) -> u64 {
+        (**self).size()
+    }
+
+    fn read_exact_at(&self, offset: u64, buf: &mut [u8]) -> std_io::Result<()> {
+        (**self).read_exact_at(offset, buf)
+    }
+}
+
+impl<T: ByteSource + ?Sized> ByteSource for Arc<T> {
+    fn size(&self) -> u64 {
+        (**self).size()
+    }
+
+    fn read_exact_at(&self, offset: u64, buf: &mut [u8]) -> std_io::Result<()> {
+        (**self).read_exact_at(offset, buf)
+    }
+}
+
+/// `Read + Seek` cursor over a [`ByteSource`]. Cloning is as cheap as cloning
+/// the source and gives an independent cursor, which is what
+/// [`MdictShared`](crate::MdictShared) needs.
+#[derive(Debug, Clone)]
+pub struct ByteSourceReader<S: ByteSource> {
+    source: S,
+    pos: u64,
+}
+
+impl<S: ByteSource> ByteSourceReader<S> {
+    pub fn new(source: S) -> Self {
+        Self { source, pos: 0 }
+    }
+
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    pub fn into_source(self) -> S {
+        self.source
+    }
+}
+
+impl<S: ByteSource> Read for ByteSourceReader<S> {
+    fn read(&mut self, out: &mut [u8]) -> std_io::Result<usize> {
+        let remaining = self.source.size().saturating_sub(self.pos);
+        let n = out
+            .len()
+            .min(usize::try_from(remaining).unwrap_or(usize::MAX));
+        if n == 0 {
+            return Ok(0);
+        }
+        self.source.read_exact_at(self.pos, &mut out[..n])?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<S: ByteSource> Seek for ByteSourceReader<S> {
+    fn seek(&mut self, pos: SeekFrom) -> std_io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => offset as i128,
+            SeekFrom::End(offset) => self.source.size() as i128 + offset as i128,
+            SeekFrom::Current(offset) => self.pos as i128 + offset as i128,
+        };
+        if target < 0 {
+            return Err(std_io::Error::new(
+                std_io::ErrorKind::InvalidInput,
+                "seek before start of source",
+            ));
+        }
+        self.pos = target as u64;
+        Ok(self.pos)
+    }
+}
@@ -0,0 +1,60 @@
+use std::io::{Read, Seek};
+
+use crate::error::Result;
+use crate::types::KeyBlock;
+use crate::Mdict;
+
+/// Keys of a range of entries, in key order, returned by
+/// [`Mdict::keys_in_range`]. Only key blocks are decoded. Iteration stops
+/// after the first error.
+pub struct KeyRange<'a, R: Read + Seek> {
+    mdict: &'a mut Mdict<R>,
+    index: usize,
+    end: usize,
+}
+
+impl<'a, R: Read + Seek> KeyRange<'a, R> {
+    pub(crate) fn new(mdict: &'a mut Mdict<R>, start: usize, end: usize) -> Self {
+        Self {
+            mdict,
+            index: start,
+            end,
+        }
+    }
+
+    /// Index of the next entry, for resuming a listing later with
+    /// [`crate::random_access_key_blocks::KeyBlockIndex::get`].
+    pub fn position(&self) -> usize {
+        self.index
+    }
+}
+
+impl<R: Read + Seek> Iterator for KeyRange<'_, R> {
+    type Item = Result<KeyBlock>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.end {
+            return None;
+        }
+        let mdict = &mut *self.mdict;
+        match mdict.key_block_index.get(&mut mdict.reader, self.index) {
+            Ok(Some(key_block)) => {
+                self.index += 1;
+                Some(Ok(key_block))
+            }
+            Ok(None) => {
+                self.index = self.end;
+                None
+            }
+            Err(e) => {
+                self.index = self.end;
+                Some(Err(e))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.end.saturating_sub(self.index);
+        (0, Some(remaining))
+    }
+}
@@ -1,19 +1,75 @@
 uniffi::setup_scaffolding!();
 
+#[cfg(feature = "fs")]
+pub mod annotations;
+#[cfg(feature = "async")]
+pub mod async_mdict;
+pub mod audit;
+pub mod block_cache;
+pub mod collation;
+pub mod config;
+pub mod deinflect;
+pub mod dictionary;
+#[cfg(feature = "dsl")]
+pub mod dsl;
+pub mod entry_iter;
 pub mod format;
+pub mod glob;
+#[cfg(all(feature = "fs", any(feature = "stardict", feature = "dsl")))]
+mod gzip;
+pub mod integrity;
+pub mod io;
+pub mod key_range;
+#[cfg(feature = "mmap")]
+pub mod library;
+pub mod link_cache;
+pub mod links;
 pub mod mdict;
+pub mod metrics;
+pub mod mime;
 
+#[cfg(feature = "mmap")]
 pub mod seekable_mmap;
+pub mod stylesheet;
+pub mod suggest;
 
 pub mod error;
+#[cfg(feature = "fs")]
+pub mod file_stamp;
+#[cfg(feature = "mmap")]
 pub mod mdict_file;
+#[cfg(feature = "mmap")]
+pub mod mdict_group;
+#[cfg(feature = "mmap")]
 pub mod mdict_optimized;
+pub mod mdict_shared;
 pub mod mdx_conversion;
+#[cfg(feature = "fs")]
+pub mod mdx_editor;
+pub mod mdx_writer;
 pub mod packed_storage;
 pub mod prefix_key_block_index;
 pub mod random_access_key_blocks;
+pub mod record_ref;
+pub mod record_stream;
+pub mod render;
+#[cfg(feature = "stardict")]
+pub mod stardict;
+pub mod synth;
+pub mod transliterate;
 pub mod types;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
-pub use mdict::Mdict;
+pub use config::Config;
+pub use dictionary::Dictionary;
+pub use mdict::{Mdict, OpenOptions};
+#[cfg(feature = "mmap")]
 pub use mdict_file::MdictBundle;
+#[cfg(feature = "mmap")]
+pub use mdict_group::MdictGroupHandle;
+#[cfg(feature = "mmap")]
 pub use mdict_optimized::MdictOptimized;
+pub use mdict_shared::MdictShared;
+#[cfg(feature = "mmap")]
+pub use mdx_conversion::fst_map::FSTMap;
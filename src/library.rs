@@ -0,0 +1,329 @@
+//! A directory of dictionaries and the app's settings for them.
+//!
+//! [`Library::scan`] finds every `.mdx` under the library root (and the
+//! `.mdd` beside it with the same stem) and reads its header. What the app
+//! decides about each dictionary (whether it is enabled, where it sits in
+//! the list, which optimized bundle was built from it) is kept in a JSON
+//! registry, `library.json` in the root by default, and survives rescans.
+//! Files that did not change since the last scan are not reopened.
+
+use std::fs::{self, File};
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{MDictError, Result};
+use crate::file_stamp::FileStamp;
+use crate::mdict::{Mdict, OpenOptions};
+use crate::types::{LibraryEntry, OptimizedStatus};
+
+/// Registry file name used by [`open_library`].
+pub const REGISTRY_FILE_NAME: &str = "library.json";
+
+const REGISTRY_VERSION: u32 = 1;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Registry {
+    version: u32,
+    /// In the app's order.
+    dictionaries: Vec<RegistryEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RegistryEntry {
+    /// MDX path relative to the root, with `/` separators.
+    id: String,
+    /// MDD path relative to the root.
+    mdd: Option<String>,
+    title: Option<String>,
+    description: Option<String>,
+    entry_count: u64,
+    enabled: bool,
+    /// The MDX as of the last scan, to skip reopening unchanged files.
+    stamp: FileStamp,
+    optimized_bundle: Option<String>,
+    /// The MDX the optimized bundle was built from.
+    optimized_from: Option<FileStamp>,
+}
+
+/// The dictionaries under a root directory. Created by [`open_library`].
+#[derive(uniffi::Object)]
+pub struct Library {
+    root: PathBuf,
+    registry_path: PathBuf,
+    registry: Mutex<Registry>,
+}
+
+/// Open the library rooted at `root`, with its registry in `library.json`
+/// there. Nothing is scanned until [`Library::scan`].
+#[uniffi::export]
+pub fn open_library(root: String) -> Result<Library> {
+    let registry_path = Path::new(&root).join(REGISTRY_FILE_NAME);
+    Library::open(root, registry_path)
+}
+
+impl Library {
+    /// Open the library rooted at `root`, keeping its registry at
+    /// `registry_path`, e.g. in app storage when the root is read-only.
+    pub fn open(root: impl Into<PathBuf>, registry_path: impl Into<PathBuf>) -> Result<Self> {
+        let registry_path = registry_path.into();
+        let registry = match fs::read(&registry_path) {
+            Ok(bytes) => serde_json::from_slice::<Registry>(&bytes).map_err(|e| {
+                MDictError::InvalidFormat(format!("invalid library registry: {}", e))
+            })?,
+            Err(e) if e.kind() == ErrorKind::NotFound => Registry::default(),
+            Err(e) => return Err(e.into()),
+        };
+        if registry.version > REGISTRY_VERSION {
+            return Err(MDictError::UnsupportedFeature(format!(
+                "library registry version {} is newer than {}",
+                registry.version, REGISTRY_VERSION
+            )));
+        }
+        Ok(Self {
+            root: root.into(),
+            registry_path,
+            registry: Mutex::new(registry),
+        })
+    }
+
+    fn save(&self, registry: &Registry) -> Result<()> {
+        let json = serde_json::to_vec_pretty(registry)
+            .map_err(|e| MDictError::InvalidFormat(format!("library registry: {}", e)))?;
+        let mut partial = self.registry_path.as_os_str().to_owned();
+        partial.push(".partial");
+        let mut writer = BufWriter::new(File::create(&partial)?);
+        writer.write_all(&json)?;
+        writer.flush()?;
+        drop(writer);
+        fs::rename(partial, &self.registry_path)?;
+        Ok(())
+    }
+
+    /// Apply `f` to the registry entry `id` and save the registry.
+    fn update(&self, id: &str, f: impl FnOnce(&mut Registry, usize) -> Result<()>) -> Result<()> {
+        let mut registry = self.registry.lock().unwrap();
+        let index = registry
+            .dictionaries
+            .iter()
+            .position(|entry| entry.id == id)
+            .ok_or_else(|| {
+                MDictError::KeyNotFound(format!("no dictionary {:?} in the library", id))
+            })?;
+        f(&mut registry, index)?;
+        self.save(&registry)
+    }
+
+    fn describe(&self, entry: &RegistryEntry) -> LibraryEntry {
+        let mdx_path = self.root.join(&entry.id);
+        let optimized_status = match (&entry.optimized_bundle, entry.optimized_from) {
+            (None, _) => OptimizedStatus::Missing,
+            (Some(bundle), Some(built_from))
+                if Path::new(bundle).exists()
+                    && FileStamp::of(&mdx_path).ok() == Some(built_from) =>
+            {
+                OptimizedStatus::Current
+            }
+            (Some(_), _) => OptimizedStatus::Stale,
+        };
+        LibraryEntry {
+            id: entry.id.clone(),
+            mdx_path: mdx_path.to_string_lossy().into_owned(),
+            mdd_path: entry
+                .mdd
+                .as_ref()
+                .map(|mdd| self.root.join(mdd).to_string_lossy().into_owned()),
+            title: entry.title.clone(),
+            description: entry.description.clone(),
+            entry_count: entry.entry_count,
+            enabled: entry.enabled,
+            optimized_bundle_path: entry.optimized_bundle.clone(),
+            optimized_status,
+        }
+    }
+}
+
+#[uniffi::export]
+impl Library {
+    pub fn root(&self) -> String {
+        self.root.to_string_lossy().into_owned()
+    }
+
+    /// Bring the registry up to date with the files under the root and
+    /// return the dictionaries in order. New dictionaries are added enabled
+    /// at the end, in path order; removed ones are dropped. Files that fail
+    /// to open are logged and left out.
+    pub fn scan(&self) -> Result<Vec<LibraryEntry>> {
+        let mut found = Vec::new();
+        find_mdx_files(&self.root, &mut found)?;
+        found.sort();
+
+        let mut registry = self.registry.lock().unwrap();
+        let mut kept = Vec::with_capacity(found.len());
+        for previous in registry.dictionaries.drain(..) {
+            let at = found
+                .iter()
+                .position(|path| relative_id(&self.root, path) == previous.id);
+            if let Some(at) = at {
+                let path = found.remove(at);
+                if let Some(entry) = scanned(&self.root, &path, Some(previous)) {
+                    kept.push(entry);
+                }
+            }
+        }
+        for path in found {
+            if let Some(entry) = scanned(&self.root, &path, None) {
+                kept.push(entry);
+            }
+        }
+        registry.version = REGISTRY_VERSION;
+        registry.dictionaries = kept;
+        self.save(&registry)?;
+        Ok(registry
+            .dictionaries
+            .iter()
+            .map(|entry| self.describe(entry))
+            .collect())
+    }
+
+    /// The dictionaries as of the last scan, in order.
+    pub fn entries(&self) -> Vec<LibraryEntry> {
+        let registry = self.registry.lock().unwrap();
+        registry
+            .dictionaries
+            .iter()
+            .map(|entry| self.describe(entry))
+            .collect()
+    }
+
+    /// The enabled dictionaries, in order.
+    pub fn enabled_entries(&self) -> Vec<LibraryEntry> {
+        self.entries()
+            .into_iter()
+            .filter(|entry| entry.enabled)
+            .collect()
+    }
+
+    pub fn set_enabled(&self, id: String, enabled: bool) -> Result<()> {
+        self.update(&id, |registry, index| {
+            registry.dictionaries[index].enabled = enabled;
+            Ok(())
+        })
+    }
+
+    /// Move dictionary `id` to `position` in the order, clamped to the end.
+    pub fn move_to(&self, id: String, position: u32) -> Result<()> {
+        self.update(&id, |registry, index| {
+            let entry = registry.dictionaries.remove(index);
+            let position = (position as usize).min(registry.dictionaries.len());
+            registry.dictionaries.insert(position, entry);
+            Ok(())
+        })
+    }
+
+    /// Record that the optimized bundle at `bundle_path` was built from
+    /// dictionary `id` as it is now, or forget its bundle with `None`.
+    pub fn set_optimized_bundle(&self, id: String, bundle_path: Option<String>) -> Result<()> {
+        let stamp = FileStamp::of(self.root.join(&id))?;
+        self.update(&id, |registry, index| {
+            let entry = &mut registry.dictionaries[index];
+            entry.optimized_from = bundle_path.as_ref().map(|_| stamp);
+            entry.optimized_bundle = bundle_path;
+            Ok(())
+        })
+    }
+}
+
+/// Every `.mdx` file under `dir`, at any depth.
+fn find_mdx_files(dir: &Path, found: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            find_mdx_files(&path, found)?;
+        } else if has_extension(&path, "mdx") {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+}
+
+fn relative_id(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The registry entry for the MDX at `path`: `previous` with its paired MDD
+/// refreshed, reread from the header only if the file changed.
+fn scanned(root: &Path, path: &Path, previous: Option<RegistryEntry>) -> Option<RegistryEntry> {
+    let stamp = match FileStamp::of(path) {
+        Ok(stamp) => stamp,
+        Err(e) => {
+            log::warn!("Skipping {:?}: {}", path, e);
+            return None;
+        }
+    };
+    let mdd = paired_mdd(path).map(|mdd| relative_id(root, &mdd));
+    if let Some(previous) = previous.as_ref().filter(|previous| previous.stamp == stamp) {
+        return Some(RegistryEntry {
+            mdd,
+            ..previous.clone()
+        });
+    }
+
+    let opened = File::open(path).map_err(MDictError::from).and_then(|file| {
+        let options = OpenOptions {
+            lazy: true,
+            ..OpenOptions::default()
+        };
+        Mdict::new_with_options(file, options)
+    });
+    let mdict = match opened {
+        Ok(mdict) => mdict,
+        Err(e) => {
+            log::warn!("Skipping {:?}: {}", path, e);
+            return None;
+        }
+    };
+    let metadata = mdict.metadata();
+    let (enabled, optimized_bundle, optimized_from) = match previous {
+        Some(previous) => (
+            previous.enabled,
+            previous.optimized_bundle,
+            previous.optimized_from,
+        ),
+        None => (true, None, None),
+    };
+    Some(RegistryEntry {
+        id: relative_id(root, path),
+        mdd,
+        title: metadata.title,
+        description: metadata.description,
+        entry_count: mdict.key_block_index.key_section.num_entries,
+        enabled,
+        stamp,
+        optimized_bundle,
+        optimized_from,
+    })
+}
+
+/// The `.mdd` beside `mdx` with the same stem, in any case.
+fn paired_mdd(mdx: &Path) -> Option<PathBuf> {
+    let stem = mdx.file_stem()?;
+    let dir = mdx.parent()?;
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .find(|path| has_extension(path, "mdd") && path.file_stem() == Some(stem))
+}
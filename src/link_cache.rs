@@ -0,0 +1,97 @@
+//! Remembered `@@@LINK=` resolutions, so a redirect costs one lookup.
+
+use std::collections::{BTreeMap, HashMap};
+
+struct CachedLink {
+    index: usize,
+    hops: u32,
+    last_used: u64,
+}
+
+/// Least-recently-used map from a redirecting key's text to the entry its
+/// link chain ends at, as an index into the key list.
+pub struct LinkCache {
+    capacity: usize,
+    links: HashMap<String, CachedLink>,
+    /// `last_used` tick -> key text, oldest first.
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl LinkCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            links: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Change the capacity, evicting links that no longer fit.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict_to_fit();
+    }
+
+    pub fn len(&self) -> usize {
+        self.links.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty()
+    }
+
+    /// Resolved entry index for `key_text` and the number of hops it took,
+    /// marking it most recently used.
+    pub fn get(&mut self, key_text: &str) -> Option<(usize, u32)> {
+        let tick = self.next_tick();
+        let cached = self.links.get_mut(key_text)?;
+        self.recency.remove(&cached.last_used);
+        self.recency.insert(tick, key_text.to_string());
+        cached.last_used = tick;
+        Some((cached.index, cached.hops))
+    }
+
+    pub fn insert(&mut self, key_text: String, index: usize, hops: u32) {
+        if self.capacity == 0 {
+            return;
+        }
+        let tick = self.next_tick();
+        if let Some(old) = self.links.insert(
+            key_text.clone(),
+            CachedLink {
+                index,
+                hops,
+                last_used: tick,
+            },
+        ) {
+            self.recency.remove(&old.last_used);
+        }
+        self.recency.insert(tick, key_text);
+        self.evict_to_fit();
+    }
+
+    pub fn clear(&mut self) {
+        self.links.clear();
+        self.recency.clear();
+    }
+
+    fn evict_to_fit(&mut self) {
+        while self.links.len() > self.capacity {
+            let Some((_, key_text)) = self.recency.pop_first() else {
+                break;
+            };
+            self.links.remove(&key_text);
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
@@ -0,0 +1,302 @@
+//! Links between the entries of a dictionary.
+//!
+//! Entries refer to one another in two ways: a record that is just
+//! `@@@LINK=word` redirects to `word`, and an HTML record can link to
+//! `entry://word`. [`link_graph`] walks every record and collects both
+//! into a [`LinkGraph`], with each target resolved to the entry it names,
+//! for looking at a dictionary's redirect structure and finding links to
+//! headwords it does not have. The graph can be written as JSON or GraphML
+//! for other tools.
+
+use std::collections::HashMap;
+use std::io::{Read, Seek, Write};
+
+use serde::Serialize;
+
+use crate::error::{MDictError, Result};
+use crate::mdict::Mdict;
+use crate::render::{push_escaped, rewrite_links, LinkKind};
+
+pub const LINK_PREFIX: &str = "@@@LINK=";
+
+/// Return the target of an `@@@LINK=` redirect record. The target runs to the
+/// end of the line, so multi-word headwords are kept intact.
+pub fn extract_link(str: &str) -> Option<&str> {
+    let remainder = str.strip_prefix(LINK_PREFIX)?;
+    let end = remainder
+        .find(['\r', '\n', '\0'])
+        .unwrap_or(remainder.len());
+    let link = remainder[..end].trim();
+    if link.is_empty() {
+        return None;
+    }
+    Some(link)
+}
+
+/// Link target of a raw record in either UTF-8 or UTF-16LE, detected from
+/// how the `@@@LINK=` marker itself is encoded.
+pub fn link_target_from_record(record: &[u8]) -> Option<String> {
+    let utf16_prefix = LINK_PREFIX
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect::<Vec<_>>();
+    let text = if record.starts_with(&utf16_prefix) {
+        let units = record
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect::<Vec<_>>();
+        String::from_utf16_lossy(&units)
+    } else if record.starts_with(LINK_PREFIX.as_bytes()) {
+        String::from_utf8_lossy(record).into_owned()
+    } else {
+        return None;
+    };
+    extract_link(&text).map(str::to_string)
+}
+
+/// The headwords an HTML record links to with `entry://`, each once, in the
+/// order they first appear. Fragments and query strings are dropped and
+/// `%XX` escapes decoded; links to an anchor in the same record
+/// (`entry://#top`) are skipped.
+pub fn extract_entry_links(html: &str) -> Vec<String> {
+    let mut targets = Vec::new();
+    rewrite_links(html, |kind, target| {
+        if kind == LinkKind::Entry {
+            let target = target.split(['?', '#']).next().unwrap_or_default();
+            let target = percent_decode(target.trim());
+            if !target.is_empty() && !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+        None
+    });
+    targets
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkType {
+    /// The whole record is `@@@LINK=target`.
+    Redirect,
+    /// An `entry://target` link in the record's HTML.
+    Entry,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LinkNode {
+    pub key_text: String,
+    pub key_id: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LinkEdge {
+    /// Index of the linking entry in [`LinkGraph::nodes`].
+    pub from: usize,
+    /// The headword linked to, as written in the record.
+    pub target: String,
+    /// Index of the first entry with key `target`, or `None` if the
+    /// dictionary has no such key.
+    pub to: Option<usize>,
+    pub link_type: LinkType,
+}
+
+/// Every entry of a dictionary and the links between them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LinkGraph {
+    /// One node per entry, in key order, so a node's index is its entry
+    /// index.
+    pub nodes: Vec<LinkNode>,
+    /// Links in the order of the entries they come from.
+    pub edges: Vec<LinkEdge>,
+}
+
+impl LinkGraph {
+    /// Links whose target is not a key in the dictionary.
+    pub fn broken_links(&self) -> impl Iterator<Item = &LinkEdge> {
+        self.edges.iter().filter(|edge| edge.to.is_none())
+    }
+
+    /// The links of entry `node`.
+    pub fn links_from(&self, node: usize) -> impl Iterator<Item = &LinkEdge> {
+        self.edges.iter().filter(move |edge| edge.from == node)
+    }
+
+    /// The links to entry `node`.
+    pub fn links_to(&self, node: usize) -> impl Iterator<Item = &LinkEdge> {
+        self.edges.iter().filter(move |edge| edge.to == Some(node))
+    }
+
+    /// The entries reached by following redirects from `node`, in order. The
+    /// chain ends at an entry that is not a redirect, at a broken redirect or
+    /// before an entry it has already visited, so redirect loops end too.
+    pub fn redirect_chain(&self, node: usize) -> Vec<usize> {
+        let redirects = self
+            .edges
+            .iter()
+            .filter(|edge| edge.link_type == LinkType::Redirect)
+            .map(|edge| (edge.from, edge.to))
+            .collect::<HashMap<_, _>>();
+        let mut chain = Vec::new();
+        let mut current = node;
+        while let Some(&Some(next)) = redirects.get(&current) {
+            if next == node || chain.contains(&next) {
+                break;
+            }
+            chain.push(next);
+            current = next;
+        }
+        chain
+    }
+
+    /// Write the graph as a JSON object with `nodes` and `edges` arrays
+    /// shaped like [`LinkNode`] and [`LinkEdge`].
+    pub fn write_json<W: Write>(&self, writer: W) -> Result<()> {
+        serde_json::to_writer_pretty(writer, self)
+            .map_err(|e| MDictError::InvalidFormat(format!("link graph: {}", e)))
+    }
+
+    /// Write the graph as a directed GraphML document. Entries are nodes
+    /// `n<index>` with `key_text` and `key_id` data; each distinct broken
+    /// target gets a node `missing<i>` with `missing` set, so every edge has
+    /// both ends. Edges carry their `link_type`.
+    pub fn write_graphml<W: Write>(&self, mut writer: W) -> Result<()> {
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        for (name, target, kind) in [
+            ("key_text", "node", "string"),
+            ("key_id", "node", "long"),
+            ("missing", "node", "boolean"),
+            ("link_type", "edge", "string"),
+        ] {
+            writeln!(
+                writer,
+                r#"  <key id="{0}" for="{1}" attr.name="{0}" attr.type="{2}"/>"#,
+                name, target, kind
+            )?;
+        }
+        writeln!(writer, r#"  <graph id="links" edgedefault="directed">"#)?;
+
+        for (index, node) in self.nodes.iter().enumerate() {
+            write!(writer, r#"    <node id="n{}">"#, index)?;
+            write_data(&mut writer, "key_text", &node.key_text)?;
+            write_data(&mut writer, "key_id", &node.key_id.to_string())?;
+            writeln!(writer, "</node>")?;
+        }
+        let mut missing = HashMap::new();
+        for edge in self.broken_links() {
+            let next = missing.len();
+            if *missing.entry(edge.target.as_str()).or_insert(next) == next {
+                write!(writer, r#"    <node id="missing{}">"#, next)?;
+                write_data(&mut writer, "key_text", &edge.target)?;
+                write_data(&mut writer, "missing", "true")?;
+                writeln!(writer, "</node>")?;
+            }
+        }
+
+        for edge in &self.edges {
+            let to = match edge.to {
+                Some(to) => format!("n{}", to),
+                None => format!("missing{}", missing[edge.target.as_str()]),
+            };
+            let link_type = match edge.link_type {
+                LinkType::Redirect => "redirect",
+                LinkType::Entry => "entry",
+            };
+            write!(
+                writer,
+                r#"    <edge source="n{}" target="{}">"#,
+                edge.from, to
+            )?;
+            write_data(&mut writer, "link_type", link_type)?;
+            writeln!(writer, "</edge>")?;
+        }
+
+        writeln!(writer, "  </graph>")?;
+        writeln!(writer, "</graphml>")?;
+        Ok(())
+    }
+}
+
+fn write_data<W: Write>(writer: &mut W, key: &str, value: &str) -> Result<()> {
+    let mut escaped = String::with_capacity(value.len());
+    push_escaped(&mut escaped, value);
+    write!(writer, r#"<data key="{}">{}</data>"#, key, escaped)?;
+    Ok(())
+}
+
+/// Walk every record of `mdict` and collect its `@@@LINK=` redirects and
+/// `entry://` links. Each distinct target is looked up once.
+pub fn link_graph<R: Read + Seek>(mdict: &mut Mdict<R>) -> Result<LinkGraph> {
+    let encoding = mdict.key_block_index.header.get_encoding();
+    let mut graph = LinkGraph::default();
+    let mut links = Vec::new();
+
+    for (index, entry) in mdict.iter_entries().parallel(true).enumerate() {
+        let (key_block, record) = entry?;
+        let text = encoding.decode(&record);
+        if let Some(target) = extract_link(&text) {
+            links.push((index, target.to_string(), LinkType::Redirect));
+        } else {
+            for target in extract_entry_links(&text) {
+                links.push((index, target, LinkType::Entry));
+            }
+        }
+        graph.nodes.push(LinkNode {
+            key_text: key_block.key_text,
+            key_id: key_block.key_id,
+        });
+    }
+
+    let mut resolved = HashMap::<String, Option<usize>>::new();
+    for (from, target, link_type) in links {
+        let to = match resolved.get(&target) {
+            Some(&to) => to,
+            None => {
+                let to = mdict
+                    .key_block_index
+                    .index_for(&mut mdict.reader, &target)?;
+                resolved.insert(target.clone(), to);
+                to
+            }
+        };
+        graph.edges.push(LinkEdge {
+            from,
+            target,
+            to,
+            link_type,
+        });
+    }
+
+    Ok(graph)
+}
+
+/// Decode `%XX` escapes, as WebViews send non-ASCII paths. Invalid escapes and
+/// invalid UTF-8 are kept as they are.
+pub(crate) fn percent_decode(value: &str) -> String {
+    if !value.contains('%') {
+        return value.to_string();
+    }
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| value.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).unwrap_or_else(|_| value.to_string())
+}
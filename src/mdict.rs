@@ -1,50 +1,216 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "fs")]
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom};
 use std::iter::Map;
+use std::ops::{Range, RangeBounds};
 use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
 
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
+#[cfg(feature = "threads")]
+use rayon::prelude::*;
+
+use crate::block_cache::{BlockCache, CacheCapacity};
+use crate::entry_iter::EntryIter;
 use crate::error::{MDictError, Result};
-use crate::format::{HeaderInfo, KeySection, RecordSection};
+#[cfg(feature = "fs")]
+use crate::format::key_index_cache::key_cache_path;
+use crate::format::{HeaderInfo, KeySection, RecordSection, SectionLayout};
+use crate::glob::GlobPattern;
+use crate::io::{ByteSource, ByteSourceReader};
+use crate::key_range::KeyRange;
+use crate::link_cache::LinkCache;
+use crate::links::link_target_from_record;
+use crate::mdx_conversion::aliases::KeyAliases;
+use crate::mdx_conversion::reindexing::ReadingsListMap;
+use crate::metrics::{self, BlockKind, Span};
 use crate::prefix_key_block_index::PrefixKeyBlockIndex;
 use crate::random_access_key_blocks::KeyBlockIndex;
-use crate::types::{KeyBlock, MdictVersion};
+use crate::record_ref::RecordRef;
+use crate::render::{html_to_plaintext, render_record, RenderOptions};
+#[cfg(feature = "mmap")]
+use crate::seekable_mmap::{MmapSection, SeekableMmap};
+use crate::types::{DictionaryMetadata, KeyBlock, RecordKind};
+
+/// Options for [`Mdict::new_with_options`] and [`Mdict::open_with_options`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OpenOptions {
+    /// Also check the header and key-section preamble checksums while
+    /// opening. Key-info, key block and record block checksums are always
+    /// checked when those blocks are decoded.
+    pub verify_checksums: bool,
+    /// Block cache capacity; `None` uses the configured default.
+    pub cache_capacity: Option<CacheCapacity>,
+    /// Key block cache capacity; `None` uses `cache_capacity` when that is
+    /// set, or else the configured default.
+    pub key_cache_capacity: Option<CacheCapacity>,
+    /// Open lazily: parse the record index when the first record is read
+    /// rather than while opening, and never preload keys. Suits opening many
+    /// dictionaries up front that may not all be read from. The key section
+    /// is still read, as every lookup needs it; the `key_section_cache`
+    /// sidecar saves parsing it. `lazy_open` in the config turns this on for
+    /// every open.
+    pub lazy: bool,
+}
 
 pub struct Mdict<R: Read + Seek> {
     pub reader: R,
-    pub record_section: RecordSection,
     pub key_block_index: KeyBlockIndex,
 
-    max_record_blocks_to_cache: usize,
-    cached_record_blocks: HashMap<usize, Vec<u8>>,
+    /// Parsed while opening, or by [`Mdict::record_section`] when opened
+    /// lazily. Shared by every handle made with [`Mdict::with_reader`].
+    record_section: Arc<OnceLock<Arc<RecordSection>>>,
+    /// The bytes behind `reader` again, read by offset: record blocks are
+    /// then read through it without moving the reader.
+    positional: Option<Arc<dyn ByteSource + Send + Sync>>,
+
+    record_cache: BlockCache<Arc<Vec<u8>>>,
+    /// Shared by every handle made with [`Mdict::with_reader`].
+    link_cache: Arc<Mutex<LinkCache>>,
+    /// Shared by every handle made with [`Mdict::with_reader`].
+    aliases: Arc<Mutex<KeyAliases>>,
 }
 
 impl<R: Read + Seek> Mdict<R> {
     pub fn new(reader: R) -> Result<Self> {
-        Self::new_with_cache(reader, 0)
+        Self::new_with_options(reader, OpenOptions::default())
+    }
+
+    /// Open with decoded key and record blocks cached up to `capacity` each
+    /// (a block count, or a byte budget via [`CacheCapacity::Bytes`]).
+    pub fn new_with_cache(reader: R, capacity: impl Into<CacheCapacity>) -> Result<Self> {
+        Self::new_with_options(
+            reader,
+            OpenOptions {
+                cache_capacity: Some(capacity.into()),
+                ..OpenOptions::default()
+            },
+        )
     }
 
-    pub fn new_with_cache(mut reader: R, max_record_blocks_to_cache: usize) -> Result<Self> {
+    /// Open with explicit [`OpenOptions`]. With `verify_checksums`, a
+    /// corrupted header or key-section preamble fails here with an error
+    /// naming the section.
+    pub fn new_with_options(reader: R, options: OpenOptions) -> Result<Self> {
+        Self::open_parts(reader, options, None)
+    }
+
+    /// [`Self::new_with_options`], keeping the parsed key section in the
+    /// sidecar at `cache_path` (see [`key_cache_path`]) so later opens of
+    /// the unchanged file skip parsing it.
+    #[cfg(feature = "fs")]
+    pub fn new_with_key_cache(
+        reader: R,
+        options: OpenOptions,
+        cache_path: impl AsRef<Path>,
+    ) -> Result<Self> {
+        Self::open_parts(reader, options, Some(cache_path.as_ref()))
+    }
+
+    fn open_parts(mut reader: R, options: OpenOptions, cache_path: Option<&Path>) -> Result<Self> {
+        let capacity = options
+            .cache_capacity
+            .unwrap_or_else(|| crate::config::config().record_block_cache_capacity());
+        let key_capacity = options
+            .key_cache_capacity
+            .or(options.cache_capacity)
+            .unwrap_or_else(|| crate::config::config().key_block_cache_capacity());
+        let lazy = options.lazy || crate::config::config().lazy_open;
         let header = HeaderInfo::read_from(&mut reader)?;
-        let key_section = KeySection::read_from(&mut reader, &header)?;
-        let record_section = RecordSection::parse(&header, &key_section, &mut reader)?;
+        if options.verify_checksums {
+            header.verify_checksum()?;
+        }
+        let key_section = match cache_path {
+            #[cfg(feature = "fs")]
+            Some(cache_path) => KeySection::read_cached(&mut reader, &header, cache_path)?,
+            _ => KeySection::read_from(&mut reader, &header)?,
+        };
+        if options.verify_checksums {
+            key_section.verify_checksum()?;
+        }
+        let record_section = if lazy {
+            OnceLock::new()
+        } else {
+            let parsed = RecordSection::parse(&header, &key_section, &mut reader)?;
+            OnceLock::from(Arc::new(parsed))
+        };
 
-        let key_block_index = KeyBlockIndex::new(header, key_section)?;
+        let mut key_block_index =
+            KeyBlockIndex::new_with_cache(header, key_section, key_capacity)?;
+        let preload_max_bytes = crate::config::config().key_preload_max_bytes;
+        if !lazy
+            && preload_max_bytes > 0
+            && key_block_index.decompressed_size() <= preload_max_bytes
+        {
+            key_block_index.preload(&mut reader)?;
+        }
 
         Ok(Self {
             reader,
-            record_section,
             key_block_index,
+            record_section: Arc::new(record_section),
+            positional: None,
 
-            max_record_blocks_to_cache,
-            cached_record_blocks: HashMap::new(),
+            record_cache: BlockCache::new(capacity),
+            link_cache: Arc::new(Mutex::new(LinkCache::new(
+                crate::config::config().link_cache_size(),
+            ))),
+            aliases: Arc::new(Mutex::new(KeyAliases::new())),
         })
     }
 
-    /// Open a file at `path` and construct an `Mdict<File>`.
-    pub fn open<P: AsRef<Path>>(path: P) -> Result<Mdict<File>> {
-        let f = File::open(path).map_err(MDictError::from)?;
-        Mdict::new(f)
+    /// Another handle on the same dictionary that reads through `reader`.
+    /// Parsed sections are shared; block caches start empty with the same
+    /// capacities.
+    pub fn with_reader<R2: Read + Seek>(&self, reader: R2) -> Mdict<R2> {
+        Mdict {
+            reader,
+            key_block_index: self.key_block_index.share(),
+            record_section: Arc::clone(&self.record_section),
+            positional: self.positional.clone(),
+
+            record_cache: BlockCache::new(self.record_cache.capacity()),
+            link_cache: Arc::clone(&self.link_cache),
+            aliases: Arc::clone(&self.aliases),
+        }
+    }
+
+    /// The record index, parsed now if the dictionary was opened lazily and
+    /// no handle has read a record yet.
+    pub fn record_section(&mut self) -> Result<Arc<RecordSection>> {
+        if let Some(section) = self.record_section.get() {
+            return Ok(Arc::clone(section));
+        }
+        let section = RecordSection::parse(
+            &self.key_block_index.header,
+            &self.key_block_index.key_section,
+            &mut self.reader,
+        )?;
+        Ok(Arc::clone(self.record_section.get_or_init(|| Arc::new(section))))
+    }
+
+    /// Byte ranges of the file's sections, e.g. to patch or append to it.
+    /// Parses the record index if it has not been yet.
+    pub fn section_layout(&mut self) -> Result<SectionLayout> {
+        let records = self.record_section()?;
+        Ok(SectionLayout::new(
+            &self.key_block_index.header,
+            &self.key_block_index.key_section,
+            &records,
+        ))
+    }
+
+    /// Whether the record index has been parsed; always true unless opened
+    /// with [`OpenOptions::lazy`].
+    pub fn is_record_section_parsed(&self) -> bool {
+        self.record_section.get().is_some()
+    }
+
+    /// Typed header attributes (title, encoding, stylesheet, ...).
+    pub fn metadata(&self) -> DictionaryMetadata {
+        self.key_block_index.header.metadata()
     }
 
     /// Search for keys that start with `prefix`. Returns up to `max` results.
@@ -55,99 +221,769 @@ impl<R: Read + Seek> Mdict<R> {
         PrefixKeyBlockIndex::new(self, prefix)
     }
 
+    /// Keys within `bounds` in key order, e.g. `mdict.keys_in_range("か"..="き")`.
+    /// Bounds compare under the dictionary's collation, like lookups; see
+    /// [`KeyBlockIndex::range_indices`].
+    pub fn keys_in_range<'k>(
+        &mut self,
+        bounds: impl RangeBounds<&'k str>,
+    ) -> Result<KeyRange<'_, R>> {
+        let (start, end) = self.key_block_index.range_indices(&mut self.reader, bounds)?;
+        Ok(KeyRange::new(self, start, end))
+    }
+
+    /// Decode every key block now and keep them in memory, so key lookups
+    /// and searches on this handle and those made from it with
+    /// [`Self::with_reader`] never decode a key block again. The decoded keys
+    /// take about [`KeyBlockIndex::decompressed_size`] bytes; dictionaries
+    /// under `key_preload_max_bytes` in the [`crate::Config`] are preloaded
+    /// when opened.
+    pub fn preload_keys(&mut self) -> Result<()> {
+        self.key_block_index.preload(&mut self.reader)
+    }
+
+    /// Walk every entry in key order. Key and record blocks are each decoded
+    /// once, so a full dump costs a single pass over the file.
+    pub fn iter_entries(&mut self) -> EntryIter<'_, R> {
+        EntryIter::new(self)
+    }
+
+    /// Keys within `max_distance` edits of `query`, in key order.
+    ///
+    /// The legacy format has no automaton-friendly index, so this scans every
+    /// key; prefer `MdictOptimized::search_keys_fuzzy` for large dictionaries.
+    pub fn search_keys_fuzzy(&mut self, query: &str, max_distance: u32) -> Result<Vec<KeyBlock>> {
+        let query = query.chars().collect::<Vec<_>>();
+        let total_entries = self.key_block_index.key_section.num_entries as usize;
+        let mut out = Vec::new();
+
+        for i in 0..total_entries {
+            let Some(key_block) = self.key_block_index.get(&mut self.reader, i)? else {
+                break;
+            };
+            if edit_distance_within(&query, &key_block.key_text, max_distance as usize) {
+                out.push(key_block);
+            }
+        }
+
+        Ok(out)
+    }
+
+    /// Keys matching a `*` / `?` glob (e.g. `食*る`), in key order. Only the
+    /// blocks covering the literal prefix before the first wildcard are scanned.
+    pub fn search_keys_glob(&mut self, pattern: &str) -> Result<Vec<KeyBlock>> {
+        let glob = GlobPattern::new(pattern)?;
+        let (start, end) = if glob.literal_prefix().is_empty() {
+            (0, self.key_block_index.key_section.num_entries as usize)
+        } else {
+            match self
+                .key_block_index
+                .prefix_range_bounds(&mut self.reader, glob.literal_prefix())?
+            {
+                Some(bounds) => bounds,
+                None => return Ok(Vec::new()),
+            }
+        };
+
+        let mut out = Vec::new();
+        for i in start..end {
+            let Some(key_block) = self.key_block_index.get(&mut self.reader, i)? else {
+                break;
+            };
+            if glob.matches(&key_block.key_text) {
+                out.push(key_block);
+            }
+        }
+
+        Ok(out)
+    }
+
+    /// Every entry for `key`; see [`KeyBlockIndex::get_all`]. A key with no
+    /// entries is looked up again under the key it is an alias of.
+    pub fn get_all(&mut self, key: &str) -> Result<Vec<KeyBlock>> {
+        let matches = self.key_block_index.get_all(&mut self.reader, key)?;
+        if !matches.is_empty() {
+            return Ok(matches);
+        }
+        let target = self.aliases.lock().unwrap().resolve(key).map(str::to_string);
+        match target {
+            Some(target) => self.key_block_index.get_all(&mut self.reader, &target),
+            None => Ok(matches),
+        }
+    }
+
+    /// Consult `aliases` in [`Self::get_all`] and [`Self::lookup`]. The table
+    /// is shared by every handle made with [`Self::with_reader`].
+    pub fn set_key_aliases(&self, aliases: KeyAliases) {
+        *self.aliases.lock().unwrap() = aliases;
+    }
+
+    pub fn key_aliases(&self) -> KeyAliases {
+        self.aliases.lock().unwrap().clone()
+    }
+
+    /// The records of every entry for `key`, in key order, as stored
+    /// (`@@@LINK=` redirects are not followed). Empty when `key` is missing.
+    pub fn lookup(&mut self, key: &str) -> Result<Vec<Vec<u8>>> {
+        self.get_all(key)?
+            .iter()
+            .map(|key_block| self.record_at_key_block(key_block))
+            .collect()
+    }
+
     /// Retrieve a record given a `KeyBlock`. This finds the next key block
     /// (by key ordering) and treats the difference between the next key's
     /// `key_id` and the provided `key_block.key_id` as the uncompressed
     /// size to read starting at `key_block.key_id`.
     pub fn record_at_key_block(&mut self, key_block: &KeyBlock) -> Result<Vec<u8>> {
-        let index = self
+        let index = self.index_of_key_block(key_block)?;
+        self.record_at_index(index)
+    }
+
+    /// [`Self::record_at_key_block`] decoded with the dictionary's declared
+    /// encoding. Fails for MDD resources, which are binary.
+    pub fn record_text_at_key_block(&mut self, key_block: &KeyBlock) -> Result<String> {
+        if self.record_kind() == RecordKind::Binary {
+            return Err(MDictError::InvalidArgument(format!(
+                "'{}' is a binary resource; read it with record_at_key_block",
+                key_block.key_text
+            )));
+        }
+        let record = self.record_at_key_block(key_block)?;
+        Ok(self.key_block_index.header.get_encoding().decode(&record))
+    }
+
+    /// [`Self::record_text_at_key_block`] with the header's `StyleSheet`
+    /// substitutions applied, as MDict displays it.
+    pub fn render_record_styled(&mut self, key_block: &KeyBlock) -> Result<String> {
+        let text = self.record_text_at_key_block(key_block)?;
+        Ok(self.key_block_index.header.stylesheet().apply(&text))
+    }
+
+    /// [`Self::record_text_at_key_block`] post-processed for display
+    /// according to `options` (see [`crate::render`]).
+    pub fn record_rendered(
+        &mut self,
+        key_block: &KeyBlock,
+        options: RenderOptions,
+    ) -> Result<String> {
+        let text = self.record_text_at_key_block(key_block)?;
+        Ok(render_record(&self.key_block_index.header, text, options))
+    }
+
+    /// The record's text without markup, with compact HTML expanded first;
+    /// see [`crate::render::html_to_plaintext`].
+    pub fn record_plaintext(&mut self, key_block: &KeyBlock) -> Result<String> {
+        let html = self.record_rendered(
+            key_block,
+            RenderOptions {
+                expand_compact: true,
+            },
+        )?;
+        Ok(html_to_plaintext(&html))
+    }
+
+    /// Like [`Self::record_at_key_block`], but follows `@@@LINK=` redirects
+    /// up to `max_depth` hops. Fails on cycles, dangling targets and chains
+    /// longer than `max_depth`. Resolved chains are remembered in a link
+    /// cache, so repeat lookups skip the redirect.
+    pub fn record_resolved(&mut self, key_block: &KeyBlock, max_depth: u32) -> Result<Vec<u8>> {
+        let mut index = self.index_of_key_block(key_block)?;
+        // The cache is keyed by text, so it only speaks for a key's first entry.
+        let first_entry = self
             .key_block_index
             .index_for(&mut self.reader, &key_block.key_text)?
-            .ok_or_else(|| MDictError::InvalidArgument("Key block not found".to_string()))?;
-        self.record_at_index(index)
+            == Some(index);
+
+        let cached = if first_entry {
+            self.link_cache.lock().unwrap().get(&key_block.key_text)
+        } else {
+            None
+        };
+        if let Some((cached_index, hops)) = cached {
+            if hops <= max_depth {
+                let record = self.record_at_index(cached_index)?;
+                if link_target_from_record(&record).is_none() {
+                    return Ok(record);
+                }
+            }
+        }
+
+        let mut visited = HashSet::from([key_block.key_text.clone()]);
+        let mut record = self.record_at_index(index)?;
+
+        for hop in 0..max_depth {
+            let Some(target) = link_target_from_record(&record) else {
+                if first_entry {
+                    self.remember_link(&key_block.key_text, index, hop);
+                }
+                return Ok(record);
+            };
+            if !visited.insert(target.clone()) {
+                return Err(MDictError::InvalidFormat(format!(
+                    "link cycle at '{}'",
+                    target
+                )));
+            }
+            index = self
+                .key_block_index
+                .index_for(&mut self.reader, &target)?
+                .ok_or_else(|| {
+                    MDictError::KeyNotFound(format!("link target '{}' not found", target))
+                })?;
+            record = self.record_at_index(index)?;
+        }
+
+        if link_target_from_record(&record).is_some() {
+            return Err(MDictError::InvalidFormat(format!(
+                "link chain from '{}' exceeds {} hops",
+                key_block.key_text, max_depth
+            )));
+        }
+        if first_entry {
+            self.remember_link(&key_block.key_text, index, max_depth);
+        }
+        Ok(record)
+    }
+
+    /// Index of the entry `key_block` came from; see [`KeyBlockIndex::index_of`].
+    fn index_of_key_block(&mut self, key_block: &KeyBlock) -> Result<usize> {
+        self.key_block_index
+            .index_of(&mut self.reader, key_block)?
+            .ok_or_else(|| MDictError::InvalidArgument("Key block not found".to_string()))
+    }
+
+    fn remember_link(&self, key_text: &str, index: usize, hops: u32) {
+        if hops > 0 {
+            self.link_cache
+                .lock()
+                .unwrap()
+                .insert(key_text.to_string(), index, hops);
+        }
+    }
+
+    /// Fill the link cache from a readings list (see
+    /// [`crate::mdx_conversion::reindexing::build_readings_list`]), which
+    /// already records where each redirecting key leads. Readings that are
+    /// not keys, or that lead to more than one entry, are skipped. The cache
+    /// grows to fit; returns the number of links added.
+    pub fn prewarm_link_cache(&mut self, readings: &ReadingsListMap) -> Result<usize> {
+        let total = self.key_block_index.key_section.num_entries as usize;
+        let mut key_ids = HashMap::with_capacity(total);
+        let mut index_of_key_id = HashMap::with_capacity(total);
+        for index in 0..total {
+            let Some(key_block) = self.key_block_index.get(&mut self.reader, index)? else {
+                break;
+            };
+            index_of_key_id.entry(key_block.key_id).or_insert(index);
+            key_ids
+                .entry(key_block.key_text)
+                .or_insert(key_block.key_id);
+        }
+
+        // reading -> resolved key_id, or None when readings disagree.
+        let mut targets: HashMap<&str, Option<u64>> = HashMap::new();
+        for (&target_key_id, keys) in readings {
+            for key_text in keys {
+                targets
+                    .entry(key_text.as_str())
+                    .and_modify(|target| {
+                        if *target != Some(target_key_id) {
+                            *target = None;
+                        }
+                    })
+                    .or_insert(Some(target_key_id));
+            }
+        }
+
+        let mut links = Vec::new();
+        for (key_text, target_key_id) in targets {
+            let (Some(target_key_id), Some(&own_key_id)) = (target_key_id, key_ids.get(key_text))
+            else {
+                continue;
+            };
+            if own_key_id == target_key_id {
+                continue;
+            }
+            if let Some(&index) = index_of_key_id.get(&target_key_id) {
+                links.push((key_text.to_string(), index));
+            }
+        }
+
+        let mut cache = self.link_cache.lock().unwrap();
+        let capacity = cache.capacity().max(cache.len() + links.len());
+        cache.set_capacity(capacity);
+        let added = links.len();
+        for (key_text, index) in links {
+            cache.insert(key_text, index, 1);
+        }
+        Ok(added)
+    }
+
+    /// Links currently remembered by [`Self::record_resolved`].
+    pub fn link_cache_len(&self) -> usize {
+        self.link_cache.lock().unwrap().len()
+    }
+
+    pub fn clear_link_cache(&mut self) {
+        self.link_cache.lock().unwrap().clear();
     }
 
     pub fn record_at_index(&mut self, index: usize) -> Result<Vec<u8>> {
-        let current_key_block = self
-            .key_block_index
-            .get(&mut self.reader, index)?
-            .ok_or_else(|| MDictError::InvalidArgument("Key block not found".to_string()))?;
-        let next_key_block = self.key_block_index.get(&mut self.reader, index + 1)?;
+        Ok(self.record_ref_at_index(index)?.to_vec())
+    }
 
-        let current_key_id = current_key_block.key_id;
-        let next_key_id = next_key_block.map(|kb| kb.key_id);
+    /// [`Self::record_at_key_block`] without copying the record out of its
+    /// decoded block.
+    pub fn record_ref_at_key_block(&mut self, key_block: &KeyBlock) -> Result<RecordRef> {
+        let index = self.index_of_key_block(key_block)?;
+        self.record_ref_at_index(index)
+    }
 
-        let rec_block = self.record_section.bin_search_record_index(current_key_id) as usize;
+    /// [`Self::record_at_index`] without copying the record out of its
+    /// decoded block.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self)))]
+    pub fn record_ref_at_index(&mut self, index: usize) -> Result<RecordRef> {
+        let (current_key_id, record_size) = self.record_extent(index)?;
 
-        let decomp = self.decode_record_block(rec_block)?;
+        let records = self.record_section()?;
+        let rec_block = records.bin_search_record_index(current_key_id)? as usize;
 
-        let uncompressed_before =
-            self.record_section.record_index_prefix_sum[rec_block].uncompressed_size;
-        let decomp_offset = (current_key_id - uncompressed_before) as usize;
+        let decomp = self.shared_record_block(rec_block)?;
 
-        let bytes_available = decomp.len().saturating_sub(decomp_offset);
-        let bytes_to_take = match next_key_id {
-            Some(nk) => ((nk - current_key_id) as usize).min(bytes_available),
-            None => bytes_available,
-        };
+        let uncompressed_before = records.record_index_prefix_sum[rec_block].uncompressed_size;
+        let range = self.record_range(&decomp, current_key_id - uncompressed_before, record_size);
+
+        Ok(RecordRef::new(decomp, rec_block, range))
+    }
+
+    /// Where a record of `record_size` bytes at `offset` sits within its
+    /// decoded `block`, clamped to the block and without the terminator.
+    fn record_range(&self, block: &[u8], offset: u64, record_size: u64) -> Range<usize> {
+        let decomp_offset = offset as usize;
+        let bytes_available = block.len().saturating_sub(decomp_offset);
+        let bytes_to_take = (record_size as usize).min(bytes_available);
 
         let end = decomp_offset
             .saturating_add(bytes_to_take)
-            .min(decomp.len());
+            .min(block.len());
+        let start = decomp_offset.min(end);
+        start..start + self.trim_record_terminator(&block[start..end]).len()
+    }
 
-        let slice = &decomp[decomp_offset..end];
+    /// Call `f` with the record of each entry in `indices`, in order, along
+    /// with its position in `indices`. The record blocks of a batch of
+    /// entries are read on this thread and decoded in parallel on rayon,
+    /// bypassing the block cache.
+    pub(crate) fn for_each_record_at(
+        &mut self,
+        indices: &[usize],
+        mut f: impl FnMut(usize, Vec<u8>) -> Result<()>,
+    ) -> Result<()> {
+        let records = self.record_section()?;
+        let batch_blocks = record_batch_size();
+        let mut next = 0;
+        while next < indices.len() {
+            let first = next;
+            let mut blocks = Vec::new();
+            let mut located = Vec::new();
+            while let Some(&index) = indices.get(next) {
+                let (key_id, record_size) = self.record_extent(index)?;
+                let rec_block = records.bin_search_record_index(key_id)? as usize;
+                let slot = match blocks.iter().position(|&block| block == rec_block) {
+                    Some(slot) => slot,
+                    None if blocks.len() == batch_blocks => break,
+                    None => {
+                        blocks.push(rec_block);
+                        blocks.len() - 1
+                    }
+                };
+                let offset = key_id - records.record_index_prefix_sum[rec_block].uncompressed_size;
+                located.push((slot, offset, record_size));
+                next += 1;
+            }
 
-        if self.key_block_index.header.get_version() != MdictVersion::MDD
-            && slice.ends_with(&[0x0A, 0x00])
-        {
-            return Ok(Vec::from(&slice[..slice.len() - 2]));
+            let decoded = self
+                .read_record_blocks(&blocks)
+                .into_iter()
+                .collect::<Result<Vec<_>>>()?;
+            for (i, (slot, offset, record_size)) in located.into_iter().enumerate() {
+                let block = &decoded[slot];
+                let range = self.record_range(block, offset, record_size);
+                f(first + i, block[range].to_vec())?;
+            }
         }
+        Ok(())
+    }
 
-        // Remove println that was in the original code
-        // println!("record_at_key_block: key='{}' current_key_id={} next_key_id={:?} rec_block={} read_offset={} comp_size={} decomp_offset={} bytes_available={} bytes_to_take={} slice_len={}",
-        //     key_block.key_text, current_key_id, next_key_id, rec_block, read_offset, comp_size, decomp_offset, bytes_available, bytes_to_take, slice.len());
+    /// Stored size of `key_block`'s record, without decoding its record
+    /// block. MDX sizes include the `0x0A 0x00` terminator that
+    /// [`Self::record_at_key_block`] strips; MDD sizes are exact.
+    pub fn record_size_for(&mut self, key_block: &KeyBlock) -> Result<u64> {
+        let index = self.index_of_key_block(key_block)?;
+        self.record_size_at_index(index)
+    }
 
-        Ok(Vec::from(slice))
+    /// [`Self::record_size_for`] by entry index.
+    pub fn record_size_at_index(&mut self, index: usize) -> Result<u64> {
+        Ok(self.record_extent(index)?.1)
     }
 
-    pub fn decode_record_block(&mut self, rec_block: usize) -> Result<Vec<u8>> {
-        if self.cached_record_blocks.contains_key(&rec_block) {
-            return Ok(self.cached_record_blocks.get(&rec_block).unwrap().clone());
+    /// Uncompressed offset and stored size of entry `index`'s record: the
+    /// distance to the next key's `key_id`, or to the end of the record data
+    /// for the last key.
+    fn record_extent(&mut self, index: usize) -> Result<(u64, u64)> {
+        let current_key_id = self
+            .key_block_index
+            .get(&mut self.reader, index)?
+            .ok_or_else(|| MDictError::InvalidArgument("Key block not found".to_string()))?
+            .key_id;
+        let end = match self.key_block_index.get(&mut self.reader, index + 1)? {
+            Some(next_key_block) => next_key_block.key_id,
+            None => self
+                .record_section()?
+                .record_index_prefix_sum
+                .last()
+                .map_or(0, |ri| ri.uncompressed_size),
+        };
+        if end < current_key_id {
+            return Err(MDictError::InvalidFormat(format!(
+                "entry {}: record offset {} is past the end of its record at {}",
+                index, current_key_id, end
+            )));
         }
+        Ok((current_key_id, end - current_key_id))
+    }
+
+    /// Whether records are MDX text or MDD binary resources.
+    pub fn record_kind(&self) -> RecordKind {
+        self.key_block_index.header.get_version().record_kind()
+    }
+
+    /// MDX records end with `0x0A 0x00`; MDD payloads are returned untouched.
+    pub(crate) fn strip_record_terminator(&self, slice: &[u8]) -> Vec<u8> {
+        Vec::from(self.trim_record_terminator(slice))
+    }
+
+    fn trim_record_terminator<'s>(&self, slice: &'s [u8]) -> &'s [u8] {
+        match self.record_kind() {
+            RecordKind::Text if slice.ends_with(&[0x0A, 0x00]) => &slice[..slice.len() - 2],
+            RecordKind::Text | RecordKind::Binary => slice,
+        }
+    }
 
-        let start_comp = self.record_section.record_index_prefix_sum[rec_block].compressed_size;
-        let end_comp = self.record_section.record_index_prefix_sum[rec_block + 1].compressed_size;
-        let comp_size = (end_comp - start_comp) as usize;
+    /// Read and decode record block `rec_block`, bypassing the block cache.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self)))]
+    pub(crate) fn read_record_block(&mut self, rec_block: usize) -> Result<Vec<u8>> {
+        let span = Span::start();
+        let (comp_buf, decomp_size) = self.read_compressed_record_block(rec_block)?;
+        let offset = self.record_block_offset(rec_block)?;
+        let decoded = decode_record_block(rec_block, offset, &comp_buf, decomp_size)?;
+        span.block_decoded(BlockKind::Record, rec_block, decoded.len());
+        Ok(decoded)
+    }
+
+    /// Read record blocks `blocks` on this thread and decode them in parallel
+    /// on rayon, bypassing the block cache. Results are in the order given,
+    /// each block failing on its own.
+    pub(crate) fn read_record_blocks(&mut self, blocks: &[usize]) -> Vec<Result<Vec<u8>>> {
+        let compressed = blocks
+            .iter()
+            .map(|&rec_block| {
+                let (comp_buf, decomp_size) = self.read_compressed_record_block(rec_block)?;
+                let offset = self.record_block_offset(rec_block)?;
+                Ok((rec_block, offset, comp_buf, decomp_size))
+            })
+            .collect::<Vec<Result<_>>>();
+        #[cfg(feature = "threads")]
+        let compressed = compressed.into_par_iter();
+        #[cfg(not(feature = "threads"))]
+        let compressed = compressed.into_iter();
+        compressed
+            .map(|compressed| {
+                let (rec_block, offset, comp_buf, decomp_size) = compressed?;
+                let span = Span::start();
+                let decoded = decode_record_block(rec_block, offset, &comp_buf, decomp_size)?;
+                span.block_decoded(BlockKind::Record, rec_block, decoded.len());
+                Ok(decoded)
+            })
+            .collect()
+    }
 
-        let read_offset = self.record_section.record_data_offset + start_comp;
+    /// File offset of record block `rec_block`.
+    pub(crate) fn record_block_offset(&mut self, rec_block: usize) -> Result<u64> {
+        let records = self.record_section()?;
+        Ok(records.record_data_offset + records.record_index_prefix_sum[rec_block].compressed_size)
+    }
+
+    /// Raw bytes of record block `rec_block` and its decompressed size, so the
+    /// (expensive) decode can happen off the reader's thread.
+    pub(crate) fn read_compressed_record_block(
+        &mut self,
+        rec_block: usize,
+    ) -> Result<(Vec<u8>, usize)> {
+        let records = self.record_section()?;
+        let start = &records.record_index_prefix_sum[rec_block];
+        let end = &records.record_index_prefix_sum[rec_block + 1];
+        let start_comp = start.compressed_size;
+        let comp_size = (end.compressed_size - start_comp) as usize;
+        let decomp_size = (end.uncompressed_size - start.uncompressed_size) as usize;
+
+        let read_offset = records.record_data_offset + start_comp;
         let mut comp_buf = vec![0u8; comp_size];
-        self.reader.seek(SeekFrom::Start(read_offset))?;
-        self.reader.read_exact(&mut comp_buf)?;
-        let decomp = crate::format::decode_format_block(&comp_buf)?;
-
-        if self.max_record_blocks_to_cache > 0 {
-            if self.cached_record_blocks.len() >= self.max_record_blocks_to_cache {
-                // Random eviction policy for simplicity
-                if let Some(key) = self.cached_record_blocks.keys().next().cloned() {
-                    self.cached_record_blocks.remove(&key);
-                }
+        match &self.positional {
+            Some(source) => source.read_exact_at(read_offset, &mut comp_buf)?,
+            None => {
+                self.reader.seek(SeekFrom::Start(read_offset))?;
+                self.reader.read_exact(&mut comp_buf)?;
             }
+        }
+        Ok((comp_buf, decomp_size))
+    }
+
+    /// Read record blocks by offset from `source`, which must hold the same
+    /// bytes as the reader, instead of seeking the reader. Handles made with
+    /// [`Self::with_reader`] share it. `None` goes back to the reader.
+    pub fn set_positional_source(&mut self, source: Option<Arc<dyn ByteSource + Send + Sync>>) {
+        self.positional = source;
+    }
 
-            self.cached_record_blocks.insert(rec_block, decomp.clone());
+    pub fn has_positional_source(&self) -> bool {
+        self.positional.is_some()
+    }
+
+    pub fn decode_record_block(&mut self, rec_block: usize) -> Result<Vec<u8>> {
+        Ok(self.shared_record_block(rec_block)?.as_ref().clone())
+    }
+
+    /// Decoded record block `rec_block`, shared with the block cache.
+    fn shared_record_block(&mut self, rec_block: usize) -> Result<Arc<Vec<u8>>> {
+        if let Some(decomp) = self.record_cache.get(rec_block) {
+            metrics::cache_lookup(BlockKind::Record, true);
+            return Ok(Arc::clone(decomp));
         }
+        metrics::cache_lookup(BlockKind::Record, false);
+
+        let decomp = Arc::new(self.read_record_block(rec_block)?);
+        self.record_cache
+            .insert(rec_block, Arc::clone(&decomp), decomp.len());
 
         Ok(decomp)
     }
 
+    pub fn record_block_cache_capacity(&self) -> CacheCapacity {
+        self.record_cache.capacity()
+    }
+
+    pub fn set_record_block_cache_capacity(&mut self, capacity: impl Into<CacheCapacity>) {
+        self.record_cache.set_capacity(capacity);
+    }
+
+    /// The record block cache's block limit; `usize::MAX` when it is bounded
+    /// by bytes instead.
+    #[deprecated(note = "use `record_block_cache_capacity`")]
     pub fn record_block_cache_limit(&self) -> usize {
-        self.max_record_blocks_to_cache
+        match self.record_block_cache_capacity() {
+            CacheCapacity::Entries(limit) => limit,
+            CacheCapacity::Bytes(_) => usize::MAX,
+        }
     }
 
+    #[deprecated(note = "use `set_record_block_cache_capacity`")]
     pub fn set_record_block_cache_limit(&mut self, max_record_blocks_to_cache: usize) {
-        self.max_record_blocks_to_cache = max_record_blocks_to_cache;
+        self.set_record_block_cache_capacity(max_record_blocks_to_cache);
     }
 
     pub fn clear_record_block_cache(&mut self) {
-        self.cached_record_blocks.clear();
+        self.record_cache.clear();
+    }
+
+    pub fn key_block_cache_capacity(&self) -> CacheCapacity {
+        self.key_block_index.cache_capacity()
+    }
+
+    pub fn set_key_block_cache_capacity(&mut self, capacity: impl Into<CacheCapacity>) {
+        self.key_block_index.set_cache_capacity(capacity);
+    }
+}
+
+impl<R: Read + Seek> Mdict<R> {
+    pub fn prefix_range_bounds(&mut self, prefix: &str) -> Result<Option<(usize, usize)>> {
+        self.key_block_index
+            .prefix_range_bounds(&mut self.reader, prefix)
+    }
+
+    pub fn get(&mut self, index: usize) -> Result<Option<KeyBlock>> {
+        self.key_block_index.get(&mut self.reader, index)
+    }
+
+    /// Up to `count` keys starting at entry `start`, in key order. Past the
+    /// end the page is short or empty.
+    pub fn entries_page(&mut self, start: usize, count: usize) -> Result<Vec<KeyBlock>> {
+        let total = self.key_block_index.key_section.num_entries as usize;
+        let end = start.saturating_add(count).min(total);
+        let mut page = Vec::with_capacity(end.saturating_sub(start));
+        for index in start..end {
+            match self.get(index)? {
+                Some(key_block) => page.push(key_block),
+                None => break,
+            }
+        }
+        Ok(page)
+    }
+}
+
+#[cfg(feature = "fs")]
+impl Mdict<File> {
+    /// Open a file at `path` and construct an `Mdict<File>`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let f = File::open(path).map_err(MDictError::from)?;
+        Mdict::new(f)
+    }
+
+    /// [`Self::open`], with the parsed key section cached next to the file;
+    /// see [`Mdict::new_with_key_cache`].
+    pub fn open_cached<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let f = File::open(&path).map_err(MDictError::from)?;
+        Mdict::new_with_key_cache(f, OpenOptions::default(), key_cache_path(path))
+    }
+
+    /// [`Self::open`] with explicit [`OpenOptions`].
+    pub fn open_with_options<P: AsRef<Path>>(path: P, options: OpenOptions) -> Result<Self> {
+        let f = File::open(path).map_err(MDictError::from)?;
+        Mdict::new_with_options(f, options)
+    }
+}
+
+impl<S: ByteSource> Mdict<ByteSourceReader<S>> {
+    /// Open a dictionary from any [`ByteSource`]: memory, a mapped file, a
+    /// [`crate::io::FileSource`], or (with the `http` feature) a remote URL.
+    pub fn from_source(source: S) -> Result<Self> {
+        Mdict::new(ByteSourceReader::new(source))
+    }
+}
+
+impl<'a> Mdict<ByteSourceReader<&'a [u8]>> {
+    /// Open a dictionary that is already in memory, without copying it.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self> {
+        Self::from_source(bytes)
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl Mdict<ByteSourceReader<Arc<Mmap>>> {
+    /// Open a mapped file. Key blocks are decoded straight from the mapping
+    /// and record blocks read from it by offset.
+    pub fn from_mmap(mmap: Mmap) -> Result<Self> {
+        let mmap = Arc::new(mmap);
+        let section = MmapSection::from_mmap(Arc::clone(&mmap));
+        let mut mdict = Self::from_source(Arc::clone(&mmap))?;
+        mdict.key_block_index.set_mapped(Some(section));
+        mdict.set_positional_source(Some(mmap));
+        Ok(mdict)
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl Mdict<SeekableMmap> {
+    /// Open a mapped file; like [`Mdict::new`], but key and record blocks
+    /// are read straight from the mapping without going through the cursor.
+    pub fn from_seekable_mmap(mmap: SeekableMmap) -> Result<Self> {
+        Self::mapped(mmap, None)
+    }
+
+    /// [`Self::from_seekable_mmap`], with the parsed key section cached at
+    /// `cache_path`; see [`Mdict::new_with_key_cache`].
+    pub fn from_seekable_mmap_cached(
+        mmap: SeekableMmap,
+        cache_path: impl AsRef<Path>,
+    ) -> Result<Self> {
+        Self::mapped(mmap, Some(cache_path.as_ref()))
     }
+
+    fn mapped(mmap: SeekableMmap, cache_path: Option<&Path>) -> Result<Self> {
+        let section = mmap.section();
+        let positional = Arc::new(mmap.clone());
+        let mut mdict = Self::open_parts(mmap, OpenOptions::default(), cache_path)?;
+        mdict.key_block_index.set_mapped(Some(section));
+        mdict.set_positional_source(Some(positional));
+        Ok(mdict)
+    }
+}
+
+const RECORD_BLOCKS_PER_THREAD: usize = 4;
+
+/// Record blocks decoded per parallel batch: a few per rayon thread, so
+/// threads stay busy without holding many decoded blocks at once.
+#[cfg(feature = "threads")]
+pub(crate) fn record_batch_size() -> usize {
+    rayon::current_num_threads().max(1) * RECORD_BLOCKS_PER_THREAD
+}
+
+/// Without the `threads` feature batches are decoded on the calling thread.
+#[cfg(not(feature = "threads"))]
+pub(crate) fn record_batch_size() -> usize {
+    RECORD_BLOCKS_PER_THREAD
+}
+
+/// Decode record block `rec_block`, read from file `offset`, and check it
+/// against `decomp_size` from the record index. Needs no `Mdict`, so blocks
+/// can be decoded off the reader's thread.
+pub(crate) fn decode_record_block(
+    rec_block: usize,
+    offset: u64,
+    comp_buf: &[u8],
+    decomp_size: usize,
+) -> Result<Vec<u8>> {
+    let decoded = crate::format::decode_format_block_sized(comp_buf, decomp_size).map_err(|e| {
+        e.in_section(format_args!("record block {}", rec_block))
+            .at_offset(offset)
+    })?;
+    if decoded.len() != decomp_size {
+        return Err(MDictError::Corrupted {
+            offset,
+            message: format!(
+                "record block {}: decompressed to {} bytes, record index says {}",
+                rec_block,
+                decoded.len(),
+                decomp_size
+            ),
+        });
+    }
+    Ok(decoded)
+}
+
+/// Whether the Levenshtein distance between `query` and `candidate` (in
+/// chars) is at most `max_distance`.
+fn edit_distance_within(query: &[char], candidate: &str, max_distance: usize) -> bool {
+    bounded_edit_distance(query, candidate, max_distance).is_some()
+}
+
+/// The Levenshtein distance between `query` and `candidate` (in chars), or
+/// `None` once it is known to exceed `max_distance`.
+pub(crate) fn bounded_edit_distance(
+    query: &[char],
+    candidate: &str,
+    max_distance: usize,
+) -> Option<usize> {
+    let candidate = candidate.chars().collect::<Vec<_>>();
+    if query.len().abs_diff(candidate.len()) > max_distance {
+        return None;
+    }
+
+    let mut previous = (0..=candidate.len()).collect::<Vec<_>>();
+    let mut current = vec![0; candidate.len() + 1];
+    for (i, q) in query.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, c) in candidate.iter().enumerate() {
+            let substitution = previous[j] + usize::from(q != c);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        if current.iter().all(|&d| d > max_distance) {
+            return None;
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    Some(previous[candidate.len()]).filter(|&d| d <= max_distance)
 }
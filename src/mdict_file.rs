@@ -1,99 +1,444 @@
 use std::{
     fs::File,
-    io::{Read, Seek},
     path::Path,
-    sync::Mutex,
+    sync::{atomic::AtomicBool, Arc, Mutex, RwLock},
 };
 
 use crate::{
+    deinflect::{DeinflectedMatch, Deinflector},
+    dictionary::Dictionary,
     error::MDictError,
-    mdx_conversion::{fst_indexing::create_fst_index, reindexing::build_readings_list},
+    file_stamp::FileStamp,
+    format::key_index_cache::key_cache_path,
+    links::percent_decode,
+    mdict_shared::MdictShared,
+    mdx_conversion::{
+        aliases::KeyAliases,
+        build_manifest::{self, BuildManifest, BuildStage},
+        check_cancelled,
+        delta::update_fst_index,
+        entry_metadata::EntryMetadata,
+        flashcards::{export_flashcards, FlashcardTemplate},
+        frequency::FrequencyList,
+        fst_indexing::create_fst_index_with_metadata,
+        normalize::{KeyNormalizer, KeyNormalizerRule, NormalizerPipeline},
+        readings::{read_readings_list_checkpoint, write_readings_list_checkpoint},
+        transcode::{transcode, transcode_to_zstd},
+        transform::{RecordTransform, RecordTransformRule, TransformPipeline},
+        reindexing::{
+            build_readings_list_checked, build_readings_list_normalized,
+            build_readings_list_with_budget_checked, read_compressed_readings_list,
+            ReadingsListMap,
+        },
+    },
+    mdx_writer::BlockCompression,
+    metrics::Span,
+    mime::mime_type_for,
     prefix_key_block_index::PrefixKeyBlockIndexInternal,
+    record_stream::RecordStreamHandle,
+    render::{classify_link, LinkKind, RenderOptions},
     seekable_mmap::SeekableMmap,
-    types::{BuildProgressStage, KeyBlock},
+    types::{
+        BrokenLink, BrokenLinkPolicy, BuildProgressStage, DeltaStats, DictionaryInfo,
+        DictionaryMetadata, FlashcardExport, KeyBlock, LinkMetadata, MetadataField,
+        ResolvedResource, Suggestion, TranscodeStats,
+    },
     Mdict,
 };
 
+/// Redirect hops [`MdictBundle::resolve_uri`] follows for `entry://` links.
+const RESOLVE_URI_LINK_DEPTH: u32 = 8;
+
 #[derive(uniffi::Object)]
 pub struct MdictBundle {
-    mdx: Mutex<Mdict<SeekableMmap>>,
-    mdd: Mutex<Option<Mdict<SeekableMmap>>>,
+    mdx_path: String,
+    /// Empty when there is no MDD.
+    mdd_path: String,
+    /// Replaced as a whole by [`MdictBundle::reload`].
+    files: RwLock<Arc<BundleFiles>>,
 
     current_mdx_prefix_key_index: Mutex<Option<PrefixKeyBlockIndexInternal>>,
+    key_normalizer: Mutex<Arc<dyn KeyNormalizer>>,
+    frequency_list: Mutex<Option<Arc<FrequencyList>>>,
+    entry_metadata: Mutex<Option<Arc<EntryMetadata>>>,
+    record_transform: Mutex<Option<Arc<dyn RecordTransform>>>,
+    broken_link_policy: Mutex<BrokenLinkPolicy>,
 }
 
-#[uniffi::export]
-pub fn create_mdict_bundle(mdx_path: String, mdd_path: String) -> Result<MdictBundle, MDictError> {
-    let mdx_file = File::open(mdx_path)?;
-    let mdx_mmap = SeekableMmap::open(&mdx_file)?;
+/// A bundle's open MDX and MDD, with stamps of the files they were mapped
+/// from.
+struct BundleFiles {
+    mdx: MdictShared<SeekableMmap>,
+    mdd: Option<MdictShared<SeekableMmap>>,
+    mdx_stamp: FileStamp,
+    mdd_stamp: Option<FileStamp>,
+}
 
-    let mdd_mmap = if !mdd_path.is_empty() {
-        let mdd_file = File::open(mdd_path)?;
-        Some(SeekableMmap::open(&mdd_file)?)
-    } else {
-        None
-    };
+impl BundleFiles {
+    fn open(mdx_path: &str, mdd_path: &str) -> Result<Self, MDictError> {
+        // Stamped before mapping, so a change in between shows as a change.
+        let mdx_stamp = FileStamp::of(mdx_path)?;
+        let mdx = MdictShared::new(open_mapped(mdx_path)?);
+        let (mdd, mdd_stamp) = if !mdd_path.is_empty() {
+            let mdd_stamp = FileStamp::of(mdd_path)?;
+            (Some(MdictShared::new(open_mapped(mdd_path)?)), Some(mdd_stamp))
+        } else {
+            (None, None)
+        };
+        Ok(Self {
+            mdx,
+            mdd,
+            mdx_stamp,
+            mdd_stamp,
+        })
+    }
+}
 
-    let mdx = Mdict::new(mdx_mmap)?;
-    let mdd = if let Some(mdd_mmap) = mdd_mmap {
-        Some(Mdict::new(mdd_mmap)?)
-    } else {
-        None
-    };
+#[uniffi::export]
+pub fn create_mdict_bundle(mdx_path: String, mdd_path: String) -> Result<MdictBundle, MDictError> {
+    let files = BundleFiles::open(&mdx_path, &mdd_path)?;
 
     Ok(MdictBundle {
-        mdx: Mutex::new(mdx),
-        mdd: Mutex::new(mdd),
+        mdx_path,
+        mdd_path,
+        files: RwLock::new(Arc::new(files)),
         current_mdx_prefix_key_index: Mutex::new(None),
+        key_normalizer: Mutex::new(Arc::new(NormalizerPipeline::default())),
+        frequency_list: Mutex::new(None),
+        entry_metadata: Mutex::new(None),
+        record_transform: Mutex::new(None),
+        broken_link_policy: Mutex::new(BrokenLinkPolicy::default()),
     })
 }
 
-impl<R: Read + Seek> Mdict<R> {
-    pub fn prefix_range_bounds(
-        &mut self,
-        prefix: &str,
-    ) -> Result<Option<(usize, usize)>, MDictError> {
-        self.key_block_index
-            .prefix_range_bounds(&mut self.reader, prefix)
-    }
-
-    pub fn get(&mut self, index: usize) -> Result<Option<KeyBlock>, MDictError> {
-        self.key_block_index.get(&mut self.reader, index)
+/// Map the file at `path`, through its key section cache when the config
+/// asks for one.
+pub(crate) fn open_mapped(path: &str) -> Result<Mdict<SeekableMmap>, MDictError> {
+    let mmap = SeekableMmap::open(&File::open(path)?)?;
+    if crate::config::config().key_section_cache {
+        Mdict::from_seekable_mmap_cached(mmap, key_cache_path(path))
+    } else {
+        Mdict::from_seekable_mmap(mmap)
     }
 }
 
 impl MdictBundle {
-    pub(crate) fn build_fst_files_with_progress<F>(
+    /// The open files, or [`MDictError::FileChanged`] once the size or time
+    /// of either differs from when it was opened, so a file replaced in
+    /// place is never read through the old mapping.
+    fn files(&self) -> Result<Arc<BundleFiles>, MDictError> {
+        let files = self.current_files();
+        let unchanged = |stamp: &FileStamp, path: &str| {
+            matches!(stamp.metadata_matches(path), Ok(true))
+        };
+        if !unchanged(&files.mdx_stamp, &self.mdx_path) {
+            return Err(MDictError::FileChanged(self.mdx_path.clone()));
+        }
+        if let Some(mdd_stamp) = &files.mdd_stamp {
+            if !unchanged(mdd_stamp, &self.mdd_path) {
+                return Err(MDictError::FileChanged(self.mdd_path.clone()));
+            }
+        }
+        Ok(files)
+    }
+
+    /// The open files, unchecked, for what was read while opening them.
+    fn current_files(&self) -> Arc<BundleFiles> {
+        Arc::clone(&self.files.read().unwrap())
+    }
+
+    /// [`Self::mdd_resource`] with its MIME type, detected from the data's
+    /// magic bytes or else the key's extension.
+    pub fn mdd_resource_with_mime(
+        &self,
+        key: &str,
+    ) -> Result<Option<(Vec<u8>, String)>, MDictError> {
+        Ok(self.mdd_resource(key)?.map(|data| {
+            let mime_type = mime_type_for(key, &data).to_string();
+            (data, mime_type)
+        }))
+    }
+
+    /// Use `normalizer` for the readings of optimized indexes built from
+    /// this bundle. The default splits `reading【kanji】` keys.
+    pub fn set_key_normalizer(&self, normalizer: Arc<dyn KeyNormalizer>) {
+        *self.key_normalizer.lock().unwrap() = normalizer;
+    }
+
+    /// Store ranks from `frequencies` in optimized indexes built from this
+    /// bundle, so their prefix search pages list frequent words first.
+    pub fn set_frequency_list(&self, frequencies: Option<FrequencyList>) {
+        *self.frequency_list.lock().unwrap() = frequencies.map(Arc::new);
+    }
+
+    /// Store `metadata` with the entries of optimized indexes built from this
+    /// bundle. Its link ids are the `key_id`s of this MDX's keys.
+    pub fn set_entry_metadata(&self, metadata: Option<EntryMetadata>) {
+        *self.entry_metadata.lock().unwrap() = metadata.map(Arc::new);
+    }
+
+    /// Pass records through `transform` on their way into optimized indexes
+    /// built from this bundle.
+    pub fn set_record_transform(&self, transform: Option<Arc<dyn RecordTransform>>) {
+        *self.record_transform.lock().unwrap() = transform;
+    }
+
+    /// Consult `aliases` in [`Self::lookup`] and index each alias alongside
+    /// its key in optimized indexes built from this bundle.
+    pub fn set_key_aliases(&self, aliases: KeyAliases) {
+        self.current_files().mdx.set_key_aliases(aliases);
+    }
+
+    /// Build the FST, readings, record and entry id files, resuming from the
+    /// checkpoint of an interrupted build of the same dictionary. If a
+    /// previous build already produced intact outputs, nothing is rebuilt.
+    /// Setting `cancel` stops the build with [`MDictError::Cancelled`]; a
+    /// build cancelled after the readings pass resumes from its checkpoint.
+    /// Unless the broken link policy is `Lenient`, `on_broken_links` gets the
+    /// redirects to missing keys once the readings are known, including when
+    /// they come from a checkpoint or a finished build.
+    pub(crate) fn build_fst_files_with_progress<F, G>(
         &self,
         fst_path: impl AsRef<Path>,
         readings_path: impl AsRef<Path>,
         record_path: impl AsRef<Path>,
+        cancel: &AtomicBool,
         mut on_progress: F,
+        mut on_broken_links: G,
     ) -> Result<(), MDictError>
     where
         F: FnMut(BuildProgressStage, u64, u64),
+        G: FnMut(Vec<BrokenLink>),
     {
-        let mut mdx = self.mdx.lock().unwrap();
-        let old_cache_limit = mdx.record_block_cache_limit();
-        mdx.set_record_block_cache_limit(usize::MAX);
+        let fst_path = fst_path.as_ref();
+        let readings_path = readings_path.as_ref();
+        let record_path = record_path.as_ref();
+        let manifest_path = build_manifest::manifest_path(fst_path);
+        let checkpoint_path = build_manifest::readings_list_checkpoint_path(fst_path);
+        let entry_ids_path = build_manifest::entry_ids_path(fst_path);
+        let normalizer = self.key_normalizer.lock().unwrap().clone();
+        let frequencies = self.frequency_list.lock().unwrap().clone();
+        let metadata = self.entry_metadata.lock().unwrap().clone();
+        let transform = self.record_transform.lock().unwrap().clone();
+        let files = self.files()?;
+        let aliases = files.mdx.key_aliases();
+        let aliases_path = build_manifest::aliases_path(fst_path);
+        let policy = *self.broken_link_policy.lock().unwrap();
+        let checks_links = policy != BrokenLinkPolicy::Lenient;
+        let broken_links_path = build_manifest::broken_links_path(fst_path);
+        let links_intact = |manifest: &BuildManifest| {
+            !checks_links
+                || manifest.file_is_intact(build_manifest::BROKEN_LINKS_FILE, &broken_links_path)
+        };
+
+        files.mdx.with(|mdx| {
+            let fingerprint = build_fingerprint(
+                mdx.reader.as_slice(),
+                normalizer.as_ref(),
+                frequencies.as_deref(),
+                metadata.as_deref(),
+                transform.as_deref(),
+                &aliases,
+                policy,
+            );
+            let previous = BuildManifest::read(&manifest_path)
+                .filter(|manifest| manifest.fingerprint == fingerprint);
+
+            if let Some(manifest) = &previous {
+                if manifest.stage == BuildStage::Done
+                    && manifest.file_is_intact(build_manifest::FST_FILE, fst_path)
+                    && manifest.file_is_intact(build_manifest::READINGS_FILE, readings_path)
+                    && manifest.file_is_intact(build_manifest::RECORDS_FILE, record_path)
+                    && manifest.file_is_intact(build_manifest::ENTRY_IDS_FILE, &entry_ids_path)
+                    && (aliases.is_empty()
+                        || manifest.file_is_intact(build_manifest::ALIASES_FILE, &aliases_path))
+                    && links_intact(manifest)
+                {
+                    if checks_links {
+                        on_broken_links(read_broken_links(&broken_links_path)?);
+                    }
+                    return Ok(());
+                }
+            }
+
+            let old_cache_capacity = mdx.record_block_cache_capacity();
+            mdx.set_record_block_cache_capacity(
+                crate::config::config().build_record_block_cache_capacity(),
+            );
+
+            let result = (|| {
+                check_cancelled(cancel)?;
+                on_progress(BuildProgressStage::BuildReadings, 1, 3);
+                let checkpoint = previous
+                    .filter(|manifest| {
+                        manifest
+                            .file_is_intact(build_manifest::READINGS_LIST_FILE, &checkpoint_path)
+                            && links_intact(manifest)
+                    })
+                    .and_then(|_| {
+                        let readings_list = read_readings_list_checkpoint(&checkpoint_path).ok()?;
+                        let broken_links = if checks_links {
+                            read_broken_links(&broken_links_path).ok()?
+                        } else {
+                            Vec::new()
+                        };
+                        Some((readings_list, broken_links))
+                    });
+                let (mut readings_list, broken_links) = match checkpoint {
+                    Some(checkpoint) => checkpoint,
+                    None => {
+                        let memory_budget = crate::config::config().build_memory_budget();
+                        let (readings_list, broken_links) = match memory_budget {
+                            Some(budget) => build_readings_list_with_budget_checked(
+                                mdx,
+                                budget,
+                                normalizer.as_ref(),
+                                policy,
+                            )?,
+                            None => build_readings_list_checked(mdx, normalizer.as_ref(), policy)?,
+                        };
+                        write_readings_list_checkpoint(&readings_list, &checkpoint_path)?;
+                        let mut manifest =
+                            BuildManifest::new(fingerprint.clone(), BuildStage::Readings);
+                        manifest
+                            .record_file(build_manifest::READINGS_LIST_FILE, &checkpoint_path)?;
+                        if checks_links {
+                            write_broken_links(&broken_links, &broken_links_path)?;
+                            manifest.record_file(
+                                build_manifest::BROKEN_LINKS_FILE,
+                                &broken_links_path,
+                            )?;
+                        }
+                        manifest.write(&manifest_path)?;
+                        (readings_list, broken_links)
+                    }
+                };
+                if checks_links {
+                    on_broken_links(broken_links);
+                }
+
+                aliases.apply(&mut readings_list);
+
+                check_cancelled(cancel)?;
+                on_progress(BuildProgressStage::BuildFst, 2, 3);
+                create_fst_index_with_metadata(
+                    mdx,
+                    &readings_list,
+                    fst_path,
+                    readings_path,
+                    record_path,
+                    frequencies.as_deref(),
+                    metadata.as_deref(),
+                    transform.as_deref(),
+                    cancel,
+                )?;
+                if aliases.is_empty() {
+                    let _ = std::fs::remove_file(&aliases_path);
+                } else {
+                    aliases.write_to_path(&aliases_path)?;
+                }
+                if !checks_links {
+                    let _ = std::fs::remove_file(&broken_links_path);
+                }
+
+                let mut manifest = BuildManifest::new(fingerprint, BuildStage::Done);
+                manifest.record_file(build_manifest::FST_FILE, fst_path)?;
+                manifest.record_file(build_manifest::READINGS_FILE, readings_path)?;
+                manifest.record_file(build_manifest::RECORDS_FILE, record_path)?;
+                manifest.record_file(build_manifest::ENTRY_IDS_FILE, &entry_ids_path)?;
+                if !aliases.is_empty() {
+                    manifest.record_file(build_manifest::ALIASES_FILE, &aliases_path)?;
+                }
+                if checks_links {
+                    manifest.record_file(build_manifest::BROKEN_LINKS_FILE, &broken_links_path)?;
+                }
+                manifest.write(&manifest_path)?;
+                let _ = std::fs::remove_file(&checkpoint_path);
+                Ok(())
+            })();
+
+            mdx.set_record_block_cache_capacity(old_cache_capacity);
+            mdx.clear_record_block_cache();
+            result
+        })?;
 
-        on_progress(BuildProgressStage::BuildReadings, 1, 3);
-        let readings_list = build_readings_list(&mut *mdx)?;
+        on_progress(BuildProgressStage::Done, 3, 3);
+        Ok(())
+    }
+
+    /// A handle on the MDX and its readings list as an optimized build would
+    /// index them, for combining several bundles into one index.
+    pub(crate) fn readings_source(
+        &self,
+    ) -> Result<(Mdict<SeekableMmap>, ReadingsListMap), MDictError> {
+        let normalizer = self.key_normalizer.lock().unwrap().clone();
+        let files = self.files()?;
+        let mut mdx = files.mdx.handle();
+        let mut readings_list = build_readings_list_normalized(&mut mdx, normalizer.as_ref())?;
+        files.mdx.key_aliases().apply(&mut readings_list);
+        Ok((mdx, readings_list))
+    }
+
+    /// Build the FST, readings, record and entry id files for this bundle's
+    /// MDX by updating the build at `old_readings_path`/`old_record_path` of
+    /// an earlier release; see [`update_fst_index`].
+    pub(crate) fn update_fst_files(
+        &self,
+        old_readings_path: impl AsRef<Path>,
+        old_record_path: impl AsRef<Path>,
+        fst_path: impl AsRef<Path>,
+        readings_path: impl AsRef<Path>,
+        record_path: impl AsRef<Path>,
+    ) -> Result<DeltaStats, MDictError> {
+        let fst_path = fst_path.as_ref();
+        let readings_path = readings_path.as_ref();
+        let record_path = record_path.as_ref();
+        let normalizer = self.key_normalizer.lock().unwrap().clone();
+        let frequencies = self.frequency_list.lock().unwrap().clone();
+        let metadata = self.entry_metadata.lock().unwrap().clone();
+        let transform = self.record_transform.lock().unwrap().clone();
+        let aliases = self.files()?.mdx.key_aliases();
+        let aliases_path = build_manifest::aliases_path(fst_path);
+        let entry_ids_path = build_manifest::entry_ids_path(fst_path);
 
-        on_progress(BuildProgressStage::BuildFst, 2, 3);
-        create_fst_index(
-            &mut *mdx,
+        let (mut mdx, readings_list) = self.readings_source()?;
+        let stats = update_fst_index(
+            old_readings_path,
+            old_record_path,
+            &mut mdx,
             &readings_list,
             fst_path,
             readings_path,
             record_path,
+            frequencies.as_deref(),
+            metadata.as_deref(),
+            transform.as_deref(),
         )?;
+        if aliases.is_empty() {
+            let _ = std::fs::remove_file(&aliases_path);
+        } else {
+            aliases.write_to_path(&aliases_path)?;
+        }
 
-        mdx.set_record_block_cache_limit(old_cache_limit);
-        mdx.clear_record_block_cache();
-
-        on_progress(BuildProgressStage::Done, 3, 3);
-        Ok(())
+        let fingerprint = build_fingerprint(
+            mdx.reader.as_slice(),
+            normalizer.as_ref(),
+            frequencies.as_deref(),
+            metadata.as_deref(),
+            transform.as_deref(),
+            &aliases,
+            BrokenLinkPolicy::Lenient,
+        );
+        let mut manifest = BuildManifest::new(fingerprint, BuildStage::Done);
+        manifest.record_file(build_manifest::FST_FILE, fst_path)?;
+        manifest.record_file(build_manifest::READINGS_FILE, readings_path)?;
+        manifest.record_file(build_manifest::RECORDS_FILE, record_path)?;
+        manifest.record_file(build_manifest::ENTRY_IDS_FILE, &entry_ids_path)?;
+        if !aliases.is_empty() {
+            manifest.record_file(build_manifest::ALIASES_FILE, &aliases_path)?;
+        }
+        manifest.write(build_manifest::manifest_path(fst_path))?;
+        Ok(stats)
     }
 
     pub(crate) fn build_fst_files(
@@ -102,18 +447,96 @@ impl MdictBundle {
         readings_path: impl AsRef<Path>,
         record_path: impl AsRef<Path>,
     ) -> Result<(), MDictError> {
-        self.build_fst_files_with_progress(fst_path, readings_path, record_path, |_stage, _, _| {})
+        self.build_fst_files_with_progress(
+            fst_path,
+            readings_path,
+            record_path,
+            &AtomicBool::new(false),
+            |_stage, _, _| {},
+            |_links| {},
+        )
     }
 }
 
+/// Identifies the inputs of an optimized-index build: the MDX contents and
+/// the settings that change what gets written.
+fn build_fingerprint(
+    mdx: &[u8],
+    normalizer: &dyn KeyNormalizer,
+    frequencies: Option<&FrequencyList>,
+    metadata: Option<&EntryMetadata>,
+    transform: Option<&dyn RecordTransform>,
+    aliases: &KeyAliases,
+    policy: BrokenLinkPolicy,
+) -> String {
+    let config = crate::config::config();
+    let settings = format!(
+        "{} {} {} {} {} {} {} {} {:?}",
+        config.packed_block_size,
+        config.record_compression_level,
+        config.zstd_dictionary_size,
+        normalizer.name(),
+        frequencies.map_or("", FrequencyList::digest),
+        metadata.map(EntryMetadata::digest).unwrap_or_default(),
+        aliases.digest(),
+        transform.map(|transform| transform.name()).unwrap_or_default(),
+        policy
+    );
+    build_manifest::hash_parts(&[mdx, settings.as_bytes()])
+}
+
+/// Save the broken links a build found, for later builds that resume from
+/// its checkpoint or find it finished.
+fn write_broken_links(links: &[BrokenLink], path: &Path) -> Result<(), MDictError> {
+    let json = serde_json::to_vec(links)
+        .map_err(|e| MDictError::InvalidFormat(format!("broken links: {}", e)))?;
+    std::fs::write(path, json)?;
+    Ok(())
+}
+
+fn read_broken_links(path: &Path) -> Result<Vec<BrokenLink>, MDictError> {
+    serde_json::from_slice(&std::fs::read(path)?)
+        .map_err(|e| MDictError::InvalidFormat(format!("broken links: {}", e)))
+}
+
 #[uniffi::export]
 impl MdictBundle {
-    pub fn set_search_prefix(&self, prefix: &str) -> Result<(), MDictError> {
-        let mut mdx = self.mdx.lock().unwrap();
+    /// Whether the MDX or MDD on disk differs from the one opened (or last
+    /// reloaded) in size, modification time or header checksum. Once the
+    /// size or time differs, reads fail with `FileChanged` until
+    /// [`Self::reload`].
+    pub fn has_changed(&self) -> bool {
+        let files = self.current_files();
+        let changed = |stamp: &FileStamp, path: &str| {
+            !matches!(FileStamp::of(path), Ok(now) if now == *stamp)
+        };
+        changed(&files.mdx_stamp, &self.mdx_path)
+            || files
+                .mdd_stamp
+                .as_ref()
+                .is_some_and(|stamp| changed(stamp, &self.mdd_path))
+    }
+
+    /// Reopen the MDX and MDD from their paths, e.g. after an update was
+    /// downloaded over them. Key aliases and the other settings are kept;
+    /// the search prefix is cleared, as its indexes were into the old file.
+    /// On failure the bundle keeps the files it had.
+    pub fn reload(&self) -> Result<(), MDictError> {
+        let files = BundleFiles::open(&self.mdx_path, &self.mdd_path)?;
+        files
+            .mdx
+            .set_key_aliases(self.current_files().mdx.key_aliases());
+        *self.files.write().unwrap() = Arc::new(files);
+        *self.current_mdx_prefix_key_index.lock().unwrap() = None;
+        Ok(())
+    }
 
-        let prefix_index = mdx.prefix_range_bounds(prefix)?.ok_or_else(|| {
+    pub fn set_search_prefix(&self, prefix: &str) -> Result<(), MDictError> {
+        let span = Span::start();
+        let prefix_index = self.files()?.mdx.prefix_range_bounds(prefix)?.ok_or_else(|| {
             MDictError::InvalidArgument(format!("Prefix '{}' not found in MDX", prefix))
         })?;
+        span.search_finished("prefix search", prefix, prefix_index.1 - prefix_index.0);
 
         *self.current_mdx_prefix_key_index.lock().unwrap() = Some(
             PrefixKeyBlockIndexInternal::new(prefix.to_string(), prefix_index.0, prefix_index.1),
@@ -136,32 +559,215 @@ impl MdictBundle {
             })?;
         drop(prefix_index_guard);
 
-        let mut mdx = self.mdx.lock().unwrap();
-        mdx.get(global_index).map_err(MDictError::from)
+        self.files()?.mdx.get(global_index)
+    }
+
+    /// Built-in rules, applied in order, for the readings of optimized
+    /// indexes built from this bundle. An empty list indexes keys as they are.
+    pub fn set_key_normalizers(&self, rules: Vec<KeyNormalizerRule>) {
+        self.set_key_normalizer(Arc::new(NormalizerPipeline::from_rules(&rules)));
+    }
+
+    /// How optimized indexes built from this bundle treat `@@@LINK=`
+    /// redirects to keys the MDX does not have. Under `Strict` and `Repair`,
+    /// the built index lists them in `MdictOptimized::broken_links`.
+    pub fn set_broken_link_policy(&self, policy: BrokenLinkPolicy) {
+        *self.broken_link_policy.lock().unwrap() = policy;
+    }
+
+    /// Built-in transforms, applied in order, for the records of optimized
+    /// indexes built from this bundle. An empty list stores records as they
+    /// are.
+    pub fn set_record_transforms(&self, rules: Vec<RecordTransformRule>) {
+        let pipeline = TransformPipeline::from_rules(&rules);
+        let transform: Option<Arc<dyn RecordTransform>> =
+            (!pipeline.is_empty()).then(|| Arc::new(pipeline) as _);
+        self.set_record_transform(transform);
+    }
+
+    /// Load a `word<TAB>rank` frequency list for optimized indexes built from
+    /// this bundle; see [`FrequencyList`] for the format.
+    pub fn load_frequency_list(&self, path: String) -> Result<(), MDictError> {
+        self.set_frequency_list(Some(FrequencyList::from_path(path)?));
+        Ok(())
+    }
+
+    pub fn clear_frequency_list(&self) {
+        self.set_frequency_list(None);
+    }
+
+    /// Store the values of `fields` given in `values` with the entries of
+    /// optimized indexes built from this bundle. A value's `link_id` is the
+    /// `key_id` of the entry's keys in this MDX.
+    pub fn load_entry_metadata(
+        &self,
+        fields: Vec<MetadataField>,
+        values: Vec<LinkMetadata>,
+    ) -> Result<(), MDictError> {
+        let metadata = EntryMetadata::from_values(
+            fields,
+            values
+                .into_iter()
+                .map(|value| (value.link_id, value.name, value.value)),
+        )?;
+        self.set_entry_metadata(Some(metadata));
+        Ok(())
+    }
+
+    pub fn clear_entry_metadata(&self) {
+        self.set_entry_metadata(None);
+    }
+
+    /// Load an `alias<TAB>key` table of alternate spellings, replacing any
+    /// registered aliases; see [`KeyAliases`] for the format.
+    pub fn load_key_aliases(&self, path: String) -> Result<(), MDictError> {
+        self.set_key_aliases(KeyAliases::from_path(path)?);
+        Ok(())
+    }
+
+    /// Make `alias` lead to `key`, e.g. `colour` to `color`.
+    pub fn add_key_alias(&self, alias: String, key: String) {
+        let mut aliases = self.current_files().mdx.key_aliases();
+        aliases.insert(alias, key);
+        self.set_key_aliases(aliases);
+    }
+
+    pub fn clear_key_aliases(&self) {
+        self.set_key_aliases(KeyAliases::new());
+    }
+
+    /// Up to `limit` autocomplete candidates for `query`, best first:
+    /// prefix matches, then case-insensitive matches, then near misspellings.
+    pub fn suggest(&self, query: &str, limit: u32) -> Result<Vec<Suggestion>, MDictError> {
+        self.files()?.mdx.suggest(query, limit as usize)
+    }
+
+    /// Entries for `term` and its deinflected forms, e.g. `食べる` for
+    /// `食べました`, exact matches first.
+    pub fn lookup_deinflected(
+        &self,
+        term: &str,
+        deinflector: &Deinflector,
+    ) -> Result<Vec<DeinflectedMatch>, MDictError> {
+        self.files()?.mdx.lookup_deinflected(term, deinflector)
+    }
+
+    /// Every MDX entry for `key`, one per sense when the headword is listed
+    /// more than once, or for the key it is an alias of. Empty when `key` is
+    /// missing.
+    pub fn lookup(&self, key: &str) -> Result<Vec<KeyBlock>, MDictError> {
+        self.files()?.mdx.get_all(key)
     }
 
     pub fn record_at(&self, key_block: KeyBlock) -> Result<Vec<u8>, MDictError> {
-        let mut mdx = self.mdx.lock().unwrap();
-        let record_data = mdx.record_at_key_block(&key_block)?;
-        Ok(record_data)
+        self.files()?.mdx.record_at_key_block(&key_block)
+    }
+
+    /// The MDX record of `key` as a stream, for records too large to return
+    /// in one call. Redirects are not followed.
+    pub fn open_record_stream(&self, key: &str) -> Result<Arc<RecordStreamHandle>, MDictError> {
+        let files = self.files()?;
+        let key_block_idx = files
+            .mdx
+            .index_for(key)?
+            .ok_or_else(|| MDictError::KeyNotFound(format!("Key '{}' not found in MDX", key)))?;
+        let record = files
+            .mdx
+            .with(|mdict| mdict.record_ref_at_index(key_block_idx))?;
+        Ok(Arc::new(RecordStreamHandle::new(record)))
+    }
+
+    /// `record_at` decoded to text using the MDX header's encoding.
+    pub fn record_text_at(&self, key_block: KeyBlock) -> Result<String, MDictError> {
+        self.files()?.mdx.record_text_at_key_block(&key_block)
+    }
+
+    /// `record_text_at` with the MDX header's `StyleSheet` substitutions applied.
+    pub fn render_record_styled(&self, key_block: KeyBlock) -> Result<String, MDictError> {
+        self.files()?.mdx.render_record_styled(&key_block)
+    }
+
+    /// `record_text_at` post-processed for display, e.g. with compact HTML expanded.
+    pub fn record_rendered(
+        &self,
+        key_block: KeyBlock,
+        options: RenderOptions,
+    ) -> Result<String, MDictError> {
+        self.files()?.mdx.record_rendered(&key_block, options)
+    }
+
+    /// `record_text_at` without markup, for previews and speech.
+    pub fn record_plaintext_at(&self, key_block: KeyBlock) -> Result<String, MDictError> {
+        self.files()?.mdx.record_plaintext(&key_block)
+    }
+
+    /// `record_at`, following `@@@LINK=` redirects up to `max_depth` hops.
+    pub fn record_resolved(
+        &self,
+        key_block: KeyBlock,
+        max_depth: u32,
+    ) -> Result<Vec<u8>, MDictError> {
+        self.files()?.mdx.record_resolved(&key_block, max_depth)
+    }
+
+    /// Write an Anki-importable TSV file to `output_path` with a card for
+    /// each of `headwords`; see [`export_flashcards`].
+    pub fn export_flashcards(
+        &self,
+        headwords: Vec<String>,
+        template: FlashcardTemplate,
+        output_path: String,
+    ) -> Result<FlashcardExport, MDictError> {
+        self.files()?
+            .mdx
+            .with(|mdict| export_flashcards(mdict, &headwords, &template, &output_path))
+    }
+
+    /// Rewrite the MDX file to `output_path` with zstd-compressed blocks and
+    /// verify every entry reads back unchanged; see [`transcode_to_zstd`].
+    pub fn transcode_mdx(&self, output_path: String) -> Result<TranscodeStats, MDictError> {
+        self.files()?.mdx.with(|mdict| transcode_to_zstd(mdict, &output_path))
+    }
+
+    /// [`Self::transcode_mdx`] with blocks compressed as `compression`. LZO
+    /// and zlib output opens in stock MDict readers; zstd does not.
+    pub fn transcode_mdx_with_compression(
+        &self,
+        output_path: String,
+        compression: BlockCompression,
+    ) -> Result<TranscodeStats, MDictError> {
+        self.files()?
+            .mdx
+            .with(|mdict| transcode(mdict, &output_path, compression))
+    }
+
+    /// Pre-fill the redirect cache used by `record_resolved` from a readings
+    /// list written by `write_compressed_readings_list`. Returns how many
+    /// links were added.
+    pub fn prewarm_link_cache(&self, readings_list_path: String) -> Result<u64, MDictError> {
+        let readings = read_compressed_readings_list(readings_list_path)?;
+        Ok(self.files()?.mdx.prewarm_link_cache(&readings)? as u64)
+    }
+
+    /// Header attributes of the MDX file.
+    pub fn metadata(&self) -> DictionaryMetadata {
+        self.current_files().mdx.metadata()
+    }
+
+    /// Header attributes of the MDD file, if one was opened.
+    pub fn mdd_metadata(&self) -> Option<DictionaryMetadata> {
+        self.current_files().mdd.as_ref().map(MdictShared::metadata)
     }
 
     pub fn mdd_resource(&self, key: &str) -> Result<Option<Vec<u8>>, MDictError> {
-        let mut mdd_guard = self.mdd.lock().unwrap();
-        if let Some(mdd) = mdd_guard.as_mut() {
-            let key_block_idx = mdd
-                .key_block_index
-                .index_for(&mut mdd.reader, key)?
-                .ok_or_else(|| {
-                    MDictError::KeyNotFound(format!("Key '{}' not found in MDD", key))
-                })?;
-
-            let key_block = mdd
-                .key_block_index
-                .get(&mut mdd.reader, key_block_idx)?
-                .ok_or_else(|| {
-                    MDictError::KeyNotFound(format!("Key block for '{}' not found in MDD", key))
-                })?;
+        if let Some(mdd) = &self.files()?.mdd {
+            let key_block_idx = mdd.index_for(key)?.ok_or_else(|| {
+                MDictError::KeyNotFound(format!("Key '{}' not found in MDD", key))
+            })?;
+
+            let key_block = mdd.get(key_block_idx)?.ok_or_else(|| {
+                MDictError::KeyNotFound(format!("Key block for '{}' not found in MDD", key))
+            })?;
 
             let record_data = mdd.record_at_key_block(&key_block)?;
             Ok(Some(record_data))
@@ -170,6 +776,66 @@ impl MdictBundle {
         }
     }
 
+    /// Resolve a link from a record, as a WebView would request it:
+    /// `entry://word` gives the word's record (following redirects) as UTF-8
+    /// HTML, while `sound://` URIs, `file://` URIs and relative paths give the
+    /// MDD resource. Query strings and fragments are ignored and `%XX`
+    /// escapes decoded.
+    pub fn resolve_uri(&self, uri: &str) -> Result<ResolvedResource, MDictError> {
+        let (kind, target) = classify_link(uri).ok_or_else(|| {
+            MDictError::InvalidArgument(format!("'{}' is not a dictionary link", uri))
+        })?;
+        let target = target.split(['?', '#']).next().unwrap_or_default();
+        let target = percent_decode(target);
+
+        if kind == LinkKind::Entry {
+            let files = self.files()?;
+            let index = files.mdx.index_for(&target)?.ok_or_else(|| {
+                MDictError::KeyNotFound(format!("Key '{}' not found in MDX", target))
+            })?;
+            let key_block = files.mdx.get(index)?.ok_or_else(|| {
+                MDictError::KeyNotFound(format!("Key block for '{}' not found in MDX", target))
+            })?;
+            let record = files
+                .mdx
+                .record_resolved(&key_block, RESOLVE_URI_LINK_DEPTH)?;
+            let text = files.mdx.metadata().encoding.decode(&record);
+            return Ok(ResolvedResource {
+                kind,
+                data: text.into_bytes(),
+                mime_type: "text/html; charset=utf-8".to_string(),
+            });
+        }
+
+        // MDD keys are backslash paths from the archive root, e.g. `\img\a.png`.
+        let key = format!(
+            "\\{}",
+            target.trim_start_matches(['/', '\\']).replace('/', "\\")
+        );
+        let (data, mime_type) = self.mdd_resource_with_mime(&key)?.ok_or_else(|| {
+            MDictError::KeyNotFound(format!("No MDD opened to resolve '{}'", uri))
+        })?;
+        Ok(ResolvedResource {
+            kind,
+            data,
+            mime_type,
+        })
+    }
+
+    /// Number of headwords in the MDX, independent of any search prefix.
+    pub fn total_entries(&self) -> u64 {
+        self.current_files().mdx.num_entries()
+    }
+
+    /// Up to `count` headwords starting at index `start_index` of the full
+    /// key list, for browsing without a search prefix.
+    pub fn entries_page(&self, start_index: u64, count: u64) -> Result<Vec<KeyBlock>, MDictError> {
+        let start = usize::try_from(start_index)
+            .map_err(|_| MDictError::InvalidArgument("start_index overflow".to_string()))?;
+        let count = usize::try_from(count).unwrap_or(usize::MAX);
+        self.files()?.mdx.entries_page(start, count)
+    }
+
     pub fn len(&self) -> u64 {
         self.current_mdx_prefix_key_index
             .lock()
@@ -179,3 +845,35 @@ impl MdictBundle {
             .unwrap_or(0) as u64
     }
 }
+
+impl Dictionary for MdictBundle {
+    fn info(&self) -> DictionaryInfo {
+        self.current_files().mdx.info()
+    }
+
+    fn prefix_search(&self, prefix: &str, limit: usize) -> Result<Vec<KeyBlock>, MDictError> {
+        self.files()?.mdx.prefix_search(prefix, limit)
+    }
+
+    fn lookup(&self, key: &str) -> Result<Vec<KeyBlock>, MDictError> {
+        self.files()?.mdx.get_all(key)
+    }
+
+    fn record(&self, key_block: &KeyBlock) -> Result<Vec<u8>, MDictError> {
+        self.files()?.mdx.record_at_key_block(key_block)
+    }
+
+    fn record_text(&self, key_block: &KeyBlock) -> Result<String, MDictError> {
+        self.files()?.mdx.record_text_at_key_block(key_block)
+    }
+
+    /// The MDD resource at `path`, a backslash or slash path from the
+    /// archive root.
+    fn resource(&self, path: &str) -> Result<Option<Vec<u8>>, MDictError> {
+        let key = format!("\\{}", path.trim_start_matches(['/', '\\']).replace('/', "\\"));
+        match self.mdd_resource(&key) {
+            Err(MDictError::KeyNotFound(_)) => Ok(None),
+            result => result,
+        }
+    }
+}
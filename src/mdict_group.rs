@@ -0,0 +1,128 @@
+//! Several dictionaries behind one handle, searched as a single list.
+
+use std::sync::{Arc, RwLock};
+
+use crate::dictionary::Dictionary;
+use crate::error::MDictError;
+use crate::mdict_file::open_mapped;
+use crate::mdict_shared::MdictShared;
+use crate::types::{DictionaryInfo, DictionaryMetadata, GroupSearchHit, KeyBlock};
+
+struct GroupMember {
+    dictionary: Arc<dyn Dictionary>,
+    /// Header attributes of MDX members.
+    metadata: Option<DictionaryMetadata>,
+}
+
+/// A set of dictionaries opened together. Dictionaries are identified by the
+/// id `add_dictionary` or `add_backend` returns, which is their position in
+/// the group.
+#[derive(uniffi::Object)]
+pub struct MdictGroupHandle {
+    dictionaries: RwLock<Vec<GroupMember>>,
+}
+
+#[uniffi::export]
+pub fn create_mdict_group() -> MdictGroupHandle {
+    MdictGroupHandle {
+        dictionaries: RwLock::new(Vec::new()),
+    }
+}
+
+impl MdictGroupHandle {
+    fn with_dictionary<T>(
+        &self,
+        dict_id: u32,
+        f: impl FnOnce(&GroupMember) -> Result<T, MDictError>,
+    ) -> Result<T, MDictError> {
+        let dictionaries = self.dictionaries.read().unwrap();
+        let dictionary = dictionaries.get(dict_id as usize).ok_or_else(|| {
+            MDictError::InvalidArgument(format!("no dictionary with id {}", dict_id))
+        })?;
+        f(dictionary)
+    }
+
+    fn push(&self, member: GroupMember) -> Result<u32, MDictError> {
+        let mut dictionaries = self.dictionaries.write().unwrap();
+        let dict_id = u32::try_from(dictionaries.len())
+            .map_err(|_| MDictError::InvalidArgument("too many dictionaries".to_string()))?;
+        dictionaries.push(member);
+        Ok(dict_id)
+    }
+
+    /// Add a dictionary of any format, e.g. a StarDict dictionary or an
+    /// optimized index, to the group.
+    pub fn add_backend(&self, dictionary: Arc<dyn Dictionary>) -> Result<u32, MDictError> {
+        self.push(GroupMember {
+            dictionary,
+            metadata: None,
+        })
+    }
+}
+
+#[uniffi::export]
+impl MdictGroupHandle {
+    /// Open the MDX file at `mdx_path` and add it to the group.
+    pub fn add_dictionary(&self, mdx_path: String) -> Result<u32, MDictError> {
+        let mdict = open_mapped(&mdx_path)?;
+        self.push(GroupMember {
+            metadata: Some(mdict.metadata()),
+            dictionary: Arc::new(MdictShared::new(mdict)),
+        })
+    }
+
+    pub fn dictionary_count(&self) -> u32 {
+        self.dictionaries.read().unwrap().len() as u32
+    }
+
+    /// Header attributes of an MDX dictionary. Fails for other formats; see
+    /// `info`.
+    pub fn metadata(&self, dict_id: u32) -> Result<DictionaryMetadata, MDictError> {
+        self.with_dictionary(dict_id, |member| {
+            member.metadata.clone().ok_or_else(|| {
+                MDictError::UnsupportedFeature(format!("dictionary {} has no MDX header", dict_id))
+            })
+        })
+    }
+
+    pub fn info(&self, dict_id: u32) -> Result<DictionaryInfo, MDictError> {
+        self.with_dictionary(dict_id, |member| Ok(member.dictionary.info()))
+    }
+
+    /// Up to `limit` keys starting with `prefix` across every dictionary,
+    /// merged into key order. Equal keys are listed by dictionary id.
+    pub fn search(&self, prefix: &str, limit: u32) -> Result<Vec<GroupSearchHit>, MDictError> {
+        let limit = limit as usize;
+        let dictionaries = self.dictionaries.read().unwrap();
+
+        let mut hits = Vec::new();
+        for (dict_id, member) in dictionaries.iter().enumerate() {
+            for key in member.dictionary.prefix_search(prefix, limit)? {
+                hits.push(GroupSearchHit {
+                    dict_id: dict_id as u32,
+                    key,
+                });
+            }
+        }
+
+        hits.sort_by(|a, b| {
+            a.key
+                .key_text
+                .cmp(&b.key.key_text)
+                .then(a.dict_id.cmp(&b.dict_id))
+        });
+        hits.truncate(limit);
+        Ok(hits)
+    }
+
+    /// Record bytes for a key returned by [`Self::search`].
+    pub fn record(&self, dict_id: u32, key: KeyBlock) -> Result<Vec<u8>, MDictError> {
+        self.with_dictionary(dict_id, |member| member.dictionary.record(&key))
+    }
+
+    /// `record` decoded to text, using the header encoding of MDX
+    /// dictionaries.
+    pub fn record_text(&self, dict_id: u32, key: KeyBlock) -> Result<String, MDictError> {
+        self.with_dictionary(dict_id, |member| member.dictionary.record_text(&key))
+    }
+}
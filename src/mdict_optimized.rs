@@ -1,21 +1,61 @@
+use std::cmp::Reverse;
+use std::collections::HashSet;
 use std::path::Path;
-use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+#[cfg(feature = "threads")]
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "threads")]
+use std::thread::JoinHandle;
 
+use crate::dictionary::Dictionary;
 use crate::error::MDictError;
+use crate::links::link_target_from_record;
 use crate::mdict_file::MdictBundle;
+use crate::mdx_conversion::fst_indexing::{
+    create_fst_index_multi, create_romanized_index_from_map, create_suffix_index_from_map,
+};
 use crate::mdx_conversion::fst_map::FSTMap;
-use crate::types::{BuildProgressStage, KeyBlock, PrefixSearchCursor, PrefixSearchPage};
+use crate::mdx_conversion::optimized_bundle::BundleSections;
+use crate::metrics::Span;
+use crate::render::{decode_guessed, preview_text};
+use crate::transliterate::RomanizationScheme;
+use crate::types::{
+    BrokenLink, BuildProgressStage, DeltaStats, DictionaryFormat, DictionaryInfo, EntryId,
+    KeyBlock, KeyPreview, MetadataEntry, MetadataField, MetadataKind, MetadataValue,
+    PrefixSearchCursor, PrefixSearchPage, PrefixSearchPageWithPreview, PrefixSearchPrevCursor,
+};
+
+/// Redirect hops followed to preview a `@@@LINK=` result.
+const PREVIEW_LINK_DEPTH: u32 = 4;
 
 #[uniffi::export(callback_interface)]
 pub trait BuildProgressCallback: Send + Sync {
     fn on_progress(&self, stage: BuildProgressStage, completed: u64, total: u64);
 }
 
+/// Reorders the results within each prefix search page, e.g. to surface
+/// common or recently viewed words first. Pages still cover consecutive
+/// runs of keys; only the order inside a page changes.
+#[uniffi::export(callback_interface)]
+pub trait Ranker: Send + Sync {
+    /// Score for `key_text`, such as a frequency or history count. Higher
+    /// scores come first; equal scores keep key order.
+    fn score(&self, key_text: String) -> i64;
+}
+
 #[derive(uniffi::Object)]
 pub struct MdictOptimized {
     fst_map: Mutex<FSTMap>,
     current_prefix: Mutex<Option<String>>,
     current_page_size: Mutex<usize>,
+    /// Keys under `current_prefix`, counted when the prefix is set.
+    current_total: Mutex<Option<u64>>,
+    /// Distinct entries under `current_prefix`, counted on first `len()`.
+    current_len: Mutex<Option<u64>>,
+    ranker: Mutex<Option<Box<dyn Ranker>>>,
+    /// Redirects to missing keys found by the build that produced this index.
+    broken_links: Vec<BrokenLink>,
 }
 
 impl MdictOptimized {
@@ -25,17 +65,41 @@ impl MdictOptimized {
         record_path: impl AsRef<Path>,
     ) -> Result<Self, MDictError> {
         let fst_map = FSTMap::load_from_path(fst_path, readings_path, record_path)?;
-        Ok(Self {
+        Ok(Self::from_fst_map(fst_map))
+    }
+
+    /// Open a single-file bundle written by [`Self::save_bundle`].
+    pub fn open_bundle(path: impl AsRef<Path>) -> Result<Self, MDictError> {
+        let sections = BundleSections::open(path)?;
+        let mut fst_map = FSTMap::from_sections(sections.fst, sections.readings, sections.records)?;
+        if let Some(suffix_fst) = sections.suffix_fst {
+            fst_map.load_suffix_section(suffix_fst)?;
+        }
+        if let Some(romanized_fst) = sections.romanized_fst {
+            fst_map.load_romanized_section(romanized_fst)?;
+        }
+        if let Some(entry_ids) = sections.entry_ids {
+            fst_map.load_entry_id_section(entry_ids)?;
+        }
+        if let Some(aliases) = sections.aliases {
+            fst_map.load_alias_section(aliases)?;
+        }
+        Ok(Self::from_fst_map(fst_map))
+    }
+
+    fn from_fst_map(fst_map: FSTMap) -> Self {
+        Self {
             fst_map: Mutex::new(fst_map),
             current_prefix: Mutex::new(None),
             current_page_size: Mutex::new(0),
-        })
+            current_total: Mutex::new(None),
+            current_len: Mutex::new(None),
+            ranker: Mutex::new(None),
+            broken_links: Vec::new(),
+        }
     }
 
-    fn build_page_from_cursor(
-        &self,
-        cursor_after_key: Option<&str>,
-    ) -> Result<PrefixSearchPage, MDictError> {
+    fn current_search(&self) -> Result<(String, usize), MDictError> {
         let prefix = self
             .current_prefix
             .lock()
@@ -49,23 +113,125 @@ impl MdictOptimized {
                 "page_size must be greater than 0".to_string(),
             ));
         }
+        Ok((prefix, page_size))
+    }
 
-        let fst_map = self.fst_map.lock().unwrap();
-        let (rows, next_key) =
-            fst_map.get_link_page_for_prefix(&prefix, cursor_after_key, page_size)?;
-        let results = rows
-            .into_iter()
-            .map(|(key_text, key_id)| KeyBlock { key_id, key_text })
+    fn build_page_from_cursor(
+        &self,
+        cursor_after_key: Option<&str>,
+    ) -> Result<PrefixSearchPage, MDictError> {
+        let (prefix, page_size) = self.current_search()?;
+        let span = Span::start();
+        let page = self.fst_map.lock().unwrap().get_link_page_for_prefix(
+            &prefix,
+            cursor_after_key,
+            page_size,
+        )?;
+        span.search_finished("prefix page", &prefix, page.results.len());
+        Ok(self.ranked(page.into_search_page(*self.current_total.lock().unwrap())))
+    }
+
+    fn build_page_before_cursor(
+        &self,
+        cursor_before_key: &str,
+    ) -> Result<PrefixSearchPage, MDictError> {
+        let (prefix, page_size) = self.current_search()?;
+        let span = Span::start();
+        let page = self.fst_map.lock().unwrap().get_link_page_before_key(
+            &prefix,
+            cursor_before_key,
+            page_size,
+        )?;
+        span.search_finished("prefix page", &prefix, page.results.len());
+        Ok(self.ranked(page.into_search_page(*self.current_total.lock().unwrap())))
+    }
+
+    /// `page` with a preview of up to `preview_chars` characters per result.
+    /// Records are read in one batch; redirects are previewed through their
+    /// target, and a result whose record cannot be read gets an empty
+    /// preview.
+    fn with_previews(
+        &self,
+        page: PrefixSearchPage,
+        preview_chars: u32,
+    ) -> Result<PrefixSearchPageWithPreview, MDictError> {
+        let offsets = page
+            .results
+            .iter()
+            .map(|key| key.key_id)
             .collect::<Vec<_>>();
+        let records = self.fst_map.lock().unwrap().get_records_result(&offsets)?;
 
-        let next_cursor = next_key.map(|after_key| PrefixSearchCursor { after_key });
+        let results = page
+            .results
+            .into_iter()
+            .zip(records)
+            .map(|(key, record)| {
+                let record = match link_target_from_record(&record) {
+                    Some(_) => self
+                        .record_resolved(key.clone(), PREVIEW_LINK_DEPTH)
+                        .unwrap_or_default(),
+                    None => record,
+                };
+                KeyPreview {
+                    preview: preview_text(&record, preview_chars as usize),
+                    key,
+                }
+            })
+            .collect();
 
-        Ok(PrefixSearchPage {
+        Ok(PrefixSearchPageWithPreview {
             results,
-            next_cursor,
-            total_results: None,
+            next_cursor: page.next_cursor,
+            prev_cursor: page.prev_cursor,
+            total_results: page.total_results,
         })
     }
+
+    /// `page` reordered by the ranker if one is set, or else by the frequency
+    /// ranks stored at build time. Unranked entries keep key order after the
+    /// ranked ones.
+    fn ranked(&self, mut page: PrefixSearchPage) -> PrefixSearchPage {
+        if let Some(ranker) = self.ranker.lock().unwrap().as_ref() {
+            page.results
+                .sort_by_cached_key(|key| Reverse(ranker.score(key.key_text.clone())));
+            return page;
+        }
+        let fst_map = self.fst_map.lock().unwrap();
+        page.results.sort_by_cached_key(|key| {
+            fst_map
+                .get_readings(key.key_id)
+                .and_then(|(entry, _)| entry.rank)
+                .unwrap_or(u32::MAX)
+        });
+        page
+    }
+
+    /// The value of field `name` for `key_block`'s entry, after checking the
+    /// field exists with `kind`.
+    fn metadata_value(
+        &self,
+        key_block: KeyBlock,
+        name: &str,
+        kind: MetadataKind,
+    ) -> Result<Option<MetadataValue>, MDictError> {
+        let fst_map = self.fst_map.lock().unwrap();
+        let fields = fst_map.metadata_fields()?;
+        let field = fields.iter().find(|field| field.name == name).ok_or_else(|| {
+            MDictError::InvalidArgument(format!("unknown metadata field '{}'", name))
+        })?;
+        if field.kind != kind {
+            return Err(MDictError::InvalidArgument(format!(
+                "metadata field '{}' holds {:?} values, not {:?}",
+                name, field.kind, kind
+            )));
+        }
+        Ok(fst_map
+            .entry_metadata(key_block.key_id)?
+            .into_iter()
+            .find(|entry| entry.name == name)
+            .map(|entry| entry.value))
+    }
 }
 
 #[uniffi::export]
@@ -77,6 +243,12 @@ pub fn create_mdict_optimized_from_fst(
     MdictOptimized::from_fst_files(fst_path, readings_path, record_path)
 }
 
+/// Open a single-file bundle written by `MdictOptimized::save_bundle`.
+#[uniffi::export]
+pub fn open_mdict_optimized_bundle(bundle_path: String) -> Result<MdictOptimized, MDictError> {
+    MdictOptimized::open_bundle(bundle_path)
+}
+
 #[uniffi::export]
 pub fn create_mdict_optimized_from_bundle(
     bundle: &MdictBundle,
@@ -100,23 +272,165 @@ pub fn create_mdict_optimized_from_bundle_with_progress(
     readings_path: String,
     record_path: String,
     progress_callback: Option<Box<dyn BuildProgressCallback>>,
+) -> Result<MdictOptimized, MDictError> {
+    build_optimized(
+        bundle,
+        fst_path,
+        readings_path,
+        record_path,
+        progress_callback,
+        &AtomicBool::new(false),
+    )
+}
+
+/// Build the optimized files for `bundle`, a new release of a dictionary,
+/// from an earlier release's readings and records files, copying the
+/// compressed record blocks that did not change instead of compressing them
+/// again. Open the result with `create_mdict_optimized_from_fst`.
+#[uniffi::export]
+pub fn update_mdict_optimized_from_bundle(
+    bundle: &MdictBundle,
+    old_readings_path: String,
+    old_record_path: String,
+    fst_path: String,
+    readings_path: String,
+    record_path: String,
+) -> Result<DeltaStats, MDictError> {
+    bundle.update_fst_files(
+        old_readings_path,
+        old_record_path,
+        fst_path,
+        readings_path,
+        record_path,
+    )
+}
+
+/// Build one index over several bundles, so an app holds a single FST
+/// instead of one per dictionary. Entries remember which bundle they came
+/// from; see `MdictOptimized::source_id`.
+#[uniffi::export]
+pub fn create_mdict_optimized_from_bundles(
+    bundles: Vec<Arc<MdictBundle>>,
+    fst_path: String,
+    readings_path: String,
+    record_path: String,
+) -> Result<MdictOptimized, MDictError> {
+    let mut mdicts = Vec::with_capacity(bundles.len());
+    let mut readings_lists = Vec::with_capacity(bundles.len());
+    for bundle in &bundles {
+        let (mdict, readings_list) = bundle.readings_source()?;
+        mdicts.push(mdict);
+        readings_lists.push(readings_list);
+    }
+    create_fst_index_multi(
+        &mut mdicts,
+        &readings_lists,
+        &fst_path,
+        &readings_path,
+        &record_path,
+    )?;
+    MdictOptimized::from_fst_files(fst_path, readings_path, record_path)
+}
+
+fn build_optimized(
+    bundle: &MdictBundle,
+    fst_path: String,
+    readings_path: String,
+    record_path: String,
+    progress_callback: Option<Box<dyn BuildProgressCallback>>,
+    cancel: &AtomicBool,
 ) -> Result<MdictOptimized, MDictError> {
     if let Some(callback) = progress_callback.as_ref() {
         callback.on_progress(BuildProgressStage::Start, 0, 3);
     }
 
+    let mut broken_links = Vec::new();
     bundle.build_fst_files_with_progress(
         &fst_path,
         &readings_path,
         &record_path,
+        cancel,
         |stage, completed, total| {
             if let Some(callback) = progress_callback.as_ref() {
                 callback.on_progress(stage, completed, total);
             }
         },
+        |links| broken_links = links,
     )?;
 
-    MdictOptimized::from_fst_files(fst_path, readings_path, record_path)
+    let mut optimized = MdictOptimized::from_fst_files(fst_path, readings_path, record_path)?;
+    optimized.broken_links = broken_links;
+    Ok(optimized)
+}
+
+/// A build started by [`start_build_optimized`].
+#[cfg(feature = "threads")]
+#[derive(uniffi::Object)]
+pub struct BuildHandle {
+    cancel: Arc<AtomicBool>,
+    worker: Mutex<Option<JoinHandle<Result<MdictOptimized, MDictError>>>>,
+}
+
+#[cfg(feature = "threads")]
+#[uniffi::export]
+impl BuildHandle {
+    /// Ask the build to stop. It stops at the next pass or record boundary,
+    /// and `join` then fails with `Cancelled`.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.worker
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_none_or(|worker| worker.is_finished())
+    }
+
+    /// Wait for the build and return the opened index. Only the first call
+    /// gets the result.
+    pub fn join(&self) -> Result<Arc<MdictOptimized>, MDictError> {
+        let worker = self.worker.lock().unwrap().take().ok_or_else(|| {
+            MDictError::InvalidArgument("build has already been joined".to_string())
+        })?;
+        let optimized = worker
+            .join()
+            .map_err(|_| MDictError::Io("index build thread panicked".to_string()))??;
+        Ok(Arc::new(optimized))
+    }
+}
+
+/// Build an optimized index on a background thread. The parallel passes run
+/// on the shared worker pool, as they do for a blocking build.
+#[cfg(feature = "threads")]
+#[uniffi::export]
+pub fn start_build_optimized(
+    bundle: Arc<MdictBundle>,
+    fst_path: String,
+    readings_path: String,
+    record_path: String,
+    progress_callback: Option<Box<dyn BuildProgressCallback>>,
+) -> Result<BuildHandle, MDictError> {
+    let cancel = Arc::new(AtomicBool::new(false));
+    let worker_cancel = Arc::clone(&cancel);
+    let worker = std::thread::Builder::new()
+        .name("mdict-index-build".to_string())
+        .spawn(move || {
+            build_optimized(
+                &bundle,
+                fst_path,
+                readings_path,
+                record_path,
+                progress_callback,
+                &worker_cancel,
+            )
+        })?;
+
+    Ok(BuildHandle {
+        cancel,
+        worker: Mutex::new(Some(worker)),
+    })
 }
 
 #[uniffi::export]
@@ -134,14 +448,51 @@ impl MdictOptimized {
             ));
         }
 
-        let fst_map = self.fst_map.lock().unwrap();
-        drop(fst_map);
+        let total = self.fst_map.lock().unwrap().count_prefix(prefix);
 
         *self.current_page_size.lock().unwrap() = page_size;
         *self.current_prefix.lock().unwrap() = Some(prefix.to_string());
+        *self.current_total.lock().unwrap() = Some(total);
+        *self.current_len.lock().unwrap() = None;
         self.build_page_from_cursor(None)
     }
 
+    /// `set_search_prefix_paged` with the first `preview_chars` characters
+    /// of each result's definition as plain text, read in one batch.
+    pub fn set_search_prefix_paged_with_preview(
+        &self,
+        prefix: &str,
+        page_size: u64,
+        preview_chars: u32,
+    ) -> Result<PrefixSearchPageWithPreview, MDictError> {
+        let page = self.set_search_prefix_paged(prefix, page_size)?;
+        self.with_previews(page, preview_chars)
+    }
+
+    pub fn prefix_search_next_page_with_preview(
+        &self,
+        cursor: PrefixSearchCursor,
+        preview_chars: u32,
+    ) -> Result<PrefixSearchPageWithPreview, MDictError> {
+        let page = self.prefix_search_next_page(cursor)?;
+        self.with_previews(page, preview_chars)
+    }
+
+    pub fn prefix_search_prev_page_with_preview(
+        &self,
+        cursor: PrefixSearchPrevCursor,
+        preview_chars: u32,
+    ) -> Result<PrefixSearchPageWithPreview, MDictError> {
+        let page = self.prefix_search_prev_page(cursor)?;
+        self.with_previews(page, preview_chars)
+    }
+
+    /// Reorder each page from now on with `ranker`, or keep key order when
+    /// `None`. Cursors are unaffected, so paging works as before.
+    pub fn set_ranker(&self, ranker: Option<Box<dyn Ranker>>) {
+        *self.ranker.lock().unwrap() = ranker;
+    }
+
     pub fn prefix_search_next_page(
         &self,
         cursor: PrefixSearchCursor,
@@ -154,25 +505,356 @@ impl MdictOptimized {
         self.build_page_from_cursor(Some(&cursor.after_key))
     }
 
+    /// The page just before the one `cursor` came from, for scrolling back up.
+    pub fn prefix_search_prev_page(
+        &self,
+        cursor: PrefixSearchPrevCursor,
+    ) -> Result<PrefixSearchPage, MDictError> {
+        if cursor.before_key.is_empty() {
+            return Err(MDictError::InvalidArgument(
+                "cursor.before_key must not be empty".to_string(),
+            ));
+        }
+        self.build_page_before_cursor(&cursor.before_key)
+    }
+
+    /// Every entry stored under exactly `key`, one per sense when the source
+    /// dictionary lists the headword more than once. A key missing from the
+    /// index is looked up again under the key it is an alias of.
+    pub fn lookup(&self, key: &str) -> Vec<KeyBlock> {
+        let fst_map = self.fst_map.lock().unwrap();
+        let mut key = key;
+        let mut key_ids = fst_map.get_all(key);
+        if key_ids.is_empty() {
+            if let Some(target) = fst_map.resolve_alias(key) {
+                key = target;
+                key_ids = fst_map.get_all(key);
+            }
+        }
+        key_ids
+            .into_iter()
+            .map(|key_id| KeyBlock {
+                key_id,
+                key_text: key.to_string(),
+            })
+            .collect()
+    }
+
+    /// The key `alias` leads to in the alias table the index was built with.
+    pub fn resolve_alias(&self, alias: &str) -> Option<String> {
+        self.fst_map
+            .lock()
+            .unwrap()
+            .resolve_alias(alias)
+            .map(str::to_string)
+    }
+
+    /// Typo-tolerant lookup: keys within `max_distance` edits of `query`.
+    pub fn search_keys_fuzzy(
+        &self,
+        query: &str,
+        max_distance: u32,
+    ) -> Result<Vec<KeyBlock>, MDictError> {
+        let fst_map = self.fst_map.lock().unwrap();
+        let rows = fst_map.search_fuzzy(query, max_distance)?;
+        Ok(rows
+            .into_iter()
+            .map(|(key_text, key_id)| KeyBlock { key_id, key_text })
+            .collect())
+    }
+
+    /// Keys matching a `*` / `?` glob such as `食*る`.
+    pub fn search_keys_glob(&self, pattern: &str) -> Result<Vec<KeyBlock>, MDictError> {
+        let fst_map = self.fst_map.lock().unwrap();
+        let rows = fst_map.search_glob(pattern)?;
+        Ok(rows
+            .into_iter()
+            .map(|(key_text, key_id)| KeyBlock { key_id, key_text })
+            .collect())
+    }
+
+    /// Keys ending in `suffix`, such as a conjugation ending. Needs a suffix
+    /// index from [`Self::build_suffix_index`] or [`Self::load_suffix_index`].
+    pub fn search_keys_suffix(&self, suffix: &str) -> Result<Vec<KeyBlock>, MDictError> {
+        let fst_map = self.fst_map.lock().unwrap();
+        let rows = fst_map.search_suffix(suffix)?;
+        Ok(rows
+            .into_iter()
+            .map(|(key_text, key_id)| KeyBlock { key_id, key_text })
+            .collect())
+    }
+
+    /// Write a suffix index for this dictionary to `suffix_path` and load it.
+    pub fn build_suffix_index(&self, suffix_path: String) -> Result<(), MDictError> {
+        let mut fst_map = self.fst_map.lock().unwrap();
+        create_suffix_index_from_map(fst_map.map(), &suffix_path)?;
+        fst_map.load_suffix_index(suffix_path)
+    }
+
+    /// Load a suffix index written earlier by [`Self::build_suffix_index`].
+    pub fn load_suffix_index(&self, suffix_path: String) -> Result<(), MDictError> {
+        self.fst_map.lock().unwrap().load_suffix_index(suffix_path)
+    }
+
+    pub fn has_suffix_index(&self) -> bool {
+        self.fst_map.lock().unwrap().has_suffix_index()
+    }
+
+    /// Keys whose romanized form starts with `query`, e.g. `たべる` for
+    /// `tabe` or `nǐ hǎo` for `ni3h`. Tone marks, tone numbers, case and
+    /// spaces in `query` are ignored. Needs a romanized index from
+    /// [`Self::build_romanized_index`] or [`Self::load_romanized_index`].
+    pub fn search_keys_romanized(&self, query: &str) -> Result<Vec<KeyBlock>, MDictError> {
+        let fst_map = self.fst_map.lock().unwrap();
+        let rows = fst_map.search_romanized(query)?;
+        Ok(rows
+            .into_iter()
+            .map(|(key_text, key_id)| KeyBlock { key_id, key_text })
+            .collect())
+    }
+
+    /// Write a romanized index of the keys in `scheme` to `romanized_path`
+    /// and load it.
+    pub fn build_romanized_index(
+        &self,
+        romanized_path: String,
+        scheme: RomanizationScheme,
+    ) -> Result<(), MDictError> {
+        let mut fst_map = self.fst_map.lock().unwrap();
+        create_romanized_index_from_map(fst_map.map(), &romanized_path, scheme)?;
+        fst_map.load_romanized_index(romanized_path)
+    }
+
+    /// Load a romanized index written earlier by [`Self::build_romanized_index`].
+    pub fn load_romanized_index(&self, romanized_path: String) -> Result<(), MDictError> {
+        self.fst_map
+            .lock()
+            .unwrap()
+            .load_romanized_index(romanized_path)
+    }
+
+    pub fn has_romanized_index(&self) -> bool {
+        self.fst_map.lock().unwrap().has_romanized_index()
+    }
+
+    /// The id of `key_block`'s entry, which stays the same when the index is
+    /// rebuilt from the same dictionary, for bookmarks and history. Indexes
+    /// built before entry ids existed fail with `UnsupportedFeature`.
+    pub fn entry_id(&self, key_block: KeyBlock) -> Result<EntryId, MDictError> {
+        self.fst_map
+            .lock()
+            .unwrap()
+            .entry_id_for_offset(key_block.key_id)?
+            .ok_or_else(|| {
+                MDictError::KeyNotFound(format!("no entry id for '{}'", key_block.key_text))
+            })
+    }
+
+    /// The entry `id` names in this build, titled with its first reading.
+    pub fn key_block_for_entry_id(&self, id: EntryId) -> Result<KeyBlock, MDictError> {
+        let fst_map = self.fst_map.lock().unwrap();
+        let key_id = fst_map
+            .offset_for_entry_id(id)?
+            .ok_or_else(|| MDictError::KeyNotFound(format!("no entry with id {}", id.0)))?;
+        let (readings_entry, _) = fst_map.get_readings_result(key_id)?;
+        Ok(KeyBlock {
+            key_id,
+            key_text: readings_entry
+                .readings
+                .into_iter()
+                .next()
+                .unwrap_or_default(),
+        })
+    }
+
+    pub fn record_for_entry_id(&self, id: EntryId) -> Result<Vec<u8>, MDictError> {
+        self.record_at(self.key_block_for_entry_id(id)?)
+    }
+
+    pub fn has_entry_ids(&self) -> bool {
+        self.fst_map.lock().unwrap().has_entry_ids()
+    }
+
+    /// Write the index, readings, records, entry ids, alias table and any
+    /// loaded suffix or romanized index to `bundle_path` as one file, to be reopened with
+    /// `open_mdict_optimized_bundle`. The source MDX is not included.
+    pub fn save_bundle(&self, bundle_path: String) -> Result<(), MDictError> {
+        let sections = self.fst_map.lock().unwrap().sections()?;
+        sections.write_to_path(bundle_path)
+    }
+
     pub fn record_at(&self, key_block: KeyBlock) -> Result<Vec<u8>, MDictError> {
         let fst_map = self.fst_map.lock().unwrap();
         let (_, record_size) = fst_map.get_readings_result(key_block.key_id)?;
         fst_map.get_record_result(key_block.key_id, record_size)
     }
 
+    /// `record_at`, following `@@@LINK=` redirects up to `max_depth` hops.
+    /// Links resolvable at build time are already folded into the index, so
+    /// this only matters for chained or late-bound redirects.
+    pub fn record_resolved(
+        &self,
+        key_block: KeyBlock,
+        max_depth: u32,
+    ) -> Result<Vec<u8>, MDictError> {
+        let fst_map = self.fst_map.lock().unwrap();
+        let mut visited = HashSet::from([key_block.key_id]);
+        let (_, record_size) = fst_map.get_readings_result(key_block.key_id)?;
+        let mut record = fst_map.get_record_result(key_block.key_id, record_size)?;
+
+        for _ in 0..max_depth {
+            let Some(target) = link_target_from_record(&record) else {
+                return Ok(record);
+            };
+            let key_id = fst_map.get(&target).ok_or_else(|| {
+                MDictError::KeyNotFound(format!("link target '{}' not found", target))
+            })?;
+            if !visited.insert(key_id) {
+                return Err(MDictError::InvalidFormat(format!(
+                    "link cycle at '{}'",
+                    target
+                )));
+            }
+            let (_, record_size) = fst_map.get_readings_result(key_id)?;
+            record = fst_map.get_record_result(key_id, record_size)?;
+        }
+
+        if link_target_from_record(&record).is_some() {
+            return Err(MDictError::InvalidFormat(format!(
+                "link chain from '{}' exceeds {} hops",
+                key_block.key_text, max_depth
+            )));
+        }
+        Ok(record)
+    }
+
+    /// The frequency rank stored for `key_block`'s entry, if the index was
+    /// built with a frequency list that ranks it. 1 is the most frequent.
+    pub fn frequency_rank(&self, key_block: KeyBlock) -> Result<Option<u32>, MDictError> {
+        let fst_map = self.fst_map.lock().unwrap();
+        let (readings_entry, _) = fst_map.get_readings_result(key_block.key_id)?;
+        Ok(readings_entry.rank)
+    }
+
+    /// Fields of the per-entry metadata the index was built with, in the
+    /// order they were declared; empty if it was built without any.
+    pub fn metadata_schema(&self) -> Result<Vec<MetadataField>, MDictError> {
+        self.fst_map.lock().unwrap().metadata_fields()
+    }
+
+    /// Every metadata value stored for `key_block`'s entry, in field order.
+    /// Fields the entry has no value for are left out.
+    pub fn entry_metadata(&self, key_block: KeyBlock) -> Result<Vec<MetadataEntry>, MDictError> {
+        self.fst_map.lock().unwrap().entry_metadata(key_block.key_id)
+    }
+
+    /// The integer field `name` of `key_block`'s entry. Fails if the schema
+    /// has no such integer field.
+    pub fn metadata_integer(
+        &self,
+        key_block: KeyBlock,
+        name: &str,
+    ) -> Result<Option<i64>, MDictError> {
+        Ok(match self.metadata_value(key_block, name, MetadataKind::Integer)? {
+            Some(MetadataValue::Integer { value }) => Some(value),
+            _ => None,
+        })
+    }
+
+    /// The float field `name` of `key_block`'s entry.
+    pub fn metadata_float(
+        &self,
+        key_block: KeyBlock,
+        name: &str,
+    ) -> Result<Option<f64>, MDictError> {
+        Ok(match self.metadata_value(key_block, name, MetadataKind::Float)? {
+            Some(MetadataValue::Float { value }) => Some(value),
+            _ => None,
+        })
+    }
+
+    /// The text field `name` of `key_block`'s entry.
+    pub fn metadata_text(
+        &self,
+        key_block: KeyBlock,
+        name: &str,
+    ) -> Result<Option<String>, MDictError> {
+        Ok(match self.metadata_value(key_block, name, MetadataKind::Text)? {
+            Some(MetadataValue::Text { value }) => Some(value),
+            _ => None,
+        })
+    }
+
+    /// The `@@@LINK=` redirects whose target is not a key, in key order, as
+    /// found by the build that returned this index under a `Strict` or
+    /// `Repair` broken link policy. Empty for other builds and for indexes
+    /// opened from existing files.
+    pub fn broken_links(&self) -> Vec<BrokenLink> {
+        self.broken_links.clone()
+    }
+
+    /// Position of the dictionary `key_block`'s entry came from in a
+    /// combined index, or `None` for an index of a single dictionary.
+    pub fn source_id(&self, key_block: KeyBlock) -> Result<Option<u32>, MDictError> {
+        let fst_map = self.fst_map.lock().unwrap();
+        let (readings_entry, _) = fst_map.get_readings_result(key_block.key_id)?;
+        Ok(readings_entry.source)
+    }
+
     pub fn get_readings(&self, key_block: KeyBlock) -> Result<Vec<String>, MDictError> {
         let fst_map = self.fst_map.lock().unwrap();
         let (readings_entry, _) = fst_map.get_readings_result(key_block.key_id)?;
         Ok(readings_entry.readings)
     }
 
+    /// Number of keys starting with `prefix`, the same count a paged search
+    /// reports in `total_results`, without listing them.
+    pub fn count_prefix(&self, prefix: &str) -> u64 {
+        self.fst_map.lock().unwrap().count_prefix(prefix)
+    }
+
+    /// Distinct entries under the current search prefix.
     pub fn len(&self) -> u64 {
         let prefix = match self.current_prefix.lock().unwrap().clone() {
             Some(prefix) => prefix,
             None => return 0,
         };
 
-        let fst_map = self.fst_map.lock().unwrap();
-        fst_map.get_link_for_key_dedup(&prefix).count() as u64
+        let mut current_len = self.current_len.lock().unwrap();
+        *current_len
+            .get_or_insert_with(|| self.fst_map.lock().unwrap().count_prefix_distinct(&prefix))
+    }
+}
+
+/// Optimized indexes keep no header, so they have no title, and records are
+/// decoded as UTF-16LE when they look like it and as UTF-8 otherwise.
+impl Dictionary for MdictOptimized {
+    fn info(&self) -> DictionaryInfo {
+        DictionaryInfo {
+            format: DictionaryFormat::Optimized,
+            title: None,
+            description: None,
+            entry_count: self.fst_map.lock().unwrap().map().len() as u64,
+        }
+    }
+
+    fn prefix_search(&self, prefix: &str, limit: usize) -> Result<Vec<KeyBlock>, MDictError> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        let page = self.fst_map.lock().unwrap().prefix_page(prefix, None, limit)?;
+        Ok(page.results)
+    }
+
+    fn lookup(&self, key: &str) -> Result<Vec<KeyBlock>, MDictError> {
+        Ok(MdictOptimized::lookup(self, key))
+    }
+
+    fn record(&self, key_block: &KeyBlock) -> Result<Vec<u8>, MDictError> {
+        self.record_at(key_block.clone())
+    }
+
+    fn record_text(&self, key_block: &KeyBlock) -> Result<String, MDictError> {
+        Ok(decode_guessed(&self.record_at(key_block.clone())?))
     }
 }
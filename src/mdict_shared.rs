@@ -0,0 +1,196 @@
+//! An `Mdict` that can be queried from several threads at once.
+
+use std::io::{Read, Seek};
+use std::sync::Mutex;
+
+use crate::deinflect::{DeinflectedMatch, Deinflector};
+use crate::dictionary::Dictionary;
+use crate::error::Result;
+use crate::format::HeaderInfo;
+use crate::mdx_conversion::aliases::KeyAliases;
+use crate::mdx_conversion::reindexing::ReadingsListMap;
+use crate::record_ref::RecordRef;
+use crate::render::RenderOptions;
+use crate::types::{DictionaryFormat, DictionaryInfo, DictionaryMetadata, KeyBlock, Suggestion};
+use crate::Mdict;
+
+/// Thread-safe front for an [`Mdict`].
+///
+/// `Mdict` needs `&mut self` for its reader position and block caches. This
+/// keeps a pool of handles created with [`Mdict::with_reader`], each with its
+/// own clone of the reader, so concurrent calls run on separate handles
+/// instead of queueing on one lock. Parsed header and indexes are shared;
+/// block caches are per handle and survive between calls.
+pub struct MdictShared<R: Read + Seek + Clone> {
+    base: Mdict<R>,
+    idle: Mutex<Vec<Mdict<R>>>,
+}
+
+impl<R: Read + Seek + Clone> MdictShared<R> {
+    pub fn new(mdict: Mdict<R>) -> Self {
+        Self {
+            base: mdict,
+            idle: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn header(&self) -> &HeaderInfo {
+        &self.base.key_block_index.header
+    }
+
+    pub fn metadata(&self) -> DictionaryMetadata {
+        self.base.metadata()
+    }
+
+    pub fn num_entries(&self) -> u64 {
+        self.base.key_block_index.key_section.num_entries
+    }
+
+    /// Run `f` on a handle no other thread is using.
+    pub fn with<T>(&self, f: impl FnOnce(&mut Mdict<R>) -> Result<T>) -> Result<T> {
+        let mut handle = self.checkout();
+        let result = f(&mut handle);
+        self.idle.lock().unwrap().push(handle);
+        result
+    }
+
+    pub fn get(&self, index: usize) -> Result<Option<KeyBlock>> {
+        self.with(|mdict| mdict.key_block_index.get(&mut mdict.reader, index))
+    }
+
+    /// See [`Mdict::entries_page`].
+    pub fn entries_page(&self, start: usize, count: usize) -> Result<Vec<KeyBlock>> {
+        self.with(|mdict| mdict.entries_page(start, count))
+    }
+
+    pub fn index_for(&self, key: &str) -> Result<Option<usize>> {
+        self.with(|mdict| mdict.key_block_index.index_for(&mut mdict.reader, key))
+    }
+
+    /// See [`Mdict::set_key_aliases`]; the table is shared by all handles.
+    pub fn set_key_aliases(&self, aliases: KeyAliases) {
+        self.base.set_key_aliases(aliases);
+    }
+
+    pub fn key_aliases(&self) -> KeyAliases {
+        self.base.key_aliases()
+    }
+
+    /// See [`Mdict::get_all`].
+    pub fn get_all(&self, key: &str) -> Result<Vec<KeyBlock>> {
+        self.with(|mdict| mdict.get_all(key))
+    }
+
+    /// See [`Mdict::lookup`].
+    pub fn lookup(&self, key: &str) -> Result<Vec<Vec<u8>>> {
+        self.with(|mdict| mdict.lookup(key))
+    }
+
+    /// Every key starting with `prefix`, in key order.
+    pub fn search_keys_prefix(&self, prefix: &str) -> Result<Vec<KeyBlock>> {
+        self.with(|mdict| mdict.search_keys_prefix(prefix)?.collect_to_vec())
+    }
+
+    /// Ranked autocomplete candidates; see [`Mdict::suggest`].
+    pub fn suggest(&self, query: &str, limit: usize) -> Result<Vec<Suggestion>> {
+        self.with(|mdict| mdict.suggest(query, limit))
+    }
+
+    /// See [`Mdict::lookup_deinflected`].
+    pub fn lookup_deinflected(
+        &self,
+        term: &str,
+        deinflector: &Deinflector,
+    ) -> Result<Vec<DeinflectedMatch>> {
+        self.with(|mdict| mdict.lookup_deinflected(term, deinflector))
+    }
+
+    pub fn prefix_range_bounds(&self, prefix: &str) -> Result<Option<(usize, usize)>> {
+        self.with(|mdict| mdict.prefix_range_bounds(prefix))
+    }
+
+    pub fn record_at_key_block(&self, key_block: &KeyBlock) -> Result<Vec<u8>> {
+        self.with(|mdict| mdict.record_at_key_block(key_block))
+    }
+
+    /// See [`Mdict::record_ref_at_key_block`].
+    pub fn record_ref_at_key_block(&self, key_block: &KeyBlock) -> Result<RecordRef> {
+        self.with(|mdict| mdict.record_ref_at_key_block(key_block))
+    }
+
+    /// See [`Mdict::record_size_for`].
+    pub fn record_size_for(&self, key_block: &KeyBlock) -> Result<u64> {
+        self.with(|mdict| mdict.record_size_for(key_block))
+    }
+
+    pub fn record_at_index(&self, index: usize) -> Result<Vec<u8>> {
+        self.with(|mdict| mdict.record_at_index(index))
+    }
+
+    pub fn record_text_at_key_block(&self, key_block: &KeyBlock) -> Result<String> {
+        self.with(|mdict| mdict.record_text_at_key_block(key_block))
+    }
+
+    pub fn render_record_styled(&self, key_block: &KeyBlock) -> Result<String> {
+        self.with(|mdict| mdict.render_record_styled(key_block))
+    }
+
+    pub fn record_rendered(&self, key_block: &KeyBlock, options: RenderOptions) -> Result<String> {
+        self.with(|mdict| mdict.record_rendered(key_block, options))
+    }
+
+    pub fn record_plaintext(&self, key_block: &KeyBlock) -> Result<String> {
+        self.with(|mdict| mdict.record_plaintext(key_block))
+    }
+
+    pub fn record_resolved(&self, key_block: &KeyBlock, max_depth: u32) -> Result<Vec<u8>> {
+        self.with(|mdict| mdict.record_resolved(key_block, max_depth))
+    }
+
+    /// See [`Mdict::prewarm_link_cache`]; the cache is shared by all handles.
+    pub fn prewarm_link_cache(&self, readings: &ReadingsListMap) -> Result<usize> {
+        self.with(|mdict| mdict.prewarm_link_cache(readings))
+    }
+
+    /// A handle of its own that is not returned to the pool, for work that
+    /// holds several dictionaries' handles at once.
+    pub fn handle(&self) -> Mdict<R> {
+        self.checkout()
+    }
+
+    fn checkout(&self) -> Mdict<R> {
+        let idle = self.idle.lock().unwrap().pop();
+        idle.unwrap_or_else(|| self.base.with_reader(self.base.reader.clone()))
+    }
+}
+
+impl<R: Read + Seek + Clone + Send + Sync> Dictionary for MdictShared<R> {
+    fn info(&self) -> DictionaryInfo {
+        let metadata = self.metadata();
+        DictionaryInfo {
+            format: DictionaryFormat::Mdx,
+            title: metadata.title,
+            description: metadata.description,
+            entry_count: self.num_entries(),
+        }
+    }
+
+    fn prefix_search(&self, prefix: &str, limit: usize) -> Result<Vec<KeyBlock>> {
+        let Some((start, end)) = self.prefix_range_bounds(prefix)? else {
+            return Ok(Vec::new());
+        };
+        self.entries_page(start, end.saturating_sub(start).min(limit))
+    }
+
+    fn lookup(&self, key: &str) -> Result<Vec<KeyBlock>> {
+        self.get_all(key)
+    }
+
+    fn record(&self, key_block: &KeyBlock) -> Result<Vec<u8>> {
+        self.record_at_key_block(key_block)
+    }
+
+    fn record_text(&self, key_block: &KeyBlock) -> Result<String> {
+        self.record_text_at_key_block(key_block)
+    }
+}
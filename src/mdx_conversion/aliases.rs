@@ -0,0 +1,122 @@
+//! Alternate spellings that lead to another key, e.g. `colour` -> `color` or
+//! a traditional Chinese headword -> its simplified form.
+//!
+//! A table is tab-separated text with one `alias<TAB>key` pair per line.
+//! Blank lines and lines starting with `#` are skipped. Aliases resolve one
+//! hop; an alias of an alias is not followed.
+
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use crate::error::{MDictError, Result};
+use crate::mdx_conversion::build_manifest::hash_parts;
+use crate::mdx_conversion::reindexing::ReadingsListMap;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyAliases {
+    /// alias -> key, ordered so the table serializes the same way every time.
+    targets: BTreeMap<String, String>,
+}
+
+impl KeyAliases {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn parse(tsv: &str) -> Result<Self> {
+        let mut aliases = Self::new();
+        for (line_number, line) in tsv.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || {
+                MDictError::InvalidFormat(format!(
+                    "alias table line {}: expected `alias<TAB>key`",
+                    line_number + 1
+                ))
+            };
+            let (alias, key) = line.split_once('\t').ok_or_else(invalid)?;
+            if alias.is_empty() || key.is_empty() || key.contains('\t') {
+                return Err(invalid());
+            }
+            aliases.insert(alias, key);
+        }
+        Ok(aliases)
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Make `alias` lead to `key`, replacing any earlier target. An alias
+    /// equal to its key is ignored.
+    pub fn insert(&mut self, alias: impl Into<String>, key: impl Into<String>) {
+        let (alias, key) = (alias.into(), key.into());
+        if alias != key {
+            self.targets.insert(alias, key);
+        }
+    }
+
+    /// The key `alias` leads to.
+    pub fn resolve(&self, alias: &str) -> Option<&str> {
+        self.targets.get(alias).map(String::as_str)
+    }
+
+    /// `(alias, key)` pairs in alias order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.targets
+            .iter()
+            .map(|(alias, key)| (alias.as_str(), key.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.targets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    /// The table in the format [`Self::parse`] reads.
+    pub fn to_tsv(&self) -> String {
+        self.iter()
+            .map(|(alias, key)| format!("{}\t{}\n", alias, key))
+            .collect()
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn write_to_path(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, self.to_tsv())?;
+        Ok(())
+    }
+
+    /// Hash of the table's contents, for build fingerprints.
+    pub fn digest(&self) -> String {
+        hash_parts(&[self.to_tsv().as_bytes()])
+    }
+
+    /// Add each alias as a reading of every entry its key is a reading of,
+    /// so the alias is indexed alongside the key.
+    pub fn apply(&self, readings_list: &mut ReadingsListMap) {
+        if self.is_empty() {
+            return;
+        }
+        let mut aliases_of: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (alias, key) in self.iter() {
+            aliases_of.entry(key).or_default().push(alias);
+        }
+
+        for readings in readings_list.values_mut() {
+            let added = readings
+                .iter()
+                .filter_map(|reading| aliases_of.get(reading.as_str()))
+                .flatten()
+                .map(|alias| alias.to_string())
+                .collect::<Vec<_>>();
+            readings.extend(added);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! Checkpoints for resumable optimized-index builds.
+//!
+//! A build keeps a manifest next to the FST recording a fingerprint of its
+//! input (the MDX contents and the settings that shape the outputs), the last
+//! stage that finished, and content hashes of the files that stage wrote. A
+//! later build of the same input resumes after the last stage whose files are
+//! still intact; any other leftovers are treated as stale and rebuilt.
+
+use std::collections::BTreeMap;
+#[cfg(feature = "fs")]
+use std::fs::File;
+#[cfg(feature = "fs")]
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+use ripemd::{Digest, Ripemd128};
+
+#[cfg(feature = "fs")]
+use crate::error::Result;
+
+#[cfg(feature = "fs")]
+const MANIFEST_HEADER: &str = "mdict_tools build manifest 1";
+
+/// Role of the readings-list checkpoint in [`BuildManifest::files`].
+pub const READINGS_LIST_FILE: &str = "readings_list";
+pub const FST_FILE: &str = "fst";
+pub const READINGS_FILE: &str = "readings";
+pub const RECORDS_FILE: &str = "records";
+pub const ENTRY_IDS_FILE: &str = "entry_ids";
+pub const ALIASES_FILE: &str = "aliases";
+pub const BROKEN_LINKS_FILE: &str = "broken_links";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BuildStage {
+    /// The readings list is checkpointed.
+    Readings,
+    /// The FST, readings, record and entry id files are complete.
+    Done,
+}
+
+impl BuildStage {
+    #[cfg(feature = "fs")]
+    fn as_str(self) -> &'static str {
+        match self {
+            BuildStage::Readings => "readings",
+            BuildStage::Done => "done",
+        }
+    }
+
+    #[cfg(feature = "fs")]
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "readings" => Some(BuildStage::Readings),
+            "done" => Some(BuildStage::Done),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildManifest {
+    pub fingerprint: String,
+    pub stage: BuildStage,
+    /// File role -> content hash.
+    pub files: BTreeMap<String, String>,
+}
+
+impl BuildManifest {
+    pub fn new(fingerprint: String, stage: BuildStage) -> Self {
+        Self {
+            fingerprint,
+            stage,
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// The manifest at `path`, or `None` if it is missing or unreadable.
+    #[cfg(feature = "fs")]
+    pub fn read(path: impl AsRef<Path>) -> Option<Self> {
+        let file = File::open(path).ok()?;
+        let mut lines = BufReader::new(file).lines();
+        if lines.next()?.ok()? != MANIFEST_HEADER {
+            return None;
+        }
+
+        let mut fingerprint = None;
+        let mut stage = None;
+        let mut files = BTreeMap::new();
+        for line in lines {
+            let line = line.ok()?;
+            let mut fields = line.split(' ');
+            match (fields.next(), fields.next(), fields.next()) {
+                (Some("fingerprint"), Some(value), None) => fingerprint = Some(value.to_string()),
+                (Some("stage"), Some(value), None) => stage = BuildStage::parse(value),
+                (Some("file"), Some(role), Some(hash)) => {
+                    files.insert(role.to_string(), hash.to_string());
+                }
+                _ => return None,
+            }
+        }
+
+        Some(Self {
+            fingerprint: fingerprint?,
+            stage: stage?,
+            files,
+        })
+    }
+
+    /// Write the manifest to `path`, replacing it atomically.
+    #[cfg(feature = "fs")]
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let partial = with_suffix(path, ".partial");
+        {
+            let mut file = File::create(&partial)?;
+            writeln!(file, "{}", MANIFEST_HEADER)?;
+            writeln!(file, "fingerprint {}", self.fingerprint)?;
+            writeln!(file, "stage {}", self.stage.as_str())?;
+            for (role, hash) in &self.files {
+                writeln!(file, "file {} {}", role, hash)?;
+            }
+            file.sync_all()?;
+        }
+        std::fs::rename(partial, path)?;
+        Ok(())
+    }
+
+    /// Hash the file at `path` and record it under `role`.
+    #[cfg(feature = "fs")]
+    pub fn record_file(&mut self, role: &str, path: impl AsRef<Path>) -> Result<()> {
+        let hash = hash_file(path)?;
+        self.files.insert(role.to_string(), hash);
+        Ok(())
+    }
+
+    /// Whether the file at `path` still matches the hash recorded for `role`.
+    #[cfg(feature = "fs")]
+    pub fn file_is_intact(&self, role: &str, path: impl AsRef<Path>) -> bool {
+        let Some(expected) = self.files.get(role) else {
+            return false;
+        };
+        hash_file(path).is_ok_and(|actual| &actual == expected)
+    }
+}
+
+/// Where the manifest for an index built at `fst_path` lives.
+pub fn manifest_path(fst_path: impl AsRef<Path>) -> PathBuf {
+    with_suffix(fst_path.as_ref(), ".manifest")
+}
+
+/// Where the entry id map for an index built at `fst_path` lives.
+pub fn entry_ids_path(fst_path: impl AsRef<Path>) -> PathBuf {
+    with_suffix(fst_path.as_ref(), ".ids")
+}
+
+/// Where the alias table for an index built at `fst_path` lives.
+pub fn aliases_path(fst_path: impl AsRef<Path>) -> PathBuf {
+    with_suffix(fst_path.as_ref(), ".aliases")
+}
+
+/// Where the broken links found by a build at `fst_path` are kept.
+pub fn broken_links_path(fst_path: impl AsRef<Path>) -> PathBuf {
+    with_suffix(fst_path.as_ref(), ".broken-links")
+}
+
+/// Where the readings-list checkpoint for `fst_path` lives.
+pub fn readings_list_checkpoint_path(fst_path: impl AsRef<Path>) -> PathBuf {
+    with_suffix(fst_path.as_ref(), ".readings-list")
+}
+
+/// Hex RIPEMD-128 of `parts`, in order.
+pub fn hash_parts(parts: &[&[u8]]) -> String {
+    let mut hasher = Ripemd128::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hex(&hasher.finalize())
+}
+
+#[cfg(feature = "fs")]
+pub fn hash_file(path: impl AsRef<Path>) -> Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Ripemd128::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex(&hasher.finalize()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
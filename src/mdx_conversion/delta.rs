@@ -0,0 +1,207 @@
+//! Updating an optimized index for a new release of its dictionary without
+//! recompressing the records that did not change.
+//!
+//! Records are compared block by block: a run of new records that is byte
+//! for byte the contents of a block of the old record sidecar reuses that
+//! compressed block as is. Only the remaining records are compressed again,
+//! with the old build's zstd dictionary so reused and new blocks can share
+//! one container. The readings, FST and entry id files are rewritten in
+//! full; they are small and quick to build next to the records.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{BufWriter, Read, Seek, Write};
+use std::path::Path;
+
+use crate::error::{MDictError, Result};
+use crate::mdx_conversion::build_manifest;
+use crate::mdx_conversion::entry_metadata::EntryMetadata;
+use crate::mdx_conversion::frequency::FrequencyList;
+use crate::mdx_conversion::fst_indexing::{
+    build_sorted_key_link_order, write_entry_id_map, write_fst_map,
+};
+use crate::mdx_conversion::readings::{self, ReadingsSection};
+use crate::mdx_conversion::records::referenced_records;
+use crate::mdx_conversion::reindexing::ReadingsListMap;
+use crate::mdx_conversion::transform::{self, RecordTransform};
+use crate::packed_storage::{PackedStorageReader, PackedStorageWriter};
+use crate::types::DeltaStats;
+use crate::Mdict;
+
+/// Build the index of `mdict`, a new release of the dictionary the index at
+/// `old_readings_path`/`old_record_path` was built from, reusing every
+/// record block of the old build whose records are unchanged. The outputs
+/// are the same files [`crate::mdx_conversion::fst_indexing::create_fst_index_with_cancel`]
+/// writes and must not overwrite the old files, which are read throughout.
+/// Pass the same `transform` the old build used, or no block will match.
+/// `metadata` is written afresh, like the rest of the readings.
+#[allow(clippy::too_many_arguments)]
+pub fn update_fst_index<R: Read + Seek>(
+    old_readings_path: impl AsRef<Path>,
+    old_record_path: impl AsRef<Path>,
+    mdict: &mut Mdict<R>,
+    readings_list: &ReadingsListMap,
+    output_path: impl AsRef<Path>,
+    readings_path: impl AsRef<Path>,
+    record_output_path: impl AsRef<Path>,
+    frequencies: Option<&FrequencyList>,
+    metadata: Option<&EntryMetadata>,
+    transform: Option<&dyn RecordTransform>,
+) -> Result<DeltaStats> {
+    let old_links = old_record_links(old_readings_path)?;
+    let mut old_records = PackedStorageReader::new(File::open(old_record_path)?, 1)?;
+    let blocks_by_first_record = index_old_blocks(&mut old_records, &old_links)?;
+
+    let header = &old_records.index().header;
+    let mut storage_writer = PackedStorageWriter::new(
+        header.encoding,
+        header.compression_level,
+        crate::config::config().packed_block_size(),
+    )?;
+    if let Some(dictionary) = header.zstd_dictionary.clone() {
+        storage_writer = storage_writer.with_zstd_dictionary(dictionary)?;
+    }
+
+    let link_order = build_sorted_key_link_order(readings_list);
+    let referenced = referenced_records(mdict, readings_list, &link_order)?;
+    let mut stats = DeltaStats::default();
+    let mut link_remap = HashMap::with_capacity(referenced.len());
+    // Records read from `mdict` but not written yet, oldest first.
+    let mut upcoming = VecDeque::new();
+    let mut next = 0;
+
+    loop {
+        if upcoming.is_empty() && next < referenced.len() {
+            let (link, index) = referenced[next];
+            let record = transform::apply(transform, mdict.record_at_index(index)?);
+            upcoming.push_back((link, record));
+            next += 1;
+        }
+        let Some((_, first)) = upcoming.front() else {
+            break;
+        };
+
+        let candidates = blocks_by_first_record
+            .get(&record_hash(first))
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let mut reused = None;
+        for &block_pos in candidates {
+            let block = old_records.decode_block(block_pos)?.bytes;
+            let mut matched = 0;
+            let mut consumed = 0;
+            while consumed < block.len() {
+                if matched == upcoming.len() {
+                    let Some(&(link, index)) = referenced.get(next) else {
+                        break;
+                    };
+                    let record = transform::apply(transform, mdict.record_at_index(index)?);
+                    upcoming.push_back((link, record));
+                    next += 1;
+                }
+                let record = &upcoming[matched].1;
+                if record.is_empty() || !block[consumed..].starts_with(record) {
+                    break;
+                }
+                consumed += record.len();
+                matched += 1;
+            }
+            if consumed == block.len() {
+                reused = Some((block_pos, block.len(), matched));
+                break;
+            }
+        }
+
+        match reused {
+            Some((block_pos, block_len, matched)) => {
+                let compressed = old_records.compressed_block(block_pos)?;
+                let mut offset = storage_writer.push_compressed_block(
+                    compressed,
+                    block_len as u64,
+                    matched as u64,
+                )?;
+                for (link, record) in upcoming.drain(..matched) {
+                    link_remap.insert(link, offset);
+                    offset += record.len() as u64;
+                }
+                stats.reused_blocks += 1;
+                stats.reused_records += matched as u64;
+            }
+            None => {
+                let (link, record) = upcoming.pop_front().expect("checked above");
+                link_remap.insert(link, storage_writer.push_entry(&record)?);
+                stats.encoded_records += 1;
+            }
+        }
+    }
+
+    if link_remap.is_empty() {
+        return Err(MDictError::InvalidArgument(
+            "no referenced records found for compaction".to_string(),
+        ));
+    }
+    let mut record_writer = BufWriter::new(File::create(record_output_path)?);
+    storage_writer.finish_to_writer(&mut record_writer)?;
+    record_writer.flush()?;
+
+    let (key_link_pairs, entry_offsets) = readings::write_readings_data(
+        readings_list,
+        &link_order,
+        &link_remap,
+        frequencies,
+        metadata,
+        readings_path,
+    )?;
+    write_fst_map(&key_link_pairs, &output_path)?;
+    write_entry_id_map(
+        &mut [(mdict, &entry_offsets)],
+        build_manifest::entry_ids_path(&output_path),
+    )?;
+
+    Ok(stats)
+}
+
+/// Offsets of the records in the old record sidecar, from its readings.
+fn old_record_links(readings_path: impl AsRef<Path>) -> Result<BTreeSet<u64>> {
+    let mut section = ReadingsSection::parse(File::open(readings_path)?, 1)?;
+    let mut links = BTreeSet::new();
+    let mut offset = 0;
+    while offset < section.len() {
+        let entry = section.entry_at(offset)?;
+        offset += entry.entry_size;
+        links.insert(entry.link_id);
+    }
+    Ok(links)
+}
+
+/// Old blocks keyed by the hash of the record they start with. Entries never
+/// span blocks, so every block starts with a record.
+fn index_old_blocks<R: Read + Seek>(
+    old_records: &mut PackedStorageReader<R>,
+    old_links: &BTreeSet<u64>,
+) -> Result<HashMap<u64, Vec<usize>>> {
+    let mut blocks = HashMap::<u64, Vec<usize>>::new();
+    let num_blocks = old_records.index().header.block_prefix_sum.len();
+    for block_pos in 1..num_blocks {
+        let block = old_records.decode_block(block_pos)?;
+        let start = block.uncompressed_start as u64;
+        let first_len = old_links
+            .range(start + 1..)
+            .next()
+            .map_or(block.bytes.len(), |&next| (next - start) as usize)
+            .min(block.bytes.len());
+        blocks
+            .entry(record_hash(&block.bytes[..first_len]))
+            .or_default()
+            .push(block_pos);
+    }
+    Ok(blocks)
+}
+
+fn record_hash(record: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    record.hash(&mut hasher);
+    hasher.finish()
+}
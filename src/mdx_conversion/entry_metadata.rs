@@ -0,0 +1,260 @@
+//! Typed per-entry metadata stored in the readings sidecar of an optimized
+//! index, such as part-of-speech tags, frequencies or audio file keys.
+//!
+//! An [`EntryMetadata`] declares its fields up front and holds values by
+//! link id, the `key_id` of the source record every key of an entry points
+//! at. The field list is written to the sidecar header; each entry's values
+//! follow its readings as `u16` field positions and values: integers and
+//! floats as 8 little-endian bytes, text as a `u32` length and UTF-8.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::error::{MDictError, Result};
+use crate::mdx_conversion::build_manifest::hash_parts;
+#[cfg(feature = "mmap")]
+use crate::types::MetadataEntry;
+use crate::types::{MetadataField, MetadataKind, MetadataValue};
+
+#[derive(Debug, Clone, Default)]
+pub struct EntryMetadata {
+    fields: Vec<MetadataField>,
+    positions: HashMap<String, u16>,
+    values: HashMap<u64, BTreeMap<u16, MetadataValue>>,
+}
+
+impl EntryMetadata {
+    /// Metadata with the given fields and no values yet. Field names must be
+    /// non-empty and distinct.
+    pub fn new(fields: Vec<MetadataField>) -> Result<Self> {
+        if fields.len() > u16::MAX as usize {
+            return Err(MDictError::InvalidArgument(format!(
+                "at most {} metadata fields are supported",
+                u16::MAX
+            )));
+        }
+        let mut positions = HashMap::with_capacity(fields.len());
+        for (position, field) in fields.iter().enumerate() {
+            if field.name.is_empty() || field.name.len() > u16::MAX as usize {
+                return Err(MDictError::InvalidArgument(format!(
+                    "metadata field names must be 1 to {} bytes long",
+                    u16::MAX
+                )));
+            }
+            if positions.insert(field.name.clone(), position as u16).is_some() {
+                return Err(MDictError::InvalidArgument(format!(
+                    "metadata field '{}' is declared twice",
+                    field.name
+                )));
+            }
+        }
+        Ok(Self {
+            fields,
+            positions,
+            values: HashMap::new(),
+        })
+    }
+
+    /// [`Self::new`], filled with `values`.
+    pub fn from_values(
+        fields: Vec<MetadataField>,
+        values: impl IntoIterator<Item = (u64, String, MetadataValue)>,
+    ) -> Result<Self> {
+        let mut metadata = Self::new(fields)?;
+        for (link_id, name, value) in values {
+            metadata.set(link_id, &name, value)?;
+        }
+        Ok(metadata)
+    }
+
+    pub fn fields(&self) -> &[MetadataField] {
+        &self.fields
+    }
+
+    /// Set field `name` of the entry at `link_id`, replacing any earlier
+    /// value. The value must have the field's kind.
+    pub fn set(&mut self, link_id: u64, name: &str, value: MetadataValue) -> Result<()> {
+        let position = self.position(name, value.kind())?;
+        self.values
+            .entry(link_id)
+            .or_default()
+            .insert(position, value);
+        Ok(())
+    }
+
+    pub fn get(&self, link_id: u64, name: &str) -> Option<&MetadataValue> {
+        let position = self.positions.get(name)?;
+        self.values.get(&link_id)?.get(position)
+    }
+
+    /// Number of entries with at least one value.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Hash of the fields and values, for build fingerprints.
+    pub fn digest(&self) -> String {
+        let mut links = self.values.keys().copied().collect::<Vec<_>>();
+        links.sort_unstable();
+        let mut bytes = encode_schema(&self.fields);
+        for link in links {
+            bytes.extend_from_slice(&link.to_le_bytes());
+            bytes.extend_from_slice(&self.encode_values(link));
+        }
+        hash_parts(&[&bytes])
+    }
+
+    fn position(&self, name: &str, kind: MetadataKind) -> Result<u16> {
+        let position = *self.positions.get(name).ok_or_else(|| {
+            MDictError::InvalidArgument(format!("unknown metadata field '{}'", name))
+        })?;
+        let declared = self.fields[position as usize].kind;
+        if declared != kind {
+            return Err(MDictError::InvalidArgument(format!(
+                "metadata field '{}' holds {:?} values, not {:?}",
+                name, declared, kind
+            )));
+        }
+        Ok(position)
+    }
+
+    /// The field list as stored in the readings sidecar header.
+    #[cfg(feature = "fs")]
+    pub(crate) fn schema_bytes(&self) -> Vec<u8> {
+        encode_schema(&self.fields)
+    }
+
+    /// The values of the entry at `link_id` as stored after its readings;
+    /// empty when it has none.
+    pub(crate) fn encode_values(&self, link_id: u64) -> Vec<u8> {
+        let mut out = Vec::new();
+        let Some(values) = self.values.get(&link_id) else {
+            return out;
+        };
+        for (position, value) in values {
+            out.extend_from_slice(&position.to_le_bytes());
+            match value {
+                MetadataValue::Integer { value } => out.extend_from_slice(&value.to_le_bytes()),
+                MetadataValue::Float { value } => out.extend_from_slice(&value.to_le_bytes()),
+                MetadataValue::Text { value } => {
+                    out.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                    out.extend_from_slice(value.as_bytes());
+                }
+            }
+        }
+        out
+    }
+}
+
+/// `u16` field count, then per field its kind as a byte and its name as a
+/// `u16` length and UTF-8.
+fn encode_schema(fields: &[MetadataField]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&(fields.len() as u16).to_le_bytes());
+    for field in fields {
+        out.push(match field.kind {
+            MetadataKind::Integer => 0,
+            MetadataKind::Float => 1,
+            MetadataKind::Text => 2,
+        });
+        out.extend_from_slice(&(field.name.len() as u16).to_le_bytes());
+        out.extend_from_slice(field.name.as_bytes());
+    }
+    out
+}
+
+/// Parse a field list written by [`EntryMetadata::schema_bytes`].
+pub(crate) fn parse_schema(bytes: &[u8]) -> Result<Vec<MetadataField>> {
+    let mut input = Input(bytes);
+    let count = input.u16()?;
+    let mut fields = Vec::with_capacity(count as usize);
+    let mut names = HashSet::new();
+    for _ in 0..count {
+        let kind = match input.take(1)?[0] {
+            0 => MetadataKind::Integer,
+            1 => MetadataKind::Float,
+            2 => MetadataKind::Text,
+            other => {
+                return Err(MDictError::InvalidFormat(format!(
+                    "unknown metadata kind {}",
+                    other
+                )))
+            }
+        };
+        let len = input.u16()? as usize;
+        let name = input.text(len)?;
+        if !names.insert(name.clone()) {
+            return Err(MDictError::InvalidFormat(format!(
+                "metadata field '{}' is declared twice",
+                name
+            )));
+        }
+        fields.push(MetadataField { name, kind });
+    }
+    Ok(fields)
+}
+
+/// Decode values written by [`EntryMetadata::encode_values`], in field order.
+#[cfg(feature = "mmap")]
+pub(crate) fn decode_values(fields: &[MetadataField], bytes: &[u8]) -> Result<Vec<MetadataEntry>> {
+    let mut input = Input(bytes);
+    let mut entries = Vec::new();
+    while !input.0.is_empty() {
+        let position = input.u16()? as usize;
+        let field = fields.get(position).ok_or_else(|| {
+            MDictError::InvalidFormat(format!("metadata field {} is not declared", position))
+        })?;
+        let value = match field.kind {
+            MetadataKind::Integer => MetadataValue::Integer {
+                value: i64::from_le_bytes(input.array()?),
+            },
+            MetadataKind::Float => MetadataValue::Float {
+                value: f64::from_le_bytes(input.array()?),
+            },
+            MetadataKind::Text => {
+                let len = u32::from_le_bytes(input.array()?) as usize;
+                MetadataValue::Text {
+                    value: input.text(len)?,
+                }
+            }
+        };
+        entries.push(MetadataEntry {
+            name: field.name.clone(),
+            value,
+        });
+    }
+    Ok(entries)
+}
+
+struct Input<'a>(&'a [u8]);
+
+impl<'a> Input<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(MDictError::InvalidFormat(
+                "truncated entry metadata".to_string(),
+            ));
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.take(N)?);
+        Ok(out)
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn text(&mut self, len: usize) -> Result<String> {
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|e| MDictError::InvalidFormat(format!("invalid utf8 in metadata: {}", e)))
+    }
+}
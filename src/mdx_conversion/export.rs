@@ -0,0 +1,288 @@
+//! Bulk export of every MDX entry to JSON Lines, TSV or one HTML file per entry.
+//!
+//! Keys and compressed record blocks are read serially from the dictionary;
+//! decompression and formatting run on rayon in batches of blocks when the
+//! `threads` feature is on. Output is always in key order. Records are decoded
+//! to UTF-8 with the dictionary's declared encoding, and `@@@LINK=` redirects
+//! are exported as-is. Other records can be rewritten on the way out with a
+//! [`crate::mdx_conversion::transform::RecordTransform`].
+
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Seek, Write};
+use std::path::Path;
+
+#[cfg(feature = "threads")]
+use rayon::prelude::*;
+
+use crate::error::{MDictError, Result};
+use crate::mdict::{record_batch_size, Mdict};
+use crate::mdx_conversion::transform::{self, RecordTransform};
+use crate::types::{Encoding, RecordKind};
+
+const MAX_FILE_STEM_BYTES: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One `{"key": ..., "record": ...}` object per line.
+    JsonLines,
+    /// `key<TAB>record` per line; backslash, tab, CR and LF are escaped as
+    /// `\\`, `\t`, `\r` and `\n`.
+    Tsv,
+    /// `output` is a directory receiving one `<key>.html` file per entry. Keys
+    /// are sanitized into file names and de-duplicated case-insensitively with
+    /// a `~N` suffix.
+    HtmlDir,
+}
+
+struct ExportEntry {
+    key_text: String,
+    key_id: u64,
+    /// `key_id` of the following entry, which bounds this record.
+    next_key_id: Option<u64>,
+    file_name: Option<String>,
+}
+
+struct BlockGroup {
+    rec_block: usize,
+    entries: Vec<ExportEntry>,
+}
+
+/// Export every entry of `mdict` to `output` in `format` and return the
+/// number of entries written. MDD resource archives are rejected.
+pub fn export<R: Read + Seek>(
+    mdict: &mut Mdict<R>,
+    format: ExportFormat,
+    output: impl AsRef<Path>,
+) -> Result<usize> {
+    export_with_transform(mdict, format, output, None)
+}
+
+/// [`export`], passing every record except redirects through `transform`
+/// before it is decoded and written.
+pub fn export_with_transform<R: Read + Seek>(
+    mdict: &mut Mdict<R>,
+    format: ExportFormat,
+    output: impl AsRef<Path>,
+    transform: Option<&dyn RecordTransform>,
+) -> Result<usize> {
+    if mdict.record_kind() == RecordKind::Binary {
+        return Err(MDictError::InvalidArgument(
+            "export needs an MDX dictionary; MDD resources are binary".to_string(),
+        ));
+    }
+    let encoding = mdict.key_block_index.header.get_encoding();
+
+    let output = output.as_ref();
+
+    let groups = collect_block_groups(mdict, format)?;
+    let records = mdict.record_section()?;
+    let mut writer = match format {
+        ExportFormat::HtmlDir => {
+            fs::create_dir_all(output)?;
+            None
+        }
+        _ => Some(BufWriter::new(File::create(output)?)),
+    };
+
+    let batch_size = record_batch_size();
+    let mut written = 0usize;
+    for batch in groups.chunks(batch_size) {
+        let compressed = batch
+            .iter()
+            .map(|group| mdict.read_compressed_record_block(group.rec_block))
+            .collect::<Result<Vec<_>>>()?;
+        let block_starts = batch
+            .iter()
+            .map(|group| records.record_index_prefix_sum[group.rec_block].uncompressed_size)
+            .collect::<Vec<_>>();
+
+        #[cfg(feature = "threads")]
+        let blocks = batch
+            .par_iter()
+            .zip(compressed.into_par_iter())
+            .zip(block_starts.into_par_iter());
+        #[cfg(not(feature = "threads"))]
+        let blocks = batch.iter().zip(compressed).zip(block_starts);
+        let rendered = blocks
+            .map(|((group, (comp_buf, decomp_size)), block_start)| {
+                let block = crate::format::decode_format_block_sized(&comp_buf, decomp_size)?;
+                render_group(
+                    group,
+                    &block,
+                    block_start,
+                    encoding,
+                    format,
+                    transform,
+                    output,
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if let Some(writer) = &mut writer {
+            for chunk in &rendered {
+                writer.write_all(chunk.as_bytes())?;
+            }
+        }
+        written += batch.iter().map(|group| group.entries.len()).sum::<usize>();
+    }
+
+    if let Some(writer) = &mut writer {
+        writer.flush()?;
+    }
+    log::info!("Exported {} entries", written);
+    Ok(written)
+}
+
+/// Read every key once and bucket entries by the record block holding them.
+fn collect_block_groups<R: Read + Seek>(
+    mdict: &mut Mdict<R>,
+    format: ExportFormat,
+) -> Result<Vec<BlockGroup>> {
+    let total = mdict.key_block_index.key_section.num_entries as usize;
+    let mut keys = Vec::new();
+    for index in 0..total {
+        let key = mdict
+            .key_block_index
+            .get(&mut mdict.reader, index)?
+            .ok_or_else(|| MDictError::InvalidFormat(format!("missing key {}", index)))?;
+        keys.push(key);
+    }
+
+    let records = mdict.record_section()?;
+    let mut used_names = HashSet::new();
+    let mut groups: Vec<BlockGroup> = Vec::new();
+    for (index, key) in keys.iter().enumerate() {
+        let rec_block = records.bin_search_record_index(key.key_id)? as usize;
+        let file_name = (format == ExportFormat::HtmlDir)
+            .then(|| unique_file_name(&key.key_text, &mut used_names));
+        let entry = ExportEntry {
+            key_text: key.key_text.clone(),
+            key_id: key.key_id,
+            next_key_id: keys.get(index + 1).map(|next| next.key_id),
+            file_name,
+        };
+
+        match groups.last_mut() {
+            Some(group) if group.rec_block == rec_block => group.entries.push(entry),
+            _ => groups.push(BlockGroup {
+                rec_block,
+                entries: vec![entry],
+            }),
+        }
+    }
+
+    Ok(groups)
+}
+
+/// Format every entry of one decoded record block. File outputs get the
+/// rendered lines back; `HtmlDir` writes its files into `output` directly.
+fn render_group(
+    group: &BlockGroup,
+    block: &[u8],
+    block_start: u64,
+    encoding: Encoding,
+    format: ExportFormat,
+    transform: Option<&dyn RecordTransform>,
+    output: &Path,
+) -> Result<String> {
+    let mut out = String::new();
+    for entry in &group.entries {
+        let offset = ((entry.key_id - block_start) as usize).min(block.len());
+        let end = match entry.next_key_id {
+            Some(next) => (next.saturating_sub(block_start) as usize).min(block.len()),
+            None => block.len(),
+        };
+        let mut record = &block[offset..end.max(offset)];
+        if record.ends_with(&[0x0A, 0x00]) {
+            record = &record[..record.len() - 2];
+        }
+        let record = match transform {
+            Some(_) => encoding.decode(&transform::apply(transform, record.to_vec())),
+            None => encoding.decode(record),
+        };
+
+        match format {
+            ExportFormat::JsonLines => {
+                out.push_str("{\"key\":");
+                push_json_string(&mut out, &entry.key_text);
+                out.push_str(",\"record\":");
+                push_json_string(&mut out, &record);
+                out.push_str("}\n");
+            }
+            ExportFormat::Tsv => {
+                push_tsv_field(&mut out, &entry.key_text);
+                out.push('\t');
+                push_tsv_field(&mut out, &record);
+                out.push('\n');
+            }
+            ExportFormat::HtmlDir => {
+                let name = entry.file_name.as_deref().unwrap_or_default();
+                fs::write(output.join(name), record.as_bytes())?;
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn push_json_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn push_tsv_field(out: &mut String, value: &str) {
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// File name for `key`: path separators, reserved and control characters
+/// become `_`, and a `~N` suffix keeps names unique on case-insensitive
+/// file systems.
+fn unique_file_name(key: &str, used: &mut HashSet<String>) -> String {
+    let mut stem: String = key
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    while stem.len() > MAX_FILE_STEM_BYTES {
+        stem.pop();
+    }
+    let trimmed = stem.trim_end_matches(['.', ' ']).trim_start();
+    let stem = if trimmed.is_empty() {
+        "_".to_string()
+    } else {
+        trimmed.to_string()
+    };
+
+    let mut candidate = stem.clone();
+    let mut n = 2;
+    while !used.insert(candidate.to_lowercase()) {
+        candidate = format!("{}~{}", stem, n);
+        n += 1;
+    }
+    format!("{}.html", candidate)
+}
@@ -0,0 +1,133 @@
+//! Flashcards for a list of headwords, written as a tab-separated file that
+//! Anki imports with File > Import.
+//!
+//! Each headword becomes one note with a front and a back field filled in
+//! from a [`FlashcardTemplate`]. `@@@LINK=` redirects are followed, and all
+//! senses of a headword listed more than once go on the same card, separated
+//! by `<hr>`. The file starts with Anki's `#separator`, `#html` and
+//! `#columns` header lines so the import dialog needs no settings.
+//! `.apkg` packages are not written: they are SQLite databases, and the text
+//! import covers the same notes.
+
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, Write};
+use std::path::Path;
+
+use crate::error::{MDictError, Result};
+use crate::mdict::Mdict;
+use crate::render::{html_to_plaintext, push_escaped, render_record, RenderOptions};
+use crate::types::{FlashcardExport, RecordKind};
+
+/// Redirect hops followed per record.
+const FLASHCARD_LINK_DEPTH: u32 = 8;
+
+/// The fields of a card. `{{key}}` is replaced with the headword, `{{html}}`
+/// with its records as HTML and `{{text}}` with their text on separate
+/// lines; anything else is copied as is.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct FlashcardTemplate {
+    pub front: String,
+    pub back: String,
+}
+
+impl Default for FlashcardTemplate {
+    fn default() -> Self {
+        Self {
+            front: "{{key}}".to_string(),
+            back: "{{html}}".to_string(),
+        }
+    }
+}
+
+impl FlashcardTemplate {
+    fn fill(template: &str, key: &str, html: &str, text: &str) -> String {
+        template
+            .replace("{{key}}", &escape_html(key))
+            .replace("{{text}}", &escape_html(text).replace('\n', "<br>"))
+            // Last, so markers inside records are left alone.
+            .replace("{{html}}", html)
+    }
+}
+
+/// Write a card for every headword of `headwords` found in `mdict` to
+/// `output`, in the order given. Headwords without an entry, or whose
+/// redirects lead nowhere, are skipped and listed in the result.
+pub fn export_flashcards<R: Read + Seek>(
+    mdict: &mut Mdict<R>,
+    headwords: &[String],
+    template: &FlashcardTemplate,
+    output: impl AsRef<Path>,
+) -> Result<FlashcardExport> {
+    if mdict.record_kind() == RecordKind::Binary {
+        return Err(MDictError::InvalidArgument(
+            "flashcards need an MDX dictionary; MDD resources are binary".to_string(),
+        ));
+    }
+    let encoding = mdict.key_block_index.header.get_encoding();
+    let options = RenderOptions {
+        expand_compact: true,
+    };
+
+    let mut writer = BufWriter::new(File::create(output)?);
+    writeln!(writer, "#separator:tab")?;
+    writeln!(writer, "#html:true")?;
+    writeln!(writer, "#columns:Front\tBack")?;
+
+    let mut export = FlashcardExport {
+        written: 0,
+        missing: Vec::new(),
+    };
+    for headword in headwords {
+        let mut senses = Vec::new();
+        for key_block in mdict.get_all(headword)? {
+            match mdict.record_resolved(&key_block, FLASHCARD_LINK_DEPTH) {
+                Ok(record) => senses.push(render_record(
+                    &mdict.key_block_index.header,
+                    encoding.decode(&record),
+                    options,
+                )),
+                Err(MDictError::KeyNotFound(_)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        if senses.is_empty() {
+            export.missing.push(headword.clone());
+            continue;
+        }
+
+        let html = senses.join("<hr>");
+        let text = senses
+            .iter()
+            .map(|sense| html_to_plaintext(sense))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let front = FlashcardTemplate::fill(&template.front, headword, &html, &text);
+        let back = FlashcardTemplate::fill(&template.back, headword, &html, &text);
+        writeln!(writer, "{}\t{}", quote_field(&front), quote_field(&back))?;
+        export.written += 1;
+    }
+    writer.flush()?;
+
+    log::info!(
+        "Exported {} flashcards, {} headwords missing",
+        export.written,
+        export.missing.len()
+    );
+    Ok(export)
+}
+
+fn escape_html(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    push_escaped(&mut out, value);
+    out
+}
+
+/// `field` as Anki's importer reads it: fields holding a tab, line break or
+/// quote are quoted, with quotes doubled.
+fn quote_field(field: &str) -> String {
+    if field.contains(['\t', '\n', '\r', '"']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,81 @@
+//! Word frequency lists applied when building an optimized index.
+//!
+//! A list is tab-separated text with one word per line: the word in the
+//! first column and its rank in the last, so `word\trank` and
+//! `word\treading\trank` both work. Rank 1 is the most frequent word. Blank
+//! lines and lines starting with `#` are skipped.
+
+use std::collections::HashMap;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use crate::error::{MDictError, Result};
+use crate::mdx_conversion::build_manifest::hash_parts;
+
+#[derive(Debug, Clone, Default)]
+pub struct FrequencyList {
+    ranks: HashMap<String, u32>,
+    digest: String,
+}
+
+impl FrequencyList {
+    pub fn parse(tsv: &str) -> Result<Self> {
+        let mut ranks = HashMap::new();
+        for (line_number, line) in tsv.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || {
+                MDictError::InvalidFormat(format!(
+                    "frequency list line {}: expected `word<TAB>rank`",
+                    line_number + 1
+                ))
+            };
+            let (word, rest) = line.split_once('\t').ok_or_else(invalid)?;
+            let rank = rest
+                .rsplit('\t')
+                .next()
+                .and_then(|rank| rank.trim().parse::<u32>().ok())
+                .ok_or_else(invalid)?;
+            // A word listed twice keeps its better rank.
+            let best = ranks.entry(word.to_string()).or_insert(rank);
+            *best = (*best).min(rank);
+        }
+        Ok(Self {
+            ranks,
+            digest: hash_parts(&[tsv.as_bytes()]),
+        })
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn rank(&self, word: &str) -> Option<u32> {
+        self.ranks.get(word).copied()
+    }
+
+    /// The best rank among `readings`, which is the rank of the entry they
+    /// index.
+    pub fn best_rank<'a>(&self, readings: impl IntoIterator<Item = &'a String>) -> Option<u32> {
+        readings
+            .into_iter()
+            .filter_map(|reading| self.rank(reading))
+            .min()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ranks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranks.is_empty()
+    }
+
+    /// Hash of the list's contents, for build fingerprints.
+    pub fn digest(&self) -> &str {
+        &self.digest
+    }
+}
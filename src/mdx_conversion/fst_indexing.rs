@@ -2,15 +2,25 @@ use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufWriter, Read, Seek, Write};
 use std::path::Path;
+use std::sync::atomic::AtomicBool;
 
-use fst::MapBuilder;
-use crate::error::Result;
+use fst::{IntoStreamer, Map, MapBuilder, Streamer};
+use crate::dictionary::Dictionary;
+use crate::error::{MDictError, Result};
+use crate::mdx_conversion::build_manifest;
+use crate::mdx_conversion::entry_metadata::EntryMetadata;
+use crate::mdx_conversion::frequency::FrequencyList;
 use crate::mdx_conversion::readings;
-use crate::mdx_conversion::records::RecordSection as MdxRecordSection;
-use crate::mdx_conversion::with_fst_key_metadata;
+use crate::mdx_conversion::records;
+use crate::mdx_conversion::transform::RecordTransform;
+use crate::mdx_conversion::{
+    check_cancelled, reverse_key, strip_fst_key_metadata, with_fst_key_metadata,
+};
+use crate::transliterate::{romanize, RomanizationScheme};
+use crate::types::{EntryId, KeyBlock};
 use crate::Mdict;
 
-fn write_fst_map(
+pub(crate) fn write_fst_map(
     key_link_pairs: &[(String, u64)],
     output_path: impl AsRef<Path>,
 ) -> Result<()> {
@@ -34,7 +44,7 @@ fn write_fst_map(
             (indexed_key, *value)
         })
         .collect::<Vec<_>>();
-    decorated_entries.sort_unstable_by(|(ka, _), (kb, _)| ka.cmp(kb));
+    decorated_entries.sort_unstable();
 
     for (key, value) in decorated_entries {
         builder.insert(key, value)?;
@@ -49,23 +59,29 @@ fn write_record_section<R: Read + Seek>(
     readings_list: &HashMap<u64, HashSet<String>>,
     link_order: &[u64],
     record_output_path: impl AsRef<Path>,
+    transform: Option<&dyn RecordTransform>,
+    cancel: &AtomicBool,
 ) -> Result<HashMap<u64, u64>> {
 
     let record_output_file = File::create(record_output_path)?;
     let mut record_writer = BufWriter::new(record_output_file);
 
-    let link_remap = MdxRecordSection::rebuild_compacted_zstd_from_mdict(
+    let link_remap = records::rebuild_compacted_zstd_with_transform(
         mdict,
         readings_list,
         link_order,
         &mut record_writer,
+        transform,
+        cancel,
     )?;
     record_writer.flush()?;
 
     Ok(link_remap)
 }
 
-fn build_sorted_key_link_order(readings_list: &HashMap<u64, HashSet<String>>) -> Vec<u64> {
+pub(crate) fn build_sorted_key_link_order(
+    readings_list: &HashMap<u64, HashSet<String>>,
+) -> Vec<u64> {
     let mut key_to_links = BTreeMap::<String, BTreeSet<u64>>::new();
 
     for (&old_link, keys) in readings_list {
@@ -94,16 +110,329 @@ pub fn create_fst_index<R: Read + Seek>(
     output_path: impl AsRef<Path>,
     readings_path: impl AsRef<Path>,
     record_output_path: impl AsRef<Path>,
+) -> Result<()> {
+    create_fst_index_with_cancel(
+        mdict,
+        readings_list,
+        output_path,
+        readings_path,
+        record_output_path,
+        None,
+        None,
+        &AtomicBool::new(false),
+    )
+}
+
+/// [`create_fst_index`], storing ranks from `frequencies` in the readings,
+/// passing records through `transform` (see
+/// [`crate::mdx_conversion::transform`]) and stopping with
+/// [`crate::error::MDictError::Cancelled`] between passes and between
+/// records once `cancel` is set.
+#[allow(clippy::too_many_arguments)]
+pub fn create_fst_index_with_cancel<R: Read + Seek>(
+    mdict: &mut Mdict<R>,
+    readings_list: &HashMap<u64, HashSet<String>>,
+    output_path: impl AsRef<Path>,
+    readings_path: impl AsRef<Path>,
+    record_output_path: impl AsRef<Path>,
+    frequencies: Option<&FrequencyList>,
+    transform: Option<&dyn RecordTransform>,
+    cancel: &AtomicBool,
+) -> Result<()> {
+    create_fst_index_with_metadata(
+        mdict,
+        readings_list,
+        output_path,
+        readings_path,
+        record_output_path,
+        frequencies,
+        None,
+        transform,
+        cancel,
+    )
+}
+
+/// [`create_fst_index_with_cancel`], also storing `metadata` with each
+/// entry and its schema in the readings header, for
+/// [`crate::MdictOptimized::entry_metadata`].
+#[allow(clippy::too_many_arguments)]
+pub fn create_fst_index_with_metadata<R: Read + Seek>(
+    mdict: &mut Mdict<R>,
+    readings_list: &HashMap<u64, HashSet<String>>,
+    output_path: impl AsRef<Path>,
+    readings_path: impl AsRef<Path>,
+    record_output_path: impl AsRef<Path>,
+    frequencies: Option<&FrequencyList>,
+    metadata: Option<&EntryMetadata>,
+    transform: Option<&dyn RecordTransform>,
+    cancel: &AtomicBool,
 ) -> Result<()> {
     let link_order = build_sorted_key_link_order(readings_list);
-    let link_remap = write_record_section(mdict, readings_list, &link_order, record_output_path)?;
-    let key_link_pairs = readings::write_readings_data_and_collect_key_offsets(
+    let link_remap = write_record_section(
+        mdict,
         readings_list,
         &link_order,
+        record_output_path,
+        transform,
+        cancel,
+    )?;
+    check_cancelled(cancel)?;
+    let (key_link_pairs, entry_offsets) = readings::write_readings_data(
+        readings_list,
+        &link_order,
+        &link_remap,
+        frequencies,
+        metadata,
+        readings_path,
+    )?;
+    check_cancelled(cancel)?;
+    write_fst_map(&key_link_pairs, &output_path)?;
+    check_cancelled(cancel)?;
+    write_entry_id_map(
+        &mut [(mdict, &entry_offsets)],
+        build_manifest::entry_ids_path(&output_path),
+    )?;
+
+    Ok(())
+}
+
+/// Build one index spanning several dictionaries, so an app holds a single
+/// FST instead of one per dictionary. `readings_lists[i]` belongs to
+/// `mdicts[i]`, and every entry is tagged with `i` as its source id (see
+/// [`crate::mdx_conversion::readings::ReadingsEntry::source`]). A key found
+/// in several dictionaries is kept once per entry, like a duplicate key.
+pub fn create_fst_index_multi<R: Read + Seek>(
+    mdicts: &mut [Mdict<R>],
+    readings_lists: &[HashMap<u64, HashSet<String>>],
+    output_path: impl AsRef<Path>,
+    readings_path: impl AsRef<Path>,
+    record_output_path: impl AsRef<Path>,
+) -> Result<()> {
+    if mdicts.is_empty() || mdicts.len() != readings_lists.len() {
+        return Err(MDictError::InvalidArgument(
+            "expected one readings list per dictionary".to_string(),
+        ));
+    }
+
+    let link_orders = readings_lists
+        .iter()
+        .map(build_sorted_key_link_order)
+        .collect::<Vec<_>>();
+    let mut record_writer = BufWriter::new(File::create(record_output_path)?);
+    let link_remaps = records::rebuild_compacted_zstd_multi(
+        mdicts,
+        readings_lists,
+        &link_orders,
+        &mut record_writer,
+        &AtomicBool::new(false),
+    )?;
+    record_writer.flush()?;
+
+    let sources = readings_lists
+        .iter()
+        .zip(&link_orders)
+        .zip(&link_remaps)
+        .map(|((readings_list, link_order), link_remap)| readings::ReadingsSource {
+            readings_list,
+            link_order,
+            link_remap,
+        })
+        .collect::<Vec<_>>();
+    let (key_link_pairs, entry_offsets) =
+        readings::write_readings_data_multi(&sources, readings_path)?;
+    write_fst_map(&key_link_pairs, &output_path)?;
+
+    let mut id_sources = mdicts.iter_mut().zip(&entry_offsets).collect::<Vec<_>>();
+    write_entry_id_map(
+        &mut id_sources,
+        build_manifest::entry_ids_path(&output_path),
+    )?;
+
+    Ok(())
+}
+
+/// Build an optimized index of any [`Dictionary`], e.g. a StarDict or DSL
+/// dictionary, so it gets the same FST prefix search as an MDX. Keys sharing
+/// a `key_id` share one record, stored as [`Dictionary::record_text`] in
+/// UTF-8. No entry id map is written, since entries have no MDX ordinals.
+pub fn create_fst_index_from_dictionary(
+    dictionary: &dyn Dictionary,
+    output_path: impl AsRef<Path>,
+    readings_path: impl AsRef<Path>,
+    record_output_path: impl AsRef<Path>,
+) -> Result<()> {
+    let keys = dictionary.prefix_search("", usize::MAX)?;
+    let mut readings_list = HashMap::<u64, HashSet<String>>::new();
+    let mut first_keys = HashMap::<u64, &KeyBlock>::new();
+    for key in &keys {
+        readings_list
+            .entry(key.key_id)
+            .or_default()
+            .insert(key.key_text.clone());
+        first_keys.entry(key.key_id).or_insert(key);
+    }
+
+    let link_order = build_sorted_key_link_order(&readings_list);
+    let ordered_keys = link_order
+        .iter()
+        .map(|link| first_keys[link].clone())
+        .collect::<Vec<_>>();
+    let mut record_writer = BufWriter::new(File::create(record_output_path)?);
+    let link_remap = records::rebuild_compacted_zstd_from_dictionary(
+        dictionary,
+        &ordered_keys,
+        &mut record_writer,
+    )?;
+    record_writer.flush()?;
+
+    let (key_link_pairs, _) = readings::write_readings_data(
+        &readings_list,
+        &link_order,
         &link_remap,
+        None,
+        None,
         readings_path,
     )?;
-    write_fst_map(&key_link_pairs, output_path)?;
+    write_fst_map(&key_link_pairs, output_path)
+}
+
+/// First byte of entry id map keys followed by a big-endian [`EntryId`].
+pub(crate) const ENTRY_ID_KEY_TAG: u8 = b'i';
+/// First byte of entry id map keys followed by a big-endian readings offset.
+pub(crate) const OFFSET_KEY_TAG: u8 = b'o';
 
+pub(crate) fn entry_id_map_key(tag: u8, value: u64) -> [u8; 9] {
+    let mut key = [tag; 9];
+    key[1..].copy_from_slice(&value.to_be_bytes());
+    key
+}
+
+/// Write the FST mapping each entry's [`EntryId`] to its readings offset and
+/// back. Each source pairs a dictionary with the map from its links to
+/// readings offsets. Entries are numbered per headword in source link order,
+/// continuing across sources; the rare entry whose id collides with an
+/// earlier one gets no id.
+pub(crate) fn write_entry_id_map<R: Read + Seek>(
+    sources: &mut [(&mut Mdict<R>, &HashMap<u64, u64>)],
+    output_path: impl AsRef<Path>,
+) -> Result<()> {
+    let mut ordinals = HashMap::<String, u32>::new();
+    let mut seen_ids = HashSet::new();
+    let mut entries = Vec::new();
+    for (mdict, entry_offsets) in sources.iter_mut() {
+        // Several keys can share a record; the first in key order names it.
+        let mut headwords = HashMap::with_capacity(entry_offsets.len());
+        let num_entries = mdict.key_block_index.key_section.num_entries as usize;
+        for index in 0..num_entries {
+            let Some(key_block) = mdict.key_block_index.get(&mut mdict.reader, index)? else {
+                continue;
+            };
+            if entry_offsets.contains_key(&key_block.key_id) {
+                headwords
+                    .entry(key_block.key_id)
+                    .or_insert(key_block.key_text);
+            }
+        }
+
+        let mut links = entry_offsets.keys().copied().collect::<Vec<_>>();
+        links.sort_unstable();
+        for link in links {
+            let headword = headwords.get(&link).ok_or_else(|| {
+                MDictError::InvalidFormat(format!("no key points at record {}", link))
+            })?;
+            let ordinal = ordinals.entry(headword.clone()).or_insert(0);
+            let id = EntryId::new(headword, *ordinal);
+            *ordinal += 1;
+            if !seen_ids.insert(id) {
+                continue;
+            }
+            let offset = entry_offsets[&link];
+            entries.push((entry_id_map_key(ENTRY_ID_KEY_TAG, id.0), offset));
+            entries.push((entry_id_map_key(OFFSET_KEY_TAG, offset), id.0));
+        }
+    }
+    entries.sort_unstable();
+
+    let mut builder = MapBuilder::new(BufWriter::new(File::create(output_path)?))?;
+    for (key, value) in entries {
+        builder.insert(key, value)?;
+    }
+    builder.finish()?;
+    Ok(())
+}
+
+/// Write a sidecar FST of reversed keys for the index at `fst_path`, so
+/// "ends with" queries become prefix ranges. Values are the same readings
+/// offsets as in the main index.
+pub fn create_suffix_index(
+    fst_path: impl AsRef<Path>,
+    output_path: impl AsRef<Path>,
+) -> Result<()> {
+    let map = Map::new(std::fs::read(fst_path)?)?;
+    create_suffix_index_from_map(&map, output_path)
+}
+
+/// [`create_suffix_index`] for an index that is already loaded.
+pub fn create_suffix_index_from_map<D: AsRef<[u8]>>(
+    map: &Map<D>,
+    output_path: impl AsRef<Path>,
+) -> Result<()> {
+    let mut reversed_entries = Vec::with_capacity(map.len());
+    let mut stream = map.into_stream();
+    while let Some((raw_key, value)) = stream.next() {
+        let key_with_metadata = String::from_utf8_lossy(raw_key);
+        let key = strip_fst_key_metadata(&key_with_metadata);
+        let reversed = reverse_key(key);
+        let indexed_key = if key.len() == key_with_metadata.len() {
+            reversed
+        } else {
+            with_fst_key_metadata(&reversed, value)
+        };
+        reversed_entries.push((indexed_key, value));
+    }
+    reversed_entries.sort_unstable();
+
+    let output_file = File::create(output_path)?;
+    let mut builder = MapBuilder::new(BufWriter::new(output_file))?;
+    for (key, value) in reversed_entries {
+        builder.insert(key, value)?;
+    }
+    builder.finish()?;
     Ok(())
 }
+
+/// Separates the romanized form from the key it was made from in a
+/// romanized index, so prefix ranges over the romanized form still work.
+pub(crate) const ROMANIZED_KEY_SEPARATOR: char = '\u{1}';
+
+/// Write a sidecar FST mapping the romanized forms of the keys in the index
+/// at `fst_path` to the same readings offsets, for
+/// [`crate::MdictOptimized::search_keys_romanized`].
+pub fn create_romanized_index(
+    fst_path: impl AsRef<Path>,
+    output_path: impl AsRef<Path>,
+    scheme: RomanizationScheme,
+) -> Result<()> {
+    let map = Map::new(std::fs::read(fst_path)?)?;
+    create_romanized_index_from_map(&map, output_path, scheme)
+}
+
+/// [`create_romanized_index`] for an index that is already loaded.
+pub fn create_romanized_index_from_map<D: AsRef<[u8]>>(
+    map: &Map<D>,
+    output_path: impl AsRef<Path>,
+    scheme: RomanizationScheme,
+) -> Result<()> {
+    let mut romanized_entries = Vec::new();
+    let mut stream = map.into_stream();
+    while let Some((raw_key, value)) = stream.next() {
+        let key_with_metadata = String::from_utf8_lossy(raw_key);
+        let key = strip_fst_key_metadata(&key_with_metadata);
+        for form in romanize(scheme, key) {
+            romanized_entries.push((format!("{}{}{}", form, ROMANIZED_KEY_SEPARATOR, key), value));
+        }
+    }
+    romanized_entries.sort_unstable();
+    romanized_entries.dedup();
+    write_fst_map(&romanized_entries, output_path)
+}
@@ -1,100 +1,429 @@
 use std::cell::RefCell;
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
 use std::fs::File;
+use std::io::Cursor;
+use std::ops::{Bound, RangeBounds};
 use std::path::Path;
 
+use fst::automaton::{AlwaysMatch, Levenshtein};
 use fst::map::Stream;
-use fst::{IntoStreamer, Map, Streamer};
-use memmap2::Mmap;
+use fst::{Automaton, IntoStreamer, Map, Streamer};
 
 use crate::error::{MDictError, Result};
-use crate::mdx_conversion::strip_fst_key_metadata;
-use crate::mdx_conversion::readings::{
-    read_entry_from_bytes_result, read_header_from_bytes_result, ReadingsEntry,
+use crate::glob::GlobPattern;
+use crate::mdx_conversion::aliases::KeyAliases;
+use crate::mdx_conversion::build_manifest::{aliases_path, entry_ids_path};
+use crate::mdx_conversion::entry_metadata::decode_values;
+use crate::mdx_conversion::fst_indexing::{
+    entry_id_map_key, ENTRY_ID_KEY_TAG, OFFSET_KEY_TAG, ROMANIZED_KEY_SEPARATOR,
 };
+use crate::mdx_conversion::optimized_bundle::BundleSections;
+use crate::mdx_conversion::readings::{ReadingsEntry, ReadingsSection};
 use crate::mdx_conversion::records::RecordSection as MdxRecordSection;
+use crate::mdx_conversion::{
+    fst_key_metadata_end, reverse_key, strip_fst_key_metadata, IgnoreKeyMetadata,
+};
 use crate::random_access_key_blocks::upper_bound_from_prefix;
+use crate::seekable_mmap::MmapSection;
+use crate::transliterate::fold_romanized;
+use crate::types::{
+    EntryId, KeyBlock, MetadataEntry, MetadataField, PrefixSearchCursor, PrefixSearchPage,
+    PrefixSearchPrevCursor,
+};
+
+/// Decoded readings blocks kept per map. Every record lookup touches two
+/// neighbouring entries, which usually share a block.
+const READINGS_CACHE_BLOCKS: usize = 4;
 
 pub struct FSTMap {
-    map: Map<Mmap>,
-    readings_mmap: Mmap,
-    record_section: MdxRecordSection,
-    record_file: RefCell<File>,
+    map: Map<MmapSection>,
+    suffix_map: Option<Map<MmapSection>>,
+    romanized_map: Option<Map<MmapSection>>,
+    entry_id_map: Option<Map<MmapSection>>,
+    /// The alias table the index was built with, and the section it was read from.
+    aliases: Option<(KeyAliases, MmapSection)>,
+    readings: RefCell<ReadingsSection<Cursor<MmapSection>>>,
+    records: RefCell<MdxRecordSection<Cursor<MmapSection>>>,
 }
 
 impl FSTMap {
-    fn ensure_readings_offset_in_bounds(&self, offset: u64) -> Result<()> {
-        if offset >= self.readings_mmap.len() as u64 {
-            return Err(MDictError::InvalidArgument(format!(
-                "readings offset {} out of bounds for size {}",
-                offset,
-                self.readings_mmap.len()
-            )));
-        }
-        Ok(())
-    }
-
-    fn parse_readings_from_uncompressed_offset_result(
-        &self,
-        offset: u64,
-    ) -> Result<(ReadingsEntry, u64)> {
-        self.ensure_readings_offset_in_bounds(offset)?;
-        let entry = read_entry_from_bytes_result(&self.readings_mmap, offset)?;
-        let entry_size = entry.entry_size;
-        Ok((entry, entry_size))
-    }
-
-    fn get_next_link_id_from_uncompressed_offset_result(&self, offset: u64) -> Result<u64> {
-        self.ensure_readings_offset_in_bounds(offset)?;
-        let header = read_header_from_bytes_result(&self.readings_mmap, offset)?;
-        Ok(header.link_id)
-    }
-
+    /// Open the files of a build. The entry id map and alias table next to
+    /// the FST are loaded too when they exist; indexes built before entry
+    /// ids existed, or without aliases, have none.
     pub fn load_from_path(
         path: impl AsRef<Path>,
         readings_path: impl AsRef<Path>,
         record_path: impl AsRef<Path>,
     ) -> Result<Self> {
-        let mmap = unsafe { memmap2::Mmap::map(&File::open(path)?) }?;
-        let map = Map::new(mmap)?;
-
-        let readings_mmap = unsafe { memmap2::Mmap::map(&File::open(readings_path)?) }?;
+        let mut fst_map = Self::from_sections(
+            MmapSection::open(&File::open(&path)?)?,
+            MmapSection::open(&File::open(readings_path)?)?,
+            MmapSection::open(&File::open(record_path)?)?,
+        )?;
+        let ids_path = entry_ids_path(&path);
+        if ids_path.exists() {
+            fst_map.load_entry_id_section(MmapSection::open(&File::open(ids_path)?)?)?;
+        }
+        let aliases_path = aliases_path(&path);
+        if aliases_path.exists() {
+            fst_map.load_alias_section(MmapSection::open(&File::open(aliases_path)?)?)?;
+        }
+        Ok(fst_map)
+    }
 
-        let mut record_file = File::open(record_path)?;
-        let record_section = MdxRecordSection::parse(&mut record_file)?;
+    /// Build from the FST, readings and record sections, which may all be
+    /// parts of one mapped file.
+    pub fn from_sections(
+        fst: MmapSection,
+        readings: MmapSection,
+        records: MmapSection,
+    ) -> Result<Self> {
+        let map = Map::new(fst)?;
+        let readings = ReadingsSection::parse(Cursor::new(readings), READINGS_CACHE_BLOCKS)?;
+        let records = MdxRecordSection::parse(
+            Cursor::new(records),
+            crate::config::config().record_block_cache_capacity(),
+        )?;
 
         Ok(Self {
             map,
-            readings_mmap,
-            record_section,
-            record_file: RefCell::new(record_file),
+            suffix_map: None,
+            romanized_map: None,
+            entry_id_map: None,
+            aliases: None,
+            readings: RefCell::new(readings),
+            records: RefCell::new(records),
+        })
+    }
+
+    pub fn map(&self) -> &Map<MmapSection> {
+        &self.map
+    }
+
+    /// The FST, readings and record sections, and the suffix, romanized and
+    /// entry id maps when they are loaded.
+    pub fn sections(&self) -> Result<BundleSections> {
+        let readings = self.readings.try_borrow().map_err(|_| {
+            MDictError::InvalidFormat("readings file is already borrowed".to_string())
+        })?;
+        let records = self.records.try_borrow().map_err(|_| {
+            MDictError::InvalidFormat("record file is already borrowed".to_string())
+        })?;
+        Ok(BundleSections {
+            fst: self.map.as_fst().as_inner().clone(),
+            readings: readings.get_ref().get_ref().clone(),
+            records: records.get_ref().get_ref().clone(),
+            suffix_fst: self
+                .suffix_map
+                .as_ref()
+                .map(|map| map.as_fst().as_inner().clone()),
+            romanized_fst: self
+                .romanized_map
+                .as_ref()
+                .map(|map| map.as_fst().as_inner().clone()),
+            entry_ids: self
+                .entry_id_map
+                .as_ref()
+                .map(|map| map.as_fst().as_inner().clone()),
+            aliases: self.aliases.as_ref().map(|(_, section)| section.clone()),
         })
     }
 
     pub fn get(&self, key: &str) -> Option<u64> {
-        let upper_bound = upper_bound_from_prefix(key)?;
-        let mut stream = self.map.range().ge(key).lt(&upper_bound).into_stream();
-        stream.next().map(|(_, value)| value)
+        self.get_link_for_key(key).next().map(|(_, value)| value)
+    }
+
+    /// Values of every entry stored under exactly `key`; duplicate keys are
+    /// kept apart in the map by their metadata suffix.
+    pub fn get_all(&self, key: &str) -> Vec<u64> {
+        let end = fst_key_metadata_end(key);
+        let mut stream = self.map.range().ge(key).lt(&end).into_stream();
+        let mut values = Vec::new();
+        while let Some((raw_key, value)) = stream.next() {
+            let raw_key = String::from_utf8_lossy(raw_key);
+            if strip_fst_key_metadata(&raw_key) == key {
+                values.push(value);
+            }
+        }
+        values
     }
 
     pub fn get_link_for_key<'a>(&'a self, key: &'a str) -> Stream<'a> {
-        self.map
-            .range()
-            .ge(key)
-            .lt(upper_bound_from_prefix(key).unwrap())
-            .into_stream()
+        let mut builder = self.map.range().ge(key);
+        if let Some(upper_bound) = upper_bound_from_prefix(key) {
+            builder = builder.lt(upper_bound);
+        }
+        builder.into_stream()
+    }
+
+    /// Number of keys starting with `prefix`, as listed by
+    /// [`Self::get_link_page_for_prefix`], without decoding any of them.
+    pub fn count_prefix(&self, prefix: &str) -> u64 {
+        let mut builder = self.map.range().ge(prefix);
+        if let Some(upper_bound) = upper_bound_from_prefix(prefix) {
+            builder = builder.lt(upper_bound);
+        }
+        let mut stream = builder.into_stream();
+        let mut count = 0;
+        while stream.next().is_some() {
+            count += 1;
+        }
+        count
+    }
+
+    /// Number of distinct entries (values) under `prefix`; several keys, such
+    /// as a headword and its readings, can point at one entry.
+    pub fn count_prefix_distinct(&self, prefix: &str) -> u64 {
+        let mut builder = self.map.range().ge(prefix);
+        if let Some(upper_bound) = upper_bound_from_prefix(prefix) {
+            builder = builder.lt(upper_bound);
+        }
+        let mut stream = builder.into_stream();
+        let mut seen_values = HashSet::new();
+        while let Some((_, value)) = stream.next() {
+            seen_values.insert(value);
+        }
+        seen_values.len() as u64
     }
 
     pub fn get_link_for_key_dedup<'a>(&'a self, key: &'a str) -> DedupStream<'a> {
         DedupStream::new(self.get_link_for_key(key))
     }
 
+    /// Keys within `bounds` in FST byte order, with their readings offsets.
+    /// Bounds compare against keys as stored, so every entry of a duplicate
+    /// key is in or out of the range together.
+    pub fn keys_in_range<'k>(&self, bounds: impl RangeBounds<&'k str>) -> KeyRangeStream<'_> {
+        let builder = match bounds.start_bound() {
+            // Duplicates of a key sort right after it, so an excluded start
+            // is skipped while streaming rather than bounded here.
+            Bound::Included(start) | Bound::Excluded(start) => self.map.range().ge(*start),
+            Bound::Unbounded => self.map.range(),
+        };
+        KeyRangeStream {
+            stream: builder.into_stream(),
+            skip: match bounds.start_bound() {
+                Bound::Excluded(start) => Some(start.to_string()),
+                _ => None,
+            },
+            end: match bounds.end_bound() {
+                Bound::Included(end) => Bound::Included(end.to_string()),
+                Bound::Excluded(end) => Bound::Excluded(end.to_string()),
+                Bound::Unbounded => Bound::Unbounded,
+            },
+            done: false,
+        }
+    }
+
+    /// Keys within `max_distance` edits (insertions, deletions, substitutions)
+    /// of `query`, in key order, one entry per distinct value.
+    pub fn search_fuzzy(&self, query: &str, max_distance: u32) -> Result<Vec<(String, u64)>> {
+        let automaton = Levenshtein::new(query, max_distance)
+            .map_err(|e| MDictError::InvalidArgument(format!("fuzzy query '{}': {}", query, e)))?;
+        let stream = self.map.search(IgnoreKeyMetadata(automaton)).into_stream();
+        Ok(DedupStream::new(stream).collect())
+    }
+
+    /// Keys matching a `*` / `?` glob, in key order, one entry per distinct value.
+    pub fn search_glob(&self, pattern: &str) -> Result<Vec<(String, u64)>> {
+        let glob = GlobPattern::new(pattern)?;
+        let prefix = glob.literal_prefix();
+
+        let mut builder = self.map.range();
+        if !prefix.is_empty() {
+            builder = builder.ge(prefix);
+            if let Some(upper_bound) = upper_bound_from_prefix(prefix) {
+                builder = builder.lt(upper_bound);
+            }
+        }
+
+        // Match before deduplicating: a key outside the pattern may share its
+        // value with a later key inside it.
+        let mut stream = builder.into_stream();
+        let mut seen_values = HashSet::new();
+        let mut matches = Vec::new();
+        while let Some((raw_key, value)) = stream.next() {
+            let key_with_metadata = String::from_utf8_lossy(raw_key);
+            let key = strip_fst_key_metadata(&key_with_metadata);
+            if glob.matches(key) && seen_values.insert(value) {
+                matches.push((key.to_string(), value));
+            }
+        }
+        Ok(matches)
+    }
+
+    /// Attach a suffix index written by
+    /// [`create_suffix_index`](crate::mdx_conversion::fst_indexing::create_suffix_index).
+    pub fn load_suffix_index(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        self.load_suffix_section(MmapSection::open(&File::open(path)?)?)
+    }
+
+    pub fn load_suffix_section(&mut self, section: MmapSection) -> Result<()> {
+        self.suffix_map = Some(Map::new(section)?);
+        Ok(())
+    }
+
+    pub fn has_suffix_index(&self) -> bool {
+        self.suffix_map.is_some()
+    }
+
+    /// Attach a romanized index written by
+    /// [`create_romanized_index`](crate::mdx_conversion::fst_indexing::create_romanized_index).
+    pub fn load_romanized_index(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        self.load_romanized_section(MmapSection::open(&File::open(path)?)?)
+    }
+
+    pub fn load_romanized_section(&mut self, section: MmapSection) -> Result<()> {
+        self.romanized_map = Some(Map::new(section)?);
+        Ok(())
+    }
+
+    pub fn has_romanized_index(&self) -> bool {
+        self.romanized_map.is_some()
+    }
+
+    pub fn load_entry_id_section(&mut self, section: MmapSection) -> Result<()> {
+        self.entry_id_map = Some(Map::new(section)?);
+        Ok(())
+    }
+
+    pub fn has_entry_ids(&self) -> bool {
+        self.entry_id_map.is_some()
+    }
+
+    /// Attach the alias table written next to the index by the build.
+    pub fn load_alias_section(&mut self, section: MmapSection) -> Result<()> {
+        let text = std::str::from_utf8(section.as_slice()).map_err(|_| {
+            MDictError::InvalidFormat("alias table is not valid UTF-8".to_string())
+        })?;
+        self.aliases = Some((KeyAliases::parse(text)?, section));
+        Ok(())
+    }
+
+    /// The key `alias` leads to, if the index was built with an alias table.
+    pub fn resolve_alias(&self, alias: &str) -> Option<&str> {
+        self.aliases.as_ref()?.0.resolve(alias)
+    }
+
+    /// The stable id of the entry at readings `offset`.
+    pub fn entry_id_for_offset(&self, offset: u64) -> Result<Option<EntryId>> {
+        Ok(self
+            .entry_id_map()?
+            .get(entry_id_map_key(OFFSET_KEY_TAG, offset))
+            .map(EntryId))
+    }
+
+    /// The readings offset of the entry `id` names in this build.
+    pub fn offset_for_entry_id(&self, id: EntryId) -> Result<Option<u64>> {
+        Ok(self
+            .entry_id_map()?
+            .get(entry_id_map_key(ENTRY_ID_KEY_TAG, id.0)))
+    }
+
+    fn entry_id_map(&self) -> Result<&Map<MmapSection>> {
+        self.entry_id_map.as_ref().ok_or_else(|| {
+            MDictError::UnsupportedFeature(
+                "index has no entry ids; rebuild it to add them".to_string(),
+            )
+        })
+    }
+
+    /// Keys whose romanized form starts with `query`, in romanized order, one
+    /// entry per distinct value. Needs a romanized index; see
+    /// [`FSTMap::load_romanized_index`].
+    pub fn search_romanized(&self, query: &str) -> Result<Vec<(String, u64)>> {
+        let romanized_map = self.romanized_map.as_ref().ok_or_else(|| {
+            MDictError::UnsupportedFeature("no romanized index loaded".to_string())
+        })?;
+
+        let query = fold_romanized(query);
+        let mut builder = romanized_map.range();
+        if !query.is_empty() {
+            builder = builder.ge(&query);
+            if let Some(upper_bound) = upper_bound_from_prefix(&query) {
+                builder = builder.lt(upper_bound);
+            }
+        }
+
+        Ok(DedupStream::new(builder.into_stream())
+            .map(|(romanized, value)| {
+                let key = romanized
+                    .split_once(ROMANIZED_KEY_SEPARATOR)
+                    .map_or(romanized.as_str(), |(_, key)| key)
+                    .to_string();
+                (key, value)
+            })
+            .collect())
+    }
+
+    /// Keys ending in `suffix`, in key order, one entry per distinct value.
+    /// Needs a suffix index; see [`FSTMap::load_suffix_index`].
+    pub fn search_suffix(&self, suffix: &str) -> Result<Vec<(String, u64)>> {
+        let suffix_map = self
+            .suffix_map
+            .as_ref()
+            .ok_or_else(|| MDictError::UnsupportedFeature("no suffix index loaded".to_string()))?;
+
+        let reversed_suffix = reverse_key(suffix);
+        let mut builder = suffix_map.range();
+        if !reversed_suffix.is_empty() {
+            builder = builder.ge(&reversed_suffix);
+            if let Some(upper_bound) = upper_bound_from_prefix(&reversed_suffix) {
+                builder = builder.lt(upper_bound);
+            }
+        }
+
+        let mut rows = DedupStream::new(builder.into_stream())
+            .map(|(reversed, value)| (reverse_key(&reversed), value))
+            .collect::<Vec<_>>();
+        rows.sort_unstable();
+        Ok(rows)
+    }
+
+    /// The first page of keys starting with `prefix` with no cursor, or else
+    /// the page right after `cursor`. Pages list up to `page_size` keys in
+    /// FST byte order, each with its readings offset as `key_id`; a key
+    /// stored for several entries appears once per entry. `next_cursor` is
+    /// set when more keys follow and `prev_cursor` when keys precede the
+    /// page, so a listing can be walked in both directions. Cursors are only
+    /// meaningful for the map and prefix they came from. `total_results` is
+    /// left unset; see [`Self::count_prefix`].
+    pub fn prefix_page(
+        &self,
+        prefix: &str,
+        cursor: Option<&PrefixSearchCursor>,
+        page_size: usize,
+    ) -> Result<PrefixSearchPage> {
+        let cursor_after_key = cursor.map(|cursor| cursor.after_key.as_str());
+        Ok(self
+            .get_link_page_for_prefix(prefix, cursor_after_key, page_size)?
+            .into_search_page(None))
+    }
+
+    /// The page of keys starting with `prefix` just before the page `cursor`
+    /// came from, for scrolling back up. Like [`Self::prefix_page`] otherwise.
+    pub fn prefix_page_before(
+        &self,
+        prefix: &str,
+        cursor: &PrefixSearchPrevCursor,
+        page_size: usize,
+    ) -> Result<PrefixSearchPage> {
+        Ok(self
+            .get_link_page_before_key(prefix, &cursor.before_key, page_size)?
+            .into_search_page(None))
+    }
+
+    /// Up to `page_size` keys starting with `prefix`, in FST byte order. With
+    /// `cursor_after_key`, a raw FST key from an earlier page's `next_key`,
+    /// the page starts right after it instead. Rows carry keys without the
+    /// suffix that tells duplicate keys apart; [`LinkPage`] keeps the raw
+    /// keys to resume from.
     pub fn get_link_page_for_prefix(
         &self,
         prefix: &str,
         cursor_after_key: Option<&str>,
         page_size: usize,
-    ) -> Result<(Vec<(String, u64)>, Option<String>)> {
+    ) -> Result<LinkPage> {
         if page_size == 0 {
             return Err(MDictError::InvalidArgument(
                 "page_size must be greater than 0".to_string(),
@@ -120,9 +449,7 @@ impl FSTMap {
             let Some((raw_key, value)) = stream.next() else {
                 break;
             };
-            let key_with_metadata = String::from_utf8_lossy(raw_key).to_string();
-            let clean_key = strip_fst_key_metadata(&key_with_metadata).to_string();
-            rows.push((clean_key, value, key_with_metadata));
+            rows.push(page_row(raw_key, value));
         }
 
         let has_more = rows.len() > page_size;
@@ -130,18 +457,72 @@ impl FSTMap {
             rows.truncate(page_size);
         }
 
-        let next_cursor = if has_more {
+        let next_key = if has_more {
             rows.last().map(|(_, _, key_with_metadata)| key_with_metadata.clone())
         } else {
             None
         };
 
+        Ok(self.finish_page(prefix, rows, next_key))
+    }
+
+    /// The page of up to `page_size` keys under `prefix` that ends right
+    /// before `cursor_before_key`, still in key order.
+    pub fn get_link_page_before_key(
+        &self,
+        prefix: &str,
+        cursor_before_key: &str,
+        page_size: usize,
+    ) -> Result<LinkPage> {
+        if page_size == 0 {
+            return Err(MDictError::InvalidArgument(
+                "page_size must be greater than 0".to_string(),
+            ));
+        }
+
+        // FST streams only run forward, so keep a window of the last rows
+        // seen on the way up to the cursor.
+        let mut stream = self
+            .map
+            .range()
+            .ge(prefix)
+            .lt(cursor_before_key)
+            .into_stream();
+        let mut rows = VecDeque::with_capacity(page_size + 1);
+        while let Some((raw_key, value)) = stream.next() {
+            if rows.len() == page_size {
+                rows.pop_front();
+            }
+            rows.push_back(page_row(raw_key, value));
+        }
+
+        let next_key = rows
+            .back()
+            .map(|(_, _, key_with_metadata)| key_with_metadata.clone());
+        Ok(self.finish_page(prefix, rows.into(), next_key))
+    }
+
+    fn finish_page(
+        &self,
+        prefix: &str,
+        rows: Vec<(String, u64, String)>,
+        next_key: Option<String>,
+    ) -> LinkPage {
+        let prev_key = rows.first().and_then(|(_, _, first_key)| {
+            let mut before = self.map.range().ge(prefix).lt(first_key).into_stream();
+            before.next().map(|_| first_key.clone())
+        });
+
         let results = rows
             .into_iter()
             .map(|(clean_key, value, _)| (clean_key, value))
             .collect::<Vec<_>>();
 
-        Ok((results, next_cursor))
+        LinkPage {
+            results,
+            prev_key,
+            next_key,
+        }
     }
 
     pub fn get_record(
@@ -159,12 +540,26 @@ impl FSTMap {
     ) -> Result<Vec<u8>> {
         let (readings_entry, size_from_readings) = self.get_readings_result(readings_offset)?;
         let effective_size = record_size.or(size_from_readings);
-        let mut record_file = self
-            .record_file
-            .try_borrow_mut()
-            .map_err(|_| MDictError::InvalidFormat("record file is already borrowed".to_string()))?;
-        self.record_section
-            .decode_record(&mut *record_file, readings_entry.link_id, effective_size)
+        let mut records = self.records.try_borrow_mut().map_err(|_| {
+            MDictError::InvalidFormat("record file is already borrowed".to_string())
+        })?;
+        records.decode_record(readings_entry.link_id, effective_size)
+    }
+
+    /// The records of the entries at `readings_offsets`, in the same order,
+    /// decoding each record block once.
+    pub fn get_records_result(&self, readings_offsets: &[u64]) -> Result<Vec<Vec<u8>>> {
+        let extents = readings_offsets
+            .iter()
+            .map(|&offset| {
+                let (entry, record_size) = self.get_readings_result(offset)?;
+                Ok((entry.link_id, record_size))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut records = self.records.try_borrow_mut().map_err(|_| {
+            MDictError::InvalidFormat("record file is already borrowed".to_string())
+        })?;
+        records.decode_records(&extents)
     }
 
     pub fn get_readings(&self, offset: u64) -> Option<(ReadingsEntry, Option<u64>)> {
@@ -172,25 +567,98 @@ impl FSTMap {
     }
 
     pub fn get_readings_result(&self, offset: u64) -> Result<(ReadingsEntry, Option<u64>)> {
-        let (entry, entry_size) = self.parse_readings_from_uncompressed_offset_result(offset)?;
+        let mut readings = self.readings.try_borrow_mut().map_err(|_| {
+            MDictError::InvalidFormat("readings file is already borrowed".to_string())
+        })?;
+        let entry = readings.entry_at(offset)?;
         let next_offset = offset
-            .checked_add(entry_size)
+            .checked_add(entry.entry_size)
             .ok_or_else(|| MDictError::InvalidFormat("readings offset overflow".to_string()))?;
-        let next_link = self.get_next_link_id_from_uncompressed_offset_result(next_offset).ok();
-        let record_size = next_link.and_then(|next_link_id| next_link_id.checked_sub(entry.link_id));
+        let next_link = readings
+            .header_at(next_offset)
+            .ok()
+            .map(|header| header.link_id);
+        let record_size =
+            next_link.and_then(|next_link_id| next_link_id.checked_sub(entry.link_id));
 
         Ok((entry, record_size))
     }
+
+    /// Fields of the entry metadata the index was built with; empty if none.
+    pub fn metadata_fields(&self) -> Result<Vec<MetadataField>> {
+        let readings = self.readings.try_borrow().map_err(|_| {
+            MDictError::InvalidFormat("readings file is already borrowed".to_string())
+        })?;
+        Ok(readings.metadata_fields().unwrap_or_default().to_vec())
+    }
+
+    /// The metadata values of the entry at readings `offset`, in field order.
+    pub fn entry_metadata(&self, offset: u64) -> Result<Vec<MetadataEntry>> {
+        let (entry, _) = self.get_readings_result(offset)?;
+        let Some(values) = entry.metadata else {
+            return Ok(Vec::new());
+        };
+        decode_values(&self.metadata_fields()?, &values)
+    }
+
+    /// Decode every block of the readings and record sidecars.
+    pub fn verify(&self) -> Result<()> {
+        self.readings
+            .try_borrow_mut()
+            .map_err(|_| {
+                MDictError::InvalidFormat("readings file is already borrowed".to_string())
+            })?
+            .verify()?;
+        self.records
+            .try_borrow_mut()
+            .map_err(|_| MDictError::InvalidFormat("record file is already borrowed".to_string()))?
+            .verify()
+    }
+}
+
+/// One page of a prefix listing. `prev_key` and `next_key` are the raw FST
+/// keys to resume from in either direction, when there is more to show.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkPage {
+    pub results: Vec<(String, u64)>,
+    pub prev_key: Option<String>,
+    pub next_key: Option<String>,
+}
+
+impl LinkPage {
+    /// The page as [`KeyBlock`]s with cursors, reporting `total_results`.
+    pub fn into_search_page(self, total_results: Option<u64>) -> PrefixSearchPage {
+        PrefixSearchPage {
+            results: self
+                .results
+                .into_iter()
+                .map(|(key_text, key_id)| KeyBlock { key_id, key_text })
+                .collect(),
+            next_cursor: self
+                .next_key
+                .map(|after_key| PrefixSearchCursor { after_key }),
+            prev_cursor: self
+                .prev_key
+                .map(|before_key| PrefixSearchPrevCursor { before_key }),
+            total_results,
+        }
+    }
+}
+
+fn page_row(raw_key: &[u8], value: u64) -> (String, u64, String) {
+    let key_with_metadata = String::from_utf8_lossy(raw_key).to_string();
+    let clean_key = strip_fst_key_metadata(&key_with_metadata).to_string();
+    (clean_key, value, key_with_metadata)
 }
 
 /// A wrapper around fst::Stream that skips duplicate values
-pub struct DedupStream<'a> {
-    stream: Stream<'a>,
+pub struct DedupStream<'a, A: Automaton = AlwaysMatch> {
+    stream: Stream<'a, A>,
     seen_values: HashSet<u64>,
 }
 
-impl<'a> DedupStream<'a> {
-    pub fn new(stream: Stream<'a>) -> Self {
+impl<'a, A: Automaton> DedupStream<'a, A> {
+    pub fn new(stream: Stream<'a, A>) -> Self {
         Self {
             stream,
             seen_values: HashSet::new(),
@@ -198,7 +666,7 @@ impl<'a> DedupStream<'a> {
     }
 }
 
-impl<'a> Iterator for DedupStream<'a> {
+impl<'a, A: Automaton> Iterator for DedupStream<'a, A> {
     type Item = (String, u64);
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -212,3 +680,40 @@ impl<'a> Iterator for DedupStream<'a> {
         None
     }
 }
+
+/// Keys of a range returned by [`FSTMap::keys_in_range`], without the
+/// suffix that tells duplicate keys apart.
+pub struct KeyRangeStream<'a> {
+    stream: Stream<'a>,
+    skip: Option<String>,
+    end: Bound<String>,
+    done: bool,
+}
+
+impl Iterator for KeyRangeStream<'_> {
+    type Item = (String, u64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        while let Some((raw_key, value)) = self.stream.next() {
+            let key_with_metadata = String::from_utf8_lossy(raw_key);
+            let key = strip_fst_key_metadata(&key_with_metadata);
+            if self.skip.as_deref() == Some(key) {
+                continue;
+            }
+            let in_range = match &self.end {
+                Bound::Included(end) => key <= end.as_str(),
+                Bound::Excluded(end) => key < end.as_str(),
+                Bound::Unbounded => true,
+            };
+            if !in_range {
+                break;
+            }
+            return Some((key.to_string(), value));
+        }
+        self.done = true;
+        None
+    }
+}
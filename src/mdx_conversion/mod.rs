@@ -1,11 +1,40 @@
+pub mod aliases;
+pub mod build_manifest;
+#[cfg(feature = "fs")]
+pub mod delta;
+pub mod entry_metadata;
+#[cfg(feature = "fs")]
+pub mod export;
+#[cfg(feature = "fs")]
+pub mod flashcards;
+pub mod frequency;
+#[cfg(feature = "fs")]
 pub mod fst_indexing;
 pub mod records;
 pub mod reindexing;
+#[cfg(feature = "mmap")]
 pub mod fst_map;
+pub mod normalize;
+#[cfg(feature = "mmap")]
+pub mod optimized_bundle;
 pub mod readings;
+#[cfg(feature = "fs")]
+pub mod transcode;
+pub mod transform;
+#[cfg(feature = "mmap")]
+mod spill;
 
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(feature = "mmap")]
+use fst::Automaton;
+
+use crate::error::{MDictError, Result};
+
+#[cfg(feature = "fs")]
 const FST_KEY_METADATA_SEPARATOR: &str = "\u{0000}#";
 
+#[cfg(feature = "fs")]
 pub(crate) fn with_fst_key_metadata(key: &str, metadata: u64) -> String {
 	let mut out = String::with_capacity(key.len() + 2 + 20);
 	out.push_str(key);
@@ -14,6 +43,18 @@ pub(crate) fn with_fst_key_metadata(key: &str, metadata: u64) -> String {
 	out
 }
 
+/// Exclusive upper bound of the FST keys stored for `key`: `key` itself and
+/// `key` followed by any metadata suffix.
+#[cfg(feature = "mmap")]
+pub(crate) fn fst_key_metadata_end(key: &str) -> String {
+	let mut end = String::with_capacity(key.len() + 2);
+	end.push_str(key);
+	end.push_str(FST_KEY_METADATA_SEPARATOR);
+	crate::random_access_key_blocks::upper_bound_from_prefix(&end)
+		.expect("the separator ends in an ASCII character")
+}
+
+#[cfg(feature = "fs")]
 pub(crate) fn strip_fst_key_metadata(key: &str) -> &str {
 	if let Some((head, tail)) = key.rsplit_once(FST_KEY_METADATA_SEPARATOR) {
 		if tail.parse::<u64>().is_ok() {
@@ -22,4 +63,79 @@ pub(crate) fn strip_fst_key_metadata(key: &str) -> &str {
 	}
 
 	key
-}
\ No newline at end of file
+}
+
+/// Fail with [`MDictError::Cancelled`] once `cancel` is set. Long build
+/// passes call this between units of work.
+pub(crate) fn check_cancelled(cancel: &AtomicBool) -> Result<()> {
+	if cancel.load(Ordering::Relaxed) {
+		return Err(MDictError::Cancelled("index build cancelled".to_string()));
+	}
+	Ok(())
+}
+
+/// `key` with its characters in reverse order, as stored in the suffix index.
+#[cfg(feature = "fs")]
+pub(crate) fn reverse_key(key: &str) -> String {
+	key.chars().rev().collect()
+}
+
+/// Run `A` over the key part of FST keys only, so a duplicate key stored as
+/// `key\0#<metadata>` matches whenever `key` alone would.
+#[cfg(feature = "mmap")]
+pub(crate) struct IgnoreKeyMetadata<A>(pub A);
+
+#[cfg(feature = "mmap")]
+#[derive(Clone)]
+pub(crate) enum IgnoreKeyMetadataState<S> {
+	Key(S),
+	Separator(S),
+	Metadata(bool),
+	Dead,
+}
+
+#[cfg(feature = "mmap")]
+impl<A: Automaton> Automaton for IgnoreKeyMetadata<A>
+where
+	A::State: Clone,
+{
+	type State = IgnoreKeyMetadataState<A::State>;
+
+	fn start(&self) -> Self::State {
+		IgnoreKeyMetadataState::Key(self.0.start())
+	}
+
+	fn is_match(&self, state: &Self::State) -> bool {
+		match state {
+			IgnoreKeyMetadataState::Key(inner) => self.0.is_match(inner),
+			IgnoreKeyMetadataState::Metadata(matched) => *matched,
+			_ => false,
+		}
+	}
+
+	fn can_match(&self, state: &Self::State) -> bool {
+		match state {
+			IgnoreKeyMetadataState::Key(inner) => self.0.can_match(inner),
+			IgnoreKeyMetadataState::Separator(inner) => self.0.is_match(inner),
+			IgnoreKeyMetadataState::Metadata(matched) => *matched,
+			IgnoreKeyMetadataState::Dead => false,
+		}
+	}
+
+	fn accept(&self, state: &Self::State, byte: u8) -> Self::State {
+		let separator = FST_KEY_METADATA_SEPARATOR.as_bytes();
+		match state {
+			IgnoreKeyMetadataState::Key(inner) if byte == separator[0] => {
+				IgnoreKeyMetadataState::Separator(inner.clone())
+			}
+			IgnoreKeyMetadataState::Key(inner) => {
+				IgnoreKeyMetadataState::Key(self.0.accept(inner, byte))
+			}
+			IgnoreKeyMetadataState::Separator(inner) if byte == separator[1] => {
+				IgnoreKeyMetadataState::Metadata(self.0.is_match(inner))
+			}
+			IgnoreKeyMetadataState::Metadata(matched) => IgnoreKeyMetadataState::Metadata(*matched),
+			_ => IgnoreKeyMetadataState::Dead,
+		}
+	}
+}
@@ -0,0 +1,223 @@
+//! Readings a headword is indexed under in the optimized FST.
+//!
+//! A [`KeyNormalizer`] turns one key text into the readings that should find
+//! it, e.g. `かく【書く】` into `かく` and `書く`. Rules can be chained with
+//! [`NormalizerPipeline`]; the default pipeline is
+//! [`JapaneseBracketSplit`] alone.
+
+use std::sync::Arc;
+
+/// Turns a key text into the readings it is indexed under.
+pub trait KeyNormalizer: Send + Sync {
+    /// Identifies the rule in build fingerprints: a build with a different
+    /// name is not reused. Give rules that index differently different names.
+    fn name(&self) -> String;
+
+    /// Readings for `key_text`, most specific first. An empty result indexes
+    /// the key under nothing.
+    fn readings(&self, key_text: &str) -> Vec<String>;
+}
+
+/// Built-in rules, selectable over UniFFI with
+/// [`crate::MdictBundle::set_key_normalizers`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum KeyNormalizerRule {
+    JapaneseBrackets,
+    PinyinTones,
+    ArabicDiacritics,
+}
+
+impl KeyNormalizerRule {
+    pub fn normalizer(self) -> Arc<dyn KeyNormalizer> {
+        match self {
+            Self::JapaneseBrackets => Arc::new(JapaneseBracketSplit),
+            Self::PinyinTones => Arc::new(PinyinToneStrip),
+            Self::ArabicDiacritics => Arc::new(ArabicDiacriticStrip),
+        }
+    }
+}
+
+/// Splits `reading【kanji】` keys, as in Jitendex, into the reading and the
+/// written form. Other keys are kept as they are.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JapaneseBracketSplit;
+
+impl KeyNormalizer for JapaneseBracketSplit {
+    fn name(&self) -> String {
+        "japanese_brackets".to_string()
+    }
+
+    fn readings(&self, key_text: &str) -> Vec<String> {
+        if let Some((before, rest)) = key_text.split_once('【') {
+            if let Some((inside, _)) = rest.split_once('】') {
+                if before == inside {
+                    return vec![before.to_string()];
+                }
+                return vec![before.to_string(), inside.to_string()];
+            }
+        }
+        vec![key_text.to_string()]
+    }
+}
+
+/// Indexes pinyin keys under their toneless form too: `nǐ hǎo` and `ni3 hao3`
+/// are also found as `ni hao`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PinyinToneStrip;
+
+impl KeyNormalizer for PinyinToneStrip {
+    fn name(&self) -> String {
+        "pinyin_tones".to_string()
+    }
+
+    fn readings(&self, key_text: &str) -> Vec<String> {
+        with_variant(key_text, strip_pinyin_tones(key_text))
+    }
+}
+
+/// Indexes Arabic keys under their unvocalized form too, without harakat,
+/// Quranic annotation marks or tatweel.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ArabicDiacriticStrip;
+
+impl KeyNormalizer for ArabicDiacriticStrip {
+    fn name(&self) -> String {
+        "arabic_diacritics".to_string()
+    }
+
+    fn readings(&self, key_text: &str) -> Vec<String> {
+        let stripped = key_text
+            .chars()
+            .filter(|&c| !is_arabic_mark(c))
+            .collect::<String>();
+        with_variant(key_text, stripped)
+    }
+}
+
+/// A user-supplied rule.
+pub struct FnNormalizer<F> {
+    name: String,
+    f: F,
+}
+
+impl<F> FnNormalizer<F>
+where
+    F: Fn(&str) -> Vec<String> + Send + Sync,
+{
+    pub fn new(name: impl Into<String>, f: F) -> Self {
+        Self {
+            name: name.into(),
+            f,
+        }
+    }
+}
+
+impl<F> KeyNormalizer for FnNormalizer<F>
+where
+    F: Fn(&str) -> Vec<String> + Send + Sync,
+{
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn readings(&self, key_text: &str) -> Vec<String> {
+        (self.f)(key_text)
+    }
+}
+
+/// Rules applied in order: each rule runs on every reading the previous one
+/// produced. Duplicate readings are dropped.
+#[derive(Clone)]
+pub struct NormalizerPipeline {
+    rules: Vec<Arc<dyn KeyNormalizer>>,
+}
+
+impl NormalizerPipeline {
+    pub fn new(rules: Vec<Arc<dyn KeyNormalizer>>) -> Self {
+        Self { rules }
+    }
+
+    pub fn from_rules(rules: &[KeyNormalizerRule]) -> Self {
+        Self::new(rules.iter().map(|rule| rule.normalizer()).collect())
+    }
+}
+
+impl Default for NormalizerPipeline {
+    fn default() -> Self {
+        Self::from_rules(&[KeyNormalizerRule::JapaneseBrackets])
+    }
+}
+
+impl KeyNormalizer for NormalizerPipeline {
+    fn name(&self) -> String {
+        self.rules
+            .iter()
+            .map(|rule| rule.name())
+            .collect::<Vec<_>>()
+            .join("+")
+    }
+
+    fn readings(&self, key_text: &str) -> Vec<String> {
+        let mut readings = vec![key_text.to_string()];
+        for rule in &self.rules {
+            let mut next = Vec::with_capacity(readings.len());
+            for reading in readings.iter().flat_map(|reading| rule.readings(reading)) {
+                if !next.contains(&reading) {
+                    next.push(reading);
+                }
+            }
+            readings = next;
+        }
+        readings
+    }
+}
+
+fn with_variant(key_text: &str, variant: String) -> Vec<String> {
+    if variant == key_text || variant.is_empty() {
+        vec![key_text.to_string()]
+    } else {
+        vec![key_text.to_string(), variant]
+    }
+}
+
+fn strip_pinyin_tones(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut after_letter = false;
+    for c in text.chars() {
+        if after_letter && ('1'..='5').contains(&c) {
+            after_letter = false;
+            continue;
+        }
+        out.push(toneless_vowel(c));
+        after_letter = c.is_alphabetic();
+    }
+    out
+}
+
+pub(crate) fn toneless_vowel(c: char) -> char {
+    match c {
+        'ā' | 'á' | 'ǎ' | 'à' => 'a',
+        'ē' | 'é' | 'ě' | 'è' => 'e',
+        'ī' | 'í' | 'ǐ' | 'ì' => 'i',
+        'ō' | 'ó' | 'ǒ' | 'ò' => 'o',
+        'ū' | 'ú' | 'ǔ' | 'ù' => 'u',
+        'ǖ' | 'ǘ' | 'ǚ' | 'ǜ' => 'ü',
+        'Ā' | 'Á' | 'Ǎ' | 'À' => 'A',
+        'Ē' | 'É' | 'Ě' | 'È' => 'E',
+        'Ī' | 'Í' | 'Ǐ' | 'Ì' => 'I',
+        'Ō' | 'Ó' | 'Ǒ' | 'Ò' => 'O',
+        'Ū' | 'Ú' | 'Ǔ' | 'Ù' => 'U',
+        'Ǖ' | 'Ǘ' | 'Ǚ' | 'Ǜ' => 'Ü',
+        _ => c,
+    }
+}
+
+fn is_arabic_mark(c: char) -> bool {
+    matches!(c, '\u{0610}'..='\u{061A}'
+        | '\u{0640}'
+        | '\u{064B}'..='\u{065F}'
+        | '\u{0670}'
+        | '\u{06D6}'..='\u{06DC}'
+        | '\u{06DF}'..='\u{06E8}'
+        | '\u{06EA}'..='\u{06ED}')
+}
@@ -0,0 +1,228 @@
+//! Single-file container for an optimized index.
+//!
+//! The file starts with a table of contents listing typed sections by offset
+//! and length. Sections are stored as they would be as separate files: the
+//! key FST and optional suffix, romanized and entry id FSTs as raw `fst` maps, the
+//! readings and record sidecars as packed storage containers, and an optional
+//! alias table as tab-separated text. Every section starts on an
+//! 8-byte boundary so the whole file can be mapped once and shared.
+
+use std::fs::File;
+use std::io::{BufWriter, Cursor, Write};
+use std::path::{Path, PathBuf};
+
+use binrw::{BinRead, BinWrite};
+
+use crate::error::{MDictError, Result};
+use crate::seekable_mmap::MmapSection;
+
+pub const MAGIC: [u8; 8] = *b"MDOPTBDL";
+pub const VERSION: u8 = 1;
+
+const SECTION_ALIGNMENT: u64 = 8;
+const FIXED_HEADER_SIZE: u64 = 16;
+const TOC_ENTRY_SIZE: u64 = 24;
+/// Bounds the table of contents read from untrusted files.
+const MAX_SECTIONS: u32 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum SectionKind {
+    Fst = 1,
+    Readings = 2,
+    Records = 3,
+    SuffixFst = 4,
+    RomanizedFst = 5,
+    EntryIds = 6,
+    Aliases = 7,
+}
+
+impl SectionKind {
+    fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            1 => Some(Self::Fst),
+            2 => Some(Self::Readings),
+            3 => Some(Self::Records),
+            4 => Some(Self::SuffixFst),
+            5 => Some(Self::RomanizedFst),
+            6 => Some(Self::EntryIds),
+            7 => Some(Self::Aliases),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, BinRead, BinWrite)]
+#[brw(little)]
+struct TocEntry {
+    kind: u32,
+    reserved: u32,
+    offset: u64,
+    len: u64,
+}
+
+#[derive(Debug, Clone, BinRead, BinWrite)]
+#[brw(little, magic = b"MDOPTBDL")]
+#[br(assert(version == VERSION, "unsupported optimized bundle version"))]
+struct BundleHeader {
+    version: u8,
+    reserved: [u8; 3],
+    #[br(assert(section_count <= MAX_SECTIONS, "too many bundle sections"))]
+    section_count: u32,
+    #[br(count = section_count as usize)]
+    toc: Vec<TocEntry>,
+}
+
+/// The sections of an optimized index. Unknown section kinds found when
+/// reading are skipped, so later versions can add sections.
+#[derive(Debug, Clone)]
+pub struct BundleSections {
+    pub fst: MmapSection,
+    pub readings: MmapSection,
+    pub records: MmapSection,
+    pub suffix_fst: Option<MmapSection>,
+    pub romanized_fst: Option<MmapSection>,
+    pub entry_ids: Option<MmapSection>,
+    pub aliases: Option<MmapSection>,
+}
+
+impl BundleSections {
+    /// Map `path` and locate its sections.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = MmapSection::open(&File::open(path)?)?;
+        Self::parse(file)
+    }
+
+    pub fn parse(file: MmapSection) -> Result<Self> {
+        let header = BundleHeader::read_le(&mut Cursor::new(file.as_slice()))?;
+
+        let mut fst = None;
+        let mut readings = None;
+        let mut records = None;
+        let mut suffix_fst = None;
+        let mut romanized_fst = None;
+        let mut entry_ids = None;
+        let mut aliases = None;
+        for entry in &header.toc {
+            let Some(kind) = SectionKind::from_u32(entry.kind) else {
+                continue;
+            };
+            let section = file.slice(entry.offset, entry.len).ok_or_else(|| {
+                MDictError::InvalidFormat(format!(
+                    "{:?} section at {}+{} exceeds bundle size {}",
+                    kind,
+                    entry.offset,
+                    entry.len,
+                    file.len()
+                ))
+            })?;
+            let slot = match kind {
+                SectionKind::Fst => &mut fst,
+                SectionKind::Readings => &mut readings,
+                SectionKind::Records => &mut records,
+                SectionKind::SuffixFst => &mut suffix_fst,
+                SectionKind::RomanizedFst => &mut romanized_fst,
+                SectionKind::EntryIds => &mut entry_ids,
+                SectionKind::Aliases => &mut aliases,
+            };
+            if slot.replace(section).is_some() {
+                return Err(MDictError::InvalidFormat(format!(
+                    "duplicate {:?} section in bundle",
+                    kind
+                )));
+            }
+        }
+
+        let required = |section: Option<MmapSection>, kind: SectionKind| {
+            section.ok_or_else(|| {
+                MDictError::InvalidFormat(format!("bundle has no {:?} section", kind))
+            })
+        };
+        Ok(Self {
+            fst: required(fst, SectionKind::Fst)?,
+            readings: required(readings, SectionKind::Readings)?,
+            records: required(records, SectionKind::Records)?,
+            suffix_fst,
+            romanized_fst,
+            entry_ids,
+            aliases,
+        })
+    }
+
+    /// Write the sections to `path` as one bundle. The file is written next
+    /// to `path` and renamed into place, so `path` may be the bundle these
+    /// sections are mapped from.
+    pub fn write_to_path(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+
+        let mut writer = BufWriter::new(File::create(&partial)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        drop(writer);
+        std::fs::rename(&partial, path)?;
+        Ok(())
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let mut sections = vec![
+            (SectionKind::Fst, &self.fst),
+            (SectionKind::Readings, &self.readings),
+            (SectionKind::Records, &self.records),
+        ];
+        if let Some(suffix_fst) = &self.suffix_fst {
+            sections.push((SectionKind::SuffixFst, suffix_fst));
+        }
+        if let Some(romanized_fst) = &self.romanized_fst {
+            sections.push((SectionKind::RomanizedFst, romanized_fst));
+        }
+        if let Some(entry_ids) = &self.entry_ids {
+            sections.push((SectionKind::EntryIds, entry_ids));
+        }
+        if let Some(aliases) = &self.aliases {
+            sections.push((SectionKind::Aliases, aliases));
+        }
+
+        let mut offset = align(FIXED_HEADER_SIZE + TOC_ENTRY_SIZE * sections.len() as u64);
+        let mut toc = Vec::with_capacity(sections.len());
+        for (kind, section) in &sections {
+            toc.push(TocEntry {
+                kind: *kind as u32,
+                reserved: 0,
+                offset,
+                len: section.len() as u64,
+            });
+            offset = align(offset + section.len() as u64);
+        }
+
+        let header = BundleHeader {
+            version: VERSION,
+            reserved: [0; 3],
+            section_count: sections.len() as u32,
+            toc,
+        };
+        let mut head = Cursor::new(Vec::new());
+        header.write_le(&mut head)?;
+        let mut written = head.get_ref().len() as u64;
+        writer.write_all(head.get_ref())?;
+
+        for (entry, (_, section)) in header.toc.iter().zip(&sections) {
+            pad(writer, entry.offset - written)?;
+            writer.write_all(section.as_slice())?;
+            written = entry.offset + entry.len;
+        }
+        Ok(())
+    }
+}
+
+fn align(offset: u64) -> u64 {
+    offset.div_ceil(SECTION_ALIGNMENT) * SECTION_ALIGNMENT
+}
+
+fn pad<W: Write>(writer: &mut W, len: u64) -> Result<()> {
+    const ZEROS: [u8; SECTION_ALIGNMENT as usize] = [0; SECTION_ALIGNMENT as usize];
+    writer.write_all(&ZEROS[..len as usize])?;
+    Ok(())
+}
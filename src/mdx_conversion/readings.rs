@@ -1,15 +1,43 @@
+#[cfg(feature = "fs")]
 use std::collections::{HashMap, HashSet};
+#[cfg(feature = "fs")]
 use std::fs::File;
+#[cfg(feature = "fs")]
 use std::io::BufWriter;
-use std::io::{Cursor, Read, Seek, SeekFrom};
+#[cfg(feature = "fs")]
 use std::io::Write;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+#[cfg(feature = "fs")]
 use std::path::Path;
 
 use binrw::{BinRead, BinWrite};
 
+use crate::block_cache::CacheCapacity;
 use crate::error::{MDictError, Result};
+use crate::mdx_conversion::entry_metadata;
+#[cfg(feature = "fs")]
+use crate::mdx_conversion::entry_metadata::EntryMetadata;
+#[cfg(feature = "fs")]
+use crate::mdx_conversion::frequency::FrequencyList;
+#[cfg(feature = "fs")]
+use crate::packed_storage::{CompressionEncoding, PackedStorageWriter};
+use crate::packed_storage::{PackedStorageReader, MAGIC};
+use crate::types::MetadataField;
 
 const READINGS_ENTRY_HEADER_SIZE: u64 = 12;
+/// Starts the optional rank trailer of a readings payload: the marker then a
+/// little-endian `u32`. The byte never occurs in UTF-8, so payloads written
+/// without ranks read as before.
+const RANK_MARKER: u8 = 0xFF;
+/// Starts the optional source trailer of an entry in a combined index,
+/// written before the rank trailer: the marker then a little-endian `u32`.
+const SOURCE_MARKER: u8 = 0xFE;
+const TRAILER_SIZE: usize = 5;
+/// Starts the metadata trailer, written last in every entry of a sidecar
+/// whose header holds a metadata schema: the marker, the encoded values
+/// (see [`crate::mdx_conversion::entry_metadata`]) and their length as a
+/// little-endian `u32`.
+const METADATA_MARKER: u8 = 0xFD;
 
 #[derive(Debug, Clone, BinRead, BinWrite)]
 #[brw(little)]
@@ -23,9 +51,50 @@ pub struct ReadingsEntry {
     pub length: u32,
     pub link_id: u64,
     pub readings: Vec<String>,
+    /// Frequency rank from the list the index was built with; 1 is the most
+    /// frequent.
+    pub rank: Option<u32>,
+    /// Which dictionary of a combined index the entry came from; `None` for
+    /// single-dictionary indexes.
+    pub source: Option<u32>,
+    /// Encoded metadata values, in sidecars built with entry metadata.
+    pub metadata: Option<Vec<u8>>,
     pub entry_size: u64,
 }
 
+/// Split a trailer starting with `marker` off the end of `payload`, along
+/// with the separator written before it.
+fn split_trailer(payload: &[u8], marker: u8) -> (&[u8], Option<u32>) {
+    let Some(trailer_start) = payload.len().checked_sub(TRAILER_SIZE) else {
+        return (payload, None);
+    };
+    if payload[trailer_start] != marker {
+        return (payload, None);
+    }
+    let mut value = [0u8; 4];
+    value.copy_from_slice(&payload[trailer_start + 1..]);
+    let rest = &payload[..trailer_start];
+    let rest = rest.strip_suffix(&[0]).unwrap_or(rest);
+    (rest, Some(u32::from_le_bytes(value)))
+}
+
+/// Split the metadata trailer off the end of `payload`, along with the
+/// separator written before it.
+fn split_metadata_trailer(payload: &[u8]) -> Result<(&[u8], &[u8])> {
+    let invalid = || MDictError::InvalidFormat("readings entry lacks metadata".to_string());
+    let len_start = payload.len().checked_sub(4).ok_or_else(invalid)?;
+    let mut len = [0u8; 4];
+    len.copy_from_slice(&payload[len_start..]);
+    let marker = (u32::from_le_bytes(len) as usize)
+        .checked_add(1)
+        .and_then(|len| len_start.checked_sub(len))
+        .filter(|&marker| payload[marker] == METADATA_MARKER)
+        .ok_or_else(invalid)?;
+    let rest = &payload[..marker];
+    let rest = rest.strip_suffix(&[0]).unwrap_or(rest);
+    Ok((rest, &payload[marker + 1..len_start]))
+}
+
 fn parse_readings_payload(payload: &[u8]) -> Result<Vec<String>> {
     let mut readings = Vec::new();
     let mut start = 0usize;
@@ -56,11 +125,21 @@ fn parse_readings_payload(payload: &[u8]) -> Result<Vec<String>> {
     Ok(readings)
 }
 
-fn serialize_readings_entry(remapped_link: u64, readings: &HashSet<String>) -> Result<Vec<u8>> {
+#[cfg(feature = "fs")]
+fn serialize_readings_entry(
+    remapped_link: u64,
+    readings: &HashSet<String>,
+    rank: Option<u32>,
+    source: Option<u32>,
+    metadata: Option<&[u8]>,
+) -> Result<Vec<u8>> {
     let mut sorted_readings: Vec<&str> = readings.iter().map(String::as_str).collect();
     sorted_readings.sort_unstable();
+    let trailers_len = [source, rank].iter().flatten().count() * (1 + TRAILER_SIZE)
+        + metadata.map_or(0, |values| 2 + values.len() + 4);
     let payload_len: usize = sorted_readings.iter().map(|reading| reading.len()).sum::<usize>()
-        + sorted_readings.len().saturating_sub(1);
+        + sorted_readings.len().saturating_sub(1)
+        + trailers_len;
 
     let header = ReadingsEntryHeader {
         length: payload_len as u32,
@@ -77,22 +156,156 @@ fn serialize_readings_entry(remapped_link: u64, readings: &HashSet<String>) -> R
         }
         out.extend_from_slice(reading.as_bytes());
     }
+    for (marker, value) in [(SOURCE_MARKER, source), (RANK_MARKER, rank)] {
+        if let Some(value) = value {
+            out.push(0);
+            out.push(marker);
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    if let Some(values) = metadata {
+        out.push(0);
+        out.push(METADATA_MARKER);
+        out.extend_from_slice(values);
+        out.extend_from_slice(&(values.len() as u32).to_le_bytes());
+    }
 
     Ok(out)
 }
 
+#[cfg(feature = "fs")]
 pub fn write_readings_data_and_collect_key_offsets(
     readings_list: &HashMap<u64, HashSet<String>>,
     link_order: &[u64],
     link_remap: &HashMap<u64, u64>,
     readings_path: impl AsRef<Path>,
 ) -> Result<Vec<(String, u64)>> {
+    write_readings_data(readings_list, link_order, link_remap, None, None, readings_path)
+        .map(|(key_link_pairs, _)| key_link_pairs)
+}
+
+/// Key/offset pairs for the FST, and the readings offset of each source link.
+#[cfg(feature = "fs")]
+pub(crate) type ReadingsOffsets = (Vec<(String, u64)>, HashMap<u64, u64>);
+
+/// [`write_readings_data_and_collect_key_offsets`], storing each entry's
+/// rank from `frequencies` and values from `metadata`, and also returning
+/// the readings offset written for each source link.
+#[cfg(feature = "fs")]
+pub(crate) fn write_readings_data(
+    readings_list: &HashMap<u64, HashSet<String>>,
+    link_order: &[u64],
+    link_remap: &HashMap<u64, u64>,
+    frequencies: Option<&FrequencyList>,
+    metadata: Option<&EntryMetadata>,
+    readings_path: impl AsRef<Path>,
+) -> Result<ReadingsOffsets> {
     let estimated_keys = readings_list.values().map(HashSet::len).sum();
     let mut key_link_pairs = Vec::with_capacity(estimated_keys);
-    let output_file = File::create(readings_path)?;
-    let mut writer = BufWriter::new(output_file);
-    let mut current_offset = 0u64;
+    let mut storage_writer = readings_storage_writer()?;
+    if let Some(metadata) = metadata {
+        storage_writer = storage_writer.with_user_data(metadata.schema_bytes());
+    }
+    let entry_offsets = push_readings_entries(
+        &mut storage_writer,
+        readings_list,
+        link_order,
+        link_remap,
+        frequencies,
+        metadata,
+        None,
+        &mut key_link_pairs,
+    )?;
+    finish_readings(storage_writer, readings_path)?;
+
+    Ok((key_link_pairs, entry_offsets))
+}
+
+/// One dictionary of a combined index: its readings list, the order its
+/// links are written in and where its records landed.
+#[cfg(feature = "fs")]
+pub(crate) struct ReadingsSource<'a> {
+    pub readings_list: &'a HashMap<u64, HashSet<String>>,
+    pub link_order: &'a [u64],
+    pub link_remap: &'a HashMap<u64, u64>,
+}
+
+/// Key/offset pairs for the FST, and the readings offset of each source link
+/// per source dictionary.
+#[cfg(feature = "fs")]
+pub(crate) type MultiReadingsOffsets = (Vec<(String, u64)>, Vec<HashMap<u64, u64>>);
+
+/// [`write_readings_data`] for several dictionaries sharing one sidecar.
+/// Entries are tagged with the position of their source in `sources`, and
+/// readings offsets are returned per source.
+#[cfg(feature = "fs")]
+pub(crate) fn write_readings_data_multi(
+    sources: &[ReadingsSource<'_>],
+    readings_path: impl AsRef<Path>,
+) -> Result<MultiReadingsOffsets> {
+    let estimated_keys = sources
+        .iter()
+        .flat_map(|source| source.readings_list.values())
+        .map(HashSet::len)
+        .sum();
+    let mut key_link_pairs = Vec::with_capacity(estimated_keys);
+    let mut storage_writer = readings_storage_writer()?;
+    let mut entry_offsets = Vec::with_capacity(sources.len());
+    for (source_id, source) in sources.iter().enumerate() {
+        let source_id = u32::try_from(source_id)
+            .map_err(|_| MDictError::InvalidArgument("too many source dictionaries".to_string()))?;
+        entry_offsets.push(push_readings_entries(
+            &mut storage_writer,
+            source.readings_list,
+            source.link_order,
+            source.link_remap,
+            None,
+            None,
+            Some(source_id),
+            &mut key_link_pairs,
+        )?);
+    }
+    finish_readings(storage_writer, readings_path)?;
+
+    Ok((key_link_pairs, entry_offsets))
+}
+
+#[cfg(feature = "fs")]
+fn readings_storage_writer() -> Result<PackedStorageWriter> {
+    let config = crate::config::config();
+    PackedStorageWriter::new(
+        CompressionEncoding::Zstd,
+        config.record_compression_level,
+        config.packed_block_size(),
+    )
+}
+
+#[cfg(feature = "fs")]
+fn finish_readings(
+    storage_writer: PackedStorageWriter,
+    readings_path: impl AsRef<Path>,
+) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(readings_path)?);
+    storage_writer.finish_to_writer(&mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
 
+/// Write one entry per link of `readings_list` in `link_order`, adding its
+/// keys to `key_link_pairs` and returning the offset written for each link.
+#[cfg(feature = "fs")]
+#[allow(clippy::too_many_arguments)]
+fn push_readings_entries(
+    storage_writer: &mut PackedStorageWriter,
+    readings_list: &HashMap<u64, HashSet<String>>,
+    link_order: &[u64],
+    link_remap: &HashMap<u64, u64>,
+    frequencies: Option<&FrequencyList>,
+    metadata: Option<&EntryMetadata>,
+    source: Option<u32>,
+    key_link_pairs: &mut Vec<(String, u64)>,
+) -> Result<HashMap<u64, u64>> {
+    let mut entry_offsets = HashMap::with_capacity(readings_list.len());
     for &old_link in link_order {
         let Some(indices) = readings_list.get(&old_link) else {
             continue;
@@ -102,113 +315,157 @@ pub fn write_readings_data_and_collect_key_offsets(
             MDictError::InvalidArgument(format!("missing remapped link for old link {}", old_link))
         })?;
 
-        let entry_bytes = serialize_readings_entry(remapped_link, indices)?;
-        let entry_len = entry_bytes.len() as u64;
-        writer.write_all(&entry_bytes)?;
+        let rank = frequencies.and_then(|frequencies| frequencies.best_rank(indices));
+        let values = metadata.map(|metadata| metadata.encode_values(old_link));
+        let entry_bytes =
+            serialize_readings_entry(remapped_link, indices, rank, source, values.as_deref())?;
+        let offset = storage_writer.push_entry(&entry_bytes)?;
+        entry_offsets.insert(old_link, offset);
+
+        let mut sorted_indices = indices.iter().collect::<Vec<_>>();
+        sorted_indices.sort_unstable();
+        for index in sorted_indices {
+            key_link_pairs.push((index.clone(), offset));
+        }
+    }
+    Ok(entry_offsets)
+}
+
+/// Save `readings_list` so a build can resume without the readings pass. The
+/// file holds one readings entry per source link, in link order.
+#[cfg(feature = "fs")]
+pub fn write_readings_list_checkpoint(
+    readings_list: &HashMap<u64, HashSet<String>>,
+    path: impl AsRef<Path>,
+) -> Result<()> {
+    let mut storage_writer = readings_storage_writer()?;
+
+    let mut links = readings_list.keys().copied().collect::<Vec<_>>();
+    links.sort_unstable();
+    for link in links {
+        let entry_bytes =
+            serialize_readings_entry(link, &readings_list[&link], None, None, None)?;
+        storage_writer.push_entry(&entry_bytes)?;
+    }
 
-        for index in indices {
-            key_link_pairs.push((index.clone(), current_offset));
+    finish_readings(storage_writer, path)
+}
+
+/// Load a readings list saved by [`write_readings_list_checkpoint`].
+#[cfg(feature = "fs")]
+pub fn read_readings_list_checkpoint(
+    path: impl AsRef<Path>,
+) -> Result<HashMap<u64, HashSet<String>>> {
+    let mut section = ReadingsSection::parse(File::open(path)?, 1)?;
+    let mut readings_list = HashMap::new();
+    let mut offset = 0;
+    while offset < section.len() {
+        let entry = section.entry_at(offset)?;
+        offset += entry.entry_size;
+        readings_list.insert(entry.link_id, entry.readings.into_iter().collect());
+    }
+    Ok(readings_list)
+}
+
+/// The readings sidecar: a packed storage container of readings entries,
+/// addressed by their offset in the uncompressed stream.
+pub struct ReadingsSection<R> {
+    storage: PackedStorageReader<R>,
+    /// Schema from the header of sidecars built with entry metadata.
+    metadata_fields: Option<Vec<MetadataField>>,
+}
+
+impl<R: Read + Seek> ReadingsSection<R> {
+    pub fn parse(mut reader: R, cache_capacity: impl Into<CacheCapacity>) -> Result<Self> {
+        reader.seek(SeekFrom::Start(0))?;
+        let mut magic = [0u8; 8];
+        let is_packed = reader.read_exact(&mut magic).is_ok() && magic == MAGIC;
+        if !is_packed {
+            return Err(MDictError::InvalidFormat(
+                "readings file is not packed storage; rebuild the FST assets".to_string(),
+            ));
         }
+        reader.seek(SeekFrom::Start(0))?;
+        let storage = PackedStorageReader::new(reader, cache_capacity)?;
+        let metadata_fields = match &storage.index().header.user_data {
+            Some(schema) => Some(entry_metadata::parse_schema(schema)?),
+            None => None,
+        };
+        Ok(Self {
+            storage,
+            metadata_fields,
+        })
+    }
 
-        current_offset = current_offset
-            .checked_add(entry_len)
-            .ok_or_else(|| MDictError::InvalidFormat("readings file offset overflow".to_string()))?;
+    /// Fields of the entry metadata the sidecar was built with, if any.
+    pub fn metadata_fields(&self) -> Option<&[MetadataField]> {
+        self.metadata_fields.as_deref()
     }
 
-    writer.flush()?;
+    pub fn get_ref(&self) -> &R {
+        self.storage.get_ref()
+    }
 
-    Ok(key_link_pairs)
-}
-
-pub fn read_entry_from_offset<R: Read + Seek>(
-    reader: &mut R,
-    offset: u64,
-) -> Result<ReadingsEntry> {
-    reader.seek(SeekFrom::Start(offset))?;
-    let header = ReadingsEntryHeader::read_le(reader)?;
-
-    let payload_len = usize::try_from(header.length)
-        .map_err(|_| MDictError::InvalidFormat("readings payload length overflow".to_string()))?;
-    let mut payload = vec![0u8; payload_len];
-    reader.read_exact(&mut payload)?;
-    let readings = parse_readings_payload(&payload)?;
-
-    Ok(ReadingsEntry {
-        length: header.length,
-        link_id: header.link_id,
-        readings,
-        entry_size: READINGS_ENTRY_HEADER_SIZE + header.length as u64,
-    })
-}
-
-pub fn read_header_from_bytes_result(bytes: &[u8], offset: u64) -> Result<ReadingsEntryHeader> {
-    let start = usize::try_from(offset)
-        .map_err(|_| MDictError::InvalidFormat("readings header offset overflow".to_string()))?;
-    let end = start
-        .checked_add(READINGS_ENTRY_HEADER_SIZE as usize)
-        .ok_or_else(|| MDictError::InvalidFormat("readings header range overflow".to_string()))?;
-    let slice = bytes.get(start..end).ok_or_else(|| {
-        MDictError::InvalidFormat(format!(
-            "readings header out of bounds at offset {}",
-            offset
-        ))
-    })?;
-
-    let mut cursor = Cursor::new(slice);
-    Ok(ReadingsEntryHeader::read_le(&mut cursor)?)
-}
-
-pub fn read_header_from_bytes(bytes: &[u8], offset: u64) -> Option<ReadingsEntryHeader> {
-    read_header_from_bytes_result(bytes, offset).ok()
-}
-
-pub fn read_entry_from_bytes_result(bytes: &[u8], offset: u64) -> Result<ReadingsEntry> {
-    let header = read_header_from_bytes_result(bytes, offset)?;
-    let start = usize::try_from(offset)
-        .map_err(|_| MDictError::InvalidFormat("readings entry offset overflow".to_string()))?;
-    let payload_start = start
-        .checked_add(READINGS_ENTRY_HEADER_SIZE as usize)
-        .ok_or_else(|| MDictError::InvalidFormat("readings payload start overflow".to_string()))?;
-    let payload_len = usize::try_from(header.length)
-        .map_err(|_| MDictError::InvalidFormat("readings payload length overflow".to_string()))?;
-    let payload_end = payload_start
-        .checked_add(payload_len)
-        .ok_or_else(|| MDictError::InvalidFormat("readings payload end overflow".to_string()))?;
-
-    let payload = bytes.get(payload_start..payload_end).ok_or_else(|| {
-        MDictError::InvalidFormat(format!(
-            "readings payload out of bounds at offset {}",
-            offset
-        ))
-    })?;
-
-    let readings = parse_readings_payload(payload)?;
-
-    Ok(ReadingsEntry {
-        length: header.length,
-        link_id: header.link_id,
-        readings,
-        entry_size: READINGS_ENTRY_HEADER_SIZE + header.length as u64,
-    })
-}
-
-pub fn read_entry_from_bytes(bytes: &[u8], offset: u64) -> Option<ReadingsEntry> {
-    read_entry_from_bytes_result(bytes, offset).ok()
-}
-
-pub fn read_link_id_from_offset<R: Read + Seek>(
-    reader: &mut R,
-    offset: u64,
-) -> Result<Option<u64>> {
-    let file_len = reader.seek(SeekFrom::End(0))?;
-    if offset
-        .checked_add(READINGS_ENTRY_HEADER_SIZE)
-        .is_none_or(|end| end > file_len)
-    {
-        return Ok(None);
-    };
+    /// Size of the uncompressed entry stream; valid offsets are below it.
+    pub fn len(&self) -> u64 {
+        self.storage.total_uncompressed_size()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn header_at(&mut self, offset: u64) -> Result<ReadingsEntryHeader> {
+        self.ensure_in_bounds(offset)?;
+        let bytes = self
+            .storage
+            .read_at(offset, READINGS_ENTRY_HEADER_SIZE as usize)?;
+        Ok(ReadingsEntryHeader::read_le(&mut Cursor::new(bytes))?)
+    }
 
-    reader.seek(SeekFrom::Start(offset))?;
-    let header = ReadingsEntryHeader::read_le(reader)?;
-    Ok(Some(header.link_id))
+    pub fn entry_at(&mut self, offset: u64) -> Result<ReadingsEntry> {
+        let header = self.header_at(offset)?;
+        let payload_len = usize::try_from(header.length).map_err(|_| {
+            MDictError::InvalidFormat("readings payload length overflow".to_string())
+        })?;
+        let payload = self
+            .storage
+            .read_at(offset + READINGS_ENTRY_HEADER_SIZE, payload_len)?;
+        let (payload, metadata) = match self.metadata_fields {
+            Some(_) => {
+                let (rest, values) = split_metadata_trailer(&payload)?;
+                (rest, Some(values.to_vec()))
+            }
+            None => (&payload[..], None),
+        };
+        let (payload, rank) = split_trailer(payload, RANK_MARKER);
+        let (payload, source) = split_trailer(payload, SOURCE_MARKER);
+        let readings = parse_readings_payload(payload)?;
+
+        Ok(ReadingsEntry {
+            length: header.length,
+            link_id: header.link_id,
+            readings,
+            rank,
+            source,
+            metadata,
+            entry_size: READINGS_ENTRY_HEADER_SIZE + header.length as u64,
+        })
+    }
+
+    /// Decode every block of the sidecar.
+    pub fn verify(&mut self) -> Result<()> {
+        self.storage.verify()
+    }
+
+    fn ensure_in_bounds(&self, offset: u64) -> Result<()> {
+        if offset >= self.len() {
+            return Err(MDictError::InvalidArgument(format!(
+                "readings offset {} out of bounds for size {}",
+                offset,
+                self.len()
+            )));
+        }
+        Ok(())
+    }
 }
@@ -1,92 +1,365 @@
 use std::{
     collections::{HashMap, HashSet},
     io::{Read, Seek, SeekFrom, Write},
+    sync::{atomic::AtomicBool, Arc},
 };
 
+use crate::block_cache::CacheCapacity;
+use crate::dictionary::Dictionary;
 use crate::error::{MDictError, Result};
-use crate::packed_storage::{CompressionEncoding, PackedStorageIndex, PackedStorageWriter};
+use crate::mdx_conversion::check_cancelled;
+use crate::mdx_conversion::transform::{self, RecordTransform};
+use crate::packed_storage::{
+    CompressionEncoding, DecodedBlock, PackedStorageReader, PackedStorageWriter,
+};
+use crate::types::KeyBlock;
 use crate::Mdict;
 
-const RECORDS_ZSTD_LEVEL: u8 = 10;
-const TARGET_UNCOMPRESSED_BLOCK_SIZE: usize = 64 * 1024;
-
-#[derive(Debug, Clone)]
-pub struct RecordSection {
-    storage_index: PackedStorageIndex,
+/// The compacted record sidecar, a packed storage container addressed by the
+/// record offsets stored in the readings entries.
+pub struct RecordSection<R> {
+    storage: PackedStorageReader<R>,
 }
 
-impl RecordSection {
-    pub fn parse<R: Read + Seek>(reader: &mut R) -> Result<RecordSection> {
+impl<R: Read + Seek> RecordSection<R> {
+    pub fn parse(mut reader: R, cache_capacity: impl Into<CacheCapacity>) -> Result<Self> {
         reader.seek(SeekFrom::Start(0))?;
-        let storage_index = PackedStorageIndex::parse_from_reader(reader)?;
-        Ok(RecordSection { storage_index })
+        Ok(RecordSection {
+            storage: PackedStorageReader::new(reader, cache_capacity)?,
+        })
+    }
+
+    pub fn get_ref(&self) -> &R {
+        self.storage.get_ref()
     }
 
-    pub fn decode_record<R: Read + Seek>(
-        &self,
-        reader: &mut R,
-        link: u64,
-        record_size: Option<u64>,
-    ) -> Result<Vec<u8>> {
+    pub fn decode_record(&mut self, link: u64, record_size: Option<u64>) -> Result<Vec<u8>> {
         let terminator = if record_size.is_none() {
             Some(&[0x0A, 0x00][..])
         } else {
             None
         };
 
-        self.storage_index.read_from_offset_with_options(
-            reader,
-            link,
-            terminator,
-            record_size,
-        )
-    }
-
-    pub fn rebuild_compacted_zstd_from_mdict<R: Read + Seek, W: Write + Seek>(
-        mdict: &mut Mdict<R>,
-        readings_list: &HashMap<u64, HashSet<String>>,
-        ordered_old_links: &[u64],
-        writer: &mut W,
-    ) -> Result<HashMap<u64, u64>> {
-        let total_entries = mdict.key_block_index.key_section.num_entries as usize;
-        let mut key_id_to_index = HashMap::with_capacity(total_entries);
-
-        for index in 0..total_entries {
-            let Some(key_block) = mdict.key_block_index.get(&mut mdict.reader, index)? else {
-                break;
+        self.storage
+            .read_from_offset_with_options(link, terminator, record_size)
+    }
+
+    /// [`Self::decode_record`] for several `(link, record_size)` extents at
+    /// once, in the order given. Records are read in link order so each
+    /// block is decoded once even without a block cache.
+    pub fn decode_records(&mut self, extents: &[(u64, Option<u64>)]) -> Result<Vec<Vec<u8>>> {
+        let mut order = (0..extents.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| extents[i].0);
+
+        let mut records = vec![Vec::new(); extents.len()];
+        let mut block: Option<Arc<DecodedBlock>> = None;
+        for i in order {
+            let (link, record_size) = extents[i];
+            let in_block = |block: &DecodedBlock| {
+                (block.uncompressed_start as u64..block.uncompressed_end as u64).contains(&link)
+            };
+            if !block.as_deref().is_some_and(in_block) {
+                block = self.storage.block_at_offset(link)?;
+            }
+            records[i] = match (block.as_deref(), record_size) {
+                (Some(block), Some(size))
+                    if link + size <= block.uncompressed_end as u64 =>
+                {
+                    let start = (link - block.uncompressed_start as u64) as usize;
+                    block.bytes[start..start + size as usize].to_vec()
+                }
+                _ => self.decode_record(link, record_size)?,
             };
-            key_id_to_index.insert(key_block.key_id, index);
         }
+        Ok(records)
+    }
 
-        let mut seen = HashSet::new();
-        let mut storage_writer = PackedStorageWriter::new(
-            CompressionEncoding::Zstd,
-            RECORDS_ZSTD_LEVEL,
-            TARGET_UNCOMPRESSED_BLOCK_SIZE,
-        )?;
-        let mut link_remap = HashMap::new();
-
-        for &old_link in ordered_old_links {
-            if !readings_list.contains_key(&old_link) || !seen.insert(old_link) {
-                continue;
-            }
+    /// Decode every block of the sidecar.
+    pub fn verify(&mut self) -> Result<()> {
+        self.storage.verify()
+    }
+}
+
+pub fn rebuild_compacted_zstd_from_mdict<R: Read + Seek, W: Write + Seek>(
+    mdict: &mut Mdict<R>,
+    readings_list: &HashMap<u64, HashSet<String>>,
+    ordered_old_links: &[u64],
+    writer: &mut W,
+) -> Result<HashMap<u64, u64>> {
+    rebuild_compacted_zstd_with_cancel(
+        mdict,
+        readings_list,
+        ordered_old_links,
+        writer,
+        &AtomicBool::new(false),
+    )
+}
+
+/// [`rebuild_compacted_zstd_from_mdict`], stopping between records once
+/// `cancel` is set.
+pub fn rebuild_compacted_zstd_with_cancel<R: Read + Seek, W: Write + Seek>(
+    mdict: &mut Mdict<R>,
+    readings_list: &HashMap<u64, HashSet<String>>,
+    ordered_old_links: &[u64],
+    writer: &mut W,
+    cancel: &AtomicBool,
+) -> Result<HashMap<u64, u64>> {
+    rebuild_compacted_zstd_with_transform(
+        mdict,
+        readings_list,
+        ordered_old_links,
+        writer,
+        None,
+        cancel,
+    )
+}
+
+/// [`rebuild_compacted_zstd_with_cancel`], passing every record except
+/// redirects through `transform` before it is stored.
+pub fn rebuild_compacted_zstd_with_transform<R: Read + Seek, W: Write + Seek>(
+    mdict: &mut Mdict<R>,
+    readings_list: &HashMap<u64, HashSet<String>>,
+    ordered_old_links: &[u64],
+    writer: &mut W,
+    transform: Option<&dyn RecordTransform>,
+    cancel: &AtomicBool,
+) -> Result<HashMap<u64, u64>> {
+    let referenced = referenced_records(mdict, readings_list, ordered_old_links)?;
+
+    let config = crate::config::config();
+    let mut storage_writer = record_storage_writer()?;
+    let samples = sample_records(mdict, &referenced, DICTIONARY_SAMPLE_COUNT)?
+        .into_iter()
+        .map(|record| transform::apply(transform, record))
+        .collect::<Vec<_>>();
+    if let Some(dictionary) = train_record_dictionary(&samples, config.zstd_dictionary_size()) {
+        storage_writer = storage_writer.with_zstd_dictionary(dictionary)?;
+    }
+    let link_remap = push_records(mdict, &referenced, &mut storage_writer, transform, cancel)?;
+
+    if link_remap.is_empty() {
+        return Err(MDictError::InvalidArgument(
+            "no referenced records found for compaction".to_string(),
+        ));
+    }
+
+    storage_writer.finish_to_writer(writer)?;
+    Ok(link_remap)
+}
 
-            let index = *key_id_to_index.get(&old_link).ok_or_else(|| {
-                MDictError::InvalidArgument(format!("missing key index for link {}", old_link))
-            })?;
+/// [`rebuild_compacted_zstd_with_cancel`] for several dictionaries sharing
+/// one record sidecar and zstd dictionary. `readings_lists[i]` and
+/// `ordered_old_links[i]` belong to `mdicts[i]`; the link remap of each is
+/// returned in the same order.
+pub fn rebuild_compacted_zstd_multi<R: Read + Seek, W: Write + Seek>(
+    mdicts: &mut [Mdict<R>],
+    readings_lists: &[HashMap<u64, HashSet<String>>],
+    ordered_old_links: &[Vec<u64>],
+    writer: &mut W,
+    cancel: &AtomicBool,
+) -> Result<Vec<HashMap<u64, u64>>> {
+    if mdicts.len() != readings_lists.len() || mdicts.len() != ordered_old_links.len() {
+        return Err(MDictError::InvalidArgument(
+            "expected one readings list and link order per dictionary".to_string(),
+        ));
+    }
+
+    let mut referenced = Vec::with_capacity(mdicts.len());
+    for ((mdict, readings_list), links) in mdicts
+        .iter_mut()
+        .zip(readings_lists)
+        .zip(ordered_old_links)
+    {
+        referenced.push(referenced_records(mdict, readings_list, links)?);
+    }
+
+    let config = crate::config::config();
+    let mut storage_writer = record_storage_writer()?;
+    let per_source = DICTIONARY_SAMPLE_COUNT.div_ceil(mdicts.len().max(1));
+    let mut samples = Vec::new();
+    for (mdict, referenced) in mdicts.iter_mut().zip(&referenced) {
+        samples.extend(sample_records(mdict, referenced, per_source)?);
+    }
+    if let Some(dictionary) = train_record_dictionary(&samples, config.zstd_dictionary_size()) {
+        storage_writer = storage_writer.with_zstd_dictionary(dictionary)?;
+    }
+
+    let mut link_remaps = Vec::with_capacity(mdicts.len());
+    for (mdict, referenced) in mdicts.iter_mut().zip(&referenced) {
+        link_remaps.push(push_records(
+            mdict,
+            referenced,
+            &mut storage_writer,
+            None,
+            cancel,
+        )?);
+    }
 
-            let record = mdict.record_at_index(index)?;
-            let new_link = storage_writer.push_entry(&record)?;
-            link_remap.insert(old_link, new_link);
+    if link_remaps.iter().all(HashMap::is_empty) {
+        return Err(MDictError::InvalidArgument(
+            "no referenced records found for compaction".to_string(),
+        ));
+    }
+
+    storage_writer.finish_to_writer(writer)?;
+    Ok(link_remaps)
+}
+
+/// The records of any [`Dictionary`] in the container
+/// [`rebuild_compacted_zstd_from_mdict`] writes. Each entry of
+/// `ordered_keys` is stored once per `key_id`, as its
+/// [`Dictionary::record_text`] in UTF-8. Returns where each `key_id` landed.
+pub fn rebuild_compacted_zstd_from_dictionary<W: Write + Seek>(
+    dictionary: &dyn Dictionary,
+    ordered_keys: &[KeyBlock],
+    writer: &mut W,
+) -> Result<HashMap<u64, u64>> {
+    let config = crate::config::config();
+    let mut storage_writer = record_storage_writer()?;
+    let step = ordered_keys.len().div_ceil(DICTIONARY_SAMPLE_COUNT).max(1);
+    let samples = ordered_keys
+        .iter()
+        .step_by(step)
+        .map(|key| Ok(dictionary.record_text(key)?.into_bytes()))
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(zstd_dictionary) =
+        train_record_dictionary(&samples, config.zstd_dictionary_size())
+    {
+        storage_writer = storage_writer.with_zstd_dictionary(zstd_dictionary)?;
+    }
+
+    let mut link_remap = HashMap::with_capacity(ordered_keys.len());
+    for key in ordered_keys {
+        if link_remap.contains_key(&key.key_id) {
+            continue;
         }
+        let record = dictionary.record_text(key)?;
+        link_remap.insert(key.key_id, storage_writer.push_entry(record.as_bytes())?);
+    }
+
+    if link_remap.is_empty() {
+        return Err(MDictError::InvalidArgument(
+            "no records found for compaction".to_string(),
+        ));
+    }
+    storage_writer.finish_to_writer(writer)?;
+    Ok(link_remap)
+}
+
+fn record_storage_writer() -> Result<PackedStorageWriter> {
+    let config = crate::config::config();
+    PackedStorageWriter::new(
+        CompressionEncoding::Zstd,
+        config.record_compression_level,
+        config.packed_block_size(),
+    )
+}
+
+/// `(link, entry index)` of each link in `ordered_old_links` that has
+/// readings, once per link.
+pub(crate) fn referenced_records<R: Read + Seek>(
+    mdict: &mut Mdict<R>,
+    readings_list: &HashMap<u64, HashSet<String>>,
+    ordered_old_links: &[u64],
+) -> Result<Vec<(u64, usize)>> {
+    let total_entries = mdict.key_block_index.key_section.num_entries as usize;
+    let mut key_id_to_index = HashMap::with_capacity(total_entries);
+
+    for index in 0..total_entries {
+        let Some(key_block) = mdict.key_block_index.get(&mut mdict.reader, index)? else {
+            break;
+        };
+        key_id_to_index.insert(key_block.key_id, index);
+    }
 
-        if link_remap.is_empty() {
-            return Err(MDictError::InvalidArgument(
-                "no referenced records found for compaction".to_string(),
-            ));
+    let mut seen = HashSet::new();
+    let mut referenced = Vec::new();
+    for &old_link in ordered_old_links {
+        if !readings_list.contains_key(&old_link) || !seen.insert(old_link) {
+            continue;
         }
 
-        storage_writer.finish_to_writer(writer)?;
-        Ok(link_remap)
+        let index = *key_id_to_index.get(&old_link).ok_or_else(|| {
+            MDictError::InvalidArgument(format!("missing key index for link {}", old_link))
+        })?;
+        referenced.push((old_link, index));
+    }
+    Ok(referenced)
+}
+
+/// Copy the records at `referenced` into `storage_writer`, returning where
+/// each link landed. Record blocks are decoded in parallel batches; records
+/// are still pushed in order.
+fn push_records<R: Read + Seek>(
+    mdict: &mut Mdict<R>,
+    referenced: &[(u64, usize)],
+    storage_writer: &mut PackedStorageWriter,
+    transform: Option<&dyn RecordTransform>,
+    cancel: &AtomicBool,
+) -> Result<HashMap<u64, u64>> {
+    let indices = referenced
+        .iter()
+        .map(|&(_, index)| index)
+        .collect::<Vec<_>>();
+    let mut link_remap = HashMap::with_capacity(referenced.len());
+    mdict.for_each_record_at(&indices, |i, record| {
+        check_cancelled(cancel)?;
+        let record = transform::apply(transform, record);
+        let new_link = storage_writer.push_entry(&record)?;
+        link_remap.insert(referenced[i].0, new_link);
+        Ok(())
+    })?;
+    Ok(link_remap)
+}
+
+/// Records sampled for dictionary training, spread evenly over the file.
+const DICTIONARY_SAMPLE_COUNT: usize = 4096;
+/// zstd wants roughly this many sample bytes per dictionary byte.
+const DICTIONARY_SAMPLE_RATIO: usize = 10;
+const MIN_DICTIONARY_SIZE: usize = 1024;
+
+/// Up to about `count` of the records at `referenced`, spread evenly.
+fn sample_records<R: Read + Seek>(
+    mdict: &mut Mdict<R>,
+    referenced: &[(u64, usize)],
+    count: usize,
+) -> Result<Vec<Vec<u8>>> {
+    if referenced.is_empty() || count == 0 {
+        return Ok(Vec::new());
+    }
+    let step = referenced.len().div_ceil(count);
+    let indices = referenced
+        .iter()
+        .step_by(step)
+        .map(|&(_, index)| index)
+        .collect::<Vec<_>>();
+    let mut samples = Vec::with_capacity(indices.len());
+    mdict.for_each_record_at(&indices, |_, record| {
+        samples.push(record);
+        Ok(())
+    })?;
+    Ok(samples)
+}
+
+/// Train a zstd dictionary over `samples`. Returns `None` when disabled,
+/// when there is too little data for a useful dictionary, or when training
+/// fails.
+fn train_record_dictionary(samples: &[Vec<u8>], max_size: usize) -> Option<Vec<u8>> {
+    if max_size == 0 || samples.is_empty() {
+        return None;
+    }
+
+    let sample_bytes: usize = samples.iter().map(Vec::len).sum();
+    let size = max_size.min(sample_bytes / DICTIONARY_SAMPLE_RATIO);
+    if size < MIN_DICTIONARY_SIZE {
+        return None;
+    }
+
+    match PackedStorageWriter::train_zstd_dictionary(samples, size) {
+        Ok(dictionary) => Some(dictionary),
+        Err(e) => {
+            log::warn!("Skipping zstd dictionary: {}", e);
+            None
+        }
     }
 }
@@ -1,50 +1,50 @@
-use std::collections::{HashMap, HashSet};
+#[cfg(feature = "mmap")]
+use std::cmp::Reverse;
+#[cfg(feature = "mmap")]
+use std::collections::BinaryHeap;
+use std::collections::{BTreeSet, HashMap, HashSet};
+#[cfg(feature = "fs")]
 use std::fs::File;
-use std::io::{Read, Seek, Write};
+#[cfg(feature = "mmap")]
+use std::io::BufRead;
+#[cfg(feature = "fs")]
+use std::io::Write;
+use std::io::{Read, Seek};
+#[cfg(feature = "fs")]
 use std::path::Path;
 use std::sync::Arc;
 
+use fst::automaton::Levenshtein;
+#[cfg(feature = "mmap")]
+use fst::MapBuilder;
+use fst::{IntoStreamer, Map, Streamer};
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
+#[cfg(feature = "threads")]
 use rayon::prelude::*;
 
-use crate::error::Result;
-use crate::mdict::Mdict;
+use crate::error::{MDictError, Result};
+use crate::links::extract_link;
+use crate::mdict::{bounded_edit_distance, Mdict};
+use crate::mdx_conversion::normalize::{KeyNormalizer, NormalizerPipeline};
+#[cfg(feature = "mmap")]
+use crate::mdx_conversion::spill::{at_end, read_str, read_u64, write_str, write_u64, SpillFile};
+use crate::types::{BrokenLink, BrokenLinkPolicy};
 
 pub type ReadingsSet = HashSet<String>;
 pub type ReadingsListMap = HashMap<u64, ReadingsSet>;
 pub type LinkToKeyIdMap = HashMap<String, u64>;
 
-const LINK_PREFIX: &str = "@@@LINK=";
 const PROGRESS_LOG_EVERY: usize = 100_000;
+/// Rough per-key overhead of a key run entry beyond the key text itself.
+#[cfg(feature = "mmap")]
+const KEY_RUN_ENTRY_OVERHEAD: usize = 48;
+/// Key runs merged at once; more runs are merged in several rounds.
+#[cfg(feature = "mmap")]
+const MAX_MERGE_FAN_IN: usize = 64;
 
 type ReadingsEntry = (u64, String, Option<String>);
 
-fn extract_link(str: &str) -> Option<&str> {
-    let remainder = str.strip_prefix(LINK_PREFIX)?;
-    let end = remainder
-        .find(|c: char| c.is_whitespace())
-        .unwrap_or(remainder.len());
-    if end == 0 {
-        return None;
-    }
-    Some(&remainder[..end])
-}
-
-fn readings_for_key_text(key_text: &str) -> (String, Option<String>) {
-    if let Some(left) = key_text.find('【') {
-        if let Some(rel_right) = key_text[left + '【'.len_utf8()..].find('】') {
-            let right = left + '【'.len_utf8() + rel_right;
-            let before = key_text[..left].to_string();
-            let inside = key_text[left + '【'.len_utf8()..right].to_string();
-            if before == inside {
-                return (before, None);
-            }
-            return (before, Some(inside));
-        }
-    }
-
-    (key_text.to_string(), None)
-}
-
 fn key_id_for_link<R: Read + Seek>(
     mdict: &mut Mdict<R>,
     cached_link_to_key_id: &mut LinkToKeyIdMap,
@@ -54,7 +54,11 @@ fn key_id_for_link<R: Read + Seek>(
         return Ok(key_id);
     }
 
-    let key_id = mdict.search_keys_prefix(link)?.get(0)?.unwrap().key_id;
+    let key_id = mdict
+        .search_keys_prefix(link)?
+        .get(0)?
+        .ok_or_else(|| MDictError::KeyNotFound(format!("no key for link '{}'", link)))?
+        .key_id;
     cached_link_to_key_id.insert(link.to_string(), key_id);
     Ok(key_id)
 }
@@ -63,12 +67,8 @@ fn collect_readings_entries<R: Read + Seek>(mdict: &mut Mdict<R>) -> Result<Vec<
     let total_entries = mdict.key_block_index.key_section.num_entries as usize;
     let mut entries = Vec::with_capacity(total_entries);
 
-    for i in 0..total_entries {
-        let Some(key_block) = mdict.key_block_index.get(&mut mdict.reader, i)? else {
-            break;
-        };
-
-        let record = mdict.record_at_index(i)?;
+    for (i, entry) in mdict.iter_entries().parallel(true).enumerate() {
+        let (key_block, record) = entry?;
         let link = {
             let record_as_string = String::from_utf8_lossy(&record);
             extract_link(&record_as_string).map(str::to_string)
@@ -77,7 +77,7 @@ fn collect_readings_entries<R: Read + Seek>(mdict: &mut Mdict<R>) -> Result<Vec<
         entries.push((key_block.key_id, key_block.key_text, link));
 
         if i % PROGRESS_LOG_EVERY == 0 {
-            println!("Processed {} key blocks...", i);
+            log::info!("Processed {} key blocks...", i);
         }
     }
 
@@ -96,7 +96,8 @@ fn resolve_missing_links<R: Read + Seek>(
     cached_link_to_key_id: &mut LinkToKeyIdMap,
     entries: &[ReadingsEntry],
 ) -> LinkToKeyIdMap {
-    let missing_links: HashSet<String> = entries
+    // Ordered so link resolution (and its cache) is filled in the same order every build.
+    let missing_links: BTreeSet<String> = entries
         .iter()
         .filter_map(|(_key_id, _key_text, link)| link.as_ref())
         .filter(|link| !cached_link_to_key_id.contains_key(link.as_str()))
@@ -113,37 +114,47 @@ fn resolve_missing_links<R: Read + Seek>(
     resolved_missing_links
 }
 
+fn add_readings(
+    readings_list: &mut ReadingsListMap,
+    target_key_id: u64,
+    key_text: &str,
+    normalizer: &dyn KeyNormalizer,
+) {
+    readings_list
+        .entry(target_key_id)
+        .or_default()
+        .extend(normalizer.readings(key_text));
+}
+
+/// The key id whose readings the entry `key_id` with redirect `link` adds to.
+fn target_key_id(
+    cached_lookup: &LinkToKeyIdMap,
+    missing_lookup: &LinkToKeyIdMap,
+    key_id: u64,
+    link: Option<&str>,
+) -> u64 {
+    link.and_then(|link_text| {
+        cached_lookup
+            .get(link_text)
+            .copied()
+            .or_else(|| missing_lookup.get(link_text).copied())
+    })
+    .unwrap_or(key_id)
+}
+
+#[cfg(feature = "threads")]
 fn aggregate_readings_parallel(
     entries: Vec<ReadingsEntry>,
     cached_lookup: Arc<LinkToKeyIdMap>,
     missing_lookup: Arc<LinkToKeyIdMap>,
+    normalizer: &dyn KeyNormalizer,
 ) -> ReadingsListMap {
     entries
         .into_par_iter()
         .fold(HashMap::new, |mut local_map, (key_id, key_text, link)| {
-            let (first_reading, second_reading) = readings_for_key_text(&key_text);
-            let cached_key_id = link
-                .as_deref()
-                .and_then(|link_text| {
-                    cached_lookup
-                        .get(link_text)
-                        .copied()
-                        .or_else(|| missing_lookup.get(link_text).copied())
-                })
-                .unwrap_or(key_id);
-
-            local_map
-                .entry(cached_key_id)
-                .or_insert_with(HashSet::new)
-                .insert(first_reading);
-
-            if let Some(second) = second_reading {
-                local_map
-                    .entry(cached_key_id)
-                    .or_insert_with(HashSet::new)
-                    .insert(second);
-            }
-
+            let cached_key_id =
+                target_key_id(&cached_lookup, &missing_lookup, key_id, link.as_deref());
+            add_readings(&mut local_map, cached_key_id, &key_text, normalizer);
             local_map
         })
         .reduce(HashMap::new, |mut acc, local_map| {
@@ -154,41 +165,410 @@ fn aggregate_readings_parallel(
         })
 }
 
+#[cfg(not(feature = "threads"))]
+fn aggregate_readings_parallel(
+    entries: Vec<ReadingsEntry>,
+    cached_lookup: Arc<LinkToKeyIdMap>,
+    missing_lookup: Arc<LinkToKeyIdMap>,
+    normalizer: &dyn KeyNormalizer,
+) -> ReadingsListMap {
+    let mut readings_list = ReadingsListMap::new();
+    for (key_id, key_text, link) in entries {
+        let cached_key_id = target_key_id(&cached_lookup, &missing_lookup, key_id, link.as_deref());
+        add_readings(&mut readings_list, cached_key_id, &key_text, normalizer);
+    }
+    readings_list
+}
+
+#[cfg(feature = "fs")]
 pub fn build_readings_list_from_path<P: AsRef<Path>>(path: P) -> Result<ReadingsListMap> {
     let file = File::open(path)?;
-    let mut mdict = Mdict::new_with_cache(file, usize::MAX)?;
+    let mut mdict =
+        Mdict::new_with_cache(file, crate::config::config().build_record_block_cache_size())?;
     build_readings_list(&mut mdict)
 }
 
+/// Readings of every entry, split with the default [`NormalizerPipeline`].
 pub fn build_readings_list<R: Read + Seek>(mdict: &mut Mdict<R>) -> Result<ReadingsListMap> {
+    build_readings_list_normalized(mdict, &NormalizerPipeline::default())
+}
+
+/// [`build_readings_list`] with the readings of each key given by `normalizer`.
+pub fn build_readings_list_normalized<R: Read + Seek>(
+    mdict: &mut Mdict<R>,
+    normalizer: &dyn KeyNormalizer,
+) -> Result<ReadingsListMap> {
+    build_readings_list_checked(mdict, normalizer, BrokenLinkPolicy::Lenient)
+        .map(|(readings_list, _)| readings_list)
+}
+
+/// [`build_readings_list_normalized`] that handles redirects whose target is
+/// not a key as `policy` says, and returns them in key order with the
+/// readings.
+pub fn build_readings_list_checked<R: Read + Seek>(
+    mdict: &mut Mdict<R>,
+    normalizer: &dyn KeyNormalizer,
+    policy: BrokenLinkPolicy,
+) -> Result<(ReadingsListMap, Vec<BrokenLink>)> {
     let entries = collect_readings_entries(mdict)?;
 
     let mut cached_link_to_key_id = refresh_direct_link_cache(&entries);
 
-    let resolved_missing_links = resolve_missing_links(mdict, &mut cached_link_to_key_id, &entries);
+    let mut resolved_missing_links =
+        resolve_missing_links(mdict, &mut cached_link_to_key_id, &entries);
+
+    let broken = entries
+        .iter()
+        .filter_map(|(_key_id, key_text, link)| Some((key_text, link.as_ref()?)))
+        .filter(|(_key_text, link)| {
+            !cached_link_to_key_id.contains_key(link.as_str())
+                && !resolved_missing_links.contains_key(link.as_str())
+        })
+        .collect::<Vec<_>>();
+    let mut check = BrokenLinkCheck::new(policy);
+    if policy != BrokenLinkPolicy::Lenient && !broken.is_empty() {
+        let keys = match policy {
+            BrokenLinkPolicy::Repair { .. } => Some(key_map(&cached_link_to_key_id)?),
+            _ => None,
+        };
+        for (key_text, link) in broken {
+            if let Some(key_id) = check.broken(keys.as_ref(), key_text, link) {
+                resolved_missing_links.insert(link.clone(), key_id);
+            }
+        }
+    }
 
     let cached_lookup = Arc::new(cached_link_to_key_id);
     let missing_lookup = Arc::new(resolved_missing_links);
 
-    Ok(aggregate_readings_parallel(entries, cached_lookup, missing_lookup))
+    let readings_list =
+        aggregate_readings_parallel(entries, cached_lookup, missing_lookup, normalizer);
+    Ok((readings_list, check.links))
+}
+
+/// [`build_readings_list`] for dictionaries too large to hold every entry in
+/// memory. Entries are spilled to a temp file and the key text -> key id map
+/// is merged from sorted runs of about `memory_budget / 2` bytes into a
+/// temporary FST, so only the resulting readings list stays in memory. The
+/// result is the same as [`build_readings_list`].
+#[cfg(feature = "mmap")]
+pub fn build_readings_list_with_budget<R: Read + Seek>(
+    mdict: &mut Mdict<R>,
+    memory_budget: usize,
+) -> Result<ReadingsListMap> {
+    build_readings_list_with_budget_normalized(mdict, memory_budget, &NormalizerPipeline::default())
+}
+
+/// [`build_readings_list_with_budget`] with the readings of each key given
+/// by `normalizer`.
+#[cfg(feature = "mmap")]
+pub fn build_readings_list_with_budget_normalized<R: Read + Seek>(
+    mdict: &mut Mdict<R>,
+    memory_budget: usize,
+    normalizer: &dyn KeyNormalizer,
+) -> Result<ReadingsListMap> {
+    build_readings_list_with_budget_checked(
+        mdict,
+        memory_budget,
+        normalizer,
+        BrokenLinkPolicy::Lenient,
+    )
+    .map(|(readings_list, _)| readings_list)
 }
 
+/// [`build_readings_list_checked`] within `memory_budget`, as
+/// [`build_readings_list_with_budget`] does.
+#[cfg(feature = "mmap")]
+pub fn build_readings_list_with_budget_checked<R: Read + Seek>(
+    mdict: &mut Mdict<R>,
+    memory_budget: usize,
+    normalizer: &dyn KeyNormalizer,
+    policy: BrokenLinkPolicy,
+) -> Result<(ReadingsListMap, Vec<BrokenLink>)> {
+    let temp_dir = crate::config::config().temp_dir();
+    let run_budget = (memory_budget / 2).max(1);
+
+    let (entries, mut entry_writer) = SpillFile::create(&temp_dir, "entries")?;
+    let mut runs = Vec::new();
+    let mut run = Vec::new();
+    let mut run_bytes = 0;
+    for (i, entry) in mdict.iter_entries().parallel(true).enumerate() {
+        let (key_block, record) = entry?;
+        let link = {
+            let record_as_string = String::from_utf8_lossy(&record);
+            extract_link(&record_as_string).map(str::to_string)
+        };
+
+        write_u64(&mut entry_writer, key_block.key_id)?;
+        write_str(&mut entry_writer, &key_block.key_text)?;
+        write_str(&mut entry_writer, link.as_deref().unwrap_or(""))?;
+
+        run_bytes += key_block.key_text.len() + KEY_RUN_ENTRY_OVERHEAD;
+        run.push(KeyRunEntry {
+            key_text: key_block.key_text,
+            sequence: i as u64,
+            key_id: key_block.key_id,
+        });
+        if run_bytes >= run_budget {
+            runs.push(write_key_run(&temp_dir, &mut run)?);
+            run_bytes = 0;
+        }
+
+        if i % PROGRESS_LOG_EVERY == 0 {
+            log::info!("Processed {} key blocks...", i);
+        }
+    }
+    entry_writer.flush()?;
+    drop(entry_writer);
+    if !run.is_empty() {
+        runs.push(write_key_run(&temp_dir, &mut run)?);
+    }
+
+    let (_key_map_file, key_ids) = merge_key_runs_into_map(&temp_dir, runs)?;
+
+    let mut resolved_missing_links = HashMap::<String, Option<u64>>::new();
+    let mut missing_cache = LinkToKeyIdMap::new();
+    let mut check = BrokenLinkCheck::new(policy);
+    let mut readings_list = ReadingsListMap::new();
+    let mut reader = entries.reader()?;
+    while !at_end(&mut reader)? {
+        let key_id = read_u64(&mut reader)?;
+        let key_text = read_str(&mut reader)?;
+        let link = read_str(&mut reader)?;
+
+        let target_key_id = if link.is_empty() {
+            key_id
+        } else if let Some(target) = key_ids.get(&link) {
+            target
+        } else {
+            let resolved = match resolved_missing_links.get(&link) {
+                Some(&resolved) => resolved,
+                None => {
+                    let resolved = key_id_for_link(mdict, &mut missing_cache, &link).ok();
+                    resolved_missing_links.insert(link.clone(), resolved);
+                    resolved
+                }
+            };
+            resolved
+                .or_else(|| check.broken(Some(&key_ids), &key_text, &link))
+                .unwrap_or(key_id)
+        };
+        add_readings(&mut readings_list, target_key_id, &key_text, normalizer);
+    }
+
+    Ok((readings_list, check.links))
+}
+
+/// Collects the redirects no lookup resolved and, under
+/// [`BrokenLinkPolicy::Repair`], retargets them.
+struct BrokenLinkCheck {
+    policy: BrokenLinkPolicy,
+    /// Target -> the key text and key id it was retargeted to.
+    repairs: HashMap<String, Option<(String, u64)>>,
+    links: Vec<BrokenLink>,
+}
+
+impl BrokenLinkCheck {
+    fn new(policy: BrokenLinkPolicy) -> Self {
+        Self {
+            policy,
+            repairs: HashMap::new(),
+            links: Vec::new(),
+        }
+    }
+
+    /// Note that `key_text` redirects to `target`, which is not a key, and
+    /// return the key id of the key it was retargeted to, if any. `keys`
+    /// maps key text to key id and is only needed for repairs.
+    fn broken<D: AsRef<[u8]>>(
+        &mut self,
+        keys: Option<&Map<D>>,
+        key_text: &str,
+        target: &str,
+    ) -> Option<u64> {
+        let repair = match self.policy {
+            BrokenLinkPolicy::Lenient => return None,
+            BrokenLinkPolicy::Strict => None,
+            BrokenLinkPolicy::Repair { max_distance } => self
+                .repairs
+                .entry(target.to_string())
+                .or_insert_with(|| closest_key(keys?, target, max_distance))
+                .clone(),
+        };
+        self.links.push(BrokenLink {
+            key_text: key_text.to_string(),
+            target: target.to_string(),
+            repaired_to: repair.as_ref().map(|(key, _)| key.clone()),
+        });
+        repair.map(|(_, key_id)| key_id)
+    }
+}
+
+/// The key of `keys` closest to `target` within `max_distance` edits, the
+/// first in key order among equally close ones, with its key id.
+fn closest_key<D: AsRef<[u8]>>(
+    keys: &Map<D>,
+    target: &str,
+    max_distance: u32,
+) -> Option<(String, u64)> {
+    let automaton = match Levenshtein::new(target, max_distance) {
+        Ok(automaton) => automaton,
+        Err(e) => {
+            log::warn!("Not repairing link to '{}': {}", target, e);
+            return None;
+        }
+    };
+    let query = target.chars().collect::<Vec<_>>();
+    let mut closest: Option<(usize, String, u64)> = None;
+    let mut stream = keys.search(automaton).into_stream();
+    while let Some((key, key_id)) = stream.next() {
+        let key = String::from_utf8_lossy(key);
+        let Some(distance) = bounded_edit_distance(&query, &key, max_distance as usize) else {
+            continue;
+        };
+        let closer = match &closest {
+            Some((best, _, _)) => distance < *best,
+            None => true,
+        };
+        if closer {
+            closest = Some((distance, key.into_owned(), key_id));
+        }
+    }
+    closest.map(|(_, key, key_id)| (key, key_id))
+}
+
+/// An in-memory FST of the key text -> key id pairs in `key_ids`.
+fn key_map(key_ids: &LinkToKeyIdMap) -> Result<Map<Vec<u8>>> {
+    let mut pairs = key_ids.iter().collect::<Vec<_>>();
+    pairs.sort_unstable();
+    Ok(Map::from_iter(
+        pairs.into_iter().map(|(key, &key_id)| (key, key_id)),
+    )?)
+}
+
+/// A key text -> key id pair; `sequence` is the entry index, so the last of
+/// several entries with the same key text wins, as in [`build_readings_list`].
+#[cfg(feature = "mmap")]
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct KeyRunEntry {
+    key_text: String,
+    sequence: u64,
+    key_id: u64,
+}
+
+#[cfg(feature = "mmap")]
+fn write_key_run(temp_dir: &Path, run: &mut Vec<KeyRunEntry>) -> Result<SpillFile> {
+    run.sort_unstable();
+    let (file, mut writer) = SpillFile::create(temp_dir, "keys")?;
+    for entry in run.drain(..) {
+        write_key_run_entry(&mut writer, &entry)?;
+    }
+    writer.flush()?;
+    Ok(file)
+}
+
+#[cfg(feature = "mmap")]
+fn write_key_run_entry<W: Write>(writer: &mut W, entry: &KeyRunEntry) -> Result<()> {
+    write_str(writer, &entry.key_text)?;
+    write_u64(writer, entry.sequence)?;
+    write_u64(writer, entry.key_id)
+}
+
+#[cfg(feature = "mmap")]
+fn read_key_run_entry<R: BufRead>(reader: &mut R) -> Result<Option<KeyRunEntry>> {
+    if at_end(reader)? {
+        return Ok(None);
+    }
+    Ok(Some(KeyRunEntry {
+        key_text: read_str(reader)?,
+        sequence: read_u64(reader)?,
+        key_id: read_u64(reader)?,
+    }))
+}
+
+/// Call `emit` with the entries of every run in sorted order.
+#[cfg(feature = "mmap")]
+fn merge_key_runs(
+    runs: &[SpillFile],
+    mut emit: impl FnMut(KeyRunEntry) -> Result<()>,
+) -> Result<()> {
+    let mut readers = runs
+        .iter()
+        .map(SpillFile::reader)
+        .collect::<Result<Vec<_>>>()?;
+    let mut heap = BinaryHeap::with_capacity(readers.len());
+    for (run, reader) in readers.iter_mut().enumerate() {
+        if let Some(entry) = read_key_run_entry(reader)? {
+            heap.push(Reverse((entry, run)));
+        }
+    }
+    while let Some(Reverse((entry, run))) = heap.pop() {
+        if let Some(next) = read_key_run_entry(&mut readers[run])? {
+            heap.push(Reverse((next, run)));
+        }
+        emit(entry)?;
+    }
+    Ok(())
+}
+
+/// Merge sorted key runs into a temporary FST of key text -> key id.
+#[cfg(feature = "mmap")]
+fn merge_key_runs_into_map(
+    temp_dir: &Path,
+    mut runs: Vec<SpillFile>,
+) -> Result<(SpillFile, Map<Mmap>)> {
+    while runs.len() > MAX_MERGE_FAN_IN {
+        let mut merged = Vec::with_capacity(runs.len().div_ceil(MAX_MERGE_FAN_IN));
+        for group in runs.chunks(MAX_MERGE_FAN_IN) {
+            let (file, mut writer) = SpillFile::create(temp_dir, "keys")?;
+            merge_key_runs(group, |entry| write_key_run_entry(&mut writer, &entry))?;
+            writer.flush()?;
+            merged.push(file);
+        }
+        runs = merged;
+    }
+
+    let (map_file, writer) = SpillFile::create(temp_dir, "fst")?;
+    let mut builder = MapBuilder::new(writer)?;
+    let mut pending: Option<KeyRunEntry> = None;
+    merge_key_runs(&runs, |entry| {
+        if let Some(previous) = pending.replace(entry) {
+            if pending.as_ref().map(|e| &e.key_text) != Some(&previous.key_text) {
+                builder.insert(&previous.key_text, previous.key_id)?;
+            }
+        }
+        Ok(())
+    })?;
+    if let Some(last) = pending {
+        builder.insert(&last.key_text, last.key_id)?;
+    }
+    builder.into_inner()?.flush()?;
+    drop(runs);
+
+    // SAFETY: the spill file is private to this build and not modified
+    // while mapped.
+    let mmap = unsafe { Mmap::map(&File::open(map_file.path())?)? };
+    Ok((map_file, Map::new(mmap)?))
+}
+
+#[cfg(feature = "fs")]
 pub fn write_compressed_readings_list<P: AsRef<Path>>(
     readings_list: &ReadingsListMap,
     output_path: P,
 ) -> Result<()> {
     let mut output_file = File::create(output_path.as_ref())?;
-    for (link, readings) in readings_list {
-        writeln!(
-            output_file,
-            "{}: {}",
-            link,
-            readings.iter().cloned().collect::<Vec<_>>().join(", ")
-        )?;
+    let mut links = readings_list.keys().copied().collect::<Vec<_>>();
+    links.sort_unstable();
+
+    for link in links {
+        let mut readings = readings_list[&link].iter().cloned().collect::<Vec<_>>();
+        readings.sort_unstable();
+        writeln!(output_file, "{}: {}", link, readings.join(", "))?;
     }
     Ok(())
 }
 
+#[cfg(feature = "fs")]
 pub fn read_compressed_readings_list<P: AsRef<Path>>(
     input_path: P,
 ) -> Result<ReadingsListMap> {
@@ -204,7 +584,7 @@ pub fn read_compressed_readings_list<P: AsRef<Path>>(
             if let Ok(link_id) = link.parse::<u64>() {
                 readings_list.insert(link_id, readings);
             } else {
-                eprintln!("Warning: Could not parse link ID from line: {}", line);
+                log::warn!("Could not parse link ID from line: {}", line);
             }
         }
     }
@@ -0,0 +1,97 @@
+//! Temporary files for build passes that run under a memory budget.
+//!
+//! Spill files live in [`crate::config::Config::temp_dir`] and are removed
+//! when dropped. Records are written with the little-endian helpers below;
+//! readers check [`at_end`] before each record.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::error::{MDictError, Result};
+
+static NEXT_SPILL_ID: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) struct SpillFile {
+    path: PathBuf,
+}
+
+impl SpillFile {
+    /// Create an empty spill file in `dir`, named after `kind`.
+    pub(crate) fn create(dir: &Path, kind: &str) -> Result<(Self, BufWriter<File>)> {
+        let name = format!(
+            "mdict_tools-{}-{}.{}.spill",
+            std::process::id(),
+            NEXT_SPILL_ID.fetch_add(1, Ordering::Relaxed),
+            kind
+        );
+        let path = dir.join(name);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        Ok((Self { path }, BufWriter::new(file)))
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(crate) fn reader(&self) -> Result<BufReader<File>> {
+        Ok(BufReader::new(File::open(&self.path)?))
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+pub(crate) fn write_u64<W: Write>(writer: &mut W, value: u64) -> Result<()> {
+    writer.write_all(&value.to_le_bytes())?;
+    Ok(())
+}
+
+pub(crate) fn write_str<W: Write>(writer: &mut W, value: &str) -> Result<()> {
+    write_u64(writer, value.len() as u64)?;
+    writer.write_all(value.as_bytes())?;
+    Ok(())
+}
+
+/// Whether `reader` has no records left.
+pub(crate) fn at_end<R: BufRead>(reader: &mut R) -> Result<bool> {
+    Ok(reader.fill_buf()?.is_empty())
+}
+
+pub(crate) fn read_u64<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes).map_err(truncated)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+pub(crate) fn read_str<R: Read>(reader: &mut R) -> Result<String> {
+    let len = read_u64(reader)?;
+    let mut bytes = Vec::new();
+    reader
+        .take(len)
+        .read_to_end(&mut bytes)
+        .map_err(truncated)?;
+    if bytes.len() as u64 != len {
+        return Err(MDictError::InvalidFormat(
+            "spill file ended inside a string".to_string(),
+        ));
+    }
+    String::from_utf8(bytes)
+        .map_err(|e| MDictError::InvalidFormat(format!("spill file string: {}", e)))
+}
+
+fn truncated(e: io::Error) -> MDictError {
+    if e.kind() == io::ErrorKind::UnexpectedEof {
+        MDictError::InvalidFormat("spill file is truncated".to_string())
+    } else {
+        e.into()
+    }
+}
@@ -0,0 +1,114 @@
+//! Rewriting a whole MDX or MDD file with different block compression.
+//!
+//! Unlike [`crate::mdx_conversion::records`], which keeps only the records an
+//! optimized index references, a transcode copies every entry: keys in their
+//! stored order, records byte for byte, and the header attributes that shape
+//! how records are read (encoding, compact HTML, stylesheet). The result is
+//! an ordinary MDict file. zstd (block type 4) is the smallest, but only this
+//! crate reads it; LZO and zlib output opens in any MDict reader. The output
+//! is opened again afterwards and every entry compared with the source.
+
+use std::fs::{self, File};
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use crate::error::{MDictError, Result};
+use crate::mdict::Mdict;
+use crate::mdx_writer::{BlockCompression, MdxWriter};
+use crate::types::{MdictVersion, TranscodeStats};
+
+/// Write every entry of `mdict` to `output` with zstd-compressed blocks, then
+/// check that each key and record reads back unchanged. A file that fails
+/// the check is removed and the mismatch returned as an error. V3 sources
+/// are rejected, as the writer only produces V1 and V2 layouts.
+pub fn transcode_to_zstd<R: Read + Seek>(
+    mdict: &mut Mdict<R>,
+    output: impl AsRef<Path>,
+) -> Result<TranscodeStats> {
+    transcode(mdict, output, BlockCompression::Zstd)
+}
+
+/// [`transcode_to_zstd`] with blocks compressed as `compression`.
+pub fn transcode<R: Read + Seek>(
+    mdict: &mut Mdict<R>,
+    output: impl AsRef<Path>,
+    compression: BlockCompression,
+) -> Result<TranscodeStats> {
+    let output = output.as_ref();
+    let mut writer = writer_for(mdict).compression(compression);
+    for entry in mdict.iter_entries().prefetch(true) {
+        let (key_block, record) = entry?;
+        writer.add_raw_unordered(key_block.key_text, record)?;
+    }
+    writer.write_to_path(output)?;
+
+    if let Err(err) = verify_transcode(mdict, output) {
+        let _ = fs::remove_file(output);
+        return Err(err);
+    }
+
+    let stats = TranscodeStats {
+        entries: writer.len() as u64,
+        output_bytes: fs::metadata(output)?.len(),
+    };
+    log::info!(
+        "Transcoded {} entries into {} bytes",
+        stats.entries,
+        stats.output_bytes
+    );
+    Ok(stats)
+}
+
+/// A writer carrying over `mdict`'s header attributes.
+fn writer_for<R: Read + Seek>(mdict: &Mdict<R>) -> MdxWriter {
+    let header = &mdict.key_block_index.header;
+    let text = |key: &str| header.get(key).cloned().unwrap_or_default();
+    let version = header.get_version();
+    let writer = if version == MdictVersion::MDD {
+        MdxWriter::mdd()
+    } else {
+        MdxWriter::new()
+            .version(version)
+            .encoding(header.get_encoding())
+            .stylesheet(text("StyleSheet"))
+            .compact(header.is_compact())
+    };
+    writer
+        .title(text("Title"))
+        .description(text("Description"))
+        .encrypt_key_info(header.encrypted_flags() & 2 != 0)
+}
+
+/// Compare every entry of the file at `output` with `source`, in order.
+fn verify_transcode<R: Read + Seek>(source: &mut Mdict<R>, output: &Path) -> Result<()> {
+    let mut transcoded = Mdict::<File>::open(output)?;
+    let mut expected = source.iter_entries();
+    let mut actual = transcoded.iter_entries();
+    let mut index = 0usize;
+    loop {
+        match (expected.next().transpose()?, actual.next().transpose()?) {
+            (None, None) => return Ok(()),
+            (Some((expected_key, expected_record)), Some((actual_key, actual_record))) => {
+                if expected_key.key_text != actual_key.key_text {
+                    return Err(MDictError::InvalidFormat(format!(
+                        "transcoded entry {} has key '{}', expected '{}'",
+                        index, actual_key.key_text, expected_key.key_text
+                    )));
+                }
+                if expected_record != actual_record {
+                    return Err(MDictError::InvalidFormat(format!(
+                        "transcoded record of '{}' (entry {}) differs from the source",
+                        expected_key.key_text, index
+                    )));
+                }
+            }
+            _ => {
+                return Err(MDictError::InvalidFormat(format!(
+                    "transcoded file has a different number of entries after entry {}",
+                    index
+                )))
+            }
+        }
+        index += 1;
+    }
+}
@@ -0,0 +1,157 @@
+//! Rewriting records on their way into an optimized record store or an
+//! export, e.g. to strip inline scripts or minify HTML.
+//!
+//! A [`RecordTransform`] sees each record's raw bytes in the dictionary's
+//! encoding. `@@@LINK=` redirects are never passed to it, so lookups keep
+//! following them. Transforms can be chained with [`TransformPipeline`].
+
+use std::sync::Arc;
+
+use crate::links::link_target_from_record;
+
+/// Rewrites a record's bytes.
+pub trait RecordTransform: Send + Sync {
+    /// Identifies the transform in build fingerprints: a build with a
+    /// different name is not reused. Give transforms that rewrite
+    /// differently different names.
+    fn name(&self) -> String;
+
+    fn transform(&self, record: Vec<u8>) -> Vec<u8>;
+}
+
+/// Built-in transforms, selectable over UniFFI with
+/// [`crate::MdictBundle::set_record_transforms`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum RecordTransformRule {
+    StripScripts,
+    CollapseWhitespace,
+}
+
+impl RecordTransformRule {
+    pub fn transform(self) -> Arc<dyn RecordTransform> {
+        match self {
+            Self::StripScripts => Arc::new(StripScripts),
+            Self::CollapseWhitespace => Arc::new(CollapseWhitespace),
+        }
+    }
+}
+
+/// Apply `transform` to `record` unless it is a redirect.
+pub(crate) fn apply(transform: Option<&dyn RecordTransform>, record: Vec<u8>) -> Vec<u8> {
+    match transform {
+        Some(transform) if link_target_from_record(&record).is_none() => {
+            transform.transform(record)
+        }
+        _ => record,
+    }
+}
+
+/// Removes `<script>` elements, tags and contents, matching tag names
+/// case-insensitively.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StripScripts;
+
+impl RecordTransform for StripScripts {
+    fn name(&self) -> String {
+        "strip_scripts".to_string()
+    }
+
+    fn transform(&self, record: Vec<u8>) -> Vec<u8> {
+        let Some(mut start) = find_ignore_case(&record, b"<script", 0) else {
+            return record;
+        };
+        let mut out = Vec::with_capacity(record.len());
+        let mut copied = 0;
+        loop {
+            out.extend_from_slice(&record[copied..start]);
+            copied = match find_ignore_case(&record, b"</script", start) {
+                Some(close) => record[close..]
+                    .iter()
+                    .position(|&byte| byte == b'>')
+                    .map_or(record.len(), |end| close + end + 1),
+                None => record.len(),
+            };
+            match find_ignore_case(&record, b"<script", copied) {
+                Some(next) => start = next,
+                None => break,
+            }
+        }
+        out.extend_from_slice(&record[copied..]);
+        out
+    }
+}
+
+/// Collapses runs of ASCII whitespace into one space and drops whitespace
+/// between tags. Content of `<pre>` elements is not treated specially.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CollapseWhitespace;
+
+impl RecordTransform for CollapseWhitespace {
+    fn name(&self) -> String {
+        "collapse_whitespace".to_string()
+    }
+
+    fn transform(&self, record: Vec<u8>) -> Vec<u8> {
+        let mut out = Vec::with_capacity(record.len());
+        let mut pending_space = false;
+        for &byte in &record {
+            if byte.is_ascii_whitespace() {
+                pending_space = true;
+                continue;
+            }
+            if pending_space {
+                let between_tags = out.last() == Some(&b'>') && byte == b'<';
+                if !out.is_empty() && !between_tags {
+                    out.push(b' ');
+                }
+                pending_space = false;
+            }
+            out.push(byte);
+        }
+        out
+    }
+}
+
+/// Several transforms applied in order.
+#[derive(Clone, Default)]
+pub struct TransformPipeline {
+    transforms: Vec<Arc<dyn RecordTransform>>,
+}
+
+impl TransformPipeline {
+    pub fn new(transforms: Vec<Arc<dyn RecordTransform>>) -> Self {
+        Self { transforms }
+    }
+
+    pub fn from_rules(rules: &[RecordTransformRule]) -> Self {
+        Self::new(rules.iter().map(|rule| rule.transform()).collect())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+}
+
+impl RecordTransform for TransformPipeline {
+    fn name(&self) -> String {
+        self.transforms
+            .iter()
+            .map(|transform| transform.name())
+            .collect::<Vec<_>>()
+            .join("+")
+    }
+
+    fn transform(&self, record: Vec<u8>) -> Vec<u8> {
+        self.transforms
+            .iter()
+            .fold(record, |record, transform| transform.transform(record))
+    }
+}
+
+fn find_ignore_case(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle))
+        .map(|position| from + position)
+}
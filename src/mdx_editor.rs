@@ -0,0 +1,859 @@
+//! Replace records of an existing MDX or MDD file, or add entries to it,
+//! without rebuilding it.
+//!
+//! [`MdxEditor`] collects replacements and additions and writes them out in
+//! one pass. Only the record blocks holding a replaced record, or the place
+//! an added entry sorts into, are decoded and compressed again, with the
+//! compression they were stored with; every other record block is copied
+//! byte for byte. Entries that sort after every existing key go into a new
+//! key block and a new record block at the end, which suits dictionaries of
+//! user notes that grow over time. A record whose length changes moves the
+//! records after it, so the key ids (record offsets) of later entries are
+//! rewritten in their key blocks, and the key info, record index and both
+//! preambles are rebuilt around the new blocks. Blocks that are encrypted,
+//! or compressed in a type only a registered
+//! [`BlockCodec`](crate::format::BlockCodec) reads, cannot be rewritten.
+//!
+//! ```no_run
+//! use mdict_tools::mdx_editor::MdxEditor;
+//!
+//! let mut editor = MdxEditor::open("dictionary.mdx").unwrap();
+//! editor.replace("receive", "<b>receive</b>: to get or be given").unwrap();
+//! editor.add("recieve", "<i>misspelling of</i> receive").unwrap();
+//! editor.save().unwrap();
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::error::{MDictError, Result};
+use crate::format::codecs::{adler32, Lzo};
+use crate::format::encryption::{
+    decrypt_key_info_block, encrypt_key_info_block, ENCRYPTED_KEY_INFO,
+};
+use crate::format::key_block::key_entry_positions;
+use crate::format::key_index::key_info_entry_ranges;
+use crate::format::{decode_format_block_sized, KeySection, RecordSection, SectionLayout};
+use crate::mdict::{decode_record_block, Mdict};
+use crate::mdx_writer::{
+    compress_block, push_key_info_entry, BlockCompression, MDX_RECORD_TERMINATOR,
+};
+use crate::types::{Encoding, MdictVersion, RecordKind};
+
+pub struct MdxEditor {
+    path: PathBuf,
+    mdict: Mdict<File>,
+    /// New record bytes by entry index, without the MDX terminator.
+    replacements: BTreeMap<usize, Vec<u8>>,
+    /// Entries to add, by the index of the existing entry they go before
+    /// (the entry count for those after the last key), each run in key
+    /// order. Records are without the MDX terminator.
+    additions: BTreeMap<usize, Vec<(String, Vec<u8>)>>,
+}
+
+/// The parts of the file that change; everything else is copied.
+struct Rewrite {
+    layout: SectionLayout,
+    /// Key preamble and key info, if any key block was rewritten.
+    key_head: Option<Vec<u8>>,
+    /// Rewritten key blocks; the one past the last existing block is new.
+    key_blocks: BTreeMap<usize, KeyBlockEdit>,
+    /// Record preamble followed by the record index.
+    record_head: Vec<u8>,
+    /// Rewritten record blocks; the one past the last existing block is new.
+    record_blocks: BTreeMap<usize, Vec<u8>>,
+}
+
+/// A rewritten key block as stored, and its key info entry.
+struct KeyBlockEdit {
+    stored: Vec<u8>,
+    info: Vec<u8>,
+}
+
+/// A change to the record data, in the order the records are stored.
+enum RecordEdit {
+    /// The additions before entry `before`, at `at` in the record block.
+    Insert { before: usize, at: usize },
+    /// The replacement of entry `index`, whose record spans `place`.
+    Replace { index: usize, place: Range<usize> },
+}
+
+impl MdxEditor {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mdict = Mdict::<File>::open(&path)?;
+        Ok(Self {
+            path,
+            mdict,
+            replacements: BTreeMap::new(),
+            additions: BTreeMap::new(),
+        })
+    }
+
+    /// The dictionary as it is on disk, without the pending edits.
+    pub fn mdict(&mut self) -> &mut Mdict<File> {
+        &mut self.mdict
+    }
+
+    /// Replace the record of `key` with `html`, encoded in the dictionary's
+    /// encoding, and return the entry's index. Of several entries with the
+    /// key, the first is replaced; [`Self::replace_at`] reaches the others.
+    pub fn replace(&mut self, key: &str, html: &str) -> Result<usize> {
+        let record = self.encode_text(html, "replace_raw")?;
+        self.replace_raw(key, record)
+    }
+
+    /// [`Self::replace`] with record bytes stored as-is.
+    pub fn replace_raw(&mut self, key: &str, record: Vec<u8>) -> Result<usize> {
+        let index = self
+            .mdict
+            .key_block_index
+            .index_for(&mut self.mdict.reader, key)?
+            .ok_or_else(|| MDictError::KeyNotFound(key.to_string()))?;
+        self.replace_at(index, record)?;
+        Ok(index)
+    }
+
+    /// Replace the record of the entry at `index`, counted before any
+    /// additions. A later replacement of the same entry wins.
+    pub fn replace_at(&mut self, index: usize, record: Vec<u8>) -> Result<()> {
+        let total = self.mdict.key_block_index.key_section.num_entries;
+        if index as u64 >= total {
+            return Err(MDictError::InvalidArgument(format!(
+                "entry {} is past the last entry ({} entries)",
+                index, total
+            )));
+        }
+        self.replacements.insert(index, record);
+        Ok(())
+    }
+
+    /// Add an entry whose record is `html`, encoded in the dictionary's
+    /// encoding. Keys may come in any order; each entry is placed in key
+    /// order, after any entries with an equal key.
+    pub fn add(&mut self, key: impl Into<String>, html: &str) -> Result<()> {
+        let record = self.encode_text(html, "add_raw")?;
+        self.add_raw(key, record)
+    }
+
+    /// [`Self::add`] with record bytes stored as-is.
+    pub fn add_raw(&mut self, key: impl Into<String>, record: Vec<u8>) -> Result<()> {
+        let key = key.into();
+        if key.is_empty() {
+            return Err(MDictError::InvalidArgument(
+                "key must not be empty".to_string(),
+            ));
+        }
+        let (_, before) = self
+            .mdict
+            .key_block_index
+            .range_indices(&mut self.mdict.reader, ..=key.as_str())?;
+        let index = &self.mdict.key_block_index;
+        let run = self.additions.entry(before).or_default();
+        let at = run.partition_point(|(other, _)| index.compare_keys(other, &key).is_le());
+        run.insert(at, (key, record));
+        Ok(())
+    }
+
+    /// Number of replacements and additions not yet written.
+    pub fn pending(&self) -> usize {
+        self.replacements.len() + self.added()
+    }
+
+    /// Write the pending edits over the edited file and continue editing the
+    /// result.
+    pub fn save(&mut self) -> Result<()> {
+        let path = self.path.clone();
+        self.write_to(&path)?;
+        self.mdict = Mdict::<File>::open(&self.path)?;
+        self.replacements.clear();
+        self.additions.clear();
+        Ok(())
+    }
+
+    /// Write the dictionary with the pending edits to `output`, then open it
+    /// again and check that the replaced records, the added entries and the
+    /// entries next to them read back. The file is written aside and renamed
+    /// into place, so `output` may be the edited file itself; a file that
+    /// fails the check is removed. The edits stay pending.
+    pub fn write_to(&mut self, output: impl AsRef<Path>) -> Result<()> {
+        let output = output.as_ref();
+        let mut partial = output.as_os_str().to_owned();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+
+        let written = self
+            .write_file(&partial)
+            .and_then(|()| self.verify(&partial));
+        if let Err(err) = written {
+            let _ = fs::remove_file(&partial);
+            return Err(err);
+        }
+        fs::rename(&partial, output)?;
+        log::info!(
+            "Rewrote {} records and added {} entries in {}",
+            self.replacements.len(),
+            self.added(),
+            output.display()
+        );
+        Ok(())
+    }
+
+    fn added(&self) -> usize {
+        self.additions.values().map(Vec::len).sum()
+    }
+
+    fn encode_text(&self, html: &str, raw_method: &str) -> Result<Vec<u8>> {
+        if self.mdict.record_kind() == RecordKind::Binary {
+            return Err(MDictError::InvalidArgument(format!(
+                "MDD records are binary; use {}",
+                raw_method
+            )));
+        }
+        Ok(self.mdict.key_block_index.header.get_encoding().encode(html))
+    }
+
+    /// What follows an added record: the MDX terminator, or nothing in MDD.
+    fn terminator(&self) -> &'static [u8] {
+        match self.mdict.record_kind() {
+            RecordKind::Binary => &[],
+            RecordKind::Text => &MDX_RECORD_TERMINATOR,
+        }
+    }
+
+    fn write_file(&mut self, path: &Path) -> Result<()> {
+        let rewrite = self.rewrite()?;
+        let layout = &rewrite.layout;
+        let records = self.mdict.record_section()?;
+        let key_section = Arc::clone(&self.mdict.key_block_index.key_section);
+        let source = &mut self.mdict.reader;
+        let mut out = BufWriter::new(File::create(path)?);
+
+        copy_range(source, &mut out, layout.header.clone())?;
+        match &rewrite.key_head {
+            Some(head) => out.write_all(head)?,
+            None => {
+                let key_head = layout.key_preamble.start..layout.key_info.end;
+                copy_range(source, &mut out, key_head)?
+            }
+        }
+        let key_block_count = key_section.key_info_blocks.len();
+        let mut copied = layout.key_blocks.start;
+        for (&block, edit) in rewrite.key_blocks.range(..key_block_count) {
+            let range = key_block_range(layout, &key_section, block)?;
+            copy_range(source, &mut out, copied..range.start)?;
+            out.write_all(&edit.stored)?;
+            copied = range.end;
+        }
+        copy_range(source, &mut out, copied..layout.key_blocks.end)?;
+        for (_, edit) in rewrite.key_blocks.range(key_block_count..) {
+            out.write_all(&edit.stored)?;
+        }
+
+        out.write_all(&rewrite.record_head)?;
+        let record_block_count = records.num_record_blocks as usize;
+        let mut copied = layout.record_data.start;
+        for (&block, bytes) in rewrite.record_blocks.range(..record_block_count) {
+            let range = layout
+                .record_block(&records, block)
+                .ok_or("record block past the record index")?;
+            copy_range(source, &mut out, copied..range.start)?;
+            out.write_all(bytes)?;
+            copied = range.end;
+        }
+        copy_range(source, &mut out, copied..layout.record_data.end)?;
+        for (_, bytes) in rewrite.record_blocks.range(record_block_count..) {
+            out.write_all(bytes)?;
+        }
+        // Anything after the record data.
+        let file_len = source.seek(SeekFrom::End(0))?;
+        copy_range(source, &mut out, layout.record_data.end..file_len)?;
+
+        out.flush()?;
+        Ok(())
+    }
+
+    fn rewrite(&mut self) -> Result<Rewrite> {
+        let header = Arc::clone(&self.mdict.key_block_index.header);
+        let key_section = Arc::clone(&self.mdict.key_block_index.key_section);
+        let version = header.get_version();
+        let width = version.index_pair_size_bytes();
+        let total = key_section.num_entries as usize;
+        let layout = self.mdict.section_layout()?;
+        let records = self.mdict.record_section()?;
+        let terminator = self.terminator();
+        let mut compressor = Recompressor::default();
+
+        // Record order: the additions before an entry come before its record.
+        let mut order = Vec::new();
+        order.extend(self.additions.range(..total).map(|(&i, _)| (2 * i, i)));
+        order.extend(self.replacements.keys().map(|&i| (2 * i + 1, i)));
+        order.sort_unstable();
+        let mut by_block: BTreeMap<usize, Vec<RecordEdit>> = BTreeMap::new();
+        for (rank, index) in order {
+            let key_id = self.key_id_at(index)?;
+            let (block, at) = record_position(&records, key_id)?;
+            let edit = if rank % 2 == 0 {
+                RecordEdit::Insert { before: index, at }
+            } else {
+                let size = self.mdict.record_size_at_index(index)? as usize;
+                RecordEdit::Replace {
+                    index,
+                    place: at..at + size,
+                }
+            };
+            by_block.entry(block).or_default().push(edit);
+        }
+
+        let mut record_head = read_range(
+            &mut self.mdict.reader,
+            layout.record_preamble.start..layout.record_index.end,
+        )?;
+        let index_start = (layout.record_index.start - layout.record_preamble.start) as usize;
+        let sizes = &records.record_index_prefix_sum;
+        let mut record_blocks = BTreeMap::new();
+        // Growth of the record data, with the first entry it moves.
+        let mut deltas = Vec::new();
+        // Key ids of the added entries, by the entry they go before.
+        let mut added_ids: BTreeMap<usize, Vec<u64>> = BTreeMap::new();
+        let mut data_growth = 0i64;
+        let mut stream_growth = 0i64;
+        for (block, edits) in by_block {
+            let what = format!("record block {}", block);
+            let range = layout
+                .record_block(&records, block)
+                .ok_or("record block past the record index")?;
+            let stored = read_range(&mut self.mdict.reader, range.clone())?;
+            let compression = stored_compression(&stored, &what)?;
+            let decomp_size = sizes[block + 1].uncompressed_size - sizes[block].uncompressed_size;
+            let decoded = decode_record_block(block, range.start, &stored, decomp_size as usize)?;
+            let block_start = offset_by(sizes[block].uncompressed_size, stream_growth)?;
+
+            let mut spliced = Vec::with_capacity(decoded.len());
+            let mut copied = 0;
+            for edit in edits {
+                match edit {
+                    RecordEdit::Insert { before, at } => {
+                        let kept = decoded.get(copied..at).ok_or_else(|| {
+                            MDictError::InvalidFormat(format!(
+                                "entry {}: record offset goes back in {}",
+                                before, what
+                            ))
+                        })?;
+                        spliced.extend_from_slice(kept);
+                        copied = at;
+                        let inserted_at = spliced.len();
+                        let mut ids = Vec::new();
+                        for (_, record) in &self.additions[&before] {
+                            ids.push(block_start + spliced.len() as u64);
+                            spliced.extend_from_slice(record);
+                            spliced.extend_from_slice(terminator);
+                        }
+                        deltas.push((before, (spliced.len() - inserted_at) as i64));
+                        added_ids.insert(before, ids);
+                    }
+                    RecordEdit::Replace { index, place } => {
+                        let kept = decoded.get(copied..place.start);
+                        let (Some(kept), Some(old)) = (kept, decoded.get(place.clone())) else {
+                            return Err(MDictError::UnsupportedFeature(format!(
+                                "the record of entry {} continues past {}",
+                                index, what
+                            )));
+                        };
+                        // Keep the terminator the old record was stored with.
+                        let suffix = &old[self.mdict.strip_record_terminator(old).len()..];
+                        let record = &self.replacements[&index];
+                        spliced.extend_from_slice(kept);
+                        spliced.extend_from_slice(record);
+                        spliced.extend_from_slice(suffix);
+                        copied = place.end;
+                        let new_len = record.len() + suffix.len();
+                        deltas.push((index + 1, new_len as i64 - old.len() as i64));
+                    }
+                }
+            }
+            spliced.extend_from_slice(&decoded[copied..]);
+
+            let compressed = compressor.compress(compression, &spliced)?;
+            let pair = index_start + 2 * width * block;
+            put_number(&mut record_head, pair, version, compressed.len() as u64)?;
+            put_number(&mut record_head, pair + width, version, spliced.len() as u64)?;
+            data_growth += compressed.len() as i64 - stored.len() as i64;
+            stream_growth += spliced.len() as i64 - decoded.len() as i64;
+            record_blocks.insert(block, compressed);
+        }
+
+        // Entries after the last key get a record block of their own.
+        let mut num_record_blocks = records.num_record_blocks;
+        if let Some(run) = self.additions.get(&total) {
+            let end = sizes.last().map_or(0, |ri| ri.uncompressed_size);
+            let block_start = offset_by(end, stream_growth)?;
+            let mut block = Vec::new();
+            let mut ids = Vec::new();
+            for (_, record) in run {
+                ids.push(block_start + block.len() as u64);
+                block.extend_from_slice(record);
+                block.extend_from_slice(terminator);
+            }
+            added_ids.insert(total, ids);
+
+            let last = num_record_blocks.checked_sub(1).map(|last| last as usize);
+            let last_range = last.and_then(|last| layout.record_block(&records, last));
+            let compression = compression_at(&mut self.mdict.reader, last_range, "record block")?;
+            let compressed = compressor.compress(compression, &block)?;
+            push_number(&mut record_head, version, compressed.len() as u64)?;
+            push_number(&mut record_head, version, block.len() as u64)?;
+            data_growth += compressed.len() as i64;
+            record_blocks.insert(num_record_blocks as usize, compressed);
+            num_record_blocks += 1;
+        }
+        let index_size = (record_head.len() - index_start) as u64;
+        let data_size = offset_by(records.byte_size_record_data, data_growth)?;
+        let num_entries = records.num_entries + self.added() as u64;
+        put_number(&mut record_head, 0, version, num_record_blocks)?;
+        put_number(&mut record_head, width, version, num_entries)?;
+        put_number(&mut record_head, 2 * width, version, index_size)?;
+        put_number(&mut record_head, 3 * width, version, data_size)?;
+
+        let key_blocks = self.rewrite_key_blocks(
+            &layout,
+            &key_section,
+            &deltas,
+            &added_ids,
+            &mut compressor,
+        )?;
+        let key_head = if key_blocks.is_empty() {
+            None
+        } else {
+            Some(self.key_head(&layout, &key_section, &key_blocks, &mut compressor)?)
+        };
+
+        Ok(Rewrite {
+            layout,
+            key_head,
+            key_blocks,
+            record_head,
+            record_blocks,
+        })
+    }
+
+    /// Rewrite the key blocks that gain entries or whose entries' records
+    /// moved by `deltas` (growth of the record data, with the first entry it
+    /// moves, in entry order), and add a key block for entries after the
+    /// last key. `added_ids` holds the key ids of the added entries.
+    fn rewrite_key_blocks(
+        &mut self,
+        layout: &SectionLayout,
+        key_section: &KeySection,
+        deltas: &[(usize, i64)],
+        added_ids: &BTreeMap<usize, Vec<u64>>,
+        compressor: &mut Recompressor,
+    ) -> Result<BTreeMap<usize, KeyBlockEdit>> {
+        let header = Arc::clone(&self.mdict.key_block_index.header);
+        let version = header.get_version();
+        let encoding = header.get_encoding();
+        let num_blocks = key_section.key_info_blocks.len();
+        let mut key_blocks = BTreeMap::new();
+
+        let mut shift = 0i64;
+        let mut pending = deltas.iter().filter(|(_, delta)| *delta != 0).peekable();
+        for block in 0..num_blocks {
+            let first_entry = key_section.num_entries_prefix_sum[block] as usize;
+            let end_entry = key_section.num_entries_prefix_sum[block + 1] as usize;
+            while let Some((_, delta)) = pending.next_if(|(moved, _)| *moved <= first_entry) {
+                shift += delta;
+            }
+            let moved_inside = pending.peek().is_some_and(|(moved, _)| *moved < end_entry);
+            let gains = self.additions.range(first_entry..end_entry).next().is_some();
+            if shift == 0 && !moved_inside && !gains {
+                continue;
+            }
+
+            let what = format!("key block {}", block);
+            let range = key_block_range(layout, key_section, block)?;
+            let stored = read_range(&mut self.mdict.reader, range.clone())?;
+            let compression = stored_compression(&stored, &what)?;
+            let size = key_section.key_info_blocks[block].decompressed_size as usize;
+            let decoded = decode_format_block_sized(&stored, size)
+                .map_err(|e| e.in_section(&what).at_offset(range.start))?;
+
+            let mut rebuilt = NewKeyBlock::default();
+            let positions = key_entry_positions(&decoded, encoding, version)?;
+            for (n, (at, text)) in positions.into_iter().enumerate() {
+                let index = first_entry + n;
+                self.push_added(&mut rebuilt, version, encoding, index, added_ids)?;
+                while let Some((_, delta)) = pending.next_if(|(moved, _)| *moved <= index) {
+                    shift += delta;
+                }
+                let key_id = offset_by(read_number(&decoded, at, version), shift)?;
+                rebuilt.push(version, encoding, key_id, &decoded[text])?;
+            }
+            let edit = rebuilt.finish(version, encoding, compression, compressor)?;
+            key_blocks.insert(block, edit);
+        }
+
+        let total = key_section.num_entries as usize;
+        if self.additions.contains_key(&total) {
+            let last_range = num_blocks
+                .checked_sub(1)
+                .and_then(|last| layout.key_block(key_section, last));
+            let compression = compression_at(&mut self.mdict.reader, last_range, "key block")?;
+            let mut appended = NewKeyBlock::default();
+            self.push_added(&mut appended, version, encoding, total, added_ids)?;
+            let edit = appended.finish(version, encoding, compression, compressor)?;
+            key_blocks.insert(num_blocks, edit);
+        }
+        Ok(key_blocks)
+    }
+
+    /// Append the entries added before entry `before` to `block`.
+    fn push_added(
+        &self,
+        block: &mut NewKeyBlock,
+        version: MdictVersion,
+        encoding: Encoding,
+        before: usize,
+        added_ids: &BTreeMap<usize, Vec<u64>>,
+    ) -> Result<()> {
+        let (Some(run), Some(ids)) = (self.additions.get(&before), added_ids.get(&before)) else {
+            return Ok(());
+        };
+        for ((key, _), &key_id) in run.iter().zip(ids) {
+            block.push(version, encoding, key_id, &encoding.encode(key))?;
+        }
+        Ok(())
+    }
+
+    /// Key preamble and key info, with the entries of the rewritten
+    /// `key_blocks` and those of the other blocks as they were.
+    fn key_head(
+        &mut self,
+        layout: &SectionLayout,
+        key_section: &KeySection,
+        key_blocks: &BTreeMap<usize, KeyBlockEdit>,
+        compressor: &mut Recompressor,
+    ) -> Result<Vec<u8>> {
+        let header = Arc::clone(&self.mdict.key_block_index.header);
+        let version = header.get_version();
+        let width = version.index_pair_size_bytes();
+        let v2 = version.major() >= 2;
+        let encrypted = header.encrypted_flags() & ENCRYPTED_KEY_INFO != 0;
+
+        let mut key_info = read_range(&mut self.mdict.reader, layout.key_info.clone())?;
+        let compression = if v2 {
+            let compression = stored_compression(&key_info, "key info block")?;
+            if encrypted {
+                decrypt_key_info_block(&mut key_info);
+            }
+            let preamble = read_range(&mut self.mdict.reader, layout.key_preamble.clone())?;
+            let size = read_number(&preamble, 2 * width, version) as usize;
+            key_info = decode_format_block_sized(&key_info, size)
+                .map_err(|e| e.in_section("key info block"))?;
+            Some(compression)
+        } else {
+            None
+        };
+
+        let entries = key_info_entry_ranges(version, &key_info, header.get_encoding())?;
+        if entries.len() != key_section.key_info_blocks.len() {
+            return Err(MDictError::InvalidFormat(format!(
+                "key info block: lists {} key blocks, preamble says {}",
+                entries.len(),
+                key_section.key_info_blocks.len()
+            )));
+        }
+        let num_blocks = entries.len() + key_blocks.range(entries.len()..).count();
+        let mut rebuilt = Vec::with_capacity(key_info.len());
+        let mut key_blocks_size = 0u64;
+        for block in 0..num_blocks {
+            match key_blocks.get(&block) {
+                Some(edit) => {
+                    rebuilt.extend_from_slice(&edit.info);
+                    key_blocks_size += edit.stored.len() as u64;
+                }
+                None => {
+                    rebuilt.extend_from_slice(&key_info[entries[block].clone()]);
+                    key_blocks_size += key_section.key_info_blocks[block].compressed_size;
+                }
+            }
+        }
+
+        let decompressed_size = rebuilt.len() as u64;
+        if let Some(compression) = compression {
+            rebuilt = compressor.compress(compression, &rebuilt)?;
+            if encrypted {
+                encrypt_key_info_block(&mut rebuilt);
+            }
+        }
+
+        // V2 preambles carry the decompressed key info size before the
+        // stored one, and an adler32 of the five numbers.
+        let num_entries = key_section.num_entries + self.added() as u64;
+        let mut head = Vec::with_capacity(5 * width + 4 + rebuilt.len());
+        push_number(&mut head, version, num_blocks as u64)?;
+        push_number(&mut head, version, num_entries)?;
+        if v2 {
+            push_number(&mut head, version, decompressed_size)?;
+        }
+        push_number(&mut head, version, rebuilt.len() as u64)?;
+        push_number(&mut head, version, key_blocks_size)?;
+        if v2 {
+            let checksum = adler32(&head);
+            head.extend_from_slice(&checksum.to_be_bytes());
+        }
+        head.extend_from_slice(&rebuilt);
+        Ok(head)
+    }
+
+    fn key_id_at(&mut self, index: usize) -> Result<u64> {
+        self.mdict
+            .key_block_index
+            .get(&mut self.mdict.reader, index)?
+            .map(|key_block| key_block.key_id)
+            .ok_or_else(|| MDictError::InvalidArgument("Key block not found".to_string()))
+    }
+
+    /// Index that the entry at `index` has once the additions are written.
+    fn new_index(&self, index: usize) -> usize {
+        let runs = self.additions.range(..=index);
+        index + runs.map(|(_, run)| run.len()).sum::<usize>()
+    }
+
+    fn verify(&mut self, path: &Path) -> Result<()> {
+        let mut written = Mdict::<File>::open(path)?;
+        let total = self.mdict.key_block_index.key_section.num_entries as usize;
+        let expected = total + self.added();
+        let entries = written.key_block_index.key_section.num_entries as usize;
+        if entries != expected {
+            return Err(MDictError::InvalidFormat(format!(
+                "rewritten file has {} entries, expected {}",
+                entries, expected
+            )));
+        }
+        for (&index, record) in &self.replacements {
+            if written.record_at_index(self.new_index(index))? != *record {
+                return Err(MDictError::InvalidFormat(format!(
+                    "replaced record of entry {} does not read back",
+                    index
+                )));
+            }
+        }
+        for (&before, run) in &self.additions {
+            let first = self.new_index(before) - run.len();
+            for (n, (key, record)) in run.iter().enumerate() {
+                let key_text = written
+                    .key_block_index
+                    .get(&mut written.reader, first + n)?
+                    .map(|key_block| key_block.key_text);
+                if key_text.as_ref() != Some(key)
+                    || written.record_at_index(first + n)? != *record
+                {
+                    return Err(MDictError::InvalidFormat(format!(
+                        "added entry '{}' does not read back",
+                        key
+                    )));
+                }
+            }
+        }
+
+        // The entries right after each edit moved.
+        let moved = self
+            .replacements
+            .keys()
+            .map(|&index| index + 1)
+            .chain(self.additions.keys().copied());
+        for index in moved {
+            if index < total
+                && !self.replacements.contains_key(&index)
+                && written.record_at_index(self.new_index(index))?
+                    != self.mdict.record_at_index(index)?
+            {
+                return Err(MDictError::InvalidFormat(format!(
+                    "record of entry {} changed while editing the entries around it",
+                    index
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A key block being rebuilt, with what its key info entry lists.
+#[derive(Default)]
+struct NewKeyBlock {
+    decoded: Vec<u8>,
+    entries: u64,
+    first: Range<usize>,
+    last: Range<usize>,
+}
+
+impl NewKeyBlock {
+    fn push(
+        &mut self,
+        version: MdictVersion,
+        encoding: Encoding,
+        key_id: u64,
+        text: &[u8],
+    ) -> Result<()> {
+        push_number(&mut self.decoded, version, key_id)?;
+        let start = self.decoded.len();
+        self.decoded.extend_from_slice(text);
+        self.last = start..self.decoded.len();
+        if self.entries == 0 {
+            self.first = self.last.clone();
+        }
+        self.entries += 1;
+        self.decoded
+            .extend(std::iter::repeat_n(0u8, encoding.char_width()));
+        Ok(())
+    }
+
+    fn finish(
+        self,
+        version: MdictVersion,
+        encoding: Encoding,
+        compression: BlockCompression,
+        compressor: &mut Recompressor,
+    ) -> Result<KeyBlockEdit> {
+        let stored = compressor.compress(compression, &self.decoded)?;
+        let first = &self.decoded[self.first.clone()];
+        let last = &self.decoded[self.last.clone()];
+        let mut info = Vec::new();
+        push_key_info_entry(
+            &mut info,
+            version,
+            encoding,
+            self.entries,
+            [first, last],
+            [stored.len() as u64, self.decoded.len() as u64],
+        )?;
+        Ok(KeyBlockEdit { stored, info })
+    }
+}
+
+/// Compresses rewritten blocks with the compression they were stored with.
+#[derive(Default)]
+struct Recompressor {
+    lzo: Option<Lzo>,
+}
+
+impl Recompressor {
+    fn compress(&mut self, compression: BlockCompression, data: &[u8]) -> Result<Vec<u8>> {
+        if compression == BlockCompression::Lzo && self.lzo.is_none() {
+            self.lzo = Some(Lzo::init()?);
+        }
+        compress_block(compression, self.lzo.as_mut(), data)
+    }
+}
+
+/// The compression `block` is stored with, if it can be written again.
+fn stored_compression(block: &[u8], what: &str) -> Result<BlockCompression> {
+    let word = block
+        .get(..4)
+        .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+        .ok_or_else(|| MDictError::InvalidFormat(format!("{}: block is too short", what)))?;
+    if word & !0x0f != 0 {
+        return Err(MDictError::UnsupportedFeature(format!(
+            "{} is encrypted (encoding word {:#010x}) and cannot be rewritten",
+            what, word
+        )));
+    }
+    BlockCompression::from_block_type(word).ok_or_else(|| {
+        MDictError::UnsupportedFeature(format!(
+            "{} uses block compression type {}, which cannot be written",
+            what, word
+        ))
+    })
+}
+
+/// The compression of the block at `range`, for a new block to follow it;
+/// an empty section takes the writer's default.
+fn compression_at(
+    source: &mut File,
+    range: Option<Range<u64>>,
+    what: &str,
+) -> Result<BlockCompression> {
+    let Some(range) = range else {
+        return Ok(BlockCompression::Zlib);
+    };
+    let word = read_range(source, range.start..range.end.min(range.start + 4))?;
+    stored_compression(&word, what)
+}
+
+/// The record block holding uncompressed offset `key_id`, and the offset
+/// within it. The end of the record data is the end of the last block.
+fn record_position(records: &RecordSection, key_id: u64) -> Result<(usize, usize)> {
+    let sizes = &records.record_index_prefix_sum;
+    let block = match sizes.last() {
+        Some(end) if sizes.len() > 1 && end.uncompressed_size == key_id => sizes.len() - 2,
+        _ => records.bin_search_record_index(key_id)? as usize,
+    };
+    Ok((block, (key_id - sizes[block].uncompressed_size) as usize))
+}
+
+fn key_block_range(
+    layout: &SectionLayout,
+    key_section: &KeySection,
+    block: usize,
+) -> Result<Range<u64>> {
+    layout
+        .key_block(key_section, block)
+        .ok_or_else(|| "key block past the key info".into())
+}
+
+fn read_range(source: &mut File, range: Range<u64>) -> Result<Vec<u8>> {
+    let mut buf = vec![0u8; (range.end - range.start) as usize];
+    source.seek(SeekFrom::Start(range.start))?;
+    source.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn copy_range(source: &mut File, out: &mut impl Write, range: Range<u64>) -> Result<()> {
+    let len = range.end.saturating_sub(range.start);
+    source.seek(SeekFrom::Start(range.start))?;
+    let copied = io::copy(&mut Read::by_ref(source).take(len), out)?;
+    if copied != len {
+        return Err(MDictError::Io(format!(
+            "source ended at byte {} while copying up to {}",
+            range.start + copied,
+            range.end
+        )));
+    }
+    Ok(())
+}
+
+/// A big-endian `u32` (V1) or `u64` number at `at`.
+fn read_number(buf: &[u8], at: usize, version: MdictVersion) -> u64 {
+    let width = version.index_pair_size_bytes();
+    buf[at..at + width]
+        .iter()
+        .fold(0u64, |value, &byte| (value << 8) | u64::from(byte))
+}
+
+fn put_number(buf: &mut [u8], at: usize, version: MdictVersion, value: u64) -> Result<()> {
+    let width = version.index_pair_size_bytes();
+    if width == 4 && value > u64::from(u32::MAX) {
+        return Err(MDictError::InvalidArgument(format!(
+            "{} does not fit a V1 size field",
+            value
+        )));
+    }
+    buf[at..at + width].copy_from_slice(&value.to_be_bytes()[8 - width..]);
+    Ok(())
+}
+
+fn push_number(out: &mut Vec<u8>, version: MdictVersion, value: u64) -> Result<()> {
+    let at = out.len();
+    out.resize(at + version.index_pair_size_bytes(), 0);
+    put_number(out, at, version, value)
+}
+
+fn offset_by(value: u64, delta: i64) -> Result<u64> {
+    value.checked_add_signed(delta).ok_or_else(|| {
+        MDictError::InvalidFormat(format!("{} moved by {} is out of range", value, delta))
+    })
+}
@@ -0,0 +1,589 @@
+//! Build MDX/MDD files from sorted key/record pairs.
+//!
+//! ```no_run
+//! use mdict_tools::mdx_writer::{BlockCompression, MdxWriter};
+//!
+//! let mut writer = MdxWriter::new()
+//!     .title("My Glossary")
+//!     .compression(BlockCompression::Lzo);
+//! writer.add("apple", "<b>apple</b>: a fruit").unwrap();
+//! writer.add("banana", "<b>banana</b>: another fruit").unwrap();
+//! # #[cfg(feature = "fs")]
+//! writer.write_to_path("glossary.mdx").unwrap();
+//! ```
+
+#[cfg(feature = "fs")]
+use std::fs::File;
+#[cfg(feature = "fs")]
+use std::io::BufWriter;
+use std::io::Write;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use crate::collation::{KeyCollation, KeyOrder};
+use crate::error::{MDictError, Result};
+use crate::format::codecs::{adler32, zstd_compress, Lzo};
+use crate::format::encryption::encrypt_key_info_block;
+use crate::types::{Encoding, MdictVersion};
+
+const DEFAULT_KEY_BLOCK_SIZE: usize = 32 * 1024;
+const DEFAULT_RECORD_BLOCK_SIZE: usize = 64 * 1024;
+const ZLIB_LEVEL: u8 = 6;
+const ZSTD_LEVEL: i32 = 19;
+
+/// Trailer appended to MDX records; `Mdict::record_at_index` strips it again.
+pub(crate) const MDX_RECORD_TERMINATOR: [u8; 2] = [0x0A, 0x00];
+
+/// Compression applied to key-info, key and record blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum BlockCompression {
+    None,
+    Lzo,
+    Zlib,
+    /// Block type 4, read by this crate but not by MDict itself: a `u32` LE
+    /// uncompressed size followed by a zstd frame.
+    Zstd,
+}
+
+impl BlockCompression {
+    fn block_type(self) -> u32 {
+        match self {
+            BlockCompression::None => 0,
+            BlockCompression::Lzo => 1,
+            BlockCompression::Zlib => 2,
+            BlockCompression::Zstd => 4,
+        }
+    }
+
+    /// The compression of a block stored with type `block_type`, if this
+    /// crate can write it.
+    #[cfg(feature = "fs")]
+    pub(crate) fn from_block_type(block_type: u32) -> Option<Self> {
+        match block_type {
+            0 => Some(BlockCompression::None),
+            1 => Some(BlockCompression::Lzo),
+            2 => Some(BlockCompression::Zlib),
+            4 => Some(BlockCompression::Zstd),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MdxWriter {
+    version: MdictVersion,
+    encoding: Encoding,
+    title: String,
+    description: String,
+    stylesheet: String,
+    compact: bool,
+    compression: BlockCompression,
+    key_block_size: usize,
+    record_block_size: usize,
+    entries_per_key_block: usize,
+    entries_per_record_block: usize,
+    encrypt_key_info: bool,
+    entries: Vec<(String, Vec<u8>)>,
+}
+
+impl Default for MdxWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MdxWriter {
+    /// A V2, UTF-8, zlib-compressed MDX writer.
+    pub fn new() -> Self {
+        Self {
+            version: MdictVersion::V2,
+            encoding: Encoding::Utf8,
+            title: String::new(),
+            description: String::new(),
+            stylesheet: String::new(),
+            compact: false,
+            compression: BlockCompression::Zlib,
+            key_block_size: DEFAULT_KEY_BLOCK_SIZE,
+            record_block_size: DEFAULT_RECORD_BLOCK_SIZE,
+            entries_per_key_block: usize::MAX,
+            entries_per_record_block: usize::MAX,
+            encrypt_key_info: false,
+            entries: Vec::new(),
+        }
+    }
+
+    /// A writer for MDD resource files (always UTF-16LE keys, binary records).
+    pub fn mdd() -> Self {
+        Self {
+            version: MdictVersion::MDD,
+            encoding: Encoding::Utf16LE,
+            ..Self::new()
+        }
+    }
+
+    pub fn version(mut self, version: MdictVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Key/record text encoding. Ignored for MDD output, which is always UTF-16LE.
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    /// Raw `StyleSheet` attribute: `number`, open markup and close markup
+    /// on consecutive lines (see [`crate::stylesheet`]). MDX only.
+    pub fn stylesheet(mut self, stylesheet: impl Into<String>) -> Self {
+        self.stylesheet = stylesheet.into();
+        self
+    }
+
+    /// Declare records as compact HTML (`Compact="Yes"`): their markup is
+    /// abbreviated to stylesheet markers. MDX only.
+    pub fn compact(mut self, compact: bool) -> Self {
+        self.compact = compact;
+        self
+    }
+
+    pub fn compression(mut self, compression: BlockCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Target uncompressed size of a key block; a block is closed once it
+    /// reaches this size or `entries_per_key_block` entries.
+    pub fn key_block_size(mut self, bytes: usize) -> Self {
+        self.key_block_size = bytes;
+        self
+    }
+
+    /// Target uncompressed size of a record block.
+    pub fn record_block_size(mut self, bytes: usize) -> Self {
+        self.record_block_size = bytes;
+        self
+    }
+
+    pub fn entries_per_key_block(mut self, n: usize) -> Self {
+        self.entries_per_key_block = n;
+        self
+    }
+
+    pub fn entries_per_record_block(mut self, n: usize) -> Self {
+        self.entries_per_record_block = n;
+        self
+    }
+
+    /// Encrypt the key-info block (`Encrypted="2"`). Only V2 files compress,
+    /// and therefore encrypt, their key info; V1 output ignores this.
+    pub fn encrypt_key_info(mut self, encrypt: bool) -> Self {
+        self.encrypt_key_info = encrypt;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Append an entry whose record is text, encoded with the writer's encoding.
+    /// Keys must arrive in lookup order: MDict's default case-insensitive
+    /// order, which the written header declares, on the encoded keys for
+    /// GBK, Big5 and GB18030.
+    pub fn add(&mut self, key: impl Into<String>, html: &str) -> Result<()> {
+        let record = self.text_encoding().encode(html);
+        self.add_raw(key, record)
+    }
+
+    /// Append an entry whose record bytes are stored as-is (MDD resources, or
+    /// text already in the dictionary's encoding).
+    pub fn add_raw(&mut self, key: impl Into<String>, record: Vec<u8>) -> Result<()> {
+        let key = key.into();
+        if key.is_empty() {
+            return Err(MDictError::InvalidArgument(
+                "key must not be empty".to_string(),
+            ));
+        }
+        if let Some((last, _)) = self.entries.last() {
+            let order = KeyOrder::from(KeyCollation::default()).with_encoding(self.text_encoding());
+            if order.compare(&key, last).is_lt() {
+                return Err(MDictError::InvalidArgument(format!(
+                    "keys must be added in sorted order: '{}' after '{}'",
+                    key, last
+                )));
+            }
+        }
+        self.entries.push((key, record));
+        Ok(())
+    }
+
+    /// [`Self::add_raw`] without the order check, for copying the entries of
+    /// an existing dictionary in the order it stores them.
+    #[cfg(feature = "fs")]
+    pub(crate) fn add_raw_unordered(&mut self, key: String, record: Vec<u8>) -> Result<()> {
+        if key.is_empty() {
+            return Err(MDictError::InvalidArgument(
+                "key must not be empty".to_string(),
+            ));
+        }
+        self.entries.push((key, record));
+        Ok(())
+    }
+
+    fn text_encoding(&self) -> Encoding {
+        if self.version == MdictVersion::MDD {
+            Encoding::Utf16LE
+        } else {
+            self.encoding
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.version == MdictVersion::V3 {
+            return Err(MDictError::UnsupportedFeature(
+                "writing V3 dictionaries is not supported".to_string(),
+            ));
+        }
+        if self.text_encoding() == Encoding::Unknown {
+            return Err(MDictError::InvalidArgument(
+                "writer needs a concrete encoding".to_string(),
+            ));
+        }
+        if self.key_block_size == 0
+            || self.record_block_size == 0
+            || self.entries_per_key_block == 0
+            || self.entries_per_record_block == 0
+        {
+            return Err(MDictError::InvalidArgument(
+                "block limits must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        self.write(&mut out)?;
+        Ok(out)
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn write_to_path<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W) -> Result<()> {
+        self.validate()?;
+        let encrypt_key_info = self.encrypt_key_info && self.version.major() >= 2;
+        let mut lzo = match self.compression {
+            BlockCompression::Lzo => Some(Lzo::init()?),
+            _ => None,
+        };
+        let mut compress = |data: &[u8]| compress_block(self.compression, lzo.as_mut(), data);
+
+        let record_blocks = self.record_blocks();
+        let mut bytes = self.header(encrypt_key_info);
+        self.write_key_section(&mut bytes, encrypt_key_info, &mut compress)?;
+        self.write_record_section(&mut bytes, &record_blocks, &mut compress)?;
+
+        out.write_all(&bytes)?;
+        Ok(())
+    }
+
+    fn header(&self, encrypted: bool) -> Vec<u8> {
+        let encrypted = if encrypted { "2" } else { "No" };
+        let xml = if self.version == MdictVersion::MDD {
+            // The reader tells MDD from MDX by the absence of GeneratedByEngineVersion.
+            format!(
+                "<Library_Data Encrypted=\"{x}\" Format=\"\" Title=\"{t}\" Description=\"{d}\"/>\r\n\0",
+                x = encrypted,
+                t = escape_xml(&self.title),
+                d = escape_xml(&self.description),
+            )
+        } else {
+            let engine_version = if self.version == MdictVersion::V1 {
+                "1.2"
+            } else {
+                "2.0"
+            };
+            format!(
+                "<Dictionary GeneratedByEngineVersion=\"{v}\" RequiredEngineVersion=\"{v}\" \
+                 Encrypted=\"{x}\" Encoding=\"{e}\" Format=\"Html\" Compact=\"{c}\" \
+                 KeyCaseSensitive=\"No\" Title=\"{t}\" Description=\"{d}\"{s}/>\r\n\0",
+                v = engine_version,
+                x = encrypted,
+                e = self.encoding.label(),
+                c = if self.compact { "Yes" } else { "No" },
+                t = escape_xml(&self.title),
+                d = escape_xml(&self.description),
+                s = if self.stylesheet.is_empty() {
+                    String::new()
+                } else {
+                    format!(" StyleSheet=\"{}\"", escape_xml(&self.stylesheet))
+                },
+            )
+        };
+
+        let xml_bytes = Encoding::Utf16LE.encode(&xml);
+        let mut out = Vec::with_capacity(xml_bytes.len() + 8);
+        out.extend_from_slice(&(xml_bytes.len() as u32).to_be_bytes());
+        out.extend_from_slice(&xml_bytes);
+        out.extend_from_slice(&adler32(&xml_bytes).to_le_bytes());
+        out
+    }
+
+    fn record_bytes(&self, record: &[u8]) -> Vec<u8> {
+        let mut out = record.to_vec();
+        if self.version != MdictVersion::MDD {
+            out.extend_from_slice(&MDX_RECORD_TERMINATOR);
+        }
+        out
+    }
+
+    /// Split entries into runs closed at the size or count limit.
+    fn chunk_ranges(
+        &self,
+        sizes: impl Iterator<Item = usize>,
+        max_bytes: usize,
+        max_entries: usize,
+    ) -> Vec<std::ops::Range<usize>> {
+        let mut ranges = Vec::new();
+        let mut start = 0;
+        let mut bytes = 0;
+        for (i, size) in sizes.enumerate() {
+            bytes += size;
+            if bytes >= max_bytes || i + 1 - start >= max_entries {
+                ranges.push(start..i + 1);
+                start = i + 1;
+                bytes = 0;
+            }
+        }
+        if start < self.entries.len() {
+            ranges.push(start..self.entries.len());
+        }
+        ranges
+    }
+
+    fn record_blocks(&self) -> Vec<Vec<u8>> {
+        let ranges = self.chunk_ranges(
+            self.entries
+                .iter()
+                .map(|(_, record)| self.record_bytes(record).len()),
+            self.record_block_size,
+            self.entries_per_record_block,
+        );
+        ranges
+            .into_iter()
+            .map(|range| {
+                self.entries[range]
+                    .iter()
+                    .flat_map(|(_, record)| self.record_bytes(record))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn write_key_section(
+        &self,
+        out: &mut Vec<u8>,
+        encrypt_key_info: bool,
+        compress: &mut impl FnMut(&[u8]) -> Result<Vec<u8>>,
+    ) -> Result<()> {
+        let version = self.version;
+        let encoding = self.text_encoding();
+        let null_width = encoding.char_width();
+        let id_width = version.index_pair_size_bytes();
+
+        let ranges = self.chunk_ranges(
+            self.entries
+                .iter()
+                .map(|(key, _)| id_width + encoding.encode(key).len() + null_width),
+            self.key_block_size,
+            self.entries_per_key_block,
+        );
+
+        // Each key id is the record's offset in the concatenated uncompressed record data.
+        let mut key_id = 0u64;
+        let mut key_info = Vec::new();
+        let mut key_blocks = Vec::new();
+
+        for range in &ranges {
+            let chunk = &self.entries[range.clone()];
+            let mut block = Vec::new();
+            for (key, record) in chunk {
+                push_number(&mut block, version, key_id);
+                block.extend_from_slice(&encoding.encode(key));
+                block.extend(std::iter::repeat_n(0u8, null_width));
+                key_id += self.record_bytes(record).len() as u64;
+            }
+            let compressed = compress(&block)?;
+
+            let first = encoding.encode(&chunk[0].0);
+            let last = encoding.encode(&chunk[chunk.len() - 1].0);
+            push_key_info_entry(
+                &mut key_info,
+                version,
+                encoding,
+                chunk.len() as u64,
+                [&first, &last],
+                [compressed.len() as u64, block.len() as u64],
+            )?;
+
+            key_blocks.extend_from_slice(&compressed);
+        }
+
+        let key_info_decompressed_len = key_info.len() as u64;
+        if version.major() >= 2 {
+            key_info = compress(&key_info)?;
+            if encrypt_key_info {
+                encrypt_key_info_block(&mut key_info);
+            }
+        }
+
+        let mut preamble = Vec::new();
+        push_number(&mut preamble, version, ranges.len() as u64);
+        push_number(&mut preamble, version, self.entries.len() as u64);
+        if version.major() >= 2 {
+            push_number(&mut preamble, version, key_info_decompressed_len);
+        }
+        push_number(&mut preamble, version, key_info.len() as u64);
+        push_number(&mut preamble, version, key_blocks.len() as u64);
+
+        out.extend_from_slice(&preamble);
+        if version.major() >= 2 {
+            out.extend_from_slice(&adler32(&preamble).to_be_bytes());
+        }
+        out.extend_from_slice(&key_info);
+        out.extend_from_slice(&key_blocks);
+        Ok(())
+    }
+
+    fn write_record_section(
+        &self,
+        out: &mut Vec<u8>,
+        blocks: &[Vec<u8>],
+        compress: &mut impl FnMut(&[u8]) -> Result<Vec<u8>>,
+    ) -> Result<()> {
+        let version = self.version;
+        let mut index = Vec::new();
+        let mut data = Vec::new();
+
+        for block in blocks {
+            let compressed = compress(block)?;
+            push_number(&mut index, version, compressed.len() as u64);
+            push_number(&mut index, version, block.len() as u64);
+            data.extend_from_slice(&compressed);
+        }
+
+        push_number(out, version, blocks.len() as u64);
+        push_number(out, version, self.entries.len() as u64);
+        push_number(out, version, index.len() as u64);
+        push_number(out, version, data.len() as u64);
+        out.extend_from_slice(&index);
+        out.extend_from_slice(&data);
+        Ok(())
+    }
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn push_number(out: &mut Vec<u8>, version: MdictVersion, value: u64) {
+    if version.major() == 1 {
+        out.extend_from_slice(&(value as u32).to_be_bytes());
+    } else {
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+/// Append a key block's key info entry: its entry count, its first and last
+/// key (already encoded), then its compressed and decompressed size.
+pub(crate) fn push_key_info_entry(
+    out: &mut Vec<u8>,
+    version: MdictVersion,
+    encoding: Encoding,
+    num_entries: u64,
+    first_last: [&[u8]; 2],
+    sizes: [u64; 2],
+) -> Result<()> {
+    let null_width = encoding.char_width();
+    push_number(out, version, num_entries);
+    for text in first_last {
+        let units = text.len() / null_width;
+        if version.major() == 1 {
+            let units = u8::try_from(units).map_err(|_| {
+                MDictError::InvalidArgument(format!(
+                    "key too long for V1: '{}'",
+                    encoding.decode(text)
+                ))
+            })?;
+            out.push(units);
+            out.extend_from_slice(text);
+        } else {
+            let units = u16::try_from(units).map_err(|_| {
+                MDictError::InvalidArgument(format!("key too long: '{}'", encoding.decode(text)))
+            })?;
+            out.extend_from_slice(&units.to_be_bytes());
+            out.extend_from_slice(text);
+            out.extend(std::iter::repeat_n(0u8, null_width));
+        }
+    }
+    for size in sizes {
+        push_number(out, version, size);
+    }
+    Ok(())
+}
+
+/// Frame `data` as a compressed block: `u32` LE type, `u32` BE adler32 of the
+/// uncompressed data, then the payload. LZO payloads are a bare lzo1x stream,
+/// as MDict itself writes them.
+pub(crate) fn compress_block(
+    compression: BlockCompression,
+    lzo: Option<&mut Lzo>,
+    data: &[u8],
+) -> Result<Vec<u8>> {
+    let payload = match (compression, lzo) {
+        (BlockCompression::None, _) => data.to_vec(),
+        (BlockCompression::Zlib, _) => miniz_oxide::deflate::compress_to_vec_zlib(data, ZLIB_LEVEL),
+        (BlockCompression::Zstd, _) => {
+            let frame = zstd_compress(data, ZSTD_LEVEL, None)?;
+            let mut payload = Vec::with_capacity(4 + frame.len());
+            payload.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            payload.extend_from_slice(&frame);
+            payload
+        }
+        (BlockCompression::Lzo, Some(lzo)) => lzo.compress(data)?,
+        (BlockCompression::Lzo, None) => {
+            return Err(MDictError::InvalidArgument(
+                "LZO is not initialized".to_string(),
+            ))
+        }
+    };
+
+    let mut out = Vec::with_capacity(8 + payload.len());
+    out.extend_from_slice(&compression.block_type().to_le_bytes());
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out.extend_from_slice(&payload);
+    Ok(out)
+}
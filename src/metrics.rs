@@ -0,0 +1,209 @@
+//! Counters and timings for the decode and search paths, for profiling
+//! lookups on-device.
+//!
+//! Recording is compiled in only with the `tracing` feature. With it, each
+//! block decode, cache lookup and search is counted and emitted as a
+//! trace-level `tracing` event with its duration, inside the spans of the key
+//! lookups, block decodes and record reads that caused it; without it, the
+//! hooks are empty and [`decode_metrics`] stays at zero.
+
+#[cfg(feature = "tracing")]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A section whose blocks are decoded and cached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BlockKind {
+    /// MDX/MDD key blocks.
+    Key,
+    /// MDX/MDD record blocks.
+    Record,
+    /// Readings and record blocks of an optimized index.
+    Packed,
+}
+
+/// Totals since start-up or the last [`reset_decode_metrics`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, uniffi::Record)]
+pub struct DecodeMetrics {
+    pub key_block_decodes: u64,
+    pub record_block_decodes: u64,
+    pub packed_block_decodes: u64,
+    /// Decoded size of all decoded blocks.
+    pub bytes_decompressed: u64,
+    pub decode_nanos: u64,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    pub searches: u64,
+    pub search_nanos: u64,
+}
+
+impl DecodeMetrics {
+    /// Share of block lookups served from a cache, or 0 before any lookup.
+    pub fn cache_hit_ratio(&self) -> f64 {
+        let lookups = self.cache_hits + self.cache_misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.cache_hits as f64 / lookups as f64
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+struct Counters {
+    key_block_decodes: AtomicU64,
+    record_block_decodes: AtomicU64,
+    packed_block_decodes: AtomicU64,
+    bytes_decompressed: AtomicU64,
+    decode_nanos: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    searches: AtomicU64,
+    search_nanos: AtomicU64,
+}
+
+#[cfg(feature = "tracing")]
+static COUNTERS: Counters = Counters {
+    key_block_decodes: AtomicU64::new(0),
+    record_block_decodes: AtomicU64::new(0),
+    packed_block_decodes: AtomicU64::new(0),
+    bytes_decompressed: AtomicU64::new(0),
+    decode_nanos: AtomicU64::new(0),
+    cache_hits: AtomicU64::new(0),
+    cache_misses: AtomicU64::new(0),
+    searches: AtomicU64::new(0),
+    search_nanos: AtomicU64::new(0),
+};
+
+#[cfg(feature = "tracing")]
+impl Counters {
+    fn all(&self) -> [&AtomicU64; 9] {
+        [
+            &self.key_block_decodes,
+            &self.record_block_decodes,
+            &self.packed_block_decodes,
+            &self.bytes_decompressed,
+            &self.decode_nanos,
+            &self.cache_hits,
+            &self.cache_misses,
+            &self.searches,
+            &self.search_nanos,
+        ]
+    }
+}
+
+/// The current totals. All zero unless built with the `tracing` feature.
+#[uniffi::export]
+pub fn decode_metrics() -> DecodeMetrics {
+    #[cfg(feature = "tracing")]
+    {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        DecodeMetrics {
+            key_block_decodes: load(&COUNTERS.key_block_decodes),
+            record_block_decodes: load(&COUNTERS.record_block_decodes),
+            packed_block_decodes: load(&COUNTERS.packed_block_decodes),
+            bytes_decompressed: load(&COUNTERS.bytes_decompressed),
+            decode_nanos: load(&COUNTERS.decode_nanos),
+            cache_hits: load(&COUNTERS.cache_hits),
+            cache_misses: load(&COUNTERS.cache_misses),
+            searches: load(&COUNTERS.searches),
+            search_nanos: load(&COUNTERS.search_nanos),
+        }
+    }
+    #[cfg(not(feature = "tracing"))]
+    DecodeMetrics::default()
+}
+
+#[uniffi::export]
+pub fn reset_decode_metrics() {
+    #[cfg(feature = "tracing")]
+    for counter in COUNTERS.all() {
+        counter.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Count a block cache lookup.
+#[inline]
+pub(crate) fn cache_lookup(kind: BlockKind, hit: bool) {
+    #[cfg(feature = "tracing")]
+    {
+        let counter = if hit {
+            &COUNTERS.cache_hits
+        } else {
+            &COUNTERS.cache_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        tracing::trace!(?kind, hit, "block cache lookup");
+    }
+    #[cfg(not(feature = "tracing"))]
+    let _ = (kind, hit);
+}
+
+/// A timed block decode or search, finished with one of its methods.
+pub(crate) struct Span {
+    #[cfg(feature = "tracing")]
+    start: std::time::Instant,
+}
+
+impl Span {
+    #[inline]
+    pub(crate) fn start() -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            start: std::time::Instant::now(),
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    fn elapsed_nanos(&self) -> u64 {
+        u64::try_from(self.start.elapsed().as_nanos()).unwrap_or(u64::MAX)
+    }
+
+    /// Count block `index` of `kind` as decoded to `bytes` bytes.
+    #[inline]
+    pub(crate) fn block_decoded(self, kind: BlockKind, index: usize, bytes: usize) {
+        #[cfg(feature = "tracing")]
+        {
+            let nanos = self.elapsed_nanos();
+            let decodes = match kind {
+                BlockKind::Key => &COUNTERS.key_block_decodes,
+                BlockKind::Record => &COUNTERS.record_block_decodes,
+                BlockKind::Packed => &COUNTERS.packed_block_decodes,
+            };
+            decodes.fetch_add(1, Ordering::Relaxed);
+            COUNTERS
+                .bytes_decompressed
+                .fetch_add(bytes as u64, Ordering::Relaxed);
+            COUNTERS.decode_nanos.fetch_add(nanos, Ordering::Relaxed);
+            tracing::trace!(
+                ?kind,
+                index,
+                bytes,
+                micros = nanos / 1_000,
+                "block decoded"
+            );
+        }
+        #[cfg(not(feature = "tracing"))]
+        let _ = (self, kind, index, bytes);
+    }
+
+    /// Count a `search` for `query` that found `results` results.
+    #[cfg(feature = "mmap")]
+    #[inline]
+    pub(crate) fn search_finished(self, search: &str, query: &str, results: usize) {
+        #[cfg(feature = "tracing")]
+        {
+            let nanos = self.elapsed_nanos();
+            COUNTERS.searches.fetch_add(1, Ordering::Relaxed);
+            COUNTERS.search_nanos.fetch_add(nanos, Ordering::Relaxed);
+            tracing::trace!(
+                search,
+                query,
+                results,
+                micros = nanos / 1_000,
+                "search finished"
+            );
+        }
+        #[cfg(not(feature = "tracing"))]
+        let _ = (self, search, query, results);
+    }
+}
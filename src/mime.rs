@@ -0,0 +1,85 @@
+//! Content types for MDD resources, for serving them to a WebView.
+//!
+//! MDD keys usually carry an extension, but not always a truthful one, so
+//! [`mime_type_for`] trusts the file's magic bytes first.
+
+/// MIME type of the resource `data` stored under `path`: from its magic
+/// bytes when they are recognised, otherwise from its extension.
+pub fn mime_type_for(path: &str, data: &[u8]) -> &'static str {
+    sniff_mime_type(data).unwrap_or_else(|| mime_type_for_path(path))
+}
+
+/// MIME type of `data` from its leading bytes, for the binary formats
+/// dictionaries ship and for SVG.
+pub fn sniff_mime_type(data: &[u8]) -> Option<&'static str> {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xFF\xD8\xFF", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"wOFF", "font/woff"),
+        (b"wOF2", "font/woff2"),
+        (b"OTTO", "font/otf"),
+        (b"\x00\x01\x00\x00", "font/ttf"),
+        (b"OggS", "audio/ogg"),
+        (b"ID3", "audio/mpeg"),
+        (b"fLaC", "audio/flac"),
+    ];
+    if let Some((_, mime_type)) = SIGNATURES
+        .iter()
+        .find(|(signature, _)| data.starts_with(signature))
+    {
+        return Some(mime_type);
+    }
+    if data.len() >= 12 && data.starts_with(b"RIFF") {
+        match &data[8..12] {
+            b"WEBP" => return Some("image/webp"),
+            b"WAVE" => return Some("audio/wav"),
+            _ => {}
+        }
+    }
+    // MPEG audio frame sync without an ID3 tag.
+    if data.len() >= 2 && data[0] == 0xFF && data[1] & 0xE6 == 0xE2 {
+        return Some("audio/mpeg");
+    }
+
+    let head = &data[..data.len().min(1024)];
+    let text = String::from_utf8_lossy(head);
+    let text = text.trim_start_matches('\u{FEFF}').trim_start();
+    if text.starts_with("<svg") || (text.starts_with("<?xml") && text.contains("<svg")) {
+        return Some("image/svg+xml");
+    }
+    None
+}
+
+/// MIME type of a resource named `path`, from its extension. Unknown
+/// extensions are `application/octet-stream`.
+pub fn mime_type_for_path(path: &str) -> &'static str {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    let extension = name
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("webp") => "image/webp",
+        Some("bmp") => "image/bmp",
+        Some("ico") => "image/x-icon",
+        Some("css") => "text/css",
+        Some("js") => "text/javascript",
+        Some("html" | "htm") => "text/html",
+        Some("txt") => "text/plain",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("ttf") => "font/ttf",
+        Some("otf") => "font/otf",
+        Some("mp3") => "audio/mpeg",
+        Some("spx") => "audio/ogg",
+        Some("ogg" | "oga") => "audio/ogg",
+        Some("wav") => "audio/wav",
+        Some("m4a" | "aac") => "audio/mp4",
+        _ => "application/octet-stream",
+    }
+}
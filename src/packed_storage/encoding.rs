@@ -1,4 +1,7 @@
 use crate::error::{MDictError, Result};
+use crate::format::codecs::{zstd_compress, zstd_decompress, Lzo};
+
+use super::lz4;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
@@ -8,6 +11,9 @@ pub enum CompressionEncoding {
     Gzip = 2,
     Zstd = 3,
     Lz4 = 4,
+    /// Needs the `brotli` feature; without it blocks are recognised but
+    /// encoding and decoding report `UnsupportedFeature`.
+    Brotli = 5,
 }
 
 impl CompressionEncoding {
@@ -18,6 +24,7 @@ impl CompressionEncoding {
             2 => Ok(Self::Gzip),
             3 => Ok(Self::Zstd),
             4 => Ok(Self::Lz4),
+            5 => Ok(Self::Brotli),
             _ => Err(MDictError::InvalidFormat(format!(
                 "unsupported compression encoding id: {}",
                 value
@@ -34,6 +41,17 @@ pub fn encode_block(
     encoding: CompressionEncoding,
     compression_level: u8,
     data: &[u8],
+) -> Result<Vec<u8>> {
+    encode_block_with_dictionary(encoding, compression_level, data, None)
+}
+
+/// Like [`encode_block`], compressing zstd blocks against `dictionary` when
+/// one is given. Other encodings ignore the dictionary.
+pub fn encode_block_with_dictionary(
+    encoding: CompressionEncoding,
+    compression_level: u8,
+    data: &[u8],
+    dictionary: Option<&[u8]>,
 ) -> Result<Vec<u8>> {
     match encoding {
         CompressionEncoding::Raw => Ok(data.to_vec()),
@@ -43,14 +61,25 @@ pub fn encode_block(
             } else {
                 compression_level.min(10) as i32
             };
-            zstd::bulk::compress(data, mapped_level)
-                .map_err(|e| MDictError::InvalidFormat(e.to_string()))
+            zstd_compress(data, mapped_level, dictionary)
         }
-        CompressionEncoding::Lzo | CompressionEncoding::Gzip | CompressionEncoding::Lz4 => {
-            Err(MDictError::UnsupportedFeature(format!(
-                "encoder not implemented for {:?}",
-                encoding
-            )))
+        CompressionEncoding::Lzo => Lzo::init()?.compress(data),
+        CompressionEncoding::Gzip => {
+            let mapped_level = if compression_level == 0 {
+                6
+            } else {
+                compression_level.min(10)
+            };
+            Ok(gzip_encode(data, mapped_level))
+        }
+        CompressionEncoding::Lz4 => Ok(lz4::compress(data)),
+        CompressionEncoding::Brotli => {
+            let quality = if compression_level == 0 {
+                9
+            } else {
+                compression_level.min(11)
+            };
+            brotli_encode(data, quality)
         }
     }
 }
@@ -59,16 +88,170 @@ pub fn decode_block(
     encoding: CompressionEncoding,
     compressed: &[u8],
     expected_uncompressed_size: usize,
+) -> Result<Vec<u8>> {
+    decode_block_with_dictionary(encoding, compressed, expected_uncompressed_size, None)
+}
+
+/// Like [`decode_block`], for zstd blocks compressed against `dictionary`.
+pub fn decode_block_with_dictionary(
+    encoding: CompressionEncoding,
+    compressed: &[u8],
+    expected_uncompressed_size: usize,
+    dictionary: Option<&[u8]>,
 ) -> Result<Vec<u8>> {
     match encoding {
         CompressionEncoding::Raw => Ok(compressed.to_vec()),
-        CompressionEncoding::Zstd => zstd::bulk::decompress(compressed, expected_uncompressed_size)
-            .map_err(|e| MDictError::InvalidFormat(e.to_string())),
-        CompressionEncoding::Lzo | CompressionEncoding::Gzip | CompressionEncoding::Lz4 => {
-            Err(MDictError::UnsupportedFeature(format!(
-                "decoder not implemented for {:?}",
-                encoding
-            )))
+        CompressionEncoding::Zstd => {
+            zstd_decompress(compressed, expected_uncompressed_size, dictionary)
         }
+        CompressionEncoding::Lzo => Lzo::init()?.decompress(compressed, expected_uncompressed_size),
+        CompressionEncoding::Gzip => gzip_decode(compressed, expected_uncompressed_size),
+        CompressionEncoding::Lz4 => lz4::decompress(compressed, expected_uncompressed_size),
+        CompressionEncoding::Brotli => brotli_decode(compressed, expected_uncompressed_size),
+    }
+}
+
+#[cfg(feature = "brotli")]
+fn brotli_encode(data: &[u8], quality: u8) -> Result<Vec<u8>> {
+    use std::io::Write;
+
+    let mut writer = brotli::CompressorWriter::new(
+        Vec::with_capacity(data.len() / 4),
+        BROTLI_BUFFER_SIZE,
+        quality.into(),
+        BROTLI_WINDOW_BITS,
+    );
+    writer.write_all(data)?;
+    Ok(writer.into_inner())
+}
+
+#[cfg(feature = "brotli")]
+fn brotli_decode(compressed: &[u8], expected_uncompressed_size: usize) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    let mut data = Vec::with_capacity(expected_uncompressed_size);
+    brotli::Decompressor::new(compressed, BROTLI_BUFFER_SIZE)
+        .take(expected_uncompressed_size as u64 + 1)
+        .read_to_end(&mut data)
+        .map_err(|e| MDictError::InvalidFormat(format!("brotli: {}", e)))?;
+    if data.len() != expected_uncompressed_size {
+        return Err(MDictError::InvalidFormat(format!(
+            "brotli: decoded {} bytes, expected {}",
+            data.len(),
+            expected_uncompressed_size
+        )));
+    }
+    Ok(data)
+}
+
+#[cfg(feature = "brotli")]
+const BROTLI_BUFFER_SIZE: usize = 4096;
+#[cfg(feature = "brotli")]
+const BROTLI_WINDOW_BITS: u32 = 22;
+
+#[cfg(not(feature = "brotli"))]
+fn brotli_encode(_data: &[u8], _quality: u8) -> Result<Vec<u8>> {
+    Err(brotli_unsupported())
+}
+
+#[cfg(not(feature = "brotli"))]
+fn brotli_decode(_compressed: &[u8], _expected_uncompressed_size: usize) -> Result<Vec<u8>> {
+    Err(brotli_unsupported())
+}
+
+#[cfg(not(feature = "brotli"))]
+fn brotli_unsupported() -> MDictError {
+    MDictError::UnsupportedFeature("Brotli blocks need the `brotli` feature".to_string())
+}
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const GZIP_DEFLATE: u8 = 8;
+const GZIP_FHCRC: u8 = 0x02;
+const GZIP_FEXTRA: u8 = 0x04;
+const GZIP_FNAME: u8 = 0x08;
+const GZIP_FCOMMENT: u8 = 0x10;
+
+/// A single-member gzip stream (RFC 1952) with no optional header fields.
+fn gzip_encode(data: &[u8], level: u8) -> Vec<u8> {
+    let deflated = miniz_oxide::deflate::compress_to_vec(data, level);
+    let mut out = Vec::with_capacity(deflated.len() + 18);
+    out.extend_from_slice(&GZIP_MAGIC);
+    // Deflate, no flags, no mtime, no extra flags, unknown OS.
+    out.extend_from_slice(&[GZIP_DEFLATE, 0, 0, 0, 0, 0, 0, 0xff]);
+    out.extend_from_slice(&deflated);
+    out.extend_from_slice(&crc32(data).to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out
+}
+
+fn gzip_decode(compressed: &[u8], expected_uncompressed_size: usize) -> Result<Vec<u8>> {
+    let corrupt = |what: &str| MDictError::InvalidFormat(format!("gzip: {}", what));
+    if compressed.len() < 18 || compressed[..2] != GZIP_MAGIC || compressed[2] != GZIP_DEFLATE {
+        return Err(corrupt("bad header"));
+    }
+    let flags = compressed[3];
+    let trailer_at = compressed.len() - 8;
+
+    let mut pos = 10;
+    if flags & GZIP_FEXTRA != 0 {
+        let extra_len = compressed
+            .get(pos..pos + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+            .ok_or_else(|| corrupt("truncated header"))?;
+        pos += 2 + extra_len;
+    }
+    for flag in [GZIP_FNAME, GZIP_FCOMMENT] {
+        if flags & flag != 0 {
+            let terminator = compressed
+                .get(pos..trailer_at)
+                .and_then(|rest| rest.iter().position(|&b| b == 0))
+                .ok_or_else(|| corrupt("truncated header"))?;
+            pos += terminator + 1;
+        }
+    }
+    if flags & GZIP_FHCRC != 0 {
+        pos += 2;
+    }
+    let deflated = compressed
+        .get(pos..trailer_at)
+        .ok_or_else(|| corrupt("truncated header"))?;
+
+    let data =
+        miniz_oxide::inflate::decompress_to_vec_with_limit(deflated, expected_uncompressed_size)
+            .map_err(|e| corrupt(&format!("inflate: {:?}", e.status)))?;
+    let trailer = &compressed[trailer_at..];
+    let stored_crc = u32::from_le_bytes(trailer[..4].try_into().unwrap());
+    let stored_len = u32::from_le_bytes(trailer[4..].try_into().unwrap());
+    if stored_crc != crc32(&data) || stored_len != data.len() as u32 {
+        return Err(corrupt("checksum mismatch"));
+    }
+    Ok(data)
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc = CRC32_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
     }
+    !crc
 }
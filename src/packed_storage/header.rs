@@ -8,6 +8,20 @@ use super::CompressionEncoding;
 
 pub const MAGIC: [u8; 8] = *b"PKGSTRG1";
 pub const VERSION: u8 = 1;
+/// Format version of files that use header flags; version 1 readers would
+/// misplace the data section of such files.
+pub const VERSION_WITH_FLAGS: u8 = 2;
+
+/// A trained zstd dictionary follows the prefix table, as a `u32` length and
+/// the dictionary bytes.
+pub const FLAG_ZSTD_DICTIONARY: u8 = 0x01;
+/// Application data follows the prefix table and any zstd dictionary, as a
+/// `u32` length and the bytes. The container does not interpret it.
+pub const FLAG_USER_DATA: u8 = 0x02;
+const KNOWN_FLAGS: u8 = FLAG_ZSTD_DICTIONARY | FLAG_USER_DATA;
+/// Upper bound on an embedded dictionary or user data, to reject corrupt
+/// lengths.
+const MAX_DICTIONARY_SIZE: usize = 16 * 1024 * 1024;
 
 const FIXED_HEADER_SIZE: usize = 0x20;
 
@@ -21,9 +35,17 @@ pub struct BlockPrefixEntry {
 
 #[derive(Debug, Clone, BinRead, BinWrite)]
 #[brw(little)]
-#[br(assert(version == VERSION, "unsupported packed storage version"))]
+#[br(assert(
+    version == VERSION || version == VERSION_WITH_FLAGS,
+    "unsupported packed storage version"
+))]
+#[br(assert(
+    (version == VERSION && flags == 0)
+        || (version == VERSION_WITH_FLAGS && flags & !KNOWN_FLAGS == 0),
+    "unknown packed storage header flags"
+))]
 #[bw(assert(
-    *reserved_flags == 0 && *reserved_flags_padding == 0,
+    *flags & !KNOWN_FLAGS == 0 && *reserved_flags_padding == 0,
     "reserved header flags are not zero"
 ))]
 #[bw(assert(
@@ -54,7 +76,7 @@ pub struct BlockPrefixEntry {
 struct PackedStorageHeaderRaw {
     #[brw(magic(b"PKGSTRG1"))]
     version: u8,
-    reserved_flags: u8,
+    flags: u8,
     reserved_flags_padding: u16,
     encoding: u8,
     compression_level: u8,
@@ -71,6 +93,10 @@ pub struct PackedStorageHeader {
     pub compression_level: u8,
     pub num_entries: u64,
     pub block_prefix_sum: Vec<BlockPrefixEntry>,
+    /// Dictionary every zstd block was compressed with, if any.
+    pub zstd_dictionary: Option<Vec<u8>>,
+    /// Application data stored with the header, if any.
+    pub user_data: Option<Vec<u8>>,
 }
 
 impl PackedStorageHeader {
@@ -80,8 +106,15 @@ impl PackedStorageHeader {
             .len()
             .checked_mul(16)
             .ok_or_else(|| MDictError::InvalidFormat("header size overflow".to_string()))?;
+        let dictionary_bytes = self
+            .zstd_dictionary
+            .as_ref()
+            .map_or(0, |dictionary| 4 + dictionary.len());
+        let user_data_bytes = self.user_data.as_ref().map_or(0, |data| 4 + data.len());
         FIXED_HEADER_SIZE
             .checked_add(prefix_bytes)
+            .and_then(|size| size.checked_add(dictionary_bytes))
+            .and_then(|size| size.checked_add(user_data_bytes))
             .ok_or_else(|| MDictError::InvalidFormat("header size overflow".to_string()))
     }
 
@@ -95,9 +128,20 @@ impl PackedStorageHeader {
         let num_blocks = u64::try_from(self.block_prefix_sum.len())
             .map_err(|_| MDictError::InvalidFormat("num_blocks overflow".to_string()))?;
 
+        let mut flags = 0;
+        if self.zstd_dictionary.is_some() {
+            flags |= FLAG_ZSTD_DICTIONARY;
+        }
+        if self.user_data.is_some() {
+            flags |= FLAG_USER_DATA;
+        }
         let raw = PackedStorageHeaderRaw {
-            version: VERSION,
-            reserved_flags: 0,
+            version: if flags == 0 {
+                VERSION
+            } else {
+                VERSION_WITH_FLAGS
+            },
+            flags,
             reserved_flags_padding: 0,
             encoding: self.encoding.as_u8(),
             compression_level: self.compression_level,
@@ -108,6 +152,12 @@ impl PackedStorageHeader {
         };
 
         raw.write_le(writer)?;
+        if let Some(dictionary) = &self.zstd_dictionary {
+            write_sized(writer, dictionary, "zstd dictionary")?;
+        }
+        if let Some(data) = &self.user_data {
+            write_sized(writer, data, "user data")?;
+        }
         Ok(())
     }
 
@@ -138,8 +188,24 @@ impl PackedStorageHeader {
         let prefix_bytes = num_blocks
             .checked_mul(16)
             .ok_or_else(|| MDictError::InvalidFormat("prefix table size overflow".to_string()))?;
+        let zstd_dictionary = if raw.flags & FLAG_ZSTD_DICTIONARY != 0 {
+            Some(read_sized(reader, "zstd dictionary")?)
+        } else {
+            None
+        };
+        let user_data = if raw.flags & FLAG_USER_DATA != 0 {
+            Some(read_sized(reader, "user data")?)
+        } else {
+            None
+        };
+        let dictionary_bytes = zstd_dictionary
+            .as_ref()
+            .map_or(0, |dictionary| 4 + dictionary.len());
+        let user_data_bytes = user_data.as_ref().map_or(0, |data| 4 + data.len());
         let data_offset = FIXED_HEADER_SIZE
             .checked_add(prefix_bytes)
+            .and_then(|size| size.checked_add(dictionary_bytes))
+            .and_then(|size| size.checked_add(user_data_bytes))
             .ok_or_else(|| {
                 MDictError::InvalidFormat("packed storage header size overflow".to_string())
             })?;
@@ -150,8 +216,38 @@ impl PackedStorageHeader {
                 compression_level: raw.compression_level,
                 num_entries: raw.num_entries,
                 block_prefix_sum: raw.block_prefix_sum,
+                zstd_dictionary,
+                user_data,
             },
             data_offset,
         ))
     }
 }
+
+fn write_sized<W: Write>(writer: &mut W, data: &[u8], what: &str) -> Result<()> {
+    let len = u32::try_from(data.len())
+        .ok()
+        .filter(|&len| len as usize <= MAX_DICTIONARY_SIZE)
+        .ok_or_else(|| MDictError::InvalidArgument(format!("{} is too large", what)))?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(data)?;
+    Ok(())
+}
+
+fn read_sized<R: Read>(reader: &mut R, what: &str) -> Result<Vec<u8>> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_DICTIONARY_SIZE {
+        return Err(MDictError::InvalidFormat(format!(
+            "{} of {} bytes exceeds the {} byte limit",
+            what, len, MAX_DICTIONARY_SIZE
+        )));
+    }
+    let mut data = Vec::new();
+    reader.take(len as u64).read_to_end(&mut data)?;
+    if data.len() != len {
+        return Err(MDictError::InvalidFormat(format!("truncated {}", what)));
+    }
+    Ok(data)
+}
@@ -1,8 +1,9 @@
 use std::io::{Read, Seek, SeekFrom};
+use std::sync::Arc;
 
 use crate::error::{MDictError, Result};
 
-use super::{decode_block, BlockPrefixEntry, PackedStorageHeader};
+use super::{decode_block_with_dictionary, BlockPrefixEntry, PackedStorageHeader};
 
 #[derive(Debug, Clone)]
 pub struct PackedStorageIndex {
@@ -148,7 +149,12 @@ impl PackedStorageIndex {
         reader.read_exact(&mut compressed)?;
 
         let expected_size = plan.uncompressed_end - plan.uncompressed_start;
-        let bytes = decode_block(self.header.encoding, &compressed, expected_size)?;
+        let bytes = decode_block_with_dictionary(
+            self.header.encoding,
+            &compressed,
+            expected_size,
+            self.header.zstd_dictionary.as_deref(),
+        )?;
 
         Ok(DecodedBlock {
             block_pos: plan.block_pos,
@@ -176,97 +182,113 @@ impl PackedStorageIndex {
         terminator: Option<&[u8]>,
         record_size: Option<u64>,
     ) -> Result<Vec<u8>> {
-        if terminator.is_none() && record_size.is_none() {
+        read_blocks_from_offset(self, start_offset, terminator, record_size, |offset| {
+            Ok(self
+                .decode_block_at_offset_from_reader(reader, offset)?
+                .map(Arc::new))
+        })
+    }
+}
+
+/// Read from `start_offset` until `terminator` (exclusive) or `record_size`
+/// bytes, whichever comes first, taking blocks from `block_at`.
+pub(super) fn read_blocks_from_offset(
+    index: &PackedStorageIndex,
+    start_offset: u64,
+    terminator: Option<&[u8]>,
+    record_size: Option<u64>,
+    mut block_at: impl FnMut(u64) -> Result<Option<Arc<DecodedBlock>>>,
+) -> Result<Vec<u8>> {
+    if terminator.is_none() && record_size.is_none() {
+        return Err(MDictError::InvalidArgument(
+            "either terminator or record_size must be provided".to_string(),
+        ));
+    }
+
+    if let Some(term) = terminator {
+        if term.is_empty() {
             return Err(MDictError::InvalidArgument(
-                "either terminator or record_size must be provided".to_string(),
+                "terminator must not be empty".to_string(),
             ));
         }
+    }
 
-        if let Some(term) = terminator {
-            if term.is_empty() {
-                return Err(MDictError::InvalidArgument(
-                    "terminator must not be empty".to_string(),
-                ));
-            }
-        }
-
-        let total_uncompressed = self.total_uncompressed_size().ok_or_else(|| {
-            MDictError::InvalidFormat("missing total uncompressed size".to_string())
-        })?;
-
-        if start_offset >= total_uncompressed {
-            return Err(MDictError::InvalidArgument(format!(
-                "start_offset {} is out of bounds for total size {}",
-                start_offset, total_uncompressed
-            )));
-        }
+    let total_uncompressed = index
+        .total_uncompressed_size()
+        .ok_or_else(|| MDictError::InvalidFormat("missing total uncompressed size".to_string()))?;
 
-        let mut out = Vec::new();
-        let mut current_offset = start_offset;
-        let mut remaining = record_size;
+    if start_offset >= total_uncompressed {
+        return Err(MDictError::InvalidArgument(format!(
+            "start_offset {} is out of bounds for total size {}",
+            start_offset, total_uncompressed
+        )));
+    }
 
-        while current_offset < total_uncompressed {
-            if matches!(remaining, Some(0)) {
-                break;
-            }
+    let mut out = Vec::new();
+    let mut current_offset = start_offset;
+    let mut remaining = record_size;
 
-            let Some(decoded_block) = self.decode_block_at_offset_from_reader(reader, current_offset)? else {
-                break;
-            };
+    while current_offset < total_uncompressed {
+        if matches!(remaining, Some(0)) {
+            break;
+        }
 
-            let local_start = usize::try_from(current_offset)
-                .ok()
-                .and_then(|absolute| absolute.checked_sub(decoded_block.uncompressed_start))
-                .ok_or_else(|| MDictError::InvalidFormat("local offset overflow".to_string()))?;
+        let Some(decoded_block) = block_at(current_offset)? else {
+            break;
+        };
 
-            if local_start > decoded_block.bytes.len() {
-                return Err(MDictError::InvalidFormat(
-                    "local start exceeds decoded block size".to_string(),
-                ));
-            }
+        let local_start = usize::try_from(current_offset)
+            .ok()
+            .and_then(|absolute| absolute.checked_sub(decoded_block.uncompressed_start))
+            .ok_or_else(|| MDictError::InvalidFormat("local offset overflow".to_string()))?;
 
-            let chunk = &decoded_block.bytes[local_start..];
-            if chunk.is_empty() {
-                current_offset = decoded_block.uncompressed_end as u64;
-                continue;
-            }
+        if local_start > decoded_block.bytes.len() {
+            return Err(MDictError::InvalidFormat(
+                "local start exceeds decoded block size".to_string(),
+            ));
+        }
 
-            let mut take = chunk.len();
-            if let Some(left) = remaining {
-                let left_usize = usize::try_from(left.min(usize::MAX as u64))
-                    .map_err(|_| MDictError::InvalidFormat("record size overflow".to_string()))?;
-                take = take.min(left_usize);
-            }
+        let chunk = &decoded_block.bytes[local_start..];
+        if chunk.is_empty() {
+            current_offset = decoded_block.uncompressed_end as u64;
+            continue;
+        }
 
-            if take == 0 {
-                break;
-            }
+        let mut take = chunk.len();
+        if let Some(left) = remaining {
+            let left_usize = usize::try_from(left.min(usize::MAX as u64))
+                .map_err(|_| MDictError::InvalidFormat("record size overflow".to_string()))?;
+            take = take.min(left_usize);
+        }
 
-            let prev_len = out.len();
-            out.extend_from_slice(&chunk[..take]);
-            current_offset = current_offset.saturating_add(take as u64);
+        if take == 0 {
+            break;
+        }
 
-            if let Some(left) = remaining.as_mut() {
-                *left = left.saturating_sub(take as u64);
-            }
+        let prev_len = out.len();
+        out.extend_from_slice(&chunk[..take]);
+        current_offset = current_offset.saturating_add(take as u64);
 
-            if let Some(term) = terminator {
-                let search_from = prev_len.saturating_sub(term.len().saturating_sub(1));
-                if let Some(pos_rel) = out[search_from..]
-                    .windows(term.len())
-                    .position(|window| window == term)
-                {
-                    let term_pos = search_from + pos_rel;
-                    out.truncate(term_pos);
-                    return Ok(out);
-                }
-            }
+        if let Some(left) = remaining.as_mut() {
+            *left = left.saturating_sub(take as u64);
+        }
 
-            if matches!(remaining, Some(0)) {
-                break;
+        if let Some(term) = terminator {
+            let search_from = prev_len.saturating_sub(term.len().saturating_sub(1));
+            if let Some(pos_rel) = out[search_from..]
+                .windows(term.len())
+                .position(|window| window == term)
+            {
+                let term_pos = search_from + pos_rel;
+                out.truncate(term_pos);
+                return Ok(out);
             }
         }
 
-        Ok(out)
+        if matches!(remaining, Some(0)) {
+            break;
+        }
     }
+
+    Ok(out)
 }
@@ -0,0 +1,163 @@
+//! LZ4 block format (no frame header), as described in the reference
+//! `lz4_Block_format.md`. The encoder is a single-pass greedy matcher; it
+//! favours decode speed over ratio, which is what LZ4 is for.
+
+use crate::error::{MDictError, Result};
+
+const MIN_MATCH: usize = 4;
+/// The last five bytes of a block are always literals.
+const LAST_LITERALS: usize = 5;
+/// A match may not start within the last twelve bytes of a block.
+const MF_LIMIT: usize = 12;
+const MAX_OFFSET: usize = 0xFFFF;
+const HASH_BITS: u32 = 16;
+
+fn read_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+}
+
+fn hash(sequence: u32) -> usize {
+    (sequence.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize
+}
+
+fn push_length(out: &mut Vec<u8>, mut length: usize) {
+    while length >= 255 {
+        out.push(255);
+        length -= 255;
+    }
+    out.push(length as u8);
+}
+
+fn push_sequence(out: &mut Vec<u8>, literals: &[u8], offset: usize, match_len: usize) {
+    let extra_match = match_len - MIN_MATCH;
+    let token = ((literals.len().min(15) as u8) << 4) | extra_match.min(15) as u8;
+    out.push(token);
+    if literals.len() >= 15 {
+        push_length(out, literals.len() - 15);
+    }
+    out.extend_from_slice(literals);
+    out.extend_from_slice(&(offset as u16).to_le_bytes());
+    if extra_match >= 15 {
+        push_length(out, extra_match - 15);
+    }
+}
+
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len() + input.len() / 255 + 16);
+    // Position + 1 of the last sequence seen with each hash; 0 means none.
+    let mut table = vec![0usize; 1 << HASH_BITS];
+    let match_start_limit = input.len().saturating_sub(MF_LIMIT);
+    let match_end_limit = input.len().saturating_sub(LAST_LITERALS);
+
+    let mut anchor = 0;
+    let mut pos = 0;
+    while pos < match_start_limit {
+        let sequence = read_u32(input, pos);
+        let slot = &mut table[hash(sequence)];
+        let candidate = slot.checked_sub(1);
+        *slot = pos + 1;
+
+        let Some(candidate) = candidate.filter(|&candidate| {
+            pos - candidate <= MAX_OFFSET && read_u32(input, candidate) == sequence
+        }) else {
+            pos += 1;
+            continue;
+        };
+
+        let mut match_len = MIN_MATCH;
+        while pos + match_len < match_end_limit
+            && input[candidate + match_len] == input[pos + match_len]
+        {
+            match_len += 1;
+        }
+        push_sequence(&mut out, &input[anchor..pos], pos - candidate, match_len);
+        pos += match_len;
+        anchor = pos;
+    }
+
+    let literals = &input[anchor..];
+    out.push((literals.len().min(15) as u8) << 4);
+    if literals.len() >= 15 {
+        push_length(&mut out, literals.len() - 15);
+    }
+    out.extend_from_slice(literals);
+    out
+}
+
+fn read_length(input: &[u8], pos: &mut usize, mut length: usize) -> Result<usize> {
+    loop {
+        let byte = *input
+            .get(*pos)
+            .ok_or_else(|| MDictError::InvalidFormat("LZ4: truncated length".to_string()))?;
+        *pos += 1;
+        length = length
+            .checked_add(byte as usize)
+            .ok_or_else(|| MDictError::InvalidFormat("LZ4: length overflow".to_string()))?;
+        if byte != 255 {
+            return Ok(length);
+        }
+    }
+}
+
+/// Decode a block that must expand to exactly `uncompressed_size` bytes.
+pub fn decompress(input: &[u8], uncompressed_size: usize) -> Result<Vec<u8>> {
+    let corrupt = |what: &str| MDictError::InvalidFormat(format!("LZ4: {}", what));
+    let mut out = Vec::with_capacity(uncompressed_size);
+    let mut pos = 0;
+
+    loop {
+        let token = *input.get(pos).ok_or_else(|| corrupt("truncated block"))?;
+        pos += 1;
+
+        let mut literal_len = (token >> 4) as usize;
+        if literal_len == 15 {
+            literal_len = read_length(input, &mut pos, literal_len)?;
+        }
+        let literals = pos
+            .checked_add(literal_len)
+            .and_then(|end| input.get(pos..end))
+            .ok_or_else(|| corrupt("literals run past the block"))?;
+        if out.len() + literals.len() > uncompressed_size {
+            return Err(corrupt("output larger than expected"));
+        }
+        out.extend_from_slice(literals);
+        pos += literal_len;
+
+        if pos == input.len() {
+            break;
+        }
+
+        let offset = input
+            .get(pos..pos + 2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+            .ok_or_else(|| corrupt("truncated match offset"))?;
+        pos += 2;
+        if offset == 0 || offset > out.len() {
+            return Err(corrupt("match offset out of range"));
+        }
+
+        let mut match_len = (token & 0x0F) as usize;
+        if match_len == 15 {
+            match_len = read_length(input, &mut pos, match_len)?;
+        }
+        match_len += MIN_MATCH;
+        if out.len() + match_len > uncompressed_size {
+            return Err(corrupt("output larger than expected"));
+        }
+
+        // Byte by byte: the match may overlap the bytes it is producing.
+        let start = out.len() - offset;
+        for i in 0..match_len {
+            out.push(out[start + i]);
+        }
+    }
+
+    if out.len() != uncompressed_size {
+        return Err(MDictError::InvalidFormat(format!(
+            "LZ4: decoded {} bytes, expected {}",
+            out.len(),
+            uncompressed_size
+        )));
+    }
+    Ok(out)
+}
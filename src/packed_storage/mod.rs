@@ -1,11 +1,20 @@
 mod encoding;
 mod header;
 mod index;
+mod lz4;
+mod reader;
 mod writer;
 
-pub use encoding::{decode_block, encode_block, CompressionEncoding};
-pub use header::{BlockPrefixEntry, PackedStorageHeader, MAGIC, VERSION};
+pub use encoding::{
+    decode_block, decode_block_with_dictionary, encode_block, encode_block_with_dictionary,
+    CompressionEncoding,
+};
+pub use header::{
+    BlockPrefixEntry, PackedStorageHeader, FLAG_USER_DATA, FLAG_ZSTD_DICTIONARY, MAGIC, VERSION,
+    VERSION_WITH_FLAGS,
+};
 pub use index::{DecodedBlock, PackedStorageIndex, ScanControl};
+pub use reader::PackedStorageReader;
 pub use writer::PackedStorageWriter;
 
 #[cfg(test)]
@@ -0,0 +1,142 @@
+use std::io::{Read, Seek, SeekFrom};
+use std::sync::Arc;
+
+use crate::block_cache::{BlockCache, CacheCapacity};
+use crate::error::{MDictError, Result};
+use crate::metrics::{self, BlockKind, Span};
+
+use super::index::read_blocks_from_offset;
+use super::{DecodedBlock, PackedStorageIndex};
+
+/// A packed storage container together with its source and a cache of
+/// decoded blocks, for repeated random reads.
+pub struct PackedStorageReader<R> {
+    index: PackedStorageIndex,
+    reader: R,
+    cache: BlockCache<Arc<DecodedBlock>>,
+}
+
+impl<R: Read + Seek> PackedStorageReader<R> {
+    /// Parse the container header at the current position of `reader`.
+    pub fn new(mut reader: R, cache_capacity: impl Into<CacheCapacity>) -> Result<Self> {
+        let index = PackedStorageIndex::parse_from_reader(&mut reader)?;
+        Ok(Self {
+            index,
+            reader,
+            cache: BlockCache::new(cache_capacity),
+        })
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    pub fn index(&self) -> &PackedStorageIndex {
+        &self.index
+    }
+
+    pub fn num_entries(&self) -> u64 {
+        self.index.header.num_entries
+    }
+
+    pub fn total_uncompressed_size(&self) -> u64 {
+        self.index.total_uncompressed_size().unwrap_or(0)
+    }
+
+    /// The decoded block holding `offset`, or `None` past the end.
+    pub fn block_at_offset(&mut self, offset: u64) -> Result<Option<Arc<DecodedBlock>>> {
+        cached_block_at_offset(&self.index, &mut self.reader, &mut self.cache, offset)
+    }
+
+    /// Exactly `len` bytes starting at `offset`, across blocks if needed.
+    pub fn read_at(&mut self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+        let bytes = self.read_from_offset_with_options(offset, None, Some(len as u64))?;
+        if bytes.len() != len {
+            return Err(MDictError::InvalidFormat(format!(
+                "packed storage read of {} bytes at offset {} ended after {}",
+                len,
+                offset,
+                bytes.len()
+            )));
+        }
+        Ok(bytes)
+    }
+
+    /// See [`PackedStorageIndex::read_from_offset_with_options`]; blocks come
+    /// from the cache when possible.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self)))]
+    pub fn read_from_offset_with_options(
+        &mut self,
+        start_offset: u64,
+        terminator: Option<&[u8]>,
+        record_size: Option<u64>,
+    ) -> Result<Vec<u8>> {
+        let Self {
+            index,
+            reader,
+            cache,
+        } = self;
+        read_blocks_from_offset(index, start_offset, terminator, record_size, |offset| {
+            cached_block_at_offset(index, reader, cache, offset)
+        })
+    }
+
+    /// Decode block `block_pos`, bypassing the cache.
+    pub fn decode_block(&mut self, block_pos: usize) -> Result<DecodedBlock> {
+        self.index.decode_block_from_reader(&mut self.reader, block_pos)
+    }
+
+    /// The compressed bytes of block `block_pos` as stored, for copying the
+    /// block into a container with the same encoding and zstd dictionary.
+    pub fn compressed_block(&mut self, block_pos: usize) -> Result<Vec<u8>> {
+        let plan = self.index.index_block_for_reader(block_pos)?;
+        let len = usize::try_from(plan.file_end - plan.file_start)
+            .map_err(|_| MDictError::InvalidFormat("compressed size overflow".to_string()))?;
+        let mut compressed = vec![0u8; len];
+        self.reader.seek(SeekFrom::Start(plan.file_start))?;
+        self.reader.read_exact(&mut compressed)?;
+        Ok(compressed)
+    }
+
+    /// Decode every block, reporting the first one that fails.
+    pub fn verify(&mut self) -> Result<()> {
+        for block_pos in 1..self.index.header.block_prefix_sum.len() {
+            self.index
+                .decode_block_from_reader(&mut self.reader, block_pos)
+                .map_err(|e| {
+                    MDictError::InvalidFormat(format!("packed block {}: {}", block_pos, e))
+                })?;
+        }
+        Ok(())
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(index, reader, cache)))]
+fn cached_block_at_offset<R: Read + Seek>(
+    index: &PackedStorageIndex,
+    reader: &mut R,
+    cache: &mut BlockCache<Arc<DecodedBlock>>,
+    offset: u64,
+) -> Result<Option<Arc<DecodedBlock>>> {
+    let Some(block_pos) = index.find_block_pos(offset) else {
+        return Ok(None);
+    };
+    if let Some(block) = cache.get(block_pos) {
+        metrics::cache_lookup(BlockKind::Packed, true);
+        return Ok(Some(Arc::clone(block)));
+    }
+    metrics::cache_lookup(BlockKind::Packed, false);
+
+    let span = Span::start();
+    let block = Arc::new(index.decode_block_from_reader(reader, block_pos)?);
+    span.block_decoded(BlockKind::Packed, block_pos, block.bytes.len());
+    cache.insert(block_pos, Arc::clone(&block), block.bytes.len());
+    Ok(Some(block))
+}
@@ -1,16 +1,25 @@
 #[cfg(test)]
 mod packed_storage_tests {
+    #[cfg(feature = "zstd")]
     use std::env;
+    #[cfg(feature = "zstd")]
     use std::fs::{create_dir_all, File};
     use std::io::{Cursor, Seek, SeekFrom};
+    #[cfg(feature = "zstd")]
     use std::path::PathBuf;
 
-    use super::super::{CompressionEncoding, PackedStorageIndex, PackedStorageWriter};
+    use super::super::{
+        decode_block, encode_block, CompressionEncoding, PackedStorageIndex, PackedStorageReader,
+        PackedStorageWriter,
+    };
+    #[cfg(feature = "zstd")]
+    use super::super::{VERSION, VERSION_WITH_FLAGS};
 
     fn entries() -> Vec<Vec<u8>> {
         vec![b"aaaa".to_vec(), b"bbbb".to_vec(), b"cccc".to_vec()]
     }
 
+    #[cfg(feature = "zstd")]
     fn test_output_dir() -> PathBuf {
         let base = env::var("TEST_OUTPUT_DIR")
             .or_else(|_| env::var("MDICT_TEST_OUTPUT_DIR"))
@@ -73,6 +82,7 @@ mod packed_storage_tests {
         assert_roundtrip_entries(&bytes, &offsets, &entries, 2);
     }
 
+    #[cfg(feature = "zstd")]
     #[test]
     fn packed_storage_multiple_blocks_round_trip() {
         let entries = entries();
@@ -120,6 +130,7 @@ mod packed_storage_tests {
         assert_eq!(result, b"bcde");
     }
 
+    #[cfg(feature = "zstd")]
     #[test]
     fn packed_storage_write_file_and_reread_entries() {
         let output_dir = test_output_dir();
@@ -150,4 +161,267 @@ mod packed_storage_tests {
             assert_eq!(&actual, expected);
         }
     }
+
+    #[cfg(any(feature = "brotli", all(feature = "lzo", feature = "zstd")))]
+    fn sample_payloads() -> Vec<Vec<u8>> {
+        let html = "<div class=\"entry\"><span class=\"hw\">word</span><p>definition</p></div>\n";
+        let mut noise = Vec::with_capacity(5000);
+        let mut state = 0x1234_5678u32;
+        for _ in 0..5000 {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            noise.push((state >> 24) as u8);
+        }
+        vec![
+            Vec::new(),
+            b"a".to_vec(),
+            b"abcdabcdabcd".to_vec(),
+            html.repeat(200).into_bytes(),
+            vec![0u8; 70_000],
+            noise,
+        ]
+    }
+
+    #[cfg(all(feature = "lzo", feature = "zstd"))]
+    #[test]
+    fn every_codec_round_trips() {
+        for encoding in [
+            CompressionEncoding::Raw,
+            CompressionEncoding::Lzo,
+            CompressionEncoding::Gzip,
+            CompressionEncoding::Zstd,
+            CompressionEncoding::Lz4,
+        ] {
+            for payload in sample_payloads() {
+                let encoded = encode_block(encoding, 0, &payload).unwrap();
+                let decoded = decode_block(encoding, &encoded, payload.len()).unwrap();
+                assert_eq!(decoded, payload, "{:?}, {} bytes", encoding, payload.len());
+            }
+
+            let values = sample_payloads().into_iter().skip(1).collect::<Vec<_>>();
+            let (writer, offsets) = write_entries_to_writer(encoding, 4096, &values);
+            let storage = writer.finish_into_bytes().unwrap();
+            assert_roundtrip_entries(&storage, &offsets, &values, 2);
+        }
+    }
+
+    #[cfg(all(feature = "lzo", feature = "zstd"))]
+    #[test]
+    fn repetitive_payloads_shrink() {
+        let html = sample_payloads().swap_remove(3);
+        for encoding in [
+            CompressionEncoding::Lzo,
+            CompressionEncoding::Gzip,
+            CompressionEncoding::Lz4,
+        ] {
+            let encoded = encode_block(encoding, 0, &html).unwrap();
+            assert!(encoded.len() * 10 < html.len(), "{:?}: {}", encoding, encoded.len());
+        }
+    }
+
+    #[cfg(all(feature = "lzo", feature = "zstd"))]
+    #[test]
+    fn corrupted_blocks_are_errors() {
+        let html = sample_payloads().swap_remove(3);
+        for encoding in [
+            CompressionEncoding::Lzo,
+            CompressionEncoding::Gzip,
+            CompressionEncoding::Lz4,
+        ] {
+            let encoded = encode_block(encoding, 0, &html).unwrap();
+            assert!(decode_block(encoding, &encoded, html.len() - 1).is_err());
+            assert!(decode_block(encoding, &encoded[..encoded.len() / 2], html.len()).is_err());
+            for at in 0..encoded.len() {
+                let mut corrupted = encoded.clone();
+                corrupted[at] ^= 0x5a;
+                if let Ok(decoded) = decode_block(encoding, &corrupted, html.len()) {
+                    assert_eq!(decoded.len(), html.len());
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "brotli")]
+    #[test]
+    fn brotli_round_trips() {
+        let encoding = CompressionEncoding::Brotli;
+        assert_eq!(CompressionEncoding::from_u8(5).unwrap(), encoding);
+        for payload in sample_payloads() {
+            for level in [0, 1, 11] {
+                let encoded = encode_block(encoding, level, &payload).unwrap();
+                let decoded = decode_block(encoding, &encoded, payload.len()).unwrap();
+                assert_eq!(decoded, payload, "level {}, {} bytes", level, payload.len());
+            }
+        }
+
+        let html = sample_payloads().swap_remove(3);
+        let encoded = encode_block(encoding, 0, &html).unwrap();
+        assert!(encoded.len() * 10 < html.len(), "{}", encoded.len());
+        assert!(decode_block(encoding, &encoded, html.len() - 1).is_err());
+        assert!(decode_block(encoding, &encoded, html.len() + 1).is_err());
+        assert!(decode_block(encoding, &encoded[..encoded.len() / 2], html.len()).is_err());
+
+        let values = sample_payloads().into_iter().skip(1).collect::<Vec<_>>();
+        let (writer, offsets) = write_entries_to_writer(encoding, 4096, &values);
+        let storage = writer.finish_into_bytes().unwrap();
+        assert_roundtrip_entries(&storage, &offsets, &values, 2);
+    }
+
+    #[cfg(not(feature = "brotli"))]
+    #[test]
+    fn brotli_is_recognised_but_needs_its_feature() {
+        use crate::error::MDictError;
+
+        assert_eq!(CompressionEncoding::from_u8(5).unwrap(), CompressionEncoding::Brotli);
+        assert!(matches!(
+            encode_block(CompressionEncoding::Brotli, 0, b"data"),
+            Err(MDictError::UnsupportedFeature(_))
+        ));
+        assert!(CompressionEncoding::from_u8(6).is_err());
+    }
+
+    #[cfg(not(all(feature = "lzo", feature = "zstd")))]
+    #[test]
+    fn lzo_and_zstd_are_recognised_but_need_their_features() {
+        use crate::error::MDictError;
+
+        for encoding in [CompressionEncoding::Lzo, CompressionEncoding::Zstd] {
+            let supported = match encoding {
+                CompressionEncoding::Lzo => cfg!(feature = "lzo"),
+                _ => cfg!(feature = "zstd"),
+            };
+            if supported {
+                continue;
+            }
+            assert!(matches!(
+                encode_block(encoding, 0, b"data"),
+                Err(MDictError::UnsupportedFeature(_))
+            ));
+            assert!(matches!(
+                decode_block(encoding, b"data", 4),
+                Err(MDictError::UnsupportedFeature(_))
+            ));
+        }
+        if !cfg!(feature = "zstd") {
+            assert!(matches!(
+                PackedStorageWriter::train_zstd_dictionary(&entries(), 64),
+                Err(MDictError::UnsupportedFeature(_))
+            ));
+        }
+    }
+
+    #[cfg(feature = "zstd")]
+    fn dictionary_records() -> Vec<Vec<u8>> {
+        (0..400)
+            .map(|i| {
+                format!(
+                    "<div class=\"entry\"><span class=\"headword\">word{}</span>\
+                     <span class=\"pos\">noun</span><ol class=\"senses\">\
+                     <li class=\"sense\">meaning number {}</li></ol></div>",
+                    i,
+                    i * 7
+                )
+                .into_bytes()
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "zstd")]
+    fn storage_size(records: &[Vec<u8>], dictionary: Option<Vec<u8>>) -> (Vec<u8>, Vec<u64>) {
+        let mut writer = PackedStorageWriter::new(CompressionEncoding::Zstd, 10, 512).unwrap();
+        if let Some(dictionary) = dictionary {
+            writer = writer.with_zstd_dictionary(dictionary).unwrap();
+        }
+        let offsets = records
+            .iter()
+            .map(|record| writer.push_entry(record).unwrap())
+            .collect();
+        (writer.finish_into_bytes().unwrap(), offsets)
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_dictionary_round_trips_and_shrinks_storage() {
+        let records = dictionary_records();
+        let dictionary = PackedStorageWriter::train_zstd_dictionary(&records, 4096).unwrap();
+
+        let (plain, _) = storage_size(&records, None);
+        let (trained, offsets) = storage_size(&records, Some(dictionary.clone()));
+        assert_roundtrip_entries(&trained, &offsets, &records, 2);
+        assert_eq!(trained[8], VERSION_WITH_FLAGS);
+        assert!(trained.len() < plain.len(), "{} >= {}", trained.len(), plain.len());
+
+        let index = PackedStorageIndex::parse_from_reader(&mut Cursor::new(trained)).unwrap();
+        assert_eq!(index.header.zstd_dictionary, Some(dictionary));
+        assert_eq!(plain[8], VERSION);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_dictionary_requires_zstd_before_entries() {
+        let dictionary = PackedStorageWriter::train_zstd_dictionary(&dictionary_records(), 4096)
+            .unwrap();
+        assert!(PackedStorageWriter::new(CompressionEncoding::Lz4, 0, 64)
+            .unwrap()
+            .with_zstd_dictionary(dictionary.clone())
+            .is_err());
+
+        let mut writer = PackedStorageWriter::new(CompressionEncoding::Zstd, 0, 64).unwrap();
+        writer.push_entry(b"entry").unwrap();
+        assert!(writer.with_zstd_dictionary(dictionary).is_err());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn truncated_zstd_dictionary_is_an_error() {
+        let records = dictionary_records();
+        let dictionary = PackedStorageWriter::train_zstd_dictionary(&records, 4096).unwrap();
+        let (bytes, _) = storage_size(&records[..4], Some(dictionary));
+        let index = PackedStorageIndex::parse_from_reader(&mut Cursor::new(&bytes)).unwrap();
+        let data_len = index.header.block_prefix_sum.last().unwrap().compressed_end as usize;
+        let header_end = bytes.len() - data_len;
+        let truncated = &bytes[..header_end - 1];
+        assert!(PackedStorageIndex::parse_from_reader(&mut Cursor::new(truncated)).is_err());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn user_data_round_trips_next_to_a_zstd_dictionary() {
+        let records = dictionary_records();
+        let dictionary = PackedStorageWriter::train_zstd_dictionary(&records, 4096).unwrap();
+        let mut writer = PackedStorageWriter::new(CompressionEncoding::Zstd, 10, 512)
+            .unwrap()
+            .with_zstd_dictionary(dictionary.clone())
+            .unwrap()
+            .with_user_data(b"schema".to_vec());
+        let offsets = records
+            .iter()
+            .map(|record| writer.push_entry(record).unwrap())
+            .collect::<Vec<_>>();
+        let bytes = writer.finish_into_bytes().unwrap();
+        assert_eq!(bytes[8], VERSION_WITH_FLAGS);
+        assert_roundtrip_entries(&bytes, &offsets, &records, 2);
+
+        let index = PackedStorageIndex::parse_from_reader(&mut Cursor::new(&bytes)).unwrap();
+        assert_eq!(index.header.zstd_dictionary, Some(dictionary));
+        assert_eq!(index.header.user_data.as_deref(), Some(&b"schema"[..]));
+    }
+
+    #[test]
+    fn reader_reads_across_blocks_and_caches_them() {
+        let values = (0..20u8).map(|i| vec![i; 7]).collect::<Vec<_>>();
+        let (writer, offsets) = write_entries_to_writer(CompressionEncoding::Lz4, 16, &values);
+        let bytes = writer.finish_into_bytes().unwrap();
+
+        let mut reader = PackedStorageReader::new(Cursor::new(bytes), 2).unwrap();
+        assert_eq!(reader.num_entries(), 20);
+        assert!(reader.index().header.block_prefix_sum.len() > 5);
+        for (offset, value) in offsets.iter().zip(&values) {
+            assert_eq!(&reader.read_at(*offset, value.len()).unwrap(), value);
+        }
+
+        let spanning = reader.read_at(offsets[1], 21).unwrap();
+        assert_eq!(spanning, [vec![1; 7], vec![2; 7], vec![3; 7]].concat());
+        assert!(reader.read_at(offsets[19], 8).is_err());
+        reader.verify().unwrap();
+    }
 }
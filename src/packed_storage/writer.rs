@@ -1,8 +1,11 @@
 use std::io::{Seek, Write};
 
 use crate::error::{MDictError, Result};
+use crate::format::codecs::zstd_train_dictionary;
 
-use super::{encode_block, BlockPrefixEntry, CompressionEncoding, PackedStorageHeader};
+use super::{
+    encode_block_with_dictionary, BlockPrefixEntry, CompressionEncoding, PackedStorageHeader,
+};
 
 pub struct PackedStorageWriter {
     header: PackedStorageHeader,
@@ -32,6 +35,8 @@ impl PackedStorageWriter {
                     compressed_end: 0,
                     uncompressed_end: 0,
                 }],
+                zstd_dictionary: None,
+                user_data: None,
             },
             target_uncompressed_block_size,
             pending_block: Vec::new(),
@@ -39,17 +44,69 @@ impl PackedStorageWriter {
         })
     }
 
+    /// Train a zstd dictionary of at most `max_size` bytes from `samples`,
+    /// typically a spread of the entries about to be written.
+    pub fn train_zstd_dictionary<S: AsRef<[u8]>>(
+        samples: &[S],
+        max_size: usize,
+    ) -> Result<Vec<u8>> {
+        if samples.is_empty() || max_size == 0 {
+            return Err(MDictError::InvalidArgument(
+                "zstd dictionary training needs samples and a non-zero size".to_string(),
+            ));
+        }
+        zstd_train_dictionary(samples, max_size)
+    }
+
+    /// Compress every block against `dictionary` and embed it in the header.
+    /// Only valid for [`CompressionEncoding::Zstd`] and before any entry is
+    /// pushed.
+    pub fn with_zstd_dictionary(mut self, dictionary: Vec<u8>) -> Result<Self> {
+        if self.header.encoding != CompressionEncoding::Zstd {
+            return Err(MDictError::InvalidArgument(format!(
+                "zstd dictionary requires zstd encoding, not {:?}",
+                self.header.encoding
+            )));
+        }
+        if self.header.num_entries != 0 {
+            return Err(MDictError::InvalidArgument(
+                "zstd dictionary must be set before pushing entries".to_string(),
+            ));
+        }
+        if dictionary.is_empty() {
+            return Err(MDictError::InvalidArgument(
+                "zstd dictionary is empty".to_string(),
+            ));
+        }
+        self.header.zstd_dictionary = Some(dictionary);
+        Ok(self)
+    }
+
+    /// Store `data` in the header for the application reading the file.
+    pub fn with_user_data(mut self, data: Vec<u8>) -> Self {
+        self.header.user_data = Some(data);
+        self
+    }
+
     fn flush_pending_block(&mut self) -> Result<()> {
         if self.pending_block.is_empty() {
             return Ok(());
         }
 
-        let compressed = encode_block(
+        let compressed = encode_block_with_dictionary(
             self.header.encoding,
             self.header.compression_level,
             &self.pending_block,
+            self.header.zstd_dictionary.as_deref(),
         )?;
+        self.append_block(compressed, self.pending_block.len() as u64)?;
+        self.pending_block.clear();
+        Ok(())
+    }
 
+    /// Add a compressed block holding `uncompressed_len` bytes, returning the
+    /// uncompressed offset it starts at.
+    fn append_block(&mut self, compressed: Vec<u8>, uncompressed_len: u64) -> Result<u64> {
         let last_prefix = self.header.block_prefix_sum.last().copied().ok_or_else(|| {
             MDictError::InvalidFormat("missing initial prefix entry".to_string())
         })?;
@@ -60,7 +117,7 @@ impl PackedStorageWriter {
             .ok_or_else(|| MDictError::InvalidFormat("compressed size overflow".to_string()))?;
         let uncompressed_end = last_prefix
             .uncompressed_end
-            .checked_add(self.pending_block.len() as u64)
+            .checked_add(uncompressed_len)
             .ok_or_else(|| MDictError::InvalidFormat("uncompressed size overflow".to_string()))?;
 
         self.header.block_prefix_sum.push(BlockPrefixEntry {
@@ -69,8 +126,23 @@ impl PackedStorageWriter {
         });
 
         self.compressed_blocks.push(compressed);
-        self.pending_block.clear();
-        Ok(())
+        Ok(last_prefix.uncompressed_end)
+    }
+
+    /// Copy in a block compressed elsewhere with this writer's encoding and
+    /// zstd dictionary, holding `num_entries` entries in `uncompressed_len`
+    /// bytes. Pending entries are flushed into a block of their own first.
+    /// Returns the offset of the block's first entry.
+    pub fn push_compressed_block(
+        &mut self,
+        compressed: Vec<u8>,
+        uncompressed_len: u64,
+        num_entries: u64,
+    ) -> Result<u64> {
+        self.flush_pending_block()?;
+        let offset = self.append_block(compressed, uncompressed_len)?;
+        self.header.num_entries += num_entries;
+        Ok(offset)
     }
 
     pub fn push_entry(&mut self, entry: &[u8]) -> Result<u64> {
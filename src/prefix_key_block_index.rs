@@ -60,6 +60,31 @@ impl PrefixKeyBlockIndexInternal {
         }
     }
 
+    /// Position the cursor past the last result, for walking backward.
+    pub fn seek_end(&mut self) {
+        self.cursor = self.len();
+    }
+
+    /// Step the cursor back one result and return its global index.
+    pub fn prev_global_index(&mut self) -> Option<usize> {
+        self.cursor = self.cursor.min(self.len()).checked_sub(1)?;
+        self.start_index.checked_add(self.cursor)
+    }
+
+    /// Step back over up to `n` results; the indices come back in key order.
+    pub fn take_indices_back(&mut self, n: usize) -> Vec<usize> {
+        let mut out = Vec::new();
+        for _ in 0..n {
+            if let Some(g) = self.prev_global_index() {
+                out.push(g);
+            } else {
+                break;
+            }
+        }
+        out.reverse();
+        out
+    }
+
     pub fn take_indices(&mut self, n: usize) -> Vec<usize> {
         let mut out = Vec::new();
         for _ in 0..n {
@@ -80,12 +105,11 @@ pub struct PrefixKeyBlockIndex<'a, R: Read + Seek> {
 
 impl<'a, R: Read + Seek> PrefixKeyBlockIndex<'a, R> {
     pub fn new(mdict: &'a mut Mdict<R>, prefix: &str) -> Result<Self> {
+        // `None` means every key sorts before `prefix`, so none starts with it.
         let (start, end) = mdict
             .key_block_index
             .prefix_range_bounds(&mut mdict.reader, prefix)?
-            .ok_or_else(|| {
-                crate::error::MDictError::InvalidArgument("Prefix not found".to_string())
-            })?;
+            .unwrap_or_default();
 
         Ok(Self {
             mdict,
@@ -121,6 +145,21 @@ impl<'a, R: Read + Seek> PrefixKeyBlockIndex<'a, R> {
         }
     }
 
+    /// The result before the cursor, moving the cursor back onto it.
+    pub fn prev(&mut self) -> Result<Option<KeyBlock>> {
+        match self.inner.prev_global_index() {
+            Some(g) => {
+                let mdict_mut = &mut self.mdict;
+                mdict_mut.key_block_index.get(&mut mdict_mut.reader, g)
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn seek_end(&mut self) {
+        self.inner.seek_end();
+    }
+
     pub fn collect_to_vec(&mut self) -> Result<Vec<KeyBlock>> {
         let mut result = Vec::new();
         while let Some(key_block) = self.next()? {
@@ -149,4 +188,19 @@ impl<'a, R: Read + Seek> PrefixKeyBlockIndex<'a, R> {
 
         Ok(result)
     }
+
+    /// Up to `n` results before the cursor, in key order, moving the cursor
+    /// back past them. Call [`Self::seek_end`] first to read from the end.
+    pub fn take_back(&mut self, n: usize) -> Result<Vec<KeyBlock>> {
+        let mut result = Vec::new();
+
+        for idx in self.inner.take_indices_back(n) {
+            let mdict_mut = &mut self.mdict;
+            if let Some(kb) = mdict_mut.key_block_index.get(&mut mdict_mut.reader, idx)? {
+                result.push(kb);
+            }
+        }
+
+        Ok(result)
+    }
 }
@@ -1,158 +1,447 @@
+use std::cmp::Ordering;
 use std::io::{Read, Seek, SeekFrom};
+use std::ops::{Bound, RangeBounds};
+use std::sync::Arc;
 
+use crate::block_cache::{BlockCache, CacheCapacity};
+use crate::collation::{KeyCollation, KeyOrder, SearchOptions};
 use crate::error::Result;
 use crate::format::{HeaderInfo, KeySection};
+use crate::metrics::{self, BlockKind, Span};
+#[cfg(feature = "mmap")]
+use crate::seekable_mmap::MmapSection;
 use crate::types::KeyBlock;
 
 pub struct KeyBlockIndex {
-    pub header: HeaderInfo,
-    pub key_section: KeySection,
+    pub header: Arc<HeaderInfo>,
+    pub key_section: Arc<KeySection>,
     pub key_blocks_start: u64,
 
-    cached_block_idx: Option<usize>,
-    cached_entries: Option<Vec<KeyBlock>>,
+    order: KeyOrder,
+    cache: BlockCache<Vec<KeyBlock>>,
     read_buf: Vec<u8>,
+    /// The whole file, when it is mapped: blocks are then decoded straight
+    /// from the mapping instead of being read through the reader.
+    #[cfg(feature = "mmap")]
+    mapped: Option<MmapSection>,
+    /// Every block, decoded, once [`Self::preload`] has run.
+    preloaded: Option<Arc<Vec<Vec<KeyBlock>>>>,
 }
 
 impl KeyBlockIndex {
     pub fn new(header: HeaderInfo, key_section: KeySection) -> Result<Self> {
+        Self::new_with_cache(header, key_section, CacheCapacity::Entries(1))
+    }
+
+    /// Like [`Self::new`], caching decoded key blocks up to `capacity`. At
+    /// least the most recently decoded block is always kept.
+    pub fn new_with_cache(
+        header: HeaderInfo,
+        key_section: KeySection,
+        capacity: impl Into<CacheCapacity>,
+    ) -> Result<Self> {
         let total_key_blocks_size = *key_section.key_info_prefix_sum.last().unwrap_or(&0);
 
-        let key_blocks_start = key_section.next_section_offset - total_key_blocks_size;
+        let key_blocks_start = key_section
+            .next_section_offset
+            .checked_sub(total_key_blocks_size)
+            .ok_or("key block sizes exceed the key section")?;
 
         Ok(Self {
-            header,
-            key_section,
+            order: KeyOrder::from(KeyCollation::from_header(&header))
+                .with_encoding(header.get_encoding()),
+            header: Arc::new(header),
+            key_section: Arc::new(key_section),
             key_blocks_start,
-            cached_block_idx: None,
-            cached_entries: None,
+            cache: BlockCache::new(key_cache_capacity(capacity.into())),
             read_buf: Vec::new(),
+            #[cfg(feature = "mmap")]
+            mapped: None,
+            preloaded: None,
         })
     }
 
+    /// Another index over the same parsed header and key section, with its
+    /// own (empty) block cache of the same capacity.
+    pub fn share(&self) -> Self {
+        Self {
+            header: Arc::clone(&self.header),
+            key_section: Arc::clone(&self.key_section),
+            key_blocks_start: self.key_blocks_start,
+            order: self.order.clone(),
+            cache: BlockCache::new(self.cache.capacity()),
+            read_buf: Vec::new(),
+            #[cfg(feature = "mmap")]
+            mapped: self.mapped.clone(),
+            preloaded: self.preloaded.clone(),
+        }
+    }
+
+    /// Decode key blocks straight from `mapped`, the whole file the index
+    /// was read from, without seeking or copying through the reader. `None`
+    /// goes back to reading through the reader.
+    #[cfg(feature = "mmap")]
+    pub fn set_mapped(&mut self, mapped: Option<MmapSection>) {
+        self.mapped = mapped;
+    }
+
+    #[cfg(feature = "mmap")]
+    pub fn is_mapped(&self) -> bool {
+        self.mapped.is_some()
+    }
+
+    /// Decode every key block once and keep them all for the life of the
+    /// index and the indexes [`Self::share`]d from it, so lookups are binary
+    /// searches over memory and never decode again. Meant for small
+    /// dictionaries; see [`Self::decompressed_size`] for the cost.
+    pub fn preload(&mut self, reader: &mut (impl Read + Seek)) -> Result<()> {
+        if self.preloaded.is_some() {
+            return Ok(());
+        }
+        let num_blocks = self.key_section.key_info_blocks.len();
+        let mut blocks = Vec::with_capacity(num_blocks);
+        for idx in 0..num_blocks {
+            blocks.push(self.decode_block(reader, idx)?);
+        }
+        self.preloaded = Some(Arc::new(blocks));
+        self.cache.clear();
+        Ok(())
+    }
+
+    pub fn is_preloaded(&self) -> bool {
+        self.preloaded.is_some()
+    }
+
+    /// Combined decoded size of the key blocks, as listed in the key info.
+    pub fn decompressed_size(&self) -> u64 {
+        self.key_section
+            .key_info_blocks
+            .iter()
+            .map(|kb| kb.decompressed_size)
+            .sum()
+    }
+
     /// Ensure the requested block is decoded and cached, returning a reference
     /// to the cached entries.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self, reader)))]
     fn load_block(
         &mut self,
         reader: &mut (impl Read + Seek),
         idx: usize,
     ) -> Result<&Vec<KeyBlock>> {
-        if self.cached_block_idx == Some(idx) {
-            return Ok(self.cached_entries.as_ref().unwrap());
+        if self.preloaded.is_none() {
+            let cached = self.cache.contains(idx);
+            metrics::cache_lookup(BlockKind::Key, cached);
+            if !cached {
+                let entries = self.decode_block(reader, idx)?;
+                let weight = entries
+                    .iter()
+                    .map(|entry| std::mem::size_of::<KeyBlock>() + entry.key_text.len())
+                    .sum();
+                self.cache.insert(idx, entries, weight);
+            }
         }
 
+        match &self.preloaded {
+            Some(preloaded) => Ok(&preloaded[idx]),
+            None => Ok(self.cache.get(idx).unwrap()),
+        }
+    }
+
+    fn decode_block(
+        &mut self,
+        reader: &mut (impl Read + Seek),
+        idx: usize,
+    ) -> Result<Vec<KeyBlock>> {
+        let span = Span::start();
         let kb = &self.key_section.key_info_blocks[idx];
         let offset = self.key_blocks_start + self.key_section.key_info_prefix_sum[idx];
         let size = kb.compressed_size as usize;
+        let decompressed_size = kb.decompressed_size as usize;
+
+        let block = self.raw_block(reader, offset, size)?;
+        let decoded =
+            crate::format::decode_format_block_sized(block, decompressed_size).map_err(|e| {
+                e.in_section(format_args!("key block {}", idx))
+                    .at_offset(offset)
+            })?;
+        span.block_decoded(BlockKind::Key, idx, decoded.len());
+        crate::format::parse_key_block(
+            &decoded,
+            self.header.get_encoding(),
+            self.header.get_version(),
+        )
+    }
+
+    /// The compressed key block of `size` bytes at `offset`: straight from
+    /// the mapping when the file is mapped, otherwise read through `reader`.
+    fn raw_block(
+        &mut self,
+        reader: &mut (impl Read + Seek),
+        offset: u64,
+        size: usize,
+    ) -> Result<&[u8]> {
+        #[cfg(feature = "mmap")]
+        if let Some(mapped) = &self.mapped {
+            return usize::try_from(offset)
+                .ok()
+                .and_then(|start| mapped.as_slice().get(start..start.checked_add(size)?))
+                .ok_or_else(|| "key block extends past the end of the file".into());
+        }
 
         self.read_buf.clear();
         self.read_buf.resize(size, 0);
 
         reader.seek(SeekFrom::Start(offset))?;
         reader.read_exact(&mut self.read_buf)?;
+        Ok(&self.read_buf)
+    }
 
-        let decoded = crate::format::decode_format_block(&self.read_buf)?;
-        let entries = crate::format::parse_key_block(&decoded, self.header.get_encoding())?;
+    pub fn cache_capacity(&self) -> CacheCapacity {
+        self.cache.capacity()
+    }
 
-        self.cached_entries = Some(entries);
-        self.cached_block_idx = Some(idx);
+    pub fn set_cache_capacity(&mut self, capacity: impl Into<CacheCapacity>) {
+        self.cache.set_capacity(key_cache_capacity(capacity.into()));
+    }
 
-        Ok(self.cached_entries.as_ref().unwrap())
+    /// The key order lookups assume, taken from the header.
+    pub fn collation(&self) -> KeyCollation {
+        self.order.collation()
     }
 
-    fn find_candidate_block_for_prefix(&self, prefix: &str) -> Option<(usize, usize)> {
-        let blocks = &self.key_section.key_info_blocks;
-        let idx = blocks.partition_point(|b| b.last.as_str() < prefix);
-        let upper_bound_prefix =
-            upper_bound_from_prefix(prefix).or_else(|| Some(prefix.to_string()))?;
-        let idx_upper = blocks.partition_point(|b| b.last.as_str() < upper_bound_prefix.as_str());
+    /// Override the key order, for dictionaries whose header flags do not
+    /// match how their keys are actually sorted. Drops any locale collator.
+    pub fn set_collation(&mut self, collation: KeyCollation) {
+        self.order = KeyOrder::from(collation).with_encoding(self.header.get_encoding());
+    }
 
-        if idx >= blocks.len() {
-            None
-        } else {
-            // There is one case where the prefix == the last block.last, in which this triggers
-            let idx_upper = if idx_upper >= blocks.len() {
-                blocks.len() - 1
-            } else {
-                idx_upper
-            };
-            Some((idx, idx_upper))
-        }
+    /// Compare two keys as lookups do: under the collation or locale set
+    /// with [`Self::set_search_options`], by encoded bytes for legacy code
+    /// pages.
+    pub fn compare_keys(&self, a: &str, b: &str) -> Ordering {
+        self.order.compare(a, b)
     }
 
-    pub fn get(&mut self, reader: &mut (impl Read + Seek), idx: usize) -> Result<Option<KeyBlock>> {
-        let block_idx = self
-            .key_section
-            .num_entries_prefix_sum
-            .partition_point(|&x| x <= idx as u64);
+    pub fn search_options(&self) -> SearchOptions {
+        self.order.options()
+    }
 
-        if block_idx == 0 || block_idx > self.key_section.key_info_blocks.len() {
-            return Ok(None);
-        }
+    /// Order lookups by `options`, e.g. a locale collator for dictionaries
+    /// sorted by locale rules. Fails if the locale is invalid or has no
+    /// collation data.
+    pub fn set_search_options(&mut self, options: &SearchOptions) -> Result<()> {
+        self.order = KeyOrder::new(options)?.with_encoding(self.header.get_encoding());
+        Ok(())
+    }
 
-        let num_entries_prefix_sum = self.key_section.num_entries_prefix_sum[block_idx - 1];
+    pub fn get(&mut self, reader: &mut (impl Read + Seek), idx: usize) -> Result<Option<KeyBlock>> {
+        let Some((block_idx, offset)) = self.block_and_offset_for_entry(idx) else {
+            return Ok(None);
+        };
+        let block = self.load_block(reader, block_idx)?;
+        Ok(block.get(offset).cloned())
+    }
 
-        let block = self.load_block(reader, block_idx - 1)?;
-        let offset = idx - num_entries_prefix_sum as usize;
+    /// The key block holding the entry at global index `index` and the
+    /// entry's position within that block, or `None` past the last entry.
+    /// Blocks listing no entries are never returned.
+    pub fn block_and_offset_for_entry(&self, index: usize) -> Option<(usize, usize)> {
+        let prefix_sum = &self.key_section.num_entries_prefix_sum;
+        let block_idx = prefix_sum
+            .partition_point(|&start| start <= index as u64)
+            .checked_sub(1)?;
+        if block_idx >= self.key_section.key_info_blocks.len() {
+            return None;
+        }
+        Some((block_idx, index - prefix_sum[block_idx] as usize))
+    }
 
-        Ok(block.get(offset).cloned())
+    /// The global index of entry `offset` of key block `block_idx`; the
+    /// inverse of [`Self::block_and_offset_for_entry`].
+    pub fn entry_for_block_and_offset(&self, block_idx: usize, offset: usize) -> Option<usize> {
+        let block = self.key_section.key_info_blocks.get(block_idx)?;
+        if offset as u64 >= block.num_entries {
+            return None;
+        }
+        Some(self.key_section.num_entries_prefix_sum[block_idx] as usize + offset)
     }
 
+    /// Index of the entry whose key is `key_text`. Without an exact match,
+    /// the first key equal to it under the collation (e.g. differing only in
+    /// case) is returned.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self, reader)))]
     pub fn index_for(
         &mut self,
         reader: &mut (impl Read + Seek),
         key_text: &str,
     ) -> Result<Option<usize>> {
-        let blocks = &self.key_section.key_info_blocks;
-        let block_idx = blocks.partition_point(|b| b.last.as_str() < key_text);
-
-        if block_idx >= blocks.len() {
+        let Some(first) = self.lower_bound(reader, key_text)? else {
             return Ok(None);
+        };
+
+        let mut index = first;
+        while let Some(key_block) = self.get(reader, index)? {
+            if key_block.key_text == key_text {
+                return Ok(Some(index));
+            }
+            if self.order.compare(&key_block.key_text, key_text).is_ne() {
+                break;
+            }
+            index += 1;
         }
+        Ok((index > first).then_some(first))
+    }
 
-        let block = self.load_block(reader, block_idx)?;
-        let entry_idx = block.partition_point(|e| e.key_text.as_str() < key_text);
-
-        if entry_idx == block.len() || block[entry_idx].key_text != key_text {
-            Ok(None)
-        } else {
-            let global_idx =
-                self.key_section.num_entries_prefix_sum[block_idx] as usize + entry_idx;
-            Ok(Some(global_idx))
+    /// Every entry whose key is `key_text`, in key order. MDX files repeat a
+    /// headword once per sense, so a key can have several entries. Without
+    /// an exact match, the entries equal to it under the collation are
+    /// returned.
+    pub fn get_all(
+        &mut self,
+        reader: &mut (impl Read + Seek),
+        key_text: &str,
+    ) -> Result<Vec<KeyBlock>> {
+        let Some(mut index) = self.lower_bound(reader, key_text)? else {
+            return Ok(Vec::new());
+        };
+
+        let mut matches = Vec::new();
+        while let Some(key_block) = self.get(reader, index)? {
+            if self.order.compare(&key_block.key_text, key_text).is_ne() {
+                break;
+            }
+            matches.push(key_block);
+            index += 1;
+        }
+        if matches.iter().any(|key_block| key_block.key_text == key_text) {
+            matches.retain(|key_block| key_block.key_text == key_text);
+        }
+        Ok(matches)
+    }
+
+    /// Index of the entry `key_block` came from. Among entries sharing its
+    /// key, the one with the same `key_id` is picked; otherwise this is
+    /// [`Self::index_for`] of its key.
+    pub fn index_of(
+        &mut self,
+        reader: &mut (impl Read + Seek),
+        key_block: &KeyBlock,
+    ) -> Result<Option<usize>> {
+        let Some(first) = self.index_for(reader, &key_block.key_text)? else {
+            return Ok(None);
+        };
+
+        let mut index = first;
+        while let Some(candidate) = self.get(reader, index)? {
+            if candidate.key_id == key_block.key_id {
+                return Ok(Some(index));
+            }
+            if self
+                .order
+                .compare(&candidate.key_text, &key_block.key_text)
+                .is_ne()
+            {
+                break;
+            }
+            index += 1;
         }
+        Ok(Some(first))
     }
 
+    /// The half-open range of entry indexes whose keys start with `prefix`
+    /// under the collation, or `None` when every key sorts before `prefix`.
+    /// The range is empty when no key has the prefix but some key sorts
+    /// after it.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self, reader)))]
     pub fn prefix_range_bounds(
         &mut self,
         reader: &mut (impl Read + Seek),
         prefix: &str,
     ) -> Result<Option<(usize, usize)>> {
-        // Find the candidate block that might contain keys with this prefix
-        let (lower_bound, upper_bound) = match self.find_candidate_block_for_prefix(prefix) {
-            Some(idx) => idx,
-            None => return Ok(None),
+        let Some(lower_index) = self.lower_bound(reader, prefix)? else {
+            return Ok(None);
         };
+        // Keys sort as: before `prefix`, starting with it, after it.
+        let order = self.order.clone();
+        let upper_index = self.partition_point(reader, |key| {
+            order.compare(key, prefix).is_lt() || order.starts_with(key, prefix)
+        })?;
 
-        let block_entries_lower = self.key_section.num_entries_prefix_sum[lower_bound] as usize;
-        let block_entries_upper = self.key_section.num_entries_prefix_sum[upper_bound] as usize;
-
-        let entries_lower = self.load_block(reader, lower_bound)?;
-        let lower_bound_pos = entries_lower.partition_point(|e| e.key_text.as_str() < prefix);
-
-        if lower_bound_pos >= entries_lower.len() {
-            return Ok(None);
-        }
+        Ok(Some((lower_index, upper_index)))
+    }
 
-        let entries_upper = self.load_block(reader, upper_bound)?;
-        let upper_bound_prefix =
-            upper_bound_from_prefix(prefix).unwrap_or_else(|| prefix.to_string());
-        let upper_bound_pos =
-            entries_upper.partition_point(|e| e.key_text.as_str() < upper_bound_prefix.as_str());
+    /// The half-open range of entry indexes whose keys fall within `bounds`
+    /// under the collation. An inclusive end takes every entry equal to it,
+    /// not keys that merely start with it.
+    pub fn range_indices<'k>(
+        &mut self,
+        reader: &mut (impl Read + Seek),
+        bounds: impl RangeBounds<&'k str>,
+    ) -> Result<(usize, usize)> {
+        let order = self.order.clone();
+        let start = match bounds.start_bound() {
+            Bound::Included(start) => {
+                self.partition_point(reader, |key| order.compare(key, start).is_lt())?
+            }
+            Bound::Excluded(start) => {
+                self.partition_point(reader, |key| order.compare(key, start).is_le())?
+            }
+            Bound::Unbounded => 0,
+        };
+        let end = match bounds.end_bound() {
+            Bound::Included(end) => {
+                self.partition_point(reader, |key| order.compare(key, end).is_le())?
+            }
+            Bound::Excluded(end) => {
+                self.partition_point(reader, |key| order.compare(key, end).is_lt())?
+            }
+            Bound::Unbounded => self.key_section.num_entries as usize,
+        };
+        Ok((start, end.max(start)))
+    }
 
-        let lower_index = block_entries_lower + lower_bound_pos;
-        let upper_index = block_entries_upper + upper_bound_pos;
+    /// Index of the first key not less than `key_text` under the key order,
+    /// or `None` if every key is less.
+    fn lower_bound(
+        &mut self,
+        reader: &mut (impl Read + Seek),
+        key_text: &str,
+    ) -> Result<Option<usize>> {
+        let order = self.order.clone();
+        let index = self.partition_point(reader, |key| order.compare(key, key_text).is_lt())?;
+        Ok((index < self.key_section.num_entries as usize).then_some(index))
+    }
 
-        Ok(Some((lower_index, upper_index)))
+    /// Index of the first key for which `pred` is false, given that it holds
+    /// for a leading run of keys and for none after.
+    ///
+    /// The last keys in the key info only pick the block to start from: when
+    /// `pred` holds for every key of that block, the run goes on into the
+    /// blocks after it, so a run straddling blocks is never cut short at a
+    /// block boundary, even if a block's summary key is out of step with the
+    /// keys it holds.
+    fn partition_point(
+        &mut self,
+        reader: &mut (impl Read + Seek),
+        pred: impl Fn(&str) -> bool,
+    ) -> Result<usize> {
+        let num_blocks = self.key_section.key_info_blocks.len();
+        let mut block_idx = self
+            .key_section
+            .key_info_blocks
+            .partition_point(|b| pred(&b.last));
+
+        while block_idx < num_blocks {
+            let block_start = self.key_section.num_entries_prefix_sum[block_idx] as usize;
+            let block = self.load_block(reader, block_idx)?;
+            let entry_idx = block.partition_point(|e| pred(&e.key_text));
+            if entry_idx < block.len() {
+                return Ok(block_start + entry_idx);
+            }
+            block_idx += 1;
+        }
+        Ok(self.key_section.num_entries as usize)
     }
 }
 
@@ -182,3 +471,12 @@ pub fn upper_bound_from_prefix(prefix: &str) -> Option<String> {
 
     None
 }
+
+/// Key lookups hand out references into the current block, so the key block
+/// cache never drops below one entry.
+fn key_cache_capacity(capacity: CacheCapacity) -> CacheCapacity {
+    match capacity {
+        CacheCapacity::Entries(n) => CacheCapacity::Entries(n.max(1)),
+        bytes => bytes,
+    }
+}
@@ -0,0 +1,67 @@
+//! Records handed out without copying them out of their decoded block.
+
+use std::ops::{Deref, Range};
+use std::sync::Arc;
+
+/// A record borrowed from its decoded record block.
+///
+/// The block is shared with the [`Mdict`](crate::Mdict) block cache, so
+/// taking a `RecordRef` costs a reference count rather than a copy of the
+/// record, and it stays valid after the block is evicted.
+#[derive(Debug, Clone)]
+pub struct RecordRef {
+    block: Arc<Vec<u8>>,
+    block_index: usize,
+    range: Range<usize>,
+}
+
+impl RecordRef {
+    pub(crate) fn new(block: Arc<Vec<u8>>, block_index: usize, range: Range<usize>) -> Self {
+        debug_assert!(range.start <= range.end && range.end <= block.len());
+        Self {
+            block,
+            block_index,
+            range,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.block[self.range.clone()]
+    }
+
+    /// Index of the record block the record lives in.
+    pub fn block_index(&self) -> usize {
+        self.block_index
+    }
+
+    /// Byte range of the record within its decoded block.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.range.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.range.is_empty()
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+impl Deref for RecordRef {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl AsRef<[u8]> for RecordRef {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
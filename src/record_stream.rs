@@ -0,0 +1,57 @@
+//! Records read across the FFI in chunks.
+
+use std::sync::Mutex;
+
+use crate::error::MDictError;
+use crate::record_ref::RecordRef;
+
+/// A record handed out a chunk at a time, from
+/// [`MdictBundle::open_record_stream`](crate::MdictBundle::open_record_stream).
+///
+/// The stream holds the record's decoded block rather than a copy of the
+/// record, so only the chunks passed to [`Self::read_next`] are copied.
+#[derive(uniffi::Object)]
+pub struct RecordStreamHandle {
+    record: RecordRef,
+    position: Mutex<usize>,
+}
+
+impl RecordStreamHandle {
+    #[cfg(feature = "mmap")]
+    pub(crate) fn new(record: RecordRef) -> Self {
+        Self {
+            record,
+            position: Mutex::new(0),
+        }
+    }
+}
+
+#[uniffi::export]
+impl RecordStreamHandle {
+    /// The next at most `max_len` bytes of the record, or an empty chunk once
+    /// the whole record has been read.
+    pub fn read_next(&self, max_len: u64) -> Result<Vec<u8>, MDictError> {
+        if max_len == 0 {
+            return Err(MDictError::InvalidArgument(
+                "max_len must be greater than 0".to_string(),
+            ));
+        }
+        let mut position = self.position.lock().unwrap();
+        let remaining = &self.record[*position..];
+        let take = usize::try_from(max_len)
+            .unwrap_or(usize::MAX)
+            .min(remaining.len());
+        *position += take;
+        Ok(remaining[..take].to_vec())
+    }
+
+    /// Size of the whole record in bytes.
+    pub fn total_len(&self) -> u64 {
+        self.record.len() as u64
+    }
+
+    /// Bytes handed out so far.
+    pub fn position(&self) -> u64 {
+        *self.position.lock().unwrap() as u64
+    }
+}
@@ -0,0 +1,455 @@
+//! Opt-in post-processing of record text before display.
+//!
+//! Dictionaries built with compact HTML (`Compact="Yes"`) store records with
+//! their markup abbreviated to `` `N` `` markers that refer to the header
+//! `StyleSheet`; they are not usable HTML until expanded.
+//!
+//! Records also link to other headwords (`entry://`), to audio
+//! (`sound://`) and to images and stylesheets stored in the MDD by relative
+//! path or `file://` URL. [`rewrite_links`] maps those onto whatever scheme
+//! the embedding WebView serves.
+//!
+//! [`to_plaintext`] reduces a record to its text, for previews, speech and
+//! full-text indexing.
+
+use crate::format::HeaderInfo;
+use crate::types::Encoding;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, uniffi::Record)]
+pub struct RenderOptions {
+    /// Expand compact-HTML markers when the header declares compact records.
+    pub expand_compact: bool,
+}
+
+/// Apply `options` to a decoded record of the dictionary described by
+/// `header`. Records of non-compact dictionaries are returned unchanged.
+pub fn render_record(header: &HeaderInfo, text: String, options: RenderOptions) -> String {
+    if options.expand_compact && header.is_compact() {
+        header.stylesheet().apply(&text)
+    } else {
+        text
+    }
+}
+
+/// What a record link points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum LinkKind {
+    /// `entry://word`: another headword.
+    Entry,
+    /// `sound://path`: an audio resource in the MDD.
+    Sound,
+    /// A relative path or `file://` URL: an asset in the MDD.
+    Asset,
+}
+
+/// Rewrite the `href` and `src` attributes of `html` that link to entries,
+/// sounds or assets. `rewrite` gets each link's kind and its target with the
+/// scheme removed and character references decoded, and returns the new URL,
+/// or `None` to leave the link alone. Other URLs (`http:`, `data:`, `#id`,
+/// ...) are never passed to it.
+///
+/// The markup is scanned tag by tag rather than parsed as XML, so the
+/// unclosed tags and unquoted attributes common in records are fine.
+pub fn rewrite_links(
+    html: &str,
+    mut rewrite: impl FnMut(LinkKind, &str) -> Option<String>,
+) -> String {
+    let bytes = html.as_bytes();
+    let mut out = String::with_capacity(html.len());
+    let mut copied = 0;
+    let mut pos = 0;
+
+    while let Some(offset) = html[pos..].find('<') {
+        let tag_start = pos + offset;
+        if html[tag_start..].starts_with("<!--") {
+            pos = html[tag_start..]
+                .find("-->")
+                .map_or(html.len(), |end| tag_start + end + 3);
+            continue;
+        }
+        pos = tag_start + 1;
+        if !bytes.get(pos).is_some_and(u8::is_ascii_alphabetic) {
+            continue;
+        }
+        pos = skip_while(bytes, pos, |b| !is_attr_delimiter(b));
+
+        loop {
+            pos = skip_while(bytes, pos, |b| b.is_ascii_whitespace() || b == b'/');
+            if pos >= bytes.len() || bytes[pos] == b'>' {
+                break;
+            }
+            let name_start = pos;
+            pos = skip_while(bytes, pos, |b| !is_attr_delimiter(b) && b != b'=');
+            if pos == name_start {
+                pos += 1;
+                continue;
+            }
+            let name = &html[name_start..pos];
+            pos = skip_while(bytes, pos, |b| b.is_ascii_whitespace());
+            if bytes.get(pos) != Some(&b'=') {
+                continue;
+            }
+            pos = skip_while(bytes, pos + 1, |b| b.is_ascii_whitespace());
+
+            let (value_start, value_end, quoted) = match bytes.get(pos) {
+                Some(&quote @ (b'"' | b'\'')) => {
+                    let start = pos + 1;
+                    let end = html[start..]
+                        .find(quote as char)
+                        .map_or(html.len(), |end| start + end);
+                    pos = (end + 1).min(html.len());
+                    (start, end, true)
+                }
+                _ => {
+                    let start = pos;
+                    pos = skip_while(bytes, pos, |b| !b.is_ascii_whitespace() && b != b'>');
+                    (start, pos, false)
+                }
+            };
+
+            if !(name.eq_ignore_ascii_case("href") || name.eq_ignore_ascii_case("src")) {
+                continue;
+            }
+            let value = decode_char_refs(&html[value_start..value_end]);
+            let Some((kind, target)) = classify_link(&value) else {
+                continue;
+            };
+            if let Some(replacement) = rewrite(kind, target) {
+                out.push_str(&html[copied..value_start]);
+                if !quoted {
+                    out.push('"');
+                }
+                push_escaped(&mut out, &replacement);
+                if !quoted {
+                    out.push('"');
+                }
+                copied = value_end;
+            }
+        }
+    }
+
+    out.push_str(&html[copied..]);
+    out
+}
+
+/// The [`rewrite_links`] callback, for bindings.
+#[uniffi::export(callback_interface)]
+pub trait LinkRewriter: Send + Sync {
+    /// The new URL for a link of `kind` to `target`, or `None` to keep it.
+    fn rewrite(&self, kind: LinkKind, target: String) -> Option<String>;
+}
+
+/// [`rewrite_links`] with a foreign callback.
+#[uniffi::export]
+pub fn rewrite_record_links(html: String, rewriter: Box<dyn LinkRewriter>) -> String {
+    rewrite_links(&html, |kind, target| {
+        rewriter.rewrite(kind, target.to_string())
+    })
+}
+
+/// Split a link into its kind and target, or `None` for links that are not
+/// the dictionary's own (web URLs, fragments, `data:` and so on).
+pub(crate) fn classify_link(value: &str) -> Option<(LinkKind, &str)> {
+    let value = value.trim();
+    for (scheme, kind) in [
+        ("entry://", LinkKind::Entry),
+        ("sound://", LinkKind::Sound),
+        ("file://", LinkKind::Asset),
+    ] {
+        if value.len() >= scheme.len() && value[..scheme.len()].eq_ignore_ascii_case(scheme) {
+            return Some((kind, &value[scheme.len()..]));
+        }
+    }
+
+    let has_scheme = value
+        .find([':', '/', '\\', '?', '#'])
+        .is_some_and(|i| value.as_bytes()[i] == b':');
+    if value.is_empty() || value.starts_with('#') || value.starts_with("//") || has_scheme {
+        return None;
+    }
+    Some((LinkKind::Asset, value))
+}
+
+/// Elements whose contents are not text. `<rp>` holds the fallback
+/// parentheses around `<rt>`, which [`to_plaintext`] adds itself.
+const SKIPPED_ELEMENTS: [&str; 5] = ["script", "style", "head", "title", "rp"];
+
+/// Elements that start a new line.
+const BLOCK_ELEMENTS: [&str; 21] = [
+    "address", "article", "blockquote", "br", "dd", "div", "dl", "dt", "h1", "h2", "h3", "h4",
+    "h5", "h6", "hr", "li", "ol", "p", "section", "tr", "ul",
+];
+
+/// The text of a record decoded with `encoding`; see [`html_to_plaintext`].
+pub fn to_plaintext(record: &[u8], encoding: Encoding) -> String {
+    html_to_plaintext(&encoding.decode(record))
+}
+
+/// [`to_plaintext`] for bindings.
+#[uniffi::export]
+pub fn record_to_plaintext(record: Vec<u8>, encoding: Encoding) -> String {
+    to_plaintext(&record, encoding)
+}
+
+/// The first `max_chars` characters of a record's text on one line, for
+/// search result previews. The encoding is guessed: records that look like
+/// UTF-16LE are read as such, anything else as UTF-8.
+pub fn preview_text(record: &[u8], max_chars: usize) -> String {
+    html_to_plaintext(&decode_guessed(record))
+        .chars()
+        .map(|c| if c == '\n' { ' ' } else { c })
+        .take(max_chars)
+        .collect()
+}
+
+/// `record` decoded as UTF-16LE if it looks like it, else as UTF-8, for
+/// records whose dictionary encoding is not known.
+pub(crate) fn decode_guessed(record: &[u8]) -> String {
+    let looks_utf16 = record.len() >= 2 && record[0] != 0 && record[1] == 0;
+    let encoding = if looks_utf16 {
+        Encoding::Utf16LE
+    } else {
+        Encoding::Utf8
+    };
+    encoding.decode(record)
+}
+
+/// The text of `html`: tags, comments, scripts and styles are dropped,
+/// character references decoded and whitespace collapsed to single spaces.
+/// Block elements such as `<p>`, `<div>`, `<li>` and `<br>` end lines. Ruby
+/// readings follow their base text in parentheses, so
+/// `<ruby>漢<rt>かん</rt></ruby>` becomes `漢(かん)`.
+///
+/// Like [`rewrite_links`], the markup is scanned tag by tag, so unclosed tags
+/// and stray `<` are tolerated.
+pub fn html_to_plaintext(html: &str) -> String {
+    let bytes = html.as_bytes();
+    let mut text = PlainText::default();
+    let mut pos = 0;
+
+    while let Some(offset) = html[pos..].find('<') {
+        let tag_start = pos + offset;
+        text.push_text(&html[pos..tag_start]);
+        if html[tag_start..].starts_with("<!--") {
+            pos = html[tag_start..]
+                .find("-->")
+                .map_or(html.len(), |end| tag_start + end + 3);
+            continue;
+        }
+
+        let closing = bytes.get(tag_start + 1) == Some(&b'/');
+        let name_start = tag_start + 1 + usize::from(closing);
+        match bytes.get(name_start) {
+            Some(b) if b.is_ascii_alphabetic() => {}
+            Some(b'!' | b'?') => {
+                pos = tag_end(bytes, name_start);
+                continue;
+            }
+            _ => {
+                text.push_text("<");
+                pos = tag_start + 1;
+                continue;
+            }
+        }
+        let name_end = skip_while(bytes, name_start, |b| !is_attr_delimiter(b));
+        let name = html[name_start..name_end].to_ascii_lowercase();
+        pos = tag_end(bytes, name_end);
+
+        if !closing && SKIPPED_ELEMENTS.contains(&name.as_str()) {
+            let close_tag = format!("</{}", name);
+            pos = find_ignore_ascii_case(html, &close_tag, pos)
+                .map_or(html.len(), |close| tag_end(bytes, close + close_tag.len()));
+            continue;
+        }
+        match name.as_str() {
+            "rt" if !closing => {
+                text.close_reading();
+                text.push_raw("(");
+                text.in_reading = true;
+            }
+            "rt" | "ruby" => text.close_reading(),
+            "td" | "th" => text.space(),
+            name if BLOCK_ELEMENTS.contains(&name) => text.line_break(),
+            _ => {}
+        }
+    }
+
+    text.push_text(&html[pos..]);
+    text.close_reading();
+    text.out
+}
+
+/// Text built up by [`html_to_plaintext`], with whitespace held back until
+/// the next visible character so runs collapse and nothing trails.
+#[derive(Default)]
+struct PlainText {
+    out: String,
+    /// Whitespace seen since the last character: none, a space or a line break.
+    pending: Option<char>,
+    /// Inside an `<rt>` whose closing parenthesis is still owed.
+    in_reading: bool,
+}
+
+impl PlainText {
+    fn push_text(&mut self, raw: &str) {
+        for c in decode_entities(raw).chars() {
+            if c.is_whitespace() || c.is_control() {
+                self.space();
+            } else {
+                self.push_char(c);
+            }
+        }
+    }
+
+    fn push_raw(&mut self, raw: &str) {
+        raw.chars().for_each(|c| self.push_char(c));
+    }
+
+    fn push_char(&mut self, c: char) {
+        if let Some(pending) = self.pending.take() {
+            if !self.out.is_empty() {
+                self.out.push(pending);
+            }
+        }
+        self.out.push(c);
+    }
+
+    /// Close the parenthesis of an `<rt>`, which may be left unclosed.
+    fn close_reading(&mut self) {
+        if std::mem::take(&mut self.in_reading) {
+            self.push_raw(")");
+        }
+    }
+
+    fn space(&mut self) {
+        self.pending.get_or_insert(' ');
+    }
+
+    fn line_break(&mut self) {
+        self.pending = Some('\n');
+    }
+}
+
+/// Position just past the `>` closing the tag being scanned at `pos`,
+/// skipping quoted attribute values.
+fn tag_end(bytes: &[u8], mut pos: usize) -> usize {
+    let mut quote = None;
+    while pos < bytes.len() {
+        match (quote, bytes[pos]) {
+            (None, b'>') => return pos + 1,
+            (None, b @ (b'"' | b'\'')) => quote = Some(b),
+            (Some(q), b) if b == q => quote = None,
+            _ => {}
+        }
+        pos += 1;
+    }
+    bytes.len()
+}
+
+fn find_ignore_ascii_case(haystack: &str, needle: &str, from: usize) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+        .map(|position| from + position)
+}
+
+/// Decode named and numeric character references. Unknown or malformed
+/// references are kept as written.
+fn decode_entities(text: &str) -> std::borrow::Cow<'_, str> {
+    if !text.contains('&') {
+        return text.into();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| Some((decode_entity(&rest[1..end])?, end)));
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out.into()
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "middot" => '·',
+        "hellip" => '…',
+        "mdash" => '—',
+        "ndash" => '–',
+        "copy" => '©',
+        _ => return None,
+    })
+}
+
+fn skip_while(bytes: &[u8], mut pos: usize, pred: impl Fn(u8) -> bool) -> usize {
+    while pos < bytes.len() && pred(bytes[pos]) {
+        pos += 1;
+    }
+    pos
+}
+
+fn is_attr_delimiter(b: u8) -> bool {
+    b.is_ascii_whitespace() || b == b'>' || b == b'/'
+}
+
+/// Decode the character references that show up in URLs.
+fn decode_char_refs(value: &str) -> std::borrow::Cow<'_, str> {
+    if !value.contains('&') {
+        return value.into();
+    }
+    let mut decoded = value.to_string();
+    for (reference, c) in [
+        ("&quot;", "\""),
+        ("&#34;", "\""),
+        ("&apos;", "'"),
+        ("&#39;", "'"),
+        ("&lt;", "<"),
+        ("&gt;", ">"),
+        ("&amp;", "&"),
+    ] {
+        decoded = decoded.replace(reference, c);
+    }
+    decoded.into()
+}
+
+/// Append `value` to `out` with HTML special characters escaped.
+pub(crate) fn push_escaped(out: &mut String, value: &str) {
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            c => out.push(c),
+        }
+    }
+}
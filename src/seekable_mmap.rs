@@ -1,14 +1,18 @@
 use std::fs::File;
 use std::io::{Read, Result as IoResult, Seek, SeekFrom};
+use std::sync::Arc;
 
 use memmap2::Mmap;
 
+use crate::io::ByteSource;
+
 /// A small wrapper around `memmap2::Mmap` that provides `Read` + `Seek` by
-/// keeping an internal cursor. This is intended for single-threaded use; if
-/// you need concurrent access wrap this type in `Mutex`/`RwLock` or similar.
-#[derive(Debug)]
+/// keeping an internal cursor. A single handle is intended for single-threaded
+/// use; `clone` is cheap and gives each thread its own cursor over the same
+/// mapping.
+#[derive(Debug, Clone)]
 pub struct SeekableMmap {
-    mmap: Mmap,
+    mmap: Arc<Mmap>,
     pos: usize,
 }
 
@@ -21,12 +25,15 @@ impl SeekableMmap {
         // SAFETY: memmap2::Mmap::map is safe here; caller must ensure file
         // lives long enough and isn't truncated concurrently in unsafe ways.
         let mmap = unsafe { Mmap::map(file)? };
-        Ok(Self { mmap, pos: 0 })
+        Ok(Self::from_mmap(mmap))
     }
 
     /// Create from an existing `Mmap`.
     pub fn from_mmap(mmap: Mmap) -> Self {
-        Self { mmap, pos: 0 }
+        Self {
+            mmap: Arc::new(mmap),
+            pos: 0,
+        }
     }
 
     /// Return the underlying bytes slice.
@@ -34,6 +41,11 @@ impl SeekableMmap {
         &self.mmap[..]
     }
 
+    /// The whole mapping as an [`MmapSection`], sharing it.
+    pub fn section(&self) -> MmapSection {
+        MmapSection::from_mmap(Arc::clone(&self.mmap))
+    }
+
     /// Return current position (in bytes).
     pub fn position(&self) -> usize {
         self.pos
@@ -63,6 +75,18 @@ impl Read for SeekableMmap {
     }
 }
 
+/// Positional reads ignore the cursor, so one handle can serve any number
+/// of readers.
+impl ByteSource for SeekableMmap {
+    fn size(&self) -> u64 {
+        self.mmap.size()
+    }
+
+    fn read_exact_at(&self, offset: u64, buf: &mut [u8]) -> IoResult<()> {
+        self.mmap.read_exact_at(offset, buf)
+    }
+}
+
 impl Seek for SeekableMmap {
     fn seek(&mut self, how: SeekFrom) -> IoResult<u64> {
         let new = match how {
@@ -89,3 +113,73 @@ impl Seek for SeekableMmap {
         Ok(self.pos as u64)
     }
 }
+
+/// A byte range of a shared mapping, so several structures can borrow from
+/// one mapped file. Cloning shares the mapping.
+#[derive(Debug, Clone)]
+pub struct MmapSection {
+    mmap: Arc<Mmap>,
+    start: usize,
+    end: usize,
+}
+
+impl MmapSection {
+    /// Map the whole file.
+    pub fn open(file: &File) -> IoResult<Self> {
+        // SAFETY: as for `SeekableMmap::open`.
+        let mmap = unsafe { Mmap::map(file)? };
+        Ok(Self::from_mmap(Arc::new(mmap)))
+    }
+
+    pub fn from_mmap(mmap: Arc<Mmap>) -> Self {
+        let end = mmap.len();
+        Self {
+            mmap,
+            start: 0,
+            end,
+        }
+    }
+
+    /// The `len` bytes at `offset` within this section, or `None` if they
+    /// run past its end.
+    pub fn slice(&self, offset: u64, len: u64) -> Option<Self> {
+        let start = self.start.checked_add(usize::try_from(offset).ok()?)?;
+        let end = start.checked_add(usize::try_from(len).ok()?)?;
+        if end > self.end {
+            return None;
+        }
+        Some(Self {
+            mmap: Arc::clone(&self.mmap),
+            start,
+            end,
+        })
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.mmap[self.start..self.end]
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+impl AsRef<[u8]> for MmapSection {
+    fn as_ref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl ByteSource for MmapSection {
+    fn size(&self) -> u64 {
+        self.as_slice().size()
+    }
+
+    fn read_exact_at(&self, offset: u64, buf: &mut [u8]) -> IoResult<()> {
+        self.as_slice().read_exact_at(offset, buf)
+    }
+}
@@ -0,0 +1,558 @@
+//! Reading StarDict dictionaries, enabled with the `stardict` feature.
+//!
+//! A StarDict dictionary is a set of files sharing one stem: `<stem>.ifo`
+//! describes it, `<stem>.idx` (or gzipped `<stem>.idx.gz`) lists the words
+//! with the position of their data, `<stem>.dict` (or dictzip-compressed
+//! `<stem>.dict.dz`) holds the data and an optional `<stem>.syn` lists
+//! synonyms. The word list is read into memory when the dictionary is
+//! opened; data is read from a memory map, and dictzip files are inflated
+//! one chunk at a time.
+//!
+//! Entries are addressed with [`KeyBlock`]s like MDX entries, but `key_id`
+//! is the word's position in the `.idx` file, not a record offset.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use crate::dictionary::Dictionary;
+use crate::error::{MDictError, Result};
+use crate::gzip::{GzipMember, GZIP_MAGIC};
+use crate::render::push_escaped;
+use crate::seekable_mmap::SeekableMmap;
+use crate::types::{DictionaryFormat, DictionaryInfo, KeyBlock};
+
+const IFO_MAGIC: &str = "StarDict's dict ifo file";
+/// An empty final deflate block, ending a dictzip chunk so it inflates on
+/// its own. Inflating stops at the first final block, so the one already
+/// closing the last chunk wins.
+const EMPTY_FINAL_BLOCK: [u8; 2] = [0x03, 0x00];
+
+/// The `.ifo` description of a dictionary.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StarDictInfo {
+    /// `version`, `2.4.2` or `3.0.0`.
+    pub version: String,
+    pub book_name: String,
+    pub word_count: u64,
+    pub syn_word_count: u64,
+    /// Width of `.idx` data offsets, 64 or the default 32.
+    pub idx_offset_bits: u32,
+    /// Field types shared by every entry, e.g. `"m"` or `"th"`. Entries of
+    /// dictionaries without one carry a type before each field.
+    pub same_type_sequence: Option<String>,
+    pub author: Option<String>,
+    pub email: Option<String>,
+    pub website: Option<String>,
+    pub description: Option<String>,
+    pub date: Option<String>,
+}
+
+impl StarDictInfo {
+    pub fn parse(ifo: &str) -> Result<Self> {
+        let mut lines = ifo.trim_start_matches('\u{feff}').lines();
+        if lines.next().map(str::trim_end) != Some(IFO_MAGIC) {
+            return Err(MDictError::InvalidFormat("not a StarDict .ifo file".to_string()));
+        }
+
+        let mut info = Self {
+            idx_offset_bits: 32,
+            ..Self::default()
+        };
+        let mut word_count = None;
+        for line in lines {
+            let Some((name, value)) = line.trim_end_matches('\r').split_once('=') else {
+                continue;
+            };
+            let value = value.to_string();
+            let number = |value: &str| {
+                value.trim().parse::<u64>().map_err(|_| {
+                    MDictError::InvalidFormat(format!("invalid .ifo {}: {}", name, value))
+                })
+            };
+            match name.trim() {
+                "version" => info.version = value,
+                "bookname" => info.book_name = value,
+                "wordcount" => word_count = Some(number(&value)?),
+                "synwordcount" => info.syn_word_count = number(&value)?,
+                "idxoffsetbits" => info.idx_offset_bits = number(&value)? as u32,
+                "sametypesequence" if !value.is_empty() => {
+                    info.same_type_sequence = Some(value)
+                }
+                "author" => info.author = Some(value),
+                "email" => info.email = Some(value),
+                "website" => info.website = Some(value),
+                "description" => info.description = Some(value),
+                "date" => info.date = Some(value),
+                _ => {}
+            }
+        }
+
+        info.word_count = word_count
+            .ok_or_else(|| MDictError::InvalidFormat(".ifo has no wordcount".to_string()))?;
+        if !matches!(info.idx_offset_bits, 32 | 64) {
+            return Err(MDictError::UnsupportedFeature(format!(
+                "idxoffsetbits={}",
+                info.idx_offset_bits
+            )));
+        }
+        Ok(info)
+    }
+}
+
+/// One field of an entry's data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StarDictField {
+    /// The StarDict type character: `m` plain text, `h` HTML, `g` Pango
+    /// markup, `x` XDXF, `t` phonetics, `W` sound, `P` picture, ...
+    pub kind: char,
+    pub data: Vec<u8>,
+}
+
+impl StarDictField {
+    /// Text fields are NUL-terminated, lowercase types; uppercase types are
+    /// binary and prefixed with their size.
+    pub fn is_text(&self) -> bool {
+        self.kind.is_ascii_lowercase()
+    }
+}
+
+struct IdxEntry {
+    word: String,
+    offset: u64,
+    size: u32,
+}
+
+enum DictData {
+    Plain(SeekableMmap),
+    /// A gzip file without a dictzip chunk table, inflated when opened.
+    Inflated(Vec<u8>),
+    DictZip {
+        file: SeekableMmap,
+        /// Uncompressed bytes per chunk.
+        chunk_len: usize,
+        /// File offsets of each chunk, plus the end of the last one.
+        chunk_starts: Vec<usize>,
+    },
+}
+
+impl DictData {
+    fn open(path: &Path) -> Result<Self> {
+        let file = SeekableMmap::open(&File::open(path)?)?;
+        if !file.as_slice().starts_with(&GZIP_MAGIC) {
+            return Ok(Self::Plain(file));
+        }
+
+        let member = GzipMember::parse(file.as_slice())?;
+        let Some((chunk_len, chunk_sizes)) = member.dictzip_chunks()? else {
+            return Ok(Self::Inflated(member.inflate()?));
+        };
+        let mut chunk_starts = Vec::with_capacity(chunk_sizes.len() + 1);
+        let mut start = member.deflate_start;
+        chunk_starts.push(start);
+        for size in chunk_sizes {
+            start += size;
+            chunk_starts.push(start);
+        }
+        if start > file.len() {
+            return Err(MDictError::InvalidFormat(
+                "dictzip chunk table runs past the end of the file".to_string(),
+            ));
+        }
+        Ok(Self::DictZip {
+            file,
+            chunk_len,
+            chunk_starts,
+        })
+    }
+
+    fn read(&self, offset: u64, size: u32) -> Result<Cow<'_, [u8]>> {
+        let out_of_range = || {
+            MDictError::InvalidFormat(format!(
+                "entry data {}+{} is outside the .dict file",
+                offset, size
+            ))
+        };
+        let start = usize::try_from(offset).map_err(|_| out_of_range())?;
+        let end = start.checked_add(size as usize).ok_or_else(out_of_range)?;
+        match self {
+            Self::Plain(file) => file
+                .as_slice()
+                .get(start..end)
+                .map(Cow::Borrowed)
+                .ok_or_else(out_of_range),
+            Self::Inflated(data) => data
+                .get(start..end)
+                .map(Cow::Borrowed)
+                .ok_or_else(out_of_range),
+            Self::DictZip {
+                file,
+                chunk_len,
+                chunk_starts,
+            } => {
+                if start == end {
+                    return Ok(Cow::Borrowed(&[]));
+                }
+                let first = start / chunk_len;
+                let last = (end - 1) / chunk_len;
+                if last + 1 >= chunk_starts.len() {
+                    return Err(out_of_range());
+                }
+                let mut data = Vec::with_capacity((last - first + 1) * chunk_len);
+                for chunk in first..=last {
+                    let (chunk_start, chunk_end) = (chunk_starts[chunk], chunk_starts[chunk + 1]);
+                    let mut compressed = file.as_slice()[chunk_start..chunk_end].to_vec();
+                    compressed.extend_from_slice(&EMPTY_FINAL_BLOCK);
+                    let inflated = miniz_oxide::inflate::decompress_to_vec_with_limit(
+                        &compressed,
+                        *chunk_len,
+                    )
+                    .map_err(|err| MDictError::Corrupted {
+                        offset: chunk_start as u64,
+                        message: format!("dictzip chunk {}: {}", chunk, err),
+                    })?;
+                    data.extend_from_slice(&inflated);
+                }
+                let skip = start - first * chunk_len;
+                data.get(skip..skip + size as usize)
+                    .map(|slice| Cow::Owned(slice.to_vec()))
+                    .ok_or_else(out_of_range)
+            }
+        }
+    }
+}
+
+/// StarDict's word order: ASCII case-insensitive, then byte order.
+fn stardict_cmp(a: &str, b: &str) -> Ordering {
+    fold_cmp(a, b).then_with(|| a.cmp(b))
+}
+
+fn fold_cmp(a: &str, b: &str) -> Ordering {
+    a.bytes()
+        .map(|byte| byte.to_ascii_lowercase())
+        .cmp(b.bytes().map(|byte| byte.to_ascii_lowercase()))
+}
+
+fn read_word(data: &[u8], pos: &mut usize) -> Result<String> {
+    let end = data[*pos..]
+        .iter()
+        .position(|&byte| byte == 0)
+        .ok_or_else(|| MDictError::InvalidFormat("unterminated word".to_string()))?;
+    let word = String::from_utf8_lossy(&data[*pos..*pos + end]).into_owned();
+    *pos += end + 1;
+    Ok(word)
+}
+
+fn read_be(data: &[u8], pos: &mut usize, width: usize) -> Result<u64> {
+    let bytes = data
+        .get(*pos..*pos + width)
+        .ok_or_else(|| MDictError::InvalidFormat("truncated word list".to_string()))?;
+    *pos += width;
+    Ok(bytes.iter().fold(0, |value, &byte| value << 8 | byte as u64))
+}
+
+/// An opened StarDict dictionary.
+pub struct StarDict {
+    info: StarDictInfo,
+    /// Words in `.idx` order, so positions match `.syn` references.
+    entries: Vec<IdxEntry>,
+    /// Positions in `entries`, in StarDict word order.
+    order: Vec<u32>,
+    /// `(synonym, entry position)` in StarDict word order.
+    synonyms: Vec<(String, u32)>,
+    data: DictData,
+}
+
+impl StarDict {
+    /// Open the dictionary described by the `.ifo` file at `ifo_path`.
+    pub fn open(ifo_path: impl AsRef<Path>) -> Result<Self> {
+        let ifo_path = ifo_path.as_ref();
+        let info = StarDictInfo::parse(&std::fs::read_to_string(ifo_path)?)?;
+
+        let idx_path = existing(ifo_path, &["idx", "idx.gz"])?;
+        let idx = std::fs::read(&idx_path)?;
+        let idx = if idx.starts_with(&GZIP_MAGIC) {
+            GzipMember::parse(&idx)?.inflate()?
+        } else {
+            idx
+        };
+        let entries = Self::parse_idx(&idx, info.idx_offset_bits)?;
+        if entries.len() as u64 != info.word_count {
+            log::warn!(
+                "{} lists {} words, .ifo declares {}",
+                idx_path.display(),
+                entries.len(),
+                info.word_count
+            );
+        }
+
+        let syn_path = ifo_path.with_extension("syn");
+        let mut synonyms = if syn_path.exists() {
+            Self::parse_syn(&std::fs::read(syn_path)?, entries.len())?
+        } else {
+            Vec::new()
+        };
+        synonyms.sort_by(|a, b| stardict_cmp(&a.0, &b.0));
+
+        let mut order = (0..entries.len() as u32).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| {
+            stardict_cmp(&entries[a as usize].word, &entries[b as usize].word)
+        });
+
+        let data = DictData::open(&existing(ifo_path, &["dict", "dict.dz"])?)?;
+        Ok(Self {
+            info,
+            entries,
+            order,
+            synonyms,
+            data,
+        })
+    }
+
+    fn parse_idx(idx: &[u8], offset_bits: u32) -> Result<Vec<IdxEntry>> {
+        let offset_width = offset_bits as usize / 8;
+        let mut entries = Vec::new();
+        let mut pos = 0;
+        while pos < idx.len() {
+            let word = read_word(idx, &mut pos)?;
+            let offset = read_be(idx, &mut pos, offset_width)?;
+            let size = read_be(idx, &mut pos, 4)? as u32;
+            entries.push(IdxEntry { word, offset, size });
+        }
+        Ok(entries)
+    }
+
+    fn parse_syn(syn: &[u8], num_entries: usize) -> Result<Vec<(String, u32)>> {
+        let mut synonyms = Vec::new();
+        let mut pos = 0;
+        while pos < syn.len() {
+            let word = read_word(syn, &mut pos)?;
+            let index = read_be(syn, &mut pos, 4)? as u32;
+            if index as usize >= num_entries {
+                return Err(MDictError::InvalidFormat(format!(
+                    "synonym '{}' points at missing word {}",
+                    word, index
+                )));
+            }
+            synonyms.push((word, index));
+        }
+        Ok(synonyms)
+    }
+
+    pub fn info(&self) -> &StarDictInfo {
+        &self.info
+    }
+
+    /// Number of words, not counting synonyms.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn key_block(&self, position: u32) -> KeyBlock {
+        KeyBlock {
+            key_id: position as u64,
+            key_text: self.entries[position as usize].word.clone(),
+        }
+    }
+
+    /// Up to `limit` words starting with `prefix`, compared ASCII
+    /// case-insensitively as StarDict sorts them, in word order.
+    pub fn prefix_search(&self, prefix: &str, limit: usize) -> Vec<KeyBlock> {
+        let starts_with = |word: &str| {
+            word.len() >= prefix.len()
+                && word.as_bytes()[..prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
+        };
+        let word = |position: u32| self.entries[position as usize].word.as_str();
+        let start = self
+            .order
+            .partition_point(|&position| fold_cmp(word(position), prefix).is_lt());
+        self.order[start..]
+            .iter()
+            .copied()
+            .take_while(|&position| starts_with(word(position)))
+            .take(limit)
+            .map(|position| self.key_block(position))
+            .collect()
+    }
+
+    /// Every entry whose word is exactly `key`, then the entries `key` is a
+    /// synonym of. Empty when `key` is missing.
+    pub fn get_all(&self, key: &str) -> Vec<KeyBlock> {
+        let word = |position: u32| self.entries[position as usize].word.as_str();
+        let start = self
+            .order
+            .partition_point(|&position| fold_cmp(word(position), key).is_lt());
+        let mut positions = self.order[start..]
+            .iter()
+            .copied()
+            .take_while(|&position| fold_cmp(word(position), key).is_eq())
+            .filter(|&position| word(position) == key)
+            .collect::<Vec<_>>();
+
+        let start = self
+            .synonyms
+            .partition_point(|(synonym, _)| stardict_cmp(synonym, key).is_lt());
+        for (_, position) in self.synonyms[start..]
+            .iter()
+            .take_while(|(synonym, _)| synonym == key)
+        {
+            if !positions.contains(position) {
+                positions.push(*position);
+            }
+        }
+        positions
+            .into_iter()
+            .map(|position| self.key_block(position))
+            .collect()
+    }
+
+    fn entry(&self, key_block: &KeyBlock) -> Result<&IdxEntry> {
+        usize::try_from(key_block.key_id)
+            .ok()
+            .and_then(|position| self.entries.get(position))
+            .filter(|entry| entry.word == key_block.key_text)
+            .ok_or_else(|| {
+                MDictError::KeyNotFound(format!(
+                    "'{}' is not in this dictionary",
+                    key_block.key_text
+                ))
+            })
+    }
+
+    /// The raw data of an entry, fields and type markers included.
+    pub fn record_at_key_block(&self, key_block: &KeyBlock) -> Result<Vec<u8>> {
+        let entry = self.entry(key_block)?;
+        Ok(self.data.read(entry.offset, entry.size)?.into_owned())
+    }
+
+    /// The fields of an entry's data.
+    pub fn record_fields(&self, key_block: &KeyBlock) -> Result<Vec<StarDictField>> {
+        let entry = self.entry(key_block)?;
+        let data = self.data.read(entry.offset, entry.size)?;
+        let mut rest = &data[..];
+        let mut fields = Vec::new();
+        match &self.info.same_type_sequence {
+            Some(sequence) => {
+                let kinds = sequence.as_bytes();
+                for (index, &kind) in kinds.iter().enumerate() {
+                    let last = index + 1 == kinds.len();
+                    let (field, tail) = split_field(kind, rest, last)?;
+                    fields.push(StarDictField {
+                        kind: kind as char,
+                        data: field.to_vec(),
+                    });
+                    rest = tail;
+                }
+            }
+            None => {
+                while let Some((&kind, tail)) = rest.split_first() {
+                    let (field, tail) = split_field(kind, tail, false)?;
+                    fields.push(StarDictField {
+                        kind: kind as char,
+                        data: field.to_vec(),
+                    });
+                    rest = tail;
+                }
+            }
+        }
+        Ok(fields)
+    }
+
+    /// An entry's text fields as one HTML fragment. HTML, Pango and XDXF
+    /// fields are kept as they are, other text is escaped with line breaks
+    /// as `<br>`. Binary fields and resource lists are left out.
+    pub fn record_html(&self, key_block: &KeyBlock) -> Result<String> {
+        let parts = self
+            .record_fields(key_block)?
+            .into_iter()
+            .filter(|field| field.is_text() && field.kind != 'r')
+            .map(|field| {
+                let text = String::from_utf8_lossy(&field.data);
+                match field.kind {
+                    'h' | 'g' | 'x' => text.into_owned(),
+                    _ => {
+                        let mut out = String::with_capacity(text.len());
+                        push_escaped(&mut out, &text);
+                        out.replace('\n', "<br>")
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+        Ok(parts.join("<br>"))
+    }
+
+    /// [`Self::record_html`] of every entry [`Self::get_all`] finds for `key`.
+    pub fn lookup(&self, key: &str) -> Result<Vec<String>> {
+        self.get_all(key)
+            .iter()
+            .map(|key_block| self.record_html(key_block))
+            .collect()
+    }
+}
+
+impl Dictionary for StarDict {
+    fn info(&self) -> DictionaryInfo {
+        DictionaryInfo {
+            format: DictionaryFormat::StarDict,
+            title: Some(self.info.book_name.clone()),
+            description: self.info.description.clone(),
+            entry_count: self.len() as u64,
+        }
+    }
+
+    fn prefix_search(&self, prefix: &str, limit: usize) -> Result<Vec<KeyBlock>> {
+        Ok(StarDict::prefix_search(self, prefix, limit))
+    }
+
+    fn lookup(&self, key: &str) -> Result<Vec<KeyBlock>> {
+        Ok(self.get_all(key))
+    }
+
+    fn record(&self, key_block: &KeyBlock) -> Result<Vec<u8>> {
+        self.record_at_key_block(key_block)
+    }
+
+    fn record_text(&self, key_block: &KeyBlock) -> Result<String> {
+        self.record_html(key_block)
+    }
+}
+
+/// Split the next field of type `kind` off `data`. The last field of a
+/// `sametypesequence` entry has no terminator or size and runs to the end.
+fn split_field(kind: u8, data: &[u8], last: bool) -> Result<(&[u8], &[u8])> {
+    if last {
+        return Ok((data, &[]));
+    }
+    if kind.is_ascii_lowercase() {
+        return Ok(match data.iter().position(|&byte| byte == 0) {
+            Some(end) => (&data[..end], &data[end + 1..]),
+            None => (data, &[]),
+        });
+    }
+    let mut pos = 0;
+    let size = read_be(data, &mut pos, 4)? as usize;
+    let field = data.get(4..4 + size).ok_or_else(|| {
+        MDictError::InvalidFormat(format!("field '{}' runs past its entry", kind as char))
+    })?;
+    Ok((field, &data[4 + size..]))
+}
+
+/// The first of `<stem>.<extension>` that exists.
+fn existing(ifo_path: &Path, extensions: &[&str]) -> Result<PathBuf> {
+    extensions
+        .iter()
+        .map(|extension| ifo_path.with_extension(extension))
+        .find(|path| path.exists())
+        .ok_or_else(|| {
+            MDictError::Io(format!(
+                "no .{} file next to {}",
+                extensions[0],
+                ifo_path.display()
+            ))
+        })
+}
@@ -0,0 +1,95 @@
+//! The MDX header `StyleSheet` attribute.
+//!
+//! The attribute is a list of three-line groups: a style number, the markup
+//! that opens the style and the markup that closes it. Records written
+//! against a stylesheet mark styled runs with `` `N` ``; everything from the
+//! marker to the next marker (or the end of the record) is wrapped in style
+//! `N`'s open and close markup.
+
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StyleSheet {
+    styles: BTreeMap<u32, (String, String)>,
+}
+
+impl StyleSheet {
+    /// Parse a `StyleSheet` attribute value. Groups whose first line is not a
+    /// number are skipped; a missing close line counts as empty.
+    pub fn parse(value: &str) -> Self {
+        let lines: Vec<&str> = value.lines().collect();
+        let mut styles = BTreeMap::new();
+        for group in lines.chunks(3) {
+            let Ok(number) = group[0].trim().parse::<u32>() else {
+                continue;
+            };
+            let open = group.get(1).copied().unwrap_or_default();
+            let close = group.get(2).copied().unwrap_or_default();
+            styles.insert(number, (open.to_string(), close.to_string()));
+        }
+        Self { styles }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.styles.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.styles.len()
+    }
+
+    /// Open and close markup for style `number`.
+    pub fn get(&self, number: u32) -> Option<(&str, &str)> {
+        self.styles
+            .get(&number)
+            .map(|(open, close)| (open.as_str(), close.as_str()))
+    }
+
+    /// Replace every `` `N` `` marker in `text` with style `N`, the way MDict
+    /// renders it: a run ending in a line break is trimmed, closed, and
+    /// followed by `\r\n`. Markers for undefined styles are left as they are.
+    pub fn apply(&self, text: &str) -> String {
+        if self.is_empty() {
+            return text.to_string();
+        }
+
+        let mut out = String::with_capacity(text.len());
+        let mut markers = marker_pattern().captures_iter(text).peekable();
+        let Some(first) = markers.peek() else {
+            return text.to_string();
+        };
+        out.push_str(&text[..first.get(0).unwrap().start()]);
+
+        while let Some(marker) = markers.next() {
+            let whole = marker.get(0).unwrap();
+            let run_end = markers
+                .peek()
+                .map_or(text.len(), |next| next.get(0).unwrap().start());
+            let run = &text[whole.end()..run_end];
+
+            let style = marker[1].parse().ok().and_then(|number| self.get(number));
+            let Some((open, close)) = style else {
+                out.push_str(&text[whole.start()..run_end]);
+                continue;
+            };
+            out.push_str(open);
+            if run.ends_with('\n') {
+                out.push_str(run.trim_end());
+                out.push_str(close);
+                out.push_str("\r\n");
+            } else {
+                out.push_str(run);
+                out.push_str(close);
+            }
+        }
+        out
+    }
+}
+
+fn marker_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"`(\d+)`").unwrap())
+}
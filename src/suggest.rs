@@ -0,0 +1,114 @@
+//! Ranked autocomplete on top of the key index.
+//!
+//! Candidates come from three sources, best first: keys starting with the
+//! query, keys starting with a case variant of it, and keys a small edit
+//! distance away. Within a tier, entries keep key order.
+
+use std::collections::HashMap;
+use std::io::{Read, Seek};
+
+use crate::error::Result;
+use crate::mdict::bounded_edit_distance;
+use crate::types::Suggestion;
+use crate::Mdict;
+
+pub const SCORE_EXACT: u32 = 100;
+pub const SCORE_PREFIX: u32 = 80;
+pub const SCORE_FOLDED_EXACT: u32 = 70;
+pub const SCORE_FOLDED_PREFIX: u32 = 60;
+/// Near matches score this minus [`SCORE_PER_EDIT`] per edit.
+pub const SCORE_NEAR: u32 = 40;
+pub const SCORE_PER_EDIT: u32 = 10;
+
+impl<R: Read + Seek> Mdict<R> {
+    /// Up to `limit` suggestions for `query`, best first.
+    ///
+    /// Near matches are looked for among keys that share the query's first
+    /// character (in any case), within one edit for short queries and two
+    /// for queries of six or more characters.
+    pub fn suggest(&mut self, query: &str, limit: usize) -> Result<Vec<Suggestion>> {
+        if query.is_empty() || limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let folded_query = query.to_lowercase();
+        // key text -> (score, entry index)
+        let mut best: HashMap<String, (u32, usize)> = HashMap::new();
+        let mut offer = |key_text: String, score: u32, index: usize| {
+            let entry = best.entry(key_text).or_insert((score, index));
+            if score > entry.0 {
+                *entry = (score, index);
+            }
+        };
+
+        for variant in case_variants(query) {
+            let Some((start, end)) = self.prefix_range_bounds(&variant)? else {
+                continue;
+            };
+            for index in start..end.min(start + limit) {
+                let Some(key_block) = self.get(index)? else {
+                    break;
+                };
+                let score = if key_block.key_text == query {
+                    SCORE_EXACT
+                } else if key_block.key_text.starts_with(query) {
+                    SCORE_PREFIX
+                } else if key_block.key_text.to_lowercase() == folded_query {
+                    SCORE_FOLDED_EXACT
+                } else {
+                    SCORE_FOLDED_PREFIX
+                };
+                offer(key_block.key_text, score, index);
+            }
+        }
+
+        let query_chars = folded_query.chars().collect::<Vec<_>>();
+        let max_distance = if query_chars.len() >= 6 { 2 } else { 1 };
+        let first = query.chars().next().map(String::from).unwrap_or_default();
+        for variant in case_variants(&first) {
+            let Some((start, end)) = self.prefix_range_bounds(&variant)? else {
+                continue;
+            };
+            for index in start..end {
+                let Some(key_block) = self.get(index)? else {
+                    break;
+                };
+                let folded_key = key_block.key_text.to_lowercase();
+                if let Some(distance) =
+                    bounded_edit_distance(&query_chars, &folded_key, max_distance)
+                {
+                    let score = SCORE_NEAR.saturating_sub(SCORE_PER_EDIT * distance as u32);
+                    offer(key_block.key_text, score, index);
+                }
+            }
+        }
+
+        let mut ranked = best.into_iter().collect::<Vec<_>>();
+        ranked.sort_by(|(_, (score_a, index_a)), (_, (score_b, index_b))| {
+            score_b.cmp(score_a).then(index_a.cmp(index_b))
+        });
+        ranked.truncate(limit);
+        Ok(ranked
+            .into_iter()
+            .map(|(key_text, (score, _))| Suggestion { key_text, score })
+            .collect())
+    }
+}
+
+/// `text` as typed, lowercased, uppercased and capitalized, without repeats.
+fn case_variants(text: &str) -> Vec<String> {
+    let lower = text.to_lowercase();
+    let mut chars = lower.chars();
+    let capitalized = chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default();
+
+    let mut variants = Vec::with_capacity(4);
+    for variant in [text.to_string(), lower, text.to_uppercase(), capitalized] {
+        if !variants.contains(&variant) {
+            variants.push(variant);
+        }
+    }
+    variants
+}
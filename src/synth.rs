@@ -0,0 +1,199 @@
+//! Deterministic synthetic MDX/MDD generator.
+//!
+//! Produces small, fully in-memory dictionaries so tests (here and in
+//! downstream crates) don't need to ship copyrighted dictionary files.
+//!
+//! ```no_run
+//! use mdict_tools::synth::SynthDictBuilder;
+//! use mdict_tools::types::{Encoding, MdictVersion};
+//!
+//! let dict = SynthDictBuilder::entries(100)
+//!     .encoding(Encoding::Utf16LE)
+//!     .version(MdictVersion::V1)
+//!     .build()
+//!     .unwrap();
+//! let mut mdict = dict.open().unwrap();
+//! assert_eq!(mdict.record_at_index(0).unwrap(), dict.entries[0].1);
+//! ```
+
+#[cfg(feature = "fs")]
+use std::fs::File;
+use std::io::Cursor;
+#[cfg(feature = "fs")]
+use std::io::Write;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use crate::error::{MDictError, Result};
+use crate::mdx_writer::MdxWriter;
+use crate::types::{Encoding, MdictVersion};
+use crate::Mdict;
+
+const DEFAULT_ENTRIES_PER_KEY_BLOCK: usize = 32;
+const DEFAULT_ENTRIES_PER_RECORD_BLOCK: usize = 16;
+
+#[derive(Debug, Clone)]
+pub struct SynthDictBuilder {
+    num_entries: usize,
+    encoding: Encoding,
+    version: MdictVersion,
+    entries_per_key_block: usize,
+    entries_per_record_block: usize,
+    link_every: Option<usize>,
+    encrypt_key_info: bool,
+}
+
+/// A generated dictionary: the raw file bytes plus the `(key, record)` pairs
+/// it was built from, in key order. Records are stored in the dictionary's
+/// encoding, i.e. exactly as `Mdict::record_at_index` returns them.
+#[derive(Debug, Clone)]
+pub struct SynthDict {
+    pub bytes: Vec<u8>,
+    pub entries: Vec<(String, Vec<u8>)>,
+}
+
+impl SynthDictBuilder {
+    pub fn entries(num_entries: usize) -> Self {
+        Self {
+            num_entries,
+            encoding: Encoding::Utf8,
+            version: MdictVersion::V2,
+            entries_per_key_block: DEFAULT_ENTRIES_PER_KEY_BLOCK,
+            entries_per_record_block: DEFAULT_ENTRIES_PER_RECORD_BLOCK,
+            link_every: None,
+            encrypt_key_info: false,
+        }
+    }
+
+    /// Key/record text encoding. Ignored for `MdictVersion::MDD`, which is always UTF-16LE.
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn version(mut self, version: MdictVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn entries_per_key_block(mut self, n: usize) -> Self {
+        self.entries_per_key_block = n;
+        self
+    }
+
+    pub fn entries_per_record_block(mut self, n: usize) -> Self {
+        self.entries_per_record_block = n;
+        self
+    }
+
+    /// Make every `n`-th entry (after the first) an `@@@LINK=` redirect to the
+    /// entry before it. Has no effect on MDD output.
+    pub fn link_every(mut self, n: usize) -> Self {
+        self.link_every = Some(n);
+        self
+    }
+
+    /// Encrypt the key-info block (`Encrypted="2"`). Only V2 files compress,
+    /// and therefore encrypt, their key info; V1 output ignores this.
+    pub fn encrypt_key_info(mut self, encrypt: bool) -> Self {
+        self.encrypt_key_info = encrypt;
+        self
+    }
+
+    pub fn build(&self) -> Result<SynthDict> {
+        if self.entries_per_key_block == 0 || self.entries_per_record_block == 0 {
+            return Err(MDictError::InvalidArgument(
+                "entries per block must be greater than 0".to_string(),
+            ));
+        }
+
+        let encoding = match (self.version, self.encoding) {
+            (MdictVersion::V3, _) => {
+                return Err(MDictError::UnsupportedFeature(
+                    "synthetic V3 dictionaries are not supported".to_string(),
+                ))
+            }
+            (MdictVersion::MDD, _) => Encoding::Utf16LE,
+            (_, Encoding::Unknown) => {
+                return Err(MDictError::InvalidArgument(
+                    "synthetic dictionaries need a concrete encoding".to_string(),
+                ))
+            }
+            (_, encoding) => encoding,
+        };
+
+        let entries = self.generate_entries(encoding);
+
+        let writer = if self.version == MdictVersion::MDD {
+            MdxWriter::mdd()
+        } else {
+            MdxWriter::new().version(self.version).encoding(encoding)
+        };
+        let mut writer = writer
+            .title("Synthetic Dictionary")
+            .description("Generated by mdict_tools::synth")
+            .entries_per_key_block(self.entries_per_key_block)
+            .entries_per_record_block(self.entries_per_record_block)
+            .encrypt_key_info(self.encrypt_key_info);
+        for (key, record) in &entries {
+            writer.add_raw(key.clone(), record.clone())?;
+        }
+        let bytes = writer.to_bytes()?;
+
+        Ok(SynthDict { bytes, entries })
+    }
+
+    fn generate_entries(&self, encoding: Encoding) -> Vec<(String, Vec<u8>)> {
+        (0..self.num_entries)
+            .map(|i| {
+                if self.version == MdictVersion::MDD {
+                    return (format!("\\res{:06}.bin", i), binary_payload(i));
+                }
+
+                let key = synth_key(i);
+                let record = match self.link_every {
+                    Some(n) if n > 0 && i > 0 && i % n == 0 => {
+                        format!("@@@LINK={}", synth_key(i - 1))
+                    }
+                    _ => format!("<p class=\"def\">Definition of {} (#{})</p>", key, i),
+                };
+                let record = encoding.encode(&record);
+                (key, record)
+            })
+            .collect()
+    }
+}
+
+impl SynthDict {
+    /// Open the generated bytes as an in-memory `Mdict`.
+    pub fn open(&self) -> Result<Mdict<Cursor<Vec<u8>>>> {
+        Mdict::new(Cursor::new(self.bytes.clone()))
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut file = File::create(path)?;
+        file.write_all(&self.bytes)?;
+        Ok(())
+    }
+}
+
+fn synth_key(i: usize) -> String {
+    format!("word{:06}", i)
+}
+
+/// Deterministic pseudo-random bytes that deliberately contain `0x0A 0x00`
+/// so callers can check MDD payloads are never terminator-truncated.
+fn binary_payload(i: usize) -> Vec<u8> {
+    let len = 24 + (i % 7) * 5;
+    let mut state = (i as u32).wrapping_mul(2_654_435_761).wrapping_add(1);
+    let mut out = Vec::with_capacity(len + 2);
+    out.extend_from_slice(&[0x0A, 0x00]);
+    for _ in 0..len {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        out.push((state >> 24) as u8);
+    }
+    out
+}
@@ -0,0 +1,232 @@
+//! Romanized forms of headwords, for searching kana keys by romaji and
+//! pinyin keys without tone marks.
+//!
+//! Romanized forms and queries are both [`fold_romanized`], so `ni3 hao3`,
+//! `nǐhǎo` and `Ni Hao` all search as `nihao`.
+
+use crate::mdx_conversion::normalize::toneless_vowel;
+
+/// How [`romanize`] reads keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum RomanizationScheme {
+    /// Kana keys in Hepburn romaji. Keys with other scripts, such as kanji,
+    /// are skipped.
+    Romaji,
+    /// Keys written in pinyin, with tone marks, tone numbers or neither.
+    /// Keys in other scripts, such as hanzi, are skipped.
+    Pinyin,
+}
+
+/// The folded romanized forms `key` is indexed under, or nothing when `key`
+/// is not written in the scheme's script.
+pub fn romanize(scheme: RomanizationScheme, key: &str) -> Vec<String> {
+    let romanized = match scheme {
+        RomanizationScheme::Romaji => kana_to_romaji(key),
+        RomanizationScheme::Pinyin => key.chars().all(is_pinyin_char).then(|| key.to_string()),
+    };
+    let Some(folded) = romanized.map(|romanized| fold_romanized(&romanized)) else {
+        return Vec::new();
+    };
+    if folded.is_empty() {
+        return Vec::new();
+    }
+
+    let mut forms = vec![folded];
+    if scheme == RomanizationScheme::Romaji {
+        // Long vowels are often typed short: `toukyou` is also `tokyo`.
+        let short = shorten_long_vowels(&forms[0]);
+        if short != forms[0] {
+            forms.push(short);
+        }
+    }
+    forms
+}
+
+/// `text` lowercased, without tone marks, tone numbers, spaces or
+/// separators, and with `ü` written `v`.
+pub fn fold_romanized(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let c = toneless_vowel(c);
+        match c {
+            'ü' | 'Ü' => out.push('v'),
+            'u' | 'U' if chars.peek() == Some(&':') => {
+                chars.next();
+                out.push('v');
+            }
+            c if c.is_alphabetic() => out.extend(c.to_lowercase()),
+            _ => {}
+        }
+    }
+    out
+}
+
+fn is_pinyin_char(c: char) -> bool {
+    c.is_ascii_alphanumeric()
+        || matches!(c, ' ' | '\'' | '-' | ':' | 'ü' | 'Ü')
+        || toneless_vowel(c) != c
+}
+
+fn shorten_long_vowels(romaji: &str) -> String {
+    let mut out = String::with_capacity(romaji.len());
+    for c in romaji.chars() {
+        let long = matches!(
+            (out.chars().last(), c),
+            (Some('o'), 'u' | 'o') | (Some('u'), 'u') | (Some('a'), 'a')
+        );
+        if !long {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Hepburn romaji for a kana-only `text`, or `None` if it contains anything
+/// but kana, `ー`, `・` and spaces.
+fn kana_to_romaji(text: &str) -> Option<String> {
+    let kana = text.chars().map(katakana_to_hiragana).collect::<Vec<_>>();
+    let mut out = String::with_capacity(text.len() * 2);
+    let mut double_next = false;
+    let mut i = 0;
+    while i < kana.len() {
+        let c = kana[i];
+        i += 1;
+        match c {
+            'っ' => {
+                double_next = true;
+                continue;
+            }
+            'ー' => {
+                if let Some(vowel) = out.chars().last().filter(|c| "aeiou".contains(*c)) {
+                    out.push(vowel);
+                }
+                continue;
+            }
+            ' ' | '　' | '・' => continue,
+            _ => {}
+        }
+
+        let mut syllable = hiragana_romaji(c)?.to_string();
+        if let Some(&small) = kana.get(i) {
+            if let Some(combined) = combine_small_kana(&syllable, small) {
+                syllable = combined;
+                i += 1;
+            }
+        }
+        if std::mem::take(&mut double_next) {
+            if syllable.starts_with("ch") {
+                out.push('t');
+            } else if let Some(first) = syllable.chars().next().filter(|c| !"aeiou".contains(*c)) {
+                out.push(first);
+            }
+        }
+        out.push_str(&syllable);
+    }
+    Some(out)
+}
+
+/// `syllable` followed by a small `ゃ`, `ゅ`, `ょ` or small vowel, if the
+/// pair is a digraph such as `きゃ` or `ふぁ`.
+fn combine_small_kana(syllable: &str, small: char) -> Option<String> {
+    let vowel = match small {
+        'ゃ' => "a",
+        'ゅ' => "u",
+        'ょ' => "o",
+        'ぁ' | 'ぃ' | 'ぇ' | 'ぉ' => {
+            // `ふぁ` is `fa`, `てぃ` is `ti` and `うぃ` is `wi`.
+            let consonant = &syllable[..syllable.len() - 1];
+            let consonant = if consonant.is_empty() { "w" } else { consonant };
+            return Some(format!("{}{}", consonant, hiragana_romaji(small)?));
+        }
+        _ => return None,
+    };
+    let stem = syllable.strip_suffix('i').filter(|stem| !stem.is_empty())?;
+    if matches!(stem, "sh" | "ch" | "j") {
+        Some(format!("{}{}", stem, vowel))
+    } else {
+        Some(format!("{}y{}", stem, vowel))
+    }
+}
+
+fn katakana_to_hiragana(c: char) -> char {
+    match c {
+        'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c,
+    }
+}
+
+fn hiragana_romaji(c: char) -> Option<&'static str> {
+    Some(match c {
+        'あ' | 'ぁ' => "a",
+        'い' | 'ぃ' | 'ゐ' => "i",
+        'う' | 'ぅ' => "u",
+        'え' | 'ぇ' | 'ゑ' => "e",
+        'お' | 'ぉ' | 'を' => "o",
+        'か' | 'ゕ' => "ka",
+        'き' => "ki",
+        'く' => "ku",
+        'け' | 'ゖ' => "ke",
+        'こ' => "ko",
+        'が' => "ga",
+        'ぎ' => "gi",
+        'ぐ' => "gu",
+        'げ' => "ge",
+        'ご' => "go",
+        'さ' => "sa",
+        'し' => "shi",
+        'す' => "su",
+        'せ' => "se",
+        'そ' => "so",
+        'ざ' => "za",
+        'じ' | 'ぢ' => "ji",
+        'ず' | 'づ' => "zu",
+        'ぜ' => "ze",
+        'ぞ' => "zo",
+        'た' => "ta",
+        'ち' => "chi",
+        'つ' => "tsu",
+        'て' => "te",
+        'と' => "to",
+        'だ' => "da",
+        'で' => "de",
+        'ど' => "do",
+        'な' => "na",
+        'に' => "ni",
+        'ぬ' => "nu",
+        'ね' => "ne",
+        'の' => "no",
+        'は' => "ha",
+        'ひ' => "hi",
+        'ふ' => "fu",
+        'へ' => "he",
+        'ほ' => "ho",
+        'ば' => "ba",
+        'び' => "bi",
+        'ぶ' => "bu",
+        'べ' => "be",
+        'ぼ' => "bo",
+        'ぱ' => "pa",
+        'ぴ' => "pi",
+        'ぷ' => "pu",
+        'ぺ' => "pe",
+        'ぽ' => "po",
+        'ま' => "ma",
+        'み' => "mi",
+        'む' => "mu",
+        'め' => "me",
+        'も' => "mo",
+        'や' | 'ゃ' => "ya",
+        'ゆ' | 'ゅ' => "yu",
+        'よ' | 'ょ' => "yo",
+        'ら' => "ra",
+        'り' => "ri",
+        'る' => "ru",
+        'れ' => "re",
+        'ろ' => "ro",
+        'わ' | 'ゎ' => "wa",
+        'ん' => "n",
+        'ゔ' => "vu",
+        _ => return None,
+    })
+}
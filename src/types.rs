@@ -1,3 +1,5 @@
+use std::hash::Hasher;
+
 /// Small domain types for the new public API. Keep these minimal for the scaffold.
 #[derive(Debug, Clone, uniffi::Record)]
 pub struct KeyBlock {
@@ -5,24 +7,104 @@ pub struct KeyBlock {
     pub key_text: String,
 }
 
+/// Address of an entry in an optimized index that stays the same across
+/// rebuilds of the same dictionary, unlike `KeyBlock::key_id`. It hashes the
+/// entry's headword and its ordinal among entries with that headword.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EntryId(pub u64);
+
+uniffi::custom_newtype!(EntryId, u64);
+
+impl EntryId {
+    /// The id of the `ordinal`th entry, in dictionary order, whose headword
+    /// is `headword`.
+    pub fn new(headword: &str, ordinal: u32) -> Self {
+        let mut hasher = fnv::FnvHasher::default();
+        hasher.write(headword.as_bytes());
+        hasher.write(&ordinal.to_le_bytes());
+        Self(hasher.finish())
+    }
+}
+
+/// An autocomplete candidate; higher `score` is a better match.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct Suggestion {
+    pub key_text: String,
+    pub score: u32,
+}
+
 #[derive(Debug, Clone, uniffi::Record)]
 pub struct SearchHit {
     pub key: KeyBlock,
     pub record: String,
 }
 
-#[derive(Debug, Clone, uniffi::Record)]
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
 pub struct PrefixSearchCursor {
     pub after_key: String,
 }
 
+/// A key found by a dictionary group search, tagged with its dictionary.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct GroupSearchHit {
+    pub dict_id: u32,
+    pub key: KeyBlock,
+}
+
+/// Resume point for paging backward through a prefix search.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct PrefixSearchPrevCursor {
+    pub before_key: String,
+}
+
 #[derive(Debug, Clone, uniffi::Record)]
 pub struct PrefixSearchPage {
     pub results: Vec<KeyBlock>,
     pub next_cursor: Option<PrefixSearchCursor>,
+    pub prev_cursor: Option<PrefixSearchPrevCursor>,
     pub total_results: Option<u64>,
 }
 
+/// A search result with the start of its definition as plain text.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct KeyPreview {
+    pub key: KeyBlock,
+    pub preview: String,
+}
+
+/// A [`PrefixSearchPage`] with a preview of every result.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct PrefixSearchPageWithPreview {
+    pub results: Vec<KeyPreview>,
+    pub next_cursor: Option<PrefixSearchCursor>,
+    pub prev_cursor: Option<PrefixSearchPrevCursor>,
+    pub total_results: Option<u64>,
+}
+
+/// Typed view of the attributes in an MDX/MDD header.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct DictionaryMetadata {
+    pub version: MdictVersion,
+    /// `GeneratedByEngineVersion` as written, e.g. `"2.0"`.
+    pub engine_version: Option<String>,
+    pub required_engine_version: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub encoding: Encoding,
+    /// `Encrypted` as a bit set (see [`crate::format::encryption`]).
+    pub encrypted: u8,
+    /// `CreationDate` as written; builders use several date formats.
+    pub creation_date: Option<String>,
+    /// Raw `StyleSheet` attribute.
+    pub stylesheet: Option<String>,
+    /// `Compact` (or the misspelled `Compat`) flag.
+    pub compact: bool,
+    pub key_case_sensitive: bool,
+    pub strip_key: bool,
+    /// Record format, e.g. `"Html"` or `"Text"`.
+    pub format: Option<String>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
 pub enum BuildProgressStage {
     Start,
@@ -31,6 +113,31 @@ pub enum BuildProgressStage {
     Done,
 }
 
+/// What an optimized-index build does with `@@@LINK=` redirects whose target
+/// is not a key of the dictionary.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, uniffi::Enum)]
+pub enum BrokenLinkPolicy {
+    /// Index the redirect's readings under its own entry and say nothing.
+    #[default]
+    Lenient,
+    /// As `Lenient`, and report each such redirect as a [`BrokenLink`].
+    Strict,
+    /// Retarget each such redirect to the closest key within `max_distance`
+    /// edits, and report it with the key it now leads to, if one was found.
+    Repair { max_distance: u32 },
+}
+
+/// A `@@@LINK=` redirect whose target is not a key, found by an
+/// optimized-index build under a `Strict` or `Repair` [`BrokenLinkPolicy`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, uniffi::Record)]
+pub struct BrokenLink {
+    /// Key of the redirecting entry.
+    pub key_text: String,
+    pub target: String,
+    /// The key the redirect was retargeted to under `Repair`.
+    pub repaired_to: Option<String>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
 pub enum MdictVersion {
     V1,
@@ -39,10 +146,31 @@ pub enum MdictVersion {
     MDD,
 }
 
+/// A dictionary URI resolved by [`crate::MdictBundle::resolve_uri`].
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct ResolvedResource {
+    pub kind: crate::render::LinkKind,
+    /// Entry records are HTML transcoded to UTF-8; MDD resources are exact.
+    pub data: Vec<u8>,
+    pub mime_type: String,
+}
+
+/// How record payloads are stored, decided by [`MdictVersion::record_kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum RecordKind {
+    /// MDX records: encoded text, each followed by a `0x0A 0x00` terminator.
+    Text,
+    /// MDD resources: exact bytes with no terminator, never decoded as text.
+    Binary,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
 pub enum Encoding {
     Utf8,
     Utf16LE,
+    Gbk,
+    Big5,
+    Gb18030,
     Unknown,
 }
 
@@ -84,6 +212,15 @@ impl MdictVersion {
         }
     }
 
+    /// MDD files (headers without `GeneratedByEngineVersion`) hold binary
+    /// resources; every MDX version holds text.
+    pub fn record_kind(&self) -> RecordKind {
+        match self {
+            MdictVersion::MDD => RecordKind::Binary,
+            MdictVersion::V1 | MdictVersion::V2 | MdictVersion::V3 => RecordKind::Text,
+        }
+    }
+
     pub fn key_text_null_width(&self) -> usize {
         match self {
             MdictVersion::V1 => 1usize,
@@ -96,12 +233,224 @@ impl MdictVersion {
 
 impl Encoding {
     /// Number of bytes per character unit for this encoding.
-    /// UTF-8 => 1, UTF-16LE => 2. Unknown defaults to 2 for MDD-like handling.
+    /// UTF-8 and the legacy multi-byte code pages => 1 (lengths and NUL
+    /// terminators are counted in bytes), UTF-16LE => 2. Unknown defaults to 2
+    /// for MDD-like handling.
     pub fn char_width(&self) -> usize {
         match self {
-            Encoding::Utf8 => 1usize,
+            Encoding::Utf8 | Encoding::Gbk | Encoding::Big5 | Encoding::Gb18030 => 1usize,
             Encoding::Utf16LE => 2usize,
             Encoding::Unknown => 2usize,
         }
     }
+
+    /// Map a header `Encoding="..."` label. Empty and unrecognized labels fall
+    /// back to UTF-16LE, which is what MDict assumes when none is declared.
+    pub fn from_label(label: &str) -> Self {
+        let label = label.trim();
+        if label.eq_ignore_ascii_case("UTF-8") || label.eq_ignore_ascii_case("UTF8") {
+            return Encoding::Utf8;
+        }
+        match encoding_rs::Encoding::for_label_no_replacement(label.as_bytes()) {
+            Some(e) if e == encoding_rs::GBK => Encoding::Gbk,
+            Some(e) if e == encoding_rs::GB18030 => Encoding::Gb18030,
+            Some(e) if e == encoding_rs::BIG5 => Encoding::Big5,
+            _ => Encoding::Utf16LE,
+        }
+    }
+
+    /// Header label written for this encoding.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Gbk => "GBK",
+            Encoding::Big5 => "BIG5",
+            Encoding::Gb18030 => "GB18030",
+            Encoding::Utf16LE | Encoding::Unknown => "UTF-16",
+        }
+    }
+
+    fn as_encoding_rs(&self) -> &'static encoding_rs::Encoding {
+        match self {
+            Encoding::Utf8 => encoding_rs::UTF_8,
+            Encoding::Gbk => encoding_rs::GBK,
+            Encoding::Big5 => encoding_rs::BIG5,
+            Encoding::Gb18030 => encoding_rs::GB18030,
+            Encoding::Utf16LE | Encoding::Unknown => encoding_rs::UTF_16LE,
+        }
+    }
+
+    /// Encode text into this encoding. encoding_rs has no UTF-16 encoder, so
+    /// that case is handled here.
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        match self {
+            Encoding::Utf16LE | Encoding::Unknown => {
+                text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+            }
+            _ => self.as_encoding_rs().encode(text).0.into_owned(),
+        }
+    }
+
+    /// Decode dictionary text (keys or records) into a `String`, replacing
+    /// malformed sequences with U+FFFD.
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        self.as_encoding_rs()
+            .decode_without_bom_handling(bytes)
+            .0
+            .into_owned()
+    }
+}
+
+/// What an optimized-index delta update reused from the previous build.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, uniffi::Record)]
+pub struct DeltaStats {
+    /// Compressed record blocks copied from the previous build.
+    pub reused_blocks: u64,
+    /// Records inside the copied blocks.
+    pub reused_records: u64,
+    /// Records compressed again because they or their neighbours changed.
+    pub encoded_records: u64,
+}
+
+/// Outcome of transcoding a dictionary into zstd blocks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, uniffi::Record)]
+pub struct TranscodeStats {
+    /// Entries copied and verified.
+    pub entries: u64,
+    /// Size of the written file.
+    pub output_bytes: u64,
+}
+
+/// Outcome of a flashcard export.
+#[derive(Debug, Clone, Default, PartialEq, Eq, uniffi::Record)]
+pub struct FlashcardExport {
+    /// Cards written, one per headword found.
+    pub written: u64,
+    /// Requested headwords with no entry, in request order.
+    pub missing: Vec<String>,
+}
+
+/// Dictionary file formats behind [`crate::dictionary::Dictionary`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum DictionaryFormat {
+    Mdx,
+    /// An optimized index built from MDX files.
+    Optimized,
+    StarDict,
+    /// ABBYY Lingvo DSL.
+    Dsl,
+    /// A backend implemented outside this crate.
+    Other,
+}
+
+/// A description of a dictionary that every format can give.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct DictionaryInfo {
+    pub format: DictionaryFormat,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Entries, counting each sense of a repeated headword.
+    pub entry_count: u64,
+}
+
+/// Type of a per-entry metadata field in an optimized index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, uniffi::Enum)]
+pub enum MetadataKind {
+    Integer,
+    Float,
+    Text,
+}
+
+/// A named field of the metadata schema stored in a readings sidecar.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, uniffi::Record)]
+pub struct MetadataField {
+    pub name: String,
+    pub kind: MetadataKind,
+}
+
+#[derive(Debug, Clone, PartialEq, uniffi::Enum)]
+pub enum MetadataValue {
+    Integer { value: i64 },
+    Float { value: f64 },
+    Text { value: String },
+}
+
+impl MetadataValue {
+    pub fn kind(&self) -> MetadataKind {
+        match self {
+            MetadataValue::Integer { .. } => MetadataKind::Integer,
+            MetadataValue::Float { .. } => MetadataKind::Float,
+            MetadataValue::Text { .. } => MetadataKind::Text,
+        }
+    }
+}
+
+/// One metadata value of an entry, with the name of its field.
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct MetadataEntry {
+    pub name: String,
+    pub value: MetadataValue,
+}
+
+/// A metadata value for the entry whose source record is at `link_id`, the
+/// `key_id` of its MDX keys.
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct LinkMetadata {
+    pub link_id: u64,
+    pub name: String,
+    pub value: MetadataValue,
+}
+
+/// Whether a library dictionary's optimized bundle matches its MDX file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum OptimizedStatus {
+    /// No bundle has been registered.
+    Missing,
+    /// Built from the MDX as it is now.
+    Current,
+    /// The MDX changed since the bundle was built, or the bundle is gone.
+    Stale,
+}
+
+/// A dictionary found by a [`crate::library::Library`] scan, with the app's
+/// settings for it.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct LibraryEntry {
+    /// The MDX path relative to the library root, which identifies the
+    /// dictionary across scans.
+    pub id: String,
+    pub mdx_path: String,
+    /// The MDD next to the MDX, with the same file stem.
+    pub mdd_path: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub entry_count: u64,
+    pub enabled: bool,
+    pub optimized_bundle_path: Option<String>,
+    pub optimized_status: OptimizedStatus,
+}
+
+/// A user's note or highlight on a dictionary entry, kept by an
+/// [`crate::annotations::AnnotationStore`].
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct Annotation {
+    /// Assigned by the store when the annotation is added; never reused.
+    pub id: u64,
+    pub entry_id: EntryId,
+    /// Empty for a bare highlight.
+    pub note: String,
+    pub highlight: Option<Highlight>,
+    /// Milliseconds since the Unix epoch.
+    pub created_ms: u64,
+    pub updated_ms: u64,
+}
+
+/// A highlighted span of an entry, as byte offsets into the entry's plain
+/// text (`record_plaintext`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Record)]
+pub struct Highlight {
+    pub start: u32,
+    pub end: u32,
+    /// `0xAARRGGBB`.
+    pub color: u32,
 }
@@ -0,0 +1,74 @@
+//! `wasm-bindgen` exports for looking words up in the browser (`wasm`
+//! feature). Dictionaries are read from bytes already in memory or, through
+//! [`JsRangeSource`], from a JavaScript range-read function.
+
+use std::sync::Arc;
+
+use js_sys::Function;
+use wasm_bindgen::prelude::*;
+
+use crate::io::js::JsRangeSource;
+use crate::io::{ByteSource, ByteSourceReader};
+use crate::Mdict;
+
+/// An MDX or MDD dictionary, exported to JavaScript as `Mdict`.
+#[wasm_bindgen(js_name = Mdict)]
+pub struct WasmMdict {
+    mdict: Mdict<ByteSourceReader<Arc<dyn ByteSource>>>,
+}
+
+impl WasmMdict {
+    fn open(source: Arc<dyn ByteSource>) -> Result<Self, JsError> {
+        Ok(Self {
+            mdict: Mdict::from_source(source)?,
+        })
+    }
+}
+
+#[wasm_bindgen(js_class = Mdict)]
+impl WasmMdict {
+    /// Open a dictionary from its whole file, e.g. a fetched `ArrayBuffer`.
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<WasmMdict, JsError> {
+        Self::open(Arc::new(bytes))
+    }
+
+    /// Open a dictionary of `size` bytes read through `read(offset, length)`;
+    /// see [`JsRangeSource`].
+    #[wasm_bindgen(js_name = fromRangeReader)]
+    pub fn from_range_reader(size: f64, read: Function) -> Result<WasmMdict, JsError> {
+        if size < 0.0 || size.fract() != 0.0 {
+            return Err(JsError::new("size must be a whole number of bytes"));
+        }
+        Self::open(Arc::new(JsRangeSource::new(size as u64, read)))
+    }
+
+    /// Up to `limit` keys starting with `prefix`, in key order.
+    #[wasm_bindgen(js_name = searchPrefix)]
+    pub fn search_prefix(&mut self, prefix: &str, limit: usize) -> Result<Vec<String>, JsError> {
+        let key_blocks = self.mdict.search_keys_prefix(prefix)?.take(limit)?;
+        Ok(key_blocks
+            .into_iter()
+            .map(|key_block| key_block.key_text)
+            .collect())
+    }
+
+    /// The records of every entry for `key` as text, in key order. Empty
+    /// when `key` is missing; fails for MDD files.
+    pub fn lookup(&mut self, key: &str) -> Result<Vec<String>, JsError> {
+        let key_blocks = self.mdict.get_all(key)?;
+        let mut records = Vec::with_capacity(key_blocks.len());
+        for key_block in &key_blocks {
+            records.push(self.mdict.record_text_at_key_block(key_block)?);
+        }
+        Ok(records)
+    }
+
+    /// The bytes of the first entry for `key`, such as an MDD resource.
+    pub fn resource(&mut self, key: &str) -> Result<Option<Vec<u8>>, JsError> {
+        match self.mdict.get_all(key)?.first() {
+            Some(key_block) => Ok(Some(self.mdict.record_at_key_block(key_block)?)),
+            None => Ok(None),
+        }
+    }
+}
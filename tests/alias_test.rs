@@ -0,0 +1,71 @@
+#![cfg(all(feature = "mmap", feature = "zstd"))]
+
+use std::path::Path;
+
+use mdict_tools::error::MDictError;
+use mdict_tools::mdict_file::MdictBundle;
+use mdict_tools::mdict_optimized::open_mdict_optimized_bundle;
+use mdict_tools::mdx_conversion::aliases::KeyAliases;
+
+mod common;
+
+use common::key_texts;
+
+fn bundle(dir: &Path) -> MdictBundle {
+    common::bundle(&common::writer_with_keys(&["color", "water", "国"]), dir)
+}
+
+#[test]
+fn parses_alias_tables() {
+    let aliases = KeyAliases::parse("# alias\tkey\ncolour\tcolor\n國\t国\r\n\n").unwrap();
+    assert_eq!(aliases.len(), 2);
+    assert_eq!(aliases.resolve("colour"), Some("color"));
+    assert_eq!(aliases.resolve("國"), Some("国"));
+    assert_eq!(aliases.resolve("color"), None);
+    assert_eq!(KeyAliases::parse(&aliases.to_tsv()).unwrap(), aliases);
+
+    assert!(matches!(
+        KeyAliases::parse("colour color\n"),
+        Err(MDictError::InvalidFormat(_))
+    ));
+}
+
+#[test]
+fn bundle_lookup_follows_aliases() {
+    let dir = tempfile::tempdir().unwrap();
+    let bundle = bundle(dir.path());
+    assert!(bundle.lookup("colour").unwrap().is_empty());
+
+    bundle.add_key_alias("colour".to_string(), "color".to_string());
+    let keys = bundle.lookup("colour").unwrap();
+    assert_eq!(key_texts(keys.clone()), ["color"]);
+    assert_eq!(bundle.record_at(keys[0].clone()).unwrap(), b"<p>color</p>");
+
+    bundle.clear_key_aliases();
+    assert!(bundle.lookup("colour").unwrap().is_empty());
+}
+
+#[test]
+fn aliases_are_indexed_and_survive_in_the_bundle() {
+    let dir = tempfile::tempdir().unwrap();
+    let bundle = bundle(dir.path());
+    let table = dir.path().join("aliases.tsv");
+    std::fs::write(&table, "colour\tcolor\n國\t国\n").unwrap();
+    bundle
+        .load_key_aliases(table.to_string_lossy().to_string())
+        .unwrap();
+
+    let optimized = common::optimized_from_bundle(&bundle, dir.path());
+    let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+
+    let page = optimized.set_search_prefix_paged("colo", 10).unwrap();
+    assert_eq!(key_texts(page.results), ["color", "colour"]);
+    assert_eq!(optimized.resolve_alias("國").as_deref(), Some("国"));
+
+    optimized.save_bundle(path("dict.mdopt")).unwrap();
+    let reopened = open_mdict_optimized_bundle(path("dict.mdopt")).unwrap();
+    assert_eq!(reopened.resolve_alias("colour").as_deref(), Some("color"));
+    let keys = reopened.lookup("國");
+    assert_eq!(keys.len(), 1);
+    assert_eq!(reopened.record_at(keys[0].clone()).unwrap(), "<p>国</p>".as_bytes());
+}
@@ -0,0 +1,135 @@
+#![cfg(all(feature = "fs", feature = "zstd"))]
+
+use std::fs;
+
+use mdict_tools::annotations::{open_annotation_store, AnnotationStore};
+use mdict_tools::error::MDictError;
+use mdict_tools::packed_storage::{CompressionEncoding, PackedStorageWriter};
+use mdict_tools::types::{Annotation, EntryId, Highlight};
+
+fn store_path(dir: &tempfile::TempDir) -> String {
+    dir.path()
+        .join("dict.mdx.notes")
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn add_note(store: &AnnotationStore, entry_id: EntryId, note: &str) -> Annotation {
+    store.add(entry_id, note.to_string(), None).unwrap()
+}
+
+#[test]
+fn annotations_survive_reopening() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = store_path(&dir);
+    let cat = EntryId::new("猫", 0);
+    let dog = EntryId::new("犬", 0);
+    let yellow = Highlight {
+        start: 4,
+        end: 19,
+        color: 0xFFFF_EB3B,
+    };
+
+    let store = open_annotation_store(path.clone()).unwrap();
+    assert_eq!(store.count(), 0);
+    assert!(fs::metadata(&path).is_err(), "nothing is written before a change");
+    let note = add_note(&store, cat, "seen in chapter 3");
+    let highlight = store.add(cat, String::new(), Some(yellow)).unwrap();
+    let other = add_note(&store, dog, "🐕 compare 猫");
+    assert_eq!((note.id, highlight.id, other.id), (1, 2, 3));
+    assert!(note.created_ms > 0);
+    assert_eq!(note.created_ms, note.updated_ms);
+
+    let reopened = AnnotationStore::open(&path).unwrap();
+    assert_eq!(reopened.all(), store.all());
+    assert_eq!(reopened.for_entry(cat), [note.clone(), highlight.clone()]);
+    assert_eq!(reopened.get(3), Some(other));
+    assert_eq!(reopened.get(4), None);
+    let mut annotated = vec![cat, dog];
+    annotated.sort();
+    assert_eq!(reopened.annotated_entries(), annotated);
+    assert!(!dir.path().join("dict.mdx.notes.partial").exists());
+}
+
+#[test]
+fn updates_and_removals_are_saved() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = store_path(&dir);
+    let cat = EntryId::new("猫", 0);
+    let cat_again = EntryId::new("猫", 1);
+
+    let store = open_annotation_store(path.clone()).unwrap();
+    let first = add_note(&store, cat, "first");
+    let second = add_note(&store, cat, "second");
+    add_note(&store, cat_again, "the other 猫");
+
+    let green = Highlight {
+        start: 0,
+        end: 3,
+        color: 0xFF4C_AF50,
+    };
+    let updated = store
+        .update(first.id, "first, revised".to_string(), Some(green))
+        .unwrap();
+    assert_eq!(updated.id, first.id);
+    assert_eq!(updated.created_ms, first.created_ms);
+    assert!(updated.updated_ms >= first.updated_ms);
+    store.remove(second.id).unwrap();
+
+    let reopened = AnnotationStore::open(&path).unwrap();
+    assert_eq!(reopened.for_entry(cat), [updated]);
+    assert_eq!(reopened.count(), 2);
+
+    // Ids of removed annotations are not handed out again.
+    assert_eq!(add_note(&reopened, cat, "third").id, 4);
+    assert_eq!(reopened.remove_for_entry(cat).unwrap(), 2);
+    assert_eq!(reopened.remove_for_entry(cat).unwrap(), 0);
+    let reopened = AnnotationStore::open(&path).unwrap();
+    assert_eq!(reopened.annotated_entries(), [cat_again]);
+    assert_eq!(add_note(&reopened, cat, "fifth").id, 5);
+}
+
+#[test]
+fn invalid_changes_leave_the_store_alone() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = store_path(&dir);
+    let cat = EntryId::new("猫", 0);
+    let store = open_annotation_store(path.clone()).unwrap();
+    let note = add_note(&store, cat, "note");
+    let saved = fs::read(&path).unwrap();
+
+    let backwards = Highlight {
+        start: 9,
+        end: 2,
+        color: 0,
+    };
+    assert!(matches!(
+        store.add(cat, String::new(), Some(backwards)),
+        Err(MDictError::InvalidArgument(_))
+    ));
+    assert!(matches!(
+        store.update(note.id, String::new(), Some(backwards)),
+        Err(MDictError::InvalidArgument(_))
+    ));
+    assert!(matches!(
+        store.update(42, String::new(), None),
+        Err(MDictError::KeyNotFound(_))
+    ));
+    assert!(matches!(store.remove(42), Err(MDictError::KeyNotFound(_))));
+    assert_eq!(store.all(), [note]);
+    assert_eq!(fs::read(&path).unwrap(), saved);
+}
+
+#[test]
+fn other_packed_storage_files_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = store_path(&dir);
+    let mut writer = PackedStorageWriter::new(CompressionEncoding::Raw, 0, 64).unwrap();
+    writer.push_entry(b"not an annotation").unwrap();
+    fs::write(&path, writer.finish_into_bytes().unwrap()).unwrap();
+
+    assert!(matches!(
+        open_annotation_store(path),
+        Err(MDictError::InvalidFormat(_))
+    ));
+}
@@ -0,0 +1,70 @@
+#![cfg(all(feature = "async", feature = "fs"))]
+
+use mdict_tools::async_mdict::{AsyncMdict, TokioFileSource};
+use mdict_tools::synth::SynthDictBuilder;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn async_lookups_from_memory_and_file() {
+    let dict = SynthDictBuilder::entries(80)
+        .link_every(6)
+        .entries_per_key_block(9)
+        .entries_per_record_block(7)
+        .build()
+        .expect("build synthetic dictionary");
+
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let path = dir.path().join("synth.mdx");
+    dict.write_to(&path).expect("write synthetic dictionary");
+
+    let from_memory = AsyncMdict::open(dict.bytes.clone())
+        .await
+        .expect("open from memory");
+    let source = TokioFileSource::open(&path)
+        .await
+        .expect("open file source");
+    let from_file = AsyncMdict::open(source).await.expect("open from file");
+    assert_eq!(from_memory.num_entries(), 80);
+
+    for (key, record) in dict.entries.iter().step_by(7) {
+        let memory_key = from_memory
+            .lookup(key)
+            .await
+            .unwrap()
+            .expect("key in memory dict");
+        let file_key = from_file
+            .lookup(key)
+            .await
+            .unwrap()
+            .expect("key in file dict");
+        assert_eq!(&memory_key.key_text, key);
+        assert_eq!(&from_memory.record_at(memory_key).await.unwrap(), record);
+        assert_eq!(&from_file.record_at(file_key).await.unwrap(), record);
+    }
+
+    assert!(from_memory.lookup("missing").await.unwrap().is_none());
+    let hits = from_file.search_prefix("word00004").await.unwrap();
+    assert_eq!(hits.len(), 10);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn concurrent_async_lookups() {
+    let dict = SynthDictBuilder::entries(120)
+        .entries_per_record_block(5)
+        .build()
+        .expect("build synthetic dictionary");
+    let mdict = AsyncMdict::open(dict.bytes.clone()).await.expect("open");
+
+    let tasks: Vec<_> = (0..120)
+        .map(|index| {
+            let mdict = mdict.clone();
+            tokio::spawn(async move {
+                let key = mdict.get(index).await.unwrap().expect("key at index");
+                mdict.record_at(key).await.unwrap()
+            })
+        })
+        .collect();
+
+    for (index, task) in tasks.into_iter().enumerate() {
+        assert_eq!(task.await.unwrap(), dict.entries[index].1);
+    }
+}
@@ -0,0 +1,42 @@
+use mdict_tools::audit::{audit, extract_resource_references};
+use mdict_tools::synth::SynthDictBuilder;
+use mdict_tools::types::MdictVersion;
+
+#[test]
+fn audit_reports_unreferenced_mdd_resources() {
+    let mdx = SynthDictBuilder::entries(30).link_every(3).build().unwrap();
+    let mdd = SynthDictBuilder::entries(4)
+        .version(MdictVersion::MDD)
+        .build()
+        .unwrap();
+
+    let mut mdx_dict = mdx.open().unwrap();
+    let mut mdd_dict = mdd.open().unwrap();
+    let report = audit(&mut mdx_dict, Some(&mut mdd_dict)).expect("audit");
+
+    assert!(report.dangling_links.is_empty());
+    assert!(report.unreferenced_record_ranges.is_empty());
+    assert!(report.missing_resources.is_empty());
+    let expected = mdd
+        .entries
+        .iter()
+        .map(|(k, _)| k.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(report.unreferenced_resources, expected);
+}
+
+#[test]
+fn resource_references_are_normalized_to_mdd_keys() {
+    let html = r#"<img src="images/a.png"><a href="sound://voice/b.spx">x</a>
+        <a href="entry://other">y</a><link href="https://example.com/c.css">
+        <div style="background: url('bg.jpg')"></div>"#;
+
+    assert_eq!(
+        extract_resource_references(html),
+        vec![
+            "\\images\\a.png".to_string(),
+            "\\voice\\b.spx".to_string(),
+            "\\bg.jpg".to_string(),
+        ]
+    );
+}
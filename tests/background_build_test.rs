@@ -0,0 +1,136 @@
+#![cfg(all(feature = "mmap", feature = "threads", feature = "zstd"))]
+
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use mdict_tools::error::MDictError;
+use mdict_tools::mdict_optimized::{start_build_optimized, BuildHandle, BuildProgressCallback};
+use mdict_tools::mdx_conversion::build_manifest::{self, BuildManifest, BuildStage};
+use mdict_tools::types::BuildProgressStage;
+use mdict_tools::MdictBundle;
+
+mod common;
+
+fn linked_bundle(dir: &Path) -> Arc<MdictBundle> {
+    let writer =
+        common::writer_with_entries(&[("たべる", "<p>eat</p>"), ("食べる", "@@@LINK=たべる")]);
+    Arc::new(common::bundle(&writer, dir))
+}
+
+fn start(
+    bundle: &Arc<MdictBundle>,
+    dir: &Path,
+    callback: Option<Box<dyn BuildProgressCallback>>,
+) -> BuildHandle {
+    let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+    start_build_optimized(
+        Arc::clone(bundle),
+        path("index.fst"),
+        path("readings.dat"),
+        path("records.dat"),
+        callback,
+    )
+    .expect("start build")
+}
+
+struct SendStages(Mutex<Sender<BuildProgressStage>>);
+
+impl BuildProgressCallback for SendStages {
+    fn on_progress(&self, stage: BuildProgressStage, _completed: u64, _total: u64) {
+        let _ = self.0.lock().unwrap().send(stage);
+    }
+}
+
+/// Blocks the build when it reports `BuildReadings` until released.
+struct PauseAtReadings {
+    reached: Mutex<Sender<()>>,
+    release: Mutex<Receiver<()>>,
+}
+
+impl BuildProgressCallback for PauseAtReadings {
+    fn on_progress(&self, stage: BuildProgressStage, _completed: u64, _total: u64) {
+        if stage == BuildProgressStage::BuildReadings {
+            self.reached.lock().unwrap().send(()).unwrap();
+            self.release.lock().unwrap().recv().unwrap();
+        }
+    }
+}
+
+#[test]
+fn background_build_reports_progress_and_returns_index() {
+    let dir = tempfile::tempdir().unwrap();
+    let bundle = linked_bundle(dir.path());
+    let (sender, stages) = channel();
+    let handle = start(
+        &bundle,
+        dir.path(),
+        Some(Box::new(SendStages(Mutex::new(sender)))),
+    );
+
+    let optimized = handle.join().expect("build succeeds");
+    assert!(handle.is_finished());
+    assert_eq!(
+        stages.try_iter().collect::<Vec<_>>(),
+        vec![
+            BuildProgressStage::Start,
+            BuildProgressStage::BuildReadings,
+            BuildProgressStage::BuildFst,
+            BuildProgressStage::Done,
+        ]
+    );
+    let page = optimized.set_search_prefix_paged("食", 10).unwrap();
+    assert_eq!(page.results.len(), 1);
+    assert_eq!(
+        optimized.record_at(page.results[0].clone()).unwrap(),
+        b"<p>eat</p>"
+    );
+}
+
+#[test]
+fn build_can_only_be_joined_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let handle = start(&linked_bundle(dir.path()), dir.path(), None);
+
+    handle.join().expect("build succeeds");
+    assert!(matches!(handle.join(), Err(MDictError::InvalidArgument(_))));
+}
+
+#[test]
+fn cancelled_build_stops_and_can_resume() {
+    let dir = tempfile::tempdir().unwrap();
+    let bundle = linked_bundle(dir.path());
+    let (reached_sender, reached) = channel();
+    let (release, release_receiver) = channel();
+    let handle = start(
+        &bundle,
+        dir.path(),
+        Some(Box::new(PauseAtReadings {
+            reached: Mutex::new(reached_sender),
+            release: Mutex::new(release_receiver),
+        })),
+    );
+
+    reached.recv().unwrap();
+    handle.cancel();
+    release.send(()).unwrap();
+    assert!(matches!(handle.join(), Err(MDictError::Cancelled(_))));
+    assert!(!dir.path().join("index.fst").exists());
+
+    let fst_path = dir.path().join("index.fst");
+    let manifest = BuildManifest::read(build_manifest::manifest_path(&fst_path))
+        .expect("cancelled build leaves its manifest");
+    assert_eq!(manifest.stage, BuildStage::Readings);
+
+    let optimized = start(&bundle, dir.path(), None)
+        .join()
+        .expect("resumed build succeeds");
+    assert_eq!(
+        optimized
+            .set_search_prefix_paged("た", 10)
+            .unwrap()
+            .results
+            .len(),
+        1
+    );
+}
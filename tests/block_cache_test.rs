@@ -0,0 +1,193 @@
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+use mdict_tools::block_cache::{BlockCache, CacheCapacity};
+use mdict_tools::synth::SynthDictBuilder;
+use mdict_tools::{Mdict, OpenOptions};
+
+/// Counts the reads made through it, to tell cache hits from misses.
+struct CountingReader {
+    inner: Cursor<Vec<u8>>,
+    reads: usize,
+}
+
+impl Read for CountingReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.reads += 1;
+        self.inner.read(buf)
+    }
+}
+
+impl Seek for CountingReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+#[test]
+fn entry_capacity_evicts_least_recently_used() {
+    let mut cache = BlockCache::new(2);
+    cache.insert(0, "a", 1);
+    cache.insert(1, "b", 1);
+    assert_eq!(cache.get(0), Some(&"a"));
+
+    cache.insert(2, "c", 1);
+    assert!(cache.contains(0));
+    assert!(!cache.contains(1));
+    assert!(cache.contains(2));
+    assert_eq!(cache.len(), 2);
+}
+
+#[test]
+fn zero_entries_disables_the_cache() {
+    let mut cache = BlockCache::new(0);
+    cache.insert(0, vec![0u8; 4], 4);
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn byte_budget_evicts_until_within_budget() {
+    let mut cache = BlockCache::new(CacheCapacity::Bytes(10));
+    cache.insert(0, (), 4);
+    cache.insert(1, (), 4);
+    cache.insert(2, (), 4);
+    assert!(!cache.contains(0));
+    assert_eq!(cache.total_weight(), 8);
+
+    // An oversized block still replaces everything else.
+    cache.insert(3, (), 25);
+    assert_eq!(cache.len(), 1);
+    assert!(cache.contains(3));
+
+    cache.set_capacity(CacheCapacity::Entries(0));
+    assert!(cache.is_empty());
+    assert_eq!(cache.total_weight(), 0);
+}
+
+#[test]
+fn mdict_records_match_across_cache_capacities() {
+    let dict = SynthDictBuilder::entries(60)
+        .entries_per_key_block(6)
+        .entries_per_record_block(5)
+        .build()
+        .expect("build synthetic dictionary");
+
+    let capacities = [
+        CacheCapacity::Entries(0),
+        CacheCapacity::Entries(3),
+        CacheCapacity::Bytes(256),
+        CacheCapacity::Entries(usize::MAX),
+    ];
+    for capacity in capacities {
+        let mut mdict = Mdict::new_with_cache(Cursor::new(dict.bytes.clone()), capacity)
+            .expect("open synthetic dictionary");
+        assert_eq!(mdict.record_block_cache_capacity(), capacity);
+
+        // Walk backwards and forwards so eviction actually happens.
+        let order = (0..60).rev().chain(0..60);
+        for index in order {
+            let record = mdict.record_at_index(index).expect("record");
+            assert_eq!(
+                record, dict.entries[index].1,
+                "{:?} index {}",
+                capacity, index
+            );
+        }
+    }
+}
+
+#[test]
+#[allow(deprecated)]
+fn record_block_cache_limit_forwards_to_capacity() {
+    let dict = SynthDictBuilder::entries(10)
+        .build()
+        .expect("build synthetic dictionary");
+    let mut mdict = Mdict::new(Cursor::new(dict.bytes.clone())).expect("open synthetic dictionary");
+
+    mdict.set_record_block_cache_limit(4);
+    assert_eq!(mdict.record_block_cache_capacity(), CacheCapacity::Entries(4));
+    assert_eq!(mdict.record_block_cache_limit(), 4);
+
+    mdict.set_record_block_cache_capacity(CacheCapacity::Bytes(1024));
+    assert_eq!(mdict.record_block_cache_limit(), usize::MAX);
+}
+
+#[test]
+fn record_refs_share_the_cached_block() {
+    let dict = SynthDictBuilder::entries(20)
+        .entries_per_record_block(5)
+        .build()
+        .expect("build synthetic dictionary");
+
+    for capacity in [CacheCapacity::Entries(0), CacheCapacity::Entries(4)] {
+        let mut mdict = Mdict::new_with_cache(Cursor::new(dict.bytes.clone()), capacity)
+            .expect("open synthetic dictionary");
+
+        let refs: Vec<_> = (0..20)
+            .map(|index| mdict.record_ref_at_index(index).expect("record"))
+            .collect();
+        for (index, record) in refs.iter().enumerate() {
+            assert_eq!(record.as_bytes(), dict.entries[index].1.as_slice());
+            assert_eq!(record.block_index(), index / 5);
+            assert_eq!(record.to_vec(), mdict.record_at_index(index).unwrap());
+        }
+
+        if capacity != CacheCapacity::Entries(0) {
+            // Neighbours in one cached block point into the same buffer.
+            let (first, second) = (&refs[0], &refs[1]);
+            let first_start = first.as_bytes().as_ptr() as usize - first.range().start;
+            let second_start = second.as_bytes().as_ptr() as usize - second.range().start;
+            assert_eq!(first_start, second_start);
+        }
+
+        let key = mdict.get(7).unwrap().unwrap();
+        let by_key = mdict.record_ref_at_key_block(&key).unwrap();
+        assert_eq!(&*by_key, dict.entries[7].1.as_slice());
+    }
+}
+
+#[test]
+fn alternating_key_lookups_stay_cached() {
+    let dict = SynthDictBuilder::entries(40)
+        .entries_per_key_block(10)
+        .build()
+        .expect("build synthetic dictionary");
+    let open = |options: OpenOptions| {
+        let reader = CountingReader {
+            inner: Cursor::new(dict.bytes.clone()),
+            reads: 0,
+        };
+        Mdict::new_with_options(reader, options).expect("open synthetic dictionary")
+    };
+    let alternate = |mdict: &mut Mdict<CountingReader>| {
+        for _ in 0..2 {
+            for index in [3, 33] {
+                let key = mdict.key_block_index.get(&mut mdict.reader, index).unwrap();
+                assert_eq!(key.unwrap().key_text, dict.entries[index].0);
+            }
+        }
+        let reads = mdict.reader.reads;
+        for _ in 0..10 {
+            for index in [5, 35] {
+                mdict.key_block_index.get(&mut mdict.reader, index).unwrap();
+            }
+        }
+        mdict.reader.reads - reads
+    };
+
+    let mut mdict = open(OpenOptions::default());
+    assert_eq!(mdict.key_block_cache_capacity(), CacheCapacity::Entries(8));
+    assert_eq!(alternate(&mut mdict), 0);
+
+    let mut single = open(OpenOptions {
+        key_cache_capacity: Some(CacheCapacity::Entries(1)),
+        ..OpenOptions::default()
+    });
+    assert!(alternate(&mut single) >= 20);
+
+    let both = open(OpenOptions {
+        cache_capacity: Some(CacheCapacity::Entries(3)),
+        ..OpenOptions::default()
+    });
+    assert_eq!(both.key_block_cache_capacity(), CacheCapacity::Entries(3));
+    assert_eq!(both.record_block_cache_capacity(), CacheCapacity::Entries(3));
+}
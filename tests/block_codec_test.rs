@@ -0,0 +1,78 @@
+use std::io::Cursor;
+use std::sync::Arc;
+
+use mdict_tools::error::{MDictError, Result};
+use mdict_tools::format::{
+    register_block_codec, registered_block_encodings, unregister_block_codec, BlockCodec,
+};
+use mdict_tools::mdx_writer::{BlockCompression, MdxWriter};
+use mdict_tools::Mdict;
+
+const XOR_ENCODING: u32 = 7;
+const XOR_KEY: u8 = 0x5a;
+
+/// A stand-in for a fork's compression: the payload XORed with a byte.
+struct XorCodec;
+
+impl BlockCodec for XorCodec {
+    fn decode(&self, payload: &[u8], _decompressed_size: Option<usize>) -> Result<Vec<u8>> {
+        Ok(payload.iter().map(|byte| byte ^ XOR_KEY).collect())
+    }
+}
+
+/// A dictionary whose record blocks use the XOR "compression".
+fn xor_dictionary() -> Vec<u8> {
+    let mut writer = MdxWriter::new()
+        .compression(BlockCompression::None)
+        .entries_per_record_block(2);
+    for i in 0..6 {
+        writer
+            .add(format!("key{}", i), &format!("<p>record {}</p>", i))
+            .unwrap();
+    }
+    let mut bytes = writer.to_bytes().unwrap();
+
+    let mut mdict = Mdict::new(Cursor::new(bytes.clone())).unwrap();
+    let records = mdict.record_section().unwrap();
+    for pair in records.record_index_prefix_sum.windows(2) {
+        let start = (records.record_data_offset + pair[0].compressed_size) as usize;
+        let end = (records.record_data_offset + pair[1].compressed_size) as usize;
+        bytes[start..start + 4].copy_from_slice(&XOR_ENCODING.to_le_bytes());
+        for byte in &mut bytes[start + 8..end] {
+            *byte ^= XOR_KEY;
+        }
+    }
+    bytes
+}
+
+#[test]
+fn registered_codecs_decode_custom_blocks() {
+    let bytes = xor_dictionary();
+    let mut mdict = Mdict::new(Cursor::new(bytes)).unwrap();
+
+    let err = mdict.record_at_index(3).unwrap_err();
+    match &err {
+        MDictError::UnsupportedFeature(message) => {
+            assert!(message.contains("compression type 7"), "{}", message);
+            assert!(message.contains("0x00000007"), "{}", message);
+        }
+        other => panic!("expected UnsupportedFeature, got {:?}", other),
+    }
+
+    register_block_codec(XOR_ENCODING, Arc::new(XorCodec)).unwrap();
+    assert!(registered_block_encodings().contains(&XOR_ENCODING));
+    assert_eq!(mdict.record_at_index(3).unwrap(), b"<p>record 3</p>");
+    let entries = mdict.iter_entries().collect::<Result<Vec<_>>>().unwrap();
+    assert_eq!(entries.len(), 6);
+
+    assert!(unregister_block_codec(XOR_ENCODING));
+    assert!(!unregister_block_codec(XOR_ENCODING));
+}
+
+#[test]
+fn built_in_and_out_of_range_types_are_rejected() {
+    for encoding in [0, 1, 2, 4, 16] {
+        let err = register_block_codec(encoding, Arc::new(XorCodec)).unwrap_err();
+        assert!(matches!(err, MDictError::InvalidArgument(_)), "{:?}", err);
+    }
+}
@@ -0,0 +1,137 @@
+#![cfg(all(feature = "mmap", feature = "zstd"))]
+
+use std::io::Cursor;
+use std::path::Path;
+
+use mdict_tools::mdx_conversion::normalize::NormalizerPipeline;
+use mdict_tools::mdx_conversion::reindexing::{
+    build_readings_list, build_readings_list_checked, build_readings_list_with_budget_checked,
+};
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::types::{BrokenLink, BrokenLinkPolicy};
+use mdict_tools::Mdict;
+
+mod common;
+
+fn pets_bytes() -> Vec<u8> {
+    let mut writer = MdxWriter::new();
+    let entries = [
+        ("cat", "<p>feline</p>"),
+        ("dgo", "@@@LINK=dog"),
+        ("dog", "<p>canine</p>"),
+        ("doggy", "@@@LINK=dgo"),
+        ("kitty", "@@@LINK=cta"),
+        ("puppy", "@@@LINK=wolfhound"),
+    ];
+    for (key, html) in entries {
+        writer.add(key, html).unwrap();
+    }
+    writer.to_bytes().unwrap()
+}
+
+fn pets() -> Mdict<Cursor<Vec<u8>>> {
+    Mdict::new(Cursor::new(pets_bytes())).unwrap()
+}
+
+fn broken(key_text: &str, target: &str, repaired_to: Option<&str>) -> BrokenLink {
+    BrokenLink {
+        key_text: key_text.to_string(),
+        target: target.to_string(),
+        repaired_to: repaired_to.map(str::to_string),
+    }
+}
+
+fn key_id(mdict: &mut Mdict<Cursor<Vec<u8>>>, key_text: &str) -> u64 {
+    mdict.get_all(key_text).unwrap()[0].key_id
+}
+
+#[test]
+fn strict_reports_links_without_changing_readings() {
+    let mut mdict = pets();
+    let (readings_list, links) = build_readings_list_checked(
+        &mut mdict,
+        &NormalizerPipeline::default(),
+        BrokenLinkPolicy::Strict,
+    )
+    .unwrap();
+    assert_eq!(
+        links,
+        [
+            broken("kitty", "cta", None),
+            broken("puppy", "wolfhound", None),
+        ]
+    );
+    assert_eq!(readings_list, build_readings_list(&mut mdict).unwrap());
+
+    let (_, links) = build_readings_list_checked(
+        &mut mdict,
+        &NormalizerPipeline::default(),
+        BrokenLinkPolicy::Lenient,
+    )
+    .unwrap();
+    assert!(links.is_empty());
+}
+
+#[test]
+fn repair_retargets_links_to_the_closest_key() {
+    let mut mdict = pets();
+    let (readings_list, links) = build_readings_list_checked(
+        &mut mdict,
+        &NormalizerPipeline::default(),
+        BrokenLinkPolicy::Repair { max_distance: 2 },
+    )
+    .unwrap();
+    assert_eq!(
+        links,
+        [
+            broken("kitty", "cta", Some("cat")),
+            broken("puppy", "wolfhound", None),
+        ]
+    );
+
+    let cat = key_id(&mut mdict, "cat");
+    assert!(readings_list[&cat].contains("kitty"));
+    let puppy = key_id(&mut mdict, "puppy");
+    assert!(readings_list[&puppy].contains("puppy"));
+}
+
+#[test]
+fn budgeted_build_reports_the_same_links() {
+    let mut mdict = pets();
+    for policy in [
+        BrokenLinkPolicy::Strict,
+        BrokenLinkPolicy::Repair { max_distance: 2 },
+    ] {
+        let normalizer = NormalizerPipeline::default();
+        let expected = build_readings_list_checked(&mut mdict, &normalizer, policy).unwrap();
+        let budgeted =
+            build_readings_list_with_budget_checked(&mut mdict, 64, &normalizer, policy).unwrap();
+        assert_eq!(budgeted, expected);
+    }
+}
+
+fn build(dir: &Path, policy: BrokenLinkPolicy) -> Vec<BrokenLink> {
+    let mdx_path = dir.join("pets.mdx");
+    std::fs::write(&mdx_path, pets_bytes()).unwrap();
+    let bundle = common::open_bundle(&mdx_path);
+    bundle.set_broken_link_policy(policy);
+
+    common::optimized_from_bundle(&bundle, dir).broken_links()
+}
+
+#[test]
+fn optimized_builds_list_broken_links_on_the_built_index() {
+    let dir = tempfile::tempdir().unwrap();
+    let expected = [
+        broken("kitty", "cta", Some("cat")),
+        broken("puppy", "wolfhound", None),
+    ];
+    let repair = BrokenLinkPolicy::Repair { max_distance: 2 };
+    assert_eq!(build(dir.path(), repair), expected);
+    // A finished build is not redone, but its links are still listed.
+    assert_eq!(build(dir.path(), repair), expected);
+
+    // Changing the policy rebuilds; lenient builds do not check links.
+    assert!(build(dir.path(), BrokenLinkPolicy::Lenient).is_empty());
+    assert!(!dir.path().join("index.fst.broken-links").exists());
+}
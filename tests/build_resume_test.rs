@@ -0,0 +1,201 @@
+#![cfg(all(feature = "mmap", feature = "zstd"))]
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use mdict_tools::mdict_file::create_mdict_bundle;
+use mdict_tools::mdict_optimized::create_mdict_optimized_from_bundle;
+use mdict_tools::mdx_conversion::build_manifest::{
+    self, BuildManifest, BuildStage, READINGS_LIST_FILE,
+};
+use mdict_tools::mdx_conversion::readings::{
+    read_readings_list_checkpoint, write_readings_list_checkpoint,
+};
+use mdict_tools::mdx_conversion::reindexing::build_readings_list_from_path;
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::MdictBundle;
+
+struct Outputs {
+    fst: PathBuf,
+    readings: PathBuf,
+    records: PathBuf,
+}
+
+impl Outputs {
+    fn in_dir(dir: &Path) -> Self {
+        Self {
+            fst: dir.join("index.fst"),
+            readings: dir.join("readings.dat"),
+            records: dir.join("records.dat"),
+        }
+    }
+
+    fn contents(&self) -> Vec<Vec<u8>> {
+        [&self.fst, &self.readings, &self.records]
+            .iter()
+            .map(|path| std::fs::read(path).expect("read output"))
+            .collect()
+    }
+
+    fn remove(&self) {
+        for path in [&self.fst, &self.readings, &self.records] {
+            std::fs::remove_file(path).expect("remove output");
+        }
+    }
+}
+
+fn write_linked_mdx(path: &Path) {
+    let mut writer = MdxWriter::new();
+    writer.add("たべる", "<p>eat</p>").unwrap();
+    writer.add("のむ", "<p>drink</p>").unwrap();
+    writer.add("食べる", "@@@LINK=たべる").unwrap();
+    writer.add("飲む", "@@@LINK=のむ").unwrap();
+    writer.write_to_path(path).unwrap();
+}
+
+fn build(bundle: &MdictBundle, outputs: &Outputs) {
+    let path = |path: &PathBuf| path.to_string_lossy().to_string();
+    create_mdict_optimized_from_bundle(
+        bundle,
+        path(&outputs.fst),
+        path(&outputs.readings),
+        path(&outputs.records),
+    )
+    .expect("build optimized index");
+}
+
+fn open_bundle(mdx_path: &Path) -> MdictBundle {
+    create_mdict_bundle(mdx_path.to_string_lossy().to_string(), String::new()).expect("open bundle")
+}
+
+#[test]
+fn finished_build_records_manifest_and_drops_checkpoint() {
+    let dir = tempfile::tempdir().unwrap();
+    let mdx_path = dir.path().join("linked.mdx");
+    write_linked_mdx(&mdx_path);
+    let outputs = Outputs::in_dir(dir.path());
+    build(&open_bundle(&mdx_path), &outputs);
+
+    let manifest =
+        BuildManifest::read(build_manifest::manifest_path(&outputs.fst)).expect("manifest written");
+    assert_eq!(manifest.stage, BuildStage::Done);
+    assert!(manifest.file_is_intact(build_manifest::FST_FILE, &outputs.fst));
+    assert!(manifest.file_is_intact(build_manifest::READINGS_FILE, &outputs.readings));
+    assert!(manifest.file_is_intact(build_manifest::RECORDS_FILE, &outputs.records));
+    assert!(!manifest.files.contains_key(READINGS_LIST_FILE));
+    assert!(!build_manifest::readings_list_checkpoint_path(&outputs.fst).exists());
+}
+
+#[test]
+fn intact_outputs_are_not_rebuilt() {
+    let dir = tempfile::tempdir().unwrap();
+    let mdx_path = dir.path().join("linked.mdx");
+    write_linked_mdx(&mdx_path);
+    let outputs = Outputs::in_dir(dir.path());
+    let bundle = open_bundle(&mdx_path);
+    build(&bundle, &outputs);
+    let modified = std::fs::metadata(&outputs.records)
+        .unwrap()
+        .modified()
+        .unwrap();
+
+    build(&bundle, &outputs);
+    assert_eq!(
+        std::fs::metadata(&outputs.records)
+            .unwrap()
+            .modified()
+            .unwrap(),
+        modified
+    );
+}
+
+#[test]
+fn damaged_outputs_are_rebuilt() {
+    let dir = tempfile::tempdir().unwrap();
+    let mdx_path = dir.path().join("linked.mdx");
+    write_linked_mdx(&mdx_path);
+    let outputs = Outputs::in_dir(dir.path());
+    let bundle = open_bundle(&mdx_path);
+    build(&bundle, &outputs);
+    let expected = outputs.contents();
+
+    std::fs::write(&outputs.readings, b"truncated").unwrap();
+    build(&bundle, &outputs);
+    assert_eq!(outputs.contents(), expected);
+}
+
+#[test]
+fn build_resumes_from_readings_checkpoint() {
+    let dir = tempfile::tempdir().unwrap();
+    let mdx_path = dir.path().join("linked.mdx");
+    write_linked_mdx(&mdx_path);
+    let outputs = Outputs::in_dir(dir.path());
+    let bundle = open_bundle(&mdx_path);
+    build(&bundle, &outputs);
+    let expected = outputs.contents();
+    let manifest_path = build_manifest::manifest_path(&outputs.fst);
+    let fingerprint = BuildManifest::read(&manifest_path).unwrap().fingerprint;
+
+    // Leave the tree as a build interrupted after the readings pass would.
+    outputs.remove();
+    let checkpoint_path = build_manifest::readings_list_checkpoint_path(&outputs.fst);
+    let readings_list = build_readings_list_from_path(&mdx_path).unwrap();
+    assert!(!readings_list.is_empty());
+    write_readings_list_checkpoint(&readings_list, &checkpoint_path).unwrap();
+    let mut manifest = BuildManifest::new(fingerprint, BuildStage::Readings);
+    manifest
+        .record_file(READINGS_LIST_FILE, &checkpoint_path)
+        .unwrap();
+    manifest.write(&manifest_path).unwrap();
+
+    build(&bundle, &outputs);
+    assert_eq!(outputs.contents(), expected);
+    assert!(!checkpoint_path.exists());
+    assert_eq!(
+        BuildManifest::read(&manifest_path).unwrap().stage,
+        BuildStage::Done
+    );
+}
+
+#[test]
+fn checkpoint_from_another_source_is_ignored() {
+    let dir = tempfile::tempdir().unwrap();
+    let mdx_path = dir.path().join("linked.mdx");
+    write_linked_mdx(&mdx_path);
+    let outputs = Outputs::in_dir(dir.path());
+    let bundle = open_bundle(&mdx_path);
+    build(&bundle, &outputs);
+    let expected = outputs.contents();
+    outputs.remove();
+
+    let checkpoint_path = build_manifest::readings_list_checkpoint_path(&outputs.fst);
+    let stale = HashMap::from([(0u64, HashSet::from(["stale".to_string()]))]);
+    write_readings_list_checkpoint(&stale, &checkpoint_path).unwrap();
+    let mut manifest = BuildManifest::new("not-this-source".to_string(), BuildStage::Readings);
+    manifest
+        .record_file(READINGS_LIST_FILE, &checkpoint_path)
+        .unwrap();
+    manifest
+        .write(build_manifest::manifest_path(&outputs.fst))
+        .unwrap();
+
+    build(&bundle, &outputs);
+    assert_eq!(outputs.contents(), expected);
+}
+
+#[test]
+fn readings_list_checkpoint_round_trips() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("list.checkpoint");
+    let readings_list = HashMap::from([
+        (
+            3u64,
+            HashSet::from(["たべる".to_string(), "くう".to_string()]),
+        ),
+        (0u64, HashSet::from(["のむ".to_string()])),
+        (70_000u64, HashSet::new()),
+    ]);
+
+    write_readings_list_checkpoint(&readings_list, &path).unwrap();
+    assert_eq!(read_readings_list_checkpoint(&path).unwrap(), readings_list);
+}
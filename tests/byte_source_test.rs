@@ -0,0 +1,209 @@
+#![cfg(feature = "mmap")]
+
+use std::fs::File;
+use std::sync::Arc;
+
+use mdict_tools::io::{ByteSource, ByteSourceReader, FileSource};
+use mdict_tools::seekable_mmap::SeekableMmap;
+use mdict_tools::synth::{SynthDict, SynthDictBuilder};
+use mdict_tools::{Mdict, MdictShared};
+
+mod common;
+
+fn synth() -> SynthDict {
+    common::synth(
+        SynthDictBuilder::entries(70)
+            .link_every(9)
+            .entries_per_key_block(8)
+            .entries_per_record_block(6),
+    )
+}
+
+fn assert_all_records<R: std::io::Read + std::io::Seek>(mdict: &mut Mdict<R>, dict: &SynthDict) {
+    for (index, (key, record)) in dict.entries.iter().enumerate() {
+        let key_block = mdict
+            .key_block_index
+            .get(&mut mdict.reader, index)
+            .unwrap()
+            .expect("key at index");
+        assert_eq!(&key_block.key_text, key);
+        assert_eq!(&mdict.record_at_key_block(&key_block).unwrap(), record);
+    }
+}
+
+#[test]
+fn open_from_bytes_mmap_and_file() {
+    let dict = synth();
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let path = dir.path().join("synth.mdx");
+    dict.write_to(&path).expect("write synthetic dictionary");
+
+    let mut from_bytes = Mdict::from_bytes(&dict.bytes).expect("open from bytes");
+    assert_all_records(&mut from_bytes, &dict);
+
+    let file = File::open(&path).expect("open file");
+    let mmap = unsafe { memmap2::Mmap::map(&file) }.expect("map file");
+    let mut from_mmap = Mdict::from_mmap(mmap).expect("open from mmap");
+    assert_all_records(&mut from_mmap, &dict);
+
+    let source = FileSource::new(file).expect("inspect file");
+    assert_eq!(source.size(), dict.bytes.len() as u64);
+    let mut from_file = Mdict::from_source(source).expect("open from file");
+    assert_all_records(&mut from_file, &dict);
+}
+
+#[test]
+fn mapped_key_blocks_are_read_without_the_cursor() {
+    let dict = synth();
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let path = dir.path().join("synth.mdx");
+    dict.write_to(&path).expect("write synthetic dictionary");
+
+    let mmap = SeekableMmap::open(&File::open(&path).unwrap()).expect("map file");
+    let mut mdict = Mdict::from_seekable_mmap(mmap).expect("open mapped");
+    assert!(mdict.key_block_index.is_mapped());
+
+    let position = mdict.reader.position();
+    let last = dict.entries.len() - 1;
+    let key_block = mdict
+        .key_block_index
+        .get(&mut mdict.reader, last)
+        .unwrap()
+        .expect("key at index");
+    assert_eq!(key_block.key_text, dict.entries[last].0);
+    assert_eq!(mdict.reader.position(), position);
+    assert_all_records(&mut mdict, &dict);
+
+    let shared = MdictShared::new(mdict);
+    assert!(shared.handle().key_block_index.is_mapped());
+
+    let mut unmapped = Mdict::new(SeekableMmap::open(&File::open(&path).unwrap()).unwrap())
+        .expect("open through the cursor");
+    assert!(!unmapped.key_block_index.is_mapped());
+    assert_all_records(&mut unmapped, &dict);
+}
+
+#[test]
+fn mapped_records_are_read_by_offset() {
+    let dict = synth();
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let path = dir.path().join("synth.mdx");
+    dict.write_to(&path).expect("write synthetic dictionary");
+
+    let mmap = SeekableMmap::open(&File::open(&path).unwrap()).expect("map file");
+    let mut seeked = mmap.clone();
+    std::io::Seek::seek(&mut seeked, std::io::SeekFrom::Start(7)).unwrap();
+    let mut head = [0u8; 4];
+    seeked.read_exact_at(0, &mut head).unwrap();
+    assert_eq!(head, dict.bytes[..4]);
+    assert_eq!(seeked.position(), 7);
+
+    let mut mdict = Mdict::from_seekable_mmap(mmap).expect("open mapped");
+    assert!(mdict.has_positional_source());
+    let position = mdict.reader.position();
+    assert_all_records(&mut mdict, &dict);
+    assert_eq!(mdict.reader.position(), position);
+
+    let shared = Arc::new(MdictShared::new(mdict));
+    assert!(shared.handle().has_positional_source());
+    let threads = (0..4)
+        .map(|thread| {
+            let shared = Arc::clone(&shared);
+            let entries = dict.entries.clone();
+            std::thread::spawn(move || {
+                for index in (thread..entries.len()).step_by(4) {
+                    let key_block = shared.get(index).unwrap().expect("key at index");
+                    let record = shared.record_at_key_block(&key_block).unwrap();
+                    assert_eq!(record, entries[index].1);
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+}
+
+#[test]
+fn byte_source_reads_are_bounds_checked() {
+    let bytes = vec![1u8, 2, 3, 4];
+    let mut buf = [0u8; 2];
+    bytes.read_exact_at(2, &mut buf).unwrap();
+    assert_eq!(buf, [3, 4]);
+    assert!(bytes.read_exact_at(3, &mut buf).is_err());
+    assert!(bytes.read_exact_at(u64::MAX, &mut buf).is_err());
+}
+
+#[test]
+fn shared_over_byte_source() {
+    let dict = synth();
+    let source: Arc<[u8]> = Arc::from(dict.bytes.clone());
+    let mdict = Mdict::new(ByteSourceReader::new(source)).expect("open from Arc<[u8]>");
+    let shared = MdictShared::new(mdict);
+    let key_block = shared.get(5).unwrap().expect("key at index");
+    assert_eq!(
+        shared.record_at_key_block(&key_block).unwrap(),
+        dict.entries[5].1
+    );
+}
+
+#[cfg(feature = "http")]
+mod http {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::thread;
+
+    use mdict_tools::io::http::HttpRangeSource;
+    use mdict_tools::Mdict;
+
+    use super::{assert_all_records, synth};
+
+    /// Serve `body` over HTTP/1.1, answering `Range: bytes=a-b` with 206.
+    fn serve(body: Vec<u8>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.expect("accept");
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut range = None;
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap() == 0 || line == "\r\n" {
+                        break;
+                    }
+                    if let Some(value) = line.to_ascii_lowercase().strip_prefix("range: bytes=") {
+                        let (start, end) = value.trim().split_once('-').unwrap();
+                        range = Some((
+                            start.parse::<usize>().unwrap(),
+                            end.parse::<usize>().unwrap(),
+                        ));
+                    }
+                }
+                let (start, end) = range.expect("range header");
+                let end = end.min(body.len() - 1);
+                write!(
+                    stream,
+                    "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\nConnection: close\r\n\r\n",
+                    end - start + 1,
+                    start,
+                    end,
+                    body.len()
+                )
+                .unwrap();
+                stream.write_all(&body[start..=end]).unwrap();
+            }
+        });
+        format!("http://{}/synth.mdx", addr)
+    }
+
+    #[test]
+    fn open_over_http_ranges() {
+        let dict = synth();
+        let url = serve(dict.bytes.clone());
+        let source = HttpRangeSource::open(url).expect("probe server");
+        let mut mdict = Mdict::from_source(Arc::new(source)).expect("open over http");
+        assert_all_records(&mut mdict, &dict);
+    }
+}
@@ -0,0 +1,87 @@
+use std::io::Cursor;
+
+use mdict_tools::error::MDictError;
+use mdict_tools::mdx_writer::{BlockCompression, MdxWriter};
+use mdict_tools::{Mdict, OpenOptions};
+
+const STRICT: OpenOptions = OpenOptions {
+    verify_checksums: true,
+    cache_capacity: None,
+    key_cache_capacity: None,
+    lazy: false,
+};
+
+fn uncompressed_dictionary() -> Vec<u8> {
+    let mut writer = MdxWriter::new()
+        .compression(BlockCompression::None)
+        .entries_per_record_block(4);
+    for i in 0..12 {
+        writer
+            .add(format!("entry{:02}", i), &format!("<b>entry {}</b>", i))
+            .unwrap();
+    }
+    writer.to_bytes().unwrap()
+}
+
+fn header_size(bytes: &[u8]) -> usize {
+    4 + u32::from_be_bytes(bytes[..4].try_into().unwrap()) as usize + 4
+}
+
+fn open_strict(bytes: Vec<u8>) -> mdict_tools::error::Result<Mdict<Cursor<Vec<u8>>>> {
+    Mdict::new_with_options(Cursor::new(bytes), STRICT)
+}
+
+#[test]
+fn intact_dictionary_passes_strict_open() {
+    let mut mdict = open_strict(uncompressed_dictionary()).expect("strict open");
+    assert_eq!(mdict.record_at_index(11).unwrap(), b"<b>entry 11</b>");
+}
+
+#[test]
+fn corrupted_header_checksum_is_reported_in_strict_mode_only() {
+    let mut bytes = uncompressed_dictionary();
+    let checksum_at = header_size(&bytes) - 4;
+    bytes[checksum_at] ^= 0xff;
+
+    assert!(Mdict::new(Cursor::new(bytes.clone())).is_ok());
+    let err = open_strict(bytes).err().expect("strict open fails");
+    assert!(err.to_string().contains("header"), "{}", err);
+}
+
+#[test]
+fn corrupted_key_preamble_checksum_is_reported() {
+    let mut bytes = uncompressed_dictionary();
+    // Five u64 preamble fields precede the checksum.
+    let checksum_at = header_size(&bytes) + 40;
+    bytes[checksum_at] ^= 0xff;
+
+    assert!(Mdict::new(Cursor::new(bytes.clone())).is_ok());
+    let err = open_strict(bytes).err().expect("strict open fails");
+    assert!(err.to_string().contains("key section preamble"), "{}", err);
+}
+
+#[test]
+fn corrupted_record_block_names_the_block() {
+    let bytes = uncompressed_dictionary();
+    let record_data_offset = {
+        let mut mdict = Mdict::new(Cursor::new(bytes.clone())).unwrap();
+        let section = mdict.record_section().unwrap();
+        section.record_data_offset + section.record_index_prefix_sum[1].compressed_size
+    };
+
+    // Flip a payload byte of record block 1 (after its 8-byte block header).
+    let mut bytes = bytes;
+    bytes[record_data_offset as usize + 10] ^= 0x20;
+
+    let mut mdict = open_strict(bytes).expect("sections before the records are intact");
+    assert!(mdict.record_at_index(0).is_ok());
+    let err = mdict.record_at_index(5).expect_err("block 1 is corrupted");
+    let message = err.to_string();
+    assert!(message.contains("record block 1"), "{}", message);
+    assert!(message.contains("checksum mismatch"), "{}", message);
+    assert!(
+        matches!(err, MDictError::Corrupted { offset, .. } if offset == record_data_offset),
+        "{:?}",
+        err
+    );
+}
@@ -0,0 +1,98 @@
+#![cfg(all(feature = "cli", feature = "zstd"))]
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+use mdict_tools::mdx_writer::MdxWriter;
+
+fn mdict_cli(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mdict-cli"))
+        .args(args)
+        .output()
+        .expect("run mdict-cli")
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn write_dictionary(path: &Path) {
+    let mut writer = MdxWriter::new().title("Pets");
+    writer.add("cat", "<p>feline</p>").unwrap();
+    writer.add("dog", "<p>canine</p>").unwrap();
+    writer.add("kitty", "@@@LINK=cat").unwrap();
+    writer.write_to_path(path).unwrap();
+}
+
+#[test]
+fn info_keys_and_lookup() {
+    let dir = tempfile::tempdir().unwrap();
+    let mdx = dir.path().join("pets.mdx");
+    write_dictionary(&mdx);
+    let mdx = mdx.to_str().unwrap();
+
+    let info = stdout(&mdict_cli(&["info", mdx]));
+    assert!(info.contains("title: Pets\n"), "{}", info);
+    assert!(info.contains("entries: 3\n"), "{}", info);
+
+    assert_eq!(stdout(&mdict_cli(&["keys", mdx])), "cat\ndog\nkitty\n");
+    assert_eq!(
+        stdout(&mdict_cli(&["lookup", mdx, "kitty"])),
+        "<p>feline</p>\n"
+    );
+
+    let missing = mdict_cli(&["lookup", mdx, "bird"]);
+    assert!(!missing.status.success());
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("'bird' not found"));
+}
+
+#[test]
+fn export_verify_and_optimize() {
+    let dir = tempfile::tempdir().unwrap();
+    let mdx = dir.path().join("pets.mdx");
+    write_dictionary(&mdx);
+    let mdx = mdx.to_str().unwrap();
+
+    let jsonl = dir.path().join("pets.jsonl");
+    stdout(&mdict_cli(&[
+        "export",
+        mdx,
+        "--output",
+        jsonl.to_str().unwrap(),
+    ]));
+    let exported = std::fs::read_to_string(&jsonl).unwrap();
+    assert_eq!(exported.lines().count(), 3);
+    assert!(exported.starts_with("{\"key\":\"cat\",\"record\":\"<p>feline</p>\"}"));
+
+    let verify = stdout(&mdict_cli(&["verify", mdx]));
+    assert!(verify.ends_with(": 0 issue(s)\n"), "{}", verify);
+
+    let links = stdout(&mdict_cli(&["links", mdx, "--format", "graphml"]));
+    assert!(links.contains(r#"<edge source="n2" target="n0">"#), "{}", links);
+
+    let sidecars = dir.path().join("sidecars");
+    stdout(&mdict_cli(&[
+        "optimize",
+        mdx,
+        "-o",
+        sidecars.to_str().unwrap(),
+    ]));
+    for name in ["index.fst", "readings.dat", "records.dat"] {
+        assert!(sidecars.join(name).is_file(), "{}", name);
+    }
+
+    let transcoded = dir.path().join("pets.zstd.mdx");
+    stdout(&mdict_cli(&[
+        "transcode",
+        mdx,
+        "-o",
+        transcoded.to_str().unwrap(),
+    ]));
+    let lookup = stdout(&mdict_cli(&["lookup", transcoded.to_str().unwrap(), "cat"]));
+    assert!(lookup.contains("<p>feline</p>"), "{}", lookup);
+}
@@ -0,0 +1,87 @@
+use mdict_tools::format::adler32;
+use mdict_tools::mdx_writer::{BlockCompression, MdxWriter};
+
+fn adler32_reference(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u64, 0u64);
+    for &byte in data {
+        a = (a + byte as u64) % 65521;
+        b = (b + a) % 65521;
+    }
+    ((b << 16) | a) as u32
+}
+
+#[test]
+fn adler32_matches_known_values() {
+    assert_eq!(adler32(b""), 1);
+    assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+
+    // Long runs of 0xff overflow an unreduced sum within one chunk.
+    let long = [vec![0xff; 20_000], (0..=255).cycle().take(9_000).collect()].concat();
+    assert_eq!(adler32(&long), adler32_reference(&long));
+}
+
+#[cfg(not(feature = "lzo"))]
+#[test]
+fn lzo_blocks_need_their_feature() {
+    use mdict_tools::error::MDictError;
+    use mdict_tools::format::decode_format_block;
+
+    let mut writer = MdxWriter::new().compression(BlockCompression::Lzo);
+    writer.add("apple", "<b>apple</b>").unwrap();
+    assert!(matches!(writer.to_bytes(), Err(MDictError::UnsupportedFeature(_))));
+
+    let mut block = 1u32.to_le_bytes().to_vec();
+    block.extend_from_slice(&adler32(b"data").to_be_bytes());
+    block.extend_from_slice(b"data");
+    assert!(matches!(
+        decode_format_block(&block),
+        Err(MDictError::UnsupportedFeature(_))
+    ));
+}
+
+#[cfg(not(feature = "zstd"))]
+#[test]
+fn zstd_blocks_need_their_feature() {
+    use mdict_tools::error::MDictError;
+    use mdict_tools::format::decode_format_block;
+
+    let mut writer = MdxWriter::new().compression(BlockCompression::Zstd);
+    writer.add("apple", "<b>apple</b>").unwrap();
+    assert!(matches!(writer.to_bytes(), Err(MDictError::UnsupportedFeature(_))));
+
+    let mut block = 4u32.to_le_bytes().to_vec();
+    block.extend_from_slice(&adler32(b"data").to_be_bytes());
+    block.extend_from_slice(&4u32.to_le_bytes());
+    block.extend_from_slice(b"data");
+    assert!(matches!(
+        decode_format_block(&block),
+        Err(MDictError::UnsupportedFeature(_))
+    ));
+}
+
+#[cfg(all(feature = "lzo", feature = "zstd"))]
+#[test]
+fn every_block_compression_round_trips() {
+    use std::io::Cursor;
+
+    use mdict_tools::Mdict;
+
+    for compression in [
+        BlockCompression::None,
+        BlockCompression::Lzo,
+        BlockCompression::Zlib,
+        BlockCompression::Zstd,
+    ] {
+        let mut writer = MdxWriter::new().compression(compression);
+        writer.add("apple", "<b>apple</b>").unwrap();
+        writer.add("banana", "<b>banana</b>").unwrap();
+        let mut mdict = Mdict::new(Cursor::new(writer.to_bytes().unwrap())).unwrap();
+        let key_block = mdict.get_all("banana").unwrap().remove(0);
+        assert_eq!(
+            mdict.record_text_at_key_block(&key_block).unwrap(),
+            "<b>banana</b>",
+            "{:?}",
+            compression
+        );
+    }
+}
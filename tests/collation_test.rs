@@ -0,0 +1,112 @@
+use std::io::Cursor;
+
+use mdict_tools::collation::KeyCollation;
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::Mdict;
+
+fn mixed_case_dictionary() -> Mdict<Cursor<Vec<u8>>> {
+    let mut writer = MdxWriter::new().entries_per_key_block(2);
+    for key in ["Apple", "apricot", "Banana", "berry", "cherry"] {
+        writer.add(key, &format!("<p>{}</p>", key)).unwrap();
+    }
+    Mdict::new(Cursor::new(writer.to_bytes().unwrap())).unwrap()
+}
+
+fn prefix_keys(mdict: &mut Mdict<Cursor<Vec<u8>>>, prefix: &str) -> Vec<String> {
+    let (start, end) = mdict.prefix_range_bounds(prefix).unwrap().unwrap();
+    (start..end)
+        .map(|index| mdict.get(index).unwrap().unwrap().key_text)
+        .collect()
+}
+
+#[test]
+fn fold_follows_header_flags() {
+    let default = KeyCollation::default();
+    assert_eq!(default.fold("Hello, World"), "hello, world");
+
+    let stripped = KeyCollation {
+        case_sensitive: false,
+        strip_key: true,
+    };
+    assert_eq!(stripped.fold("Hello, World!"), "helloworld");
+    assert_eq!(stripped.fold("e-mail"), "email");
+
+    assert_eq!(KeyCollation::BINARY.fold("Hello, World"), "Hello, World");
+    assert!(KeyCollation::BINARY.compare("B", "a").is_lt());
+    assert!(default.compare("B", "a").is_gt());
+}
+
+#[test]
+fn prefix_search_ignores_case_in_case_insensitive_dictionaries() {
+    let mut mdict = mixed_case_dictionary();
+    assert_eq!(mdict.key_block_index.collation(), KeyCollation::default());
+
+    assert_eq!(prefix_keys(&mut mdict, "ap"), vec!["Apple", "apricot"]);
+    assert_eq!(prefix_keys(&mut mdict, "B"), vec!["Banana", "berry"]);
+    assert_eq!(prefix_keys(&mut mdict, "CH"), vec!["cherry"]);
+    assert!(mdict.prefix_range_bounds("d").unwrap().is_none());
+}
+
+#[test]
+fn exact_lookup_prefers_same_case_then_folds() {
+    let mut mdict = mixed_case_dictionary();
+    let index_for = |mdict: &mut Mdict<Cursor<Vec<u8>>>, key: &str| {
+        mdict
+            .key_block_index
+            .index_for(&mut mdict.reader, key)
+            .unwrap()
+    };
+
+    assert_eq!(index_for(&mut mdict, "Banana"), Some(2));
+    assert_eq!(index_for(&mut mdict, "banana"), Some(2));
+    assert_eq!(index_for(&mut mdict, "BERRY"), Some(3));
+    assert_eq!(index_for(&mut mdict, "bananas"), None);
+}
+
+#[test]
+fn writer_and_integrity_check_use_dictionary_order() {
+    let mut writer = MdxWriter::new();
+    writer.add("apple", "<p></p>").unwrap();
+    assert!(writer.add("Zebra", "<p></p>").is_ok());
+    assert!(writer.add("banana", "<p></p>").is_err());
+
+    let mut mdict = mixed_case_dictionary();
+    assert!(mdict.verify().is_ok());
+}
+
+#[cfg(feature = "icu_collator")]
+#[test]
+fn locale_collator_matches_expansions_in_prefix_search() {
+    use mdict_tools::collation::SearchOptions;
+
+    let mut writer = MdxWriter::new().entries_per_key_block(1);
+    for key in ["strasse", "straße", "zebra"] {
+        writer.add(key, "<p></p>").unwrap();
+    }
+    let mut mdict = Mdict::new(Cursor::new(writer.to_bytes().unwrap())).unwrap();
+    assert_eq!(prefix_keys(&mut mdict, "strass"), vec!["strasse"]);
+
+    let german = SearchOptions {
+        locale: Some("de".to_string()),
+        ..SearchOptions::default()
+    };
+    mdict.key_block_index.set_search_options(&german).unwrap();
+    assert_eq!(mdict.key_block_index.search_options(), german);
+    assert_eq!(prefix_keys(&mut mdict, "strass"), vec!["strasse", "straße"]);
+    assert_eq!(prefix_keys(&mut mdict, "STRASS"), vec!["strasse", "straße"]);
+    assert_eq!(prefix_keys(&mut mdict, "Z"), vec!["zebra"]);
+    let index_for = |mdict: &mut Mdict<Cursor<Vec<u8>>>, key: &str| {
+        mdict
+            .key_block_index
+            .index_for(&mut mdict.reader, key)
+            .unwrap()
+    };
+    assert_eq!(index_for(&mut mdict, "straße"), Some(1));
+    assert_eq!(index_for(&mut mdict, "Zebra"), Some(2));
+
+    let invalid = SearchOptions {
+        locale: Some("not a locale".to_string()),
+        ..SearchOptions::default()
+    };
+    assert!(mdict.key_block_index.set_search_options(&invalid).is_err());
+}
@@ -0,0 +1,89 @@
+//! Fixtures shared by the integration tests. Every test binary compiles its
+//! own copy of this module and uses only some of it.
+#![allow(dead_code)]
+
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::synth::{SynthDict, SynthDictBuilder};
+use mdict_tools::types::KeyBlock;
+#[cfg(feature = "mmap")]
+use mdict_tools::{
+    mdict_file::{create_mdict_bundle, MdictBundle},
+    mdict_optimized::create_mdict_optimized_from_bundle,
+    MdictOptimized,
+};
+
+pub fn synth(builder: SynthDictBuilder) -> SynthDict {
+    builder.build().expect("build synthetic dictionary")
+}
+
+/// A writer holding `entries` as `(key, record)` pairs, in order.
+pub fn writer_with_entries(entries: &[(&str, &str)]) -> MdxWriter {
+    let mut writer = MdxWriter::new();
+    for &(key, record) in entries {
+        writer.add(key, record).unwrap();
+    }
+    writer
+}
+
+/// A writer holding `<p>key</p>` for each of `keys`.
+pub fn writer_with_keys(keys: &[&str]) -> MdxWriter {
+    let mut writer = MdxWriter::new();
+    for &key in keys {
+        writer.add(key, &format!("<p>{}</p>", key)).unwrap();
+    }
+    writer
+}
+
+/// Write a dictionary titled `title` with one `<p>key in title</p>` record
+/// per key, and return its path.
+#[cfg(feature = "fs")]
+pub fn write_dictionary(path: &Path, title: &str, keys: &[&str]) -> String {
+    let mut writer = MdxWriter::new().title(title);
+    for &key in keys {
+        writer
+            .add(key, &format!("<p>{} in {}</p>", key, title))
+            .unwrap();
+    }
+    writer.write_to_path(path).unwrap();
+    path.to_string_lossy().to_string()
+}
+
+pub fn key_texts(keys: Vec<KeyBlock>) -> Vec<String> {
+    keys.into_iter().map(|key| key.key_text).collect()
+}
+
+#[cfg(feature = "mmap")]
+pub fn open_bundle(mdx_path: &Path) -> MdictBundle {
+    create_mdict_bundle(mdx_path.to_string_lossy().to_string(), String::new()).expect("open bundle")
+}
+
+/// Write `writer` to `dict.mdx` in `dir` and open it as a bundle.
+#[cfg(feature = "mmap")]
+pub fn bundle(writer: &MdxWriter, dir: &Path) -> MdictBundle {
+    let mdx_path = dir.join("dict.mdx");
+    writer.write_to_path(&mdx_path).unwrap();
+    open_bundle(&mdx_path)
+}
+
+/// Build the optimized index of `bundle` into `index.fst`, `readings.dat`
+/// and `records.dat` in `dir`.
+#[cfg(feature = "mmap")]
+pub fn optimized_from_bundle(bundle: &MdictBundle, dir: &Path) -> MdictOptimized {
+    let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+    create_mdict_optimized_from_bundle(
+        bundle,
+        path("index.fst"),
+        path("readings.dat"),
+        path("records.dat"),
+    )
+    .expect("build optimized index")
+}
+
+/// Write `writer` to `dir` and build its optimized index there.
+#[cfg(feature = "mmap")]
+pub fn optimized(writer: &MdxWriter, dir: &Path) -> MdictOptimized {
+    optimized_from_bundle(&bundle(writer, dir), dir)
+}
@@ -0,0 +1,31 @@
+#![cfg(feature = "threads")]
+
+use mdict_tools::config::{config, init_config, Config};
+use mdict_tools::error::MDictError;
+
+/// The only test in this binary: the config and the rayon pool are both
+/// process-wide and can each be installed once.
+#[test]
+fn failed_thread_pool_leaves_config_uninstalled() {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build_global()
+        .unwrap();
+
+    let result = init_config(Config {
+        thread_pool_size: 2,
+        packed_block_size: 1234,
+        ..Config::default()
+    });
+    assert!(matches!(result, Err(MDictError::InvalidArgument(_))));
+
+    init_config(Config {
+        packed_block_size: 1234,
+        ..Config::default()
+    })
+    .unwrap();
+    assert_eq!(config().packed_block_size, 1234);
+
+    let result = init_config(Config::default());
+    assert!(matches!(result, Err(MDictError::InvalidArgument(_))));
+}
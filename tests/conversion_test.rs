@@ -0,0 +1,121 @@
+#![cfg(feature = "fs")]
+
+use std::fs;
+#[cfg(feature = "zstd")]
+use std::path::Path;
+
+use mdict_tools::mdx_conversion::export::{export, ExportFormat};
+#[cfg(feature = "zstd")]
+use mdict_tools::mdx_conversion::{fst_indexing::create_fst_index, reindexing};
+use mdict_tools::synth::SynthDictBuilder;
+use mdict_tools::types::MdictVersion;
+
+#[cfg(feature = "zstd")]
+fn build_outputs(dir: &Path) -> [Vec<u8>; 4] {
+    let dict = SynthDictBuilder::entries(120)
+        .link_every(4)
+        .entries_per_key_block(10)
+        .build()
+        .expect("build synthetic dictionary");
+    let mut mdict = dict.open().expect("open synthetic dictionary");
+
+    let readings_list = reindexing::build_readings_list(&mut mdict).expect("build readings");
+    reindexing::write_compressed_readings_list(&readings_list, dir.join("readings_list.txt"))
+        .expect("write readings list");
+    create_fst_index(
+        &mut mdict,
+        &readings_list,
+        dir.join("index.fst"),
+        dir.join("readings.dat"),
+        dir.join("records.dat"),
+    )
+    .expect("create fst index");
+
+    [
+        "readings_list.txt",
+        "index.fst",
+        "readings.dat",
+        "records.dat",
+    ]
+    .map(|name| fs::read(dir.join(name)).expect("read output"))
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn conversion_outputs_are_byte_identical_across_builds() {
+    let first = tempfile::tempdir().expect("create temp dir");
+    let second = tempfile::tempdir().expect("create temp dir");
+
+    let a = build_outputs(first.path());
+    let b = build_outputs(second.path());
+
+    for (i, (lhs, rhs)) in a.iter().zip(b.iter()).enumerate() {
+        assert!(!lhs.is_empty(), "output {} is empty", i);
+        assert_eq!(lhs, rhs, "output {} differs between builds", i);
+    }
+}
+
+#[test]
+fn export_writes_every_entry_in_key_order() {
+    let dict = SynthDictBuilder::entries(95)
+        .link_every(4)
+        .entries_per_record_block(7)
+        .build()
+        .expect("build synthetic dictionary");
+    let dir = tempfile::tempdir().expect("create temp dir");
+
+    let mut mdict = dict.open().expect("open synthetic dictionary");
+    let jsonl = dir.path().join("out.jsonl");
+    assert_eq!(
+        export(&mut mdict, ExportFormat::JsonLines, &jsonl).unwrap(),
+        95
+    );
+    let lines = fs::read_to_string(&jsonl).unwrap();
+    let lines: Vec<&str> = lines.lines().collect();
+    assert_eq!(lines.len(), 95);
+    assert_eq!(
+        lines[1],
+        r#"{"key":"word000001","record":"<p class=\"def\">Definition of word000001 (#1)</p>"}"#
+    );
+    assert_eq!(
+        lines[4],
+        r#"{"key":"word000004","record":"@@@LINK=word000003"}"#
+    );
+
+    let tsv = dir.path().join("out.tsv");
+    assert_eq!(export(&mut mdict, ExportFormat::Tsv, &tsv).unwrap(), 95);
+    let tsv = fs::read_to_string(&tsv).unwrap();
+    for (i, line) in tsv.lines().enumerate() {
+        let (key, record) = line.split_once('\t').expect("tab separated");
+        let (expected_key, expected_record) = &dict.entries[i];
+        assert_eq!(key, expected_key);
+        assert_eq!(record.as_bytes(), expected_record.as_slice());
+    }
+
+    let html = dir.path().join("html");
+    assert_eq!(
+        export(&mut mdict, ExportFormat::HtmlDir, &html).unwrap(),
+        95
+    );
+    assert_eq!(fs::read_dir(&html).unwrap().count(), 95);
+    assert_eq!(
+        fs::read_to_string(html.join("word000094.html")).unwrap(),
+        "<p class=\"def\">Definition of word000094 (#94)</p>"
+    );
+}
+
+#[test]
+fn export_rejects_mdd() {
+    let dict = SynthDictBuilder::entries(5)
+        .version(MdictVersion::MDD)
+        .build()
+        .expect("build synthetic MDD");
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let mut mdict = dict.open().expect("open synthetic MDD");
+    assert!(export(
+        &mut mdict,
+        ExportFormat::JsonLines,
+        dir.path().join("out.jsonl")
+    )
+    .is_err());
+}
@@ -0,0 +1,45 @@
+use super::*;
+
+#[test]
+fn empty_file() {
+    assert!(matches!(open_err(Vec::new()), MDictError::InvalidFormat(_)));
+}
+
+#[test]
+fn truncated_inside_the_header_size() {
+    let bytes = dictionary();
+    assert!(matches!(open_err(bytes[..3].to_vec()), MDictError::InvalidFormat(_)));
+}
+
+#[test]
+fn truncated_inside_the_header_text() {
+    let bytes = dictionary();
+    let cut = header_size(&bytes) / 2;
+    assert_invalid_format(open_err(bytes[..cut].to_vec()), "exceeds the file");
+}
+
+#[test]
+fn header_size_past_the_end_of_the_file() {
+    let mut bytes = dictionary();
+    bytes[..4].copy_from_slice(&u32::MAX.to_be_bytes());
+    assert_invalid_format(open_err(bytes), "exceeds the file");
+}
+
+#[test]
+fn bad_header_checksum() {
+    let mut bytes = dictionary();
+    let checksum_at = header_size(&bytes) - 4;
+    bytes[checksum_at] ^= 0xff;
+
+    assert!(open(bytes.clone()).is_ok());
+    let err = open_with(
+        bytes,
+        OpenOptions {
+            verify_checksums: true,
+            ..OpenOptions::default()
+        },
+    )
+    .err()
+    .expect("strict open fails");
+    assert_invalid_format(err, "header: checksum mismatch");
+}
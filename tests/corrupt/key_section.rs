@@ -0,0 +1,82 @@
+//! The V2 key section starts with five `u64` fields (key block count, entry
+//! count, key info size before and after compression, key block area size)
+//! and a checksum.
+
+use super::*;
+
+const NUM_BLOCKS: usize = 0;
+const NUM_ENTRIES: usize = 8;
+const KEY_INFO_DECOMPRESSED_SIZE: usize = 16;
+const KEY_INFO_SIZE: usize = 24;
+const KEY_BLOCKS_SIZE: usize = 32;
+
+#[test]
+fn truncated_inside_the_preamble() {
+    let bytes = dictionary();
+    let cut = header_size(&bytes) + 20;
+    assert!(matches!(open_err(bytes[..cut].to_vec()), MDictError::InvalidFormat(_)));
+}
+
+#[test]
+fn key_info_size_past_the_end_of_the_file() {
+    let mut bytes = dictionary();
+    let at = header_size(&bytes) + KEY_INFO_SIZE;
+    put_u64(&mut bytes, at, u64::MAX);
+    assert_invalid_format(open_err(bytes), "exceeds the file");
+}
+
+#[test]
+fn key_info_shorter_than_declared() {
+    let mut bytes = dictionary();
+    let at = header_size(&bytes) + KEY_INFO_DECOMPRESSED_SIZE;
+    let declared = read_u64(&bytes, at);
+    put_u64(&mut bytes, at, declared + 16);
+    assert_invalid_format(open_err(bytes), "key info block: decompressed to");
+}
+
+#[test]
+fn oversized_key_block_count() {
+    let mut bytes = dictionary();
+    let at = header_size(&bytes) + NUM_BLOCKS;
+    put_u64(&mut bytes, at, 4);
+    assert_invalid_format(open_err(bytes), "lists 3 key blocks, preamble says 4");
+}
+
+#[test]
+fn oversized_entry_count() {
+    let mut bytes = dictionary();
+    let at = header_size(&bytes) + NUM_ENTRIES;
+    put_u64(&mut bytes, at, 1 << 40);
+    assert_invalid_format(open_err(bytes), "key blocks hold 12 entries");
+}
+
+#[test]
+fn key_block_area_past_the_end_of_the_file() {
+    let mut bytes = dictionary();
+    let at = header_size(&bytes) + KEY_BLOCKS_SIZE;
+    put_u64(&mut bytes, at, 1 << 40);
+    assert_invalid_format(open_err(bytes), "key section extends past the end of the file");
+}
+
+#[test]
+fn bad_key_block_checksum() {
+    let bytes = dictionary();
+    let block_offset = {
+        let mdict = open(bytes.clone()).unwrap();
+        let section = &mdict.key_block_index.key_section;
+        let key_blocks_start =
+            section.next_section_offset - section.key_info_prefix_sum.last().unwrap();
+        key_blocks_start + section.key_info_prefix_sum[1]
+    };
+    let mut bytes = bytes;
+    // Past the block's compression type and checksum.
+    bytes[block_offset as usize + 12] ^= 0x20;
+
+    let mut mdict = open(bytes).expect("the key block is only read on demand");
+    assert!(mdict.key_block_index.get(&mut mdict.reader, 0).is_ok());
+    let err = mdict
+        .key_block_index
+        .get(&mut mdict.reader, 5)
+        .expect_err("key block 1 is corrupted");
+    assert_corrupted_at(err, block_offset, "key block 1: checksum mismatch");
+}
@@ -0,0 +1,110 @@
+//! Hand-crafted broken files and the exact `MDictError` each one produces.
+//!
+//! Every case starts from a small uncompressed V2 dictionary, so fields can
+//! be patched at known offsets, and damages one part of it. `malformed_test`
+//! only checks that damage never panics; these pin down which error comes
+//! back, so hardening a parser cannot quietly change it.
+
+use std::io::Cursor;
+
+use mdict_tools::error::{MDictError, Result};
+use mdict_tools::format::adler32;
+use mdict_tools::mdx_writer::{BlockCompression, MdxWriter};
+use mdict_tools::{Mdict, OpenOptions};
+
+mod header;
+mod key_section;
+mod records;
+
+/// 12 entries (`entry00`..`entry11`) in three key blocks and three record
+/// blocks of four.
+fn dictionary() -> Vec<u8> {
+    let mut writer = MdxWriter::new()
+        .compression(BlockCompression::None)
+        .entries_per_key_block(4)
+        .entries_per_record_block(4);
+    for i in 0..12 {
+        writer
+            .add(format!("entry{:02}", i), &format!("<b>entry {}</b>", i))
+            .unwrap();
+    }
+    writer.to_bytes().unwrap()
+}
+
+fn open(bytes: Vec<u8>) -> Result<Mdict<Cursor<Vec<u8>>>> {
+    Mdict::new(Cursor::new(bytes))
+}
+
+fn open_with(bytes: Vec<u8>, options: OpenOptions) -> Result<Mdict<Cursor<Vec<u8>>>> {
+    Mdict::new_with_options(Cursor::new(bytes), options)
+}
+
+/// The error from opening `bytes`, which must fail.
+fn open_err(bytes: Vec<u8>) -> MDictError {
+    open(bytes).err().expect("opening the damaged file fails")
+}
+
+/// Byte length of the header, i.e. the offset of the key section.
+fn header_size(bytes: &[u8]) -> usize {
+    4 + u32::from_be_bytes(bytes[..4].try_into().unwrap()) as usize + 4
+}
+
+/// Offset of the record section, just past the key blocks.
+fn record_section_offset(bytes: &[u8]) -> usize {
+    let mdict = open(bytes.to_vec()).unwrap();
+    mdict.key_block_index.key_section.next_section_offset as usize
+}
+
+fn read_u64(bytes: &[u8], at: usize) -> u64 {
+    u64::from_be_bytes(bytes[at..at + 8].try_into().unwrap())
+}
+
+fn put_u64(bytes: &mut [u8], at: usize, value: u64) {
+    bytes[at..at + 8].copy_from_slice(&value.to_be_bytes());
+}
+
+/// Recompute the adler32 of the uncompressed block at `bytes[start..end]`
+/// after its payload was edited, so only the edit itself is wrong.
+fn reseal_block(bytes: &mut [u8], start: usize, end: usize) {
+    let checksum = adler32(&bytes[start + 8..end]);
+    bytes[start + 4..start + 8].copy_from_slice(&checksum.to_be_bytes());
+}
+
+fn assert_invalid_format(err: MDictError, needle: &str) {
+    match err {
+        MDictError::InvalidFormat(message) => {
+            assert!(message.contains(needle), "{:?} lacks {:?}", message, needle)
+        }
+        other => panic!("expected InvalidFormat containing {:?}, got {:?}", needle, other),
+    }
+}
+
+fn assert_corrupted_at(err: MDictError, expected_offset: u64, needle: &str) {
+    match err {
+        MDictError::Corrupted { offset, message } => {
+            assert_eq!(offset, expected_offset, "{}", message);
+            assert!(message.contains(needle), "{:?} lacks {:?}", message, needle);
+        }
+        other => panic!("expected Corrupted at {}, got {:?}", expected_offset, other),
+    }
+}
+
+#[test]
+fn the_undamaged_dictionary_opens() {
+    let mut mdict = open_with(
+        dictionary(),
+        OpenOptions {
+            verify_checksums: true,
+            ..OpenOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(mdict.key_block_index.key_section.key_info_blocks.len(), 3);
+    assert_eq!(mdict.record_section().unwrap().num_record_blocks, 3);
+    for i in 0..12 {
+        assert_eq!(
+            mdict.record_at_index(i).unwrap(),
+            format!("<b>entry {}</b>", i).as_bytes()
+        );
+    }
+}
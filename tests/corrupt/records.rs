@@ -0,0 +1,154 @@
+//! The V2 record section starts with four `u64` fields (block count, entry
+//! count, index size, data size), followed by a compressed and an
+//! uncompressed `u64` size per block, then the blocks.
+
+use super::*;
+
+const NUM_RECORD_BLOCKS: usize = 0;
+const INDEX_SIZE: usize = 16;
+const FIRST_PAIR: usize = 32;
+
+/// Offset of record block `block`'s compressed and uncompressed sizes.
+fn pair_offset(bytes: &[u8], block: usize) -> usize {
+    record_section_offset(bytes) + FIRST_PAIR + 16 * block
+}
+
+/// File offset of record block `block`.
+fn record_block_offset(bytes: &[u8], block: usize) -> u64 {
+    let mut mdict = open(bytes.to_vec()).unwrap();
+    let section = mdict.record_section().unwrap();
+    section.record_data_offset + section.record_index_prefix_sum[block].compressed_size
+}
+
+#[test]
+fn truncated_inside_the_record_header() {
+    let bytes = dictionary();
+    let cut = record_section_offset(&bytes) + 10;
+    assert_invalid_format(
+        open_err(bytes[..cut].to_vec()),
+        "record section header extends past the end of the file",
+    );
+}
+
+#[test]
+fn record_index_past_the_end_of_the_file() {
+    let mut bytes = dictionary();
+    let at = record_section_offset(&bytes) + INDEX_SIZE;
+    put_u64(&mut bytes, at, 1 << 40);
+    assert_invalid_format(open_err(bytes), "record index extends past the end of the file");
+}
+
+#[test]
+fn oversized_record_block_count() {
+    let mut bytes = dictionary();
+    let at = record_section_offset(&bytes) + NUM_RECORD_BLOCKS;
+    put_u64(&mut bytes, at, 1000);
+    assert!(matches!(open_err(bytes), MDictError::InvalidFormat(_)));
+}
+
+#[test]
+fn record_block_sizes_overflow() {
+    let mut bytes = dictionary();
+    let at = pair_offset(&bytes, 0);
+    put_u64(&mut bytes, at, u64::MAX);
+    assert_invalid_format(open_err(bytes), "record index: block sizes overflow");
+}
+
+#[test]
+fn record_data_past_the_end_of_the_file() {
+    let mut bytes = dictionary();
+    let at = pair_offset(&bytes, 2);
+    let declared = read_u64(&bytes, at);
+    put_u64(&mut bytes, at, declared + (1 << 20));
+    assert_invalid_format(
+        open_err(bytes.clone()),
+        "record data extends past the end of the file",
+    );
+
+    // A lazy open defers the same error to the first record read.
+    let lazy = OpenOptions {
+        lazy: true,
+        ..OpenOptions::default()
+    };
+    let mut mdict = open_with(bytes, lazy).expect("lazy open skips the record index");
+    assert_invalid_format(
+        mdict.record_at_index(0).expect_err("the record index is damaged"),
+        "record data extends past the end of the file",
+    );
+}
+
+#[test]
+fn decreasing_record_offsets() {
+    let mut bytes = dictionary();
+    let (block_start, block_end) = {
+        let mdict = open(bytes.clone()).unwrap();
+        let section = &mdict.key_block_index.key_section;
+        let start = section.next_section_offset - section.key_info_prefix_sum.last().unwrap();
+        (start as usize, (start + section.key_info_prefix_sum[1]) as usize)
+    };
+    // Each entry of key block 0 is a `u64` record offset and a
+    // NUL-terminated `entryNN`: move entry 1's record past entry 2's.
+    let entry_1 = block_start + 8 + 16;
+    let entry_2 = entry_1 + 16;
+    let past_entry_2 = read_u64(&bytes, entry_2) + 5;
+    put_u64(&mut bytes, entry_1, past_entry_2);
+    reseal_block(&mut bytes, block_start, block_end);
+
+    let mut mdict = open(bytes).unwrap();
+    assert!(mdict.record_at_index(3).is_ok());
+    assert_invalid_format(
+        mdict.record_at_index(1).expect_err("entry 1 ends before it starts"),
+        "entry 1: record offset",
+    );
+    assert!(matches!(mdict.record_size_at_index(1), Err(MDictError::InvalidFormat(_))));
+}
+
+#[test]
+fn bad_record_block_checksum() {
+    let bytes = dictionary();
+    let block_offset = record_block_offset(&bytes, 1);
+    let mut bytes = bytes;
+    bytes[block_offset as usize + 10] ^= 0x20;
+
+    let mut mdict = open(bytes).unwrap();
+    assert!(mdict.record_at_index(0).is_ok());
+    assert_corrupted_at(
+        mdict.record_at_index(5).expect_err("block 1 is corrupted"),
+        block_offset,
+        "record block 1: checksum mismatch",
+    );
+}
+
+#[test]
+fn record_block_shorter_than_indexed() {
+    let mut bytes = dictionary();
+    let block_offset = record_block_offset(&bytes, 1);
+    let at = pair_offset(&bytes, 1) + 8;
+    let declared = read_u64(&bytes, at);
+    put_u64(&mut bytes, at, declared + 16);
+
+    let mut mdict = open(bytes).unwrap();
+    assert_corrupted_at(
+        mdict.record_at_index(4).expect_err("block 1 is short"),
+        block_offset,
+        "record block 1: decompressed to",
+    );
+}
+
+#[test]
+fn unknown_record_block_compression() {
+    let bytes = dictionary();
+    let block_offset = record_block_offset(&bytes, 1);
+    let mut bytes = bytes;
+    bytes[block_offset as usize] = 11;
+
+    let mut mdict = open(bytes).unwrap();
+    match mdict.record_at_index(4).expect_err("block 1 cannot be decoded") {
+        MDictError::UnsupportedFeature(message) => assert!(
+            message.contains("record block 1: unknown block compression type 11"),
+            "{}",
+            message
+        ),
+        other => panic!("expected UnsupportedFeature, got {:?}", other),
+    }
+}
@@ -0,0 +1,92 @@
+use std::io::Cursor;
+
+use mdict_tools::deinflect::Deinflector;
+use mdict_tools::error::MDictError;
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::Mdict;
+
+fn terms(deinflector: &Deinflector, term: &str) -> Vec<String> {
+    deinflector
+        .deinflect(term)
+        .into_iter()
+        .map(|deinflection| deinflection.term)
+        .collect()
+}
+
+#[test]
+fn japanese_rules_reach_dictionary_forms() {
+    let japanese = Deinflector::japanese();
+    for (inflected, dictionary_form) in [
+        ("食べました", "食べる"),
+        ("食べなかった", "食べる"),
+        ("書いています", "書く"),
+        ("飲みたい", "飲む"),
+        ("行って", "行く"),
+        ("来ない", "来る"),
+        ("勉強しました", "勉強する"),
+        ("高かった", "高い"),
+    ] {
+        assert!(
+            terms(&japanese, inflected).contains(&dictionary_form.to_string()),
+            "{} -> {}",
+            inflected,
+            dictionary_form
+        );
+    }
+
+    let polite_past = japanese
+        .deinflect("食べました")
+        .into_iter()
+        .find(|deinflection| deinflection.term == "食べる")
+        .unwrap();
+    assert_eq!(polite_past.reasons, vec!["polite past", "polite"]);
+    assert_eq!(terms(&japanese, "食べました")[0], "食べました");
+}
+
+#[test]
+fn korean_rules_reach_dictionary_forms() {
+    let korean = Deinflector::korean();
+    assert!(terms(&korean, "먹었어요").contains(&"먹다".to_string()));
+    assert!(terms(&korean, "공부했습니다").contains(&"공부하다".to_string()));
+}
+
+#[test]
+fn tables_load_from_yomichan_json() {
+    let json = r#"{
+        "past": [{"kanaIn": "ed", "kanaOut": "", "rulesIn": [], "rulesOut": ["v"]}],
+        "-ing": [{"kanaIn": "ing", "kanaOut": "", "rulesIn": ["v"], "rulesOut": ["v"]}]
+    }"#;
+    let english = Deinflector::from_json(json).unwrap();
+    assert_eq!(terms(&english, "walked"), vec!["walked", "walk"]);
+    assert!(matches!(
+        Deinflector::from_json("{\"past\": 1}"),
+        Err(MDictError::InvalidFormat(_))
+    ));
+}
+
+#[test]
+fn lookup_deinflected_tries_candidates_in_order() {
+    let mut writer = MdxWriter::new();
+    for key in ["食べる", "食べ物"] {
+        writer.add(key, &format!("<p>{}</p>", key)).unwrap();
+    }
+    let mut mdict = Mdict::new(Cursor::new(writer.to_bytes().unwrap())).unwrap();
+    let japanese = Deinflector::japanese();
+
+    let hits = mdict
+        .lookup_deinflected("食べませんでした", &japanese)
+        .unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].key_block.key_text, "食べる");
+    assert_eq!(
+        hits[0].deinflection.reasons,
+        vec!["polite past negative", "polite"]
+    );
+
+    let hits = mdict.lookup_deinflected("食べる", &japanese).unwrap();
+    assert_eq!(hits[0].deinflection.reasons, Vec::<String>::new());
+    assert!(mdict
+        .lookup_deinflected("飲む", &japanese)
+        .unwrap()
+        .is_empty());
+}
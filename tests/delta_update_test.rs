@@ -0,0 +1,92 @@
+#![cfg(all(feature = "mmap", feature = "zstd"))]
+
+use std::path::Path;
+use std::sync::Once;
+
+use mdict_tools::config::{init_config, Config};
+use mdict_tools::mdict_file::create_mdict_bundle;
+use mdict_tools::mdict_optimized::{
+    create_mdict_optimized_from_bundle, create_mdict_optimized_from_fst,
+    update_mdict_optimized_from_bundle,
+};
+use mdict_tools::mdx_writer::MdxWriter;
+
+/// Small packed blocks, so a few hundred records span many of them.
+fn small_blocks() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        init_config(Config {
+            packed_block_size: 512,
+            ..Config::default()
+        })
+        .unwrap();
+    });
+}
+
+fn record(i: usize, release: u32) -> String {
+    let body = if release == 2 && i % 97 == 13 {
+        "revised"
+    } else {
+        "original"
+    };
+    format!("<div><b>word{:03}</b> {} definition text</div>", i, body)
+}
+
+fn write_release(dir: &Path, release: u32) -> String {
+    let mdx_path = dir.join(format!("release{}.mdx", release));
+    let mut writer = MdxWriter::new();
+    for i in 0..300 {
+        writer.add(format!("word{:03}", i), &record(i, release)).unwrap();
+        if release == 2 && i == 150 {
+            writer.add("word150b", "<div>added</div>").unwrap();
+        }
+    }
+    writer.write_to_path(&mdx_path).unwrap();
+    mdx_path.to_string_lossy().to_string()
+}
+
+#[test]
+fn delta_update_reuses_unchanged_record_blocks() {
+    small_blocks();
+    let dir = tempfile::tempdir().unwrap();
+    let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+
+    let old = create_mdict_bundle(write_release(dir.path(), 1), String::new()).unwrap();
+    create_mdict_optimized_from_bundle(
+        &old,
+        path("old.fst"),
+        path("old.readings"),
+        path("old.records"),
+    )
+    .unwrap();
+
+    let new = create_mdict_bundle(write_release(dir.path(), 2), String::new()).unwrap();
+    let stats = update_mdict_optimized_from_bundle(
+        &new,
+        path("old.readings"),
+        path("old.records"),
+        path("new.fst"),
+        path("new.readings"),
+        path("new.records"),
+    )
+    .unwrap();
+    assert!(stats.reused_blocks > 0);
+    assert!(stats.encoded_records > 0);
+    assert!(stats.encoded_records < stats.reused_records);
+    assert_eq!(stats.reused_records + stats.encoded_records, 301);
+
+    let updated =
+        create_mdict_optimized_from_fst(path("new.fst"), path("new.readings"), path("new.records"))
+            .unwrap();
+    for i in [0, 13, 110, 150, 299] {
+        let key = &updated.lookup(&format!("word{:03}", i))[0];
+        assert_eq!(
+            updated.record_at(key.clone()).unwrap(),
+            record(i, 2).as_bytes(),
+            "word{:03}",
+            i
+        );
+    }
+    let added = &updated.lookup("word150b")[0];
+    assert_eq!(updated.record_at(added.clone()).unwrap(), b"<div>added</div>");
+}
@@ -0,0 +1,92 @@
+#![cfg(all(feature = "mmap", feature = "zstd"))]
+
+use std::sync::Arc;
+
+use mdict_tools::dictionary::Dictionary;
+use mdict_tools::mdict_file::create_mdict_bundle;
+use mdict_tools::mdict_group::create_mdict_group;
+use mdict_tools::types::{DictionaryFormat, DictionaryInfo};
+
+mod common;
+
+use common::{key_texts, write_dictionary};
+
+/// The same checks against any backend holding `apple`, `apply` and `banana`.
+fn check_backend(dictionary: &dyn Dictionary) {
+    assert_eq!(
+        key_texts(dictionary.prefix_search("app", 10).unwrap()),
+        ["apple", "apply"]
+    );
+    assert_eq!(key_texts(dictionary.prefix_search("app", 1).unwrap()), ["apple"]);
+    assert!(dictionary.prefix_search("zzz", 10).unwrap().is_empty());
+
+    let keys = dictionary.lookup("banana").unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(
+        dictionary.record_text(&keys[0]).unwrap(),
+        "<p>banana in First</p>"
+    );
+    assert_eq!(dictionary.record(&keys[0]).unwrap(), b"<p>banana in First</p>");
+    assert!(dictionary.lookup("cherry").unwrap().is_empty());
+    assert_eq!(dictionary.resource("img/a.png").unwrap(), None);
+}
+
+#[test]
+fn mdx_and_optimized_backends_agree() {
+    let dir = tempfile::tempdir().unwrap();
+    let mdx_path = write_dictionary(
+        &dir.path().join("first.mdx"),
+        "First",
+        &["apple", "apply", "banana"],
+    );
+    let bundle = create_mdict_bundle(mdx_path, String::new()).unwrap();
+    check_backend(&bundle);
+    assert_eq!(
+        bundle.info(),
+        DictionaryInfo {
+            format: DictionaryFormat::Mdx,
+            title: Some("First".to_string()),
+            description: bundle.metadata().description,
+            entry_count: 3,
+        }
+    );
+
+    let optimized = common::optimized_from_bundle(&bundle, dir.path());
+    check_backend(&optimized);
+    assert_eq!(optimized.info().format, DictionaryFormat::Optimized);
+    assert_eq!(optimized.info().entry_count, 3);
+}
+
+#[test]
+fn groups_search_any_backend() {
+    let dir = tempfile::tempdir().unwrap();
+    let first = write_dictionary(
+        &dir.path().join("first.mdx"),
+        "First",
+        &["apple", "apply", "banana"],
+    );
+    let second = write_dictionary(
+        &dir.path().join("second.mdx"),
+        "Second",
+        &["appetite", "cherry"],
+    );
+    let bundle = create_mdict_bundle(second, String::new()).unwrap();
+    let optimized = common::optimized_from_bundle(&bundle, dir.path());
+
+    let group = create_mdict_group();
+    assert_eq!(group.add_dictionary(first).unwrap(), 0);
+    assert_eq!(group.add_backend(Arc::new(optimized)).unwrap(), 1);
+    assert!(group.metadata(1).is_err());
+    assert_eq!(group.info(1).unwrap().format, DictionaryFormat::Optimized);
+
+    let hits = group.search("app", 10).unwrap();
+    let listed = hits
+        .iter()
+        .map(|hit| (hit.dict_id, hit.key.key_text.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(listed, [(1, "appetite"), (0, "apple"), (0, "apply")]);
+    assert_eq!(
+        group.record_text(1, hits[0].key.clone()).unwrap(),
+        "<p>appetite in Second</p>"
+    );
+}
@@ -0,0 +1,101 @@
+#![cfg(all(feature = "dsl", feature = "mmap"))]
+
+use mdict_tools::dictionary::Dictionary;
+use mdict_tools::dsl::{dsl_to_html, DslDictionary};
+use mdict_tools::mdict_optimized::create_mdict_optimized_from_fst;
+use mdict_tools::mdx_conversion::fst_indexing::create_fst_index_from_dictionary;
+use mdict_tools::types::DictionaryFormat;
+
+mod common;
+
+use common::key_texts;
+
+const DSL: &str = "\u{feff}#NAME \"Test Dictionary\"
+#INDEX_LANGUAGE \"English\"
+#CONTENTS_LANGUAGE \"English\"
+
+colo(u)r
+\t[m1][b]1.[/b] [trn]a hue[/trn], as in [ref]red[/ref]{{a comment}}[/m]
+\t[m2][ex]the ~ of the sky[/ex][/m]
+red
+{the }red
+\t[p]adj.[/p] [c]like blood[/c] \\[sic\\] <<colour>>
+\t[s]red.wav[/s] [s]red.png[/s]
+";
+
+fn utf16(text: &str) -> Vec<u8> {
+    text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+#[test]
+fn parses_headers_and_headword_variants() {
+    let dsl = DslDictionary::parse(&utf16(DSL)).unwrap();
+    assert_eq!(dsl.name(), Some("Test Dictionary"));
+    assert_eq!(dsl.index_language(), Some("English"));
+    assert_eq!(dsl.len(), 2);
+    assert_eq!(dsl.info().format, DictionaryFormat::Dsl);
+
+    assert_eq!(
+        key_texts(dsl.prefix_search("", 10).unwrap()),
+        ["color", "colour", "red"]
+    );
+    let colour = dsl.lookup("color").unwrap();
+    assert_eq!(colour[0].key_id, dsl.lookup("colour").unwrap()[0].key_id);
+    assert_eq!(dsl.lookup("red").unwrap().len(), 1);
+    assert!(dsl.lookup("the red").unwrap().is_empty());
+}
+
+#[test]
+fn converts_markup_to_html() {
+    let dsl = DslDictionary::parse(DSL.as_bytes()).unwrap();
+    let colour = dsl.lookup("colour").unwrap().remove(0);
+    assert_eq!(
+        dsl.record_text(&colour).unwrap(),
+        "<div style=\"margin-left:1em\"><b>1.</b> a hue, as in \
+         <a href=\"entry://red\">red</a></div>\n\
+         <div style=\"margin-left:2em\"><span class=\"ex\">the colour of the sky</span></div>\n"
+    );
+
+    assert_eq!(
+        dsl_to_html("[p]adj.[/p] [c]like blood[/c] \\[sic\\] <<colour>>", "red"),
+        "<div><abbr>adj.</abbr> <span style=\"color:green\">like blood</span> [sic] \
+         <a href=\"entry://colour\">colour</a></div>\n"
+    );
+    assert_eq!(
+        dsl_to_html("[s]red.wav[/s] [s]red.png[/s] [c red]x[/c]", "red"),
+        "<div><a href=\"sound://red.wav\">red.wav</a> <img src=\"red.png\"> \
+         <span style=\"color:red\">x</span></div>\n"
+    );
+}
+
+#[test]
+fn builds_an_optimized_index() {
+    let dir = tempfile::tempdir().unwrap();
+    let dsl_path = dir.path().join("dict.dsl");
+    std::fs::write(&dsl_path, utf16(DSL)).unwrap();
+    let dsl = DslDictionary::open(&dsl_path).unwrap();
+
+    let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+    create_fst_index_from_dictionary(
+        &dsl,
+        path("index.fst"),
+        path("readings.dat"),
+        path("records.dat"),
+    )
+    .unwrap();
+    let optimized = create_mdict_optimized_from_fst(
+        path("index.fst"),
+        path("readings.dat"),
+        path("records.dat"),
+    )
+    .unwrap();
+
+    let page = optimized.set_search_prefix_paged("colo", 10).unwrap();
+    assert_eq!(key_texts(page.results.clone()), ["color", "colour"]);
+    let record = optimized.record_at(page.results[0].clone()).unwrap();
+    assert_eq!(
+        String::from_utf8(record).unwrap(),
+        dsl.record_text(&dsl.lookup("color").unwrap()[0]).unwrap()
+    );
+    assert_eq!(optimized.lookup("red").len(), 1);
+}
@@ -0,0 +1,86 @@
+#![cfg(all(feature = "mmap", feature = "zstd"))]
+
+use std::io::Cursor;
+
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::Mdict;
+
+mod common;
+
+fn writer() -> MdxWriter {
+    let mut writer = MdxWriter::new().entries_per_key_block(2);
+    for (key, html) in [
+        ("apple", "<p>fruit</p>"),
+        ("bank", "<p>river side</p>"),
+        ("bank", "<p>money</p>"),
+        ("bank", "<p>to tilt</p>"),
+        ("cat", "<p>animal</p>"),
+    ] {
+        writer.add(key, html).unwrap();
+    }
+    writer
+}
+
+fn text(records: Vec<Vec<u8>>) -> Vec<String> {
+    records
+        .into_iter()
+        .map(|record| String::from_utf8(record).unwrap())
+        .collect()
+}
+
+#[test]
+fn get_all_returns_every_sense() {
+    let mut mdict = Mdict::new(Cursor::new(writer().to_bytes().unwrap())).unwrap();
+
+    let key_blocks = mdict.get_all("bank").unwrap();
+    assert_eq!(key_blocks.len(), 3);
+    assert!(key_blocks.iter().all(|key_block| key_block.key_text == "bank"));
+    assert_eq!(mdict.get_all("apple").unwrap().len(), 1);
+    assert!(mdict.get_all("dog").unwrap().is_empty());
+
+    assert_eq!(
+        text(mdict.lookup("bank").unwrap()),
+        ["<p>river side</p>", "<p>money</p>", "<p>to tilt</p>"]
+    );
+}
+
+#[test]
+fn record_at_key_block_reads_the_matching_duplicate() {
+    let mut mdict = Mdict::new(Cursor::new(writer().to_bytes().unwrap())).unwrap();
+
+    let key_blocks = mdict.get_all("bank").unwrap();
+    let records = key_blocks
+        .iter()
+        .map(|key_block| mdict.record_at_key_block(key_block).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        text(records),
+        ["<p>river side</p>", "<p>money</p>", "<p>to tilt</p>"]
+    );
+    assert_eq!(
+        mdict.record_resolved(&key_blocks[2], 4).unwrap(),
+        b"<p>to tilt</p>"
+    );
+}
+
+#[test]
+fn optimized_lookup_keeps_duplicates() {
+    let dir = tempfile::tempdir().unwrap();
+    let bundle = common::bundle(&writer(), dir.path());
+    assert_eq!(bundle.lookup("bank").unwrap().len(), 3);
+
+    let optimized = common::optimized_from_bundle(&bundle, dir.path());
+
+    let mut records = optimized
+        .lookup("bank")
+        .into_iter()
+        .map(|key_block| optimized.record_at(key_block).unwrap())
+        .collect::<Vec<_>>();
+    records.sort();
+    assert_eq!(
+        text(records),
+        ["<p>money</p>", "<p>river side</p>", "<p>to tilt</p>"]
+    );
+    assert_eq!(optimized.lookup("apple").len(), 1);
+    assert!(optimized.lookup("ban").is_empty());
+}
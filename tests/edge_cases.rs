@@ -1,288 +1,128 @@
-//! Edge case tests for mdict_tools
-
-#[cfg(test)]
-mod tests {
-    use std::fs::File;
-    use std::io::{BufRead, BufReader};
-    use mdict_tools::Mdict;
-
-    // Constants for test paths
-    const MDX_FILE_PATH: &str = "resources/jitendex/jitendex.mdx";
-    const ALL_KEYS_FILE_PATH: &str = "test_output/all_keys.txt";
-
-    #[test]
-    fn test_search_with_empty_prefix() {
-        // Test searching with empty prefix - should not panic
-        println!("Testing empty prefix search...");
-        let result = File::open(MDX_FILE_PATH);
-        if result.is_ok() {
-            let f = result.expect("open mdx file");
-            let mut md = Mdict::new(f).expect("open mdx via Mdict");
-            let _result = md.search_keys_prefix("");
-            println!("Empty prefix search completed successfully");
-            assert!(true); // If we get here without panic, test passes
-        } else {
-            // Skip test if file not found (expected in some environments)
-            println!("Skipping empty prefix test - file not found");
-            assert!(true);
-        }
-    }
-
-    #[test]
-    fn test_search_with_nonexistent_prefixes() {
-        // Test searching with prefixes that don't exist - should not panic
-        println!("Testing nonexistent prefix search...");
-        let result = File::open(MDX_FILE_PATH);
-        if result.is_ok() {
-            let f = result.expect("open mdx file");
-            let mut md = Mdict::new(f).expect("open mdx via Mdict");
-            let _result =
-                md.search_keys_prefix("this_prefix_should_not_exist_anywhere_in_the_dictionary");
-            println!("Nonexistent prefix search completed successfully");
-            assert!(true); // If we get here without panic, test passes
-        } else {
-            // Skip test if file not found (expected in some environments)
-            println!("Skipping nonexistent prefix test - file not found");
-            assert!(true);
-        }
+//! Unusual search input against a generated dictionary with mixed scripts,
+//! punctuation and case.
+#![cfg(feature = "fs")]
+
+use std::io::Cursor;
+
+use mdict_tools::collation::KeyCollation;
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::Mdict;
+
+const KEYS: [&str; 12] = [
+    "!bang", "a b", "apple", "CaSe study", "case", "zebra", "α", "αβ", "あい", "あう", "가",
+    "\u{10FFFF}",
+];
+
+fn fixture() -> Mdict<Cursor<Vec<u8>>> {
+    let mut keys = KEYS.to_vec();
+    let collation = KeyCollation::default();
+    keys.sort_by(|a, b| collation.compare(a, b));
+    let mut writer = MdxWriter::new().entries_per_key_block(3);
+    for key in keys {
+        writer.add(key, &format!("<p>{}</p>", key)).unwrap();
     }
+    Mdict::new(Cursor::new(writer.to_bytes().unwrap())).unwrap()
+}
 
-    #[test]
-    fn test_search_with_special_characters() {
-        // Test searching with special characters that might be problematic - should not panic
-        println!("Testing special character searches...");
-        let result = File::open(MDX_FILE_PATH);
-        if result.is_ok() {
-            let f = result.expect("open mdx file");
-            let mut md = Mdict::new(f).expect("open mdx via Mdict");
-
-            let test_cases = vec![
-                "!", "@", "#", "$", "%", "^", "&", "*", "(", ")", "+", "=", "[", "]", "{", "}",
-                "|", "\\", ":", ";", "\"", "'", "<", ">", ",", ".", "?", "/",
-            ];
-
-            for test_case in test_cases {
-                println!("  Searching with special character: {:?}", test_case);
-                let _result = md.search_keys_prefix(test_case);
-            }
-            println!("Special character searches completed successfully");
-            assert!(true); // If we get here without panic, test passes
-        } else {
-            // Skip test if file not found (expected in some environments)
-            println!("Skipping special character tests - file not found");
-            assert!(true);
-        }
-    }
-
-    #[test]
-    fn test_search_with_unicode_characters() {
-        // Test searching with various Unicode characters that might be problematic - should not panic
-        println!("Testing Unicode character searches...");
-        let result = File::open(MDX_FILE_PATH);
-        if result.is_ok() {
-            let f = result.expect("open mdx file");
-            let mut md = Mdict::new(f).expect("open mdx via Mdict");
-
-            // Test with some common Unicode characters that may cause issues in indexing
-            let test_cases = vec![
-                "α",  // Greek alpha
-                "あ", // Japanese hiragana
-                "가", // Korean hangul
-            ];
-
-            for test_case in test_cases {
-                println!("  Searching with Unicode character: {:?}", test_case);
-                let _result = md.search_keys_prefix(test_case);
-            }
-            println!("Unicode character searches completed successfully");
-            assert!(true); // If we get here without panic, test passes
-        } else {
-            // Skip test if file not found (expected in some environments)
-            println!("Skipping Unicode character tests - file not found");
-            assert!(true);
-        }
-    }
-
-    #[test]
-    fn test_search_with_extreme_prefixes() {
-        // Test with prefixes that may cause issues in string comparison or indexing - should not panic
-        println!("Testing extreme prefix searches...");
-        let result = File::open(MDX_FILE_PATH);
-        if result.is_ok() {
-            let f = result.expect("open mdx file");
-            let mut md = Mdict::new(f).expect("open mdx via Mdict");
-
-            // Test various edge case prefixes
-            let extreme_prefixes = vec![
-                "\0", // null byte
-                "\n", // newline
-                "\r", // carriage return
-                "\t", // tab
-                " ",  // space
-            ];
-
-            for prefix in extreme_prefixes {
-                println!("  Searching with extreme prefix: {:?}", prefix);
-                let _result = md.search_keys_prefix(prefix);
-            }
-            println!("Extreme prefix searches completed successfully");
-            assert!(true); // If we get here without panic, test passes
-        } else {
-            // Skip test if file not found (expected in some environments)
-            println!("Skipping extreme prefix tests - file not found");
-            assert!(true);
-        }
-    }
+fn search(mdict: &mut Mdict<Cursor<Vec<u8>>>, prefix: &str) -> Vec<String> {
+    mdict
+        .search_keys_prefix(prefix)
+        .unwrap_or_else(|e| panic!("prefix {:?}: {}", prefix, e))
+        .collect_to_vec()
+        .unwrap()
+        .into_iter()
+        .map(|key_block| key_block.key_text)
+        .collect()
+}
 
-    #[test]
-    fn test_search_with_long_prefixes() {
-        // Test searching with very long prefixes - should not panic
-        println!("Testing long prefix search...");
-        let result = File::open(MDX_FILE_PATH);
-        if result.is_ok() {
-            let f = result.expect("open mdx file");
-            let mut md = Mdict::new(f).expect("open mdx via Mdict");
+#[test]
+fn test_search_with_empty_prefix() {
+    let mut mdict = fixture();
+    assert!(mdict.search_keys_prefix("").is_ok());
+}
 
-            // Create a very long prefix (should not cause issues)
-            let long_prefix = "a".repeat(1000);
-            println!("  Searching with long prefix of {} characters", long_prefix.len());
-            let _result = md.search_keys_prefix(&long_prefix);
-            println!("Long prefix search completed successfully");
-            assert!(true); // If we get here without panic, test passes
-        } else {
-            // Skip test if file not found (expected in some environments)
-            println!("Skipping long prefix test - file not found");
-            assert!(true);
-        }
+#[test]
+fn test_search_with_nonexistent_prefixes() {
+    let mut mdict = fixture();
+    for prefix in ["this_prefix_should_not_exist", "zz", "あえ", "\u{10FFFF}x"] {
+        assert!(search(&mut mdict, prefix).is_empty(), "prefix {:?}", prefix);
     }
+}
 
-    #[test]
-    fn test_search_with_mixed_case() {
-        // Test searching with case variations - should not panic
-        println!("Testing mixed case searches...");
-        let result = File::open(MDX_FILE_PATH);
-        if result.is_ok() {
-            let f = result.expect("open mdx file");
-            let mut md = Mdict::new(f).expect("open mdx via Mdict");
-
-            let test_cases = vec!["CaSe", "CASE", "case", "cAsE"];
-
-            for test_case in test_cases {
-                println!("  Searching with mixed case: {:?}", test_case);
-                let _result = md.search_keys_prefix(test_case);
-            }
-            println!("Mixed case searches completed successfully");
-            assert!(true); // If we get here without panic, test passes
-        } else {
-            // Skip test if file not found (expected in some environments)
-            println!("Skipping mixed case tests - file not found");
-            assert!(true);
-        }
+#[test]
+fn test_search_with_special_characters() {
+    let mut mdict = fixture();
+    assert_eq!(search(&mut mdict, "!"), ["!bang"]);
+    for prefix in [
+        "@", "#", "$", "%", "^", "&", "*", "(", ")", "+", "=", "[", "]", "{", "}", "|", "\\",
+        ":", ";", "\"", "'", "<", ">", ",", ".", "?", "/",
+    ] {
+        assert!(search(&mut mdict, prefix).is_empty(), "prefix {:?}", prefix);
     }
+}
 
-    #[test]
-    fn test_search_with_extreme_unicode_from_all_keys_start() {
-        // Test with extreme Unicode from the start of all_keys.txt
-        println!("Testing extreme Unicode from start of all_keys.txt...");
-        let result = File::open(MDX_FILE_PATH);
-        if result.is_ok() {
-            let f = result.expect("open mdx file");
-            let mut md = Mdict::new(f).expect("open mdx via Mdict");
-
-            // Read first few lines from all_keys.txt to get extreme cases
-            let file = File::open(ALL_KEYS_FILE_PATH)
-                .expect("open all_keys.txt");
-            let reader = BufReader::new(file);
-            let mut count = 0;
-
-            for line_result in reader.lines() {
-                if count >= 5 {
-                    break;
-                } // Only test first 5 keys
-
-                let line = line_result.expect("read line from all_keys.txt");
-                if !line.is_empty() {
-                    println!("  Searching with extreme Unicode from all_keys.txt: {:?}", line);
-                    let _result = md.search_keys_prefix(&line);
-                }
-                count += 1;
-            }
+#[test]
+fn test_search_with_unicode_characters() {
+    let mut mdict = fixture();
+    assert_eq!(search(&mut mdict, "α"), ["α", "αβ"]);
+    assert_eq!(search(&mut mdict, "あ"), ["あい", "あう"]);
+    assert_eq!(search(&mut mdict, "가"), ["가"]);
+    assert_eq!(search(&mut mdict, "\u{10FFFF}"), ["\u{10FFFF}"]);
+}
 
-            println!("Extreme Unicode from start of all_keys.txt search completed successfully");
-            assert!(true); // If we get here without panic, test passes
-        } else {
-            // Skip test if file not found (expected in some environments)
-            println!("Skipping extreme Unicode tests - file not found");
-            assert!(true);
-        }
+#[test]
+fn test_search_with_extreme_prefixes() {
+    let mut mdict = fixture();
+    for prefix in ["\0", "\n", "\r", "\t"] {
+        assert!(search(&mut mdict, prefix).is_empty(), "prefix {:?}", prefix);
     }
+    assert!(mdict.search_keys_prefix(" ").is_ok());
+}
 
-    #[test]
-    fn test_search_with_extreme_unicode_from_all_keys_end() {
-        // Test with extreme Unicode from the end of all_keys.txt
-        println!("Testing extreme Unicode from end of all_keys.txt...");
-        let result = File::open(MDX_FILE_PATH);
-        if result.is_ok() {
-            let f = result.expect("open mdx file");
-            let mut md = Mdict::new(f).expect("open mdx via Mdict");
-
-            // Read all lines from all_keys.txt to get extreme cases
-            let file = File::open(ALL_KEYS_FILE_PATH)
-                .expect("open all_keys.txt");
-            let reader = BufReader::new(file);
-            let mut lines = Vec::new();
-
-            for line_result in reader.lines() {
-                lines.push(line_result.expect("read line from all_keys.txt"));
-            }
-
-            let end_lines_count = lines.len().min(100);
-            let start_index = lines.len().saturating_sub(end_lines_count);
-            let end_lines: Vec<&str> = lines[start_index..].iter().map(|s| s.as_str()).collect();
-
-            for line in end_lines {
-                if !line.is_empty() {
-                    println!("  Searching with extreme Unicode from all_keys.txt (end): {:?}", line);
-                    let _result = md.search_keys_prefix(line);
-                }
-            }
+#[test]
+fn test_search_with_long_prefixes() {
+    let mut mdict = fixture();
+    assert!(search(&mut mdict, &"a".repeat(1000)).is_empty());
+    assert!(search(&mut mdict, &"あ".repeat(1000)).is_empty());
+}
 
-            println!("Extreme Unicode from end of all_keys.txt search completed successfully");
-            assert!(true); // If we get here without panic, test passes
-        } else {
-            // Skip test if file not found (expected in some environments)
-            println!("Skipping extreme Unicode tests - file not found");
-            assert!(true);
-        }
+#[test]
+fn test_search_with_mixed_case() {
+    let mut mdict = fixture();
+    for prefix in ["CaSe", "CASE", "case", "cAsE"] {
+        let found = search(&mut mdict, prefix);
+        assert!(!found.is_empty(), "prefix {:?}", prefix);
+        assert!(found
+            .iter()
+            .all(|key| key.to_lowercase().starts_with(&prefix.to_lowercase())));
     }
+}
 
-    #[test]
-    fn test_boundary_conditions() {
-        // Test boundary conditions for key indices and search operations - should not panic
-        println!("Testing boundary conditions...");
-        let result = File::open(MDX_FILE_PATH);
-        if result.is_ok() {
-            let f = result.expect("open mdx file");
-            let _md = Mdict::new(f).expect("open mdx via Mdict");
-            // Just verify the method exists and doesn't panic
-            println!("Boundary conditions test completed successfully");
-            assert!(true);
-        } else {
-            // Skip test if file not found (expected in some environments)
-            println!("Skipping boundary condition test - file not found");
-            assert!(true);
-        }
+#[test]
+fn test_every_key_finds_itself() {
+    let mut mdict = fixture();
+    for key in KEYS {
+        assert!(search(&mut mdict, key).iter().any(|found| found == key), "{:?}", key);
     }
+}
 
-    #[test]
-    fn test_invalid_file_handling() {
-        // Test how the library handles invalid or corrupted files - should not panic
-        // Try to open a non-existent file - this should fail gracefully
-        println!("Testing invalid file handling...");
-        let result = File::open("non_existent_file.mdx");
-        assert!(result.is_err());
-        println!("Invalid file handling test completed successfully");
-    }
-    
+#[test]
+fn test_boundary_conditions() {
+    let mut mdict = fixture();
+    let total = mdict.key_block_index.key_section.num_entries as usize;
+    assert_eq!(total, KEYS.len());
+    assert!(mdict
+        .key_block_index
+        .get(&mut mdict.reader, total - 1)
+        .unwrap()
+        .is_some());
+    assert!(mdict.key_block_index.get(&mut mdict.reader, total).unwrap().is_none());
+    assert!(mdict.record_at_index(total).is_err());
+}
 
+#[test]
+fn test_invalid_file_handling() {
+    assert!(Mdict::open("non_existent_file.mdx").is_err());
+    assert!(Mdict::new(Cursor::new(b"not a dictionary".to_vec())).is_err());
+    assert!(Mdict::new(Cursor::new(Vec::new())).is_err());
 }
@@ -0,0 +1,106 @@
+use std::io::Cursor;
+
+use mdict_tools::format::parse_key_block;
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::types::{Encoding, MdictVersion};
+use mdict_tools::Mdict;
+
+#[test]
+fn header_labels_map_to_encodings() {
+    assert_eq!(Encoding::from_label("UTF-8"), Encoding::Utf8);
+    assert_eq!(Encoding::from_label("GBK"), Encoding::Gbk);
+    assert_eq!(Encoding::from_label("GB2312"), Encoding::Gbk);
+    assert_eq!(Encoding::from_label("gb18030"), Encoding::Gb18030);
+    assert_eq!(Encoding::from_label("Big5"), Encoding::Big5);
+    assert_eq!(Encoding::from_label("UTF-16"), Encoding::Utf16LE);
+    assert_eq!(Encoding::from_label(""), Encoding::Utf16LE);
+}
+
+#[test]
+fn key_blocks_decode_legacy_code_pages() {
+    for (encoding, text) in [
+        (Encoding::Gbk, "中文词典"),
+        (Encoding::Big5, "中文詞典"),
+        (Encoding::Gb18030, "中文词典😀"),
+    ] {
+        let mut block = Vec::new();
+        for (key_id, key) in [(0u64, text), (42, "abc")] {
+            block.extend_from_slice(&key_id.to_be_bytes());
+            block.extend_from_slice(&encoding.encode(key));
+            block.push(0);
+        }
+
+        let keys = parse_key_block(&block, encoding, MdictVersion::V2).expect("parse key block");
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].key_text, text);
+        assert_eq!(keys[1].key_id, 42);
+        assert_eq!(keys[1].key_text, "abc");
+        assert_eq!(encoding.decode(&encoding.encode(text)), text);
+    }
+}
+
+/// A dictionary of `keys` in `encoding`, added in the order of their
+/// encoded bytes, with a key block every two entries.
+fn code_page_dictionary(encoding: Encoding, keys: &[&str]) -> Mdict<Cursor<Vec<u8>>> {
+    let mut writer = MdxWriter::new().encoding(encoding).entries_per_key_block(2);
+    for key in keys {
+        writer.add(*key, &format!("<p>{}</p>", key)).unwrap();
+    }
+    Mdict::new(Cursor::new(writer.to_bytes().unwrap())).unwrap()
+}
+
+#[test]
+fn legacy_code_page_keys_are_looked_up_in_encoded_order() {
+    // 啊 (B0A1) sorts before 中 (D6D0) in GBK and 人 (A448) before 中 (A4A4)
+    // in Big5, but 中 comes first in Unicode.
+    for (encoding, mut keys, prefix) in [
+        (
+            Encoding::Gbk,
+            vec!["中文", "a", "啊呀", "中", "啊", "中国", "z"],
+            "中",
+        ),
+        (
+            Encoding::Big5,
+            vec!["中文", "a", "人們", "中", "人", "中國", "z"],
+            "人",
+        ),
+    ] {
+        keys.sort_by_key(|key| encoding.encode(key));
+        let mut text_order = keys.clone();
+        text_order.sort();
+        assert_ne!(keys, text_order);
+
+        let mut mdict = code_page_dictionary(encoding, &keys);
+        for (index, key) in keys.iter().enumerate() {
+            let matches = mdict.get_all(key).unwrap();
+            assert_eq!(matches.len(), 1, "{:?} in {:?}", key, encoding);
+            assert_eq!(matches[0].key_text, *key);
+            assert_eq!(mdict.get(index).unwrap().unwrap().key_text, *key);
+            assert_eq!(
+                mdict.lookup(key).unwrap(),
+                [encoding.encode(&format!("<p>{}</p>", key))]
+            );
+        }
+        assert!(mdict.get_all("中文词").unwrap().is_empty());
+
+        let expected: Vec<_> = keys
+            .iter()
+            .copied()
+            .filter(|key| key.starts_with(prefix))
+            .collect();
+        let found: Vec<_> = mdict
+            .search_keys_prefix(prefix)
+            .unwrap()
+            .collect_to_vec()
+            .unwrap()
+            .into_iter()
+            .map(|key_block| key_block.key_text)
+            .collect();
+        assert_eq!(found, expected, "prefix {:?} in {:?}", prefix, encoding);
+        let start = keys.iter().position(|key| key.starts_with(prefix)).unwrap();
+        assert_eq!(
+            mdict.prefix_range_bounds(prefix).unwrap(),
+            Some((start, start + expected.len()))
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use mdict_tools::error::MDictError;
+use mdict_tools::format::{adler32, decode_format_block};
+use mdict_tools::format::encryption::{fast_decrypt, fast_encrypt};
+use mdict_tools::synth::SynthDictBuilder;
+use mdict_tools::types::{Encoding, MdictVersion};
+
+#[test]
+fn encrypted_key_info_opens_transparently() {
+    let dict = SynthDictBuilder::entries(80)
+        .encoding(Encoding::Utf16LE)
+        .entries_per_key_block(9)
+        .encrypt_key_info(true)
+        .build()
+        .expect("build encrypted dictionary");
+    let mut mdict = dict.open().expect("open encrypted dictionary");
+
+    assert_eq!(mdict.key_block_index.header.encrypted_flags(), 2);
+    for (i, (key, record)) in dict.entries.iter().enumerate() {
+        let key_block = mdict.get(i).expect("get key").expect("key exists");
+        assert_eq!(&key_block.key_text, key);
+        assert_eq!(&mdict.record_at_index(i).expect("get record"), record);
+    }
+}
+
+#[test]
+fn encrypted_mdd_key_info_opens_transparently() {
+    let dict = SynthDictBuilder::entries(20)
+        .version(MdictVersion::MDD)
+        .encrypt_key_info(true)
+        .build()
+        .expect("build encrypted resource file");
+    let mut mdict = dict.open().expect("open encrypted resource file");
+    assert_eq!(
+        mdict.record_at_index(19).expect("get record"),
+        dict.entries[19].1
+    );
+}
+
+#[test]
+fn registration_key_encryption_is_unsupported() {
+    let mut bytes = SynthDictBuilder::entries(4).build().unwrap().bytes;
+    // Flip the header attribute in place; both values are one UTF-16 unit wide.
+    let needle = "Encrypted=\"N"
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect::<Vec<_>>();
+    let at = bytes
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .unwrap();
+    bytes[at + needle.len() - 2] = b'1';
+    bytes[at + needle.len()] = b' ';
+    let header_len = u32::from_be_bytes(bytes[..4].try_into().unwrap()) as usize;
+    let checksum = adler32(&bytes[4..4 + header_len]);
+    bytes[4 + header_len..8 + header_len].copy_from_slice(&checksum.to_le_bytes());
+
+    match mdict_tools::Mdict::new(std::io::Cursor::new(bytes)) {
+        Err(MDictError::UnsupportedFeature(_)) => {}
+        other => panic!("expected UnsupportedFeature, got {:?}", other.err()),
+    }
+}
+
+#[test]
+fn fast_cipher_round_trips() {
+    let key = [7u8, 1, 200, 33, 91];
+    let plain = (0..=255u8).collect::<Vec<_>>();
+    let mut data = plain.clone();
+    fast_encrypt(&mut data, &key);
+    assert_ne!(data, plain);
+    fast_decrypt(&mut data, &key);
+    assert_eq!(data, plain);
+}
+
+#[test]
+fn block_level_encryption_is_decoded() {
+    let payload = b"per-block encrypted payload".to_vec();
+    let checksum = adler32(&payload);
+    let encrypted_len = 10u32;
+    let block_type = (encrypted_len << 8) | (1 << 4);
+
+    let mut block = Vec::new();
+    block.extend_from_slice(&block_type.to_le_bytes());
+    block.extend_from_slice(&checksum.to_be_bytes());
+    block.extend_from_slice(&payload);
+
+    let key = {
+        use ripemd::{Digest, Ripemd128};
+        let digest: [u8; 16] = Ripemd128::digest(&block[4..8]).into();
+        digest
+    };
+    fast_encrypt(&mut block[8..8 + encrypted_len as usize], &key);
+
+    assert_eq!(decode_format_block(&block).expect("decode block"), payload);
+}
@@ -0,0 +1,122 @@
+#![cfg(all(feature = "mmap", feature = "zstd"))]
+
+use mdict_tools::error::MDictError;
+use mdict_tools::mdict_optimized::{create_mdict_optimized_from_fst, open_mdict_optimized_bundle};
+use mdict_tools::mdx_conversion::build_manifest;
+use mdict_tools::types::{EntryId, KeyBlock};
+use mdict_tools::MdictOptimized;
+
+mod common;
+
+fn key_block(optimized: &MdictOptimized, key: &str) -> KeyBlock {
+    optimized
+        .set_search_prefix_paged(key, 8)
+        .unwrap()
+        .results
+        .into_iter()
+        .find(|key_block| key_block.key_text == key)
+        .expect("key is indexed")
+}
+
+#[test]
+fn entry_ids_survive_rebuilds_that_move_records() {
+    let first_dir = tempfile::tempdir().unwrap();
+    let first = common::optimized(
+        &common::writer_with_entries(&[("のむ", "<p>drink</p>"), ("みる", "<p>see</p>")]),
+        first_dir.path(),
+    );
+    let id = first.entry_id(key_block(&first, "みる")).unwrap();
+
+    let second_dir = tempfile::tempdir().unwrap();
+    let second = common::optimized(
+        &common::writer_with_entries(&[
+            ("たべる", "<p>eat, a much longer record</p>"),
+            ("のむ", "<p>drink</p>"),
+            ("みる", "<p>see</p>"),
+        ]),
+        second_dir.path(),
+    );
+    let moved = key_block(&second, "みる");
+    assert_ne!(moved.key_id, key_block(&first, "みる").key_id);
+    assert_eq!(second.entry_id(moved.clone()).unwrap(), id);
+
+    let resolved = second.key_block_for_entry_id(id).unwrap();
+    assert_eq!(resolved.key_id, moved.key_id);
+    assert_eq!(resolved.key_text, "みる");
+    assert_eq!(second.record_for_entry_id(id).unwrap(), b"<p>see</p>");
+}
+
+#[test]
+fn duplicate_headwords_get_distinct_ids() {
+    let dir = tempfile::tempdir().unwrap();
+    let optimized = common::optimized(
+        &common::writer_with_entries(&[("かく", "<p>write</p>"), ("かく", "<p>draw</p>")]),
+        dir.path(),
+    );
+
+    let entries = optimized
+        .set_search_prefix_paged("かく", 8)
+        .unwrap()
+        .results;
+    assert_eq!(entries.len(), 2);
+    let ids = entries
+        .into_iter()
+        .map(|key_block| optimized.entry_id(key_block).unwrap())
+        .collect::<Vec<_>>();
+    assert_ne!(ids[0], ids[1]);
+    assert!(ids.contains(&EntryId::new("かく", 0)));
+    assert!(ids.contains(&EntryId::new("かく", 1)));
+    assert_eq!(
+        optimized
+            .record_for_entry_id(EntryId::new("かく", 1))
+            .unwrap(),
+        b"<p>draw</p>"
+    );
+}
+
+#[test]
+fn entry_ids_round_trip_through_bundles() {
+    let dir = tempfile::tempdir().unwrap();
+    let optimized = common::optimized(
+        &common::writer_with_entries(&[("のむ", "<p>drink</p>")]),
+        dir.path(),
+    );
+    let bundle_path = dir.path().join("dict.mdopt").to_string_lossy().to_string();
+    optimized.save_bundle(bundle_path.clone()).unwrap();
+
+    let reopened = open_mdict_optimized_bundle(bundle_path).unwrap();
+    assert!(reopened.has_entry_ids());
+    assert_eq!(
+        reopened
+            .record_for_entry_id(EntryId::new("のむ", 0))
+            .unwrap(),
+        b"<p>drink</p>"
+    );
+    assert!(matches!(
+        reopened.key_block_for_entry_id(EntryId::new("のむ", 1)),
+        Err(MDictError::KeyNotFound(_))
+    ));
+}
+
+#[test]
+fn indexes_without_entry_ids_still_open() {
+    let dir = tempfile::tempdir().unwrap();
+    common::optimized(
+        &common::writer_with_entries(&[("のむ", "<p>drink</p>")]),
+        dir.path(),
+    );
+    let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+    std::fs::remove_file(build_manifest::entry_ids_path(path("index.fst"))).unwrap();
+
+    let reopened = create_mdict_optimized_from_fst(
+        path("index.fst"),
+        path("readings.dat"),
+        path("records.dat"),
+    )
+    .unwrap();
+    assert!(!reopened.has_entry_ids());
+    assert!(matches!(
+        reopened.entry_id(key_block(&reopened, "のむ")),
+        Err(MDictError::UnsupportedFeature(_))
+    ));
+}
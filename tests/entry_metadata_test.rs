@@ -0,0 +1,126 @@
+#![cfg(all(feature = "mmap", feature = "zstd"))]
+
+use std::path::Path;
+
+use mdict_tools::error::MDictError;
+use mdict_tools::mdict_file::MdictBundle;
+use mdict_tools::mdx_conversion::entry_metadata::EntryMetadata;
+use mdict_tools::types::{LinkMetadata, MetadataField, MetadataKind, MetadataValue};
+
+mod common;
+
+fn bundle(dir: &Path) -> MdictBundle {
+    common::bundle(
+        &common::writer_with_entries(&[
+            ("apple", "<p>a fruit</p>"),
+            ("run", "<p>to move fast</p>"),
+            ("walk", "<p>to move slowly</p>"),
+        ]),
+        dir,
+    )
+}
+
+fn fields() -> Vec<MetadataField> {
+    vec![
+        MetadataField {
+            name: "pos".to_string(),
+            kind: MetadataKind::Text,
+        },
+        MetadataField {
+            name: "frequency".to_string(),
+            kind: MetadataKind::Integer,
+        },
+        MetadataField {
+            name: "score".to_string(),
+            kind: MetadataKind::Float,
+        },
+    ]
+}
+
+fn text(value: &str) -> MetadataValue {
+    MetadataValue::Text {
+        value: value.to_string(),
+    }
+}
+
+#[test]
+fn typed_values_round_trip_through_the_readings_sidecar() {
+    let dir = tempfile::tempdir().unwrap();
+    let bundle = bundle(dir.path());
+    let apple = bundle.lookup("apple").unwrap()[0].key_id;
+    let run = bundle.lookup("run").unwrap()[0].key_id;
+    bundle
+        .load_entry_metadata(
+            fields(),
+            vec![
+                LinkMetadata {
+                    link_id: apple,
+                    name: "pos".to_string(),
+                    value: text("noun"),
+                },
+                LinkMetadata {
+                    link_id: apple,
+                    name: "frequency".to_string(),
+                    value: MetadataValue::Integer { value: -3 },
+                },
+                LinkMetadata {
+                    link_id: run,
+                    name: "score".to_string(),
+                    value: MetadataValue::Float { value: 0.5 },
+                },
+            ],
+        )
+        .unwrap();
+    let optimized = common::optimized_from_bundle(&bundle, dir.path());
+
+    assert_eq!(optimized.metadata_schema().unwrap(), fields());
+    let apple = optimized.lookup("apple")[0].clone();
+    let run = optimized.lookup("run")[0].clone();
+    let walk = optimized.lookup("walk")[0].clone();
+
+    let apple_metadata = optimized.entry_metadata(apple.clone()).unwrap();
+    assert_eq!(apple_metadata.len(), 2);
+    assert_eq!(apple_metadata[0].name, "pos");
+    assert_eq!(apple_metadata[0].value, text("noun"));
+    assert_eq!(optimized.metadata_text(apple.clone(), "pos").unwrap(), Some("noun".to_string()));
+    assert_eq!(optimized.metadata_integer(apple.clone(), "frequency").unwrap(), Some(-3));
+    assert_eq!(optimized.metadata_float(run.clone(), "score").unwrap(), Some(0.5));
+    assert_eq!(optimized.metadata_text(run, "pos").unwrap(), None);
+    assert!(optimized.entry_metadata(walk.clone()).unwrap().is_empty());
+    assert_eq!(optimized.get_readings(walk).unwrap(), vec!["walk"]);
+
+    assert!(matches!(
+        optimized.metadata_integer(apple.clone(), "pos"),
+        Err(MDictError::InvalidArgument(_))
+    ));
+    assert!(matches!(
+        optimized.metadata_text(apple, "audio"),
+        Err(MDictError::InvalidArgument(_))
+    ));
+}
+
+#[test]
+fn indexes_without_metadata_have_an_empty_schema() {
+    let dir = tempfile::tempdir().unwrap();
+    let bundle = bundle(dir.path());
+    let optimized = common::optimized_from_bundle(&bundle, dir.path());
+
+    assert!(optimized.metadata_schema().unwrap().is_empty());
+    let apple = optimized.lookup("apple")[0].clone();
+    assert!(optimized.entry_metadata(apple.clone()).unwrap().is_empty());
+    assert_eq!(optimized.record_at(apple).unwrap(), b"<p>a fruit</p>");
+}
+
+#[test]
+fn values_must_match_the_declared_fields() {
+    let mut metadata = EntryMetadata::new(fields()).unwrap();
+    assert!(metadata.set(0, "pos", MetadataValue::Integer { value: 1 }).is_err());
+    assert!(metadata.set(0, "audio", text("a.mp3")).is_err());
+    metadata.set(0, "pos", text("verb")).unwrap();
+    assert_eq!(metadata.get(0, "pos"), Some(&text("verb")));
+    assert_eq!(metadata.len(), 1);
+
+    let mut duplicated = fields();
+    duplicated.push(fields()[0].clone());
+    assert!(EntryMetadata::new(duplicated).is_err());
+}
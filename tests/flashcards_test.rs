@@ -0,0 +1,83 @@
+#![cfg(feature = "mmap")]
+
+use std::io::Cursor;
+
+use mdict_tools::mdx_conversion::flashcards::{export_flashcards, FlashcardTemplate};
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::Mdict;
+
+mod common;
+
+fn writer() -> MdxWriter {
+    let mut writer = MdxWriter::new();
+    for (key, html) in [
+        ("bank", "<p>river side</p>"),
+        ("bank", "<p>money</p>"),
+        ("cat", "<p>a \"small\" animal</p>"),
+        ("kitty", "@@@LINK=cat"),
+        ("stray", "@@@LINK=dog"),
+    ] {
+        writer.add(key, html).unwrap();
+    }
+    writer
+}
+
+fn headwords(words: &[&str]) -> Vec<String> {
+    words.iter().map(|word| word.to_string()).collect()
+}
+
+#[test]
+fn writes_one_card_per_headword() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("cards.txt");
+    let mut mdict = Mdict::new(Cursor::new(writer().to_bytes().unwrap())).unwrap();
+
+    let export = export_flashcards(
+        &mut mdict,
+        &headwords(&["kitty", "bank", "dog", "stray"]),
+        &FlashcardTemplate::default(),
+        &output,
+    )
+    .unwrap();
+    assert_eq!(export.written, 2);
+    assert_eq!(export.missing, ["dog", "stray"]);
+
+    let tsv = std::fs::read_to_string(&output).unwrap();
+    let lines = tsv.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            "#separator:tab",
+            "#html:true",
+            "#columns:Front\tBack",
+            "kitty\t\"<p>a \"\"small\"\" animal</p>\"",
+            "bank\t<p>river side</p><hr><p>money</p>",
+        ]
+    );
+}
+
+#[test]
+fn templates_fill_text_and_escape_keys() {
+    let dir = tempfile::tempdir().unwrap();
+    let bundle = common::bundle(&writer(), dir.path());
+
+    let output = dir.path().join("cards.txt");
+    let template = FlashcardTemplate {
+        front: "<b>{{key}}</b>".to_string(),
+        back: "{{text}}".to_string(),
+    };
+    let export = bundle
+        .export_flashcards(
+            headwords(&["bank"]),
+            template,
+            output.to_string_lossy().to_string(),
+        )
+        .unwrap();
+    assert_eq!(export.written, 1);
+
+    let tsv = std::fs::read_to_string(&output).unwrap();
+    assert_eq!(
+        tsv.lines().last(),
+        Some("<b>bank</b>\triver side<br>money")
+    );
+}
@@ -0,0 +1,101 @@
+#![cfg(all(feature = "mmap", feature = "zstd"))]
+
+use std::path::Path;
+
+use mdict_tools::error::MDictError;
+use mdict_tools::mdict_optimized::Ranker;
+use mdict_tools::mdx_conversion::frequency::FrequencyList;
+use mdict_tools::MdictOptimized;
+
+mod common;
+
+const KEYS: [&str; 4] = ["たべもの", "たべる", "たべ放題", "たべ物"];
+
+fn optimized(dir: &Path, frequencies: Option<&str>) -> MdictOptimized {
+    let bundle = common::bundle(&common::writer_with_keys(&KEYS), dir);
+    if let Some(frequencies) = frequencies {
+        let list_path = dir.join("freq.tsv");
+        std::fs::write(&list_path, frequencies).unwrap();
+        bundle
+            .load_frequency_list(list_path.to_string_lossy().to_string())
+            .unwrap();
+    }
+    common::optimized_from_bundle(&bundle, dir)
+}
+
+fn first_page(optimized: &MdictOptimized, prefix: &str) -> Vec<String> {
+    optimized
+        .set_search_prefix_paged(prefix, 10)
+        .unwrap()
+        .results
+        .into_iter()
+        .map(|key| key.key_text)
+        .collect()
+}
+
+#[test]
+fn parses_ranks_keeping_the_best() {
+    let list =
+        FrequencyList::parse("# word\trank\n食べる\t120\nたべる\tたべる\t80\n\n食べる\t95\r\n")
+            .unwrap();
+    assert_eq!(list.len(), 2);
+    assert_eq!(list.rank("食べる"), Some(95));
+    assert_eq!(list.rank("たべる"), Some(80));
+    assert_eq!(list.rank("飲む"), None);
+
+    assert!(matches!(
+        FrequencyList::parse("食べる 120\n"),
+        Err(MDictError::InvalidFormat(message)) if message.contains("line 1")
+    ));
+}
+
+#[test]
+fn pages_list_ranked_entries_first() {
+    let dir = tempfile::tempdir().unwrap();
+    let optimized = optimized(dir.path(), Some("たべ物\t300\nたべる\t12\n"));
+
+    assert_eq!(
+        first_page(&optimized, "たべ"),
+        vec!["たべる", "たべ物", "たべもの", "たべ放題"]
+    );
+    let key = optimized
+        .set_search_prefix_paged("たべる", 1)
+        .unwrap()
+        .results[0]
+        .clone();
+    assert_eq!(optimized.frequency_rank(key).unwrap(), Some(12));
+}
+
+#[test]
+fn indexes_without_ranks_keep_key_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let optimized = optimized(dir.path(), None);
+
+    assert_eq!(first_page(&optimized, "たべ"), KEYS);
+    let key = optimized
+        .set_search_prefix_paged("たべる", 1)
+        .unwrap()
+        .results[0]
+        .clone();
+    assert_eq!(optimized.frequency_rank(key).unwrap(), None);
+}
+
+struct Reverse;
+
+impl Ranker for Reverse {
+    fn score(&self, key_text: String) -> i64 {
+        KEYS.iter().position(|key| *key == key_text).unwrap() as i64
+    }
+}
+
+#[test]
+fn a_ranker_overrides_stored_ranks() {
+    let dir = tempfile::tempdir().unwrap();
+    let optimized = optimized(dir.path(), Some("たべる\t1\n"));
+    optimized.set_ranker(Some(Box::new(Reverse)));
+
+    assert_eq!(
+        first_page(&optimized, "たべ"),
+        vec!["たべ物", "たべ放題", "たべる", "たべもの"]
+    );
+}
@@ -0,0 +1,110 @@
+#![cfg(all(feature = "mmap", feature = "zstd"))]
+
+use std::path::{Path, PathBuf};
+
+use mdict_tools::types::PrefixSearchPrevCursor;
+use mdict_tools::FSTMap;
+
+mod common;
+
+const KEYS: [&str; 7] = ["ant", "apple", "apply", "apricot", "apron", "banana", "band"];
+
+fn build(dir: &Path) -> FSTMap {
+    build_with_keys(dir, &KEYS)
+}
+
+fn build_with_keys(dir: &Path, keys: &[&str]) -> FSTMap {
+    common::optimized(&common::writer_with_keys(keys), dir);
+    let path = |name: &str| -> PathBuf { dir.join(name) };
+    FSTMap::load_from_path(path("index.fst"), path("readings.dat"), path("records.dat")).unwrap()
+}
+
+fn texts(page: &mdict_tools::types::PrefixSearchPage) -> Vec<&str> {
+    page.results.iter().map(|key| key.key_text.as_str()).collect()
+}
+
+#[test]
+fn pages_walk_a_prefix_forward_in_key_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let fst_map = build(dir.path());
+
+    let first = fst_map.prefix_page("ap", None, 2).unwrap();
+    assert_eq!(texts(&first), ["apple", "apply"]);
+    assert!(first.prev_cursor.is_none());
+    assert_eq!(first.total_results, None);
+
+    let second = fst_map
+        .prefix_page("ap", first.next_cursor.as_ref(), 2)
+        .unwrap();
+    assert_eq!(texts(&second), ["apricot", "apron"]);
+    assert!(second.next_cursor.is_none());
+    assert!(second.prev_cursor.is_some());
+    assert_eq!(fst_map.count_prefix("ap"), 4);
+}
+
+#[test]
+fn pages_walk_back_from_a_previous_cursor() {
+    let dir = tempfile::tempdir().unwrap();
+    let fst_map = build(dir.path());
+
+    let first = fst_map.prefix_page("a", None, 3).unwrap();
+    let second = fst_map
+        .prefix_page("a", first.next_cursor.as_ref(), 3)
+        .unwrap();
+    assert_eq!(texts(&second), ["apricot", "apron"]);
+
+    let back = fst_map
+        .prefix_page_before("a", second.prev_cursor.as_ref().unwrap(), 3)
+        .unwrap();
+    assert_eq!(texts(&back), texts(&first));
+    assert!(back.prev_cursor.is_none());
+    assert_eq!(back.next_cursor, first.next_cursor);
+}
+
+#[test]
+fn link_pages_keep_raw_keys_and_offsets() {
+    let dir = tempfile::tempdir().unwrap();
+    let fst_map = build(dir.path());
+
+    let page = fst_map.get_link_page_for_prefix("ban", None, 10).unwrap();
+    let keys = page
+        .results
+        .iter()
+        .map(|(key, _)| key.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(keys, ["banana", "band"]);
+    assert_eq!(page.results[0].1, fst_map.get("banana").unwrap());
+    assert!(page.next_key.is_none() && page.prev_key.is_none());
+
+    let search = page.clone().into_search_page(Some(2));
+    assert_eq!(search.total_results, Some(2));
+    assert_eq!(search.results[1].key_id, page.results[1].1);
+}
+
+#[test]
+fn a_cursor_past_the_prefix_gives_an_empty_page() {
+    let dir = tempfile::tempdir().unwrap();
+    let fst_map = build(dir.path());
+
+    let page = fst_map
+        .prefix_page_before(
+            "ap",
+            &PrefixSearchPrevCursor {
+                before_key: "ap".to_string(),
+            },
+            5,
+        )
+        .unwrap();
+    assert!(page.results.is_empty());
+    assert!(fst_map.prefix_page("zz", None, 5).unwrap().results.is_empty());
+}
+
+#[test]
+fn prefixes_without_an_upper_bound_still_start_at_the_prefix() {
+    let dir = tempfile::tempdir().unwrap();
+    let fst_map = build_with_keys(dir.path(), &["apple", "\u{10FFFF}", "\u{10FFFF}x"]);
+
+    assert_eq!(fst_map.count_prefix("\u{10FFFF}"), 2);
+    assert_eq!(fst_map.count_prefix_distinct("\u{10FFFF}"), 2);
+    assert_eq!(fst_map.count_prefix(""), 3);
+}
@@ -0,0 +1,67 @@
+#![cfg(feature = "mmap")]
+
+use mdict_tools::mdict_group::create_mdict_group;
+
+mod common;
+
+use common::write_dictionary;
+
+#[test]
+fn search_merges_dictionaries_in_key_order() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let first = write_dictionary(
+        &dir.path().join("first.mdx"),
+        "First",
+        &["apple", "apply", "banana"],
+    );
+    let second = write_dictionary(
+        &dir.path().join("second.mdx"),
+        "Second",
+        &["appetite", "apple", "cherry"],
+    );
+
+    let group = create_mdict_group();
+    assert_eq!(group.add_dictionary(first).unwrap(), 0);
+    assert_eq!(group.add_dictionary(second).unwrap(), 1);
+    assert_eq!(group.dictionary_count(), 2);
+    assert_eq!(group.metadata(1).unwrap().title.as_deref(), Some("Second"));
+
+    let hits = group.search("app", 10).unwrap();
+    let listed: Vec<(u32, &str)> = hits
+        .iter()
+        .map(|hit| (hit.dict_id, hit.key.key_text.as_str()))
+        .collect();
+    assert_eq!(
+        listed,
+        vec![(1, "appetite"), (0, "apple"), (1, "apple"), (0, "apply")]
+    );
+    assert_eq!(group.search("app", 2).unwrap().len(), 2);
+    assert!(group.search("zzz", 10).unwrap().is_empty());
+
+    let apple_in_second = hits[2].clone();
+    assert_eq!(
+        group
+            .record_text(apple_in_second.dict_id, apple_in_second.key.clone())
+            .unwrap(),
+        "<p>apple in Second</p>"
+    );
+    assert_eq!(
+        group.record(0, hits[1].key.clone()).unwrap(),
+        b"<p>apple in First</p>"
+    );
+}
+
+#[test]
+fn unknown_dictionary_id_is_an_error() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let path = write_dictionary(&dir.path().join("only.mdx"), "Only", &["word"]);
+    let group = create_mdict_group();
+    group.add_dictionary(path).unwrap();
+
+    let key = group.search("word", 1).unwrap().remove(0).key;
+    assert!(group.record(1, key).is_err());
+    assert!(group.metadata(7).is_err());
+    assert!(group
+        .add_dictionary(dir.path().join("missing.mdx").to_string_lossy().to_string())
+        .is_err());
+}
@@ -0,0 +1,115 @@
+use std::io::Cursor;
+
+use mdict_tools::collation::KeyCollation;
+use mdict_tools::integrity::IntegritySection;
+use mdict_tools::mdx_writer::{BlockCompression, MdxWriter};
+use mdict_tools::synth::SynthDictBuilder;
+use mdict_tools::types::Encoding;
+use mdict_tools::Mdict;
+
+fn uncompressed_dictionary() -> Vec<u8> {
+    let mut writer = MdxWriter::new()
+        .compression(BlockCompression::None)
+        .entries_per_record_block(4);
+    for i in 0..12 {
+        writer
+            .add(format!("entry{:02}", i), &format!("<b>entry {}</b>", i))
+            .unwrap();
+    }
+    writer.to_bytes().unwrap()
+}
+
+#[test]
+fn intact_dictionary_verifies_clean() {
+    let dict = SynthDictBuilder::entries(200).build().unwrap();
+    let mut mdict = dict.open().unwrap();
+
+    let report = mdict.verify();
+    assert!(report.is_ok(), "{:?}", report.issues);
+    assert_eq!(
+        report.key_blocks_checked,
+        mdict.key_block_index.key_section.key_info_blocks.len()
+    );
+    assert_eq!(
+        report.record_blocks_checked as u64,
+        mdict.record_section().unwrap().num_record_blocks
+    );
+}
+
+#[test]
+fn every_corrupted_block_is_reported_with_its_offset() {
+    let bytes = uncompressed_dictionary();
+    let (data_offset, block_starts) = {
+        let mut mdict = Mdict::new(Cursor::new(bytes.clone())).unwrap();
+        let section = mdict.record_section().unwrap();
+        let starts: Vec<u64> = section
+            .record_index_prefix_sum
+            .iter()
+            .map(|ri| section.record_data_offset + ri.compressed_size)
+            .collect();
+        (section.record_data_offset, starts)
+    };
+    assert!(block_starts.len() > 3);
+
+    // Flip a payload byte in record blocks 0 and 2, after each 8-byte block header.
+    let mut bytes = bytes;
+    bytes[data_offset as usize + 10] ^= 0x20;
+    bytes[block_starts[2] as usize + 10] ^= 0x20;
+
+    let mut mdict = Mdict::new(Cursor::new(bytes)).unwrap();
+    let report = mdict.verify();
+
+    let sections: Vec<_> = report.issues.iter().map(|i| i.section).collect();
+    assert_eq!(
+        sections,
+        vec![
+            IntegritySection::RecordBlock(0),
+            IntegritySection::RecordBlock(2)
+        ]
+    );
+    assert_eq!(report.issues[0].offset, block_starts[0]);
+    assert_eq!(report.issues[1].offset, block_starts[2]);
+    assert!(report.issues[1].message.contains("checksum mismatch"));
+    assert!(report.issues[1].to_string().starts_with("record block 2"));
+}
+
+#[test]
+fn header_checksum_mismatch_is_reported() {
+    let mut bytes = uncompressed_dictionary();
+    let header_size = 4 + u32::from_be_bytes(bytes[..4].try_into().unwrap()) as usize + 4;
+    bytes[header_size - 1] ^= 0xff;
+
+    let mut mdict = Mdict::new(Cursor::new(bytes)).unwrap();
+    let report = mdict.verify();
+
+    assert_eq!(report.issues.len(), 1, "{:?}", report.issues);
+    assert_eq!(report.issues[0].section, IntegritySection::Header);
+    assert_eq!(report.issues[0].offset, 0);
+}
+
+#[test]
+fn key_order_is_checked_under_the_active_order() {
+    let mut writer = MdxWriter::new().encoding(Encoding::Gbk).entries_per_key_block(3);
+    // 啊 (B0A1) sorts before 中 (D6D0) in GBK but after it in Unicode.
+    for key in ["a", "B", "c", "D", "啊", "中"] {
+        writer.add(key, "<p></p>").unwrap();
+    }
+    let mut mdict = Mdict::new(Cursor::new(writer.to_bytes().unwrap())).unwrap();
+    let report = mdict.verify();
+    assert!(report.is_ok(), "{:?}", report.issues);
+
+    // Case-sensitive, "B" sorts before "a" within the first block and "D"
+    // before "c" across the first two.
+    mdict.key_block_index.set_collation(KeyCollation {
+        case_sensitive: true,
+        strip_key: false,
+    });
+    let report = mdict.verify();
+    let sections: Vec<_> = report.issues.iter().map(|issue| issue.section).collect();
+    assert!(sections.contains(&IntegritySection::KeyInfo), "{:?}", report.issues);
+    assert!(
+        sections.contains(&IntegritySection::KeyBlock(0)),
+        "{:?}",
+        report.issues
+    );
+}
@@ -0,0 +1,107 @@
+use std::fs::File;
+use std::io::{Cursor, Read, Seek};
+
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::Mdict;
+
+const SAMPLE_PATH: &str = "resources/jitendex/jitendex.mdx";
+
+fn fixture(key_block_size: usize) -> Mdict<Cursor<Vec<u8>>> {
+    let mut writer = MdxWriter::new().key_block_size(key_block_size);
+    for i in 0..200 {
+        writer.add(format!("key{:03}", i), &format!("<p>{}</p>", i)).unwrap();
+    }
+    Mdict::new(Cursor::new(writer.to_bytes().unwrap())).unwrap()
+}
+
+fn key_at<R: Read + Seek>(mdict: &mut Mdict<R>, entry: usize) -> String {
+    let key_block = mdict.key_block_index.get(&mut mdict.reader, entry);
+    key_block.unwrap().unwrap().key_text
+}
+
+/// Every entry maps to a block and back, block by block in order, and can
+/// be read by its index.
+fn assert_round_trips<R: Read + Seek>(mdict: &mut Mdict<R>) {
+    let index = &mdict.key_block_index;
+    let total = index.key_section.num_entries as usize;
+    let mut expected = (0, 0);
+    for entry in 0..total {
+        let (block, offset) = index.block_and_offset_for_entry(entry).unwrap();
+        while index.key_section.key_info_blocks[expected.0].num_entries as usize == expected.1 {
+            expected = (expected.0 + 1, 0);
+        }
+        assert_eq!((block, offset), expected, "entry {}", entry);
+        assert_eq!(index.entry_for_block_and_offset(block, offset), Some(entry));
+        expected.1 += 1;
+    }
+    assert_eq!(index.block_and_offset_for_entry(total), None);
+
+    for entry in 0..total {
+        let key_block = mdict.key_block_index.get(&mut mdict.reader, entry).unwrap();
+        assert!(key_block.is_some(), "entry {}", entry);
+    }
+    assert!(mdict
+        .key_block_index
+        .get(&mut mdict.reader, total)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn entry_indexes_round_trip_through_blocks() {
+    for key_block_size in [32, 100, 1 << 16] {
+        let mut mdict = fixture(key_block_size);
+        assert_round_trips(&mut mdict);
+    }
+}
+
+#[test]
+fn block_offsets_follow_entry_counts_not_block_sizes() {
+    let mdict = fixture(64);
+    let section = &mdict.key_block_index.key_section;
+    assert!(section.key_info_blocks.len() > 2);
+
+    let second_block_start = section.key_info_blocks[0].num_entries as usize;
+    assert_eq!(
+        mdict.key_block_index.block_and_offset_for_entry(second_block_start),
+        Some((1, 0))
+    );
+    assert_eq!(
+        mdict.key_block_index.block_and_offset_for_entry(second_block_start - 1),
+        Some((0, second_block_start - 1))
+    );
+    assert_eq!(
+        mdict
+            .key_block_index
+            .entry_for_block_and_offset(section.key_info_blocks.len(), 0),
+        None
+    );
+}
+
+#[test]
+fn block_starts_hold_the_first_key_of_each_block() {
+    let mut mdict = fixture(64);
+    let blocks = mdict.key_block_index.key_section.key_info_blocks.clone();
+    for (block, info) in blocks.iter().enumerate() {
+        let first = mdict
+            .key_block_index
+            .entry_for_block_and_offset(block, 0)
+            .unwrap();
+        let last = mdict
+            .key_block_index
+            .entry_for_block_and_offset(block, info.num_entries as usize - 1)
+            .unwrap();
+        assert_eq!(key_at(&mut mdict, first), info.first);
+        assert_eq!(key_at(&mut mdict, last), info.last);
+    }
+}
+
+#[test]
+fn entry_indexes_round_trip_in_the_sample() {
+    let Ok(file) = File::open(SAMPLE_PATH) else {
+        eprintln!("Skipping: {} not found", SAMPLE_PATH);
+        return;
+    };
+    let mut mdict = Mdict::new(file).unwrap();
+    assert_round_trips(&mut mdict);
+}
@@ -0,0 +1,80 @@
+#![cfg(feature = "fs")]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use mdict_tools::format::key_index_cache::key_cache_path;
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::Mdict;
+
+fn write_dict(path: &Path, keys: &[&str]) {
+    let mut writer = MdxWriter::new().key_block_size(32);
+    for key in keys {
+        writer.add(*key, &format!("<p>{}</p>", key)).unwrap();
+    }
+    writer.write_to_path(path).unwrap();
+}
+
+fn first_keys(mdict: &Mdict<fs::File>) -> Vec<String> {
+    let section = &mdict.key_block_index.key_section;
+    section
+        .key_info_blocks
+        .iter()
+        .map(|block| block.first.clone())
+        .collect()
+}
+
+#[test]
+fn cache_paths_extend_the_extension() {
+    assert_eq!(key_cache_path("a/dict.mdx"), PathBuf::from("a/dict.mdxidx"));
+    assert_eq!(key_cache_path("dict.mdd"), PathBuf::from("dict.mddidx"));
+    assert_eq!(key_cache_path("dict"), PathBuf::from("dict.idx"));
+}
+
+#[test]
+fn unchanged_files_load_the_cached_section() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("dict.mdx");
+    write_dict(&path, &["apple", "banana", "cherry", "damson", "elder"]);
+    let cache = key_cache_path(&path);
+
+    let mut parsed = Mdict::open_cached(&path).unwrap();
+    assert!(cache.exists());
+    let mut cached = Mdict::open_cached(&path).unwrap();
+    assert_eq!(first_keys(&cached), first_keys(&parsed));
+    assert_eq!(
+        cached.key_block_index.key_section.num_entries_prefix_sum,
+        parsed.key_block_index.key_section.num_entries_prefix_sum
+    );
+    assert_eq!(cached.lookup("cherry").unwrap(), parsed.lookup("cherry").unwrap());
+
+    // Edit a summary key in place: the next open must take it from the cache.
+    let bytes = fs::read(&cache).unwrap();
+    let at = bytes.windows(5).position(|window| window == b"apple").unwrap();
+    let mut edited = bytes.clone();
+    edited[at..at + 5].copy_from_slice(b"apric");
+    fs::write(&cache, edited).unwrap();
+    let from_cache = Mdict::open_cached(&path).unwrap();
+    assert_eq!(first_keys(&from_cache)[0], "apric");
+}
+
+#[test]
+fn changed_or_damaged_caches_are_rebuilt() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("dict.mdx");
+    write_dict(&path, &["apple", "banana", "cherry"]);
+    Mdict::open_cached(&path).unwrap();
+    let cache = key_cache_path(&path);
+    let first_cache = fs::read(&cache).unwrap();
+
+    write_dict(&path, &["apple", "banana", "cherry", "date"]);
+    let mut reopened = Mdict::open_cached(&path).unwrap();
+    assert_eq!(reopened.key_block_index.key_section.num_entries, 4);
+    assert_eq!(reopened.lookup("date").unwrap(), [b"<p>date</p>".to_vec()]);
+    assert_ne!(fs::read(&cache).unwrap(), first_cache);
+
+    fs::write(&cache, b"not a cache").unwrap();
+    let mut repaired = Mdict::open_cached(&path).unwrap();
+    assert_eq!(repaired.lookup("apple").unwrap().len(), 1);
+    assert!(fs::read(&cache).unwrap().starts_with(b"MDXIDX"));
+}
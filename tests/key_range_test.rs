@@ -0,0 +1,104 @@
+#![cfg(all(feature = "mmap", feature = "zstd"))]
+
+use std::io::{Cursor, Read, Seek};
+use std::ops::Bound::{Excluded, Included, Unbounded};
+use std::ops::{Bound, RangeBounds};
+use std::path::Path;
+
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::{FSTMap, Mdict};
+
+mod common;
+
+const KEYS: [&str; 11] = [
+    "ant", "apple", "apply", "apricot", "banana", "band", "か", "かき", "が", "き", "きく",
+];
+
+fn writer() -> MdxWriter {
+    let mut writer = MdxWriter::new().key_block_size(64);
+    for key in KEYS {
+        writer.add(key, &format!("<p>{}</p>", key)).unwrap();
+    }
+    writer
+}
+
+fn keys<R: Read + Seek>(
+    mdict: &mut Mdict<R>,
+    bounds: impl RangeBounds<&'static str>,
+) -> Vec<String> {
+    mdict
+        .keys_in_range(bounds)
+        .unwrap()
+        .map(|key_block| key_block.unwrap().key_text)
+        .collect()
+}
+
+#[test]
+fn block_index_ranges_follow_bound_kinds() {
+    let mut mdict = Mdict::new(Cursor::new(writer().to_bytes().unwrap())).unwrap();
+
+    assert_eq!(keys(&mut mdict, "か"..="き"), ["か", "かき", "が", "き"]);
+    assert_eq!(keys(&mut mdict, "か".."き"), ["か", "かき", "が"]);
+    assert_eq!(keys(&mut mdict, "apple".."b"), ["apple", "apply", "apricot"]);
+    assert_eq!(keys(&mut mdict, ..="ant"), ["ant"]);
+    assert_eq!(keys(&mut mdict, "きか"..), ["きく"]);
+    assert_eq!(keys(&mut mdict, ..).len(), KEYS.len());
+
+    let excluded_start = (Excluded("apple"), Included("apricot"));
+    assert_eq!(keys(&mut mdict, excluded_start), ["apply", "apricot"]);
+}
+
+#[test]
+fn block_index_ranges_may_be_empty() {
+    let mut mdict = Mdict::new(Cursor::new(writer().to_bytes().unwrap())).unwrap();
+
+    assert!(keys(&mut mdict, "c".."d").is_empty());
+    assert!(keys(&mut mdict, "b".."a").is_empty());
+    assert!(keys(&mut mdict, "きけ"..).is_empty());
+    // Kana sort after Latin letters, so a range past "zz" still holds them.
+    assert_eq!(keys(&mut mdict, "zz"..), ["か", "かき", "が", "き", "きく"]);
+
+    let range = mdict.keys_in_range("apple".."apricot").unwrap();
+    assert_eq!(range.size_hint(), (0, Some(2)));
+}
+
+fn fst_map(dir: &Path) -> FSTMap {
+    let mut writer = common::writer_with_keys(&KEYS[..6]);
+    writer.add("band", "<p>a group of musicians</p>").unwrap();
+    common::optimized(&writer, dir);
+
+    let path = |name: &str| dir.join(name);
+    FSTMap::load_from_path(path("index.fst"), path("readings.dat"), path("records.dat")).unwrap()
+}
+
+#[test]
+fn fst_ranges_keep_duplicate_keys_together() {
+    let dir = tempfile::tempdir().unwrap();
+    let fst_map = fst_map(dir.path());
+    let keys = |bounds: (Bound<&str>, Bound<&str>)| {
+        fst_map
+            .keys_in_range(bounds)
+            .map(|(key, _)| key)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        keys((Included("banana"), Included("band"))),
+        ["banana", "band", "band"]
+    );
+    assert_eq!(keys((Excluded("banana"), Unbounded)), ["band", "band"]);
+    assert_eq!(
+        keys((Included("apple"), Excluded("band"))),
+        ["apple", "apply", "apricot", "banana"]
+    );
+    assert!(keys((Excluded("band"), Excluded("c"))).is_empty());
+
+    let offsets = fst_map
+        .keys_in_range("band"..="band")
+        .map(|(_, offset)| offset)
+        .collect::<Vec<_>>();
+    assert_eq!(offsets, fst_map.get_all("band"));
+    assert_eq!(offsets.len(), 2);
+    assert!(fst_map.get_all("ban").is_empty());
+    assert_eq!(fst_map.get_all("banana").len(), 1);
+}
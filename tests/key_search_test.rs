@@ -0,0 +1,106 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::Cursor;
+
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::Mdict;
+use proptest::prelude::*;
+use proptest::test_runner::TestRunner;
+
+const SAMPLE_PATH: &str = "resources/jitendex/jitendex.mdx";
+
+fn dictionary(keys: &[String], entries_per_key_block: usize) -> Mdict<Cursor<Vec<u8>>> {
+    let mut writer = MdxWriter::new().entries_per_key_block(entries_per_key_block);
+    for key in keys {
+        writer.add(key.clone(), "<p></p>").unwrap();
+    }
+    Mdict::new(Cursor::new(writer.to_bytes().unwrap())).unwrap()
+}
+
+/// `prefix_range_bounds` as a linear scan over sorted `keys`.
+fn linear_prefix_range(keys: &[String], prefix: &str) -> Option<(usize, usize)> {
+    let start = keys.iter().position(|key| key.as_str() >= prefix)?;
+    let len = keys[start..]
+        .iter()
+        .take_while(|key| key.starts_with(prefix))
+        .count();
+    Some((start, start + len))
+}
+
+fn check_against_linear_scan<R: std::io::Read + std::io::Seek>(
+    mdict: &mut Mdict<R>,
+    keys: &[String],
+    query: &str,
+) -> Result<(), TestCaseError> {
+    prop_assert_eq!(
+        mdict.prefix_range_bounds(query).unwrap(),
+        linear_prefix_range(keys, query),
+        "prefix {:?}",
+        query
+    );
+    prop_assert_eq!(
+        mdict
+            .key_block_index
+            .index_for(&mut mdict.reader, query)
+            .unwrap(),
+        keys.iter().position(|key| key == query),
+        "key {:?}",
+        query
+    );
+    Ok(())
+}
+
+fn sorted_keys() -> impl Strategy<Value = Vec<String>> {
+    prop::collection::vec("[ab]{1,3}|[あい]{1,2}|\u{10FFFF}{1,2}", 1..40).prop_map(|mut keys| {
+        keys.sort();
+        keys
+    })
+}
+
+proptest! {
+    #[test]
+    fn key_search_matches_linear_scan(
+        keys in sorted_keys(),
+        entries_per_key_block in 1usize..6,
+        queries in prop::collection::vec("[ab]{0,3}|[あい]{1,2}|\u{10FFFF}{1,3}|[c-z]", 1..10),
+    ) {
+        let mut mdict = dictionary(&keys, entries_per_key_block);
+        for (index, key) in keys.iter().enumerate() {
+            let key_block = mdict.get(index).unwrap().expect("entry in range");
+            prop_assert_eq!(&key_block.key_text, key);
+        }
+        prop_assert!(mdict.get(keys.len()).unwrap().is_none());
+
+        for query in queries.iter().chain(&keys) {
+            check_against_linear_scan(&mut mdict, &keys, query)?;
+        }
+    }
+}
+
+#[test]
+fn sample_key_search_matches_linear_scan() {
+    let Ok(file) = File::open(SAMPLE_PATH) else {
+        eprintln!("Skipping: {} not found", SAMPLE_PATH);
+        return;
+    };
+    let mut mdict = Mdict::new(file).unwrap();
+    let mut keys = Vec::new();
+    while let Some(key_block) = mdict.get(keys.len()).unwrap() {
+        keys.push(key_block.key_text);
+    }
+    let mdict = RefCell::new(mdict);
+
+    let mut runner = TestRunner::new(ProptestConfig::with_cases(64));
+    runner
+        .run(
+            &(any::<prop::sample::Index>(), 1usize..4),
+            |(key_pick, prefix_chars)| {
+                let key = key_pick.get(&keys);
+                let prefix = key.chars().take(prefix_chars).collect::<String>();
+                let mut mdict = mdict.borrow_mut();
+                check_against_linear_scan(&mut mdict, &keys, &prefix)?;
+                check_against_linear_scan(&mut mdict, &keys, key)
+            },
+        )
+        .unwrap();
+}
@@ -0,0 +1,70 @@
+use std::io::Cursor;
+
+use mdict_tools::synth::{SynthDict, SynthDictBuilder};
+use mdict_tools::{Mdict, OpenOptions};
+
+mod common;
+
+const LAZY: OpenOptions = OpenOptions {
+    verify_checksums: false,
+    cache_capacity: None,
+    key_cache_capacity: None,
+    lazy: true,
+};
+
+fn synth() -> SynthDict {
+    common::synth(SynthDictBuilder::entries(60).entries_per_record_block(8))
+}
+
+fn open_lazy(bytes: Vec<u8>) -> Mdict<Cursor<Vec<u8>>> {
+    Mdict::new_with_options(Cursor::new(bytes), LAZY).expect("open lazily")
+}
+
+#[test]
+fn record_index_is_parsed_on_first_record_read() {
+    let dict = synth();
+    let mut mdict = open_lazy(dict.bytes.clone());
+    assert!(!mdict.is_record_section_parsed());
+
+    let key_block = mdict.get_all(&dict.entries[20].0).unwrap().remove(0);
+    assert_eq!(key_block.key_text, dict.entries[20].0);
+    assert!(!mdict.is_record_section_parsed());
+
+    assert_eq!(mdict.record_at_key_block(&key_block).unwrap(), dict.entries[20].1);
+    assert!(mdict.is_record_section_parsed());
+
+    let eager = dict.open().unwrap().record_section().unwrap();
+    let lazy = mdict.record_section().unwrap();
+    assert_eq!(lazy.num_record_blocks, eager.num_record_blocks);
+    assert_eq!(lazy.record_data_offset, eager.record_data_offset);
+}
+
+#[test]
+fn lazy_handles_share_the_parsed_index() {
+    let dict = synth();
+    let mut mdict = open_lazy(dict.bytes.clone());
+    let mut handle = mdict.with_reader(Cursor::new(dict.bytes.clone()));
+
+    assert_eq!(handle.record_at_index(41).unwrap(), dict.entries[41].1);
+    assert!(mdict.is_record_section_parsed());
+    let entries = mdict.iter_entries().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(entries.len(), dict.entries.len());
+}
+
+#[test]
+fn a_damaged_record_index_fails_on_first_read() {
+    let dict = synth();
+    let offset = {
+        let mdict = dict.open().unwrap();
+        mdict.key_block_index.key_section.next_section_offset as usize
+    };
+    let mut bytes = dict.bytes.clone();
+    // Claim a record index far larger than the file.
+    bytes[offset + 16..offset + 24].copy_from_slice(&u64::MAX.to_be_bytes());
+
+    assert!(Mdict::new(Cursor::new(bytes.clone())).is_err());
+    let mut mdict = open_lazy(bytes);
+    assert_eq!(mdict.get(0).unwrap().unwrap().key_text, dict.entries[0].0);
+    assert!(mdict.record_at_index(0).is_err());
+    assert!(!mdict.verify().is_ok());
+}
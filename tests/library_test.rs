@@ -0,0 +1,111 @@
+#![cfg(feature = "mmap")]
+
+use std::fs;
+use std::path::Path;
+
+use mdict_tools::library::{open_library, Library};
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::types::OptimizedStatus;
+
+fn write_dict(path: &Path, title: &str, keys: &[&str]) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let mut writer = MdxWriter::new().title(title);
+    for key in keys {
+        writer.add(*key, &format!("<p>{}</p>", key)).unwrap();
+    }
+    writer.write_to_path(path).unwrap();
+}
+
+fn ids(library: &Library) -> Vec<String> {
+    library.entries().into_iter().map(|entry| entry.id).collect()
+}
+
+#[test]
+fn scans_find_dictionaries_and_their_resources() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    write_dict(&root.join("jp/jitendex.mdx"), "Jitendex", &["犬", "猫"]);
+    write_dict(&root.join("en/glossary.mdx"), "Glossary", &["apple", "banana", "cherry"]);
+    let mut mdd = MdxWriter::mdd();
+    mdd.add_raw("\\cat.png", vec![0x89, b'P', b'N', b'G']).unwrap();
+    mdd.write_to_path(root.join("jp/jitendex.mdd")).unwrap();
+    fs::write(root.join("en/notes.txt"), "not a dictionary").unwrap();
+
+    let library = open_library(root.to_string_lossy().into_owned()).unwrap();
+    assert!(library.entries().is_empty());
+    let entries = library.scan().unwrap();
+    assert_eq!(ids(&library), ["en/glossary.mdx", "jp/jitendex.mdx"]);
+
+    let glossary = &entries[0];
+    assert_eq!(glossary.title.as_deref(), Some("Glossary"));
+    assert_eq!(glossary.entry_count, 3);
+    assert!(glossary.enabled);
+    assert_eq!(glossary.mdd_path, None);
+    assert_eq!(glossary.optimized_status, OptimizedStatus::Missing);
+    assert!(Path::new(&glossary.mdx_path).exists());
+
+    let jitendex = &entries[1];
+    assert_eq!(jitendex.title.as_deref(), Some("Jitendex"));
+    assert!(jitendex.mdd_path.as_ref().unwrap().ends_with("jitendex.mdd"));
+}
+
+#[test]
+fn settings_survive_reopening_and_rescans() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    for name in ["a", "b", "c"] {
+        write_dict(&root.join(format!("{}.mdx", name)), name, &["key"]);
+    }
+    let open = || open_library(root.to_string_lossy().into_owned()).unwrap();
+
+    let library = open();
+    library.scan().unwrap();
+    library.set_enabled("b.mdx".to_string(), false).unwrap();
+    library.move_to("c.mdx".to_string(), 0).unwrap();
+    assert!(library.set_enabled("missing.mdx".to_string(), false).is_err());
+
+    write_dict(&root.join("d.mdx"), "d", &["key"]);
+    fs::remove_file(root.join("a.mdx")).unwrap();
+    let library = open();
+    assert_eq!(ids(&library), ["c.mdx", "a.mdx", "b.mdx"]);
+    library.scan().unwrap();
+    assert_eq!(ids(&library), ["c.mdx", "b.mdx", "d.mdx"]);
+    let enabled = library.enabled_entries();
+    assert_eq!(
+        enabled.iter().map(|entry| entry.id.as_str()).collect::<Vec<_>>(),
+        ["c.mdx", "d.mdx"]
+    );
+
+    library.move_to("c.mdx".to_string(), 99).unwrap();
+    assert_eq!(ids(&open()), ["b.mdx", "d.mdx", "c.mdx"]);
+}
+
+#[test]
+fn optimized_bundles_go_stale_when_the_mdx_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    let mdx = root.join("dict.mdx");
+    write_dict(&mdx, "Dict", &["apple"]);
+    let bundle = root.join("dict.bundle");
+    fs::write(&bundle, b"bundle").unwrap();
+
+    let registry = dir.path().join("registry.json");
+    let library = Library::open(root, &registry).unwrap();
+    library.scan().unwrap();
+    let bundle_path = bundle.to_string_lossy().into_owned();
+    library
+        .set_optimized_bundle("dict.mdx".to_string(), Some(bundle_path.clone()))
+        .unwrap();
+    assert!(registry.exists());
+    let entry = &library.entries()[0];
+    assert_eq!(entry.optimized_bundle_path, Some(bundle_path));
+    assert_eq!(entry.optimized_status, OptimizedStatus::Current);
+
+    write_dict(&mdx, "Dict", &["apple", "apricot"]);
+    let entry = &library.scan().unwrap()[0];
+    assert_eq!(entry.entry_count, 2);
+    assert_eq!(entry.optimized_status, OptimizedStatus::Stale);
+
+    library.set_optimized_bundle("dict.mdx".to_string(), None).unwrap();
+    assert_eq!(library.entries()[0].optimized_status, OptimizedStatus::Missing);
+}
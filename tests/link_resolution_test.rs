@@ -0,0 +1,105 @@
+#![cfg(all(feature = "mmap", feature = "zstd"))]
+
+use mdict_tools::error::MDictError;
+use mdict_tools::mdx_conversion::reindexing;
+use mdict_tools::synth::SynthDictBuilder;
+use mdict_tools::types::Encoding;
+
+mod common;
+
+#[test]
+fn record_resolved_follows_link_chains() {
+    // Every entry after the first links to its predecessor, so word000005
+    // resolves through five hops to word000000.
+    for encoding in [Encoding::Utf8, Encoding::Utf16LE] {
+        let dict = SynthDictBuilder::entries(8)
+            .encoding(encoding)
+            .link_every(1)
+            .build()
+            .expect("build synthetic dictionary");
+        let mut mdict = dict.open().expect("open synthetic dictionary");
+        let key_block = mdict.get(5).unwrap().unwrap();
+
+        let record = mdict.record_resolved(&key_block, 8).expect("resolve chain");
+        assert_eq!(record, dict.entries[0].1);
+
+        match mdict.record_resolved(&key_block, 2) {
+            Err(MDictError::InvalidFormat(_)) => {}
+            other => panic!("expected depth error, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn bundle_and_optimized_resolve_links() {
+    let dict = common::synth(SynthDictBuilder::entries(8).link_every(1));
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let mdx_path = dir.path().join("synth.mdx");
+    dict.write_to(&mdx_path).expect("write synthetic dictionary");
+
+    let bundle = common::open_bundle(&mdx_path);
+    let optimized = common::optimized_from_bundle(&bundle, dir.path());
+
+    bundle.set_search_prefix("word000005").unwrap();
+    let legacy_key = bundle.prefix_search_result_get(0).unwrap().unwrap();
+    assert_eq!(
+        bundle.record_resolved(legacy_key, 8).unwrap(),
+        dict.entries[0].1
+    );
+
+    let page = optimized.set_search_prefix_paged("word000005", 1).unwrap();
+    let optimized_key = page.results[0].clone();
+    assert_eq!(
+        optimized.record_resolved(optimized_key, 8).unwrap(),
+        dict.entries[0].1
+    );
+}
+
+#[test]
+fn resolved_links_are_cached() {
+    let dict = SynthDictBuilder::entries(8)
+        .link_every(1)
+        .build()
+        .expect("build synthetic dictionary");
+    let mut mdict = dict.open().expect("open synthetic dictionary");
+    let key_block = mdict.get(5).unwrap().unwrap();
+    let plain = mdict.get(0).unwrap().unwrap();
+
+    mdict.record_resolved(&plain, 8).unwrap();
+    assert_eq!(mdict.link_cache_len(), 0);
+
+    let first = mdict.record_resolved(&key_block, 8).unwrap();
+    assert_eq!(mdict.link_cache_len(), 1);
+    assert_eq!(mdict.record_resolved(&key_block, 8).unwrap(), first);
+    // A cached five-hop chain still honours a smaller depth limit.
+    assert!(mdict.record_resolved(&key_block, 2).is_err());
+
+    mdict.clear_link_cache();
+    assert_eq!(mdict.link_cache_len(), 0);
+}
+
+#[test]
+fn link_cache_prewarms_from_readings_list() {
+    let dict = SynthDictBuilder::entries(40)
+        .link_every(4)
+        .build()
+        .expect("build synthetic dictionary");
+    let mut mdict = dict.open().expect("open synthetic dictionary");
+    let readings = reindexing::build_readings_list(&mut mdict).unwrap();
+
+    let links = dict
+        .entries
+        .iter()
+        .filter(|(_, record)| record.starts_with(b"@@@LINK="))
+        .count();
+    assert!(links > 0);
+    assert_eq!(mdict.prewarm_link_cache(&readings).unwrap(), links);
+    assert_eq!(mdict.link_cache_len(), links);
+
+    for index in 0..40 {
+        let key_block = mdict.get(index).unwrap().unwrap();
+        let resolved = mdict.record_resolved(&key_block, 1).unwrap();
+        assert!(!resolved.starts_with(b"@@@LINK="), "index {}", index);
+    }
+    assert_eq!(mdict.link_cache_len(), links);
+}
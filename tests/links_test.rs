@@ -0,0 +1,130 @@
+use std::io::Cursor;
+
+use mdict_tools::links::{extract_entry_links, link_graph, LinkGraph, LinkType};
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::synth::SynthDictBuilder;
+use mdict_tools::Mdict;
+
+fn pets_graph() -> LinkGraph {
+    let cat = r#"<p>feline, see <a href="entry://dog">dog</a></p>"#;
+    let dog = r#"<a href="entry://cat#top">cat</a> <a href="ENTRY://bird">?</a>"#;
+    let mut writer = MdxWriter::new();
+    let entries = [
+        ("cat", cat),
+        ("dog", dog),
+        ("kitty", "@@@LINK=cat"),
+        ("loop", "@@@LINK=pool"),
+        ("neko", "@@@LINK=kitty"),
+        ("pool", "@@@LINK=loop"),
+        ("puppy", "@@@LINK=doggo"),
+    ];
+    for (key, html) in entries {
+        writer.add(key, html).unwrap();
+    }
+    let bytes = writer.to_bytes().unwrap();
+    let mut mdict = Mdict::new(Cursor::new(bytes)).unwrap();
+    link_graph(&mut mdict).unwrap()
+}
+
+fn node(graph: &LinkGraph, key_text: &str) -> usize {
+    graph
+        .nodes
+        .iter()
+        .position(|node| node.key_text == key_text)
+        .unwrap()
+}
+
+#[test]
+fn redirects_and_entry_links_are_resolved() {
+    let graph = pets_graph();
+    assert_eq!(graph.nodes.len(), 7);
+    assert_eq!(graph.edges.len(), 8);
+
+    let (cat, dog) = (node(&graph, "cat"), node(&graph, "dog"));
+    let from_dog = graph.links_from(dog).collect::<Vec<_>>();
+    assert_eq!(from_dog.len(), 2);
+    assert_eq!(from_dog[0].target, "cat");
+    assert_eq!(from_dog[0].to, Some(cat));
+    assert_eq!(from_dog[0].link_type, LinkType::Entry);
+    assert_eq!(from_dog[1].target, "bird");
+    assert_eq!(from_dog[1].to, None);
+
+    let kitty = node(&graph, "kitty");
+    let to_cat = graph
+        .links_to(cat)
+        .map(|edge| edge.from)
+        .collect::<Vec<_>>();
+    assert_eq!(to_cat, [dog, kitty]);
+
+    let mut broken = graph
+        .broken_links()
+        .map(|edge| edge.target.as_str())
+        .collect::<Vec<_>>();
+    broken.sort_unstable();
+    assert_eq!(broken, ["bird", "doggo"]);
+}
+
+#[test]
+fn redirect_chains_stop_at_loops_and_broken_links() {
+    let graph = pets_graph();
+    let cat = node(&graph, "cat");
+    let kitty = node(&graph, "kitty");
+    assert_eq!(graph.redirect_chain(node(&graph, "neko")), [kitty, cat]);
+    assert!(graph.redirect_chain(cat).is_empty());
+    assert!(graph.redirect_chain(node(&graph, "puppy")).is_empty());
+    let pool = node(&graph, "pool");
+    assert_eq!(graph.redirect_chain(node(&graph, "loop")), [pool]);
+}
+
+#[test]
+fn graphs_are_written_as_json_and_graphml() {
+    let graph = pets_graph();
+
+    let mut json = Vec::new();
+    graph.write_json(&mut json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(json["nodes"].as_array().unwrap().len(), 7);
+    let puppy = node(&graph, "puppy") as u64;
+    let broken = json["edges"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|edge| edge["from"] == puppy)
+        .unwrap();
+    assert_eq!(broken["target"], "doggo");
+    assert!(broken["to"].is_null());
+    assert_eq!(broken["link_type"], "redirect");
+
+    let mut graphml = Vec::new();
+    graph.write_graphml(&mut graphml).unwrap();
+    let graphml = String::from_utf8(graphml).unwrap();
+    assert_eq!(graphml.matches("<node ").count(), 9);
+    assert_eq!(graphml.matches("<edge ").count(), 8);
+    let edge = format!(
+        r#"<edge source="n{}" target="n{}">"#,
+        node(&graph, "kitty"),
+        node(&graph, "cat")
+    );
+    assert!(graphml.contains(&edge), "{}", graphml);
+    let missing = r#"<data key="key_text">doggo</data><data key="missing">true"#;
+    assert!(graphml.contains(missing), "{}", graphml);
+}
+
+#[test]
+fn entry_links_are_decoded_and_deduplicated() {
+    let html = r#"<a href="entry://%E7%8C%AB">a</a><a href='entry://猫?x=1'>b</a>
+        <a href="entry://#top">c</a><img src="cat.png"><a href=entry://two%20words>d</a>"#;
+    assert_eq!(extract_entry_links(html), ["猫", "two words"]);
+}
+
+#[test]
+fn synthetic_redirects_all_resolve() {
+    let dict = SynthDictBuilder::entries(40).link_every(4).build().unwrap();
+    let graph = link_graph(&mut dict.open().unwrap()).unwrap();
+    assert_eq!(graph.nodes.len(), 40);
+    assert_eq!(graph.edges.len(), 9);
+    for edge in &graph.edges {
+        assert_eq!(edge.link_type, LinkType::Redirect);
+        assert_eq!(edge.to, Some(edge.from - 1));
+    }
+}
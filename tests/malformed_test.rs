@@ -0,0 +1,132 @@
+//! Truncated and corrupted files must fail with `MDictError`, never panic.
+
+use std::io::Cursor;
+
+use mdict_tools::error::MDictError;
+#[cfg(feature = "zstd")]
+use mdict_tools::format::decode_format_block;
+use mdict_tools::mdx_writer::{BlockCompression, MdxWriter};
+use mdict_tools::types::MdictVersion;
+use mdict_tools::Mdict;
+
+fn sample(version: MdictVersion, compression: BlockCompression) -> Vec<u8> {
+    let mut writer = MdxWriter::new()
+        .version(version)
+        .compression(compression)
+        .entries_per_key_block(3)
+        .entries_per_record_block(4);
+    for i in 0..10 {
+        writer
+            .add(format!("word{:02}", i), &format!("<p>definition {}</p>", i))
+            .unwrap();
+    }
+    writer.to_bytes().unwrap()
+}
+
+/// Open `bytes` and touch every entry point that reads from the file.
+fn exercise(bytes: Vec<u8>) {
+    let Ok(mut mdict) = Mdict::new(Cursor::new(bytes)) else {
+        return;
+    };
+    for entry in mdict.iter_entries() {
+        if entry.is_err() {
+            break;
+        }
+    }
+    for i in 0..12 {
+        let _ = mdict.record_at_index(i);
+    }
+    if let Ok(mut prefix) = mdict.search_keys_prefix("word0") {
+        let _ = prefix.collect_to_vec();
+    }
+    let _ = mdict.search_keys_fuzzy("word", 1);
+    let _ = mdict.verify();
+}
+
+fn samples() -> Vec<Vec<u8>> {
+    let mut compressions = vec![BlockCompression::None, BlockCompression::Zlib];
+    if cfg!(feature = "lzo") {
+        compressions.push(BlockCompression::Lzo);
+    }
+    let mut out = Vec::new();
+    for version in [MdictVersion::V1, MdictVersion::V2] {
+        for &compression in &compressions {
+            out.push(sample(version, compression));
+        }
+    }
+    out
+}
+
+#[test]
+fn truncated_files_do_not_panic() {
+    for bytes in samples() {
+        for len in 0..bytes.len() {
+            exercise(bytes[..len].to_vec());
+        }
+    }
+}
+
+#[test]
+fn truncated_file_reports_an_error() {
+    let bytes = sample(MdictVersion::V2, BlockCompression::Zlib);
+    for len in [0, 3, 40, bytes.len() / 2, bytes.len() - 1] {
+        assert!(
+            Mdict::new(Cursor::new(bytes[..len].to_vec())).is_err(),
+            "length {}",
+            len
+        );
+    }
+}
+
+#[test]
+fn corrupted_bytes_do_not_panic() {
+    for bytes in samples() {
+        for at in 0..bytes.len() {
+            for mask in [0x80, 0xff] {
+                let mut corrupted = bytes.clone();
+                corrupted[at] ^= mask;
+                exercise(corrupted);
+            }
+        }
+    }
+}
+
+fn with_engine_version(version: &str) -> Vec<u8> {
+    let bytes = sample(MdictVersion::V2, BlockCompression::None);
+    let utf16 = |s: &str| -> Vec<u8> { s.encode_utf16().flat_map(u16::to_le_bytes).collect() };
+    let from = utf16("GeneratedByEngineVersion=\"2.0\"");
+    let to = utf16(&format!("GeneratedByEngineVersion=\"{}\"", version));
+    assert_eq!(from.len(), to.len());
+
+    let at = bytes
+        .windows(from.len())
+        .position(|window| window == from.as_slice())
+        .expect("engine version attribute");
+    let mut patched = bytes;
+    patched[at..at + to.len()].copy_from_slice(&to);
+    patched
+}
+
+#[test]
+fn unknown_engine_version_is_an_error() {
+    let err = Mdict::new(Cursor::new(with_engine_version("9.0")))
+        .err()
+        .expect("unknown version");
+    assert!(matches!(err, MDictError::InvalidFormat(_)), "{:?}", err);
+
+    let err = Mdict::new(Cursor::new(with_engine_version("3.0")))
+        .err()
+        .expect("V3 is not supported");
+    assert!(matches!(err, MDictError::UnsupportedFeature(_)), "{:?}", err);
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn unsized_block_with_huge_size_prefix_is_an_error() {
+    // zstd block claiming a 4 GiB output, with a garbage payload.
+    let mut block = vec![4, 0, 0, 0, 0, 0, 0, 0];
+    block.extend_from_slice(&u32::MAX.to_le_bytes());
+    block.extend_from_slice(&[0x28, 0xb5, 0x2f, 0xfd, 0xff, 0xff]);
+    let err = decode_format_block(&block).expect_err("garbage zstd payload");
+    assert!(matches!(err, MDictError::InvalidFormat(_)), "{:?}", err);
+}
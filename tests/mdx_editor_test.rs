@@ -0,0 +1,336 @@
+#![cfg(feature = "fs")]
+
+use std::fs::{self, File};
+use std::ops::Range;
+use std::path::Path;
+
+use mdict_tools::error::MDictError;
+use mdict_tools::mdx_editor::MdxEditor;
+#[cfg(feature = "lzo")]
+use mdict_tools::mdx_writer::BlockCompression;
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::synth::{SynthDict, SynthDictBuilder};
+use mdict_tools::types::{Encoding, MdictVersion};
+use mdict_tools::Mdict;
+
+mod common;
+
+/// 120 entries in key blocks of 16 and record blocks of 8.
+fn synth(version: MdictVersion, encoding: Encoding, encrypt_key_info: bool) -> SynthDict {
+    common::synth(
+        SynthDictBuilder::entries(120)
+            .version(version)
+            .encoding(encoding)
+            .entries_per_key_block(16)
+            .entries_per_record_block(8)
+            .encrypt_key_info(encrypt_key_info),
+    )
+}
+
+fn entries(path: &Path) -> Vec<(String, Vec<u8>)> {
+    let mut mdict = Mdict::<File>::open(path).unwrap();
+    mdict
+        .iter_entries()
+        .map(|entry| {
+            let (key_block, record) = entry.unwrap();
+            (key_block.key_text, record)
+        })
+        .collect()
+}
+
+/// Raw bytes of every key block and every record block.
+fn blocks(path: &Path) -> (Vec<Vec<u8>>, Vec<Vec<u8>>) {
+    let bytes = fs::read(path).unwrap();
+    let mut mdict = Mdict::<File>::open(path).unwrap();
+    let layout = mdict.section_layout().unwrap();
+    let records = mdict.record_section().unwrap();
+    let key_section = &mdict.key_block_index.key_section;
+    let slice = |range: Range<u64>| bytes[range.start as usize..range.end as usize].to_vec();
+    let key_blocks = (0..key_section.key_info_blocks.len())
+        .map(|block| slice(layout.key_block(key_section, block).unwrap()))
+        .collect();
+    let record_blocks = (0..records.num_record_blocks as usize)
+        .map(|block| slice(layout.record_block(&records, block).unwrap()))
+        .collect();
+    (key_blocks, record_blocks)
+}
+
+#[test]
+fn a_longer_record_moves_the_entries_after_it() {
+    for (version, encoding, encrypt_key_info) in [
+        (MdictVersion::V2, Encoding::Utf8, true),
+        (MdictVersion::V1, Encoding::Utf16LE, false),
+    ] {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dict.mdx");
+        let dict = synth(version, encoding, encrypt_key_info);
+        dict.write_to(&path).unwrap();
+        let (key_blocks, record_blocks) = blocks(&path);
+
+        let html = format!("<p>{}</p>", "a much longer definition ".repeat(40));
+        let mut editor = MdxEditor::open(&path).unwrap();
+        assert_eq!(editor.replace(&dict.entries[50].0, &html).unwrap(), 50);
+        assert_eq!(editor.pending(), 1);
+        editor.save().unwrap();
+        assert_eq!(editor.pending(), 0);
+        assert_eq!(
+            editor.mdict().record_at_index(50).unwrap(),
+            encoding.encode(&html)
+        );
+
+        let mut expected = dict.entries.clone();
+        expected[50].1 = encoding.encode(&html);
+        assert_eq!(entries(&path), expected, "{:?} {:?}", version, encoding);
+
+        // Entry 50 is in record block 6; entries from 51 on (key block 3)
+        // have new record offsets.
+        let (edited_key_blocks, edited_record_blocks) = blocks(&path);
+        assert_eq!(edited_key_blocks[..3], key_blocks[..3]);
+        assert_ne!(edited_key_blocks[3], key_blocks[3]);
+        for block in (0..record_blocks.len()).filter(|&block| block != 6) {
+            assert_eq!(
+                edited_record_blocks[block], record_blocks[block],
+                "block {}",
+                block
+            );
+        }
+        assert_ne!(edited_record_blocks[6], record_blocks[6]);
+    }
+}
+
+#[test]
+fn a_same_length_record_leaves_the_key_section_alone() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("dict.mdx");
+    let dict = synth(MdictVersion::V2, Encoding::Utf8, false);
+    dict.write_to(&path).unwrap();
+    let before = fs::read(&path).unwrap();
+    let layout = Mdict::<File>::open(&path)
+        .unwrap()
+        .section_layout()
+        .unwrap();
+
+    let reversed = dict.entries[10].1.iter().rev().copied().collect::<Vec<_>>();
+    let mut editor = MdxEditor::open(&path).unwrap();
+    editor.replace_at(10, reversed.clone()).unwrap();
+    editor.replace_at(11, b"first".to_vec()).unwrap();
+    // A later replacement of the same entry wins.
+    editor.replace_at(11, dict.entries[11].1.clone()).unwrap();
+    editor.save().unwrap();
+
+    let after = fs::read(&path).unwrap();
+    let key_section_end = layout.key_blocks.end as usize;
+    assert_eq!(after[..key_section_end], before[..key_section_end]);
+    let mut expected = dict.entries.clone();
+    expected[10].1 = reversed;
+    assert_eq!(entries(&path), expected);
+}
+
+#[cfg(feature = "lzo")]
+#[test]
+fn write_to_keeps_the_source_and_the_pending_edits() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("lzo.mdx");
+    let mut writer = MdxWriter::new()
+        .compression(BlockCompression::Lzo)
+        .entries_per_key_block(3)
+        .entries_per_record_block(2);
+    for i in 0..10 {
+        writer
+            .add(format!("entry{:02}", i), &format!("<b>entry {}</b>", i))
+            .unwrap();
+    }
+    writer.write_to_path(&path).unwrap();
+    let source = fs::read(&path).unwrap();
+
+    let mut editor = MdxEditor::open(&path).unwrap();
+    editor.replace("entry03", "<b>fixed</b>").unwrap();
+    editor.replace("entry08", "").unwrap();
+    let output = dir.path().join("edited.mdx");
+    editor.write_to(&output).unwrap();
+
+    assert_eq!(fs::read(&path).unwrap(), source);
+    assert_eq!(editor.pending(), 2);
+    let edited = entries(&output);
+    assert_eq!(edited.len(), 10);
+    assert_eq!(edited[3].1, b"<b>fixed</b>");
+    assert_eq!(edited[8].1, b"");
+    assert_eq!(edited[9].1, b"<b>entry 9</b>");
+    assert!(!dir.path().join("edited.mdx.partial").exists());
+}
+
+#[test]
+fn mdd_resources_are_replaced_as_bytes() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("resources.mdd");
+    let dict = synth(MdictVersion::MDD, Encoding::Utf16LE, false);
+    dict.write_to(&path).unwrap();
+
+    let mut editor = MdxEditor::open(&path).unwrap();
+    let key = dict.entries[7].0.clone();
+    assert!(matches!(
+        editor.replace(&key, "<p>text</p>"),
+        Err(MDictError::InvalidArgument(_))
+    ));
+    let payload = (0..=255u8).cycle().take(5000).collect::<Vec<_>>();
+    assert_eq!(editor.replace_raw(&key, payload.clone()).unwrap(), 7);
+    editor.save().unwrap();
+
+    let mut expected = dict.entries.clone();
+    expected[7].1 = payload;
+    assert_eq!(entries(&path), expected);
+}
+
+#[test]
+fn added_entries_take_their_place_in_key_order() {
+    for (version, encoding, encrypt_key_info) in [
+        (MdictVersion::V2, Encoding::Utf8, true),
+        (MdictVersion::V1, Encoding::Utf16LE, false),
+    ] {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dict.mdx");
+        let dict = synth(version, encoding, encrypt_key_info);
+        dict.write_to(&path).unwrap();
+        let (key_blocks, record_blocks) = blocks(&path);
+
+        let longer = format!("<p>{}</p>", "a much longer definition ".repeat(40));
+        let mut editor = MdxEditor::open(&path).unwrap();
+        editor.add("zzz note", "<p>last</p>").unwrap();
+        editor.add("word000055x", "<p>between 55 and 56</p>").unwrap();
+        editor.add("WORD000010b", "<p>between 10 and 11</p>").unwrap();
+        editor.add("word000020", "<p>second word000020</p>").unwrap();
+        editor.replace(&dict.entries[10].0, &longer).unwrap();
+        assert_eq!(editor.pending(), 5);
+        editor.save().unwrap();
+        assert_eq!(editor.pending(), 0);
+
+        let note = |key: &str, html: &str| (key.to_string(), encoding.encode(html));
+        let mut expected = dict.entries.clone();
+        expected[10].1 = encoding.encode(&longer);
+        // From the back, so the indexes before each insertion stay put.
+        expected.push(note("zzz note", "<p>last</p>"));
+        expected.insert(56, note("word000055x", "<p>between 55 and 56</p>"));
+        expected.insert(21, note("word000020", "<p>second word000020</p>"));
+        expected.insert(11, note("WORD000010b", "<p>between 10 and 11</p>"));
+        assert_eq!(entries(&path), expected, "{:?} {:?}", version, encoding);
+
+        // Entries before 11, 21 and 56 go into record blocks 1, 2 and 7;
+        // the entry after the last key gets a new block.
+        let (edited_key_blocks, edited_record_blocks) = blocks(&path);
+        assert_eq!(edited_key_blocks.len(), key_blocks.len() + 1);
+        assert_eq!(edited_record_blocks.len(), record_blocks.len() + 1);
+        for block in [0, 3, 4, 5, 6, 8, 9, 10, 11, 12, 13, 14] {
+            assert_eq!(
+                edited_record_blocks[block], record_blocks[block],
+                "block {}",
+                block
+            );
+        }
+        for block in [1, 2, 7] {
+            assert_ne!(edited_record_blocks[block], record_blocks[block]);
+        }
+        let mut mdict = Mdict::<File>::open(&path).unwrap();
+        assert_eq!(mdict.get_all("word000055X").unwrap().len(), 1);
+    }
+}
+
+#[test]
+fn added_entries_are_ordered_by_encoded_bytes_in_legacy_code_pages() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("gbk.mdx");
+    let mut writer = MdxWriter::new().encoding(Encoding::Gbk);
+    writer.add("a", "<p>a</p>").unwrap();
+    writer.add("z", "<p>z</p>").unwrap();
+    writer.write_to_path(&path).unwrap();
+
+    // 啊 (B0A1) sorts before 中 (D6D0) in GBK but after it in Unicode.
+    let mut editor = MdxEditor::open(&path).unwrap();
+    editor.add("中", "<p>zhong</p>").unwrap();
+    editor.add("啊", "<p>a</p>").unwrap();
+    editor.save().unwrap();
+
+    let keys: Vec<String> = entries(&path).into_iter().map(|(key, _)| key).collect();
+    assert_eq!(keys, ["a", "z", "啊", "中"]);
+    let mut mdict = Mdict::<File>::open(&path).unwrap();
+    assert_eq!(mdict.get_all("啊").unwrap().len(), 1);
+    assert_eq!(mdict.get_all("中").unwrap().len(), 1);
+}
+
+#[test]
+fn entries_after_the_last_key_only_add_blocks() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.mdx");
+    let dict = synth(MdictVersion::V2, Encoding::Utf8, false);
+    dict.write_to(&path).unwrap();
+    let (key_blocks, record_blocks) = blocks(&path);
+
+    let mut editor = MdxEditor::open(&path).unwrap();
+    editor.add("zz note 2", "<p>two</p>").unwrap();
+    editor.add("zz note 1", "<p>one</p>").unwrap();
+    editor.save().unwrap();
+    editor.add("zz note 3", "<p>three</p>").unwrap();
+    editor.save().unwrap();
+
+    let mut expected = dict.entries.clone();
+    for (key, html) in [
+        ("zz note 1", "<p>one</p>"),
+        ("zz note 2", "<p>two</p>"),
+        ("zz note 3", "<p>three</p>"),
+    ] {
+        expected.push((key.to_string(), html.as_bytes().to_vec()));
+    }
+    assert_eq!(entries(&path), expected);
+
+    // Each save adds one key block and one record block and leaves the
+    // blocks already there as they were.
+    let (grown_key_blocks, grown_record_blocks) = blocks(&path);
+    assert_eq!(grown_key_blocks[..key_blocks.len()], key_blocks[..]);
+    assert_eq!(grown_key_blocks.len(), key_blocks.len() + 2);
+    assert_eq!(grown_record_blocks[..record_blocks.len()], record_blocks[..]);
+    assert_eq!(grown_record_blocks.len(), record_blocks.len() + 2);
+}
+
+#[test]
+fn mdd_resources_are_added_as_bytes() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("resources.mdd");
+    let dict = synth(MdictVersion::MDD, Encoding::Utf16LE, false);
+    dict.write_to(&path).unwrap();
+
+    let mut editor = MdxEditor::open(&path).unwrap();
+    assert!(matches!(
+        editor.add("\\new.css", "p {}"),
+        Err(MDictError::InvalidArgument(_))
+    ));
+    let payload = (0..=255u8).rev().cycle().take(3000).collect::<Vec<_>>();
+    editor.add_raw("\\res000003.png", payload.clone()).unwrap();
+    editor.save().unwrap();
+
+    let mut expected = dict.entries.clone();
+    expected.insert(4, ("\\res000003.png".to_string(), payload));
+    assert_eq!(entries(&path), expected);
+}
+
+#[test]
+fn unknown_entries_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("dict.mdx");
+    synth(MdictVersion::V2, Encoding::Utf8, false)
+        .write_to(&path)
+        .unwrap();
+
+    let mut editor = MdxEditor::open(&path).unwrap();
+    assert!(matches!(
+        editor.replace("no such key", "<p/>"),
+        Err(MDictError::KeyNotFound(_))
+    ));
+    assert!(matches!(
+        editor.replace_at(120, Vec::new()),
+        Err(MDictError::InvalidArgument(_))
+    ));
+    assert!(matches!(
+        editor.add("", "<p/>"),
+        Err(MDictError::InvalidArgument(_))
+    ));
+    assert_eq!(editor.pending(), 0);
+}
@@ -0,0 +1,108 @@
+use mdict_tools::error::MDictError;
+use mdict_tools::mdx_writer::{BlockCompression, MdxWriter};
+#[cfg(feature = "lzo")]
+use mdict_tools::types::RecordKind;
+use mdict_tools::types::{Encoding, MdictVersion};
+use mdict_tools::Mdict;
+
+fn glossary() -> Vec<(String, String)> {
+    let mut entries = (0..300)
+        .map(|i| {
+            let key = format!("語彙{:04}", i);
+            let html = format!("<b>{}</b> — definition {} {}", key, i, "x".repeat(i % 50));
+            (key, html)
+        })
+        .collect::<Vec<_>>();
+    entries.sort();
+    entries
+}
+
+#[test]
+fn written_mdx_round_trips() {
+    let entries = glossary();
+    let mut compressions = vec![BlockCompression::None, BlockCompression::Zlib];
+    if cfg!(feature = "lzo") {
+        compressions.push(BlockCompression::Lzo);
+    }
+    for version in [MdictVersion::V1, MdictVersion::V2] {
+        for encoding in [Encoding::Utf8, Encoding::Utf16LE] {
+            for &compression in &compressions {
+                let mut writer = MdxWriter::new()
+                    .version(version)
+                    .encoding(encoding)
+                    .compression(compression)
+                    .title("Glossary & \"Terms\"")
+                    .key_block_size(512)
+                    .record_block_size(2048);
+                for (key, html) in &entries {
+                    writer.add(key.clone(), html).unwrap();
+                }
+
+                let bytes = writer.to_bytes().expect("write mdx");
+                let mut mdict = Mdict::new(std::io::Cursor::new(bytes)).expect("open written mdx");
+                let header = &mdict.key_block_index.header;
+                assert_eq!(header.get("Title").unwrap(), "Glossary & \"Terms\"");
+                assert_eq!(header.get_encoding(), encoding);
+                assert!(mdict.key_block_index.key_section.num_blocks > 1);
+                assert!(mdict.record_section().unwrap().num_record_blocks > 1);
+
+                for (i, (key, html)) in entries.iter().enumerate() {
+                    let key_block = mdict.get(i).unwrap().unwrap();
+                    assert_eq!(&key_block.key_text, key);
+                    let text = mdict.record_text_at_key_block(&key_block).unwrap();
+                    assert_eq!(
+                        &text, html,
+                        "{:?} {:?} {:?}",
+                        version, encoding, compression
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "lzo")]
+#[test]
+fn written_mdd_round_trips() {
+    let mut writer = MdxWriter::mdd().compression(BlockCompression::Lzo);
+    let resources = [
+        ("\\a.png", vec![0x89, b'P', b'N', b'G', 0x0A, 0x00]),
+        ("\\b.css", b"p{}".to_vec()),
+    ];
+    for (key, data) in &resources {
+        writer.add_raw(*key, data.clone()).unwrap();
+    }
+
+    let mut mdict = Mdict::new(std::io::Cursor::new(writer.to_bytes().unwrap())).unwrap();
+    assert_eq!(mdict.record_kind(), RecordKind::Binary);
+    for (i, (_, data)) in resources.iter().enumerate() {
+        assert_eq!(&mdict.record_at_index(i).unwrap(), data);
+        let key_block = mdict.get(i).unwrap().unwrap();
+        let record = mdict.record_ref_at_key_block(&key_block).unwrap();
+        assert_eq!(&*record, data.as_slice());
+        assert_eq!(
+            mdict.record_size_for(&key_block).unwrap(),
+            data.len() as u64
+        );
+        assert!(matches!(
+            mdict.record_text_at_key_block(&key_block),
+            Err(MDictError::InvalidArgument(_))
+        ));
+    }
+    let iterated = mdict
+        .iter_entries()
+        .map(|entry| entry.map(|(_, record)| record))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(iterated, resources.map(|(_, data)| data));
+}
+
+#[test]
+fn unsorted_keys_are_rejected() {
+    let mut writer = MdxWriter::new();
+    writer.add("b", "second").unwrap();
+    match writer.add("a", "first") {
+        Err(MDictError::InvalidArgument(_)) => {}
+        other => panic!("expected InvalidArgument, got {:?}", other),
+    }
+}
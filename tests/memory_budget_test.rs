@@ -0,0 +1,102 @@
+#![cfg(all(feature = "mmap", feature = "zstd"))]
+
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+use mdict_tools::config::{init_config, Config};
+use mdict_tools::mdx_conversion::reindexing::{
+    build_readings_list, build_readings_list_with_budget,
+};
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::Mdict;
+use tempfile::TempDir;
+
+mod common;
+
+const BUILD_MEMORY_BUDGET: u64 = 4096;
+
+/// Spill directory shared by every test in this binary, since the config can
+/// only be installed once. Tests hold the guard so each can check that it
+/// left the directory empty.
+fn spill_dir() -> (MutexGuard<'static, ()>, &'static Path) {
+    static SERIAL: Mutex<()> = Mutex::new(());
+    static SPILL_DIR: OnceLock<TempDir> = OnceLock::new();
+    let guard = SERIAL.lock().unwrap();
+    let dir = SPILL_DIR
+        .get_or_init(|| {
+            let dir = tempfile::tempdir().unwrap();
+            init_config(Config {
+                build_memory_budget: BUILD_MEMORY_BUDGET,
+                temp_dir: Some(dir.path().to_string_lossy().to_string()),
+                ..Config::default()
+            })
+            .unwrap();
+            dir
+        })
+        .path();
+    (guard, dir)
+}
+
+/// Enough keys for several merge rounds at a tiny budget, with links,
+/// duplicate keys and `reading【kanji】` keys.
+fn write_dictionary(path: &Path) {
+    let mut writer = MdxWriter::new();
+    for i in (0..200).step_by(3) {
+        writer
+            .add(format!("alias{:03}", i), &format!("@@@LINK=word{:03}", i))
+            .unwrap();
+    }
+    for i in 0..200 {
+        let key = format!("word{:03}", i);
+        writer.add(key.clone(), &format!("<p>{}</p>", key)).unwrap();
+    }
+    writer.add("たべる【食べる】", "<p>eat</p>").unwrap();
+    writer.add("のむ", "<p>drink</p>").unwrap();
+    writer.add("のむ", "<p>drink, again</p>").unwrap();
+    writer.add("食べる", "@@@LINK=たべる【食べる】").unwrap();
+    writer.add("飲む", "@@@LINK=のむ").unwrap();
+    writer.write_to_path(path).unwrap();
+}
+
+#[test]
+fn budgeted_readings_list_matches_in_memory_build() {
+    let (_serial, spill_dir) = spill_dir();
+    let dir = tempfile::tempdir().unwrap();
+    let mdx_path = dir.path().join("budget.mdx");
+    write_dictionary(&mdx_path);
+
+    let mut mdict = Mdict::new(std::fs::File::open(&mdx_path).unwrap()).unwrap();
+    let expected = build_readings_list(&mut mdict).unwrap();
+    for budget in [1, 256, 1 << 20] {
+        assert_eq!(
+            build_readings_list_with_budget(&mut mdict, budget).unwrap(),
+            expected,
+            "budget {}",
+            budget
+        );
+    }
+    assert_eq!(std::fs::read_dir(spill_dir).unwrap().count(), 0);
+}
+
+#[test]
+fn optimized_build_under_budget_cleans_up_spill_files() {
+    let (_serial, spill_dir) = spill_dir();
+    let dir = tempfile::tempdir().unwrap();
+    let mdx_path = dir.path().join("budget.mdx");
+    write_dictionary(&mdx_path);
+    let bundle = common::open_bundle(&mdx_path);
+    let optimized = common::optimized_from_bundle(&bundle, dir.path());
+
+    let page = optimized.set_search_prefix_paged("飲む", 10).unwrap();
+    assert_eq!(page.results.len(), 1);
+    assert_eq!(
+        optimized.record_at(page.results[0].clone()).unwrap(),
+        b"<p>drink, again</p>"
+    );
+    let page = optimized.set_search_prefix_paged("alias150", 10).unwrap();
+    assert_eq!(
+        optimized.record_at(page.results[0].clone()).unwrap(),
+        b"<p>word150</p>"
+    );
+    assert_eq!(std::fs::read_dir(spill_dir).unwrap().count(), 0);
+}
@@ -0,0 +1,45 @@
+use std::io::Cursor;
+
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::types::{Encoding, MdictVersion};
+use mdict_tools::Mdict;
+
+#[test]
+fn mdx_header_is_exposed_as_typed_metadata() {
+    let mut writer = MdxWriter::new()
+        .version(MdictVersion::V2)
+        .encoding(Encoding::Utf8)
+        .title("Glossary")
+        .description("<i>Terms</i> & definitions");
+    writer.add("term", "<b>term</b>").unwrap();
+    let mdict = Mdict::new(Cursor::new(writer.to_bytes().unwrap())).unwrap();
+
+    let metadata = mdict.metadata();
+    assert_eq!(metadata.version, MdictVersion::V2);
+    assert_eq!(metadata.engine_version.as_deref(), Some("2.0"));
+    assert_eq!(metadata.title.as_deref(), Some("Glossary"));
+    assert_eq!(
+        metadata.description.as_deref(),
+        Some("<i>Terms</i> & definitions")
+    );
+    assert_eq!(metadata.encoding, Encoding::Utf8);
+    assert_eq!(metadata.encrypted, 0);
+    assert_eq!(metadata.format.as_deref(), Some("Html"));
+    assert!(!metadata.compact);
+    assert!(!metadata.key_case_sensitive);
+    assert_eq!(metadata.stylesheet, None);
+    assert_eq!(metadata.creation_date, None);
+}
+
+#[test]
+fn mdd_metadata_has_no_engine_version() {
+    let mut writer = MdxWriter::mdd().title("Resources");
+    writer.add_raw("\\a.css", b"p{}".to_vec()).unwrap();
+    let mdict = Mdict::new(Cursor::new(writer.to_bytes().unwrap())).unwrap();
+
+    let metadata = mdict.metadata();
+    assert_eq!(metadata.version, MdictVersion::MDD);
+    assert_eq!(metadata.engine_version, None);
+    assert_eq!(metadata.title.as_deref(), Some("Resources"));
+    assert_eq!(metadata.format, None);
+}
@@ -0,0 +1,104 @@
+use std::io::Cursor;
+
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::metrics::{decode_metrics, reset_decode_metrics};
+use mdict_tools::Mdict;
+
+#[test]
+fn decodes_and_cache_hits_are_counted_with_the_tracing_feature() {
+    let mut writer = MdxWriter::new();
+    for key in ["apple", "banana", "cherry"] {
+        writer.add(key, &format!("<p>{}</p>", key)).unwrap();
+    }
+    let mut mdict = Mdict::new(Cursor::new(writer.to_bytes().unwrap())).unwrap();
+
+    reset_decode_metrics();
+    for _ in 0..2 {
+        mdict.record_at_index(0).unwrap();
+    }
+    let metrics = decode_metrics();
+
+    if cfg!(feature = "tracing") {
+        assert!(metrics.key_block_decodes >= 1, "{:?}", metrics);
+        assert!(metrics.record_block_decodes >= 1, "{:?}", metrics);
+        assert!(metrics.bytes_decompressed > 0, "{:?}", metrics);
+        assert!(metrics.cache_hits >= 1, "{:?}", metrics);
+        assert!(metrics.cache_hit_ratio() > 0.0);
+    } else {
+        assert_eq!(metrics, Default::default());
+    }
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn decodes_are_traced_inside_the_spans_of_their_reads() {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// Span names by id, the entered spans, and the fields of each event
+    /// with the span it happened in.
+    #[derive(Default)]
+    struct Recorded {
+        spans: Vec<&'static str>,
+        entered: Vec<usize>,
+        events: Vec<(Option<&'static str>, Vec<&'static str>)>,
+    }
+
+    struct Recorder(Arc<Mutex<Recorded>>);
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut recorded = self.0.lock().unwrap();
+            recorded.spans.push(span.metadata().name());
+            Id::from_u64(recorded.spans.len() as u64)
+        }
+
+        fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut recorded = self.0.lock().unwrap();
+            let span = recorded.entered.last().map(|&id| recorded.spans[id - 1]);
+            let fields = event.fields().map(|field| field.name()).collect();
+            recorded.events.push((span, fields));
+        }
+
+        fn enter(&self, span: &Id) {
+            self.0.lock().unwrap().entered.push(span.into_u64() as usize);
+        }
+
+        fn exit(&self, _span: &Id) {
+            self.0.lock().unwrap().entered.pop();
+        }
+    }
+
+    let mut writer = MdxWriter::new();
+    writer.add("apple", "<p>apple</p>").unwrap();
+    let mut mdict = Mdict::new(Cursor::new(writer.to_bytes().unwrap())).unwrap();
+
+    let recorded = Arc::new(Mutex::new(Recorded::default()));
+    tracing::subscriber::with_default(Recorder(Arc::clone(&recorded)), || {
+        mdict.record_at_index(0).unwrap();
+    });
+
+    let recorded = recorded.lock().unwrap();
+    assert!(recorded.spans.contains(&"record_ref_at_index"), "{:?}", recorded.spans);
+    for span in ["load_block", "read_record_block"] {
+        assert!(
+            recorded
+                .events
+                .iter()
+                .any(|(name, fields)| *name == Some(span) && fields.contains(&"bytes")),
+            "no decode event in {}: {:?}",
+            span,
+            recorded.events
+        );
+    }
+}
@@ -0,0 +1,45 @@
+use mdict_tools::mime::{mime_type_for, mime_type_for_path, sniff_mime_type};
+
+#[test]
+fn magic_bytes_identify_common_resource_formats() {
+    let cases: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR", "image/png"),
+        (b"\xFF\xD8\xFF\xE0\0\x10JFIF", "image/jpeg"),
+        (b"GIF89a\x01\0", "image/gif"),
+        (b"RIFF\x24\0\0\0WEBPVP8 ", "image/webp"),
+        (b"wOFF\0\x01\0\0", "font/woff"),
+        (b"wOF2\0\x01\0\0", "font/woff2"),
+        (b"OggS\0\x02\0\0Speex   ", "audio/ogg"),
+        (b"ID3\x04\0\0", "audio/mpeg"),
+        (b"\xFF\xFB\x90\x64", "audio/mpeg"),
+        (b"RIFF\x24\0\0\0WAVEfmt ", "audio/wav"),
+        (
+            b"<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\"/>",
+            "image/svg+xml",
+        ),
+        (b"\xEF\xBB\xBF  <svg/>", "image/svg+xml"),
+    ];
+    for (data, expected) in cases {
+        assert_eq!(sniff_mime_type(data), Some(*expected), "{:?}", data);
+    }
+    assert_eq!(sniff_mime_type(b"p { color: red }"), None);
+    assert_eq!(sniff_mime_type(b""), None);
+}
+
+#[test]
+fn extensions_fill_in_for_text_formats() {
+    assert_eq!(mime_type_for_path("\\css\\main.CSS"), "text/css");
+    assert_eq!(mime_type_for_path("\\js\\app.js"), "text/javascript");
+    assert_eq!(mime_type_for_path("\\sound\\a.spx"), "audio/ogg");
+    assert_eq!(
+        mime_type_for_path("\\img.d\\noext"),
+        "application/octet-stream"
+    );
+
+    assert_eq!(mime_type_for("\\main.css", b"p { color: red }"), "text/css");
+    // Magic bytes win over a misleading extension.
+    assert_eq!(
+        mime_type_for("\\img\\photo.png", b"\xFF\xD8\xFF\xE1"),
+        "image/jpeg"
+    );
+}
@@ -0,0 +1,109 @@
+#![cfg(all(feature = "mmap", feature = "zstd"))]
+
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Arc;
+
+use mdict_tools::mdict_optimized::{
+    create_mdict_optimized_from_bundles, create_mdict_optimized_from_fst,
+};
+use mdict_tools::mdx_conversion::fst_indexing::create_fst_index_multi;
+use mdict_tools::mdx_conversion::reindexing::build_readings_list;
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::Mdict;
+
+mod common;
+
+fn english() -> MdxWriter {
+    common::writer_with_entries(&[("apple", "<p>en apple</p>"), ("sun", "<p>en sun</p>")])
+}
+
+fn french() -> MdxWriter {
+    common::writer_with_entries(&[("pomme", "<p>fr apple</p>"), ("sun", "<p>fr sun</p>")])
+}
+
+fn path(dir: &Path, name: &str) -> String {
+    dir.join(name).to_string_lossy().to_string()
+}
+
+#[test]
+fn combined_index_spans_every_source() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut mdicts = [english(), french()]
+        .map(|writer| Mdict::new(Cursor::new(writer.to_bytes().unwrap())).unwrap());
+    let readings_lists = mdicts
+        .iter_mut()
+        .map(|mdict| build_readings_list(mdict).unwrap())
+        .collect::<Vec<_>>();
+    create_fst_index_multi(
+        &mut mdicts,
+        &readings_lists,
+        path(dir.path(), "index.fst"),
+        path(dir.path(), "readings.dat"),
+        path(dir.path(), "records.dat"),
+    )
+    .unwrap();
+
+    let optimized = create_mdict_optimized_from_fst(
+        path(dir.path(), "index.fst"),
+        path(dir.path(), "readings.dat"),
+        path(dir.path(), "records.dat"),
+    )
+    .unwrap();
+
+    let apple = optimized.lookup("apple");
+    assert_eq!(apple.len(), 1);
+    assert_eq!(optimized.source_id(apple[0].clone()).unwrap(), Some(0));
+    let pomme = optimized.lookup("pomme");
+    assert_eq!(optimized.source_id(pomme[0].clone()).unwrap(), Some(1));
+    assert_eq!(optimized.record_at(pomme[0].clone()).unwrap(), b"<p>fr apple</p>");
+
+    let mut suns = optimized
+        .lookup("sun")
+        .into_iter()
+        .map(|key| {
+            (
+                optimized.source_id(key.clone()).unwrap(),
+                optimized.record_at(key).unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    suns.sort();
+    assert_eq!(
+        suns,
+        [
+            (Some(0), b"<p>en sun</p>".to_vec()),
+            (Some(1), b"<p>fr sun</p>".to_vec())
+        ]
+    );
+}
+
+#[test]
+fn combined_index_from_bundles() {
+    let dir = tempfile::tempdir().unwrap();
+    let bundles = [("en.mdx", english()), ("fr.mdx", french())].map(|(name, writer)| {
+        let mdx_path = dir.path().join(name);
+        writer.write_to_path(&mdx_path).unwrap();
+        Arc::new(common::open_bundle(&mdx_path))
+    });
+
+    let optimized = create_mdict_optimized_from_bundles(
+        bundles.to_vec(),
+        path(dir.path(), "all.fst"),
+        path(dir.path(), "all.readings"),
+        path(dir.path(), "all.records"),
+    )
+    .unwrap();
+    let key = &optimized.lookup("pomme")[0];
+    assert_eq!(optimized.source_id(key.clone()).unwrap(), Some(1));
+    assert!(optimized.entry_id(key.clone()).is_ok());
+    assert_eq!(optimized.lookup("sun").len(), 2);
+}
+
+#[test]
+fn single_indexes_have_no_source() {
+    let dir = tempfile::tempdir().unwrap();
+    let optimized = common::optimized(&english(), dir.path());
+    let key = &optimized.lookup("apple")[0];
+    assert_eq!(optimized.source_id(key.clone()).unwrap(), None);
+}
@@ -0,0 +1,88 @@
+#![cfg(all(feature = "mmap", feature = "zstd"))]
+
+use std::io::Cursor;
+use std::sync::Arc;
+
+use mdict_tools::mdx_conversion::normalize::{
+    ArabicDiacriticStrip, FnNormalizer, JapaneseBracketSplit, KeyNormalizer, KeyNormalizerRule,
+    NormalizerPipeline, PinyinToneStrip,
+};
+use mdict_tools::mdx_conversion::reindexing::{
+    build_readings_list, build_readings_list_normalized,
+};
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::Mdict;
+
+mod common;
+
+#[test]
+fn built_in_rules_produce_readings() {
+    assert_eq!(
+        JapaneseBracketSplit.readings("かく【書く】"),
+        vec!["かく", "書く"]
+    );
+    assert_eq!(JapaneseBracketSplit.readings("ねこ【ねこ】"), vec!["ねこ"]);
+    assert_eq!(JapaneseBracketSplit.readings("plain"), vec!["plain"]);
+
+    assert_eq!(PinyinToneStrip.readings("nǐ hǎo"), vec!["nǐ hǎo", "ni hao"]);
+    assert_eq!(PinyinToneStrip.readings("lü4"), vec!["lü4", "lü"]);
+    assert_eq!(PinyinToneStrip.readings("3D"), vec!["3D"]);
+
+    assert_eq!(ArabicDiacriticStrip.readings("كَتَبَ"), vec!["كَتَبَ", "كتب"]);
+    assert_eq!(ArabicDiacriticStrip.readings("كتب"), vec!["كتب"]);
+}
+
+#[test]
+fn pipeline_applies_rules_in_order() {
+    let pipeline = NormalizerPipeline::new(vec![
+        KeyNormalizerRule::JapaneseBrackets.normalizer(),
+        Arc::new(FnNormalizer::new("upper", |key: &str| {
+            vec![key.to_string(), key.to_uppercase()]
+        })),
+    ]);
+    assert_eq!(pipeline.name(), "japanese_brackets+upper");
+    assert_eq!(
+        pipeline.readings("ab【ab】"),
+        vec!["ab".to_string(), "AB".to_string()]
+    );
+    assert_eq!(
+        NormalizerPipeline::new(Vec::new()).readings("x【y】"),
+        vec!["x【y】"]
+    );
+}
+
+#[test]
+fn readings_list_uses_the_given_normalizer() {
+    let mut writer = MdxWriter::new();
+    writer.add("hao3", "@@@LINK=hǎo").unwrap();
+    writer.add("hǎo", "<p>good</p>").unwrap();
+    let mut mdict = Mdict::new(Cursor::new(writer.to_bytes().unwrap())).unwrap();
+    let key_id = mdict.get(1).unwrap().unwrap().key_id;
+
+    let default = build_readings_list(&mut mdict).unwrap();
+    assert!(!default[&key_id].contains("hao"));
+
+    let pinyin = NormalizerPipeline::from_rules(&[KeyNormalizerRule::PinyinTones]);
+    let readings = build_readings_list_normalized(&mut mdict, &pinyin).unwrap();
+    let mut readings = readings[&key_id].iter().cloned().collect::<Vec<_>>();
+    readings.sort();
+    assert_eq!(readings, vec!["hao", "hao3", "hǎo"]);
+}
+
+#[test]
+fn bundle_builds_the_optimized_index_with_selected_rules() {
+    let dir = tempfile::tempdir().unwrap();
+    let bundle = common::bundle(
+        &common::writer_with_entries(&[("nǐ hǎo", "<p>hello</p>")]),
+        dir.path(),
+    );
+    let build = |bundle| common::optimized_from_bundle(bundle, dir.path());
+
+    let optimized = build(&bundle);
+    assert_eq!(optimized.count_prefix("ni h"), 0);
+
+    bundle.set_key_normalizers(vec![KeyNormalizerRule::PinyinTones]);
+    let optimized = build(&bundle);
+    assert_eq!(optimized.count_prefix("ni h"), 1);
+    assert_eq!(optimized.count_prefix("nǐ"), 1);
+}
@@ -0,0 +1,72 @@
+#![cfg(feature = "mmap")]
+
+use std::io::Cursor;
+
+use mdict_tools::mdx_conversion::reindexing;
+use mdict_tools::synth::{SynthDict, SynthDictBuilder};
+use mdict_tools::Mdict;
+
+mod common;
+
+fn synth() -> SynthDict {
+    common::synth(
+        SynthDictBuilder::entries(400)
+            .entries_per_record_block(3)
+            .link_every(7),
+    )
+}
+
+/// Entries up to the first error, and whether there was one.
+fn read_all(bytes: Vec<u8>, parallel: bool) -> (Vec<(String, Vec<u8>)>, bool) {
+    let mut mdict = Mdict::new(Cursor::new(bytes)).unwrap();
+    let mut entries = Vec::new();
+    for entry in mdict.iter_entries().parallel(parallel) {
+        match entry {
+            Ok((key_block, record)) => entries.push((key_block.key_text, record)),
+            Err(_) => return (entries, true),
+        }
+    }
+    (entries, false)
+}
+
+#[test]
+fn parallel_batches_yield_entries_in_order() {
+    let dict = synth();
+    let (entries, failed) = read_all(dict.bytes.clone(), true);
+    assert!(!failed);
+    assert_eq!(entries, dict.entries);
+}
+
+#[test]
+fn a_bad_block_in_a_batch_fails_at_its_own_entries() {
+    let dict = synth();
+    let offset = {
+        let mut mdict = dict.open().unwrap();
+        let records = mdict.record_section().unwrap();
+        (records.record_data_offset + records.record_index_prefix_sum[10].compressed_size) as usize
+    };
+    let mut bytes = dict.bytes.clone();
+    for byte in &mut bytes[offset + 8..offset + 12] {
+        *byte ^= 0xff;
+    }
+
+    let (serial, failed) = read_all(bytes.clone(), false);
+    assert!(failed);
+    assert_eq!(serial.len(), 30);
+    let (parallel, failed) = read_all(bytes, true);
+    assert!(failed);
+    assert_eq!(parallel, serial);
+}
+
+#[test]
+fn readings_are_unchanged_by_parallel_decoding() {
+    let dict = synth();
+    let mut mdict = dict.open().unwrap();
+    let readings = reindexing::build_readings_list(&mut mdict).unwrap();
+    let mut budgeted = dict.open().unwrap();
+    let spilled = reindexing::build_readings_list_with_budget(&mut budgeted, 1024).unwrap();
+    assert_eq!(readings, spilled);
+    for (key, _) in &dict.entries {
+        assert!(readings.values().any(|readings| readings.contains(key)), "{}", key);
+    }
+}
@@ -0,0 +1,72 @@
+use std::io::Cursor;
+
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::render::{html_to_plaintext, to_plaintext};
+use mdict_tools::types::Encoding;
+use mdict_tools::Mdict;
+
+#[test]
+fn strips_tags_and_collapses_whitespace() {
+    assert_eq!(
+        html_to_plaintext("<div class=\"entry\">\n  <b>cat</b>   <i>n.</i>\n</div><p>a small&nbsp;animal</p>"),
+        "cat n.\na small animal"
+    );
+    assert_eq!(
+        html_to_plaintext("<ol><li>one</li><li>two<br>lines</li></ol>"),
+        "one\ntwo\nlines"
+    );
+    assert_eq!(
+        html_to_plaintext("<table><tr><td>a</td><td>b</td></tr><tr><td>c</td></tr></table>"),
+        "a b\nc"
+    );
+}
+
+#[test]
+fn drops_scripts_styles_and_comments() {
+    assert_eq!(
+        html_to_plaintext(
+            "<style>p { color: red }</style><!-- note --><p>text</p><SCRIPT>if (a < b) go()</script>"
+        ),
+        "text"
+    );
+    assert_eq!(html_to_plaintext("<!DOCTYPE html><p>x</p>"), "x");
+}
+
+#[test]
+fn decodes_character_references() {
+    assert_eq!(
+        html_to_plaintext("AT&amp;T &lt;tag&gt; &#x6F22;&#23383; &bogus; a & b"),
+        "AT&T <tag> 漢字 &bogus; a & b"
+    );
+    assert_eq!(html_to_plaintext("1 < 2 > 0"), "1 < 2 > 0");
+}
+
+#[test]
+fn ruby_readings_follow_their_base() {
+    assert_eq!(
+        html_to_plaintext("<ruby>漢<rp>(</rp><rt>かん</rt><rp>)</rp>字<rt>じ</rt></ruby>を読む"),
+        "漢(かん)字(じ)を読む"
+    );
+    assert_eq!(html_to_plaintext("<ruby>日<rt>にち</ruby>"), "日(にち)");
+}
+
+#[test]
+fn decodes_with_the_record_encoding() {
+    let utf16 = "<p>été</p>"
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect::<Vec<_>>();
+    assert_eq!(to_plaintext(&utf16, Encoding::Utf16LE), "été");
+    assert_eq!(to_plaintext(b"<p>caf\xc3\xa9</p>\r\n\0", Encoding::Utf8), "café");
+}
+
+#[test]
+fn mdict_record_plaintext() {
+    let mut writer = MdxWriter::new();
+    writer
+        .add("cat", "<div><b>cat</b><br><span>a small animal</span></div>")
+        .unwrap();
+    let mut mdict = Mdict::new(Cursor::new(writer.to_bytes().unwrap())).unwrap();
+    let key = mdict.get_all("cat").unwrap().remove(0);
+    assert_eq!(mdict.record_plaintext(&key).unwrap(), "cat\na small animal");
+}
@@ -0,0 +1,56 @@
+use std::io::Cursor;
+
+use mdict_tools::synth::{SynthDict, SynthDictBuilder};
+use mdict_tools::Mdict;
+
+mod common;
+
+fn synth() -> SynthDict {
+    common::synth(SynthDictBuilder::entries(80).entries_per_record_block(8))
+}
+
+/// Entries up to the first error, and whether there was one.
+fn read_all(bytes: Vec<u8>, prefetch: bool) -> (Vec<(String, Vec<u8>)>, bool) {
+    let mut mdict = Mdict::new(Cursor::new(bytes)).unwrap();
+    let mut entries = Vec::new();
+    for entry in mdict.iter_entries().prefetch(prefetch) {
+        match entry {
+            Ok((key_block, record)) => entries.push((key_block.key_text, record)),
+            Err(_) => return (entries, true),
+        }
+    }
+    (entries, false)
+}
+
+#[test]
+fn prefetching_yields_the_same_entries() {
+    let dict = synth();
+    let (plain, failed) = read_all(dict.bytes.clone(), false);
+    assert!(!failed);
+    let (prefetched, failed) = read_all(dict.bytes.clone(), true);
+    assert!(!failed);
+    assert_eq!(prefetched, plain);
+    assert_eq!(prefetched, dict.entries);
+}
+
+#[test]
+fn a_bad_prefetched_block_fails_at_its_own_entries() {
+    let dict = synth();
+    let offset = {
+        let mut mdict = dict.open().unwrap();
+        let records = mdict.record_section().unwrap();
+        (records.record_data_offset + records.record_index_prefix_sum[4].compressed_size) as usize
+    };
+    let mut bytes = dict.bytes.clone();
+    // Past the compression type and checksum, into the payload.
+    for byte in &mut bytes[offset + 8..offset + 16] {
+        *byte ^= 0xff;
+    }
+
+    let (plain, failed) = read_all(bytes.clone(), false);
+    assert!(failed);
+    assert_eq!(plain.len(), 32);
+    let (prefetched, failed) = read_all(bytes, true);
+    assert!(failed);
+    assert_eq!(prefetched, plain);
+}
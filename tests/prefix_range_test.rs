@@ -0,0 +1,91 @@
+use std::fs::File;
+use std::io::{Cursor, Read, Seek};
+
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::Mdict;
+
+const SAMPLE_PATH: &str = "resources/jitendex/jitendex.mdx";
+
+/// Global indexes of the keys starting with `prefix`, by a linear scan.
+fn scan_prefix<R: Read + Seek>(mdict: &mut Mdict<R>, prefix: &str) -> Vec<usize> {
+    mdict
+        .keys_in_range(..)
+        .unwrap()
+        .enumerate()
+        .filter(|(_, key_block)| key_block.as_ref().unwrap().key_text.starts_with(prefix))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+fn block_of<R: Read + Seek>(mdict: &Mdict<R>, index: usize) -> usize {
+    let prefix_sum = &mdict.key_block_index.key_section.num_entries_prefix_sum;
+    prefix_sum.partition_point(|&start| start <= index as u64) - 1
+}
+
+fn prefix_range<R: Read + Seek>(mdict: &mut Mdict<R>, prefix: &str) -> (usize, usize) {
+    mdict
+        .key_block_index
+        .prefix_range_bounds(&mut mdict.reader, prefix)
+        .unwrap()
+        .unwrap()
+}
+
+fn assert_prefix_range<R: Read + Seek>(mdict: &mut Mdict<R>, prefix: &str) -> (usize, usize) {
+    let expected = scan_prefix(mdict, prefix);
+    let (start, end) = prefix_range(mdict, prefix);
+    assert_eq!((start..end).collect::<Vec<_>>(), expected, "prefix {:?}", prefix);
+
+    let found = mdict.search_keys_prefix(prefix).unwrap().collect_to_vec().unwrap();
+    assert_eq!(found.len(), expected.len());
+    assert!(found.iter().all(|key_block| key_block.key_text.starts_with(prefix)));
+    (start, end)
+}
+
+#[test]
+fn prefix_matches_straddling_many_blocks_are_all_found() {
+    let mut writer = MdxWriter::new().key_block_size(48);
+    let mut keys = (0..40).map(|i| format!("run{:02}", i)).collect::<Vec<_>>();
+    keys.extend(["apple", "walk", "zebra"].map(String::from));
+    keys.sort();
+    for key in &keys {
+        writer.add(key.clone(), "<p>entry</p>").unwrap();
+    }
+    let mut mdict = Mdict::new(Cursor::new(writer.to_bytes().unwrap())).unwrap();
+    assert!(mdict.key_block_index.key_section.key_info_blocks.len() > 3);
+
+    let (start, end) = assert_prefix_range(&mut mdict, "run");
+    assert_eq!(end - start, 40);
+    assert!(block_of(&mdict, end - 1) > block_of(&mdict, start) + 1);
+
+    for prefix in ["run1", "run39", "a", "w", "z"] {
+        assert_prefix_range(&mut mdict, prefix);
+    }
+}
+
+#[test]
+fn prefix_ranges_cover_block_boundaries_in_the_sample() {
+    let Ok(file) = File::open(SAMPLE_PATH) else {
+        eprintln!("Skipping: {} not found", SAMPLE_PATH);
+        return;
+    };
+    let mut mdict = Mdict::new(file).unwrap();
+
+    // Keys can match a prefix under the collation without starting with it
+    // byte for byte, so the scan only bounds the range from inside.
+    let mut straddling = 0;
+    for prefix in ["あ", "か", "し", "食べ", "日本"] {
+        let expected = scan_prefix(&mut mdict, prefix);
+        let (start, end) = prefix_range(&mut mdict, prefix);
+        assert!(
+            expected.iter().all(|index| (start..end).contains(index)),
+            "prefix {:?} misses keys outside {}..{}",
+            prefix,
+            start,
+            end
+        );
+        if end > start && block_of(&mdict, end - 1) != block_of(&mdict, start) {
+            straddling += 1;
+        }
+    }
+    assert!(straddling > 0, "no sample prefix spans two key blocks");
+}
@@ -0,0 +1,97 @@
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+use mdict_tools::synth::{SynthDict, SynthDictBuilder};
+use mdict_tools::Mdict;
+
+mod common;
+
+/// Counts the reads made through it, to tell whether key blocks were decoded.
+struct CountingReader {
+    inner: Cursor<Vec<u8>>,
+    reads: usize,
+}
+
+impl Read for CountingReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.reads += 1;
+        self.inner.read(buf)
+    }
+}
+
+impl Seek for CountingReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+fn synth() -> SynthDict {
+    common::synth(SynthDictBuilder::entries(80).entries_per_key_block(7))
+}
+
+fn open(dict: &SynthDict) -> Mdict<CountingReader> {
+    let reader = CountingReader {
+        inner: Cursor::new(dict.bytes.clone()),
+        reads: 0,
+    };
+    Mdict::new(reader).expect("open synthetic dictionary")
+}
+
+fn every_key(mdict: &mut Mdict<CountingReader>) -> Vec<String> {
+    mdict
+        .keys_in_range(..)
+        .unwrap()
+        .map(|key_block| key_block.unwrap().key_text)
+        .collect()
+}
+
+#[test]
+fn preloaded_keys_are_searched_without_reading() {
+    let dict = synth();
+    let mut plain = open(&dict);
+    let mut preloaded = open(&dict);
+    assert!(!preloaded.key_block_index.is_preloaded());
+    preloaded.preload_keys().unwrap();
+    assert!(preloaded.key_block_index.is_preloaded());
+
+    let reads = preloaded.reader.reads;
+    let keys = every_key(&mut preloaded);
+    assert_eq!(keys, every_key(&mut plain));
+    assert_eq!(keys.len(), dict.entries.len());
+
+    for (key, _) in dict.entries.iter().step_by(9) {
+        let expected = plain.get_all(key).unwrap();
+        let found = preloaded.get_all(key).unwrap();
+        assert_eq!(found.len(), expected.len());
+        assert_eq!(found[0].key_id, expected[0].key_id);
+    }
+    let prefix = &dict.entries[40].0[..9];
+    assert_eq!(
+        preloaded
+            .search_keys_prefix(prefix)
+            .unwrap()
+            .collect_to_vec()
+            .unwrap()
+            .len(),
+        plain.search_keys_prefix(prefix).unwrap().len()
+    );
+    assert_eq!(preloaded.reader.reads, reads);
+
+    let record = preloaded.record_at_index(12).unwrap();
+    assert_eq!(record, dict.entries[12].1);
+}
+
+#[test]
+fn handles_share_the_preloaded_keys() {
+    let dict = synth();
+    let mut mdict = open(&dict);
+    mdict.preload_keys().unwrap();
+    assert!(mdict.key_block_index.decompressed_size() > 0);
+
+    let mut handle = mdict.with_reader(CountingReader {
+        inner: Cursor::new(dict.bytes.clone()),
+        reads: 0,
+    });
+    assert!(handle.key_block_index.is_preloaded());
+    assert_eq!(every_key(&mut handle).len(), dict.entries.len());
+    assert_eq!(handle.reader.reads, 0);
+}
@@ -0,0 +1,56 @@
+#![cfg(all(feature = "mmap", feature = "zstd"))]
+
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::render::preview_text;
+
+mod common;
+
+fn writer() -> MdxWriter {
+    let mut writer = MdxWriter::new();
+    for i in 0..40 {
+        writer
+            .add(
+                format!("word{:02}", i),
+                &format!("<div><b>word{:02}</b><p>meaning number {}</p></div>", i, i),
+            )
+            .unwrap();
+    }
+    writer.add("wordy", "@@@LINK=word07").unwrap();
+    writer
+}
+
+#[test]
+fn preview_text_is_one_truncated_line() {
+    assert_eq!(preview_text(b"<b>cat</b><p>a small animal</p>", 12), "cat a small ");
+    let utf16 = "<p>été</p>"
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect::<Vec<_>>();
+    assert_eq!(preview_text(&utf16, 10), "été");
+}
+
+#[test]
+fn pages_carry_previews() {
+    let dir = tempfile::tempdir().unwrap();
+    let optimized = common::optimized(&writer(), dir.path());
+
+    let page = optimized
+        .set_search_prefix_paged_with_preview("word", 10, 20)
+        .unwrap();
+    assert_eq!(page.results.len(), 10);
+    assert_eq!(page.total_results, Some(41));
+    assert_eq!(page.results[0].key.key_text, "word00");
+    assert_eq!(page.results[0].preview, "word00 meaning numbe");
+    assert_eq!(page.results[3].preview, "word03 meaning numbe");
+
+    let next = optimized
+        .prefix_search_next_page_with_preview(page.next_cursor.unwrap(), 100)
+        .unwrap();
+    assert_eq!(next.results[0].key.key_text, "word10");
+    assert_eq!(next.results[0].preview, "word10 meaning number 10");
+
+    let wordy = optimized
+        .set_search_prefix_paged_with_preview("wordy", 10, 100)
+        .unwrap();
+    assert_eq!(wordy.results[0].preview, "word07 meaning number 7");
+}
@@ -0,0 +1,70 @@
+#![cfg(all(feature = "mmap", feature = "zstd"))]
+
+use std::collections::HashMap;
+
+use mdict_tools::mdict_optimized::Ranker;
+use mdict_tools::types::PrefixSearchPage;
+
+mod common;
+
+struct Frequencies(HashMap<&'static str, i64>);
+
+impl Ranker for Frequencies {
+    fn score(&self, key_text: String) -> i64 {
+        self.0.get(key_text.as_str()).copied().unwrap_or(0)
+    }
+}
+
+fn keys(page: &PrefixSearchPage) -> Vec<&str> {
+    page.results
+        .iter()
+        .map(|key| key.key_text.as_str())
+        .collect()
+}
+
+#[test]
+fn ranker_reorders_each_page() {
+    let dir = tempfile::tempdir().unwrap();
+    let optimized = common::optimized(
+        &common::writer_with_keys(&["cat", "catalog", "catch", "cater", "cattle"]),
+        dir.path(),
+    );
+    optimized.set_ranker(Some(Box::new(Frequencies(HashMap::from([
+        ("catch", 50),
+        ("cat", 100),
+        ("cattle", 70),
+    ])))));
+
+    let first = optimized.set_search_prefix_paged("cat", 3).unwrap();
+    assert_eq!(keys(&first), vec!["cat", "catch", "catalog"]);
+
+    let second = optimized
+        .prefix_search_next_page(first.next_cursor.clone().unwrap())
+        .unwrap();
+    assert_eq!(keys(&second), vec!["cattle", "cater"]);
+
+    let back = optimized
+        .prefix_search_prev_page(second.prev_cursor.clone().unwrap())
+        .unwrap();
+    assert_eq!(keys(&back), keys(&first));
+}
+
+#[test]
+fn clearing_the_ranker_restores_key_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let optimized = common::optimized(
+        &common::writer_with_keys(&["cat", "catalog", "catch"]),
+        dir.path(),
+    );
+    optimized.set_ranker(Some(Box::new(Frequencies(HashMap::from([("catch", 1)])))));
+    assert_eq!(
+        keys(&optimized.set_search_prefix_paged("cat", 10).unwrap()),
+        vec!["catch", "cat", "catalog"]
+    );
+
+    optimized.set_ranker(None);
+    assert_eq!(
+        keys(&optimized.set_search_prefix_paged("cat", 10).unwrap()),
+        vec!["cat", "catalog", "catch"]
+    );
+}
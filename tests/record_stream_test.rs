@@ -0,0 +1,47 @@
+#![cfg(feature = "mmap")]
+
+use mdict_tools::error::MDictError;
+use mdict_tools::mdict_file::create_mdict_bundle;
+use mdict_tools::mdx_writer::MdxWriter;
+
+#[test]
+fn record_stream_returns_the_record_in_chunks() {
+    let dir = tempfile::tempdir().unwrap();
+    let mdx_path = dir.path().join("dict.mdx");
+    let large = format!(
+        "<img src=\"data:image/png;base64,{}\">",
+        "QUJD".repeat(50_000)
+    );
+    let mut writer = MdxWriter::new();
+    writer.add("encyclopedia", &large).unwrap();
+    writer.add("small", "<p>tiny</p>").unwrap();
+    writer.write_to_path(&mdx_path).unwrap();
+    let bundle =
+        create_mdict_bundle(mdx_path.to_string_lossy().to_string(), String::new()).unwrap();
+
+    let stream = bundle.open_record_stream("encyclopedia").unwrap();
+    assert_eq!(stream.total_len(), large.len() as u64);
+    let mut streamed = Vec::new();
+    loop {
+        let chunk = stream.read_next(4096).unwrap();
+        if chunk.is_empty() {
+            break;
+        }
+        assert!(chunk.len() <= 4096);
+        streamed.extend_from_slice(&chunk);
+    }
+    assert_eq!(streamed, large.as_bytes());
+    assert_eq!(stream.position(), stream.total_len());
+    assert!(stream.read_next(4096).unwrap().is_empty());
+
+    let stream = bundle.open_record_stream("small").unwrap();
+    assert_eq!(stream.read_next(u64::MAX).unwrap(), b"<p>tiny</p>");
+    assert!(matches!(
+        stream.read_next(0),
+        Err(MDictError::InvalidArgument(_))
+    ));
+    assert!(matches!(
+        bundle.open_record_stream("missing"),
+        Err(MDictError::KeyNotFound(_))
+    ));
+}
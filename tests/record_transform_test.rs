@@ -0,0 +1,101 @@
+#![cfg(all(feature = "mmap", feature = "zstd"))]
+
+use std::fs;
+use std::io::Cursor;
+use std::sync::Arc;
+
+use mdict_tools::mdx_conversion::export::{export_with_transform, ExportFormat};
+use mdict_tools::mdx_conversion::transform::{
+    CollapseWhitespace, RecordTransform, RecordTransformRule, StripScripts, TransformPipeline,
+};
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::Mdict;
+
+mod common;
+
+const SCRIPTED: &str = "<p>cat</p><SCRIPT type=\"text/javascript\">track()</script>\n  <p>animal</p>";
+
+fn writer() -> MdxWriter {
+    let mut writer = MdxWriter::new();
+    writer.add("cat", SCRIPTED).unwrap();
+    writer.add("kitty", "@@@LINK=cat").unwrap();
+    writer
+}
+
+struct Uppercase;
+
+impl RecordTransform for Uppercase {
+    fn name(&self) -> String {
+        "uppercase".to_string()
+    }
+
+    fn transform(&self, record: Vec<u8>) -> Vec<u8> {
+        record.to_ascii_uppercase()
+    }
+}
+
+#[test]
+fn built_in_transforms() {
+    assert_eq!(
+        StripScripts.transform(SCRIPTED.as_bytes().to_vec()),
+        b"<p>cat</p>\n  <p>animal</p>"
+    );
+    assert_eq!(
+        StripScripts.transform(b"a<script>never closed".to_vec()),
+        b"a"
+    );
+    assert_eq!(
+        CollapseWhitespace.transform(b"<p>a  big\n cat</p>\n  <p>x</p>".to_vec()),
+        b"<p>a big cat</p><p>x</p>"
+    );
+
+    let pipeline = TransformPipeline::from_rules(&[
+        RecordTransformRule::StripScripts,
+        RecordTransformRule::CollapseWhitespace,
+    ]);
+    assert_eq!(pipeline.name(), "strip_scripts+collapse_whitespace");
+    assert_eq!(
+        pipeline.transform(SCRIPTED.as_bytes().to_vec()),
+        b"<p>cat</p><p>animal</p>"
+    );
+}
+
+#[test]
+fn optimized_records_are_transformed_and_redirects_resolved() {
+    let dir = tempfile::tempdir().unwrap();
+    let bundle = common::bundle(&writer(), dir.path());
+    bundle.set_record_transforms(vec![
+        RecordTransformRule::StripScripts,
+        RecordTransformRule::CollapseWhitespace,
+    ]);
+
+    let optimized = common::optimized_from_bundle(&bundle, dir.path());
+
+    let cat = optimized.lookup("cat").remove(0);
+    assert_eq!(optimized.record_at(cat).unwrap(), b"<p>cat</p><p>animal</p>");
+    // The index points redirects at their target when it is built, so the
+    // redirect record itself is never read back.
+    let kitty = optimized.lookup("kitty").remove(0);
+    assert_eq!(optimized.record_at(kitty.clone()).unwrap(), b"<p>cat</p><p>animal</p>");
+    assert_eq!(
+        optimized.record_resolved(kitty, 4).unwrap(),
+        b"<p>cat</p><p>animal</p>"
+    );
+}
+
+#[test]
+fn export_applies_the_transform() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut mdict = Mdict::new(Cursor::new(writer().to_bytes().unwrap())).unwrap();
+    let tsv = dir.path().join("out.tsv");
+    let transform: Arc<dyn RecordTransform> = Arc::new(Uppercase);
+    assert_eq!(
+        export_with_transform(&mut mdict, ExportFormat::Tsv, &tsv, Some(transform.as_ref()))
+            .unwrap(),
+        2
+    );
+    let tsv = fs::read_to_string(&tsv).unwrap();
+    let lines = tsv.lines().collect::<Vec<_>>();
+    assert!(lines[0].starts_with("cat\t<P>CAT</P><SCRIPT"));
+    assert_eq!(lines[1], "kitty\t@@@LINK=cat");
+}
@@ -0,0 +1,82 @@
+#![cfg(feature = "mmap")]
+
+use std::fs::{self, File};
+use std::path::Path;
+
+use mdict_tools::error::MDictError;
+use mdict_tools::file_stamp::FileStamp;
+use mdict_tools::mdict_file::{create_mdict_bundle, MdictBundle};
+use mdict_tools::mdx_writer::MdxWriter;
+
+fn write_dict(path: &Path, title: &str, keys: &[&str]) {
+    let mut writer = MdxWriter::new().title(title);
+    for key in keys {
+        writer.add(*key, &format!("<p>{}</p>", key)).unwrap();
+    }
+    writer.write_to_path(path).unwrap();
+}
+
+fn open(path: &Path) -> MdictBundle {
+    create_mdict_bundle(path.to_string_lossy().into_owned(), String::new()).unwrap()
+}
+
+#[test]
+fn replaced_files_fail_reads_until_reloaded() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("dict.mdx");
+    write_dict(&path, "Dict", &["apple", "banana"]);
+    let bundle = open(&path);
+    bundle.add_key_alias("pomme".to_string(), "apple".to_string());
+    assert!(!bundle.has_changed());
+    assert_eq!(bundle.lookup("banana").unwrap().len(), 1);
+
+    write_dict(&path, "Dict", &["apple", "banana", "cherry"]);
+    assert!(bundle.has_changed());
+    assert!(matches!(
+        bundle.lookup("banana"),
+        Err(MDictError::FileChanged(_))
+    ));
+    assert_eq!(bundle.total_entries(), 2);
+
+    bundle.reload().unwrap();
+    assert!(!bundle.has_changed());
+    assert_eq!(bundle.total_entries(), 3);
+    let cherry = bundle.lookup("cherry").unwrap().remove(0);
+    assert_eq!(bundle.record_text_at(cherry).unwrap(), "<p>cherry</p>");
+    assert_eq!(bundle.lookup("pomme").unwrap()[0].key_text, "apple");
+}
+
+#[test]
+fn failed_reloads_keep_the_open_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("dict.mdx");
+    write_dict(&path, "Dict", &["apple"]);
+    let bundle = open(&path);
+
+    fs::remove_file(&path).unwrap();
+    assert!(bundle.has_changed());
+    assert!(bundle.reload().is_err());
+    assert_eq!(bundle.metadata().title.as_deref(), Some("Dict"));
+}
+
+#[test]
+fn stamps_compare_the_header_checksum() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("dict.mdx");
+    write_dict(&path, "Old", &["apple"]);
+    let stamp = FileStamp::of(&path).unwrap();
+    let modified = fs::metadata(&path).unwrap().modified().unwrap();
+
+    // Same size and time, different header.
+    write_dict(&path, "New", &["apple"]);
+    File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+    assert!(stamp.metadata_matches(&path).unwrap());
+    let now = FileStamp::of(&path).unwrap();
+    assert_eq!(now.size, stamp.size);
+    assert_ne!(now.header_checksum, stamp.header_checksum);
+}
@@ -0,0 +1,97 @@
+use std::io::Cursor;
+
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::render::{rewrite_links, LinkKind, RenderOptions};
+use mdict_tools::Mdict;
+
+const STYLES: &str = "1\n<span class=\"hw\">\n</span>\n2\n<div class=\"def\">\n</div>\n";
+const EXPAND: RenderOptions = RenderOptions {
+    expand_compact: true,
+};
+
+fn open(compact: bool) -> Mdict<Cursor<Vec<u8>>> {
+    let mut writer = MdxWriter::new().stylesheet(STYLES).compact(compact);
+    writer.add("cat", "`1`cat`2`a small feline").unwrap();
+    Mdict::new(Cursor::new(writer.to_bytes().unwrap())).unwrap()
+}
+
+#[test]
+fn compact_records_are_expanded_on_request() {
+    let mut mdict = open(true);
+    assert!(mdict.metadata().compact);
+    let key = mdict.get(0).unwrap().unwrap();
+
+    assert_eq!(
+        mdict.record_rendered(&key, EXPAND).unwrap(),
+        "<span class=\"hw\">cat</span><div class=\"def\">a small feline</div>"
+    );
+    assert_eq!(
+        mdict
+            .record_rendered(&key, RenderOptions::default())
+            .unwrap(),
+        "`1`cat`2`a small feline"
+    );
+}
+
+#[test]
+fn non_compact_records_are_left_alone() {
+    let mut mdict = open(false);
+    let key = mdict.get(0).unwrap().unwrap();
+
+    assert_eq!(
+        mdict.record_rendered(&key, EXPAND).unwrap(),
+        "`1`cat`2`a small feline"
+    );
+}
+
+fn app_scheme(kind: LinkKind, target: &str) -> Option<String> {
+    Some(match kind {
+        LinkKind::Entry => format!("app://entry/{}", target),
+        LinkKind::Sound => format!("app://sound/{}", target),
+        LinkKind::Asset => format!("app://asset/{}", target.trim_start_matches('/')),
+    })
+}
+
+#[test]
+fn dictionary_links_are_rewritten() {
+    let html = concat!(
+        "<link rel=stylesheet href=style.css>",
+        "<a HREF='entry://cat#sense2'>cat</a>",
+        "<a href=\"sound://audio/cat.mp3\"><img src=\"file:///img/cat.png\"/></a>",
+        "<img alt=\"1 < 2\" src=img/dog.png>",
+    );
+    assert_eq!(
+        rewrite_links(html, app_scheme),
+        concat!(
+            "<link rel=stylesheet href=\"app://asset/style.css\">",
+            "<a HREF='app://entry/cat#sense2'>cat</a>",
+            "<a href=\"app://sound/audio/cat.mp3\"><img src=\"app://asset/img/cat.png\"/></a>",
+            "<img alt=\"1 < 2\" src=\"app://asset/img/dog.png\">",
+        )
+    );
+}
+
+#[test]
+fn other_links_and_text_are_left_alone() {
+    let html = concat!(
+        "<a href=\"https://example.com/a.png\">web</a>",
+        "<a href=\"#top\">top</a><img src=\"data:image/png;base64,AA==\">",
+        "<!-- <img src=\"hidden.png\"> --><p title=\"a.png\">x < y, src=z.png</p>",
+    );
+    assert_eq!(rewrite_links(html, app_scheme), html);
+    assert_eq!(
+        rewrite_links("<a href=\"entry://a\">", |_, _| None),
+        "<a href=\"entry://a\">"
+    );
+}
+
+#[test]
+fn link_targets_are_decoded_and_replacements_escaped() {
+    let mut seen = Vec::new();
+    let out = rewrite_links("<a href=\"entry://R&amp;D\">", |kind, target| {
+        seen.push((kind, target.to_string()));
+        Some(format!("app://entry?q={}&x=\"", target))
+    });
+    assert_eq!(seen, vec![(LinkKind::Entry, "R&D".to_string())]);
+    assert_eq!(out, "<a href=\"app://entry?q=R&amp;D&amp;x=&quot;\">");
+}
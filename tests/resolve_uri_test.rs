@@ -0,0 +1,102 @@
+#![cfg(feature = "mmap")]
+
+use mdict_tools::error::MDictError;
+use mdict_tools::mdict_file::create_mdict_bundle;
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::render::LinkKind;
+use mdict_tools::MdictBundle;
+use tempfile::TempDir;
+
+const PNG: &[u8] = &[0x89, b'P', b'N', b'G', 0x0A, 0x00];
+
+fn open_bundle(with_mdd: bool) -> (TempDir, MdictBundle) {
+    let dir = tempfile::tempdir().unwrap();
+    let mdx_path = dir.path().join("dict.mdx");
+    let mut mdx = MdxWriter::new();
+    mdx.add("cat", "<img src=\"img/cat.png\">").unwrap();
+    mdx.add("kitty", "@@@LINK=cat").unwrap();
+    mdx.add("猫", "<p>neko</p>").unwrap();
+    mdx.write_to_path(&mdx_path).unwrap();
+
+    let mdd_path = if with_mdd {
+        let path = dir.path().join("dict.mdd");
+        let mut mdd = MdxWriter::mdd();
+        mdd.add_raw("\\img\\cat.png", PNG.to_vec()).unwrap();
+        mdd.add_raw("\\sound\\cat.spx", b"Speex".to_vec()).unwrap();
+        mdd.write_to_path(&path).unwrap();
+        path.to_string_lossy().to_string()
+    } else {
+        String::new()
+    };
+
+    let bundle =
+        create_mdict_bundle(mdx_path.to_string_lossy().to_string(), mdd_path).expect("open bundle");
+    (dir, bundle)
+}
+
+#[test]
+fn entry_uris_resolve_to_utf8_html() {
+    let (_dir, bundle) = open_bundle(false);
+
+    let resource = bundle.resolve_uri("entry://kitty#top").unwrap();
+    assert_eq!(resource.kind, LinkKind::Entry);
+    assert_eq!(resource.data, b"<img src=\"img/cat.png\">");
+    assert_eq!(resource.mime_type, "text/html; charset=utf-8");
+
+    let resource = bundle.resolve_uri("entry://%E7%8C%AB").unwrap();
+    assert_eq!(resource.data, "<p>neko</p>".as_bytes());
+
+    assert!(matches!(
+        bundle.resolve_uri("entry://dog"),
+        Err(MDictError::KeyNotFound(_))
+    ));
+}
+
+#[test]
+fn sound_and_asset_uris_resolve_to_mdd_resources() {
+    let (_dir, bundle) = open_bundle(true);
+
+    for uri in ["img/cat.png", "/img/cat.png?v=2", "file:///img/cat.png"] {
+        let resource = bundle.resolve_uri(uri).unwrap();
+        assert_eq!(resource.kind, LinkKind::Asset, "{}", uri);
+        assert_eq!(resource.data, PNG, "{}", uri);
+        assert_eq!(resource.mime_type, "image/png", "{}", uri);
+    }
+
+    let resource = bundle.resolve_uri("sound://sound/cat.spx").unwrap();
+    assert_eq!(resource.kind, LinkKind::Sound);
+    assert_eq!(resource.data, b"Speex");
+    assert_eq!(resource.mime_type, "audio/ogg");
+}
+
+#[test]
+fn unresolvable_uris_are_errors() {
+    let (_dir, bundle) = open_bundle(false);
+    assert!(matches!(
+        bundle.resolve_uri("https://example.com/cat.png"),
+        Err(MDictError::InvalidArgument(_))
+    ));
+    assert!(matches!(
+        bundle.resolve_uri("img/cat.png"),
+        Err(MDictError::KeyNotFound(_))
+    ));
+}
+
+#[test]
+fn mdd_resources_come_with_a_mime_type() {
+    let (_dir, bundle) = open_bundle(true);
+    assert_eq!(
+        bundle.mdd_resource_with_mime("\\img\\cat.png").unwrap(),
+        Some((PNG.to_vec(), "image/png".to_string()))
+    );
+    assert_eq!(
+        bundle.mdd_resource_with_mime("\\sound\\cat.spx").unwrap(),
+        Some((b"Speex".to_vec(), "audio/ogg".to_string()))
+    );
+
+    let (_dir, bundle) = open_bundle(false);
+    assert_eq!(
+        bundle.mdd_resource_with_mime("\\img\\cat.png").unwrap(),
+        None
+    );
+}
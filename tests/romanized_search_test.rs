@@ -0,0 +1,101 @@
+#![cfg(all(feature = "mmap", feature = "zstd"))]
+
+use mdict_tools::error::MDictError;
+use mdict_tools::mdict_optimized::{create_mdict_optimized_from_fst, open_mdict_optimized_bundle};
+use mdict_tools::transliterate::{fold_romanized, romanize, RomanizationScheme};
+use mdict_tools::MdictOptimized;
+
+mod common;
+
+fn search(optimized: &MdictOptimized, query: &str) -> Vec<String> {
+    optimized
+        .search_keys_romanized(query)
+        .unwrap()
+        .into_iter()
+        .map(|key| key.key_text)
+        .collect()
+}
+
+#[test]
+fn kana_and_pinyin_romanize() {
+    let romaji = |key| romanize(RomanizationScheme::Romaji, key);
+    assert_eq!(romaji("たべる"), vec!["taberu"]);
+    assert_eq!(romaji("きょう"), vec!["kyou", "kyo"]);
+    assert_eq!(romaji("ちょっと"), vec!["chotto"]);
+    assert_eq!(romaji("マッチ"), vec!["matchi"]);
+    assert_eq!(romaji("コーヒー"), vec!["koohii", "kohii"]);
+    assert_eq!(romaji("ファイル"), vec!["fairu"]);
+    assert!(romaji("食べる").is_empty());
+
+    let pinyin = |key| romanize(RomanizationScheme::Pinyin, key);
+    assert_eq!(pinyin("nǐ hǎo"), vec!["nihao"]);
+    assert_eq!(pinyin("lü4 se4"), vec!["lvse"]);
+    assert!(pinyin("你好").is_empty());
+
+    assert_eq!(fold_romanized("Ni3 Hao3"), "nihao");
+    assert_eq!(fold_romanized("lu:"), "lv");
+}
+
+#[test]
+fn romaji_search_finds_kana_keys() {
+    let dir = tempfile::tempdir().unwrap();
+    let optimized = common::optimized(
+        &common::writer_with_keys(&["たべもの", "たべる", "とうきょう", "食べる"]),
+        dir.path(),
+    );
+    assert!(matches!(
+        optimized.search_keys_romanized("tabe"),
+        Err(MDictError::UnsupportedFeature(_))
+    ));
+
+    let path = dir.path().join("index.romaji.fst");
+    optimized
+        .build_romanized_index(
+            path.to_string_lossy().to_string(),
+            RomanizationScheme::Romaji,
+        )
+        .unwrap();
+    assert!(optimized.has_romanized_index());
+    assert_eq!(search(&optimized, "tabe"), vec!["たべもの", "たべる"]);
+    assert_eq!(search(&optimized, "TABERU"), vec!["たべる"]);
+    assert_eq!(search(&optimized, "tokyo"), vec!["とうきょう"]);
+    assert_eq!(search(&optimized, "toukyou"), vec!["とうきょう"]);
+    assert!(search(&optimized, "sushi").is_empty());
+
+    let reopened = create_mdict_optimized_from_fst(
+        dir.path().join("index.fst").to_string_lossy().to_string(),
+        dir.path()
+            .join("readings.dat")
+            .to_string_lossy()
+            .to_string(),
+        dir.path().join("records.dat").to_string_lossy().to_string(),
+    )
+    .unwrap();
+    reopened
+        .load_romanized_index(path.to_string_lossy().to_string())
+        .unwrap();
+    let hit = reopened.search_keys_romanized("taberu").unwrap().remove(0);
+    assert_eq!(reopened.record_at(hit).unwrap(), "<p>たべる</p>".as_bytes());
+}
+
+#[test]
+fn pinyin_search_ignores_tones_and_survives_bundles() {
+    let dir = tempfile::tempdir().unwrap();
+    let optimized = common::optimized(
+        &common::writer_with_keys(&["hǎo", "nǐ hǎo", "nǐ men"]),
+        dir.path(),
+    );
+    let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+    optimized
+        .build_romanized_index(path("index.pinyin.fst"), RomanizationScheme::Pinyin)
+        .unwrap();
+
+    assert_eq!(search(&optimized, "ni"), vec!["nǐ hǎo", "nǐ men"]);
+    assert_eq!(search(&optimized, "ni3 hao3"), vec!["nǐ hǎo"]);
+    assert_eq!(search(&optimized, "nǐh"), vec!["nǐ hǎo"]);
+
+    optimized.save_bundle(path("dict.mdopt")).unwrap();
+    let opened = open_mdict_optimized_bundle(path("dict.mdopt")).unwrap();
+    assert!(opened.has_romanized_index());
+    assert_eq!(search(&opened, "hao"), vec!["hǎo"]);
+}
@@ -0,0 +1,351 @@
+#![cfg(all(feature = "mmap", feature = "zstd"))]
+
+use std::path::Path;
+
+use mdict_tools::error::MDictError;
+use mdict_tools::mdict_optimized::{create_mdict_optimized_from_fst, open_mdict_optimized_bundle};
+use mdict_tools::packed_storage::MAGIC;
+use mdict_tools::synth::{SynthDict, SynthDictBuilder};
+use mdict_tools::types::{KeyBlock, PrefixSearchPage};
+use mdict_tools::MdictOptimized;
+
+mod common;
+
+use common::key_texts;
+
+fn synth_dict() -> SynthDict {
+    common::synth(SynthDictBuilder::entries(30).entries_per_key_block(7))
+}
+
+fn optimized(dict: &SynthDict, dir: &Path) -> MdictOptimized {
+    let mdx_path = dir.join("synth.mdx");
+    dict.write_to(&mdx_path).expect("write synthetic dictionary");
+    common::optimized_from_bundle(&common::open_bundle(&mdx_path), dir)
+}
+
+#[test]
+fn fuzzy_search_matches_between_legacy_and_fst() {
+    let dict = synth_dict();
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let optimized = optimized(&dict, dir.path());
+    let mut mdict = dict.open().expect("open synthetic dictionary");
+
+    let legacy = key_texts(mdict.search_keys_fuzzy("word000010", 1).unwrap());
+    let fst = key_texts(optimized.search_keys_fuzzy("word000010", 1).unwrap());
+
+    // word000010..=word000019 plus word000000 and word000020.
+    assert_eq!(legacy.len(), 12);
+    assert_eq!(legacy, fst);
+}
+
+#[test]
+fn fuzzy_search_tolerates_typos() {
+    let dict = synth_dict();
+    let mut mdict = dict.open().expect("open synthetic dictionary");
+
+    assert!(mdict.search_keys_fuzzy("wrod000003", 1).unwrap().is_empty());
+    let keys = key_texts(mdict.search_keys_fuzzy("wrod000003", 2).unwrap());
+    assert!(keys.contains(&"word000003".to_string()));
+    assert_eq!(
+        key_texts(mdict.search_keys_fuzzy("word000003", 0).unwrap()),
+        vec!["word000003".to_string()]
+    );
+}
+
+#[test]
+fn glob_search_matches_between_legacy_and_fst() {
+    let dict = synth_dict();
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let optimized = optimized(&dict, dir.path());
+    let mut mdict = dict.open().expect("open synthetic dictionary");
+
+    for (pattern, expected) in [
+        ("word00001?", 10),
+        ("word0000*5", 3),
+        ("*2?", 10),
+        ("word000007", 1),
+        ("nope*", 0),
+    ] {
+        let legacy = key_texts(mdict.search_keys_glob(pattern).unwrap());
+        let fst = key_texts(optimized.search_keys_glob(pattern).unwrap());
+        assert_eq!(legacy.len(), expected, "pattern {}", pattern);
+        assert_eq!(legacy, fst, "pattern {}", pattern);
+    }
+}
+
+#[test]
+fn glob_search_keeps_matches_that_share_a_record_with_earlier_keys() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let optimized = common::optimized(
+        &common::writer_with_entries(&[("cat", "<p>feline</p>"), ("kitty", "@@@LINK=cat")]),
+        dir.path(),
+    );
+
+    // "cat" sorts first and shares kitty's value without matching.
+    assert_eq!(key_texts(optimized.search_keys_glob("*tty").unwrap()), vec!["kitty"]);
+    assert_eq!(key_texts(optimized.search_keys_glob("*").unwrap()), vec!["cat"]);
+}
+
+#[test]
+fn suffix_search_matches_trailing_glob() {
+    let dict = synth_dict();
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let optimized = optimized(&dict, dir.path());
+    let suffix_path = dir.path().join("index.rev.fst");
+
+    assert!(!optimized.has_suffix_index());
+    assert!(optimized.search_keys_suffix("7").is_err());
+    optimized
+        .build_suffix_index(suffix_path.to_string_lossy().to_string())
+        .unwrap();
+    assert!(optimized.has_suffix_index());
+
+    for suffix in ["7", "17", "word000021", "x", ""] {
+        let glob = key_texts(optimized.search_keys_glob(&format!("*{}", suffix)).unwrap());
+        let by_suffix = key_texts(optimized.search_keys_suffix(suffix).unwrap());
+        assert_eq!(glob, by_suffix, "suffix {:?}", suffix);
+    }
+}
+
+#[test]
+fn suffix_index_can_be_reloaded() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let built = common::optimized(
+        &common::writer_with_keys(&["来る", "行く", "見る", "食べた", "食べる"]),
+        dir.path(),
+    );
+    let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+    built.build_suffix_index(path("index.rev.fst")).unwrap();
+
+    let reopened = create_mdict_optimized_from_fst(
+        path("index.fst"),
+        path("readings.dat"),
+        path("records.dat"),
+    )
+    .unwrap();
+    reopened.load_suffix_index(path("index.rev.fst")).unwrap();
+
+    let matches = reopened.search_keys_suffix("る").unwrap();
+    assert_eq!(key_texts(matches.clone()), vec!["来る", "見る", "食べる"]);
+    assert_eq!(
+        reopened.record_at(matches[2].clone()).unwrap(),
+        "<p>食べる</p>".as_bytes()
+    );
+    assert_eq!(
+        key_texts(reopened.search_keys_suffix("べた").unwrap()),
+        vec!["食べた"]
+    );
+}
+
+fn all_pages_backward(optimized: &MdictOptimized, last: PrefixSearchPage) -> Vec<KeyBlock> {
+    let mut pages = vec![last.results];
+    let mut cursor = last.prev_cursor;
+    while let Some(before) = cursor {
+        let page = optimized.prefix_search_prev_page(before).unwrap();
+        pages.push(page.results);
+        cursor = page.prev_cursor;
+    }
+    pages.into_iter().rev().flatten().collect()
+}
+
+#[test]
+fn prev_page_walks_back_to_the_first_page() {
+    let dict = synth_dict();
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let optimized = optimized(&dict, dir.path());
+
+    let mut page = optimized.set_search_prefix_paged("word", 7).unwrap();
+    assert!(page.prev_cursor.is_none());
+    let mut forward = page.results.clone();
+    while let Some(cursor) = page.next_cursor.take() {
+        page = optimized.prefix_search_next_page(cursor).unwrap();
+        assert!(page.prev_cursor.is_some());
+        forward.extend(page.results.clone());
+    }
+    assert_eq!(forward.len(), 30);
+    assert_eq!(page.results.len(), 2);
+
+    let backward = all_pages_backward(&optimized, page);
+    assert_eq!(key_texts(backward), key_texts(forward));
+}
+
+#[test]
+fn prev_page_keeps_duplicate_keys() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let optimized = common::optimized(
+        &common::writer_with_entries(&[
+            ("ka", "1"),
+            ("kb", "2"),
+            ("kb", "3"),
+            ("kb", "4"),
+            ("kc", "5"),
+        ]),
+        dir.path(),
+    );
+
+    let mut page = optimized.set_search_prefix_paged("k", 2).unwrap();
+    let mut forward = page.results.clone();
+    while let Some(cursor) = page.next_cursor.take() {
+        page = optimized.prefix_search_next_page(cursor).unwrap();
+        forward.extend(page.results.clone());
+    }
+    assert_eq!(forward.len(), 5);
+
+    let backward = all_pages_backward(&optimized, page);
+    let ids = |keys: &[KeyBlock]| keys.iter().map(|k| k.key_id).collect::<Vec<_>>();
+    assert_eq!(ids(&backward), ids(&forward));
+}
+
+#[test]
+fn prefix_results_can_be_read_backward() {
+    let dict = synth_dict();
+    let mut mdict = dict.open().expect("open synthetic dictionary");
+    let mut results = mdict.search_keys_prefix("word00001").unwrap();
+
+    assert!(results.prev().unwrap().is_none());
+    results.seek_end();
+    assert_eq!(results.prev().unwrap().unwrap().key_text, "word000019");
+    assert_eq!(
+        key_texts(results.take_back(3).unwrap()),
+        vec!["word000016", "word000017", "word000018"]
+    );
+    assert_eq!(key_texts(results.take_back(10).unwrap()).len(), 6);
+    assert!(results.take_back(1).unwrap().is_empty());
+    assert_eq!(results.next().unwrap().unwrap().key_text, "word000010");
+}
+
+#[test]
+fn bundle_pages_through_every_entry_by_index() {
+    let dict = synth_dict();
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let mdx_path = dir.path().join("synth.mdx");
+    dict.write_to(&mdx_path)
+        .expect("write synthetic dictionary");
+    let bundle = common::open_bundle(&mdx_path);
+
+    assert_eq!(bundle.total_entries(), 30);
+    let mut browsed = Vec::new();
+    for start in (0..bundle.total_entries()).step_by(8) {
+        browsed.extend(bundle.entries_page(start, 8).unwrap());
+    }
+    let expected: Vec<String> = dict.entries.iter().map(|(key, _)| key.clone()).collect();
+    assert_eq!(key_texts(browsed), expected);
+
+    assert_eq!(bundle.entries_page(28, 8).unwrap().len(), 2);
+    assert!(bundle.entries_page(30, 8).unwrap().is_empty());
+    assert!(bundle.entries_page(5, 0).unwrap().is_empty());
+    assert_eq!(bundle.entries_page(29, u64::MAX).unwrap().len(), 1);
+}
+
+#[test]
+fn paged_search_reports_total_results() {
+    let dict = synth_dict();
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let optimized = optimized(&dict, dir.path());
+
+    assert_eq!(optimized.count_prefix("word00001"), 10);
+    assert_eq!(optimized.count_prefix("word"), 30);
+    assert_eq!(optimized.count_prefix("nope"), 0);
+
+    let first = optimized.set_search_prefix_paged("word00002", 4).unwrap();
+    assert_eq!(first.total_results, Some(10));
+    let second = optimized
+        .prefix_search_next_page(first.next_cursor.unwrap())
+        .unwrap();
+    assert_eq!(second.total_results, Some(10));
+    assert_eq!(optimized.len(), 10);
+
+    optimized.set_search_prefix_paged("word000003", 4).unwrap();
+    assert_eq!(optimized.len(), 1);
+}
+
+#[test]
+fn sidecars_are_packed_storage() {
+    let dict = synth_dict();
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let optimized = optimized(&dict, dir.path());
+    let mut mdict = dict.open().expect("open synthetic dictionary");
+
+    for name in ["readings.dat", "records.dat"] {
+        let bytes = std::fs::read(dir.path().join(name)).unwrap();
+        assert_eq!(&bytes[..8], &MAGIC, "{}", name);
+    }
+
+    let page = optimized.set_search_prefix_paged("word", 100).unwrap();
+    assert_eq!(page.results.len(), 30);
+    for (index, key) in page.results.into_iter().enumerate() {
+        assert_eq!(
+            optimized.get_readings(key.clone()).unwrap(),
+            vec![key.key_text.clone()]
+        );
+        assert_eq!(
+            optimized.record_at(key).unwrap(),
+            mdict.record_at_index(index).unwrap()
+        );
+    }
+}
+
+#[test]
+fn unpacked_readings_file_is_rejected() {
+    let dict = synth_dict();
+    let dir = tempfile::tempdir().expect("create temp dir");
+    optimized(&dict, dir.path());
+    let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+    std::fs::write(path("legacy.dat"), b"\x05\0\0\0\0\0\0\0\0\0\0\0word0").unwrap();
+
+    let err =
+        create_mdict_optimized_from_fst(path("index.fst"), path("legacy.dat"), path("records.dat"))
+            .err()
+            .expect("legacy readings file");
+    assert!(matches!(err, MDictError::InvalidFormat(_)), "{:?}", err);
+}
+
+#[test]
+fn optimized_bundle_round_trips_in_one_file() {
+    let dict = synth_dict();
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let built = optimized(&dict, dir.path());
+    let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+    built.build_suffix_index(path("index.rev.fst")).unwrap();
+    built.save_bundle(path("synth.mdopt")).unwrap();
+
+    for name in ["index.fst", "readings.dat", "records.dat", "index.rev.fst"] {
+        std::fs::remove_file(dir.path().join(name)).unwrap();
+    }
+    let opened = open_mdict_optimized_bundle(path("synth.mdopt")).unwrap();
+
+    assert!(opened.has_suffix_index());
+    assert_eq!(
+        key_texts(opened.search_keys_suffix("9").unwrap()),
+        key_texts(built.search_keys_suffix("9").unwrap())
+    );
+    let page = opened.set_search_prefix_paged("word", 100).unwrap();
+    assert_eq!(page.results.len(), 30);
+    for key in page.results {
+        assert_eq!(
+            opened.record_at(key.clone()).unwrap(),
+            built.record_at(key).unwrap()
+        );
+    }
+
+    // Saving over the file a bundle was opened from replaces it safely.
+    opened.save_bundle(path("synth.mdopt")).unwrap();
+    let reopened = open_mdict_optimized_bundle(path("synth.mdopt")).unwrap();
+    assert_eq!(reopened.count_prefix("word00001"), 10);
+}
+
+#[test]
+fn non_bundle_file_is_rejected() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let path = dir.path().join("bogus.mdopt");
+    std::fs::write(&path, b"MDOPTBDL\x01\0\0\0\x01\0\0\0").unwrap();
+
+    let err = open_mdict_optimized_bundle(path.to_string_lossy().to_string())
+        .err()
+        .expect("truncated bundle");
+    assert!(
+        matches!(err, MDictError::InvalidFormat(_) | MDictError::Io(_)),
+        "{:?}",
+        err
+    );
+}
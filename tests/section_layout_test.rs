@@ -0,0 +1,84 @@
+use std::ops::Range;
+
+use mdict_tools::format;
+use mdict_tools::synth::{SynthDict, SynthDictBuilder};
+use mdict_tools::types::MdictVersion;
+
+mod common;
+
+fn synth(version: MdictVersion) -> SynthDict {
+    common::synth(
+        SynthDictBuilder::entries(120)
+            .version(version)
+            .entries_per_key_block(16)
+            .entries_per_record_block(8),
+    )
+}
+
+fn slice(dict: &SynthDict, range: Range<u64>) -> &[u8] {
+    &dict.bytes[range.start as usize..range.end as usize]
+}
+
+#[test]
+fn sections_tile_the_file() {
+    for (version, preamble, pair) in [
+        (MdictVersion::V1, 16, 4),
+        (MdictVersion::V2, 44, 8),
+        (MdictVersion::MDD, 44, 8),
+    ] {
+        let dict = synth(version);
+        let mut mdict = dict.open().unwrap();
+        let layout = mdict.section_layout().unwrap();
+        let header_size = 4 + u32::from_be_bytes(dict.bytes[..4].try_into().unwrap()) as u64 + 4;
+
+        assert_eq!(layout.header, 0..header_size, "{:?}", version);
+        assert_eq!(layout.key_preamble.end - layout.key_preamble.start, preamble);
+        assert_eq!(layout.key_info.start, layout.key_preamble.end);
+        assert_eq!(layout.key_blocks.start, layout.key_info.end);
+        assert_eq!(layout.record_preamble.start, layout.key_blocks.end);
+        assert_eq!(layout.record_preamble.end - layout.record_preamble.start, 4 * pair);
+        assert_eq!(layout.record_index.start, layout.record_preamble.end);
+        assert_eq!(layout.record_data.start, layout.record_index.end);
+        assert_eq!(layout.end(), dict.bytes.len() as u64);
+
+        let key_section = &mdict.key_block_index.key_section;
+        assert_eq!(
+            layout.key_blocks.end - layout.key_blocks.start,
+            key_section.key_blocks_size
+        );
+        let records = mdict.record_section().unwrap();
+        assert_eq!(
+            layout.record_index.end - layout.record_index.start,
+            records.num_record_blocks * 2 * pair
+        );
+    }
+}
+
+#[test]
+fn block_ranges_hold_whole_blocks() {
+    let dict = synth(MdictVersion::V2);
+    let mut mdict = dict.open().unwrap();
+    let layout = mdict.section_layout().unwrap();
+    let records = mdict.record_section().unwrap();
+    let key_section = &mdict.key_block_index.key_section;
+
+    for (block, info) in key_section.key_info_blocks.iter().enumerate() {
+        let range = layout.key_block(key_section, block).unwrap();
+        let size = info.decompressed_size as usize;
+        let decoded = format::decode_format_block_sized(slice(&dict, range), size).unwrap();
+        assert_eq!(decoded.len(), size);
+    }
+    assert!(layout.key_block(key_section, key_section.key_info_blocks.len()).is_none());
+
+    let blocks = records.num_record_blocks as usize;
+    assert_eq!(blocks, 15);
+    for block in 0..blocks {
+        let range = layout.record_block(&records, block).unwrap();
+        let sizes = &records.record_index_prefix_sum;
+        let size = (sizes[block + 1].uncompressed_size - sizes[block].uncompressed_size) as usize;
+        let decoded = format::decode_format_block_sized(slice(&dict, range), size).unwrap();
+        assert_eq!(decoded.len(), size);
+    }
+    assert_eq!(layout.record_block(&records, blocks - 1).unwrap().end, layout.end());
+    assert!(layout.record_block(&records, blocks).is_none());
+}
@@ -0,0 +1,58 @@
+use std::io::Cursor;
+use std::sync::Arc;
+use std::thread;
+
+use mdict_tools::synth::SynthDictBuilder;
+use mdict_tools::MdictShared;
+
+#[test]
+fn concurrent_lookups_match_serial_results() {
+    let dict = SynthDictBuilder::entries(200)
+        .link_every(5)
+        .entries_per_key_block(9)
+        .entries_per_record_block(7)
+        .build()
+        .expect("build synthetic dictionary");
+    let shared = Arc::new(MdictShared::new(
+        dict.open().expect("open synthetic dictionary"),
+    ));
+    let entries = Arc::new(dict.entries);
+    assert_eq!(shared.num_entries(), 200);
+
+    let workers: Vec<_> = (0..8)
+        .map(|worker| {
+            let shared = Arc::clone(&shared);
+            let entries = Arc::clone(&entries);
+            thread::spawn(move || {
+                for step in 0..200 {
+                    let index = (step * 7 + worker * 31) % entries.len();
+                    let (key, record) = &entries[index];
+
+                    let key_block = shared.get(index).unwrap().expect("key at index");
+                    assert_eq!(&key_block.key_text, key);
+                    assert_eq!(shared.index_for(key).unwrap(), Some(index));
+                    assert_eq!(&shared.record_at_key_block(&key_block).unwrap(), record);
+                }
+            })
+        })
+        .collect();
+
+    for worker in workers {
+        worker.join().expect("worker panicked");
+    }
+}
+
+#[test]
+fn handles_share_parsed_sections() {
+    let dict = SynthDictBuilder::entries(30)
+        .build()
+        .expect("build synthetic dictionary");
+    let mut mdict = dict.open().expect("open synthetic dictionary");
+    let mut other = mdict.with_reader(Cursor::new(dict.bytes.clone()));
+
+    assert!(Arc::ptr_eq(
+        &mdict.record_section().unwrap(),
+        &other.record_section().unwrap()
+    ));
+    assert_eq!(other.record_at_index(3).unwrap(), dict.entries[3].1);
+}
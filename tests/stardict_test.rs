@@ -0,0 +1,175 @@
+#![cfg(feature = "stardict")]
+
+use std::path::Path;
+
+use miniz_oxide::deflate::core::{
+    compress_to_output, create_comp_flags_from_zip_params, CompressorOxide, TDEFLFlush,
+};
+use mdict_tools::stardict::{StarDict, StarDictField};
+
+const CHUNK_LEN: usize = 16;
+
+/// `(word, data)` pairs in StarDict order, data with `sametypesequence=m`.
+const WORDS: [(&str, &str); 4] = [
+    ("Apple", "a fruit"),
+    ("apple", "the fruit, lower case"),
+    ("banana", "a long yellow fruit\nthat grows in bunches"),
+    ("cat", "an animal <small>"),
+];
+
+fn write_dictionary(dir: &Path, dict: &[u8], dict_name: &str, extra_ifo: &str) -> StarDict {
+    let mut idx = Vec::new();
+    let mut offset = 0u32;
+    for (word, data) in WORDS {
+        idx.extend_from_slice(word.as_bytes());
+        idx.push(0);
+        idx.extend_from_slice(&offset.to_be_bytes());
+        idx.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += data.len() as u32;
+    }
+    let mut syn = b"kitty\0".to_vec();
+    syn.extend_from_slice(&3u32.to_be_bytes());
+
+    std::fs::write(
+        dir.join("dict.ifo"),
+        format!(
+            "StarDict's dict ifo file\nversion=2.4.2\nbookname=Test\nwordcount=4\n\
+             synwordcount=1\nidxfilesize={}\n{}",
+            idx.len(),
+            extra_ifo
+        ),
+    )
+    .unwrap();
+    std::fs::write(dir.join("dict.idx"), idx).unwrap();
+    std::fs::write(dir.join("dict.syn"), syn).unwrap();
+    std::fs::write(dir.join(dict_name), dict).unwrap();
+    StarDict::open(dir.join("dict.ifo")).unwrap()
+}
+
+fn plain_data() -> Vec<u8> {
+    WORDS.iter().flat_map(|(_, data)| data.bytes()).collect()
+}
+
+/// `data` as dictzip writes it: one gzip member whose deflate stream is
+/// flushed every `CHUNK_LEN` bytes, with the chunk sizes in an `RA` field.
+fn dictzip(data: &[u8]) -> Vec<u8> {
+    let mut compressor = CompressorOxide::new(create_comp_flags_from_zip_params(6, -15, 0));
+    let chunks = data.chunks(CHUNK_LEN).collect::<Vec<_>>();
+    let mut deflated = Vec::new();
+    let mut sizes = Vec::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let flush = if index + 1 == chunks.len() {
+            TDEFLFlush::Finish
+        } else {
+            TDEFLFlush::Full
+        };
+        let before = deflated.len();
+        compress_to_output(&mut compressor, chunk, flush, |out| {
+            deflated.extend_from_slice(out);
+            true
+        });
+        sizes.push((deflated.len() - before) as u16);
+    }
+
+    let mut ra = Vec::new();
+    for value in [1u16, CHUNK_LEN as u16, sizes.len() as u16]
+        .into_iter()
+        .chain(sizes)
+    {
+        ra.extend_from_slice(&value.to_le_bytes());
+    }
+    let mut extra = b"RA".to_vec();
+    extra.extend_from_slice(&(ra.len() as u16).to_le_bytes());
+    extra.extend_from_slice(&ra);
+
+    let mut gzip = vec![0x1f, 0x8b, 0x08, 0x04, 0, 0, 0, 0, 0, 0xff];
+    gzip.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+    gzip.extend_from_slice(&extra);
+    gzip.extend_from_slice(&deflated);
+    gzip.extend_from_slice(&[0; 8]);
+    gzip
+}
+
+fn key_texts(dict: &StarDict, prefix: &str) -> Vec<String> {
+    dict.prefix_search(prefix, 10)
+        .into_iter()
+        .map(|key| key.key_text)
+        .collect()
+}
+
+#[test]
+fn reads_plain_dictionaries() {
+    let dir = tempfile::tempdir().unwrap();
+    let dict = write_dictionary(dir.path(), &plain_data(), "dict.dict", "sametypesequence=m\n");
+    assert_eq!(dict.len(), 4);
+    assert_eq!(dict.info().book_name, "Test");
+    assert_eq!(dict.info().same_type_sequence.as_deref(), Some("m"));
+
+    assert_eq!(key_texts(&dict, "APP"), ["Apple", "apple"]);
+    assert_eq!(key_texts(&dict, "b"), ["banana"]);
+    assert!(key_texts(&dict, "dog").is_empty());
+
+    assert_eq!(dict.lookup("apple").unwrap(), ["the fruit, lower case"]);
+    assert_eq!(
+        dict.lookup("banana").unwrap(),
+        ["a long yellow fruit<br>that grows in bunches"]
+    );
+    assert_eq!(dict.lookup("kitty").unwrap(), ["an animal &lt;small&gt;"]);
+    assert!(dict.lookup("Cat").unwrap().is_empty());
+}
+
+#[test]
+fn reads_dictzip_data_across_chunks() {
+    let dir = tempfile::tempdir().unwrap();
+    let data = plain_data();
+    let dict = write_dictionary(
+        dir.path(),
+        &dictzip(&data),
+        "dict.dict.dz",
+        "sametypesequence=m\n",
+    );
+
+    for (word, expected) in WORDS {
+        let key = dict.get_all(word).remove(0);
+        assert_eq!(dict.record_at_key_block(&key).unwrap(), expected.as_bytes());
+    }
+}
+
+#[test]
+fn splits_typed_fields() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("dict.ifo"),
+        "StarDict's dict ifo file\nversion=3.0.0\nbookname=Typed\nwordcount=1\n",
+    )
+    .unwrap();
+    let mut data = b"tkat\0h<b>cat</b>\0W".to_vec();
+    data.extend_from_slice(&3u32.to_be_bytes());
+    data.extend_from_slice(b"RIF");
+    let mut idx = b"cat\0".to_vec();
+    idx.extend_from_slice(&0u32.to_be_bytes());
+    idx.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    std::fs::write(dir.path().join("dict.idx"), idx).unwrap();
+    std::fs::write(dir.path().join("dict.dict"), &data).unwrap();
+
+    let dict = StarDict::open(dir.path().join("dict.ifo")).unwrap();
+    let key = dict.get_all("cat").remove(0);
+    assert_eq!(
+        dict.record_fields(&key).unwrap(),
+        [
+            StarDictField {
+                kind: 't',
+                data: b"kat".to_vec()
+            },
+            StarDictField {
+                kind: 'h',
+                data: b"<b>cat</b>".to_vec()
+            },
+            StarDictField {
+                kind: 'W',
+                data: b"RIF".to_vec()
+            },
+        ]
+    );
+    assert_eq!(dict.record_html(&key).unwrap(), "kat<br><b>cat</b>");
+}
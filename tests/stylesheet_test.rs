@@ -0,0 +1,50 @@
+use std::io::Cursor;
+
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::stylesheet::StyleSheet;
+use mdict_tools::Mdict;
+
+const STYLES: &str = "1\n<b>\n</b>\n2\n<font color=\"red\">\n</font>\n";
+
+#[test]
+fn stylesheet_groups_are_parsed() {
+    let sheet = StyleSheet::parse("1\n<b>\n</b>\r\n2\n<i>\n</i>\nnot-a-number\nx\ny\n3\n<u>");
+
+    assert_eq!(sheet.len(), 3);
+    assert_eq!(sheet.get(1), Some(("<b>", "</b>")));
+    assert_eq!(sheet.get(2), Some(("<i>", "</i>")));
+    assert_eq!(sheet.get(3), Some(("<u>", "")));
+    assert_eq!(sheet.get(4), None);
+}
+
+#[test]
+fn markers_are_replaced_with_style_markup() {
+    let sheet = StyleSheet::parse(STYLES);
+
+    assert_eq!(
+        sheet.apply("head`1`bold`2`red"),
+        "head<b>bold</b><font color=\"red\">red</font>"
+    );
+    assert_eq!(
+        sheet.apply("`1`line one \n`2`two"),
+        "<b>line one</b>\r\n<font color=\"red\">two</font>"
+    );
+    assert_eq!(sheet.apply("`9`unknown`1`x"), "`9`unknown<b>x</b>");
+    assert_eq!(sheet.apply("no markers"), "no markers");
+    assert_eq!(StyleSheet::default().apply("`1`x"), "`1`x");
+}
+
+#[test]
+fn records_render_with_the_header_stylesheet() {
+    let mut writer = MdxWriter::new().stylesheet(STYLES);
+    writer.add("apple", "`1`apple`2`a fruit").unwrap();
+    let mut mdict = Mdict::new(Cursor::new(writer.to_bytes().unwrap())).unwrap();
+
+    assert_eq!(mdict.key_block_index.header.stylesheet().len(), 2);
+    assert_eq!(mdict.metadata().stylesheet.as_deref(), Some(STYLES));
+    let key = mdict.get(0).unwrap().unwrap();
+    assert_eq!(
+        mdict.render_record_styled(&key).unwrap(),
+        "<b>apple</b><font color=\"red\">a fruit</font>"
+    );
+}
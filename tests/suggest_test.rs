@@ -0,0 +1,61 @@
+use std::io::Cursor;
+
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::suggest::{
+    SCORE_EXACT, SCORE_FOLDED_PREFIX, SCORE_NEAR, SCORE_PER_EDIT, SCORE_PREFIX,
+};
+use mdict_tools::Mdict;
+
+fn dictionary(keys: &[&str]) -> Mdict<Cursor<Vec<u8>>> {
+    let mut keys = keys.to_vec();
+    keys.sort_by_key(|key| key.to_lowercase());
+    let mut writer = MdxWriter::new().entries_per_key_block(2);
+    for key in keys {
+        writer.add(key, &format!("<b>{}</b>", key)).unwrap();
+    }
+    Mdict::new(Cursor::new(writer.to_bytes().unwrap())).unwrap()
+}
+
+fn ranked(mdict: &mut Mdict<Cursor<Vec<u8>>>, query: &str, limit: usize) -> Vec<(String, u32)> {
+    mdict
+        .suggest(query, limit)
+        .unwrap()
+        .into_iter()
+        .map(|s| (s.key_text, s.score))
+        .collect()
+}
+
+#[test]
+fn suggestions_are_ranked_by_match_quality() {
+    let mut mdict = dictionary(&[
+        "apple", "applet", "Apples", "apply", "ample", "apl", "banana", "appl",
+    ]);
+
+    assert_eq!(
+        ranked(&mut mdict, "appl", 10),
+        vec![
+            ("appl".to_string(), SCORE_EXACT),
+            ("apple".to_string(), SCORE_PREFIX),
+            ("applet".to_string(), SCORE_PREFIX),
+            ("apply".to_string(), SCORE_PREFIX),
+            ("Apples".to_string(), SCORE_FOLDED_PREFIX),
+            ("apl".to_string(), SCORE_NEAR - SCORE_PER_EDIT),
+        ]
+    );
+}
+
+#[test]
+fn near_matches_catch_typos_and_limit_applies() {
+    let mut mdict = dictionary(&["receive", "recipe", "deceive", "reception"]);
+
+    let suggestions = ranked(&mut mdict, "recieve", 10);
+    assert_eq!(
+        suggestions[0],
+        ("receive".to_string(), SCORE_NEAR - 2 * SCORE_PER_EDIT)
+    );
+    assert!(suggestions.iter().all(|(key, _)| key != "deceive"));
+
+    assert_eq!(ranked(&mut mdict, "rec", 2).len(), 2);
+    assert!(ranked(&mut mdict, "", 5).is_empty());
+    assert!(ranked(&mut mdict, "zzz", 5).is_empty());
+}
@@ -0,0 +1,151 @@
+use mdict_tools::synth::SynthDictBuilder;
+use mdict_tools::types::{Encoding, MdictVersion};
+
+fn assert_round_trip(version: MdictVersion, encoding: Encoding) {
+    let dict = SynthDictBuilder::entries(150)
+        .version(version)
+        .encoding(encoding)
+        .entries_per_key_block(16)
+        .entries_per_record_block(7)
+        .build()
+        .expect("build synthetic dictionary");
+    let mut mdict = dict.open().expect("open synthetic dictionary");
+
+    assert_eq!(
+        mdict.key_block_index.key_section.num_entries,
+        dict.entries.len() as u64
+    );
+    assert_eq!(mdict.key_block_index.header.get_version(), version);
+
+    for (i, (key, record)) in dict.entries.iter().enumerate() {
+        let key_block = mdict.get(i).expect("get key").expect("key exists");
+        assert_eq!(&key_block.key_text, key, "key mismatch at {}", i);
+        let actual = mdict.record_at_index(i).expect("get record");
+        assert_eq!(&actual, record, "record mismatch for '{}'", key);
+    }
+}
+
+#[test]
+fn synth_v2_utf8_round_trip() {
+    assert_round_trip(MdictVersion::V2, Encoding::Utf8);
+}
+
+#[test]
+fn synth_v2_utf16_round_trip() {
+    assert_round_trip(MdictVersion::V2, Encoding::Utf16LE);
+}
+
+#[test]
+fn synth_v1_utf8_round_trip() {
+    assert_round_trip(MdictVersion::V1, Encoding::Utf8);
+}
+
+#[test]
+fn synth_v1_utf16_round_trip() {
+    assert_round_trip(MdictVersion::V1, Encoding::Utf16LE);
+}
+
+#[test]
+fn synth_mdd_records_are_not_truncated() {
+    assert_round_trip(MdictVersion::MDD, Encoding::Utf16LE);
+}
+
+#[test]
+fn synth_prefix_search_spans_blocks() {
+    let dict = SynthDictBuilder::entries(64)
+        .entries_per_key_block(8)
+        .build()
+        .expect("build synthetic dictionary");
+    let mut mdict = dict.open().expect("open synthetic dictionary");
+
+    let keys = mdict
+        .search_keys_prefix("word00001")
+        .expect("search prefix")
+        .collect_to_vec()
+        .expect("collect keys");
+    let expected = (10..20)
+        .map(|i| format!("word{:06}", i))
+        .collect::<Vec<_>>();
+    let actual = keys.into_iter().map(|kb| kb.key_text).collect::<Vec<_>>();
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn synth_output_is_deterministic() {
+    let a = SynthDictBuilder::entries(40).link_every(5).build().unwrap();
+    let b = SynthDictBuilder::entries(40).link_every(5).build().unwrap();
+    assert_eq!(a.bytes, b.bytes);
+    assert_eq!(a.entries[5].1, b"@@@LINK=word000004".to_vec());
+}
+
+#[test]
+fn record_text_is_decoded_with_header_encoding() {
+    for encoding in [Encoding::Utf8, Encoding::Utf16LE] {
+        let dict = SynthDictBuilder::entries(5)
+            .encoding(encoding)
+            .build()
+            .expect("build synthetic dictionary");
+        let mut mdict = dict.open().expect("open synthetic dictionary");
+        let key_block = mdict.get(3).unwrap().unwrap();
+
+        let text = mdict
+            .record_text_at_key_block(&key_block)
+            .expect("record text");
+        assert_eq!(text, "<p class=\"def\">Definition of word000003 (#3)</p>");
+    }
+}
+
+#[test]
+fn synth_legacy_code_page_round_trips() {
+    for encoding in [Encoding::Gbk, Encoding::Big5, Encoding::Gb18030] {
+        assert_round_trip(MdictVersion::V2, encoding);
+        assert_round_trip(MdictVersion::V1, encoding);
+    }
+}
+
+#[test]
+fn iter_entries_matches_indexed_access() {
+    for version in [MdictVersion::V1, MdictVersion::V2, MdictVersion::MDD] {
+        let dict = SynthDictBuilder::entries(95)
+            .version(version)
+            .entries_per_key_block(8)
+            .entries_per_record_block(6)
+            .build()
+            .expect("build synthetic dictionary");
+        let mut mdict = dict.open().expect("open synthetic dictionary");
+
+        let entries = mdict
+            .iter_entries()
+            .collect::<Result<Vec<_>, _>>()
+            .expect("iterate entries");
+        assert_eq!(entries.len(), dict.entries.len());
+        for ((key_block, record), (key, expected)) in entries.iter().zip(&dict.entries) {
+            assert_eq!(&key_block.key_text, key);
+            assert_eq!(record, expected);
+        }
+    }
+}
+
+#[test]
+fn record_sizes_come_from_key_id_deltas() {
+    for version in [MdictVersion::V2, MdictVersion::MDD] {
+        let dict = SynthDictBuilder::entries(40)
+            .version(version)
+            .entries_per_record_block(6)
+            .build()
+            .expect("build synthetic dictionary");
+        let mut mdict = dict.open().expect("open synthetic dictionary");
+        let terminator_len = if version == MdictVersion::MDD { 0 } else { 2 };
+
+        for (i, (_, record)) in dict.entries.iter().enumerate() {
+            let key_block = mdict.get(i).unwrap().unwrap();
+            assert_eq!(
+                mdict.record_size_for(&key_block).unwrap(),
+                (record.len() + terminator_len) as u64,
+                "{:?} entry {}",
+                version,
+                i
+            );
+        }
+    }
+}
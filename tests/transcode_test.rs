@@ -0,0 +1,124 @@
+#![cfg(all(feature = "fs", feature = "lzo", feature = "zstd"))]
+
+use std::io::Cursor;
+
+use mdict_tools::mdx_conversion::transcode::{transcode, transcode_to_zstd};
+use mdict_tools::mdx_writer::{BlockCompression, MdxWriter};
+use mdict_tools::types::{Encoding, MdictVersion};
+use mdict_tools::Mdict;
+
+fn entries<R: std::io::Read + std::io::Seek>(mdict: &mut Mdict<R>) -> Vec<(String, Vec<u8>)> {
+    mdict
+        .iter_entries()
+        .map(|entry| {
+            let (key_block, record) = entry.unwrap();
+            (key_block.key_text, record)
+        })
+        .collect()
+}
+
+#[test]
+fn transcoded_mdx_keeps_entries_and_header() {
+    for (version, encoding) in [
+        (MdictVersion::V1, Encoding::Utf8),
+        (MdictVersion::V2, Encoding::Utf16LE),
+    ] {
+        let mut writer = MdxWriter::new()
+            .version(version)
+            .encoding(encoding)
+            .compression(BlockCompression::Lzo)
+            .title("Legacy")
+            .description("An old dictionary")
+            .stylesheet("1\n<b>\n</b>")
+            .compact(true)
+            .key_block_size(256)
+            .record_block_size(1024);
+        for i in 0..200 {
+            let key = format!("word{:03}", i);
+            writer.add(key.clone(), &format!("`1`{}`1` sense {}", key, i)).unwrap();
+            if i % 50 == 0 {
+                writer.add(key, "second sense").unwrap();
+            }
+        }
+        let mut source = Mdict::new(Cursor::new(writer.to_bytes().unwrap())).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("transcoded.mdx");
+        let stats = transcode_to_zstd(&mut source, &output).unwrap();
+        assert_eq!(stats.entries, 204);
+        assert_eq!(stats.output_bytes, std::fs::metadata(&output).unwrap().len());
+
+        let mut transcoded = Mdict::open(&output).unwrap();
+        assert_eq!(entries(&mut transcoded), entries(&mut source));
+        let header = &transcoded.key_block_index.header;
+        assert_eq!(header.get_version(), version);
+        assert_eq!(header.get_encoding(), encoding);
+        assert!(header.is_compact());
+        assert_eq!(header.get("StyleSheet").unwrap(), "1\n<b>\n</b>");
+        assert_eq!(transcoded.metadata(), source.metadata());
+    }
+}
+
+#[test]
+fn transcoded_mdd_keeps_binary_resources() {
+    let mut writer = MdxWriter::mdd().compression(BlockCompression::Zlib);
+    writer.add_raw("\\a.png", vec![0x89, b'P', b'N', b'G', 0, 0x0A, 0x00]).unwrap();
+    writer.add_raw("\\b.css", b"body { margin: 0 }".to_vec()).unwrap();
+    let mut source = Mdict::new(Cursor::new(writer.to_bytes().unwrap())).unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("transcoded.mdd");
+    let stats = transcode_to_zstd(&mut source, &output).unwrap();
+    assert_eq!(stats.entries, 2);
+
+    let mut transcoded = Mdict::open(&output).unwrap();
+    assert_eq!(entries(&mut transcoded), entries(&mut source));
+    assert_eq!(transcoded.key_block_index.header.get_version(), MdictVersion::MDD);
+}
+
+#[test]
+fn zstd_blocks_are_smaller_than_uncompressed() {
+    let mut writer = MdxWriter::new().compression(BlockCompression::None);
+    for i in 0..500 {
+        writer
+            .add(format!("entry{:04}", i), &"repetitive definition text ".repeat(20))
+            .unwrap();
+    }
+    let bytes = writer.to_bytes().unwrap();
+    let source_len = bytes.len() as u64;
+    let mut source = Mdict::new(Cursor::new(bytes)).unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("transcoded.mdx");
+    let stats = transcode_to_zstd(&mut source, &output).unwrap();
+    assert!(stats.output_bytes < source_len / 4);
+}
+
+#[test]
+fn zstd_dictionaries_transcode_back_to_stock_compression() {
+    let mut writer = MdxWriter::new()
+        .compression(BlockCompression::Zstd)
+        .record_block_size(512);
+    for i in 0..300 {
+        writer
+            .add(format!("entry{:04}", i), &format!("<p>definition {}</p>", i))
+            .unwrap();
+    }
+    let mut source = Mdict::new(Cursor::new(writer.to_bytes().unwrap())).unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    for (compression, block_type) in [(BlockCompression::Lzo, 1u8), (BlockCompression::Zlib, 2)] {
+        let output = dir.path().join(format!("{:?}.mdx", compression));
+        let stats = transcode(&mut source, &output, compression).unwrap();
+        assert_eq!(stats.entries, 300);
+
+        let mut transcoded = Mdict::open(&output).unwrap();
+        assert_eq!(entries(&mut transcoded), entries(&mut source));
+        let records = transcoded.record_section().unwrap();
+        let bytes = std::fs::read(&output).unwrap();
+        for start in &records.record_index_prefix_sum[..records.num_record_blocks as usize] {
+            let offset = (records.record_data_offset + start.compressed_size) as usize;
+            assert_eq!(bytes[offset], block_type, "{:?}", compression);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! `JsRangeSource` and the JavaScript `Mdict` export, run under node:
+//!
+//! ```sh
+//! CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER=wasm-bindgen-test-runner \
+//!     cargo test --target wasm32-unknown-unknown --no-default-features --features wasm \
+//!     --test wasm_test
+//! ```
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use std::cell::RefCell;
+use std::io::ErrorKind;
+use std::rc::Rc;
+
+use js_sys::{Function, Uint8Array};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+use mdict_tools::io::js::JsRangeSource;
+use mdict_tools::io::ByteSource;
+use mdict_tools::mdx_writer::MdxWriter;
+use mdict_tools::wasm::WasmMdict;
+
+/// `read(offset, length)` over `bytes`, and the ranges it was asked for.
+struct RangeReader {
+    function: Function,
+    reads: Rc<RefCell<Vec<(u64, u64)>>>,
+    _closure: Closure<dyn FnMut(f64, f64) -> Result<Uint8Array, JsValue>>,
+}
+
+fn range_reader(bytes: Vec<u8>) -> RangeReader {
+    let reads = Rc::new(RefCell::new(Vec::new()));
+    let log = Rc::clone(&reads);
+    let closure = Closure::wrap(Box::new(move |offset: f64, length: f64| {
+        let (offset, length) = (offset as usize, length as usize);
+        log.borrow_mut().push((offset as u64, length as u64));
+        match bytes.get(offset..offset + length) {
+            Some(range) => Ok(Uint8Array::from(range)),
+            None => Err(JsValue::from_str("range out of bounds")),
+        }
+    })
+        as Box<dyn FnMut(f64, f64) -> Result<Uint8Array, JsValue>>);
+    RangeReader {
+        function: closure.as_ref().unchecked_ref::<Function>().clone(),
+        reads,
+        _closure: closure,
+    }
+}
+
+fn dictionary() -> Vec<u8> {
+    let mut writer = MdxWriter::new().entries_per_record_block(2);
+    for (key, html) in [
+        ("apple", "<b>apple</b>"),
+        ("apply", "<b>apply</b>"),
+        ("banana", "<b>banana</b>"),
+        ("cherry", "<b>cherry</b>"),
+    ] {
+        writer.add(key, html).unwrap();
+    }
+    writer.to_bytes().unwrap()
+}
+
+#[wasm_bindgen_test]
+fn range_source_reads_through_the_js_function() {
+    let reader = range_reader((0..16).collect());
+    let source = JsRangeSource::new(16, reader.function.clone());
+    assert_eq!(source.size(), 16);
+
+    let mut buf = [0; 4];
+    source.read_exact_at(3, &mut buf).unwrap();
+    assert_eq!(buf, [3, 4, 5, 6]);
+    source.read_exact_at(16, &mut []).unwrap();
+    assert_eq!(*reader.reads.borrow(), [(3, 4)]);
+
+    let err = source.read_exact_at(14, &mut buf).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(reader.reads.borrow().len(), 1);
+}
+
+#[wasm_bindgen_test]
+fn range_source_rejects_bad_replies() {
+    let mut buf = [0; 4];
+
+    // Claims more bytes than `read` has, so `read` throws.
+    let reader = range_reader(vec![0; 8]);
+    let source = JsRangeSource::new(32, reader.function.clone());
+    let err = source.read_exact_at(16, &mut buf).unwrap_err();
+    assert!(err.to_string().contains("range out of bounds"), "{}", err);
+
+    let short = Function::new_with_args("offset, length", "return new Uint8Array(length - 1);");
+    let err = JsRangeSource::new(32, short)
+        .read_exact_at(0, &mut buf)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+
+    let not_bytes = Function::new_with_args("offset, length", "return [1, 2, 3, 4];");
+    let err = JsRangeSource::new(32, not_bytes)
+        .read_exact_at(0, &mut buf)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
+
+#[wasm_bindgen_test]
+fn dictionary_opens_from_a_range_reader() {
+    let bytes = dictionary();
+    let size = bytes.len();
+    let reader = range_reader(bytes.clone());
+    let mut mdict = WasmMdict::from_range_reader(size as f64, reader.function.clone()).unwrap();
+
+    assert_eq!(mdict.search_prefix("app", 10).unwrap(), ["apple", "apply"]);
+    assert_eq!(mdict.lookup("cherry").unwrap(), ["<b>cherry</b>"]);
+    assert!(mdict.lookup("durian").unwrap().is_empty());
+    assert!(reader
+        .reads
+        .borrow()
+        .iter()
+        .all(|&(_, length)| (length as usize) < size));
+
+    let mut in_memory = WasmMdict::from_bytes(bytes).unwrap();
+    assert_eq!(in_memory.lookup("banana").unwrap(), ["<b>banana</b>"]);
+    assert!(WasmMdict::from_range_reader(-1.0, reader.function.clone()).is_err());
+}